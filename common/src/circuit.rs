@@ -1,24 +1,325 @@
-use alloc::{string::String, vec::Vec};
+use alloc::{format, string::String, vec::Vec};
+use anyhow::bail;
 use plonky2::{
     field::goldilocks_field::GoldilocksField,
+    gates::{gate::Gate, poseidon::PoseidonGate},
+    hash::poseidon::SPONGE_WIDTH,
     iop::witness::PartialWitness,
-    plonk::{circuit_builder::CircuitBuilder, config::PoseidonGoldilocksConfig},
+    plonk::{
+        circuit_builder::CircuitBuilder, circuit_data::CircuitConfig,
+        config::PoseidonGoldilocksConfig,
+    },
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-// Plonky2 setup parameters.
+use crate::describe::FragmentSummary;
+
+// Plonky2 setup parameters. Circuits are always built over `F` with extension degree `D`; `C` is
+// the default proving config, which can be swapped for any other `GenericConfig<D, F = F>`.
 pub const D: usize = 2; // D=2 provides 100-bits of security
 pub type C = PoseidonGoldilocksConfig;
 pub type F = GoldilocksField;
 
-#[derive(Debug, Deserialize)]
+/// Checks that circuits hashing with Poseidon can be built with `config`.
+///
+/// Building a circuit with a config that has too few wires for the Poseidon gate panics deep
+/// inside plonky2. Configs read from circuit artifacts are checked with this first, so a bad
+/// artifact is reported as an error instead.
+///
+/// # Errors
+///
+/// Returns an error if the Poseidon gate needs more wires or routed wires than `config` has.
+pub fn check_config(config: &CircuitConfig) -> anyhow::Result<()> {
+    let poseidon_wires = PoseidonGate::<F, D>::new().num_wires();
+    if config.num_wires < poseidon_wires {
+        bail!(
+            "circuit config has {} wires, the Poseidon gate needs at least {}",
+            config.num_wires,
+            poseidon_wires
+        );
+    }
+    // The inputs, outputs and swap flag of the Poseidon gate are routed.
+    let poseidon_routed_wires = 2 * SPONGE_WIDTH + 1;
+    if config.num_routed_wires < poseidon_routed_wires {
+        bail!(
+            "circuit config has {} routed wires, the Poseidon gate needs at least {}",
+            config.num_routed_wires,
+            poseidon_routed_wires
+        );
+    }
+    if config.num_routed_wires > config.num_wires {
+        bail!(
+            "circuit config has more routed wires than wires: {} > {}",
+            config.num_routed_wires,
+            config.num_wires
+        );
+    }
+
+    Ok(())
+}
+
+/// The version of the [`TransferProofJson`] schema written by this crate.
+pub const TRANSFER_PROOF_JSON_VERSION: u32 = 1;
+
+/// The size of a child hash within a storage proof node, in hex characters.
+const NODE_HASH_SIZE_HEX: usize = 64;
+
+/// The storage proof of a transfer, as exported by a node.
+///
+/// This is the contract between node-side proof exporters and this crate. Version 1 is a JSON
+/// object with exactly these fields, unknown fields are rejected:
+///
+/// - `version`: [`TRANSFER_PROOF_JSON_VERSION`].
+/// - `transfer_count`: the transfer count of the transfer, as a number.
+/// - `state_root`: the 32 byte state root the proof is against, as 64 lowercase hex digits without
+///   a `0x` prefix.
+/// - `storage_proof`: the trie nodes from the root to the leaf, each as lowercase hex without a
+///   `0x` prefix.
+/// - `indices`: for every node, the position of the child hash in its hex encoding, so
+///   `indices[i] + 64` is at most the length of `storage_proof[i]`.
+///
+/// Deserializing only checks the field names and types, [`Self::validate`] checks the rest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct TransferProofJson {
+    pub version: u32,
     pub transfer_count: u64,
-    pub state_root: String,         // hex (no 0x)
-    pub storage_proof: Vec<String>, // hex-encoded nodes
+    pub state_root: String,
+    pub storage_proof: Vec<String>,
     pub indices: Vec<usize>,
 }
 
+impl TransferProofJson {
+    /// Checks the version, the hex encodings and that every index leaves room for a hash in its
+    /// node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first field that doesn't follow the schema.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.version != TRANSFER_PROOF_JSON_VERSION {
+            bail!(
+                "unsupported transfer proof version {}, expected {}",
+                self.version,
+                TRANSFER_PROOF_JSON_VERSION
+            );
+        }
+        check_hex("state_root", &self.state_root)?;
+        if self.state_root.len() != NODE_HASH_SIZE_HEX {
+            bail!(
+                "state_root must be {} hex digits, got {}",
+                NODE_HASH_SIZE_HEX,
+                self.state_root.len()
+            );
+        }
+        if self.storage_proof.is_empty() {
+            bail!("storage_proof is empty");
+        }
+        if self.indices.len() != self.storage_proof.len() {
+            bail!(
+                "indices has {} entries but storage_proof has {} nodes",
+                self.indices.len(),
+                self.storage_proof.len()
+            );
+        }
+
+        for (i, (node, &index)) in self.storage_proof.iter().zip(&self.indices).enumerate() {
+            check_hex(&format!("storage_proof[{}]", i), node)?;
+            if index.saturating_add(NODE_HASH_SIZE_HEX) > node.len() {
+                bail!(
+                    "indices[{}] is {}, which leaves no room for a hash in the {} hex digits of \
+                     storage_proof[{}]",
+                    i,
+                    index,
+                    node.len(),
+                    i
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that `hex` is an even number of lowercase hex digits without a prefix.
+fn check_hex(field: &str, hex: &str) -> anyhow::Result<()> {
+    if let Some(index) = hex.find(|c: char| !matches!(c, '0'..='9' | 'a'..='f')) {
+        bail!("{} is not lowercase hex, see index {}", field, index);
+    }
+    if hex.len() % 2 != 0 {
+        bail!("{} has an odd number of hex digits", field);
+    }
+    Ok(())
+}
+
+/// Why the inputs of a fragment don't fit the targets given to [`CircuitFragment::fill_targets`].
+///
+/// Plonky2 panics when a witness assigns a different number of values than there are targets, so
+/// fragments check the lengths of their inputs before setting any target. `fragment` is the name
+/// the fragment describes itself with, and `field` the input that doesn't fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeError {
+    /// The targets hold exactly `expected` values.
+    LengthMismatch {
+        fragment: &'static str,
+        field: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    /// The targets hold at most `max` values.
+    TooLong {
+        fragment: &'static str,
+        field: &'static str,
+        max: usize,
+        got: usize,
+    },
+    /// Another input needs at least `min` values.
+    TooShort {
+        fragment: &'static str,
+        field: &'static str,
+        min: usize,
+        got: usize,
+    },
+}
+
+impl ShapeError {
+    /// A stable identifier of the error kind, see the wormhole circuit's error catalog.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::LengthMismatch { .. } => "shape.length_mismatch",
+            Self::TooLong { .. } => "shape.too_long",
+            Self::TooShort { .. } => "shape.too_short",
+        }
+    }
+
+    pub const fn fragment(&self) -> &'static str {
+        match *self {
+            Self::LengthMismatch { fragment, .. }
+            | Self::TooLong { fragment, .. }
+            | Self::TooShort { fragment, .. } => fragment,
+        }
+    }
+
+    pub const fn field(&self) -> &'static str {
+        match *self {
+            Self::LengthMismatch { field, .. }
+            | Self::TooLong { field, .. }
+            | Self::TooShort { field, .. } => field,
+        }
+    }
+
+    /// Checks that `field` of `fragment` has exactly `expected` values.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ShapeError::LengthMismatch`] if `got` differs from `expected`.
+    pub fn check_len(
+        fragment: &'static str,
+        field: &'static str,
+        expected: usize,
+        got: usize,
+    ) -> Result<(), Self> {
+        if got != expected {
+            return Err(Self::LengthMismatch {
+                fragment,
+                field,
+                expected,
+                got,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that `field` of `fragment` has at most `max` values.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ShapeError::TooLong`] if `got` exceeds `max`.
+    pub fn check_max_len(
+        fragment: &'static str,
+        field: &'static str,
+        max: usize,
+        got: usize,
+    ) -> Result<(), Self> {
+        if got > max {
+            return Err(Self::TooLong {
+                fragment,
+                field,
+                max,
+                got,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that `field` of `fragment` has at least `min` values.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ShapeError::TooShort`] if `got` is below `min`.
+    pub fn check_min_len(
+        fragment: &'static str,
+        field: &'static str,
+        min: usize,
+        got: usize,
+    ) -> Result<(), Self> {
+        if got < min {
+            return Err(Self::TooShort {
+                fragment,
+                field,
+                min,
+                got,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::LengthMismatch {
+                fragment,
+                field,
+                expected,
+                got,
+            } => write!(
+                f,
+                "length mismatch in {}.{}: the circuit expects {} values, got {}",
+                fragment, field, expected, got
+            ),
+            Self::TooLong {
+                fragment,
+                field,
+                max,
+                got,
+            } => write!(
+                f,
+                "{}.{} has {} values, the circuit holds at most {}",
+                fragment, field, got, max
+            ),
+            Self::TooShort {
+                fragment,
+                field,
+                min,
+                got,
+            } => write!(
+                f,
+                "{}.{} has {} values, at least {} are needed",
+                fragment, field, got, min
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ShapeError {}
+
+/// A self-contained piece of a circuit over the Goldilocks field [`F`] with extension degree
+/// [`D`].
+///
+/// Fragments don't depend on the proving config, so the circuits built from them can be proven
+/// with any `GenericConfig<D, F = F>`. They are not generic over the field: their encodings, such
+/// as 32 bit limbs and injective byte packing, rely on the Goldilocks order.
 pub trait CircuitFragment {
     /// The targets that the circuit operates on. These are constrained in the circuit definition
     /// and filled with [`Self::fill_targets`].
@@ -27,7 +328,14 @@ pub trait CircuitFragment {
     /// Builds a circuit with the operating wires being provided by [`Self::Targets`].
     fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>);
 
+    /// Describes the constraints [`Self::circuit`] adds for `targets`, see
+    /// [`crate::describe`].
+    fn describe(targets: &Self::Targets) -> FragmentSummary;
+
     /// Fills the targets in the partial witness with the provided inputs.
+    ///
+    /// Implementations check that the inputs fit the targets before setting any of them, and
+    /// return a [`ShapeError`] if they don't.
     fn fill_targets(
         &self,
         pw: &mut PartialWitness<F>,
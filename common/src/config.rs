@@ -0,0 +1,103 @@
+//! [`CircuitConfig`] presets for the circuits in this workspace.
+//!
+//! All presets target the same ~100 bits of conjectured FRI security as plonky2's
+//! `standard_recursion_config`, computed as `rate_bits * num_query_rounds + proof_of_work_bits`.
+//! They differ in how that budget is spent:
+//!
+//! | preset        | rate_bits | queries | pow bits | zero knowledge |
+//! |---------------|-----------|---------|----------|----------------|
+//! | `standard`    | 3         | 28      | 16       | no             |
+//! | `fast`        | 3         | 31      | 8        | never          |
+//! | `small-proof` | 4         | 20      | 20       | no             |
+//!
+//! A rate below 3 is not an option: the Poseidon gate has constraint degree 7, which needs a
+//! blowup factor of at least 8. The `fast` preset therefore keeps the smallest possible blowup,
+//! replaces most of the proof-of-work grinding with a few extra queries, and never enables zero
+//! knowledge, saving the blinding work. `small-proof` doubles the blowup, roughly doubling LDE and
+//! commitment time, in exchange for about 30% fewer FRI queries, which dominate proof size.
+//!
+//! The `prover` benchmark in the wormhole prover crate measures proving time and proof size for
+//! every preset; run `cargo bench -p qp-wormhole-prover --bench prover` on the target hardware
+//! before switching presets.
+use core::str::FromStr;
+
+use anyhow::bail;
+use plonky2::plonk::circuit_data::CircuitConfig;
+
+/// The smallest blowup factor allowed by the Poseidon gate, with little grinding and without
+/// zero knowledge.
+pub fn wormhole_fast_config() -> CircuitConfig {
+    let mut config = CircuitConfig::standard_recursion_config();
+    config.zero_knowledge = false;
+    config.fri_config.rate_bits = 3;
+    config.fri_config.num_query_rounds = 31;
+    config.fri_config.proof_of_work_bits = 8;
+    config
+}
+
+/// Trades proving time for fewer FRI queries and therefore smaller proofs.
+pub fn wormhole_small_proof_config() -> CircuitConfig {
+    let mut config = CircuitConfig::standard_recursion_config();
+    config.fri_config.rate_bits = 4;
+    config.fri_config.num_query_rounds = 20;
+    config.fri_config.proof_of_work_bits = 20;
+    config
+}
+
+/// A named [`CircuitConfig`] preset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigPreset {
+    /// plonky2's `standard_recursion_config`.
+    #[default]
+    Standard,
+    /// See [`wormhole_fast_config`].
+    Fast,
+    /// See [`wormhole_small_proof_config`].
+    SmallProof,
+}
+
+impl ConfigPreset {
+    pub const ALL: [ConfigPreset; 3] = [Self::Standard, Self::Fast, Self::SmallProof];
+
+    /// Returns the circuit config for this preset, with zero knowledge enabled if `zk` is set and
+    /// the preset allows it.
+    pub fn config(self, zk: bool) -> CircuitConfig {
+        match self {
+            Self::Standard => {
+                let mut config = CircuitConfig::standard_recursion_config();
+                config.zero_knowledge = zk;
+                config
+            }
+            Self::Fast => wormhole_fast_config(),
+            Self::SmallProof => {
+                let mut config = wormhole_small_proof_config();
+                config.zero_knowledge = zk;
+                config
+            }
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Fast => "fast",
+            Self::SmallProof => "small-proof",
+        }
+    }
+}
+
+impl FromStr for ConfigPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Self::Standard),
+            "fast" => Ok(Self::Fast),
+            "small-proof" => Ok(Self::SmallProof),
+            _ => bail!(
+                "unknown config preset `{}`, expected one of: standard, fast, small-proof",
+                s
+            ),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Human-readable summaries of the constraints of circuits, for audits.
+//!
+//! [`CircuitFragment::describe`](crate::circuit::CircuitFragment::describe) lists the constraints
+//! a fragment adds for its targets: which values are hashed into which, which are connected and
+//! which are range checked to how many bits. Values are named after the fields of the fragment's
+//! targets and constants after the Rust constants they come from, so the statement a circuit
+//! proves can be read off its summary instead of being reconstructed from the builder calls.
+//!
+//! Summaries are written next to the circuits rather than derived from the built gates, so a
+//! change to a circuit has to update its `describe` as well.
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use serde::Serialize;
+
+/// The name of the hash used by [`FragmentSummary::poseidon`].
+pub const POSEIDON: &str = "Poseidon";
+
+/// One constraint, or group of constraints, added by a fragment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Constraint {
+    /// `output == hash(inputs[0] || inputs[1] || ...)`, hashed without padding.
+    Hash {
+        hash: String,
+        inputs: Vec<String>,
+        output: String,
+    },
+    /// `left == right`, element by element.
+    Equal { left: String, right: String },
+    /// Every element of `value` is less than `2^bits`.
+    RangeCheck { value: String, bits: usize },
+    /// A constraint the other kinds don't capture, such as a comparison or a constraint that only
+    /// applies under a condition.
+    Assert { statement: String },
+    /// The constraints of a nested fragment.
+    Fragment(FragmentSummary),
+}
+
+/// The constraints a fragment adds, in the order it adds them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FragmentSummary {
+    pub name: String,
+    pub constraints: Vec<Constraint>,
+}
+
+impl FragmentSummary {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Adds `output == Poseidon(inputs[0] || inputs[1] || ...)`.
+    pub fn poseidon(mut self, output: impl Into<String>, inputs: &[&str]) -> Self {
+        self.constraints.push(Constraint::Hash {
+            hash: POSEIDON.to_string(),
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+            output: output.into(),
+        });
+        self
+    }
+
+    /// Adds `left == right`.
+    pub fn equal(mut self, left: impl Into<String>, right: impl Into<String>) -> Self {
+        self.constraints.push(Constraint::Equal {
+            left: left.into(),
+            right: right.into(),
+        });
+        self
+    }
+
+    /// Adds `value < 2^bits`.
+    pub fn range_check(mut self, value: impl Into<String>, bits: usize) -> Self {
+        self.constraints.push(Constraint::RangeCheck {
+            value: value.into(),
+            bits,
+        });
+        self
+    }
+
+    /// Adds a constraint described by `statement`.
+    pub fn assert(mut self, statement: impl Into<String>) -> Self {
+        self.constraints.push(Constraint::Assert {
+            statement: statement.into(),
+        });
+        self
+    }
+
+    /// Adds the constraints of the nested fragment `summary`.
+    pub fn fragment(mut self, summary: FragmentSummary) -> Self {
+        self.constraints.push(Constraint::Fragment(summary));
+        self
+    }
+
+    /// The number of hash invocations, including those of nested fragments.
+    pub fn num_hashes(&self) -> usize {
+        self.constraints
+            .iter()
+            .map(|constraint| match constraint {
+                Constraint::Hash { .. } => 1,
+                Constraint::Fragment(summary) => summary.num_hashes(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self.name, indent = 2 * depth)?;
+        for constraint in &self.constraints {
+            if let Constraint::Fragment(summary) = constraint {
+                summary.write_indented(f, depth + 1)?;
+            } else {
+                writeln!(f, "{:indent$}{}", "", constraint, indent = 2 * (depth + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hash {
+                hash,
+                inputs,
+                output,
+            } => write!(f, "hash:  {} == {}({})", output, hash, inputs.join(" || ")),
+            Self::Equal { left, right } => write!(f, "equal: {} == {}", left, right),
+            Self::RangeCheck { value, bits } => write!(f, "range: {} < 2^{}", value, bits),
+            Self::Assert { statement } => write!(f, "check: {}", statement),
+            Self::Fragment(summary) => write!(f, "{}", summary),
+        }
+    }
+}
+
+impl fmt::Display for FragmentSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// A public input range of a circuit, in field elements.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublicInputSummary {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The public inputs and constraints of a whole circuit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CircuitSummary {
+    pub name: String,
+    pub public_inputs: Vec<PublicInputSummary>,
+    pub fragments: Vec<FragmentSummary>,
+}
+
+impl CircuitSummary {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            public_inputs: Vec::new(),
+            fragments: Vec::new(),
+        }
+    }
+
+    /// Adds the public input `name` at `start..end`.
+    pub fn public_input(mut self, name: impl Into<String>, start: usize, end: usize) -> Self {
+        self.public_inputs.push(PublicInputSummary {
+            name: name.into(),
+            start,
+            end,
+        });
+        self
+    }
+
+    /// Adds the constraints of the fragment `summary`.
+    pub fn fragment(mut self, summary: FragmentSummary) -> Self {
+        self.fragments.push(summary);
+        self
+    }
+
+    /// The number of hash invocations of all fragments.
+    pub fn num_hashes(&self) -> usize {
+        self.fragments.iter().map(FragmentSummary::num_hashes).sum()
+    }
+}
+
+impl fmt::Display for CircuitSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        writeln!(f, "public inputs:")?;
+        for input in &self.public_inputs {
+            writeln!(f, "  {:>3}..{:<3} {}", input.start, input.end, input.name)?;
+        }
+        writeln!(f, "constraints:")?;
+        for summary in &self.fragments {
+            summary.write_indented(f, 1)?;
+        }
+        Ok(())
+    }
+}
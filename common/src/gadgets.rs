@@ -1,4 +1,4 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 use plonky2::{
     field::extension::Extendable,
     hash::hash_types::RichField,
@@ -6,6 +6,9 @@ use plonky2::{
     plonk::circuit_builder::CircuitBuilder,
 };
 
+#[cfg(feature = "u32-gate")]
+use crate::u32_gate::U32RangeCheckGate;
+
 /// Compares a constant integer `left` with a variable `right` in a circuit, and returns whether
 /// or not `left < right`.
 ///
@@ -40,6 +43,49 @@ pub fn is_const_less_than<F: RichField + Extendable<D>, const D: usize>(
     lt
 }
 
+/// Returns whether `a < b`, where both are assumed to be range checked to `num_bits` bits.
+///
+/// # Panics
+///
+/// Panics if `num_bits` is 63 or more, as the comparison needs `num_bits + 1` bits of headroom
+/// below the field order.
+pub fn is_less_than<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    a: Target,
+    b: Target,
+    num_bits: usize,
+) -> BoolTarget {
+    assert!(num_bits < 63, "can't compare values of {} bits", num_bits);
+
+    // `a + 2^num_bits - b` is below `2^num_bits` exactly when `a < b`, so its top bit is clear.
+    let two_pow_n = builder.constant(F::from_canonical_u64(1u64 << num_bits));
+    let shifted = builder.add(a, two_pow_n);
+    let diff = builder.sub(shifted, b);
+    let (_, not_less) = builder.split_low_high(diff, num_bits, num_bits + 1);
+
+    // `not_less` is a range checked bit, so its negation is boolean.
+    builder.not(BoolTarget::new_unsafe(not_less))
+}
+
+/// Asserts that `a <= b`, where both are assumed to be range checked to `num_bits` bits.
+///
+/// # Panics
+///
+/// Panics if `num_bits` is 63 or more, see [`is_less_than`].
+pub fn assert_less_than_or_equal<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    a: Target,
+    b: Target,
+    num_bits: usize,
+) {
+    assert!(num_bits < 63, "can't compare values of {} bits", num_bits);
+
+    // If `a > b`, the difference wraps around to at least `p - 2^num_bits`, which fails the range
+    // check.
+    let diff = builder.sub(b, a);
+    builder.range_check(diff, num_bits);
+}
+
 /// Computes the XOR of two boolean values in a circuit.
 ///
 /// The following mathematical expression is used:
@@ -63,3 +109,136 @@ pub fn xor<F: RichField + Extendable<D>, const D: usize>(
     let xor = builder.sub(a_plus_b, two_ab);
     BoolTarget::new_unsafe(xor)
 }
+
+/// Adds numbers given as big-endian 32-bit limbs, e.g. amounts produced by `u128_to_felts`.
+///
+/// All limbs are assumed to be range checked to 32 bits, and fewer than `2^31` numbers may be
+/// added. The result is normalized to 32-bit limbs and has one more limb than the inputs to hold
+/// the final carry.
+///
+/// # Panics
+///
+/// Panics if `2^31` or more numbers are given.
+pub fn add_u32_limbs<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    numbers: &[Vec<Target>],
+    num_limbs: usize,
+) -> Vec<Target> {
+    let carry_bits = u32_limbs_carry_bits(numbers.len());
+
+    let mut sum = vec![builder.zero(); num_limbs + 1];
+    let mut carry = builder.zero();
+    for limb in (0..num_limbs).rev() {
+        let mut total = carry;
+        for number in numbers {
+            total = builder.add(total, number[limb]);
+        }
+
+        // Bounding the carry keeps `low + carry * 2^32` below the field order, so the split is
+        // unique. A 32 bit carry would let the prover pick a split that wraps around it and
+        // carries a value into the next limb that the limbs don't add up to.
+        let (low, high) = builder.split_low_high(total, 32, 32 + carry_bits);
+        sum[limb + 1] = low;
+        carry = high;
+    }
+    sum[0] = carry;
+
+    sum
+}
+
+/// The number of bits of the carry out of a limb when adding `num_numbers` numbers with
+/// [`add_u32_limbs`].
+///
+/// A limb total is at most `num_numbers * (2^32 - 1)` plus the carry into it, which is below
+/// `num_numbers`, so the carry out of it is at most `num_numbers - 1`. The bound is kept at one
+/// bit, as a zero bit range check is not supported.
+///
+/// # Panics
+///
+/// Panics if `num_numbers` is `2^31` or more, as the carry could then wrap around the field
+/// order.
+pub fn u32_limbs_carry_bits(num_numbers: usize) -> usize {
+    assert!(
+        num_numbers < 1 << 31,
+        "can't add {} numbers of 32-bit limbs",
+        num_numbers
+    );
+    ((usize::BITS - num_numbers.leading_zeros()) as usize).max(1)
+}
+
+/// Asserts that `left >= right`, where both are given as big-endian 32-bit limbs of the same
+/// length. All limbs are assumed to be range checked to 32 bits.
+pub fn assert_u32_limbs_at_least<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    left: &[Target],
+    right: &[Target],
+) {
+    let less_than = u32_limbs_less_than(builder, left, right);
+    let zero = builder.zero();
+    builder.connect(less_than.target, zero);
+}
+
+/// Returns whether `left < right`, where both are given as big-endian 32-bit limbs of the same
+/// length. All limbs are assumed to be range checked to 32 bits.
+pub fn u32_limbs_less_than<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    left: &[Target],
+    right: &[Target],
+) -> BoolTarget {
+    assert_eq!(left.len(), right.len(), "limb counts must match");
+
+    let one = builder.one();
+    let zero = builder.zero();
+    let two_pow_32 = builder.constant(F::from_canonical_u64(1u64 << 32));
+
+    // Subtract limb by limb, starting from the least significant one. Shifting each difference by
+    // 2^32 keeps it positive, and its 33rd bit tells whether the limb borrowed.
+    let mut borrow = zero;
+    for (&l, &r) in left.iter().zip(right).rev() {
+        let shifted = builder.add(l, two_pow_32);
+        let diff = builder.sub(shifted, r);
+        let diff = builder.sub(diff, borrow);
+        let (_, no_borrow) = builder.split_low_high(diff, 32, 33);
+        borrow = builder.sub(one, no_borrow);
+    }
+
+    // A borrow out of the most significant limb means `left < right`. The borrow is `1 - b` for
+    // a range checked bit `b`, so it is boolean.
+    BoolTarget::new_unsafe(borrow)
+}
+
+/// Range checks each of `targets` to 32 bits.
+///
+/// With the `u32-gate` feature, the checks are packed into `U32RangeCheckGate`s, several to a
+/// row, including checks from separate calls. Otherwise every target is checked with
+/// [`CircuitBuilder::range_check`], which takes a row each.
+pub fn range_check_u32<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    targets: &[Target],
+) {
+    #[cfg(feature = "u32-gate")]
+    {
+        let gate = U32RangeCheckGate::new_from_config(&builder.config);
+        for &target in targets {
+            let (row, op) = builder.find_slot(gate, &[], &[]);
+            builder.connect(Target::wire(row, gate.wire_input(op)), target);
+        }
+    }
+    #[cfg(not(feature = "u32-gate"))]
+    for &target in targets {
+        builder.range_check(target, 32);
+    }
+}
+
+/// Recombines the little-endian 32-bit limbs `lo` and `hi` into `lo + hi * 2^32`.
+///
+/// The limbs are assumed to be range checked to 32 bits, see [`range_check_u32`].
+pub fn recompose_u32_pair<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lo: Target,
+    hi: Target,
+) -> Target {
+    let two_pow_32 = builder.constant(F::from_canonical_u64(1u64 << 32));
+    let hi_shifted = builder.mul(hi, two_pow_32);
+    builder.add(lo, hi_shifted)
+}
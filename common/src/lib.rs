@@ -3,5 +3,12 @@
 extern crate alloc;
 
 pub mod circuit;
+pub mod config;
+pub mod describe;
 pub mod gadgets;
+pub mod merkle;
+pub mod prelude;
+pub mod proof;
 pub mod utils;
+#[cfg(feature = "u32-gate")]
+pub mod u32_gate;
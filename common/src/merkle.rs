@@ -0,0 +1,124 @@
+//! Merkle proofs of binary trees, natively and in-circuit.
+//!
+//! A proof is a leaf, its siblings from the bottom of the tree up, and one path index per
+//! level that is set when the node at that level is the right child. Parents are
+//! `H(left || right)` for the hasher `H` the caller picks, so trees built with
+//! [`merkle_root`] verify with [`merkle_proof_verify`] as long as both use the same hasher.
+use alloc::vec::Vec;
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::{HashOut, HashOutTarget, RichField},
+    iop::target::{BoolTarget, Target},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        config::{AlgebraicHasher, Hasher},
+    },
+};
+
+use crate::circuit::F;
+use crate::gadgets::is_const_less_than;
+use crate::utils::{Digest, DIGEST_NUM_FIELD_ELEMENTS};
+
+/// `H(left || right)`.
+pub fn hash_pair<H: Hasher<F, Hash = HashOut<F>>>(left: &Digest, right: &Digest) -> Digest {
+    let mut preimage = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
+    preimage.extend(left);
+    preimage.extend(right);
+    H::hash_no_pad(&preimage).elements
+}
+
+/// Computes the root of the tree with `leaf` at the position given by `path_indices`, see
+/// [`merkle_proof_root`]. Levels beyond the shorter of `path_indices` and `siblings` are ignored.
+pub fn merkle_root<H: Hasher<F, Hash = HashOut<F>>>(
+    leaf: Digest,
+    path_indices: &[bool],
+    siblings: &[Digest],
+) -> Digest {
+    path_indices
+        .iter()
+        .zip(siblings)
+        .fold(leaf, |node, (&is_right, sibling)| {
+            if is_right {
+                hash_pair::<H>(sibling, &node)
+            } else {
+                hash_pair::<H>(&node, sibling)
+            }
+        })
+}
+
+/// Computes the root of the tree with `leaf` at the position given by `path_indices`.
+///
+/// With `active_depth`, only that many levels are hashed and the node reached at that level is
+/// returned, so a circuit sized for trees of depth `siblings.len()` also verifies proofs of
+/// shallower trees. The siblings and path indices of the inactive levels are unconstrained.
+/// `active_depth` must be at most `siblings.len()`.
+///
+/// # Panics
+///
+/// Panics if `path_indices` and `siblings` have different lengths.
+pub fn merkle_proof_root<H, F, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    leaf: HashOutTarget,
+    path_indices: &[BoolTarget],
+    siblings: &[HashOutTarget],
+    active_depth: Option<Target>,
+) -> HashOutTarget
+where
+    H: AlgebraicHasher<F>,
+    F: RichField + Extendable<D>,
+{
+    assert_eq!(
+        path_indices.len(),
+        siblings.len(),
+        "a merkle proof needs one path index per sibling"
+    );
+
+    // Enough bits to hold every depth up to and including `siblings.len()`.
+    let depth_bits = (usize::BITS - siblings.len().leading_zeros()) as usize;
+    let mut node = leaf;
+    for (level, (&is_right, sibling)) in path_indices.iter().zip(siblings).enumerate() {
+        let mut preimage = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
+        for (&n, &s) in node.elements.iter().zip(&sibling.elements) {
+            preimage.push(builder.select(is_right, s, n));
+        }
+        for (&n, &s) in node.elements.iter().zip(&sibling.elements) {
+            preimage.push(builder.select(is_right, n, s));
+        }
+        let parent = builder.hash_n_to_hash_no_pad::<H>(preimage);
+
+        node = match active_depth {
+            Some(depth) => {
+                let is_active = is_const_less_than(builder, level, depth, depth_bits);
+                HashOutTarget {
+                    elements: core::array::from_fn(|k| {
+                        builder.select(is_active, parent.elements[k], node.elements[k])
+                    }),
+                }
+            }
+            None => parent,
+        };
+    }
+    node
+}
+
+/// Asserts that `leaf` is at the position given by `path_indices` of the tree with root `root`,
+/// see [`merkle_proof_root`].
+///
+/// # Panics
+///
+/// Panics if `path_indices` and `siblings` have different lengths.
+pub fn merkle_proof_verify<H, F, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    root: HashOutTarget,
+    leaf: HashOutTarget,
+    path_indices: &[BoolTarget],
+    siblings: &[HashOutTarget],
+    active_depth: Option<Target>,
+) where
+    H: AlgebraicHasher<F>,
+    F: RichField + Extendable<D>,
+{
+    let computed_root =
+        merkle_proof_root::<H, F, D>(builder, leaf, path_indices, siblings, active_depth);
+    builder.connect_hashes(computed_root, root);
+}
@@ -0,0 +1,9 @@
+//! The plonky2 types used at the API boundaries of the circuit crates.
+//!
+//! Depending crates should import these from here instead of depending on plonky2 directly, so
+//! they always get the types matching this crate's plonky2 version.
+pub use plonky2::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierCircuitData};
+pub use plonky2::plonk::proof::ProofWithPublicInputs;
+
+pub use crate::circuit::{C, D, F};
+pub use crate::proof::Proof;
@@ -0,0 +1,63 @@
+//! An opaque proof type for crate APIs.
+//!
+//! [`Proof`] wraps plonky2's [`ProofWithPublicInputs`] for the default proving config. Downstream
+//! crates can store, serialize and pass proofs around without naming plonky2 types, so a plonky2
+//! bump that changes those types only breaks code that reaches into [`Proof::as_inner`].
+use alloc::vec::Vec;
+use anyhow::anyhow;
+use plonky2::{
+    field::types::PrimeField64,
+    plonk::{circuit_data::CommonCircuitData, proof::ProofWithPublicInputs},
+};
+
+use crate::circuit::{C, D, F};
+
+/// A proof of one of the circuits in this workspace, together with its public inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof(ProofWithPublicInputs<F, C, D>);
+
+impl Proof {
+    /// The public inputs as canonical `u64` values.
+    pub fn public_inputs(&self) -> Vec<u64> {
+        self.0
+            .public_inputs
+            .iter()
+            .map(PrimeField64::to_canonical_u64)
+            .collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    /// Deserializes a proof of the circuit described by `common_data`.
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        ProofWithPublicInputs::from_bytes(bytes, common_data)
+            .map(Self)
+            .map_err(|e| anyhow!("failed to deserialize proof: {}", e))
+    }
+
+    /// The wrapped plonky2 proof. Code using it is tied to the plonky2 version of this crate.
+    pub fn as_inner(&self) -> &ProofWithPublicInputs<F, C, D> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> ProofWithPublicInputs<F, C, D> {
+        self.0
+    }
+}
+
+impl From<ProofWithPublicInputs<F, C, D>> for Proof {
+    fn from(value: ProofWithPublicInputs<F, C, D>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Proof> for ProofWithPublicInputs<F, C, D> {
+    fn from(value: Proof) -> Self {
+        value.0
+    }
+}
@@ -0,0 +1,232 @@
+//! A gate that range checks several field elements to 32 bits in a single row.
+//!
+//! plonky2 range checks every element with its own `BaseSumGate` row, which leaves most wires of
+//! the row unused. [`U32RangeCheckGate`] instead decomposes each checked element into 16 base 4
+//! limbs, so a row of the standard recursion config checks 7 elements. The gate is used through
+//! [`crate::gadgets::range_check_u32`], which batches checks into partially filled gates.
+//!
+//! Circuits containing the gate can't be serialized with plonky2's default gate and generator
+//! serializers.
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use plonky2::{
+    field::extension::Extendable,
+    gates::{gate::Gate, util::StridedConstraintConsumer},
+    hash::hash_types::RichField,
+    iop::{
+        ext_target::ExtensionTarget,
+        generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef},
+        target::Target,
+        witness::{PartitionWitness, Witness, WitnessWrite},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CommonCircuitData},
+        plonk_common::{reduce_with_powers, reduce_with_powers_ext_circuit},
+        vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase},
+    },
+    util::serialization::{Buffer, IoResult, Read, Write},
+};
+
+/// The number of bits of each limb.
+pub const LIMB_BITS: usize = 2;
+/// The base of the limbs.
+pub const LIMB_BASE: usize = 1 << LIMB_BITS;
+/// The number of limbs each checked element is decomposed into.
+pub const NUM_LIMBS: usize = 32 / LIMB_BITS;
+
+/// Checks that each of its `num_ops` inputs is below `2^32`.
+///
+/// The inputs occupy the first `num_ops` wires, so they are routable, and are followed by the
+/// limbs of every input, least significant first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct U32RangeCheckGate {
+    pub num_ops: usize,
+}
+
+impl U32RangeCheckGate {
+    pub const fn new(num_ops: usize) -> Self {
+        Self { num_ops }
+    }
+
+    /// Creates a gate that checks as many elements as fit into a row with `config`.
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        let num_ops = (config.num_wires / (1 + NUM_LIMBS)).min(config.num_routed_wires);
+        Self::new(num_ops)
+    }
+
+    /// The wire holding the input of operation `op`.
+    pub const fn wire_input(&self, op: usize) -> usize {
+        op
+    }
+
+    /// The wires holding the limbs of the input of operation `op`.
+    pub const fn wires_limbs(&self, op: usize) -> Range<usize> {
+        let start = self.num_ops + op * NUM_LIMBS;
+        start..start + NUM_LIMBS
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for U32RangeCheckGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_ops = src.read_usize()?;
+        Ok(Self::new(num_ops))
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let base = F::Extension::from_canonical_usize(LIMB_BASE);
+        let mut constraints = Vec::with_capacity(self.num_ops * (1 + NUM_LIMBS));
+        for op in 0..self.num_ops {
+            let input = vars.local_wires[self.wire_input(op)];
+            let limbs = &vars.local_wires[self.wires_limbs(op)];
+            constraints.push(reduce_with_powers(limbs, base) - input);
+            for &limb in limbs {
+                constraints.push(
+                    (0..LIMB_BASE)
+                        .map(|i| limb - F::Extension::from_canonical_usize(i))
+                        .product(),
+                );
+            }
+        }
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        let base = F::from_canonical_usize(LIMB_BASE);
+        for op in 0..self.num_ops {
+            let input = vars.local_wires[self.wire_input(op)];
+            let limbs: Vec<F> = self
+                .wires_limbs(op)
+                .map(|wire| vars.local_wires[wire])
+                .collect();
+            let computed = limbs.iter().rev().fold(F::ZERO, |acc, &limb| acc * base + limb);
+            yield_constr.one(computed - input);
+            yield_constr.many(limbs.iter().map(|&limb| {
+                (0..LIMB_BASE)
+                    .map(|i| limb - F::from_canonical_usize(i))
+                    .product::<F>()
+            }));
+        }
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let base = builder.constant(F::from_canonical_usize(LIMB_BASE));
+        let mut constraints = Vec::with_capacity(self.num_ops * (1 + NUM_LIMBS));
+        for op in 0..self.num_ops {
+            let input = vars.local_wires[self.wire_input(op)];
+            let limbs = vars.local_wires[self.wires_limbs(op)].to_vec();
+            let computed = reduce_with_powers_ext_circuit(builder, &limbs, base);
+            constraints.push(builder.sub_extension(computed, input));
+            for limb in limbs {
+                let mut product = builder.one_extension();
+                for i in 0..LIMB_BASE {
+                    let i = builder.constant_extension(F::Extension::from_canonical_usize(i));
+                    let difference = builder.sub_extension(limb, i);
+                    product = builder.mul_extension(product, difference);
+                }
+                constraints.push(product);
+            }
+        }
+        constraints
+    }
+
+    /// One generator per operation, so that the builder can zero the inputs of unused operations.
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|op| {
+                let generator = U32RangeCheckGenerator {
+                    gate: *self,
+                    row,
+                    op,
+                };
+                WitnessGeneratorRef::new(generator.adapter())
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * (1 + NUM_LIMBS)
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        LIMB_BASE
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * (1 + NUM_LIMBS)
+    }
+}
+
+/// Fills the limbs of one operation of a [`U32RangeCheckGate`].
+#[derive(Debug, Clone)]
+pub struct U32RangeCheckGenerator {
+    gate: U32RangeCheckGate,
+    row: usize,
+    op: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for U32RangeCheckGenerator
+{
+    fn id(&self) -> String {
+        "U32RangeCheckGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        alloc::vec![Target::wire(self.row, self.gate.wire_input(self.op))]
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> anyhow::Result<()> {
+        let input = witness
+            .get_target(Target::wire(self.row, self.gate.wire_input(self.op)))
+            .to_canonical_u64();
+        anyhow::ensure!(input >> 32 == 0, "{} does not fit into 32 bits", input);
+        for (i, wire) in self.gate.wires_limbs(self.op).enumerate() {
+            let limb = (input >> (i * LIMB_BITS)) & (LIMB_BASE as u64 - 1);
+            out_buffer.set_target(Target::wire(self.row, wire), F::from_canonical_u64(limb))?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.gate.num_ops)?;
+        dst.write_usize(self.row)?;
+        dst.write_usize(self.op)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let gate = U32RangeCheckGate::new(src.read_usize()?);
+        let row = src.read_usize()?;
+        let op = src.read_usize()?;
+        Ok(Self { gate, row, op })
+    }
+}
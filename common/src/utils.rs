@@ -26,6 +26,16 @@ pub enum DigestError {
     InvalidLength { expected: usize, got: usize },
 }
 
+impl DigestError {
+    /// A stable identifier of the error kind, see the wormhole circuit's error catalog.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::ChunkOutOfFieldRange { .. } => "digest.chunk_out_of_field_range",
+            Self::InvalidLength { .. } => "digest.invalid_length",
+        }
+    }
+}
+
 impl TryFrom<&[u8]> for BytesDigest {
     type Error = DigestError;
 
@@ -84,6 +94,40 @@ impl Deref for BytesDigest {
     }
 }
 
+/// SCALE encodes as the 32 raw bytes. Decoding rejects chunks that are not canonical field
+/// elements, like [`BytesDigest::try_from`].
+#[cfg(feature = "scale")]
+impl parity_scale_codec::Encode for BytesDigest {
+    fn size_hint(&self) -> usize {
+        32
+    }
+
+    fn encode_to<T: parity_scale_codec::Output + ?Sized>(&self, dest: &mut T) {
+        dest.write(&self.0)
+    }
+}
+
+#[cfg(feature = "scale")]
+impl parity_scale_codec::EncodeLike for BytesDigest {}
+
+#[cfg(feature = "scale")]
+impl parity_scale_codec::Decode for BytesDigest {
+    fn decode<I: parity_scale_codec::Input>(
+        input: &mut I,
+    ) -> Result<Self, parity_scale_codec::Error> {
+        let bytes = <[u8; 32] as parity_scale_codec::Decode>::decode(input)?;
+        BytesDigest::try_from(bytes)
+            .map_err(|_| "digest chunk is not a canonical field element".into())
+    }
+}
+
+#[cfg(feature = "scale")]
+impl parity_scale_codec::MaxEncodedLen for BytesDigest {
+    fn max_encoded_len() -> usize {
+        32
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FeltWidthError {
     pub index: usize, // which limb failed
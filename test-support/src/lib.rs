@@ -0,0 +1,91 @@
+//! Test harness shared by the test suites of the workspace.
+//!
+//! Circuits are built with [`CircuitConfig::standard_recursion_config`], with zero knowledge
+//! switched on or off per test. Together with the `no_random` feature of plonky2, which the
+//! workspace enables, proofs of circuits built without zero knowledge are deterministic, see
+//! [`assert_proofs_deterministic`].
+use std::time::{Duration, Instant};
+
+use plonky2::{
+    iop::witness::PartialWitness,
+    plonk::{
+        circuit_builder::CircuitBuilder, circuit_data::CircuitConfig, proof::ProofWithPublicInputs,
+    },
+};
+use zk_circuits_common::circuit::{C, D, F};
+
+/// Convenience function for initializing a test circuit environment.
+pub fn setup_test_builder_and_witness(zk: bool) -> (CircuitBuilder<F, D>, PartialWitness<F>) {
+    let mut config = CircuitConfig::standard_recursion_config();
+    if zk {
+        config.zero_knowledge = true;
+    }
+    let builder = CircuitBuilder::<F, D>::new(config);
+    let pw = PartialWitness::new();
+
+    (builder, pw)
+}
+
+/// Convenience function for building and verifying a test function. The circuit is assumed to
+/// have been setup prior to calling this function.
+pub fn build_and_prove_test(
+    builder: CircuitBuilder<F, D>,
+    pw: PartialWitness<F>,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let data = builder.build::<C>();
+    data.prove(pw)
+}
+
+/// The time spent in each step of [`build_prove_and_verify_timed`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestTimings {
+    pub build: Duration,
+    pub prove: Duration,
+    pub verify: Duration,
+}
+
+/// Like [`build_and_prove_test`], but also verifies the proof and reports how long each step
+/// took, for tests that keep an eye on circuit performance.
+pub fn build_prove_and_verify_timed(
+    builder: CircuitBuilder<F, D>,
+    pw: PartialWitness<F>,
+) -> anyhow::Result<(ProofWithPublicInputs<F, C, D>, TestTimings)> {
+    let start = Instant::now();
+    let data = builder.build::<C>();
+    let build = start.elapsed();
+
+    let start = Instant::now();
+    let proof = data.prove(pw)?;
+    let prove = start.elapsed();
+
+    let start = Instant::now();
+    data.verify(proof.clone())?;
+    let verify = start.elapsed();
+
+    Ok((
+        proof,
+        TestTimings {
+            build,
+            prove,
+            verify,
+        },
+    ))
+}
+
+/// Runs `prove` twice and asserts that both proofs serialize to the same bytes.
+///
+/// Only meaningful for circuits built without zero knowledge, where no blinding randomness is
+/// mixed into the proof. Any difference then points at nondeterminism in circuit building or
+/// witness generation, such as iterating over a `HashMap`.
+pub fn assert_proofs_deterministic<P>(mut prove: P)
+where
+    P: FnMut() -> anyhow::Result<ProofWithPublicInputs<F, C, D>>,
+{
+    let first = prove().unwrap().to_bytes();
+    let second = prove().unwrap().to_bytes();
+
+    assert_eq!(first.len(), second.len(), "proof lengths differ");
+    if let Some(offset) = first.iter().zip(&second).position(|(a, b)| a != b) {
+        panic!("proofs differ, first mismatch at byte {}", offset);
+    }
+}
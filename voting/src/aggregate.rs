@@ -0,0 +1,316 @@
+//! Aggregated vote proofs bound to one proposal.
+//!
+//! The aggregation circuit verifies every leaf against the vote circuit, but nothing ties the
+//! leaves to each other, so an aggregated proof can mix votes on different proposals or voter
+//! sets. An [`AggregatedVoteEnvelope`] carries an aggregated proof together with the proposal,
+//! the merkle root of the eligible voters and the counts it claims, and
+//! [`AggregatedVoteEnvelope::verify`] checks that every counted leaf agrees with them. Leaves that
+//! don't are reported in a [`MixedVotesError`], which the returned error can be downcast to.
+//!
+//! # Format
+//!
+//! [`AggregatedVoteEnvelope::to_bytes`] writes, with all integers little-endian and field
+//! elements as their canonical `u64`:
+//!
+//! `version (1 byte) || proposal_id || merkle_root || yes (u64) || no (u64) ||
+//! proof length (u32) || proof`
+use alloc::vec::Vec;
+use anyhow::{bail, Context};
+use core::fmt;
+use plonky2::plonk::{circuit_data::VerifierCircuitData, proof::ProofWithPublicInputs};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::Digest;
+
+use crate::tally::{write_digest, Reader};
+use crate::VotePublicInputs;
+
+/// The version written by [`AggregatedVoteEnvelope::to_bytes`].
+pub const AGGREGATED_VOTE_ENVELOPE_VERSION: u8 = 1;
+
+/// An aggregated vote proof with the proposal and counts it claims.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregatedVoteEnvelope {
+    pub proposal_id: Digest,
+    /// The merkle root of the eligible voters.
+    pub merkle_root: Digest,
+    pub yes: u64,
+    pub no: u64,
+    /// The serialized aggregated proof. Its first `yes + no` leaves are the votes, the leaves
+    /// after them are the padding added by the aggregator.
+    pub proof: Vec<u8>,
+}
+
+/// A leaf of an aggregated vote proof that is not bound to the proposal of the envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviatingLeaf {
+    pub index: usize,
+    pub proposal_id: Digest,
+    pub merkle_root: Digest,
+}
+
+/// The leaves of an aggregated vote proof whose proposal id or merkle root differ from the
+/// expected ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixedVotesError {
+    pub proposal_id: Digest,
+    pub merkle_root: Digest,
+    /// The deviating leaves, in leaf order.
+    pub leaves: Vec<DeviatingLeaf>,
+}
+
+impl fmt::Display for MixedVotesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indices: Vec<usize> = self.leaves.iter().map(|leaf| leaf.index).collect();
+        write!(
+            f,
+            "leaves {:?} are not bound to proposal {:?} with merkle root {:?}",
+            indices, self.proposal_id, self.merkle_root
+        )
+    }
+}
+
+impl core::error::Error for MixedVotesError {}
+
+impl AggregatedVoteEnvelope {
+    /// Wraps a proof aggregated from `num_votes` vote proofs with `leaf_pi_len` public inputs
+    /// each, claiming the proposal and merkle root of its first leaf and the counts of its votes.
+    ///
+    /// The proof is not verified, see [`Self::verify`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no votes, the proof holds fewer than `num_votes` leaves or
+    /// they can't be decoded, or a [`MixedVotesError`] if they are not all bound to the proposal
+    /// of the first leaf.
+    pub fn new(
+        proof: &ProofWithPublicInputs<F, C, D>,
+        leaf_pi_len: usize,
+        num_votes: usize,
+    ) -> anyhow::Result<Self> {
+        let votes = leaf_votes(&proof.public_inputs, leaf_pi_len, num_votes)?;
+        let Some(first) = votes.first() else {
+            bail!("cannot wrap an aggregated proof without votes");
+        };
+        let (proposal_id, merkle_root) = (first.proposal_id, first.merkle_root);
+        check_binding(&votes, proposal_id, merkle_root)?;
+
+        let yes = votes.iter().filter(|vote| vote.vote).count() as u64;
+        Ok(Self {
+            proposal_id,
+            merkle_root,
+            yes,
+            no: votes.len() as u64 - yes,
+            proof: proof.to_bytes(),
+        })
+    }
+
+    /// Verifies the proof with the `verifier` data of the aggregation circuit, and checks that
+    /// its first `yes + no` leaves of `leaf_pi_len` public inputs are votes on the proposal of
+    /// the envelope with its counts. Returns the votes, in leaf order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof doesn't verify, holds too few leaves, or its votes don't
+    /// add up to the counts, or a [`MixedVotesError`] if any vote is not bound to the proposal
+    /// and merkle root.
+    pub fn verify(
+        &self,
+        verifier: &VerifierCircuitData<F, C, D>,
+        leaf_pi_len: usize,
+    ) -> anyhow::Result<Vec<VotePublicInputs>> {
+        let proof =
+            ProofWithPublicInputs::<F, C, D>::from_bytes(self.proof.clone(), &verifier.common)
+                .context("failed to deserialize aggregated vote proof")?;
+        verifier
+            .verify(proof.clone())
+            .context("aggregated vote proof failed verification")?;
+
+        let num_votes = self
+            .yes
+            .checked_add(self.no)
+            .and_then(|num_votes| usize::try_from(num_votes).ok())
+            .context("vote counts overflow")?;
+        let votes = leaf_votes(&proof.public_inputs, leaf_pi_len, num_votes)?;
+        check_binding(&votes, self.proposal_id, self.merkle_root)?;
+
+        let yes = votes.iter().filter(|vote| vote.vote).count() as u64;
+        if yes != self.yes {
+            bail!("envelope claims {} yes votes, the proof holds {}", self.yes, yes);
+        }
+        Ok(votes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push(AGGREGATED_VOTE_ENVELOPE_VERSION);
+        write_digest(&mut bytes, &self.proposal_id);
+        write_digest(&mut bytes, &self.merkle_root);
+        bytes.extend(self.yes.to_le_bytes());
+        bytes.extend(self.no.to_le_bytes());
+        bytes.extend((self.proof.len() as u32).to_le_bytes());
+        bytes.extend(&self.proof);
+        bytes
+    }
+
+    /// Decodes an envelope written by [`Self::to_bytes`]. The proof is not verified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are malformed.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader(bytes);
+        let version = reader.take::<1>()?[0];
+        if version != AGGREGATED_VOTE_ENVELOPE_VERSION {
+            bail!(
+                "unsupported aggregated vote envelope version: {}, expected: {}",
+                version,
+                AGGREGATED_VOTE_ENVELOPE_VERSION
+            );
+        }
+
+        let proposal_id = reader.digest()?;
+        let merkle_root = reader.digest()?;
+        let yes = u64::from_le_bytes(reader.take()?);
+        let no = u64::from_le_bytes(reader.take()?);
+        let proof_len = u32::from_le_bytes(reader.take()?) as usize;
+        let proof = reader.bytes(proof_len)?.to_vec();
+        if !reader.0.is_empty() {
+            bail!("{} trailing bytes after aggregated vote envelope", reader.0.len());
+        }
+
+        Ok(Self {
+            proposal_id,
+            merkle_root,
+            yes,
+            no,
+            proof,
+        })
+    }
+}
+
+/// The first `num_votes` leaves of the public inputs of an aggregated vote proof.
+fn leaf_votes(
+    pis: &[F],
+    leaf_pi_len: usize,
+    num_votes: usize,
+) -> anyhow::Result<Vec<VotePublicInputs>> {
+    let Some(pis) = num_votes
+        .checked_mul(leaf_pi_len)
+        .and_then(|len| pis.get(..len))
+    else {
+        bail!(
+            "aggregated proof has {} public inputs, too few for {} votes",
+            pis.len(),
+            num_votes
+        );
+    };
+    VotePublicInputs::try_from_aggregated(pis, leaf_pi_len)
+}
+
+fn check_binding(
+    votes: &[VotePublicInputs],
+    proposal_id: Digest,
+    merkle_root: Digest,
+) -> Result<(), MixedVotesError> {
+    let leaves: Vec<DeviatingLeaf> = votes
+        .iter()
+        .enumerate()
+        .filter(|(_, vote)| vote.proposal_id != proposal_id || vote.merkle_root != merkle_root)
+        .map(|(index, vote)| DeviatingLeaf {
+            index,
+            proposal_id: vote.proposal_id,
+            merkle_root: vote.merkle_root,
+        })
+        .collect();
+    if leaves.is_empty() {
+        return Ok(());
+    }
+    Err(MixedVotesError {
+        proposal_id,
+        merkle_root,
+        leaves,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use plonky2::{
+        field::types::Field,
+        iop::witness::PartialWitness,
+        plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
+    };
+    use zk_circuits_common::circuit::CircuitFragment;
+
+    use crate::{voting_tests::create_test_inputs, VoteCircuitData, VoteTargets};
+
+    fn vote_proof() -> (ProofWithPublicInputs<F, C, D>, VerifierCircuitData<F, C, D>) {
+        let inputs = create_test_inputs();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::new(&mut builder);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        let mut pw = PartialWitness::new();
+        inputs.fill_targets(&mut pw, targets).unwrap();
+
+        let circuit_data = builder.build::<C>();
+        let proof = circuit_data.prove(pw).unwrap();
+        (proof, circuit_data.verifier_data())
+    }
+
+    #[test]
+    fn test_envelope_round_trips_and_verifies() {
+        // A single vote proof stands in for an aggregate of one leaf.
+        let (proof, verifier) = vote_proof();
+        let leaf_pi_len = proof.public_inputs.len();
+        let envelope = AggregatedVoteEnvelope::new(&proof, leaf_pi_len, 1).unwrap();
+        assert_eq!((envelope.yes, envelope.no), (1, 0));
+
+        let decoded = AggregatedVoteEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+        assert_eq!(decoded, envelope);
+        let votes = decoded.verify(&verifier, leaf_pi_len).unwrap();
+        assert_eq!(votes.len(), 1);
+
+        let mut wrong_counts = envelope.clone();
+        (wrong_counts.yes, wrong_counts.no) = (0, 1);
+        assert!(wrong_counts.verify(&verifier, leaf_pi_len).is_err());
+
+        let mut other_proposal = envelope;
+        other_proposal.proposal_id = [F::ZERO; 4];
+        let err = other_proposal.verify(&verifier, leaf_pi_len).unwrap_err();
+        let mixed = err.downcast_ref::<MixedVotesError>().unwrap();
+        assert_eq!(mixed.leaves[0].index, 0);
+    }
+
+    #[test]
+    fn test_mixed_leaves_are_identified() {
+        let (mut proof, _) = vote_proof();
+        let leaf = proof.public_inputs.clone();
+        let leaf_pi_len = leaf.len();
+
+        let mut other_root = leaf.clone();
+        other_root[4] = F::ZERO;
+        let mut other_proposal = leaf.clone();
+        other_proposal[0] = F::ZERO;
+        proof.public_inputs = [leaf.clone(), other_root, leaf, other_proposal].concat();
+
+        let err = AggregatedVoteEnvelope::new(&proof, leaf_pi_len, 4).unwrap_err();
+        let mixed = err.downcast_ref::<MixedVotesError>().unwrap();
+        let indices: Vec<usize> = mixed.leaves.iter().map(|leaf| leaf.index).collect();
+        assert_eq!(indices, [1, 3]);
+
+        // The deviating leaves can be left out as padding.
+        let envelope = AggregatedVoteEnvelope::new(&proof, leaf_pi_len, 1).unwrap();
+        assert_eq!((envelope.yes, envelope.no), (1, 0));
+        assert!(AggregatedVoteEnvelope::new(&proof, leaf_pi_len, 5).is_err());
+    }
+
+    #[test]
+    fn test_truncated_envelope_is_rejected() {
+        let (proof, _) = vote_proof();
+        let envelope = AggregatedVoteEnvelope::new(&proof, proof.public_inputs.len(), 1).unwrap();
+        let bytes = envelope.to_bytes();
+        assert!(AggregatedVoteEnvelope::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(AggregatedVoteEnvelope::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+    }
+}
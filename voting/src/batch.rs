@@ -0,0 +1,477 @@
+//! Batched vote proofs.
+//!
+//! A coordinator collecting votes off-chain can prove the votes of up to `max_votes` distinct
+//! voters of one voter set on one proposal in a single proof, instead of aggregating one proof per
+//! vote. Every vote still has its own merkle path and nullifier, and all nullifiers are exposed.
+//!
+//! # Public inputs
+//!
+//! `proposal_id || merkle_root`, followed by `active || vote || nullifier` for every slot. Slots
+//! are filled in order, the slots after the last vote are inactive and expose zeros. See
+//! [`vote_batch_public_inputs_len`].
+use alloc::{format, vec, vec::Vec};
+use anyhow::{bail, Context};
+use plonky2::{
+    field::types::Field,
+    hash::hash_types::HashOutTarget,
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+use zk_circuits_common::circuit::{CircuitFragment, ShapeError, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::utils::{felts_to_hashout, Digest, DIGEST_NUM_FIELD_ELEMENTS, ZERO_DIGEST};
+
+use crate::{
+    check_merkle_path, fill_merkle_path, merkle_path_circuit, nullifier_circuit, VoteCircuitData,
+    VotePrivateInputs, VotePublicInputs, MAX_MERKLE_DEPTH,
+};
+
+/// The number of public inputs of a slot of a batch: the active flag, the vote and the nullifier.
+pub const VOTE_BATCH_SLOT_LEN: usize = 2 + DIGEST_NUM_FIELD_ELEMENTS;
+
+/// The number of public inputs of a batch proof with `max_votes` slots.
+pub const fn vote_batch_public_inputs_len(max_votes: usize) -> usize {
+    2 * DIGEST_NUM_FIELD_ELEMENTS + max_votes * VOTE_BATCH_SLOT_LEN
+}
+
+/// The targets of a vote batch.
+#[derive(Clone, Debug)]
+pub struct VoteBatchTargets {
+    pub proposal_id: HashOutTarget,
+    pub merkle_root: HashOutTarget,
+    pub slots: Vec<VoteSlotTargets>,
+}
+
+/// The targets of one vote of a batch.
+#[derive(Clone, Debug)]
+pub struct VoteSlotTargets {
+    // Public Input Targets
+    pub active: BoolTarget,
+    pub vote: BoolTarget,
+    pub nullifier: HashOutTarget,
+
+    // Private Input Targets
+    pub private_key: HashOutTarget,
+    pub merkle_siblings: Vec<HashOutTarget>,
+    pub path_indices: Vec<BoolTarget>,
+    pub actual_merkle_depth: Target,
+}
+
+impl VoteBatchTargets {
+    /// Creates the targets of a batch of up to `max_votes` votes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `max_votes` is zero.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, max_votes: usize) -> anyhow::Result<Self> {
+        if max_votes == 0 {
+            bail!("a vote batch must hold at least one vote");
+        }
+
+        let proposal_id = builder.add_virtual_hash_public_input();
+        let merkle_root = builder.add_virtual_hash_public_input();
+        let slots = (0..max_votes)
+            .map(|_| {
+                let active = builder.add_virtual_bool_target_safe();
+                builder.register_public_input(active.target);
+                let vote = builder.add_virtual_bool_target_safe();
+                builder.register_public_input(vote.target);
+                let nullifier = builder.add_virtual_hash_public_input();
+
+                VoteSlotTargets {
+                    active,
+                    vote,
+                    nullifier,
+                    private_key: builder.add_virtual_hash(),
+                    merkle_siblings: (0..MAX_MERKLE_DEPTH)
+                        .map(|_| builder.add_virtual_hash())
+                        .collect(),
+                    path_indices: (0..MAX_MERKLE_DEPTH)
+                        .map(|_| builder.add_virtual_bool_target_safe())
+                        .collect(),
+                    actual_merkle_depth: builder.add_virtual_target(),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            proposal_id,
+            merkle_root,
+            slots,
+        })
+    }
+}
+
+/// One vote of a [`VoteBatch`].
+#[derive(Debug, Clone)]
+pub struct BatchedVote {
+    /// The vote (0 for no, 1 for yes)
+    pub vote: bool,
+    /// The nullifier of the voter on the proposal
+    pub nullifier: Digest,
+    /// The private key and merkle path of the voter. Batches don't bind proposal metadata.
+    pub private_inputs: VotePrivateInputs,
+}
+
+/// The votes of distinct voters of one voter set on one proposal, proven together.
+#[derive(Debug, Clone)]
+pub struct VoteBatch {
+    pub proposal_id: Digest,
+    /// The merkle root of the eligible voters
+    pub merkle_root: Digest,
+    /// The votes, filling the slots of the batch in order.
+    pub votes: Vec<BatchedVote>,
+}
+
+impl VoteBatch {
+    /// Batches the inputs of single vote proofs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no votes, the votes are for different proposals or voter
+    /// sets, or a vote has a voter tag, a sequence number or proposal metadata, which batches
+    /// don't support.
+    pub fn from_votes(votes: &[VoteCircuitData]) -> anyhow::Result<Self> {
+        let Some(first) = votes.first() else {
+            bail!("cannot batch without votes");
+        };
+
+        let mut batched = Vec::with_capacity(votes.len());
+        for (i, vote) in votes.iter().enumerate() {
+            let public = &vote.public_inputs;
+            if public.proposal_id != first.public_inputs.proposal_id {
+                bail!("vote {} is for a different proposal than vote 0", i);
+            }
+            if public.merkle_root != first.public_inputs.merkle_root {
+                bail!("vote {} has a different merkle root than vote 0", i);
+            }
+            if public.voter_tag.is_some() || public.sequence != 0 {
+                bail!("vote {} has a voter tag or sequence number", i);
+            }
+            if vote.private_inputs.proposal_metadata.is_some() {
+                bail!("vote {} binds proposal metadata", i);
+            }
+            batched.push(BatchedVote {
+                vote: public.vote,
+                nullifier: public.nullifier,
+                private_inputs: vote.private_inputs.clone(),
+            });
+        }
+
+        Ok(Self {
+            proposal_id: first.public_inputs.proposal_id,
+            merkle_root: first.public_inputs.merkle_root,
+            votes: batched,
+        })
+    }
+
+    /// Encodes the public inputs of a batch proof with `max_votes` slots, in the order the
+    /// circuit registers them.
+    pub fn to_field_elements(&self, max_votes: usize) -> Vec<F> {
+        let mut felts = Vec::with_capacity(vote_batch_public_inputs_len(max_votes));
+        felts.extend_from_slice(&self.proposal_id);
+        felts.extend_from_slice(&self.merkle_root);
+        for vote in &self.votes {
+            felts.push(F::ONE);
+            felts.push(F::from_bool(vote.vote));
+            felts.extend_from_slice(&vote.nullifier);
+        }
+        felts.resize(vote_batch_public_inputs_len(max_votes), F::ZERO);
+        felts
+    }
+
+    /// Parses the public inputs of a batch proof into the votes of its active slots, so they can
+    /// be tallied like the votes of single vote proofs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length doesn't match a batch, a flag is not 0 or 1, an active slot
+    /// follows an inactive one, or an inactive slot is not zero.
+    pub fn votes_from_public_inputs(pis: &[F]) -> anyhow::Result<Vec<VotePublicInputs>> {
+        let header_len = 2 * DIGEST_NUM_FIELD_ELEMENTS;
+        if pis.len() <= header_len || !(pis.len() - header_len).is_multiple_of(VOTE_BATCH_SLOT_LEN)
+        {
+            bail!(
+                "vote batch public inputs should contain {} plus a multiple of {} field elements, \
+                 got: {}",
+                header_len,
+                VOTE_BATCH_SLOT_LEN,
+                pis.len()
+            );
+        }
+
+        let digest = |felts: &[F]| -> Digest { felts.try_into().expect("slice has digest length") };
+        let flag = |felt: F, name: &str| -> anyhow::Result<bool> {
+            match felt {
+                felt if felt == F::ZERO => Ok(false),
+                felt if felt == F::ONE => Ok(true),
+                felt => bail!("{} must be 0 or 1, got: {}", name, felt),
+            }
+        };
+        let proposal_id = digest(&pis[..DIGEST_NUM_FIELD_ELEMENTS]);
+        let merkle_root = digest(&pis[DIGEST_NUM_FIELD_ELEMENTS..header_len]);
+
+        let mut votes = Vec::new();
+        for (i, slot) in pis[header_len..].chunks(VOTE_BATCH_SLOT_LEN).enumerate() {
+            if !flag(slot[0], "active")? {
+                if slot.iter().any(|felt| !felt.is_zero()) {
+                    bail!("inactive slot {} is not zero", i);
+                }
+                continue;
+            }
+            if votes.len() != i {
+                bail!("slot {} is active after an inactive slot", i);
+            }
+            votes.push(VotePublicInputs {
+                proposal_id,
+                merkle_root,
+                previous_merkle_roots: Vec::new(),
+                vote: flag(slot[1], "vote")?,
+                nullifier: digest(&slot[2..]),
+                voter_tag: None,
+                sequence: 0,
+            });
+        }
+
+        Ok(votes)
+    }
+}
+
+impl CircuitFragment for VoteBatch {
+    type Targets = VoteBatchTargets;
+
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        let zero = builder.zero();
+        let mut previous_active = builder._true();
+        for slot in &targets.slots {
+            let (leaf_hash, root) = merkle_path_circuit(
+                builder,
+                slot.private_key,
+                &slot.merkle_siblings,
+                &slot.path_indices,
+                slot.actual_merkle_depth,
+            );
+            let nullifier = nullifier_circuit(builder, leaf_hash, targets.proposal_id);
+
+            // Active slots hold votes of eligible voters on the proposal, inactive ones zeros.
+            let inactive = builder.not(slot.active);
+            for k in 0..DIGEST_NUM_FIELD_ELEMENTS {
+                let root_diff = builder.sub(root.elements[k], targets.merkle_root.elements[k]);
+                let root_diff = builder.mul(slot.active.target, root_diff);
+                builder.connect(root_diff, zero);
+
+                let nullifier_diff = builder.sub(nullifier.elements[k], slot.nullifier.elements[k]);
+                let nullifier_diff = builder.mul(slot.active.target, nullifier_diff);
+                builder.connect(nullifier_diff, zero);
+
+                let inactive_nullifier = builder.mul(inactive.target, slot.nullifier.elements[k]);
+                builder.connect(inactive_nullifier, zero);
+            }
+            let inactive_vote = builder.and(inactive, slot.vote);
+            builder.connect(inactive_vote.target, zero);
+
+            // Slots are filled in order, so an active slot can't follow an inactive one.
+            let previous_inactive = builder.not(previous_active);
+            let gap = builder.and(slot.active, previous_inactive);
+            builder.connect(gap.target, zero);
+            previous_active = slot.active;
+        }
+
+        // The nullifier is a hash of the voter's leaf, so distinct nullifiers mean distinct voters.
+        for (i, a) in targets.slots.iter().enumerate() {
+            for b in &targets.slots[i + 1..] {
+                let mut duplicate = builder.and(a.active, b.active);
+                for k in 0..DIGEST_NUM_FIELD_ELEMENTS {
+                    let equal = builder.is_equal(a.nullifier.elements[k], b.nullifier.elements[k]);
+                    duplicate = builder.and(duplicate, equal);
+                }
+                builder.connect(duplicate.target, zero);
+            }
+        }
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let n_log = (usize::BITS - (MAX_MERKLE_DEPTH - 1).leading_zeros()) as usize;
+        FragmentSummary::new("vote_batch")
+            .range_check("slots[j].active, slots[j].vote, slots[j].path_indices", 1)
+            .poseidon("leaf_hash[j]", &["slots[j].private_key"])
+            .range_check("slots[j].actual_merkle_depth", n_log)
+            .assert(format!(
+                "root[j] is the merkle root of leaf_hash[j] along the first \
+                 slots[j].actual_merkle_depth <= {} levels of slots[j].merkle_siblings and \
+                 slots[j].path_indices, as in the vote circuit",
+                MAX_MERKLE_DEPTH
+            ))
+            .poseidon("nullifier[j]", &["leaf_hash[j]", "proposal_id"])
+            .assert(
+                "root[j] == merkle_root and nullifier[j] == slots[j].nullifier if slots[j].active",
+            )
+            .assert("slots[j].vote == 0 and slots[j].nullifier == 0 if not slots[j].active")
+            .assert("slots[j].active implies slots[j - 1].active")
+            .assert(format!(
+                "slots[i].nullifier != slots[j].nullifier for active slots i < j < {}",
+                targets.slots.len()
+            ))
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        ShapeError::check_max_len("vote_batch", "votes", targets.slots.len(), self.votes.len())?;
+        for (i, vote) in self.votes.iter().enumerate() {
+            check_merkle_path("vote_batch", &vote.private_inputs)
+                .with_context(|| format!("vote {}", i))?;
+            if vote.private_inputs.proposal_metadata.is_some() {
+                bail!(
+                    "vote {} binds proposal metadata, which batches don't support",
+                    i
+                );
+            }
+            if let Some(j) = self.votes[..i]
+                .iter()
+                .position(|other| other.nullifier == vote.nullifier)
+            {
+                bail!("votes {} and {} have the same nullifier", j, i);
+            }
+        }
+
+        pw.set_hash_target(targets.proposal_id, felts_to_hashout(&self.proposal_id))?;
+        pw.set_hash_target(targets.merkle_root, felts_to_hashout(&self.merkle_root))?;
+
+        let inactive = VotePrivateInputs {
+            private_key: ZERO_DIGEST,
+            merkle_siblings: vec![],
+            path_indices: vec![],
+            actual_merkle_depth: 0,
+            proposal_metadata: None,
+        };
+        for (i, slot) in targets.slots.iter().enumerate() {
+            let vote = self.votes.get(i);
+            pw.set_bool_target(slot.active, vote.is_some())?;
+            pw.set_bool_target(slot.vote, vote.is_some_and(|vote| vote.vote))?;
+            let nullifier = vote.map_or(ZERO_DIGEST, |vote| vote.nullifier);
+            pw.set_hash_target(slot.nullifier, felts_to_hashout(&nullifier))?;
+            fill_merkle_path(
+                pw,
+                vote.map_or(&inactive, |vote| &vote.private_inputs),
+                slot.private_key,
+                &slot.merkle_siblings,
+                &slot.path_indices,
+                slot.actual_merkle_depth,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use zk_circuits_common::circuit::C;
+
+    use crate::tally::TallyCertificate;
+    use crate::voting_tests::create_test_inputs_for_voter;
+
+    const MAX_VOTES: usize = 3;
+
+    fn prove_batch(batch: &VoteBatch) -> anyhow::Result<Vec<F>> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteBatchTargets::new(&mut builder, MAX_VOTES)?;
+        VoteBatch::circuit(&targets, &mut builder);
+        let mut pw = PartialWitness::new();
+        batch.fill_targets(&mut pw, targets)?;
+
+        let circuit_data = builder.build::<C>();
+        let proof = circuit_data.prove(pw)?;
+        circuit_data.verify(proof.clone())?;
+        Ok(proof.public_inputs)
+    }
+
+    fn two_votes() -> VoteBatch {
+        let mut second = create_test_inputs_for_voter(1);
+        second.public_inputs.vote = false;
+        VoteBatch::from_votes(&[create_test_inputs_for_voter(0), second]).unwrap()
+    }
+
+    #[test]
+    fn test_batch_exposes_every_vote() -> anyhow::Result<()> {
+        let batch = two_votes();
+        let pis = prove_batch(&batch)?;
+        assert_eq!(pis.len(), vote_batch_public_inputs_len(MAX_VOTES));
+        assert_eq!(pis, batch.to_field_elements(MAX_VOTES));
+
+        let votes = VoteBatch::votes_from_public_inputs(&pis)?;
+        assert_eq!(votes.len(), 2);
+        assert_eq!(votes[1].nullifier, batch.votes[1].nullifier);
+        let tally = TallyCertificate::from_votes(&votes)?;
+        assert_eq!((tally.yes, tally.no), (1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_rejects_duplicate_and_ineligible_voters() {
+        let mut batch = two_votes();
+        batch.votes[1] = batch.votes[0].clone();
+        let err = prove_batch(&batch).unwrap_err();
+        assert!(err.to_string().contains("same nullifier"), "{}", err);
+
+        let mut batch = two_votes();
+        batch.votes[1].private_inputs.merkle_siblings[0] = ZERO_DIGEST;
+        assert!(prove_batch(&batch).is_err());
+
+        let votes: Vec<_> = (0..=MAX_VOTES).map(create_test_inputs_for_voter).collect();
+        let batch = VoteBatch::from_votes(&votes).unwrap();
+        assert!(prove_batch(&batch).is_err());
+    }
+
+    #[test]
+    fn test_batch_rejects_misshapen_inputs() {
+        let votes: Vec<_> = (0..=MAX_VOTES).map(create_test_inputs_for_voter).collect();
+        let batch = VoteBatch::from_votes(&votes).unwrap();
+        let err = prove_batch(&batch).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ShapeError>(),
+            Some(&ShapeError::TooLong {
+                fragment: "vote_batch",
+                field: "votes",
+                max: MAX_VOTES,
+                got: MAX_VOTES + 1,
+            })
+        );
+
+        let mut batch = two_votes();
+        batch.votes[1].private_inputs.merkle_siblings.pop();
+        let err = prove_batch(&batch).unwrap_err();
+        assert!(err.to_string().contains("vote 1"), "{}", err);
+        let shape = err.downcast_ref::<ShapeError>().unwrap();
+        assert_eq!(
+            (shape.fragment(), shape.field(), shape.code()),
+            ("vote_batch", "merkle_siblings", "shape.too_short")
+        );
+    }
+
+    #[test]
+    fn test_public_inputs_reject_gaps_and_nonzero_inactive_slots() {
+        let batch = two_votes();
+        let pis = batch.to_field_elements(MAX_VOTES);
+        let first_slot = 2 * DIGEST_NUM_FIELD_ELEMENTS;
+        let last_slot = first_slot + 2 * VOTE_BATCH_SLOT_LEN;
+
+        let mut gap = pis.clone();
+        gap.copy_within(first_slot + VOTE_BATCH_SLOT_LEN..last_slot, last_slot);
+        gap[first_slot + VOTE_BATCH_SLOT_LEN..last_slot].fill(F::ZERO);
+        assert!(VoteBatch::votes_from_public_inputs(&gap).is_err());
+
+        let mut nonzero = pis.clone();
+        nonzero[last_slot + 1] = F::ONE;
+        assert!(VoteBatch::votes_from_public_inputs(&nonzero).is_err());
+        assert!(VoteBatch::votes_from_public_inputs(&pis[..pis.len() - 1]).is_err());
+    }
+}
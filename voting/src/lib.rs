@@ -1,16 +1,28 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod aggregate;
+pub mod batch;
+pub mod proposal;
+pub mod tally;
+
+use alloc::{format, vec, vec::Vec};
 use plonky2::{
-    field::types::Field,
-    hash::hash_types::HashOutTarget,
+    field::types::{Field, PrimeField64},
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
     iop::{
         target::{BoolTarget, Target},
         witness::{PartialWitness, WitnessWrite},
     },
-    plonk::circuit_builder::CircuitBuilder,
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
 };
 
-use anyhow::bail;
-use zk_circuits_common::circuit::{CircuitFragment, D, F};
-use zk_circuits_common::gadgets::is_const_less_than;
+use anyhow::{anyhow, bail};
+use proposal::{ProposalMetadata, ProposalMetadataTargets};
+use zk_circuits_common::circuit::{check_config, CircuitFragment, ShapeError, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::merkle::merkle_proof_root;
 use zk_circuits_common::utils::{
     felts_to_hashout, Digest, PrivateKey, DIGEST_NUM_FIELD_ELEMENTS, ZERO_DIGEST,
 };
@@ -19,6 +31,47 @@ use zk_circuits_common::utils::{
 /// This allows for up to 2^32 eligible voters.
 pub const MAX_MERKLE_DEPTH: usize = 32;
 
+/// The number of public inputs of a vote proof.
+///
+/// This matches the public inputs of a wormhole leaf proof, so vote proofs can be batched by the
+/// wormhole aggregator and split back into leaves with the same stride. The inputs are laid out as
+/// `proposal_id || merkle_root || vote || nullifier`, followed by zero padding.
+pub const VOTE_PUBLIC_INPUTS_LEN: usize = 16;
+
+/// The number of public inputs of a vote proof of a circuit built with
+/// [`VoteTargets::with_voter_tag`], laid out as
+/// `proposal_id || merkle_root || vote || nullifier || election_namespace || voter_tag`, followed
+/// by a single zero. These don't fit the wormhole leaf layout. The zero sets their length apart
+/// from every [`vote_with_allowed_roots_public_inputs_len`], so the layouts can't be mistaken for
+/// each other.
+pub const VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN: usize =
+    VOTE_PUBLIC_INPUTS_USED + 2 * DIGEST_NUM_FIELD_ELEMENTS + 1;
+
+/// The number of public inputs of a vote proof of a circuit built with
+/// [`VoteTargets::with_allowed_roots`] allowing `num_allowed_roots` merkle roots, laid out as
+/// `proposal_id || merkle_root || vote || nullifier || previous_merkle_roots`. These don't fit the
+/// wormhole leaf layout and are not padded.
+pub const fn vote_with_allowed_roots_public_inputs_len(num_allowed_roots: usize) -> usize {
+    VOTE_PUBLIC_INPUTS_USED + num_allowed_roots.saturating_sub(1) * DIGEST_NUM_FIELD_ELEMENTS
+}
+
+/// The number of public inputs that carry values, before the zero padding.
+const VOTE_PUBLIC_INPUTS_USED: usize = 3 * DIGEST_NUM_FIELD_ELEMENTS + 1;
+
+// Proofs with allowed roots have `VOTE_PUBLIC_INPUTS_USED` public inputs plus a multiple of a
+// digest, which the other layouts must never match.
+const _: () =
+    assert!((VOTE_PUBLIC_INPUTS_LEN - VOTE_PUBLIC_INPUTS_USED) % DIGEST_NUM_FIELD_ELEMENTS != 0);
+const _: () = assert!(
+    (VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN - VOTE_PUBLIC_INPUTS_USED) % DIGEST_NUM_FIELD_ELEMENTS
+        != 0
+);
+
+/// The index of the sequence number of a vote proof of a circuit built with
+/// [`VoteTargets::with_revoting`], in the first element of the padding. Circuits without revoting
+/// pad it with zero, so their votes have sequence number 0.
+pub const VOTE_SEQUENCE_INDEX: usize = VOTE_PUBLIC_INPUTS_USED;
+
 /// Public inputs for the vote circuit.
 ///
 /// These inputs are visible to all parties and are used to verify the vote's validity.
@@ -26,12 +79,182 @@ pub const MAX_MERKLE_DEPTH: usize = 32;
 pub struct VotePublicInputs {
     /// The proposal ID this vote is for
     pub proposal_id: Digest,
-    /// The merkle root of eligible addresses
+    /// The merkle root of eligible addresses, the most recent snapshot for circuits built with
+    /// [`VoteTargets::with_allowed_roots`]
     pub merkle_root: Digest,
+    /// Older snapshots of the eligible addresses the vote may be proven against instead, only
+    /// present for circuits built with [`VoteTargets::with_allowed_roots`]
+    pub previous_merkle_roots: Vec<Digest>,
     /// The vote (0 for no, 1 for yes)
     pub vote: bool,
     /// The nullifier to prevent double voting
     pub nullifier: Digest,
+    /// The voter tag, only present for circuits built with [`VoteTargets::with_voter_tag`]
+    pub voter_tag: Option<VoterTag>,
+    /// The number of votes of the voter on the proposal the chain included before this one, only
+    /// the last vote is counted. Always 0 for circuits built without [`VoteTargets::with_revoting`]
+    pub sequence: u32,
+}
+
+/// Links the votes of a voter across proposals without revealing the voter.
+///
+/// The tag is `H(private_key || election_namespace)`. Unlike the nullifier, it doesn't depend on
+/// the proposal, so every vote of a voter within the same namespace carries the same tag. Votes in
+/// different namespaces, and votes of circuits built without the tag, stay unlinkable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoterTag {
+    /// Scopes the tag, e.g. to one governance body or one election period
+    pub election_namespace: Digest,
+    /// The tag of the voter in the namespace
+    pub tag: Digest,
+}
+
+impl VoterTag {
+    /// Computes the tag of the voter with `private_key` in `election_namespace`.
+    pub fn new(private_key: &PrivateKey, election_namespace: Digest) -> Self {
+        let mut preimage = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
+        preimage.extend_from_slice(private_key);
+        preimage.extend_from_slice(&election_namespace);
+        Self {
+            election_namespace,
+            tag: PoseidonHash::hash_no_pad(&preimage).elements,
+        }
+    }
+}
+
+impl VotePublicInputs {
+    /// Encodes the public inputs in the order the vote circuit registers them, including the
+    /// padding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both a voter tag and previous merkle roots are set, which no circuit exposes.
+    pub fn to_field_elements(&self) -> Vec<F> {
+        assert!(
+            self.voter_tag.is_none() || self.previous_merkle_roots.is_empty(),
+            "voter tags can't be combined with previous merkle roots"
+        );
+        let mut felts = Vec::with_capacity(VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN);
+        felts.extend_from_slice(&self.proposal_id);
+        felts.extend_from_slice(&self.merkle_root);
+        felts.push(F::from_bool(self.vote));
+        felts.extend_from_slice(&self.nullifier);
+        match &self.voter_tag {
+            Some(voter_tag) => {
+                felts.extend_from_slice(&voter_tag.election_namespace);
+                felts.extend_from_slice(&voter_tag.tag);
+                felts.push(F::ZERO);
+            }
+            None if !self.previous_merkle_roots.is_empty() => {
+                felts.extend(self.previous_merkle_roots.iter().flatten());
+            }
+            None => {
+                felts.push(F::from_canonical_u32(self.sequence));
+                felts.resize(VOTE_PUBLIC_INPUTS_LEN, F::ZERO);
+            }
+        }
+        felts
+    }
+
+    /// Parses the public inputs of a single vote proof, with or without a voter tag. Proofs of
+    /// circuits built with [`VoteTargets::with_allowed_roots`] are parsed with
+    /// [`Self::try_from_slice_with_allowed_roots`]; their lengths differ from both layouts, so they
+    /// are rejected here.
+    pub fn try_from_slice(pis: &[F]) -> anyhow::Result<Self> {
+        let has_voter_tag = match pis.len() {
+            VOTE_PUBLIC_INPUTS_LEN => false,
+            VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN => true,
+            len => bail!(
+                "vote public inputs should contain: {} or {} field elements, got: {}",
+                VOTE_PUBLIC_INPUTS_LEN,
+                VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN,
+                len
+            ),
+        };
+        let sequence = if has_voter_tag {
+            if !pis[VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN - 1].is_zero() {
+                bail!("vote public input padding is not zero");
+            }
+            0
+        } else {
+            if pis[VOTE_SEQUENCE_INDEX + 1..].iter().any(|felt| !felt.is_zero()) {
+                bail!("vote public input padding is not zero");
+            }
+            let sequence = pis[VOTE_SEQUENCE_INDEX].to_canonical_u64();
+            u32::try_from(sequence)
+                .map_err(|_| anyhow!("vote sequence number {} exceeds u32", sequence))?
+        };
+
+        let digest = |start: usize| -> Digest {
+            pis[start..start + DIGEST_NUM_FIELD_ELEMENTS]
+                .try_into()
+                .expect("slice has digest length")
+        };
+        let vote = match pis[2 * DIGEST_NUM_FIELD_ELEMENTS] {
+            felt if felt == F::ZERO => false,
+            felt if felt == F::ONE => true,
+            felt => bail!("vote must be 0 or 1, got: {}", felt),
+        };
+        let voter_tag = has_voter_tag.then(|| VoterTag {
+            election_namespace: digest(VOTE_PUBLIC_INPUTS_USED),
+            tag: digest(VOTE_PUBLIC_INPUTS_USED + DIGEST_NUM_FIELD_ELEMENTS),
+        });
+
+        Ok(Self {
+            proposal_id: digest(0),
+            merkle_root: digest(DIGEST_NUM_FIELD_ELEMENTS),
+            previous_merkle_roots: Vec::new(),
+            vote,
+            nullifier: digest(2 * DIGEST_NUM_FIELD_ELEMENTS + 1),
+            voter_tag,
+            sequence,
+        })
+    }
+
+    /// Parses the public inputs of a vote proof of a circuit built with
+    /// [`VoteTargets::with_allowed_roots`] allowing `num_allowed_roots` merkle roots.
+    pub fn try_from_slice_with_allowed_roots(
+        pis: &[F],
+        num_allowed_roots: usize,
+    ) -> anyhow::Result<Self> {
+        if num_allowed_roots <= 1 {
+            return Self::try_from_slice(pis);
+        }
+        let expected_len = vote_with_allowed_roots_public_inputs_len(num_allowed_roots);
+        if pis.len() != expected_len {
+            bail!(
+                "expected {} vote public inputs for {} allowed merkle roots, got: {}",
+                expected_len,
+                num_allowed_roots,
+                pis.len()
+            );
+        }
+
+        // Up to the previous roots, the inputs are those of a vote without the padding.
+        let mut padded = pis[..VOTE_PUBLIC_INPUTS_USED].to_vec();
+        padded.resize(VOTE_PUBLIC_INPUTS_LEN, F::ZERO);
+        let mut vote = Self::try_from_slice(&padded)?;
+        vote.previous_merkle_roots = pis[VOTE_PUBLIC_INPUTS_USED..]
+            .chunks(DIGEST_NUM_FIELD_ELEMENTS)
+            .map(|chunk| chunk.try_into().expect("chunk has digest length"))
+            .collect();
+        Ok(vote)
+    }
+
+    /// Splits the public inputs of a proof aggregated from vote proofs back into the votes, in
+    /// leaf order. `leaf_pi_len` is the number of public inputs of a vote proof, either
+    /// [`VOTE_PUBLIC_INPUTS_LEN`] or [`VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN`].
+    pub fn try_from_aggregated(pis: &[F], leaf_pi_len: usize) -> anyhow::Result<Vec<Self>> {
+        if leaf_pi_len == 0 || pis.len() % leaf_pi_len != 0 {
+            bail!(
+                "aggregated public inputs should be a multiple of {}, got: {}",
+                leaf_pi_len,
+                pis.len()
+            );
+        }
+
+        pis.chunks(leaf_pi_len).map(Self::try_from_slice).collect()
+    }
 }
 
 /// Private inputs for the vote circuit.
@@ -48,6 +271,9 @@ pub struct VotePrivateInputs {
     pub path_indices: Vec<bool>,
     /// The actual depth of this specific Merkle proof
     pub actual_merkle_depth: usize,
+    /// The metadata hashed into the proposal id, only used by circuits built with
+    /// [`VoteTargets::with_proposal_metadata`]
+    pub proposal_metadata: Option<ProposalMetadata>,
 }
 
 /// Holds all the targets created during circuit construction.
@@ -56,18 +282,105 @@ pub struct VoteTargets {
     // Public Input Targets
     pub proposal_id: HashOutTarget,
     pub expected_merkle_root: HashOutTarget,
+    pub previous_merkle_roots: Vec<HashOutTarget>,
     pub vote: BoolTarget,
     pub expected_nullifier: HashOutTarget,
+    pub voter_tag: Option<VoterTagTargets>,
+    pub sequence: Option<Target>,
 
     // Private Input Targets
     pub private_key: HashOutTarget,
     pub merkle_siblings: Vec<HashOutTarget>,
     pub path_indices: Vec<BoolTarget>,
     pub actual_merkle_depth: Target,
+    pub proposal_metadata: Option<ProposalMetadataTargets>,
+}
+
+/// The targets of a [`VoterTag`].
+#[derive(Clone, Debug)]
+pub struct VoterTagTargets {
+    pub election_namespace: HashOutTarget,
+    pub tag: HashOutTarget,
 }
 
 impl VoteTargets {
     pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_options(builder, false, false, false, 1)
+    }
+
+    /// Like [`Self::new`], but first checks that the builder's config can hold the vote circuit,
+    /// which would otherwise panic once it is built.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config fails [`check_config`].
+    pub fn try_new(builder: &mut CircuitBuilder<F, D>) -> anyhow::Result<Self> {
+        check_config(&builder.config)?;
+        Ok(Self::new(builder))
+    }
+
+    /// Like [`Self::new`], but additionally exposes a [`VoterTag`], making the votes of a voter
+    /// linkable across proposals of the same election namespace.
+    pub fn with_voter_tag(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_options(builder, true, false, false, 1)
+    }
+
+    /// Like [`Self::new`], but additionally proves that the proposal id is the hash of the
+    /// [`ProposalMetadata`] given as private inputs, binding every vote to the proposal content.
+    /// The public inputs are the same as those of [`Self::new`].
+    pub fn with_proposal_metadata(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_options(builder, false, true, false, 1)
+    }
+
+    /// Like [`Self::new`], but additionally exposes a 32 bit sequence number at
+    /// [`VOTE_SEQUENCE_INDEX`], so voters can cast the vote on a proposal again.
+    ///
+    /// The nullifier stays `H(H(private_key) || proposal_id)`, so every vote of a voter on the
+    /// proposal carries the same nullifier. The sequence number must be the number of votes the
+    /// chain already included with the nullifier, which the contract checks against its own
+    /// counter, and tallies count only the last vote, see
+    /// [`tally::TallyCertificate::from_revotes`].
+    ///
+    /// This lets voters change their vote, but gives no coercion resistance: the nullifier is
+    /// public and the same for every vote, so anyone can watch for a later vote of a voter and
+    /// tell which vote was counted.
+    pub fn with_revoting(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_options(builder, false, false, true, 1)
+    }
+
+    /// Like [`Self::new`], but accepts membership in any of `num_allowed_roots` merkle roots: the
+    /// public `merkle_root` and `num_allowed_roots - 1` public `previous_merkle_roots` after the
+    /// nullifier, see [`vote_with_allowed_roots_public_inputs_len`].
+    ///
+    /// Voters whose merkle proof was generated against a recent snapshot of the eligible
+    /// addresses are not rejected while the registry rotates to a new root. The nullifier doesn't
+    /// depend on the root, so a voter can't vote once per snapshot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_allowed_roots` is 0.
+    pub fn with_allowed_roots(
+        builder: &mut CircuitBuilder<F, D>,
+        num_allowed_roots: usize,
+    ) -> Self {
+        assert!(num_allowed_roots > 0, "at least one merkle root must be allowed");
+        Self::with_options(builder, false, false, false, num_allowed_roots)
+    }
+
+    fn with_options(
+        builder: &mut CircuitBuilder<F, D>,
+        voter_tag: bool,
+        proposal_metadata: bool,
+        revoting: bool,
+        num_allowed_roots: usize,
+    ) -> Self {
+        // The voter tag, the previous roots and the sequence number each replace the padding, so
+        // a circuit can only have one of them.
+        assert!(
+            usize::from(voter_tag) + usize::from(revoting) + usize::from(num_allowed_roots > 1)
+                <= 1,
+            "voter tags, revoting and multiple merkle roots can't be combined"
+        );
         // Public Input Targets
         let proposal_id = builder.add_virtual_hash_public_input();
         let expected_merkle_root = builder.add_virtual_hash_public_input();
@@ -75,6 +388,36 @@ impl VoteTargets {
         builder.register_public_input(vote.target); // Explicitly make it public
         let expected_nullifier = builder.add_virtual_hash_public_input();
 
+        let mut sequence = None;
+        let mut previous_merkle_roots = Vec::new();
+        let voter_tag = if voter_tag {
+            let voter_tag = VoterTagTargets {
+                election_namespace: builder.add_virtual_hash_public_input(),
+                tag: builder.add_virtual_hash_public_input(),
+            };
+            let zero = builder.zero();
+            builder.register_public_input(zero);
+            Some(voter_tag)
+        } else if num_allowed_roots > 1 {
+            previous_merkle_roots = (1..num_allowed_roots)
+                .map(|_| builder.add_virtual_hash_public_input())
+                .collect();
+            None
+        } else {
+            let mut num_public_inputs = VOTE_PUBLIC_INPUTS_USED;
+            if revoting {
+                let target = builder.add_virtual_public_input();
+                sequence = Some(target);
+                num_public_inputs += 1;
+            }
+            // Pad to the wormhole leaf layout with constant zeros.
+            let zero = builder.zero();
+            for _ in num_public_inputs..VOTE_PUBLIC_INPUTS_LEN {
+                builder.register_public_input(zero);
+            }
+            None
+        };
+
         // Private Input Targets
         let private_key = builder.add_virtual_hash();
         let merkle_siblings: Vec<_> = (0..MAX_MERKLE_DEPTH)
@@ -84,18 +427,141 @@ impl VoteTargets {
             .map(|_| builder.add_virtual_bool_target_safe())
             .collect();
         let actual_merkle_depth = builder.add_virtual_target();
+        let proposal_metadata =
+            proposal_metadata.then(|| ProposalMetadataTargets::new(builder, proposal_id));
 
         Self {
             proposal_id,
             expected_merkle_root,
+            previous_merkle_roots,
             vote,
             expected_nullifier,
+            voter_tag,
+            sequence,
             private_key,
             merkle_siblings,
             path_indices,
             actual_merkle_depth,
+            proposal_metadata,
+        }
+    }
+}
+
+/// Hashes the private key into the leaf of the voter and walks its merkle path up to
+/// `actual_merkle_depth`. Returns the leaf hash and the computed root.
+pub(crate) fn merkle_path_circuit(
+    builder: &mut CircuitBuilder<F, D>,
+    private_key: HashOutTarget,
+    merkle_siblings: &[HashOutTarget],
+    path_indices: &[BoolTarget],
+    actual_merkle_depth: Target,
+) -> (HashOutTarget, HashOutTarget) {
+    let leaf_hash_targets =
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>(private_key.elements.to_vec());
+    let root = merkle_proof_root::<PoseidonHash, F, D>(
+        builder,
+        leaf_hash_targets,
+        path_indices,
+        merkle_siblings,
+        Some(actual_merkle_depth),
+    );
+
+    (leaf_hash_targets, root)
+}
+
+/// Constrains `root` to equal one of `allowed_roots`.
+pub(crate) fn assert_root_allowed(
+    builder: &mut CircuitBuilder<F, D>,
+    root: HashOutTarget,
+    allowed_roots: &[HashOutTarget],
+) {
+    let mut any_equal = builder._false();
+    for allowed_root in allowed_roots {
+        let mut equal = builder._true();
+        for (&element, &allowed_element) in root.elements.iter().zip(&allowed_root.elements) {
+            let element_equal = builder.is_equal(element, allowed_element);
+            equal = builder.and(equal, element_equal);
         }
+        any_equal = builder.or(any_equal, equal);
     }
+    builder.assert_one(any_equal.target);
+}
+
+/// Computes the nullifier `H(leaf_hash || proposal_id)` of a vote.
+pub(crate) fn nullifier_circuit(
+    builder: &mut CircuitBuilder<F, D>,
+    leaf_hash: HashOutTarget,
+    proposal_id: HashOutTarget,
+) -> HashOutTarget {
+    let mut nullifier_input_elements = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
+    nullifier_input_elements.extend_from_slice(&leaf_hash.elements);
+    nullifier_input_elements.extend_from_slice(&proposal_id.elements);
+
+    builder.hash_n_to_hash_no_pad::<PoseidonHash>(nullifier_input_elements)
+}
+
+/// Checks that the merkle path of a voter fits the targets of [`merkle_path_circuit`], naming
+/// `fragment` in the error.
+pub(crate) fn check_merkle_path(
+    fragment: &'static str,
+    private_inputs: &VotePrivateInputs,
+) -> anyhow::Result<()> {
+    // Validate merkle depth
+    if private_inputs.actual_merkle_depth > MAX_MERKLE_DEPTH {
+        bail!(
+            "Merkle tree depth {} exceeds maximum allowed depth {}",
+            private_inputs.actual_merkle_depth,
+            MAX_MERKLE_DEPTH
+        );
+    }
+
+    // Validate merkle proof length
+    let siblings = private_inputs.merkle_siblings.len();
+    ShapeError::check_max_len(fragment, "merkle_siblings", MAX_MERKLE_DEPTH, siblings)?;
+    ShapeError::check_min_len(
+        fragment,
+        "merkle_siblings",
+        private_inputs.actual_merkle_depth,
+        siblings,
+    )?;
+    ShapeError::check_len(
+        fragment,
+        "path_indices",
+        siblings,
+        private_inputs.path_indices.len(),
+    )?;
+    Ok(())
+}
+
+/// Fills the private key and merkle path targets of a voter, see [`merkle_path_circuit`]. The
+/// path must have passed [`check_merkle_path`].
+pub(crate) fn fill_merkle_path(
+    pw: &mut PartialWitness<F>,
+    private_inputs: &VotePrivateInputs,
+    private_key: HashOutTarget,
+    merkle_siblings: &[HashOutTarget],
+    path_indices: &[BoolTarget],
+    actual_merkle_depth: Target,
+) -> anyhow::Result<()> {
+    pw.set_hash_target(private_key, felts_to_hashout(&private_inputs.private_key))?;
+    pw.set_target(
+        actual_merkle_depth,
+        F::from_canonical_usize(private_inputs.actual_merkle_depth),
+    )?;
+
+    for i in 0..MAX_MERKLE_DEPTH {
+        if i < private_inputs.actual_merkle_depth {
+            pw.set_hash_target(
+                merkle_siblings[i],
+                felts_to_hashout(&private_inputs.merkle_siblings[i]),
+            )?;
+            pw.set_bool_target(path_indices[i], private_inputs.path_indices[i])?;
+        } else {
+            pw.set_hash_target(merkle_siblings[i], felts_to_hashout(&ZERO_DIGEST))?;
+            pw.set_bool_target(path_indices[i], false)?;
+        }
+    }
+    Ok(())
 }
 
 /// Data for the vote circuit, used for witness generation.
@@ -122,102 +588,105 @@ impl CircuitFragment for VoteCircuitData {
 
     fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
         // --- 1. Merkle Proof Verification ---
-        let leaf_hash_targets = builder
-            .hash_n_to_hash_no_pad::<plonky2::hash::poseidon::PoseidonHash>(
-                targets.private_key.elements.to_vec(),
-            );
-        let mut current_hash_targets = leaf_hash_targets;
-
-        let n_log = (usize::BITS - (MAX_MERKLE_DEPTH - 1).leading_zeros()) as usize;
-        for i in 0..MAX_MERKLE_DEPTH {
-            let is_active_level =
-                is_const_less_than(builder, i, targets.actual_merkle_depth, n_log);
-
-            let sibling_hash_targets = targets.merkle_siblings[i];
-            let path_index_bool_target = targets.path_indices[i];
-
-            let mut combined_elements = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
-            let mut left_elements = Vec::with_capacity(DIGEST_NUM_FIELD_ELEMENTS);
-            let mut right_elements = Vec::with_capacity(DIGEST_NUM_FIELD_ELEMENTS);
-
-            for k in 0..DIGEST_NUM_FIELD_ELEMENTS {
-                let left_k = builder.select(
-                    path_index_bool_target,
-                    sibling_hash_targets.elements[k],
-                    current_hash_targets.elements[k],
-                );
-                left_elements.push(left_k);
+        let (leaf_hash_targets, current_hash_targets) = merkle_path_circuit(
+            builder,
+            targets.private_key,
+            &targets.merkle_siblings,
+            &targets.path_indices,
+            targets.actual_merkle_depth,
+        );
 
-                let right_k = builder.select(
-                    path_index_bool_target,
-                    current_hash_targets.elements[k],
-                    sibling_hash_targets.elements[k],
-                );
-                right_elements.push(right_k);
-            }
-            combined_elements.extend(&left_elements);
-            combined_elements.extend(&right_elements);
-
-            let parent_hash_candidacy = builder
-                .hash_n_to_hash_no_pad::<plonky2::hash::poseidon::PoseidonHash>(combined_elements);
-
-            let mut next_hash_elements = Vec::with_capacity(DIGEST_NUM_FIELD_ELEMENTS);
-            for k in 0..DIGEST_NUM_FIELD_ELEMENTS {
-                let selected_k = builder.select(
-                    is_active_level,
-                    parent_hash_candidacy.elements[k],
-                    current_hash_targets.elements[k],
-                );
-                next_hash_elements.push(selected_k);
-            }
-            current_hash_targets = HashOutTarget {
-                elements: next_hash_elements.try_into().unwrap(),
-            };
+        // Final root verification - ensure the computed root matches the expected root, or one of
+        // the previous roots if the circuit allows them
+        if targets.previous_merkle_roots.is_empty() {
+            builder.connect_hashes(current_hash_targets, targets.expected_merkle_root);
+        } else {
+            let mut allowed_roots = vec![targets.expected_merkle_root];
+            allowed_roots.extend_from_slice(&targets.previous_merkle_roots);
+            assert_root_allowed(builder, current_hash_targets, &allowed_roots);
         }
 
-        // Final root verification - ensure the computed root matches the expected root
-        builder.connect_hashes(current_hash_targets, targets.expected_merkle_root);
-
         // --- 2. Nullifier Generation & Verification ---
-        let mut nullifier_input_elements = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
-        nullifier_input_elements.extend_from_slice(&leaf_hash_targets.elements);
-        nullifier_input_elements.extend_from_slice(&targets.proposal_id.elements);
-
-        let computed_nullifier_targets = builder
-            .hash_n_to_hash_no_pad::<plonky2::hash::poseidon::PoseidonHash>(
-                nullifier_input_elements,
-            );
+        let computed_nullifier_targets =
+            nullifier_circuit(builder, leaf_hash_targets, targets.proposal_id);
 
         // Ensure the computed nullifier matches the expected nullifier
         builder.connect_hashes(computed_nullifier_targets, targets.expected_nullifier);
 
-        // --- 3. Vote Validation ---
+        // --- 3. Voter Tag ---
+        if let Some(voter_tag) = &targets.voter_tag {
+            let mut tag_input_elements = Vec::with_capacity(2 * DIGEST_NUM_FIELD_ELEMENTS);
+            tag_input_elements.extend_from_slice(&targets.private_key.elements);
+            tag_input_elements.extend_from_slice(&voter_tag.election_namespace.elements);
+
+            let computed_tag = builder
+                .hash_n_to_hash_no_pad::<plonky2::hash::poseidon::PoseidonHash>(
+                    tag_input_elements,
+                );
+            builder.connect_hashes(computed_tag, voter_tag.tag);
+        }
+
+        // --- 4. Proposal Metadata ---
+        if let Some(proposal_metadata) = &targets.proposal_metadata {
+            ProposalMetadata::circuit(proposal_metadata, builder);
+        }
+
+        // --- 5. Sequence Number ---
+        if let Some(sequence) = targets.sequence {
+            builder.range_check(sequence, 32);
+        }
+
+        // --- 6. Vote Validation ---
         // targets.vote_target is BoolTarget, which implies it is 0 or 1.
         // No explicit constraint needed here as add_virtual_bool_public_input ensures this.
     }
 
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let n_log = (usize::BITS - (MAX_MERKLE_DEPTH - 1).leading_zeros()) as usize;
+        let mut summary = FragmentSummary::new("vote")
+            .range_check("vote, path_indices", 1)
+            .poseidon("leaf_hash", &["private_key"])
+            .range_check("actual_merkle_depth", n_log)
+            .assert(format!(
+                "node[i + 1] == Poseidon(left || right) of node[i] and merkle_siblings[i], \
+                 swapped if path_indices[i], for i < actual_merkle_depth <= {}, with node[0] = \
+                 leaf_hash",
+                MAX_MERKLE_DEPTH
+            ));
+        summary = match targets.previous_merkle_roots.len() {
+            0 => summary.equal("node[actual_merkle_depth]", "expected_merkle_root"),
+            n => summary.assert(format!(
+                "node[actual_merkle_depth] == expected_merkle_root or previous_merkle_roots[i] \
+                 for some i < {}",
+                n
+            )),
+        };
+        summary = summary
+            .poseidon("computed_nullifier", &["leaf_hash", "proposal_id"])
+            .equal("computed_nullifier", "expected_nullifier");
+        if targets.voter_tag.is_some() {
+            summary = summary
+                .poseidon(
+                    "computed_tag",
+                    &["private_key", "voter_tag.election_namespace"],
+                )
+                .equal("computed_tag", "voter_tag.tag");
+        }
+        if let Some(proposal_metadata) = &targets.proposal_metadata {
+            summary = summary.fragment(ProposalMetadata::describe(proposal_metadata));
+        }
+        if targets.sequence.is_some() {
+            summary = summary.range_check("sequence", 32);
+        }
+        summary
+    }
+
     fn fill_targets(
         &self,
         pw: &mut PartialWitness<F>,
         targets: Self::Targets,
     ) -> anyhow::Result<()> {
-        // Validate merkle depth
-        if self.private_inputs.actual_merkle_depth > MAX_MERKLE_DEPTH {
-            bail!(
-                "Merkle tree depth {} exceeds maximum allowed depth {}",
-                self.private_inputs.actual_merkle_depth,
-                MAX_MERKLE_DEPTH
-            );
-        }
-
-        // Validate merkle proof length
-        if self.private_inputs.merkle_siblings.len() != self.private_inputs.path_indices.len() {
-            bail!(
-                "Merkle proof length mismatch: {} siblings vs {} path indices",
-                self.private_inputs.merkle_siblings.len(),
-                self.private_inputs.path_indices.len()
-            );
-        }
+        check_merkle_path("vote", &self.private_inputs)?;
 
         // Set public input witnesses
         pw.set_hash_target(
@@ -228,41 +697,75 @@ impl CircuitFragment for VoteCircuitData {
             targets.expected_merkle_root,
             felts_to_hashout(&self.public_inputs.merkle_root),
         )?;
+        ShapeError::check_len(
+            "vote",
+            "previous_merkle_roots",
+            targets.previous_merkle_roots.len(),
+            self.public_inputs.previous_merkle_roots.len(),
+        )?;
+        for (&target, root) in targets
+            .previous_merkle_roots
+            .iter()
+            .zip(&self.public_inputs.previous_merkle_roots)
+        {
+            pw.set_hash_target(target, felts_to_hashout(root))?;
+        }
         pw.set_bool_target(targets.vote, self.public_inputs.vote)?;
         pw.set_hash_target(
             targets.expected_nullifier,
             felts_to_hashout(&self.public_inputs.nullifier),
         )?;
-
-        // Set private input witnesses
-        pw.set_hash_target(
-            targets.private_key,
-            felts_to_hashout(&self.private_inputs.private_key),
-        )?;
-        pw.set_target(
-            targets.actual_merkle_depth,
-            F::from_canonical_usize(self.private_inputs.actual_merkle_depth),
-        )?;
-
-        for i in 0..MAX_MERKLE_DEPTH {
-            if i < self.private_inputs.actual_merkle_depth {
+        match (&targets.voter_tag, &self.public_inputs.voter_tag) {
+            (Some(targets), Some(voter_tag)) => {
                 pw.set_hash_target(
-                    targets.merkle_siblings[i],
-                    felts_to_hashout(&self.private_inputs.merkle_siblings[i]),
+                    targets.election_namespace,
+                    felts_to_hashout(&voter_tag.election_namespace),
                 )?;
-                pw.set_bool_target(targets.path_indices[i], self.private_inputs.path_indices[i])?;
-            } else {
-                pw.set_hash_target(targets.merkle_siblings[i], felts_to_hashout(&ZERO_DIGEST))?;
-                pw.set_bool_target(targets.path_indices[i], false)?;
+                pw.set_hash_target(targets.tag, felts_to_hashout(&voter_tag.tag))?;
             }
+            (None, None) => {}
+            (Some(_), None) => bail!("the circuit exposes a voter tag, but none was provided"),
+            (None, Some(_)) => bail!("the circuit does not expose a voter tag"),
         }
-        Ok(())
+        match targets.sequence {
+            Some(target) => {
+                pw.set_target(target, F::from_canonical_u32(self.public_inputs.sequence))?;
+            }
+            None if self.public_inputs.sequence != 0 => {
+                bail!("the circuit does not allow revoting, the sequence number must be 0")
+            }
+            None => {}
+        }
+        match (&targets.proposal_metadata, &self.private_inputs.proposal_metadata) {
+            (Some(targets), Some(proposal_metadata)) => {
+                if proposal_metadata.proposal_id() != self.public_inputs.proposal_id {
+                    bail!("the proposal id is not the hash of the proposal metadata");
+                }
+                proposal_metadata.fill_targets(pw, targets.clone())?;
+            }
+            (None, None) => {}
+            (Some(_), None) => {
+                bail!("the circuit binds the proposal metadata, but none was provided")
+            }
+            (None, Some(_)) => bail!("the circuit does not bind the proposal metadata"),
+        }
+
+        // Set private input witnesses
+        fill_merkle_path(
+            pw,
+            &self.private_inputs,
+            targets.private_key,
+            &targets.merkle_siblings,
+            &targets.path_indices,
+            targets.actual_merkle_depth,
+        )
     }
 }
 
 #[cfg(test)]
 mod voting_tests {
     use super::*;
+    use alloc::vec;
     use plonky2::{
         field::types::Field,
         hash::poseidon::PoseidonHash,
@@ -282,7 +785,12 @@ mod voting_tests {
         PoseidonHash::hash_no_pad(&input).elements
     }
 
-    fn create_test_inputs() -> VoteCircuitData {
+    pub(crate) fn create_test_inputs() -> VoteCircuitData {
+        create_test_inputs_for_voter(0)
+    }
+
+    /// A vote of the voter at `index` of a tree of 4 voters, with private keys `[index + 1; 32]`.
+    pub(crate) fn create_test_inputs_for_voter(index: usize) -> VoteCircuitData {
         let private_keys_for_tree: [BytesDigest; 4] = [
             zk_circuits_common::utils::BytesDigest::try_from([1u8; 32]).unwrap(),
             zk_circuits_common::utils::BytesDigest::try_from([2u8; 32]).unwrap(),
@@ -316,9 +824,9 @@ mod voting_tests {
         }
 
         let root = current_level[0];
-        let voter_private_key: PrivateKey = digest_bytes_to_felts(private_keys_for_tree[0]);
-        let merkle_siblings: Vec<Digest> = vec![leaves[1], merkle_tree[1][1]];
-        let path_indices: Vec<bool> = vec![false, false];
+        let voter_private_key: PrivateKey = digest_bytes_to_felts(private_keys_for_tree[index]);
+        let merkle_siblings: Vec<Digest> = vec![leaves[index ^ 1], merkle_tree[1][(index / 2) ^ 1]];
+        let path_indices: Vec<bool> = vec![index % 2 == 1, index / 2 == 1];
         let actual_merkle_depth = 2;
 
         let digest_bytes = BytesDigest::try_from([42u8; 32]).unwrap();
@@ -329,14 +837,18 @@ mod voting_tests {
         let public_inputs = VotePublicInputs {
             proposal_id,
             merkle_root: root,
+            previous_merkle_roots: Vec::new(),
             vote,
             nullifier,
+            voter_tag: None,
+            sequence: 0,
         };
         let private_inputs = VotePrivateInputs {
             private_key: voter_private_key,
             merkle_siblings,
             path_indices,
             actual_merkle_depth,
+            proposal_metadata: None,
         };
 
         VoteCircuitData::new(public_inputs, private_inputs)
@@ -345,19 +857,287 @@ mod voting_tests {
     #[test]
     fn test_vote_circuit_end_to_end() -> anyhow::Result<()> {
         let vote_circuit_data = create_test_inputs();
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let (mut builder, mut pw) = test_support::setup_test_builder_and_witness(false);
         let targets = VoteTargets::new(&mut builder);
         VoteCircuitData::circuit(&targets, &mut builder);
-        let mut pw = PartialWitness::new();
         vote_circuit_data.fill_targets(&mut pw, targets.clone())?;
 
+        test_support::build_prove_and_verify_timed(builder, pw)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_inputs_padded_to_leaf_layout() -> anyhow::Result<()> {
+        let vote_circuit_data = create_test_inputs();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::new(&mut builder);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        let mut pw = PartialWitness::new();
+        vote_circuit_data.fill_targets(&mut pw, targets)?;
+
         let circuit_built_data = builder.build::<C>();
+        assert_eq!(
+            circuit_built_data.common.num_public_inputs,
+            VOTE_PUBLIC_INPUTS_LEN
+        );
+
         let proof = circuit_built_data.prove(pw)?;
-        circuit_built_data.verify(proof)?;
+        let expected = vote_circuit_data.public_inputs.to_field_elements();
+        assert_eq!(proof.public_inputs, expected);
+
+        let parsed = VotePublicInputs::try_from_slice(&proof.public_inputs)?;
+        assert_eq!(parsed.to_field_elements(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_inputs_reject_nonzero_padding() {
+        let mut pis = create_test_inputs().public_inputs.to_field_elements();
+        pis[VOTE_PUBLIC_INPUTS_LEN - 1] = F::ONE;
+        let result = VotePublicInputs::try_from_slice(&pis);
+        assert!(result.unwrap_err().to_string().contains("padding"));
+    }
+
+    fn prove_with_voter_tag(inputs: &VoteCircuitData) -> anyhow::Result<Vec<F>> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::with_voter_tag(&mut builder);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        let mut pw = PartialWitness::new();
+        inputs.fill_targets(&mut pw, targets)?;
+
+        let circuit_built_data = builder.build::<C>();
+        let proof = circuit_built_data.prove(pw)?;
+        circuit_built_data.verify(proof.clone())?;
+        Ok(proof.public_inputs)
+    }
+
+    #[test]
+    fn test_voter_tag_links_votes_across_proposals() -> anyhow::Result<()> {
+        let namespace = [F::from_canonical_u64(7); 4];
+        let mut nullifiers = Vec::new();
+        let mut tags = Vec::new();
+        for proposal in [42u8, 43u8] {
+            let mut inputs = create_test_inputs();
+            let private_key = inputs.private_inputs.private_key;
+            let proposal_id = digest_bytes_to_felts(BytesDigest::try_from([proposal; 32]).unwrap());
+            inputs.public_inputs.proposal_id = proposal_id;
+            inputs.public_inputs.nullifier = compute_nullifier(&private_key, &proposal_id);
+            inputs.public_inputs.voter_tag = Some(VoterTag::new(&private_key, namespace));
+
+            let public_inputs = prove_with_voter_tag(&inputs)?;
+            assert_eq!(public_inputs.len(), VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN);
+            let parsed = VotePublicInputs::try_from_slice(&public_inputs)?;
+            nullifiers.push(parsed.nullifier);
+            tags.push(parsed.voter_tag.expect("proof exposes a voter tag"));
+        }
+
+        // Different nullifiers, same tag.
+        assert_ne!(nullifiers[0], nullifiers[1]);
+        assert_eq!(tags[0], tags[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_voter_tag_fails() {
+        let mut inputs = create_test_inputs();
+        let other_key = [F::from_canonical_u64(12345); 4];
+        inputs.public_inputs.voter_tag = Some(VoterTag::new(&other_key, [F::ONE; 4]));
+        assert!(prove_with_voter_tag(&inputs).is_err());
+
+        // The tagged circuit requires a tag.
+        let inputs = create_test_inputs();
+        assert!(prove_with_voter_tag(&inputs).is_err());
+    }
+
+    #[test]
+    fn test_revoting_exposes_sequence() -> anyhow::Result<()> {
+        let mut inputs = create_test_inputs();
+        inputs.public_inputs.sequence = 3;
+        let (mut builder, mut pw) = test_support::setup_test_builder_and_witness(false);
+        let targets = VoteTargets::with_revoting(&mut builder);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        inputs.fill_targets(&mut pw, targets)?;
+
+        let (proof, _) = test_support::build_prove_and_verify_timed(builder, pw)?;
+        assert_eq!(proof.public_inputs.len(), VOTE_PUBLIC_INPUTS_LEN);
+        assert_eq!(proof.public_inputs[VOTE_SEQUENCE_INDEX], F::from_canonical_u32(3));
+        let parsed = VotePublicInputs::try_from_slice(&proof.public_inputs)?;
+        assert_eq!(parsed.sequence, 3);
+        assert_eq!(parsed.nullifier, inputs.public_inputs.nullifier);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_requires_revoting() {
+        let mut inputs = create_test_inputs();
+        inputs.public_inputs.sequence = 1;
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::new(&mut builder);
+        let result = inputs.fill_targets(&mut PartialWitness::new(), targets);
+        assert!(result.unwrap_err().to_string().contains("does not allow revoting"));
+    }
+
+    fn bind_proposal_metadata(inputs: &mut VoteCircuitData, metadata: ProposalMetadata) {
+        let proposal_id = metadata.proposal_id();
+        inputs.public_inputs.proposal_id = proposal_id;
+        inputs.public_inputs.nullifier =
+            compute_nullifier(&inputs.private_inputs.private_key, &proposal_id);
+        inputs.private_inputs.proposal_metadata = Some(metadata);
+    }
+
+    fn test_proposal_metadata() -> ProposalMetadata {
+        ProposalMetadata {
+            title_hash: [F::from_canonical_u64(11); 4],
+            options_hash: [F::from_canonical_u64(22); 4],
+            voting_starts_at: 1_000,
+            voting_ends_at: 2_000,
+        }
+    }
+
+    fn prove_with_proposal_metadata(inputs: &VoteCircuitData) -> anyhow::Result<Vec<F>> {
+        let (mut builder, mut pw) = test_support::setup_test_builder_and_witness(false);
+        let targets = VoteTargets::with_proposal_metadata(&mut builder);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        inputs.fill_targets(&mut pw, targets)?;
+
+        let (proof, _) = test_support::build_prove_and_verify_timed(builder, pw)?;
+        Ok(proof.public_inputs)
+    }
+
+    #[test]
+    fn test_proposal_metadata_binds_proposal_id() -> anyhow::Result<()> {
+        let mut inputs = create_test_inputs();
+        let metadata = test_proposal_metadata();
+        bind_proposal_metadata(&mut inputs, metadata);
+
+        let public_inputs = prove_with_proposal_metadata(&inputs)?;
+        assert_eq!(public_inputs.len(), VOTE_PUBLIC_INPUTS_LEN);
+        let parsed = VotePublicInputs::try_from_slice(&public_inputs)?;
+        assert_eq!(parsed.proposal_id, metadata.proposal_id());
         Ok(())
     }
 
+    #[test]
+    fn test_wrong_proposal_metadata_fails() {
+        // Metadata that doesn't hash to the proposal id.
+        let mut inputs = create_test_inputs();
+        bind_proposal_metadata(&mut inputs, test_proposal_metadata());
+        let mut other = test_proposal_metadata();
+        other.title_hash = [F::from_canonical_u64(33); 4];
+        inputs.private_inputs.proposal_metadata = Some(other);
+        let result = prove_with_proposal_metadata(&inputs);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("not the hash of the proposal metadata"));
+
+        // A voting window that ends before it starts.
+        let mut inputs = create_test_inputs();
+        let mut reversed = test_proposal_metadata();
+        reversed.voting_ends_at = reversed.voting_starts_at - 1;
+        bind_proposal_metadata(&mut inputs, reversed);
+        assert!(prove_with_proposal_metadata(&inputs).is_err());
+
+        // The binding circuit requires the metadata.
+        let inputs = create_test_inputs();
+        assert!(prove_with_proposal_metadata(&inputs).is_err());
+    }
+
+    fn prove_with_allowed_roots(
+        inputs: &VoteCircuitData,
+        num_allowed_roots: usize,
+    ) -> anyhow::Result<Vec<F>> {
+        let (mut builder, mut pw) = test_support::setup_test_builder_and_witness(false);
+        let targets = VoteTargets::with_allowed_roots(&mut builder, num_allowed_roots);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        inputs.fill_targets(&mut pw, targets)?;
+
+        let (proof, _) = test_support::build_prove_and_verify_timed(builder, pw)?;
+        Ok(proof.public_inputs)
+    }
+
+    #[test]
+    fn test_vote_against_any_allowed_root() -> anyhow::Result<()> {
+        let snapshot_root = create_test_inputs().public_inputs.merkle_root;
+        let other_roots = [[F::from_canonical_u64(98); 4], [F::from_canonical_u64(99); 4]];
+
+        // The voter's snapshot as the current root, and as each of the previous roots.
+        for position in 0..3 {
+            let mut roots = other_roots.to_vec();
+            roots.insert(position, snapshot_root);
+            let mut inputs = create_test_inputs();
+            inputs.public_inputs.merkle_root = roots[0];
+            inputs.public_inputs.previous_merkle_roots = roots[1..].to_vec();
+
+            let public_inputs = prove_with_allowed_roots(&inputs, 3)?;
+            assert_eq!(
+                public_inputs.len(),
+                vote_with_allowed_roots_public_inputs_len(3)
+            );
+            assert_eq!(public_inputs, inputs.public_inputs.to_field_elements());
+            let parsed = VotePublicInputs::try_from_slice_with_allowed_roots(&public_inputs, 3)?;
+            assert_eq!(parsed.merkle_root, roots[0]);
+            assert_eq!(parsed.previous_merkle_roots, roots[1..]);
+            assert_eq!(parsed.nullifier, inputs.public_inputs.nullifier);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_vote_outside_allowed_roots_fails() {
+        let mut inputs = create_test_inputs();
+        inputs.public_inputs.merkle_root = [F::from_canonical_u64(98); 4];
+        inputs.public_inputs.previous_merkle_roots = vec![[F::from_canonical_u64(99); 4]];
+        assert!(prove_with_allowed_roots(&inputs, 2).is_err());
+    }
+
+    #[test]
+    fn test_allowed_roots_must_match_circuit() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::with_allowed_roots(&mut builder, 3);
+        let mut inputs = create_test_inputs();
+        inputs.public_inputs.previous_merkle_roots = vec![ZERO_DIGEST];
+        let err = inputs
+            .fill_targets(&mut PartialWitness::new(), targets)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ShapeError>(),
+            Some(&ShapeError::LengthMismatch {
+                fragment: "vote",
+                field: "previous_merkle_roots",
+                expected: 2,
+                got: 1,
+            })
+        );
+
+        // A single allowed root is the circuit without previous roots.
+        let pis = create_test_inputs().public_inputs.to_field_elements();
+        assert!(VotePublicInputs::try_from_slice_with_allowed_roots(&pis, 1).is_ok());
+        assert!(VotePublicInputs::try_from_slice_with_allowed_roots(&pis, 2).is_err());
+    }
+
+    #[test]
+    fn test_allowed_roots_are_not_parsed_as_voter_tag() {
+        for num_allowed_roots in 2..8 {
+            let len = vote_with_allowed_roots_public_inputs_len(num_allowed_roots);
+            assert_ne!(len, VOTE_PUBLIC_INPUTS_LEN);
+            assert_ne!(len, VOTE_WITH_VOTER_TAG_PUBLIC_INPUTS_LEN);
+        }
+
+        let mut inputs = create_test_inputs();
+        inputs.public_inputs.previous_merkle_roots = vec![[F::from_canonical_u64(98); 4]; 2];
+        let pis = inputs.public_inputs.to_field_elements();
+        assert!(VotePublicInputs::try_from_slice(&pis).is_err());
+        assert!(VotePublicInputs::try_from_slice_with_allowed_roots(&pis, 3).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be combined")]
+    fn test_voter_tag_with_allowed_roots_panics() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        VoteTargets::with_options(&mut builder, true, false, false, 3);
+    }
+
     #[test]
     fn test_invalid_merkle_depth() {
         let mut inputs = create_test_inputs();
@@ -383,20 +1163,59 @@ mod voting_tests {
         assert!(result.unwrap_err().to_string().contains("length mismatch"));
     }
 
+    #[test]
+    fn test_merkle_proof_shorter_than_depth() {
+        let mut inputs = create_test_inputs();
+        inputs.private_inputs.merkle_siblings.pop();
+        inputs.private_inputs.path_indices.pop();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::new(&mut builder);
+        let err = inputs
+            .fill_targets(&mut PartialWitness::new(), targets)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ShapeError>(),
+            Some(&ShapeError::TooShort {
+                fragment: "vote",
+                field: "merkle_siblings",
+                min: 2,
+                got: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_merkle_proof_longer_than_circuit() {
+        let mut inputs = create_test_inputs();
+        inputs.private_inputs.merkle_siblings = vec![ZERO_DIGEST; MAX_MERKLE_DEPTH + 1];
+        inputs.private_inputs.path_indices = vec![false; MAX_MERKLE_DEPTH + 1];
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::new(&mut builder);
+        let err = inputs
+            .fill_targets(&mut PartialWitness::new(), targets)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ShapeError>(),
+            Some(&ShapeError::TooLong {
+                fragment: "vote",
+                field: "merkle_siblings",
+                max: MAX_MERKLE_DEPTH,
+                got: MAX_MERKLE_DEPTH + 1,
+            })
+        );
+    }
+
     #[test]
     fn test_invalid_merkle_proof() -> anyhow::Result<()> {
         let mut inputs = create_test_inputs();
         // Create an invalid proof by using a different actual_merkle_depth
         inputs.private_inputs.actual_merkle_depth = 1; // Should be 2 for our test tree
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let (mut builder, mut pw) = test_support::setup_test_builder_and_witness(false);
         let targets = VoteTargets::new(&mut builder);
         VoteCircuitData::circuit(&targets, &mut builder);
-        let mut pw = PartialWitness::new();
         inputs.fill_targets(&mut pw, targets.clone())?;
 
-        let circuit_built_data = builder.build::<C>();
-        let proof_result = circuit_built_data.prove(pw);
+        let proof_result = test_support::build_and_prove_test(builder, pw);
         assert!(
             proof_result.is_err(),
             "Proof generation should have failed but it succeeded"
@@ -433,6 +1252,15 @@ mod voting_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_try_new_rejects_narrow_config() {
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.num_wires = 64;
+        config.num_routed_wires = 64;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        assert!(VoteTargets::try_new(&mut builder).is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_simple_fail() {
@@ -0,0 +1,135 @@
+//! Proposal ids bound to the content of the proposal.
+//!
+//! The vote circuit accepts any digest as the proposal id, so voters have to trust whoever
+//! published the id that it refers to the proposal they are shown. Circuits built with
+//! [`crate::VoteTargets::with_proposal_metadata`] additionally prove that the proposal id is the
+//! hash of [`ProposalMetadata`], given as private inputs. As the proposal id is public, every vote
+//! then commits to the exact title, options and voting window, which can't be changed after voting
+//! started without invalidating the votes.
+use alloc::vec::Vec;
+use plonky2::{
+    field::types::Field,
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::{assert_less_than_or_equal, range_check_u32};
+use zk_circuits_common::utils::{felts_to_hashout, Digest, DIGEST_NUM_FIELD_ELEMENTS};
+
+/// The number of field elements hashed into the proposal id.
+pub const PROPOSAL_METADATA_NUM_FIELD_ELEMENTS: usize = 2 * DIGEST_NUM_FIELD_ELEMENTS + 2;
+
+/// The content of a proposal, whose hash is the proposal id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProposalMetadata {
+    /// The hash of the proposal title and description
+    pub title_hash: Digest,
+    /// The hash of the options voted on
+    pub options_hash: Digest,
+    /// The first block of the voting window
+    pub voting_starts_at: u32,
+    /// The last block of the voting window, not before `voting_starts_at`
+    pub voting_ends_at: u32,
+}
+
+impl ProposalMetadata {
+    /// Encodes the metadata in the order it is hashed:
+    /// `title_hash || options_hash || voting_starts_at || voting_ends_at`.
+    pub fn to_field_elements(&self) -> Vec<F> {
+        let mut felts = Vec::with_capacity(PROPOSAL_METADATA_NUM_FIELD_ELEMENTS);
+        felts.extend_from_slice(&self.title_hash);
+        felts.extend_from_slice(&self.options_hash);
+        felts.push(F::from_canonical_u32(self.voting_starts_at));
+        felts.push(F::from_canonical_u32(self.voting_ends_at));
+        felts
+    }
+
+    /// The proposal id of the proposal, `H(title_hash || options_hash || voting_starts_at ||
+    /// voting_ends_at)`.
+    pub fn proposal_id(&self) -> Digest {
+        PoseidonHash::hash_no_pad(&self.to_field_elements()).elements
+    }
+}
+
+/// The targets of [`ProposalMetadata`].
+#[derive(Clone, Debug)]
+pub struct ProposalMetadataTargets {
+    pub title_hash: HashOutTarget,
+    pub options_hash: HashOutTarget,
+    pub voting_starts_at: Target,
+    pub voting_ends_at: Target,
+    /// The proposal id public input of the vote circuit.
+    pub proposal_id: HashOutTarget,
+}
+
+impl ProposalMetadataTargets {
+    /// Adds the metadata as private inputs, to be hashed into `proposal_id`.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, proposal_id: HashOutTarget) -> Self {
+        Self {
+            title_hash: builder.add_virtual_hash(),
+            options_hash: builder.add_virtual_hash(),
+            voting_starts_at: builder.add_virtual_target(),
+            voting_ends_at: builder.add_virtual_target(),
+            proposal_id,
+        }
+    }
+}
+
+impl CircuitFragment for ProposalMetadata {
+    type Targets = ProposalMetadataTargets;
+
+    /// Asserts that the proposal id is the hash of the metadata, and that the voting window does
+    /// not end before it starts.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        let window = [targets.voting_starts_at, targets.voting_ends_at];
+        range_check_u32(builder, &window);
+        assert_less_than_or_equal(builder, window[0], window[1], 32);
+
+        let mut preimage = Vec::with_capacity(PROPOSAL_METADATA_NUM_FIELD_ELEMENTS);
+        preimage.extend_from_slice(&targets.title_hash.elements);
+        preimage.extend_from_slice(&targets.options_hash.elements);
+        preimage.extend_from_slice(&window);
+        let computed_proposal_id = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        builder.connect_hashes(computed_proposal_id, targets.proposal_id);
+    }
+
+    fn describe(_targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("proposal_metadata")
+            .range_check("voting_starts_at, voting_ends_at", 32)
+            .assert("voting_starts_at <= voting_ends_at")
+            .poseidon(
+                "computed_proposal_id",
+                &[
+                    "title_hash",
+                    "options_hash",
+                    "voting_starts_at",
+                    "voting_ends_at",
+                ],
+            )
+            .equal("computed_proposal_id", "proposal_id")
+    }
+
+    /// Fills the metadata. The proposal id is filled by the vote circuit.
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        pw.set_hash_target(targets.title_hash, felts_to_hashout(&self.title_hash))?;
+        pw.set_hash_target(targets.options_hash, felts_to_hashout(&self.options_hash))?;
+        pw.set_target(
+            targets.voting_starts_at,
+            F::from_canonical_u32(self.voting_starts_at),
+        )?;
+        pw.set_target(
+            targets.voting_ends_at,
+            F::from_canonical_u32(self.voting_ends_at),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,445 @@
+//! Certified vote tallies.
+//!
+//! A [`TallyCertificate`] is the result of a proposal, built from verified vote proofs or from a
+//! proof aggregated from them. It holds the yes and no counts together with the nullifiers of the
+//! counted votes, so governance frontends can display and archive a single artifact, and anyone
+//! can check that a vote was counted or recount the tally against the on-chain nullifiers.
+//!
+//! # Format
+//!
+//! [`TallyCertificate::to_bytes`] writes, with all integers little-endian and field elements as
+//! their canonical `u64`:
+//!
+//! `version (1 byte) || proposal_id || merkle_root || yes (u64) || no (u64) ||
+//! nullifiers_commitment || number of nullifiers (u32) || nullifiers`
+use alloc::vec::Vec;
+use anyhow::{bail, Context};
+use plonky2::{
+    field::types::{Field, PrimeField64},
+    hash::poseidon::PoseidonHash,
+    plonk::{circuit_data::VerifierCircuitData, config::Hasher, proof::ProofWithPublicInputs},
+};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::{Digest, DIGEST_NUM_FIELD_ELEMENTS};
+
+use crate::VotePublicInputs;
+
+/// The version written by [`TallyCertificate::to_bytes`].
+pub const TALLY_CERTIFICATE_VERSION: u8 = 1;
+
+/// The tally of the votes on a proposal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TallyCertificate {
+    pub proposal_id: Digest,
+    /// The merkle root of the eligible voters.
+    pub merkle_root: Digest,
+    pub yes: u64,
+    pub no: u64,
+    /// The nullifiers of the counted votes, sorted by their canonical field elements.
+    pub nullifiers: Vec<Digest>,
+    /// The Poseidon hash of the sorted nullifiers, see [`Self::commit_nullifiers`].
+    pub nullifiers_commitment: Digest,
+}
+
+impl TallyCertificate {
+    /// Tallies the public inputs of verified vote proofs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no votes, the votes are for different proposals or voter
+    /// sets, or two votes have the same nullifier.
+    pub fn from_votes(votes: &[VotePublicInputs]) -> anyhow::Result<Self> {
+        let Some(first) = votes.first() else {
+            bail!("cannot certify a tally without votes");
+        };
+
+        let mut yes = 0u64;
+        let mut no = 0u64;
+        let mut nullifiers = Vec::with_capacity(votes.len());
+        for (i, vote) in votes.iter().enumerate() {
+            if vote.proposal_id != first.proposal_id {
+                bail!("vote {} is for a different proposal than vote 0", i);
+            }
+            if vote.merkle_root != first.merkle_root {
+                bail!("vote {} has a different merkle root than vote 0", i);
+            }
+            if vote.previous_merkle_roots != first.previous_merkle_roots {
+                bail!("vote {} allows different previous merkle roots than vote 0", i);
+            }
+            if vote.vote {
+                yes += 1;
+            } else {
+                no += 1;
+            }
+            nullifiers.push(vote.nullifier);
+        }
+
+        nullifiers.sort_by_key(canonical);
+        if let Some(pair) = nullifiers.windows(2).find(|pair| pair[0] == pair[1]) {
+            bail!("nullifier {:?} was counted twice", pair[0]);
+        }
+
+        Ok(Self {
+            proposal_id: first.proposal_id,
+            merkle_root: first.merkle_root,
+            yes,
+            no,
+            nullifiers_commitment: Self::commit_nullifiers(&nullifiers),
+            nullifiers,
+        })
+    }
+
+    /// Tallies the public inputs of verified vote proofs of a circuit built with
+    /// [`crate::VoteTargets::with_revoting`], where a voter may vote on the proposal several
+    /// times. Of the votes with the same nullifier, only the last one is counted.
+    ///
+    /// The sequence number of a vote is the number of votes the chain included before it with
+    /// the same nullifier, and the contract rejects votes with any other sequence number. So the
+    /// votes of a nullifier have the sequence numbers `0, 1, ..., n` in inclusion order, and a
+    /// vote with a high sequence number can't pre-empt the votes it skips.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sequence numbers of the votes of a nullifier don't count up from
+    /// 0, or the latest votes can't be tallied, see [`Self::from_votes`].
+    pub fn from_revotes(votes: &[VotePublicInputs]) -> anyhow::Result<Self> {
+        let mut sorted: Vec<&VotePublicInputs> = votes.iter().collect();
+        sorted.sort_by_key(|vote| (canonical(&vote.nullifier), vote.sequence));
+
+        let mut latest: Vec<VotePublicInputs> = Vec::with_capacity(sorted.len());
+        for vote in sorted {
+            let expected = match latest.last() {
+                Some(last) if last.nullifier == vote.nullifier => u64::from(last.sequence) + 1,
+                _ => 0,
+            };
+            if u64::from(vote.sequence) != expected {
+                bail!(
+                    "nullifier {:?} voted with sequence number {}, expected {}",
+                    vote.nullifier,
+                    vote.sequence,
+                    expected
+                );
+            }
+            match latest.last_mut() {
+                Some(last) if last.nullifier == vote.nullifier => *last = vote.clone(),
+                _ => latest.push(vote.clone()),
+            }
+        }
+        Self::from_votes(&latest)
+    }
+
+    /// Verifies vote proofs of the circuit with `verifier` data and tallies them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a proof doesn't verify, or the votes can't be tallied, see
+    /// [`Self::from_votes`].
+    pub fn from_proofs(
+        proofs: &[ProofWithPublicInputs<F, C, D>],
+        verifier: &VerifierCircuitData<F, C, D>,
+    ) -> anyhow::Result<Self> {
+        let votes = proofs
+            .iter()
+            .enumerate()
+            .map(|(i, proof)| {
+                verifier
+                    .verify(proof.clone())
+                    .with_context(|| alloc::format!("vote proof {} failed verification", i))?;
+                VotePublicInputs::try_from_slice(&proof.public_inputs)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Self::from_votes(&votes)
+    }
+
+    /// Verifies a proof aggregated from vote proofs with `verifier` data and tallies its first
+    /// `num_votes` leaves. Leaves after them are the padding added by the aggregator and ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof doesn't verify, holds fewer than `num_votes` leaves of
+    /// `leaf_pi_len` public inputs, or the votes can't be tallied, see [`Self::from_votes`].
+    pub fn from_aggregated(
+        proof: &ProofWithPublicInputs<F, C, D>,
+        verifier: &VerifierCircuitData<F, C, D>,
+        leaf_pi_len: usize,
+        num_votes: usize,
+    ) -> anyhow::Result<Self> {
+        verifier
+            .verify(proof.clone())
+            .context("aggregated vote proof failed verification")?;
+
+        let Some(pis) = proof.public_inputs.get(..num_votes * leaf_pi_len) else {
+            bail!(
+                "aggregated proof has {} public inputs, too few for {} votes",
+                proof.public_inputs.len(),
+                num_votes
+            );
+        };
+        Self::from_votes(&VotePublicInputs::try_from_aggregated(pis, leaf_pi_len)?)
+    }
+
+    /// The commitment to a sorted list of nullifiers.
+    pub fn commit_nullifiers(nullifiers: &[Digest]) -> Digest {
+        let felts: Vec<F> = nullifiers.iter().flatten().copied().collect();
+        PoseidonHash::hash_no_pad(&felts).elements
+    }
+
+    /// Returns `true` if the vote with `nullifier` was counted.
+    pub fn contains(&self, nullifier: &Digest) -> bool {
+        self.nullifiers
+            .binary_search_by_key(&canonical(nullifier), canonical)
+            .is_ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push(TALLY_CERTIFICATE_VERSION);
+        write_digest(&mut bytes, &self.proposal_id);
+        write_digest(&mut bytes, &self.merkle_root);
+        bytes.extend(self.yes.to_le_bytes());
+        bytes.extend(self.no.to_le_bytes());
+        write_digest(&mut bytes, &self.nullifiers_commitment);
+        bytes.extend((self.nullifiers.len() as u32).to_le_bytes());
+        for nullifier in &self.nullifiers {
+            write_digest(&mut bytes, nullifier);
+        }
+        bytes
+    }
+
+    /// Decodes a certificate written by [`Self::to_bytes`] and checks that it is consistent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are malformed, the counts don't match the number of
+    /// nullifiers, or the nullifiers are not sorted, unique and committed to.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader(bytes);
+        let version = reader.take::<1>()?[0];
+        if version != TALLY_CERTIFICATE_VERSION {
+            bail!(
+                "unsupported tally certificate version: {}, expected: {}",
+                version,
+                TALLY_CERTIFICATE_VERSION
+            );
+        }
+
+        let proposal_id = reader.digest()?;
+        let merkle_root = reader.digest()?;
+        let yes = u64::from_le_bytes(reader.take()?);
+        let no = u64::from_le_bytes(reader.take()?);
+        let nullifiers_commitment = reader.digest()?;
+        let num_nullifiers = u32::from_le_bytes(reader.take()?) as usize;
+        let nullifiers = (0..num_nullifiers)
+            .map(|_| reader.digest())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if !reader.0.is_empty() {
+            bail!("{} trailing bytes after tally certificate", reader.0.len());
+        }
+
+        if yes.checked_add(no) != Some(num_nullifiers as u64) {
+            bail!(
+                "tally of {} yes and {} no votes doesn't match {} nullifiers",
+                yes,
+                no,
+                num_nullifiers
+            );
+        }
+        if nullifiers
+            .windows(2)
+            .any(|pair| canonical(&pair[0]) >= canonical(&pair[1]))
+        {
+            bail!("tally certificate nullifiers are not sorted and unique");
+        }
+        if Self::commit_nullifiers(&nullifiers) != nullifiers_commitment {
+            bail!("tally certificate nullifiers don't match their commitment");
+        }
+
+        Ok(Self {
+            proposal_id,
+            merkle_root,
+            yes,
+            no,
+            nullifiers,
+            nullifiers_commitment,
+        })
+    }
+}
+
+pub(crate) fn canonical(digest: &Digest) -> [u64; DIGEST_NUM_FIELD_ELEMENTS] {
+    digest.map(|felt| felt.to_canonical_u64())
+}
+
+pub(crate) fn write_digest(bytes: &mut Vec<u8>, digest: &Digest) {
+    for value in canonical(digest) {
+        bytes.extend(value.to_le_bytes());
+    }
+}
+
+/// Reads the encodings of [`TallyCertificate::to_bytes`] and
+/// [`crate::aggregate::AggregatedVoteEnvelope::to_bytes`].
+pub(crate) struct Reader<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let Some((head, rest)) = self.0.split_first_chunk::<N>() else {
+            bail!("input is truncated");
+        };
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("input is truncated");
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    pub(crate) fn digest(&mut self) -> anyhow::Result<Digest> {
+        let mut digest = [F::ZERO; DIGEST_NUM_FIELD_ELEMENTS];
+        for felt in &mut digest {
+            let value = u64::from_le_bytes(self.take()?);
+            if value > F::NEG_ONE.to_canonical_u64() {
+                bail!("input contains a non-canonical field element");
+            }
+            *felt = F::from_canonical_u64(value);
+        }
+        Ok(digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::{
+        iop::witness::PartialWitness,
+        plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
+    };
+    use zk_circuits_common::circuit::CircuitFragment;
+
+    use crate::{voting_tests::create_test_inputs, VoteCircuitData, VoteTargets};
+
+    fn vote(vote: bool, nullifier: u64) -> VotePublicInputs {
+        VotePublicInputs {
+            proposal_id: [F::ONE; 4],
+            merkle_root: [F::TWO; 4],
+            previous_merkle_roots: Vec::new(),
+            vote,
+            nullifier: [F::from_canonical_u64(nullifier); 4],
+            voter_tag: None,
+            sequence: 0,
+        }
+    }
+
+    fn revote(vote_value: bool, nullifier: u64, sequence: u32) -> VotePublicInputs {
+        VotePublicInputs {
+            sequence,
+            ..vote(vote_value, nullifier)
+        }
+    }
+
+    #[test]
+    fn test_tally_counts_votes() {
+        let tally =
+            TallyCertificate::from_votes(&[vote(true, 3), vote(false, 1), vote(true, 2)]).unwrap();
+        assert_eq!((tally.yes, tally.no), (2, 1));
+        assert_eq!(tally.nullifiers, [[F::ONE; 4], [F::TWO; 4], [F::from_canonical_u64(3); 4]]);
+        assert!(tally.contains(&[F::TWO; 4]));
+        assert!(!tally.contains(&[F::ZERO; 4]));
+
+        let bytes = tally.to_bytes();
+        assert_eq!(TallyCertificate::from_bytes(&bytes).unwrap(), tally);
+    }
+
+    #[test]
+    fn test_tally_rejects_invalid_votes() {
+        assert!(TallyCertificate::from_votes(&[]).is_err());
+        assert!(TallyCertificate::from_votes(&[vote(true, 1), vote(false, 1)]).is_err());
+
+        let mut other_proposal = vote(true, 2);
+        other_proposal.proposal_id = [F::ZERO; 4];
+        assert!(TallyCertificate::from_votes(&[vote(true, 1), other_proposal]).is_err());
+    }
+
+    #[test]
+    fn test_tally_counts_latest_revote() {
+        let votes = [
+            revote(true, 1, 1),
+            revote(true, 2, 0),
+            revote(false, 1, 2),
+            revote(true, 1, 0),
+        ];
+        let tally = TallyCertificate::from_revotes(&votes).unwrap();
+        // Voter 1 voted no last, voter 2 voted yes once.
+        assert_eq!((tally.yes, tally.no), (1, 1));
+        assert_eq!(tally.nullifiers, [[F::ONE; 4], [F::TWO; 4]]);
+
+        // Without revoting, the same nullifier can't be counted twice.
+        assert!(TallyCertificate::from_votes(&votes).is_err());
+
+        // The latest vote must be unambiguous.
+        let result = TallyCertificate::from_revotes(&[revote(true, 1, 0), revote(false, 1, 0)]);
+        assert!(result.unwrap_err().to_string().contains("sequence number 0"));
+    }
+
+    #[test]
+    fn test_tally_rejects_skipped_sequence_numbers() {
+        // A coerced vote with the highest sequence number can't be included before the votes it
+        // skips, so the voter's later vote still overrides it.
+        let coerced = revote(true, 1, u32::MAX);
+        let result = TallyCertificate::from_revotes(&[revote(false, 1, 0), coerced.clone()]);
+        assert!(result.unwrap_err().to_string().contains("expected 1"));
+        assert!(TallyCertificate::from_revotes(&[coerced]).is_err());
+
+        // The first vote of a voter has sequence number 0.
+        assert!(TallyCertificate::from_revotes(&[revote(true, 2, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_tampered_certificate_is_rejected() {
+        let tally = TallyCertificate::from_votes(&[vote(true, 1), vote(false, 2)]).unwrap();
+        let bytes = tally.to_bytes();
+
+        // Flip the yes count.
+        let yes_offset = 1 + 2 * 8 * DIGEST_NUM_FIELD_ELEMENTS;
+        let mut tampered = bytes.clone();
+        tampered[yes_offset] += 1;
+        assert!(TallyCertificate::from_bytes(&tampered).is_err());
+
+        // Replace the last nullifier.
+        let mut tampered = bytes.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(TallyCertificate::from_bytes(&tampered).is_err());
+
+        assert!(TallyCertificate::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_tally_from_proofs() -> anyhow::Result<()> {
+        let inputs = create_test_inputs();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = VoteTargets::new(&mut builder);
+        VoteCircuitData::circuit(&targets, &mut builder);
+        let mut pw = PartialWitness::new();
+        inputs.fill_targets(&mut pw, targets)?;
+
+        let circuit_data = builder.build::<C>();
+        let proof = circuit_data.prove(pw)?;
+        let verifier = circuit_data.verifier_data();
+
+        let tally = TallyCertificate::from_proofs(&[proof.clone()], &verifier)?;
+        assert_eq!(tally.proposal_id, inputs.public_inputs.proposal_id);
+        assert_eq!((tally.yes, tally.no), (1, 0));
+        assert!(tally.contains(&inputs.public_inputs.nullifier));
+
+        // The same vote can't be counted twice.
+        assert!(TallyCertificate::from_proofs(&[proof.clone(), proof.clone()], &verifier).is_err());
+
+        let mut tampered = proof;
+        tampered.public_inputs[2 * DIGEST_NUM_FIELD_ELEMENTS] = F::ZERO;
+        assert!(TallyCertificate::from_proofs(&[tampered], &verifier).is_err());
+        Ok(())
+    }
+}
@@ -1,21 +1,22 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use plonky2::plonk::circuit_data::CommonCircuitData;
-use qp_wormhole_aggregator::aggregator::WormholeProofAggregator;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use qp_wormhole_aggregator::aggregator::{DuplicatePolicy, WormholeProofAggregator};
 use qp_wormhole_aggregator::circuits::tree::TreeAggregationConfig;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
 use wormhole_verifier::ProofWithPublicInputs;
 use zk_circuits_common::circuit::{C, D, F};
 
-const DUMMY_PROOF_BYTES: &[u8] = include_bytes!("../data/dummy_proof_zk.bin");
-
-fn deserialize_proofs(
-    common_data: &CommonCircuitData<F, D>,
-    len: usize,
-) -> Vec<ProofWithPublicInputs<F, C, D>> {
-    (0..len)
-        .map(|_| {
-            ProofWithPublicInputs::from_bytes(DUMMY_PROOF_BYTES.to_vec(), common_data).unwrap()
-        })
-        .collect()
+/// `len` copies of a proof of the test inputs for the leaf circuit of the default aggregator.
+fn test_proofs(len: usize) -> Vec<ProofWithPublicInputs<F, C, D>> {
+    let prover = WormholeProver::new(CircuitConfig::standard_recursion_zk_config());
+    let proof = prover
+        .commit(&CircuitInputs::test_inputs())
+        .unwrap()
+        .prove()
+        .unwrap();
+    vec![proof; len]
 }
 
 // A macro for creating an aggregation benchmark with a specified number of proofs to
@@ -26,13 +27,7 @@ macro_rules! aggregate_proofs_benchmark {
             let config = TreeAggregationConfig::new($tree_branching_factor, $tree_depth);
 
             // Setup proofs.
-            let proofs = {
-                let temp_aggregator = WormholeProofAggregator::default().with_config(config);
-                deserialize_proofs(
-                    &temp_aggregator.leaf_circuit_data.common,
-                    config.num_leaf_proofs,
-                )
-            };
+            let proofs = test_proofs(config.num_leaf_proofs);
 
             c.bench_function(
                 &format!(
@@ -42,8 +37,9 @@ macro_rules! aggregate_proofs_benchmark {
                 |b| {
                     b.iter_batched(
                         || {
-                            let mut aggregator =
-                                WormholeProofAggregator::default().with_config(config);
+                            let mut aggregator = WormholeProofAggregator::default()
+                                .with_config(config)
+                                .with_duplicate_policy(DuplicatePolicy::Allow);
                             for proof in proofs.clone() {
                                 aggregator.push_proof(proof).unwrap();
                             }
@@ -66,13 +62,7 @@ macro_rules! verify_aggregate_proof_benchmark {
             let config = TreeAggregationConfig::new($tree_branching_factor, $tree_depth);
 
             // Setup proofs.
-            let proofs = {
-                let temp_aggregator = WormholeProofAggregator::default().with_config(config);
-                deserialize_proofs(
-                    &temp_aggregator.leaf_circuit_data.common,
-                    config.num_leaf_proofs,
-                )
-            };
+            let proofs = test_proofs(config.num_leaf_proofs);
 
             c.bench_function(
                 &format!(
@@ -82,8 +72,9 @@ macro_rules! verify_aggregate_proof_benchmark {
                 |b| {
                     b.iter_batched(
                         || {
-                            let mut aggregator =
-                                WormholeProofAggregator::default().with_config(config);
+                            let mut aggregator = WormholeProofAggregator::default()
+                                .with_config(config)
+                                .with_duplicate_policy(DuplicatePolicy::Allow);
                             for proof in proofs.clone() {
                                 aggregator.push_proof(proof).unwrap();
                             }
@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use qp_wormhole_aggregator::aggregator::{DuplicatePolicy, WormholeProofAggregator};
+use qp_wormhole_aggregator::circuits::tree::TreeAggregationConfig;
+use test_helpers::storage_proof::TestInputs;
+use test_helpers::throughput::{circuit_config, thread_pool, ThroughputSummary, THREAD_COUNTS};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+
+/// A full tree, so every leaf is a proof and none is padding.
+const TREE_BRANCHING_FACTOR: usize = 2;
+const TREE_DEPTH: u32 = 3;
+
+/// Aggregates a full tree of leaf proofs with and without zero knowledge on every thread count.
+/// Only the aggregation is timed, filling the proofs buffer is not.
+fn aggregate_throughput_benchmark(c: &mut Criterion) {
+    let mut summary = ThroughputSummary::default();
+    let mut group = c.benchmark_group("aggregator_throughput");
+    let tree_config = TreeAggregationConfig::new(TREE_BRANCHING_FACTOR, TREE_DEPTH);
+    for zk in [false, true] {
+        let leaf_circuit_config = circuit_config(zk);
+        let leaf_circuit_data =
+            WormholeProofAggregator::from_circuit_config(leaf_circuit_config.clone())
+                .leaf_circuit_data;
+        let proof = WormholeProver::new(leaf_circuit_config)
+            .commit(&CircuitInputs::test_inputs())
+            .unwrap()
+            .prove()
+            .unwrap();
+        let proofs = vec![proof; tree_config.num_leaf_proofs];
+
+        for threads in THREAD_COUNTS {
+            let pool = thread_pool(threads);
+            let id = BenchmarkId::new(if zk { "zk" } else { "no_zk" }, threads);
+            group.bench_function(id, |b| {
+                b.iter_custom(|iters| {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iters {
+                        let mut aggregator =
+                            WormholeProofAggregator::new(leaf_circuit_data.clone())
+                                .with_config(tree_config)
+                                .with_duplicate_policy(DuplicatePolicy::Allow);
+                        for proof in proofs.clone() {
+                            aggregator.push_proof(proof).unwrap();
+                        }
+                        let start = Instant::now();
+                        pool.install(|| aggregator.aggregate().unwrap());
+                        elapsed += start.elapsed();
+                    }
+                    let num_proofs = tree_config.num_leaf_proofs;
+                    summary.record("aggregator_aggregate", zk, threads, num_proofs, iters, elapsed);
+                    elapsed
+                });
+            });
+        }
+    }
+    group.finish();
+    summary.emit().unwrap();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .sample_size(10);
+    targets = aggregate_throughput_benchmark
+);
+criterion_main!(benches);
@@ -1,19 +1,81 @@
-use anyhow::bail;
+use anyhow::{bail, Context};
 use plonky2::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
-use wormhole_circuit::inputs::PublicCircuitInputs;
+use wormhole_circuit::inputs::{PublicCircuitInputs, NULLIFIER_END_INDEX, NULLIFIER_START_INDEX};
 use wormhole_verifier::{ProofWithPublicInputs, WormholeVerifier};
 use zk_circuits_common::circuit::{C, D, F};
 
+#[cfg(feature = "distributed")]
+use crate::distributed::{aggregate_to_tree_distributed, Worker};
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, FailureStage};
 use crate::{
-    circuits::tree::{aggregate_to_tree, AggregatedProof, TreeAggregationConfig},
-    util::pad_with_dummy_proofs,
+    circuits::{
+        shape::{commit_tree_shape, TreeShapeCommitments},
+        tree::{aggregate_leaves_to_tree, AggregatedProof, TreeAggregationConfig},
+    },
+    util::pad_leaves,
 };
 
+/// A leaf proof that failed verification and was replaced by padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplacedProof {
+    /// Position of the proof in the proofs buffer, which is also its leaf index in the tree.
+    pub index: usize,
+    /// Why the proof was rejected.
+    pub reason: String,
+}
+
+/// Report returned by [`WormholeProofAggregator::aggregate_skipping_invalid`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AggregationReport {
+    pub replaced: Vec<ReplacedProof>,
+}
+
+impl AggregationReport {
+    /// Returns `true` if every buffered proof made it into the aggregated proof.
+    pub fn is_clean(&self) -> bool {
+        self.replaced.is_empty()
+    }
+}
+
+/// What [`WormholeProofAggregator::push_proof`] does with a proof whose nullifier is already in
+/// the proofs buffer.
+///
+/// Proofs are identified by their nullifier rather than their bytes, so a copy of a proof and a
+/// second proof spending the same deposit are both duplicates. Either would put the nullifier
+/// into the aggregated public inputs twice.
+///
+/// Only the current buffer is checked, so a nullifier is unique within one aggregated proof but
+/// not across them. Whoever consumes the aggregated proofs, e.g. the chain, still has to track
+/// spent nullifiers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Buffer duplicates like any other proof, e.g. to benchmark with copies of one proof.
+    Allow,
+    /// Return an error for duplicates.
+    #[default]
+    Reject,
+    /// Drop duplicates without an error.
+    Skip,
+}
+
 /// A circuit that aggregates proofs from the Wormhole circuit.
+///
+/// Unless a tree shape is set with [`Self::with_config`], the tree is picked for the number of
+/// buffered proofs with [`TreeAggregationConfig::auto_for`] on every aggregation, and `config`
+/// only bounds how many proofs can be buffered.
 pub struct WormholeProofAggregator {
     pub leaf_circuit_data: VerifierCircuitData<F, C, D>,
     pub config: TreeAggregationConfig,
     pub proofs_buffer: Option<Vec<ProofWithPublicInputs<F, C, D>>>,
+    pub duplicate_policy: DuplicatePolicy,
+    /// Whether aggregated proofs end with the [`TreeShapeCommitments`], see
+    /// [`Self::with_tree_shape_commitment`].
+    pub commit_tree_shape: bool,
+    explicit_config: bool,
+    duplicates_seen: usize,
+    #[cfg(feature = "distributed")]
+    workers: Vec<Box<dyn Worker>>,
 }
 
 impl Default for WormholeProofAggregator {
@@ -33,6 +95,12 @@ impl WormholeProofAggregator {
             leaf_circuit_data: verifier_circuit_data,
             config: aggregation_config,
             proofs_buffer,
+            duplicate_policy: DuplicatePolicy::default(),
+            commit_tree_shape: false,
+            explicit_config: false,
+            duplicates_seen: 0,
+            #[cfg(feature = "distributed")]
+            workers: Vec::new(),
         }
     }
 
@@ -45,29 +113,180 @@ impl WormholeProofAggregator {
 
     pub fn with_config(mut self, config: TreeAggregationConfig) -> Self {
         self.config = config;
+        self.explicit_config = true;
         self
     }
 
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Wraps every aggregated proof with [`commit_tree_shape`], so its public inputs end with
+    /// commitments to the tree shape and leaf order. This adds one more recursive proof to each
+    /// aggregation.
+    pub fn with_tree_shape_commitment(mut self) -> Self {
+        self.commit_tree_shape = true;
+        self
+    }
+
+    /// Aggregates the leaf level of every tree on `workers`, see [`crate::distributed`].
+    #[cfg(feature = "distributed")]
+    pub fn with_workers(mut self, workers: Vec<Box<dyn Worker>>) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// The number of duplicate proofs pushed since the aggregator was created, whether they were
+    /// buffered, rejected or skipped.
+    pub fn duplicates_seen(&self) -> usize {
+        self.duplicates_seen
+    }
+
+    /// The tree shape used to aggregate `num_proofs` buffered proofs.
+    pub fn tree_config_for(&self, num_proofs: usize) -> TreeAggregationConfig {
+        if self.explicit_config {
+            self.config
+        } else {
+            TreeAggregationConfig::auto_for(num_proofs)
+        }
+    }
+
+    /// Adds a leaf proof to the proofs buffer. Proofs with a nullifier that is already buffered
+    /// are handled according to [`Self::duplicate_policy`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is full, or the proof is a duplicate and the policy is
+    /// [`DuplicatePolicy::Reject`].
     pub fn push_proof(&mut self, proof: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
-        if let Some(proofs_buffer) = self.proofs_buffer.as_mut() {
-            if proofs_buffer.len() >= self.config.num_leaf_proofs {
-                bail!("tried to add proof when proof buffer is full")
+        let proofs_buffer = self.proofs_buffer.get_or_insert_with(Vec::new);
+        if proofs_buffer.len() >= self.config.num_leaf_proofs {
+            #[cfg(feature = "metrics")]
+            metrics::record_failure(FailureStage::Push);
+            bail!("tried to add proof when proof buffer is full")
+        }
+
+        if let Some(nullifier) = nullifier_of(&proof) {
+            let is_duplicate = proofs_buffer
+                .iter()
+                .any(|buffered| nullifier_of(buffered) == Some(nullifier));
+            if is_duplicate {
+                self.duplicates_seen += 1;
+                match self.duplicate_policy {
+                    DuplicatePolicy::Allow => {}
+                    DuplicatePolicy::Reject => {
+                        #[cfg(feature = "metrics")]
+                        metrics::record_failure(FailureStage::Push);
+                        bail!("proof with nullifier {:?} is already buffered", nullifier)
+                    }
+                    DuplicatePolicy::Skip => {
+                        tracing::debug!(?nullifier, "skipping duplicate proof");
+                        return Ok(());
+                    }
+                }
             }
-            proofs_buffer.push(proof);
-        } else {
-            self.proofs_buffer = Some(vec![proof]);
+        }
+        proofs_buffer.push(proof);
+        #[cfg(feature = "metrics")]
+        {
+            metrics::record_proof_pushed();
+            metrics::set_queue_depth(proofs_buffer.len());
         }
 
         Ok(())
     }
 
+    /// Decodes the public inputs of every leaf of an aggregated proof, in leaf order. The padding
+    /// leaves of the tree decode to [`PublicCircuitInputs::padding`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the public inputs don't hold a whole number of leaves, or a leaf
+    /// doesn't decode.
     pub fn extract_leaf_public_inputs(
         &self,
         aggr: &wormhole_verifier::ProofWithPublicInputs<F, C, D>,
     ) -> anyhow::Result<Vec<PublicCircuitInputs>> {
         let leaf_pi_len = self.leaf_circuit_data.common.num_public_inputs;
-        let num_leaves = self.config.num_leaf_proofs;
-        PublicCircuitInputs::try_from_aggregated(aggr, leaf_pi_len, num_leaves)
+        let leaf_public_inputs = self.leaf_public_inputs(&aggr.public_inputs)?;
+        let num_leaves = if self.explicit_config || leaf_pi_len == 0 {
+            self.config.num_leaf_proofs
+        } else {
+            // The tree shape depended on the batch size, so recover it from the proof.
+            leaf_public_inputs.len() / leaf_pi_len
+        };
+        let leaves = ProofWithPublicInputs {
+            proof: aggr.proof.clone(),
+            public_inputs: leaf_public_inputs.to_vec(),
+        };
+        PublicCircuitInputs::try_from_aggregated(&leaves, leaf_pi_len, num_leaves)
+    }
+
+    /// The public inputs of the leaves of an aggregated proof, without the
+    /// [`TreeShapeCommitments`] if the aggregator commits to them.
+    fn leaf_public_inputs<'a>(&self, public_inputs: &'a [F]) -> anyhow::Result<&'a [F]> {
+        if self.commit_tree_shape {
+            Ok(TreeShapeCommitments::split_public_inputs(public_inputs)?.0)
+        } else {
+            Ok(public_inputs)
+        }
+    }
+
+    /// Verifies an aggregated proof and checks its leaves against the public inputs the caller
+    /// expects, returning the indices of the leaves that don't match.
+    ///
+    /// `expected[i]` is compared with leaf `i` of the tree of shape `config`. Leaves past the end
+    /// of `expected` must be padding, such as the padding added by [`Self::aggregate`], and are
+    /// reported otherwise, and so are leaves expected to be [`PublicCircuitInputs::padding`].
+    /// An expected proof that was replaced by padding, e.g. by
+    /// [`Self::aggregate_skipping_invalid`], is reported as a mismatch, as is any expectation
+    /// beyond the number of leaves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the root proof doesn't verify or its public inputs don't hold
+    /// `config.num_leaf_proofs` leaves.
+    pub fn verify_aggregated_with_expectations(
+        &self,
+        root_proof: &AggregatedProof<F, C, D>,
+        expected: &[PublicCircuitInputs],
+        config: TreeAggregationConfig,
+    ) -> anyhow::Result<Vec<usize>> {
+        root_proof
+            .circuit_data
+            .verify(root_proof.proof.clone())
+            .context("aggregated proof failed verification")?;
+
+        let leaf_pi_len = self.leaf_circuit_data.common.num_public_inputs;
+        let expected_len = leaf_pi_len * config.num_leaf_proofs;
+        let leaf_public_inputs = self.leaf_public_inputs(&root_proof.proof.public_inputs)?;
+        if leaf_pi_len == 0 || leaf_public_inputs.len() != expected_len {
+            bail!(
+                "aggregated public inputs should contain: {} (= {} leaves × {} fields), got: {}",
+                expected_len,
+                config.num_leaf_proofs,
+                leaf_pi_len,
+                leaf_public_inputs.len()
+            );
+        }
+
+        let leaves = leaf_public_inputs.chunks(leaf_pi_len);
+        let mut mismatches = Vec::new();
+        for (index, leaf) in leaves.enumerate() {
+            let leaf = PublicCircuitInputs::try_from_leaf(leaf);
+            let matches = match expected.get(index) {
+                Some(expected) if expected.is_padding() => leaf.is_ok_and(|leaf| leaf.is_padding()),
+                Some(expected) => leaf.is_ok_and(|leaf| leaf == *expected),
+                None => leaf.is_ok_and(|leaf| leaf.is_padding()),
+            };
+            if !matches {
+                mismatches.push(index);
+            }
+        }
+        mismatches.extend(config.num_leaf_proofs..expected.len());
+
+        Ok(mismatches)
     }
 
     /// Aggregates `N` number of leaf proofs into an [`AggregatedProof`].
@@ -76,18 +295,100 @@ impl WormholeProofAggregator {
             bail!("there are no proofs to aggregate")
         };
 
-        let padded_proofs = pad_with_dummy_proofs(
-            proofs,
-            self.config.num_leaf_proofs,
-            &self.leaf_circuit_data.common,
-        )?;
-        let root_proof = aggregate_to_tree(
+        self.aggregate_leaves(proofs.into_iter().map(Some).collect())
+    }
+
+    /// Aggregates `leaves`, with `None` for padding, into a tree padded to the tree shape.
+    fn aggregate_leaves(
+        &mut self,
+        leaves: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
+    ) -> anyhow::Result<AggregatedProof<F, C, D>> {
+        let _span = tracing::info_span!("aggregate", num_proofs = leaves.len()).entered();
+        #[cfg(feature = "metrics")]
+        metrics::set_queue_depth(0);
+        self.aggregate_proofs(leaves).inspect_err(|_| {
+            #[cfg(feature = "metrics")]
+            metrics::record_failure(FailureStage::Aggregate);
+        })
+    }
+
+    fn aggregate_proofs(
+        &mut self,
+        leaves: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
+    ) -> anyhow::Result<AggregatedProof<F, C, D>> {
+        let config = self.tree_config_for(leaves.len());
+        let padded_proofs = pad_leaves(leaves, config.num_leaf_proofs)?;
+        #[cfg(feature = "distributed")]
+        let root_proof = if self.workers.is_empty() {
+            aggregate_leaves_to_tree(
+                padded_proofs,
+                &self.leaf_circuit_data.common,
+                &self.leaf_circuit_data.verifier_only,
+                config,
+            )?
+        } else {
+            aggregate_to_tree_distributed(
+                padded_proofs,
+                &self.leaf_circuit_data.common,
+                &self.leaf_circuit_data.verifier_only,
+                config,
+                &mut self.workers,
+            )?
+        };
+        #[cfg(not(feature = "distributed"))]
+        let root_proof = aggregate_leaves_to_tree(
             padded_proofs,
             &self.leaf_circuit_data.common,
             &self.leaf_circuit_data.verifier_only,
-            self.config,
+            config,
         )?;
 
+        if self.commit_tree_shape {
+            return commit_tree_shape(root_proof, config);
+        }
         Ok(root_proof)
     }
+
+    /// Aggregates the buffered leaf proofs like [`Self::aggregate`], but first verifies each of
+    /// them and replaces invalid ones with padding instead of failing the whole batch. Fails if
+    /// no proof is valid, as there is no proof to verify in place of the padding.
+    ///
+    /// Replaced proofs keep their position, so the leaf indices in the returned
+    /// [`AggregationReport`] match the order the proofs were pushed in.
+    pub fn aggregate_skipping_invalid(
+        &mut self,
+    ) -> anyhow::Result<(AggregatedProof<F, C, D>, AggregationReport)> {
+        let Some(proofs) = self.proofs_buffer.take() else {
+            bail!("there are no proofs to aggregate")
+        };
+
+        let mut report = AggregationReport::default();
+        let mut checked_proofs = Vec::with_capacity(proofs.len());
+        for (index, proof) in proofs.into_iter().enumerate() {
+            match self.leaf_circuit_data.verify(proof.clone()) {
+                Ok(()) => checked_proofs.push(Some(proof)),
+                Err(e) => {
+                    tracing::warn!(index, error = %e, "replacing invalid proof with padding");
+                    #[cfg(feature = "metrics")]
+                    metrics::record_failure(FailureStage::Verify);
+                    report.replaced.push(ReplacedProof {
+                        index,
+                        reason: e.to_string(),
+                    });
+                    checked_proofs.push(None);
+                }
+            }
+        }
+
+        let root_proof = self.aggregate_leaves(checked_proofs)?;
+
+        Ok((root_proof, report))
+    }
+}
+
+/// The nullifier public inputs of a leaf proof, if it has them.
+fn nullifier_of(proof: &ProofWithPublicInputs<F, C, D>) -> Option<&[F]> {
+    proof
+        .public_inputs
+        .get(NULLIFIER_START_INDEX..NULLIFIER_END_INDEX)
 }
@@ -0,0 +1,210 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use qp_wormhole_aggregator::aggregator::{DuplicatePolicy, WormholeProofAggregator};
+use qp_wormhole_aggregator::circuits::tree::{
+    TreeAggregationConfig, DEFAULT_TREE_BRANCHING_FACTOR, DEFAULT_TREE_DEPTH,
+};
+use qp_wormhole_aggregator::service::read_proof_file;
+use qp_wormhole_aggregator::soak::{resident_memory_bytes, SoakFailure, SoakStats};
+use test_helpers::storage_proof::TestInputs;
+use tracing_subscriber::EnvFilter;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+use wormhole_verifier::{ProofWithPublicInputs, WormholeVerifier};
+use zk_circuits_common::circuit::{C, D, F};
+
+/// Aggregates leaf proofs in rolling batches for hours, tracking the latency, failures and memory
+/// growth of the aggregation pipeline.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Directory containing the leaf circuit's `prover.bin`, `verifier.bin` and `common.bin`.
+    #[arg(long, default_value = "generated-bins")]
+    circuit_dir: PathBuf,
+
+    /// Replay the proof files in this directory, in a loop, instead of generating leaf proofs.
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// How long to run, in minutes.
+    #[arg(long, default_value_t = 240)]
+    duration_mins: u64,
+
+    /// Stop after this many batches, even if the duration hasn't elapsed.
+    #[arg(long)]
+    max_batches: Option<usize>,
+
+    /// Branching factor of the aggregation tree.
+    #[arg(long, default_value_t = DEFAULT_TREE_BRANCHING_FACTOR)]
+    branching_factor: usize,
+
+    /// Depth of the aggregation tree.
+    #[arg(long, default_value_t = DEFAULT_TREE_DEPTH)]
+    tree_depth: u32,
+
+    /// Proofs per batch. Defaults to the number of tree leaves, smaller batches are padded.
+    #[arg(long)]
+    batch_size: Option<usize>,
+
+    /// Batches run before the memory baseline is taken, while allocations settle.
+    #[arg(long, default_value_t = 3)]
+    warmup_batches: usize,
+
+    /// Log a summary every this many batches.
+    #[arg(long, default_value_t = 10)]
+    report_every: usize,
+
+    /// Exit with an error if resident memory grows by more than this many MiB after the warmup.
+    #[arg(long)]
+    max_memory_growth_mib: Option<u64>,
+}
+
+/// Where the leaf proofs of the soak test come from.
+enum LeafProofs {
+    /// Proves the test inputs with a prover loaded from the circuit directory for every proof.
+    Generate { circuit_dir: PathBuf },
+    /// Cycles through proofs read from disk.
+    Replay {
+        proofs: Vec<ProofWithPublicInputs<F, C, D>>,
+        next: usize,
+    },
+}
+
+impl LeafProofs {
+    fn next(&mut self) -> Result<ProofWithPublicInputs<F, C, D>> {
+        match self {
+            Self::Generate { circuit_dir } => WormholeProver::from_artifacts_dir(circuit_dir)?
+                .commit(&CircuitInputs::test_inputs())?
+                .prove(),
+            Self::Replay { proofs, next } => {
+                let proof = proofs[*next % proofs.len()].clone();
+                *next += 1;
+                Ok(proof)
+            }
+        }
+    }
+}
+
+/// Reads every proof file in `dir`, in file name order.
+fn read_proofs(
+    dir: &Path,
+    verifier: &WormholeVerifier,
+) -> Result<Vec<ProofWithPublicInputs<F, C, D>>> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {:?}", dir))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    let common = &verifier.circuit_data.common;
+    let proofs = paths
+        .iter()
+        .map(|path| {
+            ProofWithPublicInputs::from_bytes(read_proof_file(path)?, common)
+                .with_context(|| format!("failed to deserialize {:?}", path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if proofs.is_empty() {
+        bail!("no proofs to replay in {:?}", dir);
+    }
+
+    Ok(proofs)
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+    let args = Args::parse();
+
+    let verifier = WormholeVerifier::new_from_files(
+        &args.circuit_dir.join("verifier.bin"),
+        &args.circuit_dir.join("common.bin"),
+    )?;
+    let config = TreeAggregationConfig::new(args.branching_factor, args.tree_depth);
+    let batch_size = args.batch_size.unwrap_or(config.num_leaf_proofs);
+    if batch_size == 0 || batch_size > config.num_leaf_proofs {
+        bail!(
+            "batch size must be between 1 and {} for this tree",
+            config.num_leaf_proofs
+        );
+    }
+    let mut leaf_proofs = match &args.replay {
+        Some(dir) => LeafProofs::Replay {
+            proofs: read_proofs(dir, &verifier)?,
+            next: 0,
+        },
+        None => LeafProofs::Generate {
+            circuit_dir: args.circuit_dir.clone(),
+        },
+    };
+
+    let mut stats = SoakStats::default();
+    let deadline = Instant::now() + Duration::from_secs(args.duration_mins * 60);
+    while Instant::now() < deadline && args.max_batches.is_none_or(|max| stats.batches() < max) {
+        // Generated proofs all spend the test deposit, and replayed ones repeat.
+        let mut aggregator = WormholeProofAggregator::new(verifier.circuit_data.clone())
+            .with_config(config)
+            .with_duplicate_policy(DuplicatePolicy::Allow);
+        for _ in 0..batch_size {
+            match leaf_proofs.next() {
+                Ok(proof) => aggregator.push_proof(proof)?,
+                Err(e) => {
+                    tracing::warn!(error = %format_args!("{:#}", e), "leaf proof failed");
+                    stats.record_failure(SoakFailure::Proof, None);
+                }
+            }
+        }
+
+        let start = Instant::now();
+        // The aggregated proof and its circuit are dropped before memory is measured.
+        let result = aggregator.aggregate().map(|aggregated| {
+            let latency = start.elapsed();
+            (latency, aggregated.circuit_data.verify(aggregated.proof))
+        });
+        drop(aggregator);
+        match result {
+            Ok((latency, verified)) => {
+                if let Err(e) = verified {
+                    tracing::warn!(error = %e, "aggregated proof failed to verify");
+                    stats.record_failure(SoakFailure::Verification, None);
+                }
+                stats.record_batch(latency, resident_memory_bytes());
+                tracing::debug!(batch = stats.batches(), ?latency, "aggregated batch");
+            }
+            Err(e) => {
+                tracing::warn!(error = %format_args!("{:#}", e), "aggregation failed");
+                stats.record_failure(SoakFailure::Aggregation, resident_memory_bytes());
+            }
+        }
+
+        if args.report_every > 0 && stats.batches().is_multiple_of(args.report_every) {
+            tracing::info!("soak test progress\n{}", stats.summary(args.warmup_batches));
+        }
+    }
+
+    let summary = stats.summary(args.warmup_batches);
+    println!("{}", summary);
+
+    if let (Some(max), Some(growth)) = (args.max_memory_growth_mib, summary.memory_growth) {
+        if growth > (max * 1024 * 1024) as i64 {
+            bail!(
+                "resident memory grew by {} bytes, more than {} MiB",
+                growth,
+                max
+            );
+        }
+    }
+    if stats.failures() > 0 {
+        bail!("{} soak test iterations failed", stats.failures());
+    }
+
+    Ok(())
+}
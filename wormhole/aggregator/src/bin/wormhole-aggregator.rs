@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Parser;
+use qp_wormhole_aggregator::aggregator::{DuplicatePolicy, WormholeProofAggregator};
+use qp_wormhole_aggregator::circuits::tree::{
+    TreeAggregationConfig, DEFAULT_TREE_BRANCHING_FACTOR, DEFAULT_TREE_DEPTH,
+};
+#[cfg(feature = "distributed")]
+use qp_wormhole_aggregator::distributed;
+use qp_wormhole_aggregator::intake::{
+    IntakeLimits, DEFAULT_MAX_PROOF_BYTES, DEFAULT_MAX_QUEUE_DEPTH,
+};
+use qp_wormhole_aggregator::service::{
+    AggregationService, BatchPolicy, CommandHook, ProofSource, SubmitHook,
+};
+use tracing_subscriber::EnvFilter;
+use wormhole_verifier::WormholeVerifier;
+
+/// Aggregates wormhole leaf proofs read from a directory or stdin.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Directory to watch for proof files. Hex encoded proofs are read from stdin when omitted.
+    #[arg(long)]
+    watch: Option<PathBuf>,
+
+    /// Directory containing the leaf circuit's `verifier.bin` and `common.bin`.
+    #[arg(long, default_value = "generated-bins")]
+    circuit_dir: PathBuf,
+
+    /// Directory aggregated proofs are written to.
+    #[arg(long, default_value = "aggregated-proofs")]
+    output_dir: PathBuf,
+
+    /// Branching factor of the aggregation tree.
+    #[arg(long, default_value_t = DEFAULT_TREE_BRANCHING_FACTOR)]
+    branching_factor: usize,
+
+    /// Depth of the aggregation tree.
+    #[arg(long, default_value_t = DEFAULT_TREE_DEPTH)]
+    tree_depth: u32,
+
+    /// Pick the tree shape for the size of each batch. The branching factor and depth then only
+    /// bound the batch size.
+    #[arg(long)]
+    auto_tree: bool,
+
+    /// Aggregate once this many proofs are buffered. Defaults to the number of tree leaves.
+    #[arg(long)]
+    batch_size: Option<usize>,
+
+    /// Aggregate a non-empty batch after this many seconds, even if it is not full.
+    #[arg(long, default_value_t = 60)]
+    batch_timeout_secs: u64,
+
+    /// How often the watched directory is polled for new files, in milliseconds.
+    #[arg(long, default_value_t = 1000)]
+    poll_interval_ms: u64,
+
+    /// Skip proofs larger than this many bytes without decoding them.
+    #[arg(long, default_value_t = DEFAULT_MAX_PROOF_BYTES)]
+    max_proof_bytes: usize,
+
+    /// Stop reading proofs while this many are waiting to be verified.
+    #[arg(long, default_value_t = DEFAULT_MAX_QUEUE_DEPTH)]
+    max_queue_depth: usize,
+
+    /// Shell command run after every aggregation, with the proof path in `AGGREGATED_PROOF_PATH`.
+    #[arg(long)]
+    submit_command: Option<String>,
+
+    /// Reject proofs whose nullifier is already in the batch with a warning, instead of dropping
+    /// them quietly.
+    #[arg(long)]
+    reject_duplicates: bool,
+
+    /// Serve chunk jobs on stdin and stdout instead of aggregating, for a parent started with
+    /// `--local-workers`.
+    #[cfg(feature = "distributed")]
+    #[arg(long, conflicts_with = "listen")]
+    worker: bool,
+
+    /// Serve chunk jobs to parents connecting to this address instead of aggregating.
+    #[cfg(feature = "distributed")]
+    #[arg(long)]
+    listen: Option<String>,
+
+    /// Aggregate the leaf level on this many worker processes spawned from this binary.
+    #[cfg(feature = "distributed")]
+    #[arg(long, default_value_t = 0)]
+    local_workers: usize,
+
+    /// Aggregate the leaf level on a worker listening at this address. Can be repeated.
+    #[cfg(feature = "distributed")]
+    #[arg(long)]
+    worker_addr: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+    let args = Args::parse();
+
+    #[cfg(feature = "distributed")]
+    if args.worker {
+        return distributed::serve(std::io::stdin().lock(), std::io::stdout().lock());
+    }
+    #[cfg(feature = "distributed")]
+    if let Some(addr) = &args.listen {
+        return listen(addr);
+    }
+
+    let verifier = WormholeVerifier::new_from_files(
+        &args.circuit_dir.join("verifier.bin"),
+        &args.circuit_dir.join("common.bin"),
+    )?;
+    let config = TreeAggregationConfig::new(args.branching_factor, args.tree_depth);
+    let duplicate_policy = if args.reject_duplicates {
+        DuplicatePolicy::Reject
+    } else {
+        DuplicatePolicy::Skip
+    };
+    let mut aggregator =
+        WormholeProofAggregator::new(verifier.circuit_data).with_duplicate_policy(duplicate_policy);
+    if args.auto_tree {
+        aggregator.config = config;
+    } else {
+        aggregator = aggregator.with_config(config);
+    }
+
+    #[cfg(feature = "distributed")]
+    {
+        let mut workers = Vec::<Box<dyn distributed::Worker>>::new();
+        for _ in 0..args.local_workers {
+            let mut command = std::process::Command::new(std::env::current_exe()?);
+            command.arg("--worker");
+            workers.push(Box::new(distributed::ProcessWorker::spawn(&mut command)?));
+        }
+        for addr in &args.worker_addr {
+            workers.push(Box::new(distributed::TcpWorker::connect(addr)?));
+        }
+        if !workers.is_empty() {
+            aggregator = aggregator.with_workers(workers);
+        }
+    }
+
+    let policy = BatchPolicy {
+        max_proofs: args.batch_size.unwrap_or(config.num_leaf_proofs),
+        max_wait: Duration::from_secs(args.batch_timeout_secs),
+    };
+    let hook = args
+        .submit_command
+        .map(|command| Box::new(CommandHook { command }) as Box<dyn SubmitHook>);
+
+    let limits = IntakeLimits {
+        max_proof_bytes: args.max_proof_bytes,
+        max_queue_depth: args.max_queue_depth,
+        max_proofs_per_source: None,
+    };
+    let source = match args.watch {
+        Some(path) => ProofSource::Directory {
+            path,
+            poll_interval: Duration::from_millis(args.poll_interval_ms),
+        },
+        None => ProofSource::Stdin,
+    };
+
+    let mut service = AggregationService::new(aggregator, policy, args.output_dir, hook)?;
+    service.run(source.spawn_with_limits(limits))
+}
+
+/// Serves every parent connecting to `addr` on its own thread.
+#[cfg(feature = "distributed")]
+fn listen(addr: &str) -> Result<()> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    tracing::info!(addr, "serving chunk jobs");
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = stream.try_clone()?;
+        std::thread::spawn(move || {
+            if let Err(e) = distributed::serve(reader, stream) {
+                tracing::warn!(error = %format_args!("{:#}", e), "parent connection failed");
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Commitments to the shape of an aggregation tree.
+//!
+//! The public inputs of a root proof are the leaf public inputs in leaf order, which don't tell
+//! which tree they were aggregated with. [`commit_tree_shape`] wraps a root proof in one more
+//! circuit, which verifies it against its constant verifier data, re-exposes its public inputs and
+//! appends:
+//! - `tree_shape_commitment = H(tree_branching_factor || tree_depth || num_leaf_proofs)`, a
+//!   constant of the circuit, see [`tree_shape_commitment`].
+//! - `leaf_ordering_commitment = H(H(leaf_0) || ... || H(leaf_n))`, where `leaf_i` are the public
+//!   inputs of leaf `i`, see [`leaf_ordering_commitment`].
+//!
+//! An on-chain verifier can then compare the first commitment with the shape the protocol
+//! expects, and the second with the leaves it processes, to detect a relayer presenting an
+//! aggregate of a different shape or order.
+use anyhow::bail;
+use plonky2::{
+    field::types::Field,
+    hash::{hash_types::HashOut, poseidon::PoseidonHash},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::{Digest, DIGEST_NUM_FIELD_ELEMENTS};
+
+use crate::circuits::tree::{AggregatedProof, TreeAggregationConfig};
+
+/// The number of public inputs [`commit_tree_shape`] appends to those of the root proof.
+pub const TREE_SHAPE_PUBLIC_INPUTS_LEN: usize = 2 * DIGEST_NUM_FIELD_ELEMENTS;
+
+/// The commitments appended to the public inputs of a root proof by [`commit_tree_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeShapeCommitments {
+    pub tree_shape: Digest,
+    pub leaf_ordering: Digest,
+}
+
+impl TreeShapeCommitments {
+    /// Splits the public inputs of a proof of [`commit_tree_shape`] into the leaf public inputs
+    /// and the commitments.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are fewer public inputs than commitments.
+    pub fn split_public_inputs(pis: &[F]) -> anyhow::Result<(&[F], Self)> {
+        let Some(leaves_len) = pis.len().checked_sub(TREE_SHAPE_PUBLIC_INPUTS_LEN) else {
+            bail!(
+                "public inputs should end with {} tree shape commitments, got: {} elements",
+                TREE_SHAPE_PUBLIC_INPUTS_LEN,
+                pis.len()
+            );
+        };
+        let (leaves, commitments) = pis.split_at(leaves_len);
+        let (tree_shape, leaf_ordering) = commitments.split_at(DIGEST_NUM_FIELD_ELEMENTS);
+        Ok((
+            leaves,
+            Self {
+                tree_shape: tree_shape.try_into().expect("slice has digest length"),
+                leaf_ordering: leaf_ordering.try_into().expect("slice has digest length"),
+            },
+        ))
+    }
+}
+
+/// The commitment to the tree shape of `config`.
+pub fn tree_shape_commitment(config: &TreeAggregationConfig) -> Digest {
+    let shape = [
+        config.tree_branching_factor as u64,
+        config.tree_depth as u64,
+        config.num_leaf_proofs as u64,
+    ];
+    PoseidonHash::hash_no_pad(&shape.map(F::from_canonical_u64)).elements
+}
+
+/// The commitment to the order of the leaves with the concatenated `leaf_public_inputs`, each
+/// `leaf_pi_len` elements long.
+///
+/// # Errors
+///
+/// Returns an error if the public inputs are not a multiple of `leaf_pi_len`.
+pub fn leaf_ordering_commitment(
+    leaf_public_inputs: &[F],
+    leaf_pi_len: usize,
+) -> anyhow::Result<Digest> {
+    if leaf_pi_len == 0 || leaf_public_inputs.len() % leaf_pi_len != 0 {
+        bail!(
+            "leaf public inputs should be a multiple of {}, got: {}",
+            leaf_pi_len,
+            leaf_public_inputs.len()
+        );
+    }
+    let leaf_hashes: Vec<F> = leaf_public_inputs
+        .chunks(leaf_pi_len)
+        .flat_map(|leaf| PoseidonHash::hash_no_pad(leaf).elements)
+        .collect();
+    Ok(PoseidonHash::hash_no_pad(&leaf_hashes).elements)
+}
+
+/// Wraps `root`, the root proof of a tree of shape `config`, in a proof whose public inputs end
+/// with the [`TreeShapeCommitments`], see the [module documentation](self).
+///
+/// # Errors
+///
+/// Returns an error if the public inputs of `root` don't split into `config.num_leaf_proofs`
+/// leaves, or proving fails.
+pub fn commit_tree_shape(
+    root: AggregatedProof<F, C, D>,
+    config: TreeAggregationConfig,
+) -> anyhow::Result<AggregatedProof<F, C, D>> {
+    let _span = tracing::info_span!("commit_tree_shape").entered();
+    let common_data = &root.circuit_data.common;
+    let num_public_inputs = common_data.num_public_inputs;
+    if config.num_leaf_proofs == 0 || num_public_inputs % config.num_leaf_proofs != 0 {
+        bail!(
+            "root proof with {} public inputs does not hold {} leaves",
+            num_public_inputs,
+            config.num_leaf_proofs
+        );
+    }
+    let leaf_pi_len = num_public_inputs / config.num_leaf_proofs;
+
+    let mut builder = CircuitBuilder::<F, D>::new(common_data.config.clone());
+    let verifier_data_t = builder.constant_verifier_data(&root.circuit_data.verifier_only);
+    let proof_t = builder.add_virtual_proof_with_pis(common_data);
+    builder.verify_proof::<C>(&proof_t, &verifier_data_t, common_data);
+    builder.register_public_inputs(&proof_t.public_inputs);
+
+    let tree_shape = builder.constant_hash(HashOut::from(tree_shape_commitment(&config)));
+    builder.register_public_inputs(&tree_shape.elements);
+
+    let mut leaf_hashes: Vec<Target> = Vec::with_capacity(num_public_inputs);
+    for leaf in proof_t.public_inputs.chunks(leaf_pi_len) {
+        let leaf_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(leaf.to_vec());
+        leaf_hashes.extend(leaf_hash.elements);
+    }
+    let leaf_ordering = builder.hash_n_to_hash_no_pad::<PoseidonHash>(leaf_hashes);
+    builder.register_public_inputs(&leaf_ordering.elements);
+
+    let circuit_data = builder.build::<C>();
+    let mut pw = PartialWitness::new();
+    pw.set_proof_with_pis_target(&proof_t, &root.proof)?;
+    let proof = circuit_data.prove(pw)?;
+
+    Ok(AggregatedProof {
+        proof,
+        circuit_data,
+    })
+}
@@ -1,13 +1,20 @@
 use plonky2::{
     field::extension::Extendable,
     hash::hash_types::RichField,
-    iop::witness::{PartialWitness, WitnessWrite},
+    iop::{
+        target::BoolTarget,
+        witness::{PartialWitness, WitnessWrite},
+    },
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitData, CommonCircuitData, VerifierOnlyCircuitData},
+        circuit_data::{
+            CircuitData, CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+        },
         config::GenericConfig,
+        proof::ProofWithPublicInputsTarget,
     },
 };
+use anyhow::bail;
 use rayon::{iter::ParallelIterator, slice::ParallelSlice};
 use wormhole_verifier::ProofWithPublicInputs;
 use zk_circuits_common::circuit::{C, D, F};
@@ -18,6 +25,9 @@ pub const DEFAULT_TREE_BRANCHING_FACTOR: usize = 2;
 /// The default depth of the tree of the aggregated proof, counted as the longest path of edges between the
 /// leaf nodes and the root node.
 pub const DEFAULT_TREE_DEPTH: u32 = 3;
+/// The widest tree [`TreeAggregationConfig::auto_for`] picks. Wider trees verify more proofs per
+/// aggregation circuit, which makes each circuit larger.
+pub const MAX_AUTO_TREE_BRANCHING_FACTOR: usize = 8;
 
 /// A proof containing both the proof data and the circuit data needed to verify it.
 #[derive(Debug)]
@@ -44,6 +54,32 @@ impl TreeAggregationConfig {
             tree_depth,
         }
     }
+
+    /// Picks the tree shape that aggregates `n_proofs` proofs with the least total work.
+    ///
+    /// The work of a tree is estimated as the number of recursive proof verifications across all
+    /// of its aggregation circuits, including those of the padding leaves. Ties are broken in
+    /// favour of shallower trees, then narrower ones.
+    pub fn auto_for(n_proofs: usize) -> Self {
+        let n_proofs = n_proofs.max(1);
+        (2..=MAX_AUTO_TREE_BRANCHING_FACTOR)
+            .map(|tree_branching_factor| {
+                let mut tree_depth = 1;
+                while tree_branching_factor.pow(tree_depth) < n_proofs {
+                    tree_depth += 1;
+                }
+                Self::new(tree_branching_factor, tree_depth)
+            })
+            .min_by_key(|config| (config.num_verifications(), config.tree_depth))
+            .expect("branching factor range is not empty")
+    }
+
+    /// The number of proofs verified by all aggregation circuits of the tree.
+    fn num_verifications(&self) -> usize {
+        // Every node above the leaves verifies `tree_branching_factor` proofs.
+        let num_nodes = (self.num_leaf_proofs - 1) / (self.tree_branching_factor - 1);
+        num_nodes * self.tree_branching_factor
+    }
 }
 
 impl Default for TreeAggregationConfig {
@@ -58,16 +94,64 @@ pub fn aggregate_to_tree(
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     config: TreeAggregationConfig,
 ) -> anyhow::Result<AggregatedProof<F, C, D>> {
-    // Aggregate the first level.
-    let mut proofs = aggregate_level(leaf_proofs, common_data, verifier_data, config)?;
+    let leaves = leaf_proofs.into_iter().map(Some).collect();
+    aggregate_leaves_to_tree(leaves, common_data, verifier_data, config)
+}
+
+/// Like [`aggregate_to_tree`], but `None` leaves are padding.
+///
+/// The aggregation circuit registers all-zero public inputs for a padding leaf instead of those
+/// of the proof it verifies in its place, which is another proof of the same level. A leaf proof
+/// whose public inputs are not all zero, such as any proof with a nullifier, is therefore never
+/// taken for padding, and padding never carries public inputs of a proof.
+///
+/// # Errors
+///
+/// Returns an error if all leaves are padding, or aggregating fails.
+pub fn aggregate_leaves_to_tree(
+    leaves: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
+    common_data: &CommonCircuitData<F, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    config: TreeAggregationConfig,
+) -> anyhow::Result<AggregatedProof<F, C, D>> {
+    let _span = tracing::info_span!(
+        "aggregate_to_tree",
+        num_leaf_proofs = leaves.len(),
+        tree_branching_factor = config.tree_branching_factor
+    )
+    .entered();
+
+    aggregate_from_level(leaves, common_data, verifier_data, config, 0)
+}
+
+/// Aggregates `proofs` of tree level `level` up to the root, with `None` for padding.
+pub(crate) fn aggregate_from_level(
+    proofs: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
+    common_data: &CommonCircuitData<F, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    config: TreeAggregationConfig,
+    mut level: usize,
+) -> anyhow::Result<AggregatedProof<F, C, D>> {
+    // Aggregate the given level.
+    #[cfg(feature = "metrics")]
+    let started = std::time::Instant::now();
+    let mut proofs = aggregate_level(proofs, common_data, verifier_data, config, level)?;
+    #[cfg(feature = "metrics")]
+    crate::metrics::observe_level_duration(level, started.elapsed());
 
     // Do the next levels by utilizing the circuit data within each aggregated proof.
     while proofs.len() > 1 {
         let common_data = &proofs[0].circuit_data.common.clone();
         let verifier_data = &proofs[0].circuit_data.verifier_only.clone();
-        let to_aggregate = proofs.into_iter().map(|p| p.proof).collect();
+        let to_aggregate = proofs.into_iter().map(|p| Some(p.proof)).collect();
 
-        let aggregated_proofs = aggregate_level(to_aggregate, common_data, verifier_data, config)?;
+        level += 1;
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+        let aggregated_proofs =
+            aggregate_level(to_aggregate, common_data, verifier_data, config, level)?;
+        #[cfg(feature = "metrics")]
+        crate::metrics::observe_level_duration(level, started.elapsed());
 
         proofs = aggregated_proofs;
     }
@@ -76,61 +160,179 @@ pub fn aggregate_to_tree(
     Ok(proofs.pop().unwrap())
 }
 
+/// Merges two aggregated proofs into a proof one level higher, whose public inputs are those of
+/// `a` followed by those of `b`.
+///
+/// Both proofs must come from the same aggregation circuit, that is from trees with the same
+/// config and leaf circuit, at the same level. The merged proof is again a valid input to this
+/// function, together with another merged proof of the same shape.
+pub fn merge(
+    a: AggregatedProof<F, C, D>,
+    b: AggregatedProof<F, C, D>,
+) -> anyhow::Result<AggregatedProof<F, C, D>> {
+    if a.circuit_data.verifier_only.circuit_digest != b.circuit_data.verifier_only.circuit_digest
+        || a.circuit_data.common != b.circuit_data.common
+    {
+        bail!("cannot merge aggregated proofs of different circuits")
+    }
+
+    let _span = tracing::info_span!("merge_aggregated_proofs").entered();
+    aggregate_chunk(
+        &[a.proof, b.proof],
+        &[false, false],
+        &a.circuit_data.common,
+        &a.circuit_data.verifier_only,
+    )
+}
+
 #[cfg(not(feature = "multithread"))]
 fn aggregate_level(
-    proofs: Vec<ProofWithPublicInputs<F, C, D>>,
+    proofs: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
     common_data: &CommonCircuitData<F, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     config: TreeAggregationConfig,
+    level: usize,
 ) -> anyhow::Result<Vec<AggregatedProof<F, C, D>>> {
+    let _span =
+        tracing::info_span!("aggregation_level", level, num_proofs = proofs.len()).entered();
+    let filler = padding_filler(&proofs)?;
     proofs
         .chunks(config.tree_branching_factor)
-        .map(|chunk| aggregate_chunk(chunk, common_data, verifier_data))
+        .map(|chunk| {
+            let (chunk, padding) = fill_padding(chunk, filler);
+            aggregate_chunk(&chunk, &padding, common_data, verifier_data)
+        })
         .collect()
 }
 
 #[cfg(feature = "multithread")]
 fn aggregate_level(
-    proofs: Vec<ProofWithPublicInputs<F, C, D>>,
+    proofs: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
     common_data: &CommonCircuitData<F, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     config: TreeAggregationConfig,
+    level: usize,
 ) -> anyhow::Result<Vec<AggregatedProof<F, C, D>>> {
+    let _span =
+        tracing::info_span!("aggregation_level", level, num_proofs = proofs.len()).entered();
+    let filler = padding_filler(&proofs)?;
     proofs
         .par_chunks(config.tree_branching_factor)
-        .map(|chunk| aggregate_chunk(chunk, common_data, verifier_data))
+        .map(|chunk| {
+            let (chunk, padding) = fill_padding(chunk, filler);
+            aggregate_chunk(&chunk, &padding, common_data, verifier_data)
+        })
         .collect()
 }
 
-/// Circuit gadget that takes in a pair of proofs, a and b, aggregates it and return the new proof.
-fn aggregate_chunk(
-    chunk: &[ProofWithPublicInputs<F, C, D>],
-    common_data: &CommonCircuitData<F, D>,
-    verifier_data: &VerifierOnlyCircuitData<C, D>,
-) -> anyhow::Result<AggregatedProof<F, C, D>> {
+/// The proof that the padding slots of a level verify in place of one, the first proof of the
+/// level.
+pub(crate) fn padding_filler(
+    proofs: &[Option<ProofWithPublicInputs<F, C, D>>],
+) -> anyhow::Result<&ProofWithPublicInputs<F, C, D>> {
+    proofs
+        .iter()
+        .flatten()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("cannot aggregate a tree level of padding only"))
+}
+
+/// Splits a chunk into the proofs to verify, with `filler` in the padding slots, and whether each
+/// slot is padding.
+pub(crate) fn fill_padding(
+    chunk: &[Option<ProofWithPublicInputs<F, C, D>>],
+    filler: &ProofWithPublicInputs<F, C, D>,
+) -> (Vec<ProofWithPublicInputs<F, C, D>>, Vec<bool>) {
+    chunk
+        .iter()
+        .map(|proof| match proof {
+            Some(proof) => (proof.clone(), false),
+            None => (filler.clone(), true),
+        })
+        .unzip()
+}
+
+/// The circuit aggregating `num_proofs` proofs of the circuit with `common_data`.
+struct ChunkCircuit {
+    circuit_data: CircuitData<F, C, D>,
+    verifier_data_t: VerifierCircuitTarget,
+    proof_targets: Vec<ProofWithPublicInputsTarget<D>>,
+    padding_targets: Vec<BoolTarget>,
+}
+
+fn build_chunk_circuit(num_proofs: usize, common_data: &CommonCircuitData<F, D>) -> ChunkCircuit {
     let mut builder = CircuitBuilder::new(common_data.config.clone());
     let verifier_data_t =
         builder.add_virtual_verifier_data(common_data.fri_params.config.cap_height);
+    let zero = builder.zero();
 
-    let mut proof_targets = Vec::with_capacity(chunk.len());
-    for _ in 0..chunk.len() {
+    let mut proof_targets = Vec::with_capacity(num_proofs);
+    let mut padding_targets = Vec::with_capacity(num_proofs);
+    for _ in 0..num_proofs {
         // Verify the proof.
         let proof_t = builder.add_virtual_proof_with_pis(common_data);
         builder.verify_proof::<C>(&proof_t, &verifier_data_t, common_data);
 
-        // Aggregate public inputs of proof.
-        builder.register_public_inputs(&proof_t.public_inputs);
+        // Aggregate public inputs of proof, or zeros if the slot is padding.
+        let is_padding = builder.add_virtual_bool_target_safe();
+        for &input in &proof_t.public_inputs {
+            let input = builder.select(is_padding, zero, input);
+            builder.register_public_input(input);
+        }
 
         proof_targets.push(proof_t);
+        padding_targets.push(is_padding);
+    }
+
+    ChunkCircuit {
+        circuit_data: builder.build(),
+        verifier_data_t,
+        proof_targets,
+        padding_targets,
     }
+}
 
-    let circuit_data = builder.build();
+/// The data of the circuit [`aggregate_chunk`] proves for `num_proofs` proofs of the circuit with
+/// `common_data`, without proving it.
+pub(crate) fn chunk_circuit_data(
+    num_proofs: usize,
+    common_data: &CommonCircuitData<F, D>,
+) -> CircuitData<F, C, D> {
+    build_chunk_circuit(num_proofs, common_data).circuit_data
+}
+
+/// Circuit gadget that aggregates a chunk of proofs and returns the new proof. The public inputs
+/// of the slots `padding` marks are replaced by zeros, see [`aggregate_leaves_to_tree`].
+pub(crate) fn aggregate_chunk(
+    chunk: &[ProofWithPublicInputs<F, C, D>],
+    padding: &[bool],
+    common_data: &CommonCircuitData<F, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+) -> anyhow::Result<AggregatedProof<F, C, D>> {
+    if padding.len() != chunk.len() {
+        bail!(
+            "chunk has {} proofs but {} padding flags",
+            chunk.len(),
+            padding.len()
+        );
+    }
+    let ChunkCircuit {
+        circuit_data,
+        verifier_data_t,
+        proof_targets,
+        padding_targets,
+    } = build_chunk_circuit(chunk.len(), common_data);
 
     // Fill targets.
     let mut pw = PartialWitness::new();
     pw.set_verifier_data_target(&verifier_data_t, verifier_data)?;
-    for (target, proof) in proof_targets.iter().zip(chunk) {
+    for ((target, proof), (&padding_t, &is_padding)) in proof_targets
+        .iter()
+        .zip(chunk)
+        .zip(padding_targets.iter().zip(padding))
+    {
         pw.set_proof_with_pis_target(target, proof)?;
+        pw.set_bool_target(padding_t, is_padding)?;
     }
 
     let proof = circuit_data.prove(pw)?;
@@ -158,7 +360,8 @@ mod tests {
     use zk_circuits_common::circuit::{C, D, F};
 
     use crate::circuits::tree::{
-        aggregate_chunk, aggregate_to_tree, AggregatedProof, TreeAggregationConfig,
+        aggregate_chunk, aggregate_leaves_to_tree, aggregate_to_tree, merge, AggregatedProof,
+        TreeAggregationConfig, MAX_AUTO_TREE_BRANCHING_FACTOR,
     };
 
     fn generate_base_circuit() -> (CircuitData<F, C, D>, Target) {
@@ -210,6 +413,91 @@ mod tests {
         root_proof.circuit_data.verify(root_proof.proof).unwrap()
     }
 
+    fn aggregate_squares(
+        values: &[u64],
+        config: TreeAggregationConfig,
+    ) -> AggregatedProof<F, C, D> {
+        let proofs = values
+            .iter()
+            .map(|&v| prove_square(F::from_canonical_u64(v)))
+            .collect::<Vec<_>>();
+
+        let common_data = &proofs[0].circuit_data.common.clone();
+        let verifier_data = &proofs[0].circuit_data.verifier_only.clone();
+        let to_aggregate = proofs.into_iter().map(|p| p.proof).collect();
+
+        aggregate_to_tree(to_aggregate, common_data, verifier_data, config).unwrap()
+    }
+
+    #[test]
+    fn padding_leaves_aggregate_to_zeros() {
+        let proofs = [3, 5].map(|v| prove_square(F::from_canonical_u64(v)));
+        let common_data = &proofs[0].circuit_data.common.clone();
+        let verifier_data = &proofs[0].circuit_data.verifier_only.clone();
+        let [a, b] = proofs.map(|p| Some(p.proof));
+
+        let config = TreeAggregationConfig::new(2, 2);
+        let root =
+            aggregate_leaves_to_tree(vec![a, None, b, None], common_data, verifier_data, config)
+                .unwrap();
+        let expected = [9, 0, 25, 0].map(F::from_canonical_u64);
+        assert_eq!(root.proof.public_inputs, expected);
+        root.circuit_data.verify(root.proof).unwrap();
+
+        // A level of padding only has no proof to verify in place of the padding.
+        let result = aggregate_leaves_to_tree(vec![None, None], common_data, verifier_data, config);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn merge_aggregated_proofs() {
+        let config = TreeAggregationConfig::new(2, 1);
+        let a = aggregate_squares(&[3, 4], config);
+        let b = aggregate_squares(&[5, 6], config);
+
+        let merged = merge(a, b).unwrap();
+        let expected = [9, 16, 25, 36].map(F::from_canonical_u64);
+        assert_eq!(merged.proof.public_inputs, expected);
+
+        // Merged proofs of the same shape can be merged again.
+        let c = aggregate_squares(&[7, 8], config);
+        let d = aggregate_squares(&[9, 10], config);
+        let other = merge(c, d).unwrap();
+        let root = merge(merged, other).unwrap();
+        assert_eq!(root.proof.public_inputs.len(), 8);
+        root.circuit_data.verify(root.proof).unwrap();
+    }
+
+    #[test]
+    fn merge_rejects_different_circuits() {
+        let a = aggregate_squares(&[3, 4], TreeAggregationConfig::new(2, 1));
+        let b = aggregate_squares(&[5, 6, 7, 8], TreeAggregationConfig::new(2, 2));
+
+        let result = merge(a, b);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn auto_config_fits_small_batches_into_one_level() {
+        for n_proofs in 1..=MAX_AUTO_TREE_BRANCHING_FACTOR {
+            let config = TreeAggregationConfig::auto_for(n_proofs);
+            assert_eq!(config.tree_depth, 1);
+            assert_eq!(config.num_leaf_proofs, n_proofs.max(2));
+        }
+    }
+
+    #[test]
+    fn auto_config_minimizes_padding_work() {
+        let config = TreeAggregationConfig::auto_for(20);
+        assert_eq!(config.tree_branching_factor, 5);
+        assert_eq!(config.tree_depth, 2);
+
+        for n_proofs in [9, 17, 64, 100, 1000] {
+            let config = TreeAggregationConfig::auto_for(n_proofs);
+            assert!(config.num_leaf_proofs >= n_proofs);
+        }
+    }
+
     #[test]
     fn pair_aggregation() {
         let proof1 = prove_square(F::from_canonical_u64(7));
@@ -217,6 +505,7 @@ mod tests {
 
         let aggregated = aggregate_chunk(
             &[proof1.proof, proof2.proof],
+            &[false, false],
             &proof1.circuit_data.common,
             &proof1.circuit_data.verifier_only,
         )
@@ -232,13 +521,12 @@ mod tests {
 
         let aggregated = aggregate_chunk(
             &[proof1.proof, proof2.proof],
+            &[false, false],
             &proof1.circuit_data.common,
             &proof1.circuit_data.verifier_only,
         )
         .unwrap();
 
-        println!("{:?}", aggregated.proof.public_inputs);
-
         assert_eq!(aggregated.proof.public_inputs.len(), 2);
     }
 }
@@ -0,0 +1,418 @@
+//! Aggregation across worker processes.
+//!
+//! The leaf level of the tree holds most of the aggregation work, so it is sharded across
+//! [`Worker`]s: local processes running `wormhole-aggregator --worker`, or remote machines
+//! running `wormhole-aggregator --listen <addr>`. Each worker aggregates one chunk of
+//! `tree_branching_factor` leaf proofs at a time, and the parent aggregates the upper levels
+//! itself.
+//!
+//! Workers are not trusted. The parent verifies every proof it receives against the leaf
+//! aggregation circuit, and checks that its public inputs are those of the chunk it sent.
+//!
+//! ## Protocol
+//!
+//! Messages are framed as a little endian `u32` length followed by that many bytes. A job holds
+//! the leaf circuit's common and verifier data and the chunk proofs, each as a length prefixed
+//! section after a version byte, and each proof is followed by a `1` byte if its slot is padding
+//! or a `0` byte otherwise. The worker replies with a `0` status byte and the aggregated
+//! proof, or a `1` status byte and an error message. A worker serves jobs until its input is
+//! closed.
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{anyhow, bail, Context};
+use plonky2::field::types::Field;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use plonky2::util::serialization::DefaultGateSerializer;
+use wormhole_verifier::ProofWithPublicInputs;
+use zk_circuits_common::circuit::{C, D, F};
+
+use crate::circuits::tree::{
+    aggregate_chunk, aggregate_from_level, chunk_circuit_data, fill_padding, padding_filler,
+    AggregatedProof, TreeAggregationConfig,
+};
+
+const JOB_VERSION: u8 = 2;
+const STATUS_OK: u8 = 0;
+const STATUS_ERR: u8 = 1;
+
+/// A chunk of leaf proofs to aggregate.
+pub struct ChunkJob {
+    pub common_data: CommonCircuitData<F, D>,
+    pub verifier_data: VerifierOnlyCircuitData<C, D>,
+    pub proofs: Vec<ProofWithPublicInputs<F, C, D>>,
+    /// Whether each slot of the chunk is padding, see
+    /// [`crate::circuits::tree::aggregate_leaves_to_tree`].
+    pub padding: Vec<bool>,
+}
+
+impl ChunkJob {
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let common_bytes = self
+            .common_data
+            .to_bytes(&DefaultGateSerializer)
+            .map_err(|e| anyhow!("Failed to serialize common data: {}", e))?;
+        let verifier_bytes = self
+            .verifier_data
+            .to_bytes()
+            .map_err(|e| anyhow!("Failed to serialize verifier data: {}", e))?;
+
+        let mut bytes = vec![JOB_VERSION];
+        write_section(&mut bytes, &common_bytes)?;
+        write_section(&mut bytes, &verifier_bytes)?;
+        if self.padding.len() != self.proofs.len() {
+            bail!(
+                "job has {} proofs but {} padding flags",
+                self.proofs.len(),
+                self.padding.len()
+            );
+        }
+        bytes.extend(u32::try_from(self.proofs.len())?.to_le_bytes());
+        for (proof, &is_padding) in self.proofs.iter().zip(&self.padding) {
+            write_section(&mut bytes, &proof.to_bytes())?;
+            bytes.push(u8::from(is_padding));
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let Some((&version, mut rest)) = bytes.split_first() else {
+            bail!("job is empty");
+        };
+        if version != JOB_VERSION {
+            bail!("unsupported job version {}, expected {}", version, JOB_VERSION);
+        }
+
+        let common_bytes = read_section(&mut rest)?.to_vec();
+        let common_data = CommonCircuitData::from_bytes(common_bytes, &DefaultGateSerializer)
+            .map_err(|e| anyhow!("Failed to deserialize common data: {}", e))?;
+        let verifier_bytes = read_section(&mut rest)?.to_vec();
+        let verifier_data = VerifierOnlyCircuitData::from_bytes(verifier_bytes)
+            .map_err(|e| anyhow!("Failed to deserialize verifier data: {}", e))?;
+
+        let num_proofs = read_u32(&mut rest)?;
+        let mut proofs = Vec::with_capacity(num_proofs as usize);
+        let mut padding = Vec::with_capacity(num_proofs as usize);
+        for _ in 0..num_proofs {
+            let proof_bytes = read_section(&mut rest)?.to_vec();
+            proofs.push(ProofWithPublicInputs::from_bytes(proof_bytes, &common_data)?);
+            let Some((&flag, tail)) = rest.split_first() else {
+                bail!("job is truncated");
+            };
+            padding.push(match flag {
+                0 => false,
+                1 => true,
+                _ => bail!("invalid padding flag {}", flag),
+            });
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            bail!("job has {} trailing bytes", rest.len());
+        }
+
+        Ok(Self {
+            common_data,
+            verifier_data,
+            proofs,
+            padding,
+        })
+    }
+
+    /// Aggregates the chunk, which is what a worker does with a job.
+    pub fn run(&self) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let _span = tracing::info_span!("chunk_job", num_proofs = self.proofs.len()).entered();
+        let proof = aggregate_chunk(
+            &self.proofs,
+            &self.padding,
+            &self.common_data,
+            &self.verifier_data,
+        )?;
+        Ok(proof.proof)
+    }
+}
+
+/// Serves jobs read from `reader` until it is closed, writing the replies to `writer`.
+///
+/// Jobs that fail are answered with an error message, so one bad job doesn't take the worker
+/// down. Errors reading or writing the streams are returned.
+pub fn serve<R: Read, W: Write>(reader: R, writer: W) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut writer = BufWriter::new(writer);
+    while let Some(job) = read_frame(&mut reader)? {
+        let result = ChunkJob::from_bytes(&job).and_then(|job| job.run());
+        let reply = match result {
+            Ok(proof) => [&[STATUS_OK][..], &proof.to_bytes()].concat(),
+            Err(e) => {
+                tracing::warn!(error = %format_args!("{:#}", e), "chunk job failed");
+                [&[STATUS_ERR][..], format!("{:#}", e).as_bytes()].concat()
+            }
+        };
+        write_frame(&mut writer, &reply)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Aggregates chunks on behalf of the parent.
+pub trait Worker: Send {
+    /// Sends a serialized [`ChunkJob`] and returns the serialized aggregated proof.
+    fn aggregate(&mut self, job: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+/// A worker process on this machine, talking over its stdin and stdout.
+pub struct ProcessWorker {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ProcessWorker {
+    /// Spawns `command`, which must serve jobs on its stdin and stdout, e.g.
+    /// `wormhole-aggregator --worker`.
+    pub fn spawn(command: &mut Command) -> anyhow::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to spawn aggregation worker")?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().context("worker has no stdout")?;
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+}
+
+impl Worker for ProcessWorker {
+    fn aggregate(&mut self, job: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let stdin = self.stdin.as_mut().context("worker has no stdin")?;
+        request(&mut self.stdout, stdin, job)
+    }
+}
+
+impl Drop for ProcessWorker {
+    fn drop(&mut self) {
+        // Closing stdin ends the worker's job loop.
+        drop(self.stdin.take());
+        if let Err(e) = self.child.wait() {
+            tracing::warn!(error = %e, "failed to wait for aggregation worker");
+        }
+    }
+}
+
+/// A worker on another machine, reached over TCP.
+pub struct TcpWorker {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl TcpWorker {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> anyhow::Result<Self> {
+        let writer = TcpStream::connect(addr).context("Failed to connect to aggregation worker")?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+}
+
+impl Worker for TcpWorker {
+    fn aggregate(&mut self, job: &[u8]) -> anyhow::Result<Vec<u8>> {
+        request(&mut self.reader, &mut self.writer, job)
+    }
+}
+
+/// Like [`crate::circuits::tree::aggregate_to_tree`], but aggregates the leaf level on
+/// `workers`, see the [module documentation](self).
+///
+/// # Errors
+///
+/// Returns an error if `workers` is empty, a worker fails or returns a proof that doesn't
+/// aggregate the chunk it was sent, or aggregating the upper levels fails.
+pub fn aggregate_to_tree_distributed(
+    leaf_proofs: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
+    common_data: &CommonCircuitData<F, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    config: TreeAggregationConfig,
+    workers: &mut [Box<dyn Worker>],
+) -> anyhow::Result<AggregatedProof<F, C, D>> {
+    if workers.is_empty() {
+        bail!("distributed aggregation needs at least one worker");
+    }
+    let _span = tracing::info_span!(
+        "aggregate_to_tree_distributed",
+        num_leaf_proofs = leaf_proofs.len(),
+        num_workers = workers.len()
+    )
+    .entered();
+
+    let filler = padding_filler(&leaf_proofs)?;
+    let chunks = leaf_proofs
+        .chunks(config.tree_branching_factor)
+        .map(|chunk| fill_padding(chunk, filler))
+        .collect::<Vec<_>>();
+    if let Some(index) = chunks
+        .iter()
+        .position(|(proofs, _)| proofs.len() != config.tree_branching_factor)
+    {
+        bail!("chunk {} is not full, pad the leaf proofs to the tree size", index);
+    }
+    let jobs = chunks
+        .iter()
+        .map(|(proofs, padding)| {
+            ChunkJob {
+                common_data: common_data.clone(),
+                verifier_data: verifier_data.clone(),
+                proofs: proofs.clone(),
+                padding: padding.clone(),
+            }
+            .to_bytes()
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let replies = dispatch(&jobs, workers)?;
+
+    // Every full chunk is aggregated by the same circuit, which the parent builds to check the
+    // workers' proofs.
+    let circuit_data = chunk_circuit_data(config.tree_branching_factor, common_data);
+    let mut level_proofs = Vec::with_capacity(replies.len());
+    for (index, (reply, (chunk, padding))) in replies.into_iter().zip(&chunks).enumerate() {
+        let proof = ProofWithPublicInputs::from_bytes(reply, &circuit_data.common)
+            .with_context(|| format!("worker returned an invalid proof for chunk {}", index))?;
+        circuit_data
+            .verify(proof.clone())
+            .with_context(|| format!("worker proof for chunk {} failed verification", index))?;
+        let expected = chunk
+            .iter()
+            .zip(padding)
+            .flat_map(|(proof, &is_padding)| {
+                proof
+                    .public_inputs
+                    .iter()
+                    .map(move |&input| if is_padding { F::ZERO } else { input })
+            })
+            .collect::<Vec<_>>();
+        if proof.public_inputs != expected {
+            bail!("worker proof for chunk {} aggregates other proofs", index);
+        }
+        level_proofs.push(proof);
+    }
+
+    if level_proofs.len() == 1 {
+        return Ok(AggregatedProof {
+            proof: level_proofs.pop().unwrap(),
+            circuit_data,
+        });
+    }
+    aggregate_from_level(
+        level_proofs.into_iter().map(Some).collect(),
+        &circuit_data.common,
+        &circuit_data.verifier_only,
+        config,
+        1,
+    )
+}
+
+/// Sends `jobs` to `workers`, each taking the next job once it is done with its last one, and
+/// returns the replies in job order.
+fn dispatch(jobs: &[Vec<u8>], workers: &mut [Box<dyn Worker>]) -> anyhow::Result<Vec<Vec<u8>>> {
+    let queue = Mutex::new(jobs.iter().enumerate());
+    let replies = Mutex::new(vec![None; jobs.len()]);
+
+    thread::scope(|scope| {
+        let handles = workers
+            .iter_mut()
+            .map(|worker| {
+                let queue = &queue;
+                let replies = &replies;
+                scope.spawn(move || -> anyhow::Result<()> {
+                    loop {
+                        let Some((index, job)) = queue.lock().unwrap().next() else {
+                            return Ok(());
+                        };
+                        let reply = worker
+                            .aggregate(job)
+                            .with_context(|| format!("worker failed on chunk {}", index))?;
+                        replies.lock().unwrap()[index] = Some(reply);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().map_err(|_| anyhow!("worker thread panicked"))?)
+    })?;
+
+    replies
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .enumerate()
+        .map(|(index, reply)| reply.ok_or_else(|| anyhow!("no worker took chunk {}", index)))
+        .collect()
+}
+
+fn request<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    job: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    write_frame(writer, job)?;
+    writer.flush()?;
+    let reply = read_frame(reader)?.context("worker closed the connection")?;
+    match reply.split_first() {
+        Some((&STATUS_OK, proof)) => Ok(proof.to_vec()),
+        Some((&STATUS_ERR, message)) => {
+            bail!("worker error: {}", String::from_utf8_lossy(message))
+        }
+        _ => bail!("worker sent a malformed reply"),
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, bytes: &[u8]) -> anyhow::Result<()> {
+    writer.write_all(&u32::try_from(bytes.len())?.to_le_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Reads a frame, or `None` if the stream ends before one starts.
+fn read_frame<R: Read>(reader: &mut R) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+fn write_section(bytes: &mut Vec<u8>, section: &[u8]) -> anyhow::Result<()> {
+    bytes.extend(u32::try_from(section.len())?.to_le_bytes());
+    bytes.extend(section);
+    Ok(())
+}
+
+fn read_u32(bytes: &mut &[u8]) -> anyhow::Result<u32> {
+    if bytes.len() < 4 {
+        bail!("job is truncated");
+    }
+    let (value, rest) = bytes.split_at(4);
+    *bytes = rest;
+    Ok(u32::from_le_bytes(value.try_into()?))
+}
+
+fn read_section<'a>(bytes: &mut &'a [u8]) -> anyhow::Result<&'a [u8]> {
+    let len = read_u32(bytes)? as usize;
+    if bytes.len() < len {
+        bail!("job is truncated");
+    }
+    let (section, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(section)
+}
@@ -0,0 +1,222 @@
+//! Limits on the proofs the aggregation service takes in.
+//!
+//! The service only deserializes and verifies a proof once it picks it up, so an endpoint
+//! feeding it submissions could otherwise be made to hold any amount of garbage in memory.
+//! [`ProofIntake`] checks every submission against [`IntakeLimits`] before queueing it, and
+//! rejects it with an [`IntakeRejection`] if it is larger than a leaf proof can be, too many
+//! submissions are already waiting, or its source already has its quota of submissions waiting.
+//!
+//! Quotas are kept by the [`IncomingProof::source_id`] the caller supplies, e.g. an API key or
+//! peer address. A submission stops counting towards the queue and its source's quota once the
+//! service picks it up, whether or not it then verifies.
+use core::fmt;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, FailureStage};
+use crate::service::IncomingProof;
+
+/// The default of [`IntakeLimits::max_proof_bytes`], well above the size of a leaf proof.
+pub const DEFAULT_MAX_PROOF_BYTES: usize = 1 << 20;
+
+/// The default of [`IntakeLimits::max_queue_depth`].
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 1024;
+
+/// What a [`ProofIntake`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntakeLimits {
+    /// Submissions of more bytes are rejected without being decoded.
+    pub max_proof_bytes: usize,
+    /// How many submissions can wait for the service at once. A depth of 0 is treated as 1.
+    pub max_queue_depth: usize,
+    /// How many submissions of one source can wait for the service at once, or `None` for no
+    /// quota. Submissions without a source id only count towards the queue depth.
+    pub max_proofs_per_source: Option<usize>,
+}
+
+impl Default for IntakeLimits {
+    fn default() -> Self {
+        Self {
+            max_proof_bytes: DEFAULT_MAX_PROOF_BYTES,
+            max_queue_depth: DEFAULT_MAX_QUEUE_DEPTH,
+            max_proofs_per_source: None,
+        }
+    }
+}
+
+impl IntakeLimits {
+    /// Checks that a submission of `size` bytes isn't too large.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IntakeRejection::TooLarge`] if `size` exceeds [`Self::max_proof_bytes`].
+    pub fn check_size(&self, size: usize) -> Result<(), IntakeRejection> {
+        if size > self.max_proof_bytes {
+            return Err(IntakeRejection::TooLarge {
+                size,
+                max: self.max_proof_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why a [`ProofIntake`] didn't queue a submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntakeRejection {
+    /// The submission has more than `max` bytes.
+    TooLarge { size: usize, max: usize },
+    /// `depth` submissions are already waiting for the service.
+    QueueFull { depth: usize },
+    /// The source already has `quota` submissions waiting for the service.
+    QuotaExceeded { source_id: String, quota: usize },
+    /// The service has shut down.
+    Closed,
+}
+
+impl fmt::Display for IntakeRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { size, max } => write!(
+                f,
+                "proof of {} bytes exceeds the limit of {} bytes",
+                size, max
+            ),
+            Self::QueueFull { depth } => {
+                write!(f, "{} proofs are already waiting to be aggregated", depth)
+            }
+            Self::QuotaExceeded { source_id, quota } => write!(
+                f,
+                "source {} already has {} proofs waiting to be aggregated",
+                source_id, quota
+            ),
+            Self::Closed => write!(f, "the aggregation service has shut down"),
+        }
+    }
+}
+
+impl std::error::Error for IntakeRejection {}
+
+/// The number of queued submissions of each source.
+type QueuedBySource = Arc<Mutex<HashMap<String, usize>>>;
+
+fn lock(queued: &QueuedBySource) -> MutexGuard<'_, HashMap<String, usize>> {
+    // The counts are updated in single statements, so they are consistent even after a panic.
+    queued
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Queues submissions for an [`crate::service::AggregationService`] within [`IntakeLimits`].
+/// Clones share the queue and the quotas.
+#[derive(Debug, Clone)]
+pub struct ProofIntake {
+    limits: IntakeLimits,
+    tx: SyncSender<IncomingProof>,
+    queued: QueuedBySource,
+}
+
+impl ProofIntake {
+    /// Creates an intake and the receiver to pass to [`crate::service::AggregationService::run`].
+    pub fn new(limits: IntakeLimits) -> (Self, IntakeReceiver) {
+        let (tx, rx) = mpsc::sync_channel(limits.max_queue_depth.max(1));
+        let queued = QueuedBySource::default();
+        let receiver = IntakeReceiver {
+            rx,
+            queued: Some(queued.clone()),
+        };
+        (Self { limits, tx, queued }, receiver)
+    }
+
+    pub fn limits(&self) -> IntakeLimits {
+        self.limits
+    }
+
+    /// Queues `proof` for the service, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IntakeRejection`] if the proof is too large, the queue is full, its source
+    /// has no quota left or the service has shut down. Rejected proofs are dropped.
+    pub fn submit(&self, proof: IncomingProof) -> Result<(), IntakeRejection> {
+        let result = self.try_submit(proof);
+        #[cfg(feature = "metrics")]
+        if result.is_err() {
+            metrics::record_failure(FailureStage::Intake);
+        }
+        result
+    }
+
+    fn try_submit(&self, proof: IncomingProof) -> Result<(), IntakeRejection> {
+        self.limits.check_size(proof.bytes.len())?;
+
+        // Hold the lock until the count is updated, so the receiver can't release the proof
+        // before it was counted.
+        let mut queued = lock(&self.queued);
+        let source_id = proof.source_id.clone();
+        if let (Some(source_id), Some(quota)) = (&source_id, self.limits.max_proofs_per_source) {
+            if queued.get(source_id).copied().unwrap_or(0) >= quota {
+                return Err(IntakeRejection::QuotaExceeded {
+                    source_id: source_id.clone(),
+                    quota,
+                });
+            }
+        }
+
+        match self.tx.try_send(proof) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                return Err(IntakeRejection::QueueFull {
+                    depth: self.limits.max_queue_depth,
+                })
+            }
+            Err(TrySendError::Disconnected(_)) => return Err(IntakeRejection::Closed),
+        }
+        if let Some(source_id) = source_id {
+            *queued.entry(source_id).or_default() += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Queues `proof` for the service, waiting while the queue is full. Only the size limit
+    /// applies, so this is meant for trusted local sources like [`crate::service::ProofSource`].
+    pub(crate) fn submit_blocking(&self, proof: IncomingProof) -> Result<(), IntakeRejection> {
+        self.limits.check_size(proof.bytes.len())?;
+        self.tx.send(proof).map_err(|_| IntakeRejection::Closed)
+    }
+}
+
+/// The service end of a [`ProofIntake`], or of a plain channel without limits.
+#[derive(Debug)]
+pub struct IntakeReceiver {
+    rx: Receiver<IncomingProof>,
+    queued: Option<QueuedBySource>,
+}
+
+impl IntakeReceiver {
+    /// Waits up to `timeout` for the next submission, releasing its place in the quota of its
+    /// source.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<IncomingProof, RecvTimeoutError> {
+        let proof = self.rx.recv_timeout(timeout)?;
+        if let (Some(queued), Some(source_id)) = (&self.queued, &proof.source_id) {
+            let mut queued = lock(queued);
+            if let Some(count) = queued.get_mut(source_id) {
+                *count -= 1;
+                if *count == 0 {
+                    queued.remove(source_id);
+                }
+            }
+        }
+        Ok(proof)
+    }
+}
+
+impl From<Receiver<IncomingProof>> for IntakeReceiver {
+    fn from(rx: Receiver<IncomingProof>) -> Self {
+        Self { rx, queued: None }
+    }
+}
@@ -1,3 +1,12 @@
 pub mod aggregator;
 pub mod circuits;
+#[cfg(feature = "distributed")]
+pub mod distributed;
+pub mod intake;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod prelude;
+pub mod service;
+pub mod soak;
 mod util;
+pub mod verification_tables;
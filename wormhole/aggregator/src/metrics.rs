@@ -0,0 +1,181 @@
+//! Aggregation metrics in the Prometheus text format, enabled with the `metrics` feature.
+//!
+//! The aggregator records how many proofs were pushed, how long each tree level took to
+//! aggregate, how many proofs or batches failed and how many proofs are waiting in the buffer.
+//! [`gather`] renders them for a scrape endpoint, e.g. one served next to the aggregation service.
+//!
+//! Like the registries of Prometheus client libraries, the metrics are process-wide, so every
+//! aggregator in a process adds to the same values.
+use core::fmt::Write;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The upper bounds in seconds of the buckets of the level duration histogram.
+pub const LEVEL_DURATION_BUCKETS: [f64; 11] = [
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0,
+];
+
+/// Where in the pipeline a proof or batch failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureStage {
+    /// A leaf proof couldn't be deserialized or didn't verify.
+    Verify,
+    /// A leaf proof was rejected by the buffer, e.g. because it was full.
+    Push,
+    /// Aggregating a batch returned an error.
+    Aggregate,
+    /// A submission was rejected by a [`crate::intake::ProofIntake`] before being queued.
+    Intake,
+}
+
+impl FailureStage {
+    const ALL: [Self; 4] = [Self::Verify, Self::Push, Self::Aggregate, Self::Intake];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Verify => "verify",
+            Self::Push => "push",
+            Self::Aggregate => "aggregate",
+            Self::Intake => "intake",
+        }
+    }
+}
+
+/// A histogram with the buckets of [`LEVEL_DURATION_BUCKETS`].
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// The number of observations in each bucket, not cumulative.
+    buckets: [u64; LEVEL_DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = LEVEL_DURATION_BUCKETS.iter().position(|&le| value <= le) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+static PROOFS_PUSHED: AtomicU64 = AtomicU64::new(0);
+static FAILURES: [AtomicU64; 4] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+static QUEUE_DEPTH: AtomicU64 = AtomicU64::new(0);
+static LEVEL_DURATIONS: Mutex<BTreeMap<usize, Histogram>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn record_proof_pushed() {
+    PROOFS_PUSHED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_failure(stage: FailureStage) {
+    FAILURES[stage as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn set_queue_depth(depth: usize) {
+    QUEUE_DEPTH.store(depth as u64, Ordering::Relaxed);
+}
+
+pub(crate) fn observe_level_duration(level: usize, duration: Duration) {
+    // A panic while holding the lock leaves the histograms consistent, so keep recording.
+    let mut durations = LEVEL_DURATIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    durations
+        .entry(level)
+        .or_default()
+        .observe(duration.as_secs_f64());
+}
+
+/// Renders every metric in the Prometheus text exposition format.
+pub fn gather() -> String {
+    let mut out = String::new();
+    // Writing to a `String` can't fail.
+    let _ = write_metrics(&mut out);
+    out
+}
+
+fn write_metrics(out: &mut String) -> core::fmt::Result {
+    write_header(
+        out,
+        "wormhole_aggregator_proofs_pushed_total",
+        "counter",
+        "Leaf proofs added to the aggregation buffer.",
+    )?;
+    let pushed = PROOFS_PUSHED.load(Ordering::Relaxed);
+    writeln!(out, "wormhole_aggregator_proofs_pushed_total {}", pushed)?;
+
+    write_header(
+        out,
+        "wormhole_aggregator_failures_total",
+        "counter",
+        "Leaf proofs or batches that failed, by pipeline stage.",
+    )?;
+    for stage in FailureStage::ALL {
+        let failures = FAILURES[stage as usize].load(Ordering::Relaxed);
+        writeln!(
+            out,
+            "wormhole_aggregator_failures_total{{stage=\"{}\"}} {}",
+            stage.label(),
+            failures
+        )?;
+    }
+
+    write_header(
+        out,
+        "wormhole_aggregator_queue_depth",
+        "gauge",
+        "Leaf proofs waiting in the aggregation buffer.",
+    )?;
+    let depth = QUEUE_DEPTH.load(Ordering::Relaxed);
+    writeln!(out, "wormhole_aggregator_queue_depth {}", depth)?;
+
+    write_header(
+        out,
+        "wormhole_aggregator_level_duration_seconds",
+        "histogram",
+        "Time taken to aggregate one level of the proof tree, by level.",
+    )?;
+    let durations = LEVEL_DURATIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    for (level, histogram) in durations {
+        let name = "wormhole_aggregator_level_duration_seconds";
+        let mut cumulative = 0;
+        for (le, count) in LEVEL_DURATION_BUCKETS.iter().zip(histogram.buckets) {
+            cumulative += count;
+            writeln!(
+                out,
+                "{}_bucket{{level=\"{}\",le=\"{}\"}} {}",
+                name, level, le, cumulative
+            )?;
+        }
+        writeln!(
+            out,
+            "{}_bucket{{level=\"{}\",le=\"+Inf\"}} {}",
+            name, level, histogram.count
+        )?;
+        writeln!(out, "{}_sum{{level=\"{}\"}} {}", name, level, histogram.sum)?;
+        writeln!(
+            out,
+            "{}_count{{level=\"{}\"}} {}",
+            name, level, histogram.count
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) -> core::fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind)
+}
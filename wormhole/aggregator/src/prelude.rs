@@ -0,0 +1,6 @@
+//! Everything needed to aggregate wormhole proofs, including the plonky2 types of
+//! [`zk_circuits_common::prelude`].
+pub use zk_circuits_common::prelude::*;
+
+pub use crate::aggregator::{AggregationReport, DuplicatePolicy, WormholeProofAggregator};
+pub use crate::circuits::tree::{AggregatedProof, TreeAggregationConfig};
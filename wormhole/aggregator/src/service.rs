@@ -0,0 +1,532 @@
+//! Long-running aggregation service.
+//!
+//! Leaf proofs are read from a [`ProofSource`] or submitted through a [`ProofIntake`] within its
+//! [`IntakeLimits`], validated against the leaf circuit and buffered until either
+//! [`BatchPolicy::max_proofs`] proofs have been collected or [`BatchPolicy::max_wait`] has elapsed
+//! since the first proof of the batch arrived. Every aggregated proof is written to the output
+//! directory and handed to a [`SubmitHook`].
+//!
+//! A failure to aggregate a batch or to submit an aggregated proof doesn't stop the service. A
+//! batch that fails to aggregate is retried once it is due again, and after
+//! [`MAX_BATCH_ATTEMPTS`] failures its leaf proofs are written to the `failed` subdirectory of the
+//! output directory. Proofs the hook failed on are submitted again with the next batch. While
+//! [`MAX_UNSUBMITTED`] proofs are waiting to be submitted again, the service stops taking proofs,
+//! so its intake queue fills up and the sources wait.
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use wormhole_circuit::hex_util;
+use wormhole_verifier::ProofWithPublicInputs;
+use zk_circuits_common::circuit::{C, D, F};
+
+use crate::aggregator::WormholeProofAggregator;
+use crate::circuits::tree::AggregatedProof;
+use crate::intake::{IntakeLimits, IntakeReceiver, IntakeRejection, ProofIntake};
+
+/// A proof as received from a [`ProofSource`] or [`ProofIntake`], before any validation.
+#[derive(Debug, Clone)]
+pub struct IncomingProof {
+    /// Where the proof came from (file path or stdin line), used for error reporting.
+    pub origin: String,
+    pub bytes: Vec<u8>,
+    /// The id of the submitter, e.g. an API key or peer address, whose quota the proof counts
+    /// towards in a [`ProofIntake`]. Supplied by the caller and not authenticated.
+    pub source_id: Option<String>,
+}
+
+/// Where the service reads leaf proofs from.
+#[derive(Debug, Clone)]
+pub enum ProofSource {
+    /// Polls a directory for new proof files. Files with a `.hex` extension are hex decoded, all
+    /// other files are read as raw proof bytes.
+    ///
+    /// Files with the [`TEMP_FILE_EXTENSION`] are ignored, so writers should write a proof to a
+    /// temporary file and rename it once it is complete. Files are moved to the
+    /// [`PROCESSED_DIR`] subdirectory once they were handed to the service, or to the
+    /// [`REJECTED_DIR`] subdirectory if they are not valid submissions. Files that can't be read
+    /// are retried on the next poll. The source stops if it can't move a file.
+    Directory {
+        path: PathBuf,
+        poll_interval: Duration,
+    },
+    /// Reads one hex encoded proof per line from stdin.
+    Stdin,
+}
+
+impl ProofSource {
+    /// Spawns a thread feeding proofs from this source into the returned receiver, within the
+    /// default [`IntakeLimits`]. The receiver is closed once the source is exhausted (EOF on
+    /// stdin) or fails.
+    pub fn spawn(self) -> IntakeReceiver {
+        self.spawn_with_limits(IntakeLimits::default())
+    }
+
+    /// Like [`Self::spawn`], but within `limits`. Proofs that are too large are skipped, and the
+    /// source waits while the queue is full. Proofs of local sources have no source id, so the
+    /// per-source quota doesn't apply.
+    pub fn spawn_with_limits(self, limits: IntakeLimits) -> IntakeReceiver {
+        let (intake, rx) = ProofIntake::new(limits);
+        thread::spawn(move || {
+            let result = match self {
+                ProofSource::Directory {
+                    path,
+                    poll_interval,
+                } => watch_directory(&path, poll_interval, &intake),
+                ProofSource::Stdin => read_stdin(&intake),
+            };
+            if let Err(e) = result {
+                tracing::error!(error = %format_args!("{:#}", e), "proof source stopped");
+            }
+        });
+        rx
+    }
+}
+
+/// The extension of proof files that are still being written, which [`ProofSource::Directory`]
+/// ignores.
+pub const TEMP_FILE_EXTENSION: &str = "tmp";
+/// The subdirectory of a watched directory that proof files are moved to once they were handed to
+/// the service.
+pub const PROCESSED_DIR: &str = "processed";
+/// The subdirectory of a watched directory that files which are not valid submissions are moved
+/// to.
+pub const REJECTED_DIR: &str = "rejected";
+
+fn watch_directory(
+    path: &Path,
+    poll_interval: Duration,
+    intake: &ProofIntake,
+) -> anyhow::Result<()> {
+    // Hex encoding doubles the size, and the file may end with a line break.
+    let max_file_bytes = intake.limits().max_proof_bytes.saturating_mul(2) + 2;
+    let processed_dir = path.join(PROCESSED_DIR);
+    let rejected_dir = path.join(REJECTED_DIR);
+    loop {
+        // Handled files are moved out of the directory, so every file left in it is new.
+        let mut new_files = fs::read_dir(path)
+            .with_context(|| format!("failed to read proof directory {:?}", path))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| {
+                file.is_file()
+                    && !file
+                        .extension()
+                        .is_some_and(|ext| ext == TEMP_FILE_EXTENSION)
+            })
+            .collect::<Vec<_>>();
+        // Process files in a stable order so that batches are reproducible.
+        new_files.sort();
+
+        for file in new_files {
+            let file_bytes = fs::metadata(&file).map_or(0, |metadata| metadata.len());
+            if file_bytes > max_file_bytes as u64 {
+                tracing::warn!(?file, file_bytes, "rejecting oversized proof file");
+                move_file(&file, &rejected_dir)?;
+                continue;
+            }
+            let contents = match fs::read(&file) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    tracing::warn!(?file, error = %e, "failed to read proof file, retrying");
+                    continue;
+                }
+            };
+            let bytes = match decode_proof_file(&file, contents) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    tracing::warn!(?file, error = %format_args!("{:#}", e), "rejecting proof file");
+                    move_file(&file, &rejected_dir)?;
+                    continue;
+                }
+            };
+            let incoming = IncomingProof {
+                origin: file.display().to_string(),
+                bytes,
+                source_id: None,
+            };
+            match intake.submit_blocking(incoming) {
+                Ok(()) => move_file(&file, &processed_dir)?,
+                // The service has shut down.
+                Err(IntakeRejection::Closed) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!(?file, error = %e, "rejecting proof file");
+                    move_file(&file, &rejected_dir)?;
+                }
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+/// Moves `file` into `dir`, so the directory source doesn't pick it up again.
+fn move_file(file: &Path, dir: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create directory {:?}", dir))?;
+    let name = file
+        .file_name()
+        .with_context(|| format!("{:?} has no file name", file))?;
+    fs::rename(file, dir.join(name))
+        .with_context(|| format!("failed to move {:?} to {:?}", file, dir))
+}
+
+fn read_stdin(intake: &ProofIntake) -> anyhow::Result<()> {
+    for (i, line) in io::stdin().lock().lines().enumerate() {
+        let line = line.context("failed to read from stdin")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let origin = format!("stdin:{}", i + 1);
+        let bytes = match hex_util::decode(line) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!(%origin, error = %e, "skipping proof with invalid hex");
+                continue;
+            }
+        };
+        let incoming = IncomingProof {
+            origin: origin.clone(),
+            bytes,
+            source_id: None,
+        };
+        match intake.submit_blocking(incoming) {
+            Ok(()) => {}
+            Err(IntakeRejection::Closed) => return Ok(()),
+            Err(e) => tracing::warn!(%origin, error = %e, "skipping proof"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a proof file, hex decoding it if it has a `.hex` extension.
+pub fn read_proof_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let contents = fs::read(path).with_context(|| format!("failed to read {:?}", path))?;
+    decode_proof_file(path, contents)
+}
+
+/// Decodes the contents of the proof file at `path`, see [`read_proof_file`].
+fn decode_proof_file(path: &Path, contents: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    if path.extension().is_some_and(|ext| ext == "hex") {
+        let text = core::str::from_utf8(&contents).context("hex proof file is not UTF-8")?;
+        return hex_util::decode(text.trim())
+            .map_err(|e| anyhow!("invalid hex in {:?}: {}", path, e));
+    }
+
+    Ok(contents)
+}
+
+/// Invoked with every aggregated proof produced by the service.
+pub trait SubmitHook {
+    fn submit(
+        &mut self,
+        proof_path: &Path,
+        proof: &AggregatedProof<F, C, D>,
+    ) -> anyhow::Result<()>;
+}
+
+/// A [`SubmitHook`] that runs a shell command. The path of the aggregated proof file is passed
+/// through the `AGGREGATED_PROOF_PATH` environment variable, so a webhook can be driven with e.g.
+/// `curl --data-binary @"$AGGREGATED_PROOF_PATH" https://...`.
+#[derive(Debug, Clone)]
+pub struct CommandHook {
+    pub command: String,
+}
+
+impl SubmitHook for CommandHook {
+    fn submit(
+        &mut self,
+        proof_path: &Path,
+        proof: &AggregatedProof<F, C, D>,
+    ) -> anyhow::Result<()> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("AGGREGATED_PROOF_PATH", proof_path)
+            .env(
+                "AGGREGATED_PROOF_NUM_PUBLIC_INPUTS",
+                proof.proof.public_inputs.len().to_string(),
+            )
+            .status()
+            .with_context(|| format!("failed to run submit command `{}`", self.command))?;
+
+        if !status.success() {
+            bail!("submit command `{}` exited with {}", self.command, status);
+        }
+
+        Ok(())
+    }
+}
+
+/// How often the service tries to aggregate a batch before it gives up on it.
+pub const MAX_BATCH_ATTEMPTS: usize = 3;
+/// The number of aggregated proofs waiting to be submitted again at which the service stops
+/// taking proofs. The batch being collected can still add one more.
+pub const MAX_UNSUBMITTED: usize = 16;
+
+/// Controls when a batch of buffered proofs gets aggregated.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchPolicy {
+    /// Aggregate as soon as this many proofs are buffered.
+    pub max_proofs: usize,
+    /// Aggregate once this much time has passed since the first proof of the batch arrived.
+    pub max_wait: Duration,
+}
+
+/// Drives a [`WormholeProofAggregator`] from a stream of incoming proofs.
+pub struct AggregationService {
+    aggregator: WormholeProofAggregator,
+    policy: BatchPolicy,
+    output_dir: PathBuf,
+    hook: Option<Box<dyn SubmitHook>>,
+    pending: usize,
+    batch_started: Option<Instant>,
+    batches_submitted: usize,
+    /// The number of times the current batch failed to aggregate.
+    failed_attempts: usize,
+    batches_failed: usize,
+    /// Saved aggregated proofs the submit hook failed on.
+    unsubmitted: Vec<(PathBuf, AggregatedProof<F, C, D>)>,
+}
+
+impl AggregationService {
+    pub fn new(
+        aggregator: WormholeProofAggregator,
+        policy: BatchPolicy,
+        output_dir: impl Into<PathBuf>,
+        hook: Option<Box<dyn SubmitHook>>,
+    ) -> anyhow::Result<Self> {
+        if policy.max_proofs == 0 || policy.max_proofs > aggregator.config.num_leaf_proofs {
+            bail!(
+                "batch size must be between 1 and the number of tree leaves ({}), got: {}",
+                aggregator.config.num_leaf_proofs,
+                policy.max_proofs
+            );
+        }
+
+        let output_dir = output_dir.into();
+        fs::create_dir_all(&output_dir)
+            .with_context(|| format!("failed to create output directory {:?}", output_dir))?;
+
+        Ok(Self {
+            aggregator,
+            policy,
+            output_dir,
+            hook,
+            pending: 0,
+            batch_started: None,
+            batches_submitted: 0,
+            failed_attempts: 0,
+            batches_failed: 0,
+            unsubmitted: Vec::new(),
+        })
+    }
+
+    /// The number of saved aggregated proofs the submit hook failed on, which are submitted again
+    /// with the next batch.
+    pub fn num_unsubmitted(&self) -> usize {
+        self.unsubmitted.len()
+    }
+
+    /// Runs until the receiver is closed, aggregating any remaining proofs before returning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the remaining proofs fail to aggregate or submit. Failures while
+    /// running are logged and retried, see the [module docs](self).
+    pub fn run(&mut self, proofs: impl Into<IntakeReceiver>) -> anyhow::Result<()> {
+        let proofs = proofs.into();
+        loop {
+            if self.unsubmitted.len() >= MAX_UNSUBMITTED {
+                // Don't aggregate more proofs the hook can't take. The intake queue fills up
+                // meanwhile, and the sources wait for it.
+                thread::sleep(self.policy.max_wait);
+                if let Err(e) = self.retry_submissions() {
+                    tracing::error!(
+                        error = %format_args!("{:#}", e),
+                        num_unsubmitted = self.unsubmitted.len(),
+                        "failed to submit aggregated proofs"
+                    );
+                }
+                continue;
+            }
+
+            let timeout = match self.batch_started {
+                Some(started) => self.policy.max_wait.saturating_sub(started.elapsed()),
+                None => self.policy.max_wait,
+            };
+
+            match proofs.recv_timeout(timeout) {
+                Ok(incoming) => {
+                    let origin = incoming.origin.clone();
+                    if let Err(e) = self.accept(incoming) {
+                        tracing::warn!(%origin, error = %format_args!("{:#}", e), "rejected proof");
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return self.flush(),
+            }
+
+            if self.batch_is_due() {
+                if let Err(e) = self.flush() {
+                    tracing::error!(error = %format_args!("{:#}", e), "failed to flush batch");
+                }
+            }
+        }
+    }
+
+    /// Deserializes and verifies a leaf proof, adding it to the current batch if valid.
+    pub fn accept(&mut self, incoming: IncomingProof) -> anyhow::Result<()> {
+        let leaf_circuit_data = &self.aggregator.leaf_circuit_data;
+        let verified = ProofWithPublicInputs::from_bytes(incoming.bytes, &leaf_circuit_data.common)
+            .context("failed to deserialize proof")
+            .and_then(|proof| {
+                leaf_circuit_data
+                    .verify(proof.clone())
+                    .context("proof verification failed")?;
+                Ok(proof)
+            });
+        #[cfg(feature = "metrics")]
+        if verified.is_err() {
+            crate::metrics::record_failure(crate::metrics::FailureStage::Verify);
+        }
+        let proof = verified?;
+
+        self.aggregator.push_proof(proof)?;
+        // Skipped duplicates don't count towards the batch.
+        self.pending = self.aggregator.proofs_buffer.as_ref().map_or(0, Vec::len);
+        if self.pending > 0 {
+            self.batch_started.get_or_insert_with(Instant::now);
+        }
+
+        Ok(())
+    }
+
+    fn batch_is_due(&self) -> bool {
+        match self.batch_started {
+            // A batch that failed to aggregate is retried after the wait, not as soon as it's full.
+            Some(started) => {
+                (self.pending >= self.policy.max_proofs && self.failed_attempts == 0)
+                    || started.elapsed() >= self.policy.max_wait
+            }
+            None => false,
+        }
+    }
+
+    /// Submits the proofs the hook failed on before, then aggregates the current batch, writes it
+    /// to the output directory and calls the submit hook.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a submission or the aggregation fails. A batch that fails to aggregate
+    /// is kept for the next flush, and a proof the hook fails on is submitted again by it.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        let retried = self.retry_submissions();
+        if self.pending > 0 {
+            self.aggregate_batch()?;
+        }
+        retried
+    }
+
+    fn aggregate_batch(&mut self) -> anyhow::Result<()> {
+        // The aggregator drains its buffer even if aggregation fails, so keep the batch to retry.
+        let batch = self.aggregator.proofs_buffer.clone().unwrap_or_default();
+        self.pending = 0;
+        self.batch_started = None;
+        let (proof_path, aggregated_proof) = match self.aggregate_and_save() {
+            Ok(saved) => saved,
+            Err(e) => {
+                self.keep_failed_batch(batch)?;
+                return Err(e);
+            }
+        };
+        self.failed_attempts = 0;
+
+        self.submit(proof_path, aggregated_proof)
+    }
+
+    fn aggregate_and_save(&mut self) -> anyhow::Result<(PathBuf, AggregatedProof<F, C, D>)> {
+        let aggregated_proof = self.aggregator.aggregate()?;
+
+        let proof_path = self
+            .output_dir
+            .join(format!("aggregated_{:06}.bin", self.batches_submitted));
+        fs::write(&proof_path, aggregated_proof.proof.to_bytes())
+            .with_context(|| format!("failed to write aggregated proof to {:?}", proof_path))?;
+        self.batches_submitted += 1;
+        tracing::info!(
+            path = %proof_path.display(),
+            duplicates_seen = self.aggregator.duplicates_seen(),
+            "aggregated proof saved"
+        );
+
+        Ok((proof_path, aggregated_proof))
+    }
+
+    /// Puts a batch that failed to aggregate back into the buffer, or writes its proofs to the
+    /// `failed` subdirectory once it failed [`MAX_BATCH_ATTEMPTS`] times.
+    fn keep_failed_batch(
+        &mut self,
+        batch: Vec<ProofWithPublicInputs<F, C, D>>,
+    ) -> anyhow::Result<()> {
+        self.failed_attempts += 1;
+        if self.failed_attempts < MAX_BATCH_ATTEMPTS {
+            self.pending = batch.len();
+            self.aggregator.proofs_buffer = Some(batch);
+            self.batch_started = Some(Instant::now());
+            return Ok(());
+        }
+
+        self.failed_attempts = 0;
+        let failed_dir = self.output_dir.join("failed");
+        fs::create_dir_all(&failed_dir)
+            .with_context(|| format!("failed to create directory {:?}", failed_dir))?;
+        for (i, proof) in batch.iter().enumerate() {
+            let proof_path =
+                failed_dir.join(format!("batch_{:06}_{:03}.bin", self.batches_failed, i));
+            fs::write(&proof_path, proof.to_bytes())
+                .with_context(|| format!("failed to write leaf proof to {:?}", proof_path))?;
+        }
+        self.batches_failed += 1;
+        tracing::error!(
+            path = %failed_dir.display(),
+            num_proofs = batch.len(),
+            attempts = MAX_BATCH_ATTEMPTS,
+            "giving up on batch, leaf proofs saved"
+        );
+        Ok(())
+    }
+
+    fn submit(
+        &mut self,
+        proof_path: PathBuf,
+        aggregated_proof: AggregatedProof<F, C, D>,
+    ) -> anyhow::Result<()> {
+        let Some(hook) = self.hook.as_mut() else {
+            return Ok(());
+        };
+        if let Err(e) = hook.submit(&proof_path, &aggregated_proof) {
+            let context = format!("failed to submit {:?}", proof_path);
+            self.unsubmitted.push((proof_path, aggregated_proof));
+            return Err(e.context(context));
+        }
+
+        Ok(())
+    }
+
+    fn retry_submissions(&mut self) -> anyhow::Result<()> {
+        let mut result = Ok(());
+        for (proof_path, aggregated_proof) in core::mem::take(&mut self.unsubmitted) {
+            if let Err(e) = self.submit(proof_path, aggregated_proof) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
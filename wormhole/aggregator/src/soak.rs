@@ -0,0 +1,179 @@
+//! Bookkeeping for long-running aggregation soak tests, see the `soak` binary.
+//!
+//! A soak test aggregates batches of leaf proofs for hours. [`SoakStats`] records the latency and
+//! outcome of every batch and the resident memory of the process after it. A leak or
+//! fragmentation in the aggregation pipeline shows up as memory that keeps growing after the
+//! first batches have warmed up the allocator and plonky2's caches.
+use core::fmt;
+use std::fs;
+use std::time::Duration;
+
+/// The resident set size of this process in bytes, read from `/proc/self/status`.
+///
+/// Returns `None` on platforms without procfs.
+pub fn resident_memory_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    parse_resident_memory(&status)
+}
+
+/// Parses the `VmRSS` line of a `/proc/<pid>/status` file into bytes.
+pub fn parse_resident_memory(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim();
+    kib.parse::<u64>().ok().map(|kib| kib * 1024)
+}
+
+/// Why a soak test iteration failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoakFailure {
+    /// A leaf proof couldn't be generated or replayed.
+    Proof,
+    /// Aggregating a batch returned an error.
+    Aggregation,
+    /// An aggregated proof didn't verify.
+    Verification,
+}
+
+/// Latencies, failures and memory samples of a soak test.
+#[derive(Debug, Clone, Default)]
+pub struct SoakStats {
+    batches: usize,
+    latencies: Vec<Duration>,
+    proof_failures: usize,
+    aggregation_failures: usize,
+    verification_failures: usize,
+    memory: Vec<u64>,
+}
+
+impl SoakStats {
+    /// Records a batch that was aggregated in `latency`, and the resident memory after it.
+    pub fn record_batch(&mut self, latency: Duration, resident_memory: Option<u64>) {
+        self.batches += 1;
+        self.latencies.push(latency);
+        self.memory.extend(resident_memory);
+    }
+
+    /// Records a failure. Failed aggregations count as batches but have no latency.
+    pub fn record_failure(&mut self, failure: SoakFailure, resident_memory: Option<u64>) {
+        match failure {
+            SoakFailure::Proof => self.proof_failures += 1,
+            SoakFailure::Aggregation => {
+                self.batches += 1;
+                self.aggregation_failures += 1;
+                self.memory.extend(resident_memory);
+            }
+            SoakFailure::Verification => self.verification_failures += 1,
+        }
+    }
+
+    /// The number of batches attempted.
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    /// The number of failures of all kinds.
+    pub fn failures(&self) -> usize {
+        self.proof_failures + self.aggregation_failures + self.verification_failures
+    }
+
+    /// The nearest-rank `percentile` of the successful batch latencies, or `None` if no batch
+    /// succeeded yet.
+    pub fn latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        let rank = (percentile / 100.0 * latencies.len() as f64).ceil() as usize;
+        Some(latencies[rank.clamp(1, latencies.len()) - 1])
+    }
+
+    /// The resident memory after the first `warmup` batches, which later samples are compared
+    /// against. Falls back to the latest sample while the test is still warming up.
+    pub fn baseline_memory(&self, warmup: usize) -> Option<u64> {
+        self.memory
+            .get(warmup.saturating_sub(1))
+            .or(self.memory.last())
+            .copied()
+    }
+
+    /// How many bytes the resident memory grew since the baseline of [`Self::baseline_memory`].
+    /// Negative if the process returned memory to the system.
+    pub fn memory_growth(&self, warmup: usize) -> Option<i64> {
+        let baseline = self.baseline_memory(warmup)?;
+        let last = *self.memory.last()?;
+        Some(last as i64 - baseline as i64)
+    }
+
+    /// A summary of the test so far.
+    pub fn summary(&self, warmup: usize) -> SoakSummary {
+        SoakSummary {
+            batches: self.batches,
+            proof_failures: self.proof_failures,
+            aggregation_failures: self.aggregation_failures,
+            verification_failures: self.verification_failures,
+            p50: self.latency_percentile(50.0),
+            p90: self.latency_percentile(90.0),
+            p99: self.latency_percentile(99.0),
+            max: self.latencies.iter().max().copied(),
+            baseline_memory: self.baseline_memory(warmup),
+            peak_memory: self.memory.iter().max().copied(),
+            memory_growth: self.memory_growth(warmup),
+        }
+    }
+}
+
+/// A snapshot of [`SoakStats`], printed periodically and at the end of a soak test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoakSummary {
+    pub batches: usize,
+    pub proof_failures: usize,
+    pub aggregation_failures: usize,
+    pub verification_failures: usize,
+    pub p50: Option<Duration>,
+    pub p90: Option<Duration>,
+    pub p99: Option<Duration>,
+    pub max: Option<Duration>,
+    pub baseline_memory: Option<u64>,
+    pub peak_memory: Option<u64>,
+    pub memory_growth: Option<i64>,
+}
+
+impl fmt::Display for SoakSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn latency(latency: Option<Duration>) -> String {
+            latency.map_or("-".to_string(), |latency| format!("{:.2?}", latency))
+        }
+        fn mib(bytes: Option<i64>) -> String {
+            bytes.map_or("-".to_string(), |bytes| {
+                format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+            })
+        }
+
+        writeln!(f, "batches:   {}", self.batches)?;
+        writeln!(
+            f,
+            "failures:  {} proof, {} aggregation, {} verification",
+            self.proof_failures, self.aggregation_failures, self.verification_failures
+        )?;
+        writeln!(
+            f,
+            "latency:   p50 {}, p90 {}, p99 {}, max {}",
+            latency(self.p50),
+            latency(self.p90),
+            latency(self.p99),
+            latency(self.max)
+        )?;
+        write!(
+            f,
+            "memory:    baseline {}, peak {}, growth {}",
+            mib(self.baseline_memory.map(|bytes| bytes as i64)),
+            mib(self.peak_memory.map(|bytes| bytes as i64)),
+            mib(self.memory_growth)
+        )
+    }
+}
@@ -1,29 +1,17 @@
-use anyhow::{bail, Context};
-use plonky2::plonk::circuit_data::CommonCircuitData;
+use anyhow::bail;
 use wormhole_verifier::ProofWithPublicInputs;
 use zk_circuits_common::circuit::{C, D, F};
 
-#[cfg(not(feature = "no_zk"))]
-const DUMMY_PROOF_BYTES: &[u8] = include_bytes!("../data/dummy_proof_zk.bin");
-#[cfg(feature = "no_zk")]
-const DUMMY_PROOF_BYTES: &[u8] = include_bytes!("../data/dummy_proof.bin");
-
-pub fn pad_with_dummy_proofs(
-    mut proofs: Vec<ProofWithPublicInputs<F, C, D>>,
-    proof_len: usize,
-    common_data: &CommonCircuitData<F, D>,
-) -> anyhow::Result<Vec<ProofWithPublicInputs<F, C, D>>> {
-    let num_proofs = proofs.len();
-
-    if num_proofs > proof_len {
+/// Pads `leaves` with padding leaves, `None`, up to `num_leaves`. See
+/// [`crate::circuits::tree::aggregate_leaves_to_tree`] for how the tree treats them.
+pub fn pad_leaves(
+    mut leaves: Vec<Option<ProofWithPublicInputs<F, C, D>>>,
+    num_leaves: usize,
+) -> anyhow::Result<Vec<Option<ProofWithPublicInputs<F, C, D>>>> {
+    if leaves.len() > num_leaves {
         bail!("proofs to aggregate was more than the maximum allowed")
     }
 
-    let dummy_proof = ProofWithPublicInputs::from_bytes(DUMMY_PROOF_BYTES.to_vec(), common_data)
-        .context("failed to deserialize dummy proof")?;
-    for _ in 0..(proof_len - num_proofs) {
-        proofs.push(dummy_proof.clone());
-    }
-
-    Ok(proofs)
+    leaves.resize_with(num_leaves, || None);
+    Ok(leaves)
 }
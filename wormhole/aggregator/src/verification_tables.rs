@@ -0,0 +1,296 @@
+//! Precomputed tables for verifying root proofs of a fixed aggregation circuit.
+//!
+//! Plonky2 already computes everything about a circuit that doesn't depend on the challenges of
+//! a proof, such as the constants and sigmas cap and the circuit digest, when the circuit is
+//! built. What a validator verifying root proofs of the same tree over and over pays for instead
+//! is getting at that data: building the root circuit means building every aggregation circuit
+//! below it.
+//!
+//! [`RootVerificationTables`] keeps the verifier data of the root circuit together with the
+//! expected tree shape commitment, and serializes them, so a validator computes them once per
+//! circuit and afterwards only deserializes and verifies proofs.
+//!
+//! ## Format
+//!
+//! A version byte, followed by the tree shape as three little endian `u32`s (branching factor,
+//! depth, number of leaves) and the leaf public inputs length as a `u32`. Then a `0` byte, or a
+//! `1` byte and the four elements of the tree shape commitment. Field elements are little endian
+//! `u64`s and must be canonical. The root circuit's common and verifier data follow as length
+//! prefixed sections.
+use anyhow::{anyhow, bail, Context};
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData};
+use plonky2::util::serialization::DefaultGateSerializer;
+use wormhole_circuit::inputs::PublicCircuitInputs;
+use wormhole_verifier::ProofWithPublicInputs;
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::{Digest, DIGEST_NUM_FIELD_ELEMENTS};
+
+use crate::aggregator::WormholeProofAggregator;
+use crate::circuits::shape::{
+    tree_shape_commitment, TreeShapeCommitments, TREE_SHAPE_PUBLIC_INPUTS_LEN,
+};
+use crate::circuits::tree::{AggregatedProof, TreeAggregationConfig};
+
+const TABLES_VERSION: u8 = 2;
+
+/// Everything needed to verify root proofs of one aggregation circuit and decode their leaves,
+/// see the [module documentation](self).
+pub struct RootVerificationTables {
+    pub verifier_data: VerifierCircuitData<F, C, D>,
+    pub config: TreeAggregationConfig,
+    /// The number of public inputs of each leaf proof.
+    pub leaf_pi_len: usize,
+    /// The commitment root proofs end with, if the circuit commits to the tree shape.
+    pub tree_shape: Option<Digest>,
+}
+
+impl RootVerificationTables {
+    /// Computes the tables of the circuit of `root`, a root proof of a tree of shape `config`
+    /// over proofs of the leaf circuit with `leaf_common` data. `commits_tree_shape` tells
+    /// whether `root` was wrapped with [`crate::circuits::shape::commit_tree_shape`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the public inputs of the root circuit don't hold `config`'s leaves.
+    pub fn new(
+        root: &AggregatedProof<F, C, D>,
+        leaf_common: &CommonCircuitData<F, D>,
+        config: TreeAggregationConfig,
+        commits_tree_shape: bool,
+    ) -> anyhow::Result<Self> {
+        let leaf_pi_len = leaf_common.num_public_inputs;
+        let tables = Self {
+            verifier_data: root.circuit_data.verifier_data(),
+            config,
+            leaf_pi_len,
+            tree_shape: commits_tree_shape.then(|| tree_shape_commitment(&config)),
+        };
+        tables.check_layout()?;
+        Ok(tables)
+    }
+
+    /// The digest of the root circuit.
+    pub fn circuit_digest(&self) -> Digest {
+        self.verifier_data.verifier_only.circuit_digest.elements
+    }
+
+    /// Verifies a serialized root proof and decodes its leaves, with `None` for padding leaves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a proof of the root circuit, the proof is not valid,
+    /// it commits to a different tree shape, or a leaf that is not padding doesn't decode.
+    pub fn verify(&self, proof_bytes: &[u8]) -> anyhow::Result<Vec<Option<PublicCircuitInputs>>> {
+        let proof = ProofWithPublicInputs::<F, C, D>::from_bytes(
+            proof_bytes.to_vec(),
+            &self.verifier_data.common,
+        )
+        .map_err(|e| anyhow!("failed to deserialize root proof: {}", e))?;
+        let public_inputs = proof.public_inputs.clone();
+        self.verifier_data
+            .verify(proof)
+            .map_err(|e| anyhow!("root proof failed verification: {}", e))?;
+
+        let leaf_public_inputs = match self.tree_shape {
+            Some(tree_shape) => {
+                let (leaves, commitments) =
+                    TreeShapeCommitments::split_public_inputs(&public_inputs)?;
+                if commitments.tree_shape != tree_shape {
+                    bail!("root proof commits to a different tree shape");
+                }
+                leaves
+            }
+            None => &public_inputs,
+        };
+
+        leaf_public_inputs
+            .chunks(self.leaf_pi_len)
+            .enumerate()
+            .map(|(index, leaf)| {
+                PublicCircuitInputs::try_from_leaf(leaf)
+                    .map(|leaf| (!leaf.is_padding()).then_some(leaf))
+                    .with_context(|| format!("failed to decode leaf {}", index))
+            })
+            .collect()
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let common_bytes = self
+            .verifier_data
+            .common
+            .to_bytes(&DefaultGateSerializer)
+            .map_err(|e| anyhow!("Failed to serialize common data: {}", e))?;
+        let verifier_bytes = self
+            .verifier_data
+            .verifier_only
+            .to_bytes()
+            .map_err(|e| anyhow!("Failed to serialize verifier data: {}", e))?;
+
+        let mut bytes = vec![TABLES_VERSION];
+        bytes.extend(u32::try_from(self.config.tree_branching_factor)?.to_le_bytes());
+        bytes.extend(self.config.tree_depth.to_le_bytes());
+        bytes.extend(u32::try_from(self.config.num_leaf_proofs)?.to_le_bytes());
+        bytes.extend(u32::try_from(self.leaf_pi_len)?.to_le_bytes());
+        match self.tree_shape {
+            Some(tree_shape) => {
+                bytes.push(1);
+                write_felts(&mut bytes, &tree_shape);
+            }
+            None => bytes.push(0),
+        }
+        write_section(&mut bytes, &common_bytes)?;
+        write_section(&mut bytes, &verifier_bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let Some((&version, mut rest)) = bytes.split_first() else {
+            bail!("verification tables are empty");
+        };
+        if version != TABLES_VERSION {
+            bail!(
+                "unsupported verification tables version {}, expected {}",
+                version,
+                TABLES_VERSION
+            );
+        }
+
+        let tree_branching_factor = read_u32(&mut rest)? as usize;
+        let tree_depth = read_u32(&mut rest)?;
+        let num_leaf_proofs = read_u32(&mut rest)? as usize;
+        let leaf_pi_len = read_u32(&mut rest)? as usize;
+        let tree_shape = match read_bytes(&mut rest, 1)? {
+            [0] => None,
+            [1] => Some(
+                read_felts(&mut rest, DIGEST_NUM_FIELD_ELEMENTS)?
+                    .try_into()
+                    .expect("read digest length"),
+            ),
+            _ => bail!("invalid tree shape flag"),
+        };
+
+        let common_bytes = read_section(&mut rest)?.to_vec();
+        let common = CommonCircuitData::from_bytes(common_bytes, &DefaultGateSerializer)
+            .map_err(|e| anyhow!("Failed to deserialize common data: {}", e))?;
+        let verifier_bytes = read_section(&mut rest)?.to_vec();
+        let verifier_only = VerifierOnlyCircuitData::from_bytes(verifier_bytes)
+            .map_err(|e| anyhow!("Failed to deserialize verifier data: {}", e))?;
+        if !rest.is_empty() {
+            bail!("verification tables have {} trailing bytes", rest.len());
+        }
+
+        let tables = Self {
+            verifier_data: VerifierCircuitData {
+                verifier_only,
+                common,
+            },
+            config: TreeAggregationConfig {
+                num_leaf_proofs,
+                tree_branching_factor,
+                tree_depth,
+            },
+            leaf_pi_len,
+            tree_shape,
+        };
+        tables.check_layout()?;
+        Ok(tables)
+    }
+
+    /// Checks that the public inputs of the root circuit are `config`'s leaves, followed by the
+    /// tree shape commitments if there are any.
+    fn check_layout(&self) -> anyhow::Result<()> {
+        if self.config.tree_branching_factor.checked_pow(self.config.tree_depth)
+            != Some(self.config.num_leaf_proofs)
+        {
+            bail!("tree has an inconsistent number of leaves");
+        }
+        if self.leaf_pi_len == 0 {
+            bail!("leaves have no public inputs");
+        }
+
+        let mut expected_len = self.leaf_pi_len * self.config.num_leaf_proofs;
+        if self.tree_shape.is_some() {
+            expected_len += TREE_SHAPE_PUBLIC_INPUTS_LEN;
+        }
+        let num_public_inputs = self.verifier_data.common.num_public_inputs;
+        if num_public_inputs != expected_len {
+            bail!(
+                "root circuit should have {} public inputs (= {} leaves × {} fields{}), got: {}",
+                expected_len,
+                self.config.num_leaf_proofs,
+                self.leaf_pi_len,
+                if self.tree_shape.is_some() {
+                    " + tree shape commitments"
+                } else {
+                    ""
+                },
+                num_public_inputs
+            );
+        }
+        Ok(())
+    }
+}
+
+impl WormholeProofAggregator {
+    /// The [`RootVerificationTables`] of the circuit of `root`, a proof returned by
+    /// [`Self::aggregate`] for a tree of shape `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` doesn't hold `config`'s leaves.
+    pub fn verification_tables(
+        &self,
+        root: &AggregatedProof<F, C, D>,
+        config: TreeAggregationConfig,
+    ) -> anyhow::Result<RootVerificationTables> {
+        RootVerificationTables::new(
+            root,
+            &self.leaf_circuit_data.common,
+            config,
+            self.commit_tree_shape,
+        )
+    }
+}
+
+fn write_felts(bytes: &mut Vec<u8>, felts: &[F]) {
+    for felt in felts {
+        bytes.extend(felt.to_canonical_u64().to_le_bytes());
+    }
+}
+
+fn write_section(bytes: &mut Vec<u8>, section: &[u8]) -> anyhow::Result<()> {
+    bytes.extend(u32::try_from(section.len())?.to_le_bytes());
+    bytes.extend(section);
+    Ok(())
+}
+
+fn read_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+    if bytes.len() < len {
+        bail!("verification tables are truncated");
+    }
+    let (value, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(value)
+}
+
+fn read_u32(bytes: &mut &[u8]) -> anyhow::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(bytes, 4)?.try_into()?))
+}
+
+fn read_felts(bytes: &mut &[u8], len: usize) -> anyhow::Result<Vec<F>> {
+    (0..len)
+        .map(|i| {
+            let value = u64::from_le_bytes(read_bytes(bytes, 8)?.try_into()?);
+            if value >= F::ORDER {
+                bail!("element {} is not a canonical field element", i);
+            }
+            Ok(F::from_canonical_u64(value))
+        })
+        .collect()
+}
+
+fn read_section<'a>(bytes: &mut &'a [u8]) -> anyhow::Result<&'a [u8]> {
+    let len = read_u32(bytes)? as usize;
+    read_bytes(bytes, len)
+}
@@ -0,0 +1,24 @@
+//! Exposes the resolved qp-plonky2 version as `QP_PLONKY2_VERSION`, which keys the recorded
+//! artifact hashes, see `reproducibility`.
+use std::env;
+use std::fs::read_to_string;
+use std::path::Path;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let lock_path = Path::new(&manifest_dir).join("../../Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let version = read_to_string(&lock_path)
+        .ok()
+        .and_then(|lock| plonky2_version(&lock))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=QP_PLONKY2_VERSION={}", version);
+}
+
+fn plonky2_version(lock: &str) -> Option<String> {
+    let mut lines = lock.lines();
+    lines.find(|line| line.trim() == r#"name = "qp-plonky2""#)?;
+    let version = lines.next()?.trim().strip_prefix("version = ")?;
+    Some(version.trim_matches('"').to_string())
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use wormhole_circuit::accumulator::MAX_ACCUMULATOR_DEPTH;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::statement::WORMHOLE_STATEMENT;
+use zk_circuits_common::config::ConfigPreset;
+
+/// Prints the public inputs and constraints of the wormhole circuit, for audits.
+#[derive(Debug, Parser)]
+struct Args {
+    /// The variant of the circuit to describe.
+    #[arg(long, value_enum, default_value_t = Variant::Default)]
+    variant: Variant,
+    /// The depth of the spent nullifier accumulator, for the `spent-nullifier-accumulator`
+    /// variant.
+    #[arg(long, default_value_t = MAX_ACCUMULATOR_DEPTH)]
+    accumulator_depth: usize,
+    /// The transfer count from which deposits use version 2 nullifiers, for the
+    /// `nullifier-migration` variant.
+    #[arg(long, default_value_t = 0)]
+    nullifier_v2_cutover: u64,
+    /// Print the summary as JSON instead of text.
+    #[arg(long)]
+    json: bool,
+    /// Print the statement the circuit proves, with the fragments enforcing each clause, instead
+    /// of the constraints.
+    #[arg(long)]
+    statement: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Variant {
+    Default,
+    NullifierMigration,
+    DestinationChain,
+    RelayerFee,
+    ExitAccountFormat,
+    PublicInputsDigest,
+    SpentNullifierAccumulator,
+    FundingCommitment,
+    IntentHash,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = ConfigPreset::Standard.config(false);
+    let circuit = match args.variant {
+        Variant::Default => WormholeCircuit::new(config),
+        Variant::NullifierMigration => {
+            WormholeCircuit::with_nullifier_migration(config, args.nullifier_v2_cutover)
+        }
+        Variant::DestinationChain => WormholeCircuit::with_destination_chain(config),
+        Variant::RelayerFee => WormholeCircuit::with_relayer_fee(config),
+        Variant::ExitAccountFormat => WormholeCircuit::with_exit_account_format(config),
+        Variant::PublicInputsDigest => WormholeCircuit::with_public_inputs_digest(config),
+        Variant::SpentNullifierAccumulator => {
+            WormholeCircuit::with_spent_nullifier_accumulator(config, args.accumulator_depth)?
+        }
+        Variant::FundingCommitment => WormholeCircuit::with_funding_commitment(config),
+        Variant::IntentHash => WormholeCircuit::with_intent_hash(config),
+    };
+
+    let summary = circuit.describe()?;
+    WORMHOLE_STATEMENT.check(&summary)?;
+    if args.statement {
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&WORMHOLE_STATEMENT)?);
+        } else {
+            println!("{}", WORMHOLE_STATEMENT.claim);
+            for clause in WORMHOLE_STATEMENT.clauses {
+                let feature = clause.feature.unwrap_or("always");
+                println!("  {} ({}): {}", clause.name, feature, clause.statement);
+                println!("    enforced by: {}", clause.fragments.join(", "));
+            }
+        }
+    } else if args.json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        print!("{}", summary);
+    }
+    Ok(())
+}
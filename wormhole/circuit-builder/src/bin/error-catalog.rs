@@ -0,0 +1,22 @@
+use std::fs::write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use wormhole_circuit::errors::error_catalog;
+
+/// Writes the catalog of structured error codes as JSON.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Where to write the catalog.
+    #[arg(long, default_value = "error-catalog.json")]
+    output: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let json = serde_json::to_string_pretty(error_catalog())?;
+    write(&args.output, json)?;
+    println!("Error catalog saved to {}", args.output.display());
+    Ok(())
+}
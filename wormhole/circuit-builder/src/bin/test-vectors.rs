@@ -0,0 +1,22 @@
+use std::fs::write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use wormhole_circuit::vectors::test_vectors;
+
+/// Writes the wormhole preimage test vectors as JSON.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Where to write the test vectors.
+    #[arg(long, default_value = "test-vectors.json")]
+    output: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let json = serde_json::to_string_pretty(&test_vectors())?;
+    write(&args.output, json)?;
+    println!("Test vectors saved to {}", args.output.display());
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! The circuits whose artifacts the builder generates with `--circuit`.
+//!
+//! Every [`Circuit`] is built with the same config and written to `<output_dir>/<circuit>` by
+//! [`crate::generate_circuit_artifacts`], so deployment tooling finds `common.bin`,
+//! `verifier.bin` and `prover.bin` of every circuit at the same place. New circuits are added as
+//! variants of [`Circuit`].
+use anyhow::bail;
+use std::str::FromStr;
+
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::deposit_vote::circuit_logic::DepositVoteCircuit;
+use zk_circuits_common::circuit::{C, D, F};
+
+/// A circuit the builder can generate artifacts for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Circuit {
+    /// The wormhole circuit.
+    Wormhole,
+    /// The deposit vote circuit, the `vote` release profile.
+    Vote,
+}
+
+impl Circuit {
+    pub const ALL: [Circuit; 2] = [Self::Wormhole, Self::Vote];
+
+    /// Builds this circuit with `config`.
+    pub fn build_circuit(self, config: CircuitConfig) -> CircuitData<F, C, D> {
+        match self {
+            Self::Wormhole => WormholeCircuit::new(config).build_circuit(),
+            Self::Vote => DepositVoteCircuit::new(config).build_circuit(),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wormhole => "wormhole",
+            Self::Vote => "vote",
+        }
+    }
+
+    /// Whether the public inputs follow the wormhole layout described by the encoding descriptor.
+    pub(crate) fn has_wormhole_encoding(self) -> bool {
+        self == Self::Wormhole
+    }
+}
+
+impl FromStr for Circuit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wormhole" => Ok(Self::Wormhole),
+            "vote" => Ok(Self::Vote),
+            _ => bail!("unknown circuit `{}`, expected one of: wormhole, vote", s),
+        }
+    }
+}
+
+/// The circuits selected with `--circuit`: one of them by name, or `all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitSelection {
+    One(Circuit),
+    All,
+}
+
+impl CircuitSelection {
+    pub fn circuits(self) -> Vec<Circuit> {
+        match self {
+            Self::One(circuit) => vec![circuit],
+            Self::All => Circuit::ALL.to_vec(),
+        }
+    }
+}
+
+impl FromStr for CircuitSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            _ => match s.parse() {
+                Ok(circuit) => Ok(Self::One(circuit)),
+                Err(_) => bail!(
+                    "unknown circuit `{}`, expected one of: wormhole, vote, all",
+                    s
+                ),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_names_round_trip() {
+        for circuit in Circuit::ALL {
+            assert_eq!(circuit.name().parse::<Circuit>().unwrap(), circuit);
+            assert_eq!(
+                circuit.name().parse::<CircuitSelection>().unwrap(),
+                CircuitSelection::One(circuit)
+            );
+        }
+        assert!("all".parse::<Circuit>().is_err());
+        assert!("nullifier".parse::<CircuitSelection>().is_err());
+    }
+
+    #[test]
+    fn all_selects_every_circuit() {
+        let selection = "all".parse::<CircuitSelection>().unwrap();
+        assert_eq!(selection.circuits(), Circuit::ALL.to_vec());
+    }
+}
@@ -0,0 +1,225 @@
+//! Standalone circuits for the individual statements of the wormhole circuit.
+//!
+//! Each circuit proves a single [`CircuitFragment`] on its own, so projects that only need one
+//! statement, e.g. that an account was derived from a secret, can reuse it without the rest of
+//! the wormhole circuit. The fragments are the same as in the wormhole circuit, but the circuits
+//! are not, so their proofs and artifacts are not interchangeable with wormhole ones.
+//!
+//! Artifacts are written with [`crate::generate_fragment_binaries`].
+use anyhow::{bail, Result};
+use std::str::FromStr;
+
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{
+    CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData,
+};
+use wormhole_circuit::nullifier::{Nullifier, NullifierTargets};
+use wormhole_circuit::storage_proof::{StorageProof, StorageProofParams, StorageProofTargets};
+use wormhole_circuit::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::gadgets::range_check_u32;
+
+/// Proves that the public nullifier was derived from a secret and transfer count.
+pub struct NullifierCircuit {
+    builder: CircuitBuilder<F, D>,
+    targets: NullifierTargets,
+}
+
+impl NullifierCircuit {
+    pub fn new(config: CircuitConfig) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = NullifierTargets::new(&mut builder);
+        Nullifier::circuit(&targets, &mut builder);
+
+        Self { builder, targets }
+    }
+
+    pub fn targets(&self) -> NullifierTargets {
+        self.targets.clone()
+    }
+
+    pub fn build_circuit(self) -> CircuitData<F, C, D> {
+        self.builder.build()
+    }
+
+    pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+        self.builder.build_prover()
+    }
+
+    pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+        self.builder.build_verifier()
+    }
+}
+
+/// Proves that the public unspendable account was derived from a secret.
+pub struct UnspendableAccountCircuit {
+    builder: CircuitBuilder<F, D>,
+    targets: UnspendableAccountTargets,
+}
+
+impl UnspendableAccountCircuit {
+    pub fn new(config: CircuitConfig) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = UnspendableAccountTargets::new(&mut builder);
+        builder.register_public_inputs(&targets.account_id.elements);
+        // The wormhole circuit range checks the secret as part of the nullifier, which isn't
+        // computed here.
+        range_check_u32(&mut builder, &targets.secret[..]);
+        UnspendableAccount::circuit(&targets, &mut builder);
+
+        Self { builder, targets }
+    }
+
+    pub fn targets(&self) -> UnspendableAccountTargets {
+        self.targets.clone()
+    }
+
+    pub fn build_circuit(self) -> CircuitData<F, C, D> {
+        self.builder.build()
+    }
+
+    pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+        self.builder.build_prover()
+    }
+
+    pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+        self.builder.build_verifier()
+    }
+}
+
+/// Proves that a transfer leaf is included under the public storage root, with the public
+/// funding amount.
+pub struct StorageProofCircuit {
+    builder: CircuitBuilder<F, D>,
+    targets: StorageProofTargets,
+}
+
+impl StorageProofCircuit {
+    pub fn new(config: CircuitConfig) -> Self {
+        Self::with_params(config, StorageProofParams::default())
+    }
+
+    /// Like [`Self::new`], but accepts storage proofs of the given shape.
+    pub fn with_params(config: CircuitConfig, params: StorageProofParams) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = StorageProofTargets::with_params(&mut builder, params);
+        StorageProof::circuit(&targets, &mut builder);
+
+        Self { builder, targets }
+    }
+
+    pub fn targets(&self) -> StorageProofTargets {
+        self.targets.clone()
+    }
+
+    pub fn build_circuit(self) -> CircuitData<F, C, D> {
+        self.builder.build()
+    }
+
+    pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+        self.builder.build_prover()
+    }
+
+    pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+        self.builder.build_verifier()
+    }
+}
+
+/// A fragment of the wormhole circuit that can be built on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fragment {
+    /// See [`NullifierCircuit`].
+    Nullifier,
+    /// See [`UnspendableAccountCircuit`].
+    UnspendableAccount,
+    /// See [`StorageProofCircuit`].
+    StorageProof,
+}
+
+impl Fragment {
+    pub const ALL: [Fragment; 3] = [
+        Self::Nullifier,
+        Self::UnspendableAccount,
+        Self::StorageProof,
+    ];
+
+    /// Builds the standalone circuit of this fragment with `config`.
+    pub fn build_circuit(self, config: CircuitConfig) -> CircuitData<F, C, D> {
+        match self {
+            Self::Nullifier => NullifierCircuit::new(config).build_circuit(),
+            Self::UnspendableAccount => UnspendableAccountCircuit::new(config).build_circuit(),
+            Self::StorageProof => StorageProofCircuit::new(config).build_circuit(),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nullifier => "nullifier",
+            Self::UnspendableAccount => "unspendable-account",
+            Self::StorageProof => "storage-proof",
+        }
+    }
+}
+
+impl FromStr for Fragment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nullifier" => Ok(Self::Nullifier),
+            "unspendable-account" => Ok(Self::UnspendableAccount),
+            "storage-proof" => Ok(Self::StorageProof),
+            _ => bail!(
+                "unknown fragment `{}`, expected one of: nullifier, unspendable-account, \
+                 storage-proof",
+                s
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::iop::witness::PartialWitness;
+
+    const SECRET: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn fragment_names_round_trip() {
+        for fragment in Fragment::ALL {
+            assert_eq!(fragment.name().parse::<Fragment>().unwrap(), fragment);
+        }
+        assert!("wormhole".parse::<Fragment>().is_err());
+    }
+
+    #[test]
+    fn unspendable_account_circuit_exposes_account() {
+        let circuit = UnspendableAccountCircuit::new(CircuitConfig::standard_recursion_config());
+        let targets = circuit.targets();
+        let data = circuit.build_circuit();
+
+        let unspendable_account = UnspendableAccount::from_secret(&SECRET);
+        let mut pw = PartialWitness::new();
+        unspendable_account.fill_targets(&mut pw, targets).unwrap();
+        let proof = data.prove(pw).unwrap();
+
+        assert_eq!(proof.public_inputs, unspendable_account.account_id);
+        data.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn nullifier_circuit_exposes_nullifier() {
+        let circuit = NullifierCircuit::new(CircuitConfig::standard_recursion_config());
+        let targets = circuit.targets();
+        let data = circuit.build_circuit();
+
+        let nullifier = Nullifier::from_preimage(&SECRET, 3);
+        let mut pw = PartialWitness::new();
+        nullifier.fill_targets(&mut pw, targets).unwrap();
+        let proof = data.prove(pw).unwrap();
+
+        assert_eq!(proof.public_inputs, nullifier.hash);
+        data.verify(proof).unwrap();
+    }
+}
@@ -1,62 +1,171 @@
-use anyhow::{anyhow, Result};
-use std::fs::{create_dir_all, write};
-use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use std::fs::{create_dir_all, read, read_to_string, write};
+use std::path::{Path, PathBuf};
 
-use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::gates::gate::Gate;
+use plonky2::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierOnlyCircuitData,
+};
 use plonky2::plonk::config::PoseidonGoldilocksConfig;
 use plonky2::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
 use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
-use zk_circuits_common::circuit::D;
+use wormhole_circuit::encoding::{ENCODING_DESCRIPTOR_FILE_NAME, WORMHOLE_ENCODING};
+use wormhole_circuit::envelope::circuit_digest_to_bytes;
+use wormhole_circuit::layout::{TargetsLayout, TARGETS_LAYOUT_FILE_NAME};
+use zk_circuits_common::circuit::{C, D, F};
+
+use crate::circuits::Circuit;
+use crate::fragments::Fragment;
+
+pub mod circuits;
+pub mod fragments;
+pub mod profiles;
+pub mod reproducibility;
 
 pub fn generate_circuit_binaries<P: AsRef<Path>>(
     output_dir: P,
     include_prover: bool,
 ) -> Result<()> {
-    println!("Building wormhole circuit...");
     let config = CircuitConfig::standard_recursion_config();
-    let circuit = WormholeCircuit::new(config);
-    let circuit_data = circuit.build_circuit();
+    generate_circuit_binaries_with_config(output_dir, config, include_prover)
+}
+
+/// Like [`generate_circuit_binaries`], but builds the circuit with the given config, e.g. one of
+/// the presets in [`zk_circuits_common::config`].
+pub fn generate_circuit_binaries_with_config<P: AsRef<Path>>(
+    output_dir: P,
+    config: CircuitConfig,
+    include_prover: bool,
+) -> Result<()> {
+    println!("Building wormhole circuit...");
+    let (circuit_data, layout) = build_wormhole_circuit(config);
     println!("Circuit built.");
 
-    let gate_serializer = DefaultGateSerializer;
-    let generator_serializer = DefaultGeneratorSerializer::<PoseidonGoldilocksConfig, D> {
-        _phantom: Default::default(),
-    };
+    write_circuit_binaries(output_dir.as_ref(), circuit_data, &layout, include_prover)
+}
 
-    println!("Serializing circuit data...");
+/// Builds the circuit with `config` and writes its verifier artifacts to a subdirectory of
+/// `registry_dir` named after the hex encoded circuit digest, the layout loaded by the verifier's
+/// `VerifierRegistry`. Returns the path of the written entry.
+pub fn generate_registry_entry<P: AsRef<Path>>(
+    registry_dir: P,
+    config: CircuitConfig,
+) -> Result<PathBuf> {
+    println!("Building wormhole circuit...");
+    let (circuit_data, layout) = build_wormhole_circuit(config);
+    println!("Circuit built.");
+
+    let circuit_digest = circuit_digest_hex(&circuit_data.verifier_only);
+    let entry_path = registry_dir.as_ref().join(circuit_digest);
+    write_circuit_binaries(&entry_path, circuit_data, &layout, false)?;
+
+    Ok(entry_path)
+}
+
+/// Builds the standalone circuit of `fragment` with `config` and writes its artifacts to a
+/// subdirectory of `output_dir` named after the fragment. Returns the path of the written
+/// artifacts. See [`fragments`].
+pub fn generate_fragment_binaries<P: AsRef<Path>>(
+    output_dir: P,
+    fragment: Fragment,
+    config: CircuitConfig,
+    include_prover: bool,
+) -> Result<PathBuf> {
+    println!("Building {} circuit...", fragment.name());
+    let circuit_data = fragment.build_circuit(config);
+    println!("Circuit built.");
 
-    let verifier_data = circuit_data.verifier_data();
-    let prover_data = circuit_data.prover_data();
-    let common_data = &verifier_data.common;
+    let output_path = output_dir.as_ref().join(fragment.name());
+    write_circuit_data(&output_path, circuit_data, include_prover)?;
+
+    Ok(output_path)
+}
+
+/// Builds every circuit in `circuits` with `config` and writes its artifacts to a subdirectory of
+/// `output_dir` named after the circuit. The wormhole circuit also gets its encoding descriptor
+/// and targets layout.
+/// Returns the paths of the written artifacts, in the order of `circuits`. See [`circuits`].
+pub fn generate_circuit_artifacts<P: AsRef<Path>>(
+    output_dir: P,
+    circuits: &[Circuit],
+    config: CircuitConfig,
+    include_prover: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::with_capacity(circuits.len());
+    for &circuit in circuits {
+        println!("Building {} circuit...", circuit.name());
+        let output_path = output_dir.as_ref().join(circuit.name());
+        if circuit.has_wormhole_encoding() {
+            let (circuit_data, layout) = build_wormhole_circuit(config.clone());
+            println!("Circuit built.");
+            write_circuit_binaries(&output_path, circuit_data, &layout, include_prover)?;
+        } else {
+            let circuit_data = circuit.build_circuit(config.clone());
+            println!("Circuit built.");
+            write_circuit_data(&output_path, circuit_data, include_prover)?;
+        }
+        paths.push(output_path);
+    }
+
+    Ok(paths)
+}
+
+/// Builds the wormhole circuit with `config`, along with the layout of its targets.
+fn build_wormhole_circuit(config: CircuitConfig) -> (CircuitData<F, C, D>, TargetsLayout) {
+    let circuit = WormholeCircuit::new(config);
+    let layout = circuit.targets().layout();
+    (circuit.build_circuit(), layout)
+}
+
+fn write_circuit_binaries(
+    output_path: &Path,
+    circuit_data: CircuitData<F, C, D>,
+    layout: &TargetsLayout,
+    include_prover: bool,
+) -> Result<()> {
+    write_circuit_data(output_path, circuit_data, include_prover)?;
+
+    // Describe the public input encoding for non-Rust tooling
+    let encoding_json = encoding_json()?;
+    write(output_path.join(ENCODING_DESCRIPTOR_FILE_NAME), encoding_json)?;
+    println!(
+        "Encoding descriptor saved to {}/{}",
+        output_path.display(),
+        ENCODING_DESCRIPTOR_FILE_NAME
+    );
+
+    // Let verifiers check that the targets line up with their version of the circuit
+    write(output_path.join(TARGETS_LAYOUT_FILE_NAME), serde_json::to_string(layout)?)?;
+    println!(
+        "Targets layout saved to {}/{}",
+        output_path.display(),
+        TARGETS_LAYOUT_FILE_NAME
+    );
+
+    Ok(())
+}
+
+fn write_circuit_data(
+    output_path: &Path,
+    circuit_data: CircuitData<F, C, D>,
+    include_prover: bool,
+) -> Result<()> {
+    println!("Serializing circuit data...");
+    let bytes = serialize_circuit_data(&circuit_data, include_prover)?;
 
-    let output_path = output_dir.as_ref();
     create_dir_all(output_path)?;
 
-    // Serialize common data
-    let common_bytes = common_data
-        .to_bytes(&gate_serializer)
-        .map_err(|e| anyhow!("Failed to serialize common data: {}", e))?;
-    write(output_path.join("common.bin"), common_bytes)?;
+    write(output_path.join("common.bin"), bytes.common)?;
     println!("Common data saved to {}/common.bin", output_path.display());
 
-    // Serialize verifier only data
-    let verifier_only_bytes = verifier_data
-        .verifier_only
-        .to_bytes()
-        .map_err(|e| anyhow!("Failed to serialize verifier data: {}", e))?;
-    write(output_path.join("verifier.bin"), verifier_only_bytes)?;
+    write(output_path.join("verifier.bin"), bytes.verifier)?;
     println!(
         "Verifier data saved to {}/verifier.bin",
         output_path.display()
     );
 
-    // Serialize prover only data (optional)
-    if include_prover {
-        let prover_only_bytes = prover_data
-            .prover_only
-            .to_bytes(&generator_serializer, common_data)
-            .map_err(|e| anyhow!("Failed to serialize prover data: {}", e))?;
-        write(output_path.join("prover.bin"), prover_only_bytes)?;
+    if let Some(prover) = bytes.prover {
+        write(output_path.join("prover.bin"), prover)?;
         println!("Prover data saved to {}/prover.bin", output_path.display());
     } else {
         println!("Skipping prover binary generation");
@@ -65,6 +174,216 @@ pub fn generate_circuit_binaries<P: AsRef<Path>>(
     Ok(())
 }
 
+/// The serialized artifacts of a circuit, as written to `common.bin`, `verifier.bin` and
+/// `prover.bin`.
+pub(crate) struct CircuitBytes {
+    pub(crate) common: Vec<u8>,
+    pub(crate) verifier: Vec<u8>,
+    pub(crate) prover: Option<Vec<u8>>,
+}
+
+pub(crate) fn serialize_circuit_data(
+    circuit_data: &CircuitData<F, C, D>,
+    include_prover: bool,
+) -> Result<CircuitBytes> {
+    let gate_serializer = DefaultGateSerializer;
+    let generator_serializer = DefaultGeneratorSerializer::<PoseidonGoldilocksConfig, D> {
+        _phantom: Default::default(),
+    };
+
+    let common = circuit_data
+        .common
+        .to_bytes(&gate_serializer)
+        .map_err(|e| anyhow!("Failed to serialize common data: {}", e))?;
+    let verifier = circuit_data
+        .verifier_only
+        .to_bytes()
+        .map_err(|e| anyhow!("Failed to serialize verifier data: {}", e))?;
+    let prover = if include_prover {
+        let prover = circuit_data
+            .prover_only
+            .to_bytes(&generator_serializer, &circuit_data.common)
+            .map_err(|e| anyhow!("Failed to serialize prover data: {}", e))?;
+        Some(prover)
+    } else {
+        None
+    };
+
+    Ok(CircuitBytes {
+        common,
+        verifier,
+        prover,
+    })
+}
+
+fn circuit_digest_hex(verifier_only: &VerifierOnlyCircuitData<C, D>) -> String {
+    circuit_digest_to_bytes(verifier_only.circuit_digest)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn encoding_json() -> Result<String> {
+    Ok(serde_json::to_string_pretty(&WORMHOLE_ENCODING)?)
+}
+
+/// The properties of a built circuit that decide whether its artifacts have to be redistributed.
+///
+/// The circuit digest commits to everything else, the remaining fields only make a mismatch easier
+/// to understand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitFingerprint {
+    /// The hex encoded circuit digest of the verifier data.
+    pub circuit_digest: String,
+    pub degree_bits: usize,
+    /// The ids of the gate types used by the circuit.
+    pub gates: Vec<String>,
+    pub num_public_inputs: usize,
+    /// The serialized encoding descriptor, which describes the public input layout.
+    pub encoding: String,
+}
+
+impl CircuitFingerprint {
+    pub fn new(
+        common_data: &CommonCircuitData<F, D>,
+        verifier_only: &VerifierOnlyCircuitData<C, D>,
+        encoding: String,
+    ) -> Self {
+        Self {
+            circuit_digest: circuit_digest_hex(verifier_only),
+            degree_bits: common_data.degree_bits(),
+            gates: common_data.gates.iter().map(|gate| gate.0.id()).collect(),
+            num_public_inputs: common_data.num_public_inputs,
+            encoding,
+        }
+    }
+
+    /// Reads the fingerprint of the artifacts written to `dir` by
+    /// [`generate_circuit_binaries_with_config`].
+    pub fn from_artifacts_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+
+        let common_path = dir.join("common.bin");
+        let common_bytes =
+            read(&common_path).with_context(|| format!("Failed to read {:?}", common_path))?;
+        let common_data = CommonCircuitData::from_bytes(common_bytes, &DefaultGateSerializer)
+            .map_err(|e| anyhow!("Failed to deserialize {:?}: {}", common_path, e))?;
+
+        let verifier_path = dir.join("verifier.bin");
+        let verifier_bytes =
+            read(&verifier_path).with_context(|| format!("Failed to read {:?}", verifier_path))?;
+        let verifier_only = VerifierOnlyCircuitData::from_bytes(verifier_bytes)
+            .map_err(|e| anyhow!("Failed to deserialize {:?}: {}", verifier_path, e))?;
+
+        // Artifacts generated before the descriptor existed don't have one.
+        let encoding = read_to_string(dir.join(ENCODING_DESCRIPTOR_FILE_NAME)).unwrap_or_default();
+
+        Ok(Self::new(&common_data, &verifier_only, encoding))
+    }
+
+    /// Describes how `other` differs from `self`, one line per difference. Returns an empty list
+    /// if the fingerprints match.
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        let mut diff = Vec::new();
+
+        if self.circuit_digest != other.circuit_digest {
+            diff.push(format!(
+                "circuit digest: {} -> {}",
+                self.circuit_digest,
+                other.circuit_digest
+            ));
+        }
+        if self.degree_bits != other.degree_bits {
+            diff.push(format!("degree: 2^{} -> 2^{}", self.degree_bits, other.degree_bits));
+        }
+        if self.gates.len() != other.gates.len() {
+            diff.push(format!("gate types: {} -> {}", self.gates.len(), other.gates.len()));
+        }
+        for gate in self.gates.iter().filter(|gate| !other.gates.contains(gate)) {
+            diff.push(format!("removed gate: {}", gate));
+        }
+        for gate in other.gates.iter().filter(|gate| !self.gates.contains(gate)) {
+            diff.push(format!("added gate: {}", gate));
+        }
+        if self.num_public_inputs != other.num_public_inputs {
+            diff.push(format!(
+                "public inputs: {} -> {}",
+                self.num_public_inputs,
+                other.num_public_inputs
+            ));
+        }
+        if self.encoding != other.encoding {
+            diff.push(format!("public input layout: {} changed", ENCODING_DESCRIPTOR_FILE_NAME));
+        }
+
+        diff
+    }
+}
+
+/// Rebuilds the circuit with `config` and compares it against the artifacts in `artifacts_dir`,
+/// without writing anything.
+///
+/// Returns the differences as described by [`CircuitFingerprint::diff`], so an empty list means
+/// the artifacts are up to date.
+pub fn check_circuit_binaries<P: AsRef<Path>>(
+    artifacts_dir: P,
+    config: CircuitConfig,
+) -> Result<Vec<String>> {
+    let existing = CircuitFingerprint::from_artifacts_dir(artifacts_dir)?;
+
+    println!("Building wormhole circuit...");
+    let verifier_data = WormholeCircuit::new(config).build_verifier();
+    println!("Circuit built.");
+
+    let rebuilt = CircuitFingerprint::new(
+        &verifier_data.common,
+        &verifier_data.verifier_only,
+        encoding_json()?,
+    );
+    Ok(existing.diff(&rebuilt))
+}
+
 pub fn main() -> Result<()> {
     generate_circuit_binaries("generated-bins", true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint() -> CircuitFingerprint {
+        CircuitFingerprint {
+            circuit_digest: "00".repeat(32),
+            degree_bits: 15,
+            gates: vec!["ArithmeticGate".to_string(), "PoseidonGate".to_string()],
+            num_public_inputs: 16,
+            encoding: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn matching_fingerprints_have_no_diff() {
+        assert!(fingerprint().diff(&fingerprint()).is_empty());
+    }
+
+    #[test]
+    fn diff_summarizes_changes() {
+        let existing = fingerprint();
+        let mut rebuilt = fingerprint();
+        rebuilt.circuit_digest = "11".repeat(32);
+        rebuilt.degree_bits = 16;
+        rebuilt.gates[1] = "RandomAccessGate".to_string();
+        rebuilt.num_public_inputs = 20;
+
+        assert_eq!(
+            existing.diff(&rebuilt),
+            vec![
+                format!("circuit digest: {} -> {}", "00".repeat(32), "11".repeat(32)),
+                "degree: 2^15 -> 2^16".to_string(),
+                "removed gate: PoseidonGate".to_string(),
+                "added gate: RandomAccessGate".to_string(),
+                "public inputs: 16 -> 20".to_string(),
+            ]
+        );
+    }
+}
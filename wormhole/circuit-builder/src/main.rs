@@ -1,6 +1,148 @@
-use anyhow::Result;
-use qp_wormhole_circuit_builder::generate_circuit_binaries;
+use anyhow::{bail, Result};
+use clap::Parser;
+use qp_wormhole_circuit_builder::circuits::CircuitSelection;
+use qp_wormhole_circuit_builder::fragments::Fragment;
+use qp_wormhole_circuit_builder::profiles::{generate_release_artifacts, Profile};
+use qp_wormhole_circuit_builder::reproducibility::{
+    check_artifact_hashes, record_artifact_hashes, PLONKY2_VERSION,
+};
+use qp_wormhole_circuit_builder::{
+    check_circuit_binaries, generate_circuit_artifacts, generate_circuit_binaries_with_config,
+    generate_fragment_binaries, generate_registry_entry,
+};
+use std::path::PathBuf;
+use zk_circuits_common::config::ConfigPreset;
+
+/// Builds the wormhole circuit and serializes it to `generated-bins`.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Circuit config preset: standard, fast or small-proof.
+    #[arg(long, default_value = "standard")]
+    preset: ConfigPreset,
+
+    /// Enable zero knowledge, if the preset supports it.
+    #[arg(long)]
+    zk: bool,
+
+    /// Rebuild the circuit and compare it against the existing `generated-bins` instead of
+    /// overwriting them. Exits with an error if they are out of date.
+    #[arg(long)]
+    check: bool,
+
+    /// Write the verifier artifacts to a subdirectory of this verifier registry directory named
+    /// after the circuit digest, instead of to `generated-bins`.
+    #[arg(long, conflicts_with = "check")]
+    registry: Option<PathBuf>,
+
+    /// Build the standalone circuit of a single fragment instead: nullifier,
+    /// unspendable-account or storage-proof. Its artifacts are written to a subdirectory of
+    /// `generated-bins` named after the fragment.
+    #[arg(long, conflicts_with_all = ["check", "registry"])]
+    fragment: Option<Fragment>,
+
+    /// Build one circuit or all of them: wormhole, vote (the deposit vote circuit) or all. The
+    /// artifacts of each circuit are written to a subdirectory of `generated-bins` named after
+    /// it, so every circuit is deployed from the same layout.
+    #[arg(long, conflicts_with_all = ["check", "registry", "fragment"])]
+    circuit: Option<CircuitSelection>,
+
+    /// Build every release profile (zk, no-zk, small-proof and vote) in parallel into a
+    /// subdirectory of this directory named after the builder version, with a manifest. The
+    /// `--preset` and `--zk` flags are ignored, each profile has its own config.
+    #[arg(long, conflicts_with_all = ["check", "registry", "fragment", "circuit"])]
+    release: Option<PathBuf>,
+
+    /// Build every release profile and record the SHA-256 of its artifacts for the current
+    /// plonky2 version in this file, e.g. `artifact-hashes.json`. Nothing else is written.
+    #[arg(long, conflicts_with_all = ["check", "registry", "fragment", "circuit", "release"])]
+    record_hashes: Option<PathBuf>,
+
+    /// Build every release profile and compare the SHA-256 of its artifacts against the hashes
+    /// recorded in this file for the current plonky2 version. Exits with an error if the host
+    /// builds different artifacts.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "check",
+            "registry",
+            "fragment",
+            "circuit",
+            "release",
+            "record_hashes"
+        ]
+    )]
+    check_hashes: Option<PathBuf>,
+}
 
 fn main() -> Result<()> {
-    generate_circuit_binaries("generated-bins", true)
+    let args = Args::parse();
+
+    if let Some(release) = args.release {
+        let path = generate_release_artifacts(release, &Profile::ALL, true)?;
+        println!("Release artifacts written to {}", path.display());
+        return Ok(());
+    }
+
+    if let Some(path) = args.record_hashes {
+        record_artifact_hashes(&path, &Profile::ALL)?;
+        println!(
+            "Artifact hashes for plonky2 {} recorded in {}",
+            PLONKY2_VERSION,
+            path.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(path) = args.check_hashes {
+        let diff = check_artifact_hashes(&path, &Profile::ALL)?;
+        if !diff.is_empty() {
+            for line in &diff {
+                eprintln!("  {}", line);
+            }
+            bail!(
+                "the artifacts built on this host differ from those recorded for plonky2 {}",
+                PLONKY2_VERSION
+            );
+        }
+        println!("The artifacts match those recorded for plonky2 {}", PLONKY2_VERSION);
+        return Ok(());
+    }
+
+    let config = args.preset.config(args.zk);
+    println!("Using the {} config preset", args.preset.name());
+
+    if args.check {
+        let diff = check_circuit_binaries("generated-bins", config)?;
+        if !diff.is_empty() {
+            for line in &diff {
+                eprintln!("  {}", line);
+            }
+            bail!("generated-bins is out of date, re-run the circuit builder to regenerate it");
+        }
+        println!("generated-bins is up to date");
+        return Ok(());
+    }
+
+    if let Some(registry) = args.registry {
+        let entry = generate_registry_entry(registry, config)?;
+        println!("Registry entry written to {}", entry.display());
+        return Ok(());
+    }
+
+    if let Some(fragment) = args.fragment {
+        let path = generate_fragment_binaries("generated-bins", fragment, config, true)?;
+        println!("Fragment artifacts written to {}", path.display());
+        return Ok(());
+    }
+
+    if let Some(selection) = args.circuit {
+        let paths =
+            generate_circuit_artifacts("generated-bins", &selection.circuits(), config, true)?;
+        for path in paths {
+            println!("Circuit artifacts written to {}", path.display());
+        }
+        return Ok(());
+    }
+
+    generate_circuit_binaries_with_config("generated-bins", config, true)
 }
@@ -0,0 +1,286 @@
+//! Release artifacts for every circuit profile in one run.
+//!
+//! A release ships the wormhole circuit with and without zero knowledge, with the small-proof
+//! preset, and the deposit vote circuit. [`generate_release_artifacts`] builds the requested
+//! [`Profile`]s in parallel and writes them to `<output_dir>/v<version>/<profile>`, next to a
+//! [`MANIFEST_FILE_NAME`] listing the circuit digest and files of each profile.
+//!
+//! The profiles are written to a staging directory first, which is only renamed to the versioned
+//! directory once every profile and the manifest have been written. A failed run therefore never
+//! leaves a partial release behind, and an existing release is never overwritten.
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::fs::{create_dir_all, remove_dir_all, rename, write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::deposit_vote::circuit_logic::DepositVoteCircuit;
+use wormhole_circuit::encoding::ENCODING_DESCRIPTOR_FILE_NAME;
+use wormhole_circuit::layout::TARGETS_LAYOUT_FILE_NAME;
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::config::ConfigPreset;
+
+use crate::{
+    build_wormhole_circuit, circuit_digest_hex, write_circuit_binaries, write_circuit_data,
+};
+
+/// The name of the manifest written next to the profile directories.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// A circuit and config whose artifacts are part of a release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// The wormhole circuit with the standard preset and zero knowledge.
+    Zk,
+    /// The wormhole circuit with the standard preset, without zero knowledge.
+    NoZk,
+    /// The wormhole circuit with the small-proof preset, without zero knowledge.
+    SmallProof,
+    /// The deposit vote circuit with the standard preset, without zero knowledge.
+    Vote,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 4] = [Self::Zk, Self::NoZk, Self::SmallProof, Self::Vote];
+
+    pub fn config(self) -> CircuitConfig {
+        match self {
+            Self::Zk => ConfigPreset::Standard.config(true),
+            Self::NoZk | Self::Vote => ConfigPreset::Standard.config(false),
+            Self::SmallProof => ConfigPreset::SmallProof.config(false),
+        }
+    }
+
+    pub fn build_circuit(self) -> CircuitData<F, C, D> {
+        match self {
+            Self::Zk | Self::NoZk | Self::SmallProof => {
+                WormholeCircuit::new(self.config()).build_circuit()
+            }
+            Self::Vote => DepositVoteCircuit::new(self.config()).build_circuit(),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Zk => "zk",
+            Self::NoZk => "no-zk",
+            Self::SmallProof => "small-proof",
+            Self::Vote => "vote",
+        }
+    }
+
+    /// Whether the public inputs follow the wormhole layout described by the encoding descriptor.
+    fn has_wormhole_encoding(self) -> bool {
+        self != Self::Vote
+    }
+}
+
+impl FromStr for Profile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zk" => Ok(Self::Zk),
+            "no-zk" => Ok(Self::NoZk),
+            "small-proof" => Ok(Self::SmallProof),
+            "vote" => Ok(Self::Vote),
+            _ => bail!(
+                "unknown profile `{}`, expected one of: zk, no-zk, small-proof, vote",
+                s
+            ),
+        }
+    }
+}
+
+/// The contents of [`MANIFEST_FILE_NAME`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReleaseManifest {
+    /// The version of the circuit builder that wrote the release.
+    pub version: String,
+    pub profiles: Vec<ProfileManifest>,
+}
+
+/// The artifacts of one profile in a [`ReleaseManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileManifest {
+    pub name: String,
+    /// The hex encoded circuit digest of the verifier data.
+    pub circuit_digest: String,
+    pub degree_bits: usize,
+    pub num_public_inputs: usize,
+    pub zero_knowledge: bool,
+    /// The files in the profile directory.
+    pub files: Vec<String>,
+}
+
+/// The directory of the release of the current builder version in `output_dir`.
+pub fn release_dir<P: AsRef<Path>>(output_dir: P) -> PathBuf {
+    output_dir.as_ref().join(format!("v{}", env!("CARGO_PKG_VERSION")))
+}
+
+/// Builds `profiles` in parallel and writes their artifacts and a manifest to
+/// [`release_dir`], see the [module documentation](self). Returns the path of the release.
+///
+/// # Errors
+///
+/// Returns an error if no or duplicate profiles are given, the release directory already exists,
+/// or building or writing any profile fails. Nothing is left in `output_dir` in that case.
+pub fn generate_release_artifacts<P: AsRef<Path>>(
+    output_dir: P,
+    profiles: &[Profile],
+    include_prover: bool,
+) -> Result<PathBuf> {
+    if profiles.is_empty() {
+        bail!("no profiles to build");
+    }
+    for (i, profile) in profiles.iter().enumerate() {
+        if profiles[..i].contains(profile) {
+            bail!("profile `{}` is given more than once", profile.name());
+        }
+    }
+
+    let release_path = release_dir(&output_dir);
+    if release_path.exists() {
+        bail!(
+            "{} already exists, remove it to rebuild the release",
+            release_path.display()
+        );
+    }
+    let staging_path = output_dir.as_ref().join(format!(
+        ".{}.partial",
+        release_path.file_name().unwrap().to_string_lossy()
+    ));
+    if staging_path.exists() {
+        // Left behind by an interrupted run.
+        remove_dir_all(&staging_path)
+            .with_context(|| format!("Failed to remove {:?}", staging_path))?;
+    }
+    create_dir_all(&staging_path)?;
+
+    let result = write_release(&staging_path, profiles, include_prover)
+        .and_then(|()| rename(&staging_path, &release_path).map_err(Into::into));
+    if result.is_err() {
+        let _ = remove_dir_all(&staging_path);
+    }
+    result?;
+
+    Ok(release_path)
+}
+
+fn write_release(staging_path: &Path, profiles: &[Profile], include_prover: bool) -> Result<()> {
+    // Every profile writes to its own directory, so they can't interfere with each other.
+    let results: Vec<Result<ProfileManifest>> = thread::scope(|scope| {
+        let handles: Vec<_> = profiles
+            .iter()
+            .map(|&profile| {
+                let profile_path = staging_path.join(profile.name());
+                scope.spawn(move || write_profile(&profile_path, profile, include_prover))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("profile build panicked")))
+            })
+            .collect()
+    });
+
+    let mut manifest = ReleaseManifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        profiles: Vec::with_capacity(profiles.len()),
+    };
+    for (profile, result) in profiles.iter().zip(results) {
+        let profile_manifest =
+            result.with_context(|| format!("Failed to build profile `{}`", profile.name()))?;
+        manifest.profiles.push(profile_manifest);
+    }
+
+    write(staging_path.join(MANIFEST_FILE_NAME), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(())
+}
+
+fn write_profile(
+    profile_path: &Path,
+    profile: Profile,
+    include_prover: bool,
+) -> Result<ProfileManifest> {
+    println!("Building {} profile...", profile.name());
+    let (circuit_data, layout) = if profile.has_wormhole_encoding() {
+        let (circuit_data, layout) = build_wormhole_circuit(profile.config());
+        (circuit_data, Some(layout))
+    } else {
+        (profile.build_circuit(), None)
+    };
+    println!("Profile {} built.", profile.name());
+
+    let common_data = &circuit_data.common;
+    let mut profile_manifest = ProfileManifest {
+        name: profile.name().to_string(),
+        circuit_digest: circuit_digest_hex(&circuit_data.verifier_only),
+        degree_bits: common_data.degree_bits(),
+        num_public_inputs: common_data.num_public_inputs,
+        zero_knowledge: common_data.config.zero_knowledge,
+        files: vec!["common.bin".to_string(), "verifier.bin".to_string()],
+    };
+    if include_prover {
+        profile_manifest.files.push("prover.bin".to_string());
+    }
+
+    if let Some(layout) = layout {
+        write_circuit_binaries(profile_path, circuit_data, &layout, include_prover)?;
+        profile_manifest.files.push(ENCODING_DESCRIPTOR_FILE_NAME.to_string());
+        profile_manifest.files.push(TARGETS_LAYOUT_FILE_NAME.to_string());
+    } else {
+        write_circuit_data(profile_path, circuit_data, include_prover)?;
+    }
+
+    Ok(profile_manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_names_round_trip() {
+        for profile in Profile::ALL {
+            assert_eq!(profile.name().parse::<Profile>().unwrap(), profile);
+        }
+        assert!("standard".parse::<Profile>().is_err());
+    }
+
+    #[test]
+    fn profiles_have_the_expected_zero_knowledge() {
+        assert!(Profile::Zk.config().zero_knowledge);
+        for profile in [Profile::NoZk, Profile::SmallProof, Profile::Vote] {
+            assert!(!profile.config().zero_knowledge, "{}", profile.name());
+        }
+    }
+
+    #[test]
+    fn existing_release_is_not_overwritten() {
+        let output_dir = std::env::temp_dir().join("qp-circuit-builder-existing-release");
+        let release_path = release_dir(&output_dir);
+        create_dir_all(&release_path).unwrap();
+
+        let err = generate_release_artifacts(&output_dir, &[Profile::Vote], false).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{}", err);
+
+        remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn duplicate_profiles_are_rejected() {
+        let output_dir = std::env::temp_dir().join("qp-circuit-builder-duplicate-profiles");
+        assert!(
+            generate_release_artifacts(&output_dir, &[Profile::Zk, Profile::Zk], false).is_err()
+        );
+        assert!(generate_release_artifacts(&output_dir, &[], false).is_err());
+        assert!(!output_dir.exists());
+    }
+}
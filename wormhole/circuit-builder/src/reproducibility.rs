@@ -0,0 +1,217 @@
+//! Reproducibility of the serialized circuit artifacts across hosts.
+//!
+//! Provers and verifiers on different machines only interoperate if they build byte-identical
+//! artifacts, so anything platform dependent in circuit building, such as gates or generators
+//! ordered by `HashMap` iteration, breaks artifact distribution. [`ARTIFACT_HASHES_FILE_NAME`]
+//! records the SHA-256 of the artifacts of each [`Profile`] per plonky2 version:
+//! [`record_artifact_hashes`] writes the record of the host's build and
+//! [`check_artifact_hashes`] compares a fresh build against it, so running the check on every
+//! platform of a fleet shows whether they all build the recorded artifacts.
+//!
+//! Hashes are recorded per [`PLONKY2_VERSION`] because a plonky2 upgrade may legitimately change
+//! the serialization. A version without a record can't be checked, so the record has to be
+//! written once after every upgrade.
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+use crate::profiles::Profile;
+use crate::serialize_circuit_data;
+
+/// The file the artifact hashes are recorded in, checked in at the root of this crate.
+pub const ARTIFACT_HASHES_FILE_NAME: &str = "artifact-hashes.json";
+
+/// The qp-plonky2 version the builder was compiled against, as resolved in `Cargo.lock`.
+pub const PLONKY2_VERSION: &str = env!("QP_PLONKY2_VERSION");
+
+/// The hex encoded SHA-256 hashes of the artifacts of one profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileHashes {
+    pub name: String,
+    pub common: String,
+    pub verifier: String,
+    pub prover: String,
+}
+
+/// The artifact hashes built with one plonky2 version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactHashRecord {
+    pub plonky2_version: String,
+    pub profiles: Vec<ProfileHashes>,
+}
+
+impl ProfileHashes {
+    /// Builds `profile` and hashes its serialized artifacts, including the prover data.
+    pub fn build(profile: Profile) -> Result<Self> {
+        println!("Building {} profile...", profile.name());
+        let bytes = serialize_circuit_data(&profile.build_circuit(), true)?;
+        let prover = bytes.prover.expect("prover data is requested");
+
+        Ok(Self {
+            name: profile.name().to_string(),
+            common: sha256_hex(&bytes.common),
+            verifier: sha256_hex(&bytes.verifier),
+            prover: sha256_hex(&prover),
+        })
+    }
+
+    /// Describes how `other` differs from `self`, one line per artifact. Returns an empty list if
+    /// the hashes match.
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        [
+            ("common.bin", &self.common, &other.common),
+            ("verifier.bin", &self.verifier, &other.verifier),
+            ("prover.bin", &self.prover, &other.prover),
+        ]
+        .into_iter()
+        .filter(|(_, recorded, built)| recorded != built)
+        .map(|(file, recorded, built)| format!("{}/{}: {} -> {}", self.name, file, recorded, built))
+        .collect()
+    }
+}
+
+impl ArtifactHashRecord {
+    /// Builds `profiles` with the current plonky2 version and hashes their artifacts.
+    pub fn build(profiles: &[Profile]) -> Result<Self> {
+        let profiles = profiles
+            .iter()
+            .map(|&profile| ProfileHashes::build(profile))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            plonky2_version: PLONKY2_VERSION.to_string(),
+            profiles,
+        })
+    }
+}
+
+/// Reads the records in `path`. A missing file has no records.
+pub fn read_artifact_hashes<P: AsRef<Path>>(path: P) -> Result<Vec<ArtifactHashRecord>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse {:?}", path))
+}
+
+/// Builds `profiles` and records their hashes in `path` for [`PLONKY2_VERSION`], replacing the
+/// previous record of that version and keeping those of other versions. Returns the new record.
+pub fn record_artifact_hashes<P: AsRef<Path>>(
+    path: P,
+    profiles: &[Profile],
+) -> Result<ArtifactHashRecord> {
+    let path = path.as_ref();
+    let mut records = read_artifact_hashes(path)?;
+    let record = ArtifactHashRecord::build(profiles)?;
+
+    records.retain(|existing| existing.plonky2_version != record.plonky2_version);
+    records.push(record.clone());
+    records.sort_by(|a, b| a.plonky2_version.cmp(&b.plonky2_version));
+    write(path, serde_json::to_string_pretty(&records)? + "\n")?;
+
+    Ok(record)
+}
+
+/// Builds `profiles` on the host and compares their hashes against the record of
+/// [`PLONKY2_VERSION`] in `path`, without writing anything.
+///
+/// Returns the differences as described by [`ProfileHashes::diff`], so an empty list means the
+/// host builds the recorded artifacts.
+///
+/// # Errors
+///
+/// Returns an error if `path` has no record for [`PLONKY2_VERSION`] or any of `profiles`.
+pub fn check_artifact_hashes<P: AsRef<Path>>(
+    path: P,
+    profiles: &[Profile],
+) -> Result<Vec<String>> {
+    let path = path.as_ref();
+    let records = read_artifact_hashes(path)?;
+    let record = records
+        .iter()
+        .find(|record| record.plonky2_version == PLONKY2_VERSION)
+        .ok_or_else(|| {
+            anyhow!(
+                "{:?} has no artifact hashes for plonky2 {}, record them first",
+                path,
+                PLONKY2_VERSION
+            )
+        })?;
+
+    let mut diff = Vec::new();
+    for &profile in profiles {
+        let Some(recorded) = record.profiles.iter().find(|p| p.name == profile.name()) else {
+            bail!(
+                "{:?} has no artifact hashes for profile `{}` with plonky2 {}",
+                path,
+                profile.name(),
+                PLONKY2_VERSION
+            );
+        };
+        diff.extend(recorded.diff(&ProfileHashes::build(profile)?));
+    }
+    Ok(diff)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn hashes_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(ARTIFACT_HASHES_FILE_NAME)
+    }
+
+    #[test]
+    fn plonky2_version_is_resolved() {
+        assert_ne!(PLONKY2_VERSION, "unknown");
+    }
+
+    #[test]
+    fn diff_lists_changed_artifacts() {
+        let recorded = ProfileHashes {
+            name: "no-zk".to_string(),
+            common: "00".repeat(32),
+            verifier: "11".repeat(32),
+            prover: "22".repeat(32),
+        };
+        let mut built = recorded.clone();
+        assert!(recorded.diff(&built).is_empty());
+
+        built.prover = "33".repeat(32);
+        assert_eq!(
+            recorded.diff(&built),
+            vec![format!("no-zk/prover.bin: {} -> {}", "22".repeat(32), "33".repeat(32))]
+        );
+    }
+
+    #[test]
+    fn artifacts_are_byte_identical_across_builds() {
+        // Every `HashMap` gets its own random seed, so ordering by iteration shows up as two
+        // builds in one process differing.
+        let first = ProfileHashes::build(Profile::NoZk).unwrap();
+        let second = ProfileHashes::build(Profile::NoZk).unwrap();
+        assert!(first.diff(&second).is_empty(), "{:?}", first.diff(&second));
+    }
+
+    #[test]
+    fn artifacts_match_recorded_hashes() {
+        let records = read_artifact_hashes(hashes_path()).unwrap();
+        if !records.iter().any(|record| record.plonky2_version == PLONKY2_VERSION) {
+            eprintln!("no artifact hashes recorded for plonky2 {}", PLONKY2_VERSION);
+            return;
+        }
+
+        let diff = check_artifact_hashes(hashes_path(), &[Profile::NoZk]).unwrap();
+        assert!(diff.is_empty(), "{}", diff.join("\n"));
+    }
+}
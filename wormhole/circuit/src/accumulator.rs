@@ -0,0 +1,866 @@
+//! Nullifier accumulator.
+//!
+//! Instead of storing every spent nullifier, the chain can keep the root of an indexed Merkle
+//! tree of them and accept batches of insertions proven by this circuit. Every leaf holds a
+//! nullifier together with the next larger nullifier in the tree and that one's leaf index, so
+//! the leaves form a sorted linked list. A nullifier `n` is inserted by proving a "low" leaf with
+//! `value < n < next_value`, which shows that `n` is not in the tree, pointing that leaf at `n`,
+//! and filling the next empty leaf with `n`. Leaf 0 is a sentinel with the zero digest as its
+//! value, and a zero `next_value` ends the list.
+//!
+//! Leaves are filled in order, so besides the root the chain only stores the index of the next
+//! empty leaf, and checks it against `start_index` before accepting an update.
+//!
+//! The same low leaf proves on its own that a nullifier is not spent, see
+//! [`NonMembershipProof`]. The wormhole circuit can include that proof, so chains verifying
+//! claims statelessly only compare its root with the current one. See
+//! [`crate::circuit::circuit_logic::WormholeCircuit::with_spent_nullifier_accumulator`].
+//!
+//! ## Public Inputs
+//!
+//! - `old_root`: the root before the batch.
+//! - `new_root`: the root after the batch.
+//! - `start_index`: the leaf index of the first inserted nullifier.
+//! - `nullifiers`: the inserted nullifiers, in insertion order.
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{bail, Context};
+use plonky2::{
+    field::types::{Field, PrimeField64},
+    hash::{
+        hash_types::{HashOut, HashOutTarget},
+        poseidon::PoseidonHash,
+    },
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, ShapeError, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::u32_limbs_less_than;
+use zk_circuits_common::merkle::{self, hash_pair, merkle_proof_root, merkle_proof_verify};
+use zk_circuits_common::utils::{felts_to_hashout, Digest, ZERO_DIGEST};
+
+use crate::types::NullifierHash;
+
+pub const OLD_ROOT_START_INDEX: usize = 0;
+pub const OLD_ROOT_END_INDEX: usize = 4;
+pub const NEW_ROOT_START_INDEX: usize = 4;
+pub const NEW_ROOT_END_INDEX: usize = 8;
+pub const START_INDEX_INDEX: usize = 8;
+pub const NULLIFIERS_START_INDEX: usize = 9;
+
+/// The deepest tree supported, holding up to 2^32 nullifiers.
+pub const MAX_ACCUMULATOR_DEPTH: usize = 32;
+
+/// A leaf of the nullifier tree.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexedLeaf {
+    pub value: Digest,
+    pub next_value: Digest,
+    pub next_index: u64,
+}
+
+impl IndexedLeaf {
+    /// `H(value || next_value || next_index)`.
+    pub fn hash(&self) -> Digest {
+        let mut preimage = Vec::with_capacity(9);
+        preimage.extend(self.value);
+        preimage.extend(self.next_value);
+        preimage.push(F::from_canonical_u64(self.next_index));
+        PoseidonHash::hash_no_pad(&preimage).elements
+    }
+}
+
+/// The order of nullifiers in the tree: the canonical values of the digest elements compared
+/// lexicographically, first element first.
+fn digest_key(digest: &Digest) -> [u64; 4] {
+    digest.map(|felt| felt.to_canonical_u64())
+}
+
+/// A proof that a nullifier is not in the tree with root `root`, produced by
+/// [`NullifierAccumulator::prove_non_membership`].
+#[derive(Debug, Clone)]
+pub struct NonMembershipProof {
+    pub root: Digest,
+    /// The leaf with the largest value below the nullifier.
+    pub low_leaf: IndexedLeaf,
+    pub low_leaf_index: u64,
+    pub siblings: Vec<Digest>,
+}
+
+impl NonMembershipProof {
+    /// Checks the proof for `nullifier` outside the circuit, with the same rules the circuit
+    /// enforces.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the low leaf is not in the tree or does not enclose `nullifier`.
+    pub fn verify(&self, nullifier: &NullifierHash) -> anyhow::Result<()> {
+        let depth = self.siblings.len();
+        if depth == 0 || depth > MAX_ACCUMULATOR_DEPTH || self.low_leaf_index >> depth != 0 {
+            bail!(
+                "low leaf index {} is out of range for a tree of depth {}",
+                self.low_leaf_index,
+                depth
+            );
+        }
+
+        let path_indices: Vec<bool> = (0..depth)
+            .map(|height| (self.low_leaf_index >> height) & 1 == 1)
+            .collect();
+        let root = merkle::merkle_root::<PoseidonHash>(
+            self.low_leaf.hash(),
+            &path_indices,
+            &self.siblings,
+        );
+        if root != self.root {
+            bail!("low leaf is not in the tree");
+        }
+
+        let key = digest_key(&nullifier.to_felts());
+        let is_last = self.low_leaf.next_value == ZERO_DIGEST;
+        if digest_key(&self.low_leaf.value) >= key
+            || !(is_last || key < digest_key(&self.low_leaf.next_value))
+        {
+            bail!("low leaf does not enclose the nullifier, which may be spent");
+        }
+        Ok(())
+    }
+}
+
+/// The witness for inserting one nullifier.
+#[derive(Debug, Clone)]
+pub struct Insertion {
+    pub nullifier: NullifierHash,
+    /// The low leaf before it is pointed at the nullifier.
+    pub low_leaf: IndexedLeaf,
+    pub low_leaf_index: u64,
+    pub low_leaf_siblings: Vec<Digest>,
+    /// The siblings of the empty leaf the nullifier is inserted at, after the low leaf update.
+    pub new_leaf_siblings: Vec<Digest>,
+}
+
+/// Inputs to the accumulator circuit, produced by [`NullifierAccumulator::insert_batch`].
+#[derive(Debug, Clone)]
+pub struct AccumulatorUpdate {
+    pub old_root: Digest,
+    pub new_root: Digest,
+    pub start_index: u64,
+    pub insertions: Vec<Insertion>,
+}
+
+/// An indexed Merkle tree of spent nullifiers.
+#[derive(Debug, Clone)]
+pub struct NullifierAccumulator {
+    depth: usize,
+    leaves: Vec<IndexedLeaf>,
+    /// The hashes of the filled nodes of every level, from the leaves to the root.
+    levels: Vec<Vec<Digest>>,
+    /// The hash of an empty subtree of every height.
+    empty_hashes: Vec<Digest>,
+}
+
+impl NullifierAccumulator {
+    /// Creates a tree of the given depth holding only the sentinel leaf.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `depth` is zero or above [`MAX_ACCUMULATOR_DEPTH`].
+    pub fn new(depth: usize) -> anyhow::Result<Self> {
+        if depth == 0 || depth > MAX_ACCUMULATOR_DEPTH {
+            bail!(
+                "accumulator depth must be between 1 and {}, got: {}",
+                MAX_ACCUMULATOR_DEPTH,
+                depth
+            );
+        }
+
+        let mut empty_hashes = vec![ZERO_DIGEST];
+        for height in 0..depth {
+            let empty = empty_hashes[height];
+            empty_hashes.push(hash_pair::<PoseidonHash>(&empty, &empty));
+        }
+
+        let mut accumulator = Self {
+            depth,
+            leaves: Vec::new(),
+            levels: vec![Vec::new(); depth + 1],
+            empty_hashes,
+        };
+        accumulator.set_leaf(0, IndexedLeaf::default());
+        Ok(accumulator)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn root(&self) -> Digest {
+        self.node(self.depth, 0)
+    }
+
+    /// The index of the next empty leaf, which the chain stores next to the root.
+    pub fn next_index(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    pub fn contains(&self, nullifier: &NullifierHash) -> bool {
+        let value = nullifier.to_felts();
+        value != ZERO_DIGEST && self.leaves.iter().any(|leaf| leaf.value == value)
+    }
+
+    /// Proves that `nullifier` is not in the tree, against the current root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the nullifier is the zero digest or already in the tree.
+    pub fn prove_non_membership(
+        &self,
+        nullifier: &NullifierHash,
+    ) -> anyhow::Result<NonMembershipProof> {
+        let value = nullifier.to_felts();
+        if value == ZERO_DIGEST {
+            bail!("nullifier is the zero digest");
+        }
+        if self.contains(nullifier) {
+            bail!("nullifier is already spent");
+        }
+
+        let (low_leaf_index, low_leaf) = self.low_leaf(&value);
+        Ok(NonMembershipProof {
+            root: self.root(),
+            low_leaf,
+            low_leaf_index: low_leaf_index as u64,
+            siblings: self.siblings(low_leaf_index),
+        })
+    }
+
+    /// Inserts `nullifiers` in order and returns the witness proving the update.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the tree unchanged, if a nullifier is the zero digest, already
+    /// in the tree or repeated in the batch, or the tree has no room for the batch.
+    pub fn insert_batch(
+        &mut self,
+        nullifiers: &[NullifierHash],
+    ) -> anyhow::Result<AccumulatorUpdate> {
+        let capacity = 1usize << self.depth;
+        if self.leaves.len() + nullifiers.len() > capacity {
+            bail!(
+                "accumulator has room for {} more nullifiers, got: {}",
+                capacity - self.leaves.len(),
+                nullifiers.len()
+            );
+        }
+        for (i, nullifier) in nullifiers.iter().enumerate() {
+            if nullifier.to_felts() == ZERO_DIGEST {
+                bail!("nullifier {} is the zero digest", i);
+            }
+            if self.contains(nullifier) || nullifiers[..i].contains(nullifier) {
+                bail!("nullifier {} is already spent", i);
+            }
+        }
+
+        let old_root = self.root();
+        let start_index = self.next_index();
+        let insertions = nullifiers
+            .iter()
+            .map(|&nullifier| self.insert(nullifier))
+            .collect();
+
+        Ok(AccumulatorUpdate {
+            old_root,
+            new_root: self.root(),
+            start_index,
+            insertions,
+        })
+    }
+
+    /// The index and value of the leaf with the largest value below `value`.
+    fn low_leaf(&self, value: &Digest) -> (usize, IndexedLeaf) {
+        let key = digest_key(value);
+        let (index, &leaf) = self
+            .leaves
+            .iter()
+            .enumerate()
+            .filter(|(_, leaf)| digest_key(&leaf.value) < key)
+            .max_by_key(|(_, leaf)| digest_key(&leaf.value))
+            .expect("the sentinel leaf is below every nullifier");
+        (index, leaf)
+    }
+
+    fn insert(&mut self, nullifier: NullifierHash) -> Insertion {
+        let value = nullifier.to_felts();
+        let (low_leaf_index, low_leaf) = self.low_leaf(&value);
+        let low_leaf_siblings = self.siblings(low_leaf_index);
+
+        let new_index = self.leaves.len();
+        self.set_leaf(
+            low_leaf_index,
+            IndexedLeaf {
+                next_value: value,
+                next_index: new_index as u64,
+                ..low_leaf
+            },
+        );
+        let new_leaf_siblings = self.siblings(new_index);
+        self.set_leaf(
+            new_index,
+            IndexedLeaf {
+                value,
+                next_value: low_leaf.next_value,
+                next_index: low_leaf.next_index,
+            },
+        );
+
+        Insertion {
+            nullifier,
+            low_leaf,
+            low_leaf_index: low_leaf_index as u64,
+            low_leaf_siblings,
+            new_leaf_siblings,
+        }
+    }
+
+    fn node(&self, height: usize, index: usize) -> Digest {
+        self.levels[height]
+            .get(index)
+            .copied()
+            .unwrap_or(self.empty_hashes[height])
+    }
+
+    fn siblings(&self, mut index: usize) -> Vec<Digest> {
+        (0..self.depth)
+            .map(|height| {
+                let sibling = self.node(height, index ^ 1);
+                index >>= 1;
+                sibling
+            })
+            .collect()
+    }
+
+    /// Sets leaf `index`, which is either filled or the next empty leaf, and updates its path.
+    fn set_leaf(&mut self, mut index: usize, leaf: IndexedLeaf) {
+        if index == self.leaves.len() {
+            self.leaves.push(leaf);
+        } else {
+            self.leaves[index] = leaf;
+        }
+
+        let mut hash = leaf.hash();
+        for height in 0..=self.depth {
+            let level = &mut self.levels[height];
+            if index == level.len() {
+                level.push(hash);
+            } else {
+                level[index] = hash;
+            }
+
+            if height < self.depth {
+                let parent = index / 2;
+                hash = hash_pair::<PoseidonHash>(
+                    &self.node(height, 2 * parent),
+                    &self.node(height, 2 * parent + 1),
+                );
+                index = parent;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexedLeafTargets {
+    pub value: HashOutTarget,
+    pub next_value: HashOutTarget,
+    pub next_index: Target,
+}
+
+impl IndexedLeafTargets {
+    pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self {
+            value: builder.add_virtual_hash(),
+            next_value: builder.add_virtual_hash(),
+            next_index: builder.add_virtual_target(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InsertionTargets {
+    pub nullifier: HashOutTarget,
+    pub low_leaf: IndexedLeafTargets,
+    pub low_leaf_index: Target,
+    pub low_leaf_siblings: Vec<HashOutTarget>,
+    pub new_leaf_siblings: Vec<HashOutTarget>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccumulatorTargets {
+    pub old_root: HashOutTarget,
+    pub new_root: HashOutTarget,
+    pub start_index: Target,
+    pub insertions: Vec<InsertionTargets>,
+}
+
+impl AccumulatorTargets {
+    /// Targets for inserting batches of `batch_size` nullifiers into a tree of depth `depth`.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, depth: usize, batch_size: usize) -> Self {
+        let old_root = builder.add_virtual_hash_public_input();
+        let new_root = builder.add_virtual_hash_public_input();
+        let start_index = builder.add_virtual_public_input();
+        let insertions = (0..batch_size)
+            .map(|_| InsertionTargets {
+                nullifier: builder.add_virtual_hash_public_input(),
+                low_leaf: IndexedLeafTargets::new(builder),
+                low_leaf_index: builder.add_virtual_target(),
+                low_leaf_siblings: builder.add_virtual_hashes(depth),
+                new_leaf_siblings: builder.add_virtual_hashes(depth),
+            })
+            .collect();
+
+        Self {
+            old_root,
+            new_root,
+            start_index,
+            insertions,
+        }
+    }
+
+    /// The depth of the tree these targets insert into.
+    pub fn depth(&self) -> usize {
+        self.insertions
+            .first()
+            .map_or(0, |insertion| insertion.low_leaf_siblings.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NonMembershipTargets {
+    pub root: HashOutTarget,
+    /// Shared with the circuit proving the nullifier, which fills it.
+    pub nullifier: HashOutTarget,
+    pub low_leaf: IndexedLeafTargets,
+    pub low_leaf_index: Target,
+    pub siblings: Vec<HashOutTarget>,
+}
+
+impl NonMembershipTargets {
+    /// Targets proving that `nullifier` is not in a tree of depth `depth`. The root is
+    /// registered as public inputs.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, nullifier: HashOutTarget, depth: usize) -> Self {
+        Self {
+            root: builder.add_virtual_hash_public_input(),
+            nullifier,
+            low_leaf: IndexedLeafTargets::new(builder),
+            low_leaf_index: builder.add_virtual_target(),
+            siblings: builder.add_virtual_hashes(depth),
+        }
+    }
+
+    /// The depth of the tree these targets prove non-membership in.
+    pub fn depth(&self) -> usize {
+        self.siblings.len()
+    }
+}
+
+fn leaf_hash(
+    builder: &mut CircuitBuilder<F, D>,
+    value: HashOutTarget,
+    next_value: HashOutTarget,
+    next_index: Target,
+) -> HashOutTarget {
+    let mut preimage = Vec::with_capacity(9);
+    preimage.extend(value.elements);
+    preimage.extend(next_value.elements);
+    preimage.push(next_index);
+    builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage)
+}
+
+/// Computes the root of the tree with `leaf` at the leaf index given by `index_bits`, least
+/// significant bit first.
+fn merkle_root(
+    builder: &mut CircuitBuilder<F, D>,
+    leaf: HashOutTarget,
+    index_bits: &[BoolTarget],
+    siblings: &[HashOutTarget],
+) -> HashOutTarget {
+    merkle_proof_root::<PoseidonHash, F, D>(builder, leaf, index_bits, siblings, None)
+}
+
+/// Splits a digest into big-endian 32-bit limbs, ordered like [`digest_key`].
+fn digest_limbs(builder: &mut CircuitBuilder<F, D>, digest: HashOutTarget) -> Vec<Target> {
+    let zero = builder.zero();
+    let max_limb = builder.constant(F::from_canonical_u32(u32::MAX));
+    let mut limbs = Vec::with_capacity(8);
+    for &element in &digest.elements {
+        let (low, high) = builder.split_low_high(element, 32, 64);
+        // `low + high * 2^32` wraps around the field order only if `high` is all ones and `low`
+        // isn't zero. Ruling that out makes the limbs those of the canonical value.
+        let high_is_max = builder.is_equal(high, max_limb);
+        let wrapped = builder.mul(high_is_max.target, low);
+        builder.connect(wrapped, zero);
+        limbs.push(high);
+        limbs.push(low);
+    }
+    limbs
+}
+
+/// Asserts that `low_leaf` is at leaf `low_leaf_index` of the tree with root `root`, and that
+/// `value < nullifier < next_value`, where a zero `next_value` ends the list. Returns the bits of
+/// the leaf index, least significant first.
+fn low_leaf_circuit(
+    builder: &mut CircuitBuilder<F, D>,
+    root: HashOutTarget,
+    nullifier: HashOutTarget,
+    low_leaf: &IndexedLeafTargets,
+    low_leaf_index: Target,
+    siblings: &[HashOutTarget],
+) -> Vec<BoolTarget> {
+    let one = builder.one();
+    let zero = builder.zero();
+
+    // The low leaf is in the tree.
+    let low_leaf_bits = builder.split_le(low_leaf_index, siblings.len());
+    let low_leaf_hash = leaf_hash(
+        builder,
+        low_leaf.value,
+        low_leaf.next_value,
+        low_leaf.next_index,
+    );
+    merkle_proof_verify::<PoseidonHash, F, D>(
+        builder,
+        root,
+        low_leaf_hash,
+        &low_leaf_bits,
+        siblings,
+        None,
+    );
+
+    // `value < nullifier < next_value`, where a zero `next_value` ends the list.
+    let nullifier_limbs = digest_limbs(builder, nullifier);
+    let value_limbs = digest_limbs(builder, low_leaf.value);
+    let next_value_limbs = digest_limbs(builder, low_leaf.next_value);
+    let above_low = u32_limbs_less_than(builder, &value_limbs, &nullifier_limbs);
+    builder.connect(above_low.target, one);
+    let below_next = u32_limbs_less_than(builder, &nullifier_limbs, &next_value_limbs);
+    let mut is_last = builder._true();
+    for &element in &low_leaf.next_value.elements {
+        let is_zero = builder.is_equal(element, zero);
+        is_last = builder.and(is_last, is_zero);
+    }
+    let in_range = builder.or(below_next, is_last);
+    builder.connect(in_range.target, one);
+
+    low_leaf_bits
+}
+
+fn fill_leaf(
+    pw: &mut PartialWitness<F>,
+    targets: &IndexedLeafTargets,
+    leaf: &IndexedLeaf,
+) -> anyhow::Result<()> {
+    pw.set_hash_target(targets.value, felts_to_hashout(&leaf.value))?;
+    pw.set_hash_target(targets.next_value, felts_to_hashout(&leaf.next_value))?;
+    pw.set_target(targets.next_index, F::from_canonical_u64(leaf.next_index))?;
+    Ok(())
+}
+
+impl CircuitFragment for AccumulatorUpdate {
+    type Targets = AccumulatorTargets;
+
+    /// Builds a circuit that asserts that inserting the nullifiers one after another, starting
+    /// at leaf `start_index`, turns `old_root` into `new_root`, and that none of them was in the
+    /// tree before.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        let depth = targets.depth();
+        let empty_leaf = builder.constant_hash(HashOut {
+            elements: ZERO_DIGEST,
+        });
+
+        let mut root = targets.old_root;
+        for (k, insertion) in targets.insertions.iter().enumerate() {
+            let low_leaf = &insertion.low_leaf;
+
+            // The nullifier isn't in the tree yet.
+            let low_leaf_bits = low_leaf_circuit(
+                builder,
+                root,
+                insertion.nullifier,
+                low_leaf,
+                insertion.low_leaf_index,
+                &insertion.low_leaf_siblings,
+            );
+
+            // Point the low leaf at the new leaf.
+            let new_index = builder.add_const(targets.start_index, F::from_canonical_usize(k));
+            let new_leaf_bits = builder.split_le(new_index, depth);
+            let updated_low_leaf_hash =
+                leaf_hash(builder, low_leaf.value, insertion.nullifier, new_index);
+            let updated_root = merkle_root(
+                builder,
+                updated_low_leaf_hash,
+                &low_leaf_bits,
+                &insertion.low_leaf_siblings,
+            );
+
+            // The new leaf is empty, and takes over the low leaf's successor.
+            let computed_root = merkle_root(
+                builder,
+                empty_leaf,
+                &new_leaf_bits,
+                &insertion.new_leaf_siblings,
+            );
+            builder.connect_hashes(computed_root, updated_root);
+            let new_leaf_hash = leaf_hash(
+                builder,
+                insertion.nullifier,
+                low_leaf.next_value,
+                low_leaf.next_index,
+            );
+            root = merkle_root(
+                builder,
+                new_leaf_hash,
+                &new_leaf_bits,
+                &insertion.new_leaf_siblings,
+            );
+        }
+
+        builder.connect_hashes(root, targets.new_root);
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let depth = targets.depth();
+        let leaf = ["value", "next_value", "next_index"];
+        let low_leaf = [
+            "low_leaf.value",
+            "low_leaf.next_value",
+            "low_leaf.next_index",
+        ];
+        let updated_low_leaf = ["low_leaf.value", "nullifier", "new_index"];
+        let new_leaf = ["nullifier", "low_leaf.next_value", "low_leaf.next_index"];
+
+        let mut summary = FragmentSummary::new("accumulator_update")
+            .poseidon("leaf_hash", &leaf)
+            .assert(format!(
+                "merkle_root(leaf, index, path) hashes leaf up {} levels with Poseidon(left || \
+                 right), the index bits least significant first",
+                depth
+            ));
+        if !targets.insertions.is_empty() {
+            summary = summary.fragment(
+                FragmentSummary::new(format!(
+                    "insertions[k] for k < {}, with root[0] = old_root",
+                    targets.insertions.len()
+                ))
+                .range_check("low_leaf_index", depth)
+                .poseidon("low_leaf_hash", &low_leaf)
+                .equal(
+                    "merkle_root(low_leaf_hash, low_leaf_index, low_leaf_siblings)",
+                    "root[k]",
+                )
+                .assert(
+                    "nullifier, low_leaf.value and low_leaf.next_value are split into canonical \
+                     32-bit limbs",
+                )
+                .assert("low_leaf.value < nullifier")
+                .assert("nullifier < low_leaf.next_value or low_leaf.next_value == 0")
+                .equal("new_index", "start_index + k")
+                .range_check("new_index", depth)
+                .poseidon("updated_low_leaf_hash", &updated_low_leaf)
+                .equal(
+                    "merkle_root(0, new_index, new_leaf_siblings)",
+                    "merkle_root(updated_low_leaf_hash, low_leaf_index, low_leaf_siblings)",
+                )
+                .poseidon("new_leaf_hash", &new_leaf)
+                .equal(
+                    "root[k + 1]",
+                    "merkle_root(new_leaf_hash, new_index, new_leaf_siblings)",
+                ),
+            );
+        }
+        summary.equal(format!("root[{}]", targets.insertions.len()), "new_root")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        let depth = targets.depth();
+        ShapeError::check_len(
+            "accumulator_update",
+            "insertions",
+            targets.insertions.len(),
+            self.insertions.len(),
+        )?;
+        for (i, insertion) in self.insertions.iter().enumerate() {
+            ShapeError::check_len(
+                "accumulator_update",
+                "low_leaf_siblings",
+                depth,
+                insertion.low_leaf_siblings.len(),
+            )
+            .with_context(|| format!("insertion {}", i))?;
+            ShapeError::check_len(
+                "accumulator_update",
+                "new_leaf_siblings",
+                depth,
+                insertion.new_leaf_siblings.len(),
+            )
+            .with_context(|| format!("insertion {}", i))?;
+        }
+
+        pw.set_hash_target(targets.old_root, felts_to_hashout(&self.old_root))?;
+        pw.set_hash_target(targets.new_root, felts_to_hashout(&self.new_root))?;
+        pw.set_target(
+            targets.start_index,
+            F::from_canonical_u64(self.start_index),
+        )?;
+
+        for (insertion, insertion_targets) in self.insertions.iter().zip(targets.insertions) {
+            pw.set_hash_target(
+                insertion_targets.nullifier,
+                felts_to_hashout(&insertion.nullifier.to_felts()),
+            )?;
+            fill_leaf(pw, &insertion_targets.low_leaf, &insertion.low_leaf)?;
+            pw.set_target(
+                insertion_targets.low_leaf_index,
+                F::from_canonical_u64(insertion.low_leaf_index),
+            )?;
+
+            for (&target, sibling) in insertion_targets
+                .low_leaf_siblings
+                .iter()
+                .zip(&insertion.low_leaf_siblings)
+            {
+                pw.set_hash_target(target, felts_to_hashout(sibling))?;
+            }
+            for (&target, sibling) in insertion_targets
+                .new_leaf_siblings
+                .iter()
+                .zip(&insertion.new_leaf_siblings)
+            {
+                pw.set_hash_target(target, felts_to_hashout(sibling))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CircuitFragment for NonMembershipProof {
+    type Targets = NonMembershipTargets;
+
+    /// Builds a circuit that asserts that the nullifier is not in the tree with root `root`.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        low_leaf_circuit(
+            builder,
+            targets.root,
+            targets.nullifier,
+            &targets.low_leaf,
+            targets.low_leaf_index,
+            &targets.siblings,
+        );
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let depth = targets.depth();
+        let low_leaf = [
+            "low_leaf.value",
+            "low_leaf.next_value",
+            "low_leaf.next_index",
+        ];
+
+        FragmentSummary::new("spent_nullifiers")
+            .assert(format!(
+                "merkle_root(leaf, index, path) hashes leaf up {} levels with Poseidon(left || \
+                 right), the index bits least significant first",
+                depth
+            ))
+            .range_check("low_leaf_index", depth)
+            .poseidon("low_leaf_hash", &low_leaf)
+            .equal(
+                "merkle_root(low_leaf_hash, low_leaf_index, siblings)",
+                "root",
+            )
+            .assert(
+                "nullifier, low_leaf.value and low_leaf.next_value are split into canonical \
+                 32-bit limbs",
+            )
+            .assert("low_leaf.value < nullifier")
+            .assert("nullifier < low_leaf.next_value or low_leaf.next_value == 0")
+    }
+
+    /// Fills every target but the nullifier, which the circuit proving it fills.
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        ShapeError::check_len(
+            "spent_nullifiers",
+            "siblings",
+            targets.depth(),
+            self.siblings.len(),
+        )?;
+
+        pw.set_hash_target(targets.root, felts_to_hashout(&self.root))?;
+        fill_leaf(pw, &targets.low_leaf, &self.low_leaf)?;
+        pw.set_target(
+            targets.low_leaf_index,
+            F::from_canonical_u64(self.low_leaf_index),
+        )?;
+        for (&target, sibling) in targets.siblings.iter().zip(&self.siblings) {
+            pw.set_hash_target(target, felts_to_hashout(sibling))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub mod circuit_logic {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData},
+    };
+    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+    use super::{AccumulatorTargets, AccumulatorUpdate};
+
+    /// The accumulator circuit for a fixed tree depth and batch size.
+    pub struct AccumulatorCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: AccumulatorTargets,
+    }
+
+    impl AccumulatorCircuit {
+        pub fn new(config: CircuitConfig, depth: usize, batch_size: usize) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = AccumulatorTargets::new(&mut builder, depth, batch_size);
+            AccumulatorUpdate::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> AccumulatorTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::ops::Bound;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{ArgGroup, Parser};
+use serde_json::{json, Value};
+use wormhole_circuit::client::Deposit;
+use wormhole_circuit::hex_util::{self, HEX_PREFIX};
+use wormhole_circuit::recover::{recover, recover_from_snapshot, ClaimableDeposit};
+use wormhole_circuit::storage_proof::snapshot::StorageSnapshot;
+use wormhole_circuit::storage_proof::StorageProofParams;
+use wormhole_circuit::types::{AccountId, RootHash, SecretBytes};
+
+/// Lists the deposits into the unspendable account of a secret that can be claimed, as JSON.
+#[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("source").required(true).args(["node", "snapshot"])))]
+struct Args {
+    /// The hex encoded secret.
+    #[arg(long)]
+    secret: String,
+
+    /// A candidate deposit as `<funding account hex>:<amount>`. May be given more than once.
+    #[arg(long = "deposit", required = true)]
+    deposits: Vec<String>,
+
+    /// The hex encoded account the recovered inputs claim to.
+    #[arg(long)]
+    exit_account: String,
+
+    /// Websocket RPC endpoint of the node to scan.
+    #[arg(long)]
+    node: Option<String>,
+
+    /// State snapshot to scan instead of a node, see `StorageSnapshot::from_reader`.
+    #[arg(long, requires_all = ["root", "to_count"])]
+    snapshot: Option<PathBuf>,
+
+    /// The hex encoded state root of the snapshot.
+    #[arg(long)]
+    root: Option<String>,
+
+    /// The first transfer count to scan.
+    #[arg(long, default_value_t = 0)]
+    from_count: u64,
+
+    /// The transfer count to stop scanning at. Defaults to the transfer count of the node.
+    #[arg(long)]
+    to_count: Option<u64>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let secret = SecretBytes::from(hex_util::decode_array::<32>(&args.secret)?);
+    let exit_account = account_id(&args.exit_account).context("invalid exit account")?;
+    let candidates = args
+        .deposits
+        .iter()
+        .map(|deposit| {
+            parse_deposit(deposit).with_context(|| format!("invalid deposit `{}`", deposit))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let deposits = match (&args.node, &args.snapshot) {
+        (Some(node), _) => {
+            let end = args.to_count.map_or(Bound::Unbounded, Bound::Excluded);
+            let transfer_counts = (Bound::Included(args.from_count), end);
+            recover(node, secret, &candidates, transfer_counts, exit_account).await?
+        }
+        (None, Some(snapshot)) => {
+            let snapshot = StorageSnapshot::from_file(snapshot, StorageProofParams::default())?;
+            let root = args.root.as_deref().expect("required by clap");
+            let root_hash = RootHash::from(hex_util::decode_digest(root).context("invalid root")?);
+            let to_count = args.to_count.expect("required by clap");
+            recover_from_snapshot(
+                secret,
+                &candidates,
+                args.from_count..to_count,
+                &snapshot,
+                root_hash,
+                exit_account,
+            )?
+        }
+        (None, None) => unreachable!("required by clap"),
+    };
+
+    let report: Vec<Value> = deposits.iter().map(deposit_json).collect();
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn parse_deposit(deposit: &str) -> Result<Deposit> {
+    let (account, amount) = deposit
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected `<funding account hex>:<amount>`"))?;
+    Ok(Deposit {
+        funding_account: account_id(account)?,
+        funding_amount: amount.parse()?,
+    })
+}
+
+fn account_id(hex: &str) -> Result<AccountId> {
+    AccountId::try_from(hex_util::decode_array::<32>(hex)?)
+        .map_err(|e| anyhow!("invalid account: {:?}", e))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    format!("{}{}", HEX_PREFIX, hex::encode(bytes))
+}
+
+fn deposit_json(deposit: &ClaimableDeposit) -> Value {
+    let inputs = &deposit.inputs;
+    let storage_proof = &inputs.private.storage_proof;
+    json!({
+        "funding_account": encode(&*deposit.deposit.funding_account),
+        "funding_amount": deposit.deposit.funding_amount.to_string(),
+        "transfer_count": deposit.transfer_count,
+        "root_hash": encode(&*deposit.root_hash()),
+        "nullifier": encode(&*inputs.public.nullifier),
+        "storage_proof": {
+            "proof": storage_proof.proof.iter().map(|node| encode(node)).collect::<Vec<_>>(),
+            "indices": storage_proof.indices,
+        },
+    })
+}
@@ -31,15 +31,41 @@ pub fn circuit_data_from_bytes(
 
 #[cfg(feature = "std")]
 pub mod circuit_logic {
+    use crate::accumulator::{NonMembershipProof, NonMembershipTargets, MAX_ACCUMULATOR_DEPTH};
+    use crate::encoding::WORMHOLE_ENCODING;
+    use crate::exit_format::{ExitAccountFormat, ExitAccountFormatTargets};
+    use crate::funding_commitment::{FundingAccountOpening, FundingCommitmentTargets};
+    use crate::inputs::{
+        public_inputs_digest_circuit, register_public_input, Public, CONTEXT_END_INDEX,
+        CONTEXT_START_INDEX, DESTINATION_CHAIN_ID_END_INDEX, DESTINATION_CHAIN_ID_START_INDEX,
+        EXIT_ACCOUNT_FORMAT_END_INDEX, EXIT_ACCOUNT_FORMAT_INDEX, EXIT_AMOUNT_END_INDEX,
+        EXIT_AMOUNT_START_INDEX, FUNDING_COMMITMENT_END_INDEX, FUNDING_COMMITMENT_START_INDEX,
+        INTENT_HASH_END_INDEX, INTENT_HASH_START_INDEX, NULLIFIER_IS_V2_END_INDEX,
+        NULLIFIER_IS_V2_INDEX, PUBLIC_INPUTS_DIGEST_END_INDEX, PUBLIC_INPUTS_DIGEST_START_INDEX,
+        PUBLIC_INPUTS_FELTS_LEN, RELAYER_ACCOUNT_START_INDEX, RELAYER_FEE_START_INDEX,
+        SPENT_NULLIFIERS_ROOT_END_INDEX, SPENT_NULLIFIERS_ROOT_START_INDEX,
+    };
+    use crate::intent::{ClaimIntent, ClaimIntentTargets};
     use crate::nullifier::{Nullifier, NullifierTargets};
-    use crate::storage_proof::{StorageProof, StorageProofTargets};
+    use crate::relayer::{RelayerFee, RelayerFeeTargets};
+    use crate::storage_proof::{StorageProof, StorageProofParams, StorageProofTargets};
     use crate::substrate_account::{ExitAccountTargets, SubstrateAccount};
     use crate::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+    use alloc::vec::Vec;
+    use anyhow::{bail, ensure};
+    use core::array;
+    use core::marker::PhantomData;
     use plonky2::{
+        hash::hash_types::HashOutTarget,
+        iop::target::Target,
         plonk::circuit_data::{CircuitData, ProverCircuitData, VerifierCircuitData},
-        plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
+        plonk::{
+            circuit_builder::CircuitBuilder, circuit_data::CircuitConfig, config::GenericConfig,
+        },
     };
-    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+    use zk_circuits_common::circuit::{check_config, CircuitFragment, C, D, F};
+    use zk_circuits_common::describe::{CircuitSummary, FragmentSummary};
+    use zk_circuits_common::utils::FELTS_PER_U64;
 
     #[derive(Debug, Clone)]
     pub struct CircuitTargets {
@@ -47,22 +73,493 @@ pub mod circuit_logic {
         pub unspendable_account: UnspendableAccountTargets,
         pub storage_proof: StorageProofTargets,
         pub exit_account: ExitAccountTargets,
+        /// The hash of the [`crate::types::ProofContext`], if the circuit binds proofs to one.
+        pub context: Option<HashOutTarget>,
+        /// The [`crate::types::ChainId`] the deposit can be claimed on, if the circuit binds
+        /// deposits to one. Shared with the nullifier and unspendable account targets.
+        pub destination_chain_id: Option<[Target; FELTS_PER_U64]>,
+        /// The fee paid to a relayer out of the funding amount, if the circuit pays one.
+        pub relayer_fee: Option<RelayerFeeTargets>,
+        /// The [`crate::inputs::PublicCircuitInputs::digest`], if the circuit exposes it instead
+        /// of the public inputs it hashes. Computed from the other targets, so it isn't filled.
+        pub public_inputs_digest: Option<HashOutTarget>,
+        /// The format of the exit account, if the circuit accepts EVM addresses. Shares the
+        /// exit account targets.
+        pub exit_account_format: Option<ExitAccountFormatTargets>,
+        /// The proof that the nullifier is not in an accumulator of spent nullifiers, if the
+        /// circuit checks for double spends itself. Shares the nullifier hash target.
+        pub spent_nullifiers: Option<NonMembershipTargets>,
+        /// The commitment to the funding account, if the circuit exposes one. Shares the funding
+        /// account target of the storage proof leaf.
+        pub funding_commitment: Option<FundingCommitmentTargets>,
+        /// The hash of the claim intent, if the circuit exposes one. Shares the nullifier hash,
+        /// exit account and funding amount targets.
+        pub intent: Option<ClaimIntentTargets>,
     }
 
     impl CircuitTargets {
         pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+            Self::with_parts(builder, NullifierTargets::new, StorageProofParams::default())
+        }
+
+        /// Like [`Self::new`], but accepts both nullifier versions, version 2 for deposits with a
+        /// transfer count of at least `v2_cutover`. The version flag is registered as a public
+        /// input after the exit account. See [`crate::nullifier::NullifierVersion`].
+        pub fn new_with_nullifier_migration(
+            builder: &mut CircuitBuilder<F, D>,
+            v2_cutover: u64,
+        ) -> Self {
+            let targets = Self::with_parts(
+                builder,
+                |builder| NullifierTargets::new_with_migration(builder, v2_cutover),
+                StorageProofParams::default(),
+            );
+            if let Some(is_v2) = targets.nullifier.is_v2 {
+                builder.register_public_input(is_v2.target);
+            }
+            targets
+        }
+
+        /// Like [`Self::new`], but accepts storage proofs of the given shape.
+        pub fn with_storage_proof_params(
+            builder: &mut CircuitBuilder<F, D>,
+            params: StorageProofParams,
+        ) -> Self {
+            Self::with_parts(builder, NullifierTargets::new, params)
+        }
+
+        /// Like [`Self::new`], but binds proofs to a [`crate::types::ProofContext`], whose hash is
+        /// registered as public inputs after the exit account.
+        pub fn with_context(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self::new(builder);
+            targets.context = Some(builder.add_virtual_hash_public_input());
+            targets
+        }
+
+        /// Like [`Self::new`], but binds deposits to a destination [`crate::types::ChainId`],
+        /// which is registered as public inputs after the exit account and prepended to the
+        /// preimages of the unspendable account and the nullifier.
+        pub fn with_destination_chain(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self::new(builder);
+            let chain_id = array::from_fn(|_| builder.add_virtual_public_input());
+            targets.nullifier.destination_chain_id = Some(chain_id);
+            targets.unspendable_account.destination_chain_id = Some(chain_id);
+            targets.destination_chain_id = Some(chain_id);
+            targets
+        }
+
+        /// Like [`Self::new`], but pays a [`RelayerFee`] out of the funding amount. The relayer
+        /// account, fee and exit amount are registered as public inputs after the exit account.
+        pub fn with_relayer_fee(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self::new(builder);
+            let funding_amount = targets.storage_proof.leaf_inputs.funding_amount;
+            targets.relayer_fee = Some(RelayerFeeTargets::new(builder, funding_amount));
+            targets
+        }
+
+        /// Like [`Self::new`], but accepts EVM addresses as exit accounts. The
+        /// [`ExitAccountFormat`] flag is registered as a public input after the exit account.
+        pub fn with_exit_account_format(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self::new(builder);
+            let exit_account = targets.exit_account.address;
+            let exit_account_format = ExitAccountFormatTargets::new(builder, exit_account);
+            targets.exit_account_format = Some(exit_account_format);
+            targets
+        }
+
+        /// Like [`Self::new`], but proves that the nullifier is not in a
+        /// [`crate::accumulator::NullifierAccumulator`] of depth `depth`, whose root is registered
+        /// as public inputs after the exit account.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `depth` is zero or above [`MAX_ACCUMULATOR_DEPTH`].
+        pub fn with_spent_nullifier_accumulator(
+            builder: &mut CircuitBuilder<F, D>,
+            depth: usize,
+        ) -> Self {
+            assert!(
+                (1..=MAX_ACCUMULATOR_DEPTH).contains(&depth),
+                "accumulator depth must be between 1 and {}, got: {}",
+                MAX_ACCUMULATOR_DEPTH,
+                depth
+            );
+            let mut targets = Self::new(builder);
+            let nullifier = targets.nullifier.hash;
+            targets.spent_nullifiers = Some(NonMembershipTargets::new(builder, nullifier, depth));
+            targets
+        }
+
+        /// Like [`Self::new`], but commits to the funding account with a private blinding. The
+        /// commitment is registered as public inputs after the exit account.
+        pub fn with_funding_commitment(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self::new(builder);
+            let funding_account = targets.storage_proof.leaf_inputs.funding_account;
+            let funding_commitment = FundingCommitmentTargets::new(builder, funding_account);
+            targets.funding_commitment = Some(funding_commitment);
+            targets
+        }
+
+        /// Like [`Self::new`], but exposes the hash of a [`ClaimIntent`] to claim the funding
+        /// amount with the nullifier to the exit account. The intent hash is registered as public
+        /// inputs after the exit account.
+        pub fn with_intent_hash(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self::new(builder);
+            let nullifier = targets.nullifier.hash;
+            let exit_account = targets.exit_account.address;
+            let funding_amount = targets.storage_proof.leaf_inputs.funding_amount;
+            let intent = ClaimIntentTargets::new(builder, nullifier, exit_account, funding_amount);
+            targets.intent = Some(intent);
+            targets
+        }
+
+        /// Like [`Self::new`], but keeps the nullifier, root hash, funding amount and exit account
+        /// private and registers only their [`crate::inputs::PublicCircuitInputs::digest`] as
+        /// public inputs, so an aggregator carries 4 felts per leaf instead of 16.
+        pub fn with_public_inputs_digest(builder: &mut CircuitBuilder<F, D>) -> Self {
+            let mut targets = Self {
+                nullifier: NullifierTargets::new_private(builder),
+                unspendable_account: UnspendableAccountTargets::new(builder),
+                storage_proof: StorageProofTargets::new_private(builder),
+                exit_account: ExitAccountTargets::new_private(builder),
+                context: None,
+                destination_chain_id: None,
+                relayer_fee: None,
+                public_inputs_digest: None,
+                exit_account_format: None,
+                spent_nullifiers: None,
+                funding_commitment: None,
+                intent: None,
+            };
+
+            let mut public_inputs = Vec::new();
+            public_inputs.extend(targets.nullifier.hash.elements);
+            public_inputs.extend(targets.storage_proof.root_hash.elements);
+            public_inputs.extend(targets.storage_proof.leaf_inputs.funding_amount);
+            public_inputs.extend(targets.exit_account.address.elements);
+            let digest = public_inputs_digest_circuit(builder, &public_inputs);
+            register_public_input(builder, &Public::new(digest));
+            targets.public_inputs_digest = Some(digest);
+            targets
+        }
+
+        fn with_parts(
+            builder: &mut CircuitBuilder<F, D>,
+            nullifier: impl FnOnce(&mut CircuitBuilder<F, D>) -> NullifierTargets,
+            storage_proof_params: StorageProofParams,
+        ) -> Self {
             Self {
-                nullifier: NullifierTargets::new(builder),
+                nullifier: nullifier(builder),
                 unspendable_account: UnspendableAccountTargets::new(builder),
-                storage_proof: StorageProofTargets::new(builder),
+                storage_proof: StorageProofTargets::with_params(builder, storage_proof_params),
                 exit_account: ExitAccountTargets::new(builder),
+                context: None,
+                destination_chain_id: None,
+                relayer_fee: None,
+                public_inputs_digest: None,
+                exit_account_format: None,
+                spent_nullifiers: None,
+                funding_commitment: None,
+                intent: None,
+            }
+        }
+
+        /// Returns every target filled in when committing to [`crate::inputs::CircuitInputs`], in
+        /// a canonical order. Circuits built with the same config allocate the same targets, so
+        /// this order can be used to move witness values between processes.
+        pub fn input_targets(&self) -> Vec<Target> {
+            let mut targets = Vec::new();
+
+            targets.extend(self.nullifier.hash.elements);
+            targets.extend(self.nullifier.secret.iter());
+            targets.extend(self.nullifier.transfer_count.iter());
+            targets.extend(self.nullifier.is_v2.map(|is_v2| is_v2.target));
+
+            targets.extend(self.unspendable_account.account_id.elements);
+            targets.extend(self.unspendable_account.secret.iter());
+
+            let storage_proof = &self.storage_proof;
+            targets.extend(storage_proof.root_hash.elements);
+            targets.push(storage_proof.proof_len);
+            targets.extend(storage_proof.proof_data.iter().flatten());
+            targets.extend(&storage_proof.indices);
+            targets.extend(storage_proof.leaf_inputs.transfer_count);
+            targets.extend(storage_proof.leaf_inputs.funding_account.elements);
+            targets.extend(storage_proof.leaf_inputs.to_account.elements);
+            targets.extend(storage_proof.leaf_inputs.funding_amount);
+
+            targets.extend(self.exit_account.address.elements);
+
+            targets.extend(self.context.iter().flat_map(|context| context.elements));
+            targets.extend(self.destination_chain_id.iter().flatten());
+
+            if let Some(relayer_fee) = &self.relayer_fee {
+                targets.extend(relayer_fee.relayer_account.elements);
+                targets.extend(relayer_fee.fee);
+                targets.extend(relayer_fee.exit_amount);
+            }
+
+            targets.extend(self.exit_account_format.map(|format| format.is_evm.target));
+
+            if let Some(spent_nullifiers) = &self.spent_nullifiers {
+                targets.extend(spent_nullifiers.root.elements);
+                let low_leaf = &spent_nullifiers.low_leaf;
+                targets.extend(low_leaf.value.elements);
+                targets.extend(low_leaf.next_value.elements);
+                targets.push(low_leaf.next_index);
+                targets.push(spent_nullifiers.low_leaf_index);
+                targets.extend(spent_nullifiers.siblings.iter().flat_map(|s| s.elements));
+            }
+
+            if let Some(funding_commitment) = &self.funding_commitment {
+                targets.extend(funding_commitment.commitment.elements);
+                targets.extend(funding_commitment.blinding.iter());
+            }
+
+            if let Some(intent) = &self.intent {
+                targets.extend(intent.intent_hash.elements);
+                targets.extend(intent.min_amount.iter());
+            }
+
+            targets
+        }
+
+        /// Checks that these targets have at most one optional feature with public inputs. The
+        /// public inputs of every feature start right after the exit account, or replace the
+        /// public inputs entirely for the digest, so two features would share indices.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error naming the features if there is more than one.
+        pub fn check_optional_features(&self) -> anyhow::Result<()> {
+            let features = [
+                ("nullifier_is_v2", self.nullifier.is_v2.is_some()),
+                ("context", self.context.is_some()),
+                ("destination_chain_id", self.destination_chain_id.is_some()),
+                ("relayer_fee", self.relayer_fee.is_some()),
+                ("public_inputs_digest", self.public_inputs_digest.is_some()),
+                ("exit_account_format", self.exit_account_format.is_some()),
+                ("spent_nullifiers", self.spent_nullifiers.is_some()),
+                ("funding_commitment", self.funding_commitment.is_some()),
+                ("intent", self.intent.is_some()),
+            ];
+            let present: Vec<&str> = features
+                .into_iter()
+                .filter_map(|(name, present)| present.then_some(name))
+                .collect();
+            ensure!(
+                present.len() <= 1,
+                "the optional features {} share public input indices and can't be combined",
+                present.join(", ")
+            );
+            Ok(())
+        }
+
+        /// The number of public inputs the layout has for the optional features of these targets.
+        /// It depends only on which features the circuit has, never on the witness.
+        pub fn num_public_inputs(&self) -> usize {
+            if self.public_inputs_digest.is_some() {
+                return PUBLIC_INPUTS_DIGEST_END_INDEX;
+            }
+            let mut len = PUBLIC_INPUTS_FELTS_LEN;
+            if self.nullifier.is_v2.is_some() {
+                len = len.max(NULLIFIER_IS_V2_END_INDEX);
+            }
+            if self.context.is_some() {
+                len = len.max(CONTEXT_END_INDEX);
+            }
+            if self.destination_chain_id.is_some() {
+                len = len.max(DESTINATION_CHAIN_ID_END_INDEX);
+            }
+            if self.relayer_fee.is_some() {
+                len = len.max(EXIT_AMOUNT_END_INDEX);
+            }
+            if self.exit_account_format.is_some() {
+                len = len.max(EXIT_ACCOUNT_FORMAT_END_INDEX);
+            }
+            if self.spent_nullifiers.is_some() {
+                len = len.max(SPENT_NULLIFIERS_ROOT_END_INDEX);
+            }
+            if self.funding_commitment.is_some() {
+                len = len.max(FUNDING_COMMITMENT_END_INDEX);
+            }
+            if self.intent.is_some() {
+                len = len.max(INTENT_HASH_END_INDEX);
+            }
+            len
+        }
+
+        /// Checks that the fragments registered `public_inputs`, the public input targets of the
+        /// built circuit, in the layout of [`WORMHOLE_ENCODING`], followed by the public inputs of
+        /// the optional features the circuit has, e.g. the context. Circuits exposing the public
+        /// inputs digest register only the digest.
+        ///
+        /// The order of the public inputs follows from the order the fragments create their
+        /// targets in, so reordering them would otherwise silently shift every index.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error naming the first field that is not registered where the layout
+        /// places it.
+        pub fn check_public_input_layout(&self, public_inputs: &[Target]) -> anyhow::Result<()> {
+            let fields = self.public_input_fields()?;
+            let len = fields
+                .iter()
+                .map(|(_, start, targets)| start + targets.len())
+                .max()
+                .unwrap_or_default();
+            ensure!(
+                public_inputs.len() == len,
+                "{} public inputs are registered, but the layout has {}",
+                public_inputs.len(),
+                len
+            );
+            for (name, start, targets) in fields {
+                ensure!(
+                    public_inputs[start..start + targets.len()] == targets[..],
+                    "public input field {} is not registered at index {}",
+                    name,
+                    start
+                );
+            }
+            Ok(())
+        }
+
+        /// The name, start index and targets of every public input field the layout has for
+        /// these targets.
+        fn public_input_fields(&self) -> anyhow::Result<Vec<(&'static str, usize, Vec<Target>)>> {
+            self.check_optional_features()?;
+            let mut fields = Vec::new();
+            if let Some(digest) = self.public_inputs_digest {
+                let start = PUBLIC_INPUTS_DIGEST_START_INDEX;
+                fields.push(("public_inputs_digest", start, digest.elements.to_vec()));
+            }
+            let encoded_fields = match self.public_inputs_digest {
+                Some(_) => &[][..],
+                None => WORMHOLE_ENCODING.public_inputs,
+            };
+            for field in encoded_fields {
+                let targets = match field.name {
+                    "nullifier" => self.nullifier.hash.elements.to_vec(),
+                    "root_hash" => self.storage_proof.root_hash.elements.to_vec(),
+                    "funding_amount" => self.storage_proof.leaf_inputs.funding_amount.to_vec(),
+                    "exit_account" => self.exit_account.address.elements.to_vec(),
+                    name => bail!("no targets for public input field {}", name),
+                };
+                ensure!(
+                    targets.len() == field.end - field.start,
+                    "public input field {} has {} targets, but the layout has {}",
+                    field.name,
+                    targets.len(),
+                    field.end - field.start
+                );
+                fields.push((field.name, field.start, targets));
             }
+            if let Some(is_v2) = self.nullifier.is_v2 {
+                fields.push(("nullifier_is_v2", NULLIFIER_IS_V2_INDEX, vec![is_v2.target]));
+            }
+            if let Some(context) = self.context {
+                fields.push(("context", CONTEXT_START_INDEX, context.elements.to_vec()));
+            }
+            if let Some(chain_id) = self.destination_chain_id {
+                let start = DESTINATION_CHAIN_ID_START_INDEX;
+                fields.push(("destination_chain_id", start, chain_id.to_vec()));
+            }
+            if let Some(relayer_fee) = &self.relayer_fee {
+                let account = relayer_fee.relayer_account.elements.to_vec();
+                fields.push(("relayer_account", RELAYER_ACCOUNT_START_INDEX, account));
+                fields.push(("relayer_fee", RELAYER_FEE_START_INDEX, relayer_fee.fee.to_vec()));
+                let exit_amount = relayer_fee.exit_amount.to_vec();
+                fields.push(("exit_amount", EXIT_AMOUNT_START_INDEX, exit_amount));
+            }
+            if let Some(format) = self.exit_account_format {
+                let start = EXIT_ACCOUNT_FORMAT_INDEX;
+                fields.push(("exit_account_format", start, vec![format.is_evm.target]));
+            }
+            if let Some(spent_nullifiers) = &self.spent_nullifiers {
+                let start = SPENT_NULLIFIERS_ROOT_START_INDEX;
+                let root = spent_nullifiers.root.elements.to_vec();
+                fields.push(("spent_nullifiers_root", start, root));
+            }
+            if let Some(funding_commitment) = &self.funding_commitment {
+                let start = FUNDING_COMMITMENT_START_INDEX;
+                let commitment = funding_commitment.commitment.elements.to_vec();
+                fields.push(("funding_commitment", start, commitment));
+            }
+            if let Some(intent) = &self.intent {
+                let start = INTENT_HASH_START_INDEX;
+                fields.push(("intent_hash", start, intent.intent_hash.elements.to_vec()));
+            }
+            Ok(fields)
+        }
+
+        /// Describes the public inputs of the circuit built on these targets and the constraints
+        /// of its fragments, for audits. See [`zk_circuits_common::describe`].
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the public input layout names a field without targets.
+        pub fn describe(&self) -> anyhow::Result<CircuitSummary> {
+            let mut summary = CircuitSummary::new("wormhole circuit");
+            for (name, start, targets) in self.public_input_fields()? {
+                summary = summary.public_input(name, start, start + targets.len());
+            }
+
+            summary = summary
+                .fragment(Nullifier::describe(&self.nullifier))
+                .fragment(UnspendableAccount::describe(&self.unspendable_account))
+                .fragment(StorageProof::describe(&self.storage_proof))
+                .fragment(SubstrateAccount::describe(&self.exit_account));
+            if let Some(relayer_fee) = &self.relayer_fee {
+                summary = summary.fragment(RelayerFee::describe(relayer_fee));
+            }
+            if let Some(exit_account_format) = &self.exit_account_format {
+                summary = summary.fragment(ExitAccountFormat::describe(exit_account_format));
+            }
+            if let Some(spent_nullifiers) = &self.spent_nullifiers {
+                summary = summary.fragment(NonMembershipProof::describe(spent_nullifiers));
+            }
+            if let Some(funding_commitment) = &self.funding_commitment {
+                summary = summary.fragment(FundingAccountOpening::describe(funding_commitment));
+            }
+            if let Some(intent) = &self.intent {
+                summary = summary.fragment(ClaimIntent::describe(intent));
+            }
+            summary = summary.fragment(
+                FragmentSummary::new("shared_targets")
+                    .equal("nullifier.secret", "unspendable_account.secret")
+                    .equal(
+                        "nullifier.transfer_count",
+                        "storage_proof.leaf_inputs.transfer_count",
+                    )
+                    .equal(
+                        "unspendable_account.account_id",
+                        "storage_proof.leaf_inputs.to_account",
+                    ),
+            );
+            if self.public_inputs_digest.is_some() {
+                summary = summary.fragment(FragmentSummary::new("public_inputs_digest").poseidon(
+                    "public_inputs_digest",
+                    &[
+                        "nullifier.hash",
+                        "storage_proof.root_hash",
+                        "storage_proof.leaf_inputs.funding_amount",
+                        "exit_account.address",
+                    ],
+                ));
+            }
+            Ok(summary)
         }
     }
 
-    pub struct WormholeCircuit {
+    /// The wormhole circuit, built and proven with the proving config `Cfg`.
+    ///
+    /// `Cfg` defaults to the Poseidon Goldilocks config used for all published artifacts. Other
+    /// configs over the Goldilocks field can be used through [`Self::with_generic_config`].
+    pub struct WormholeCircuit<Cfg: GenericConfig<D, F = F> = C> {
         builder: CircuitBuilder<F, D>,
         targets: CircuitTargets,
+        _config: PhantomData<Cfg>,
     }
 
     impl Default for WormholeCircuit {
@@ -73,38 +570,243 @@ pub mod circuit_logic {
     }
 
     impl WormholeCircuit {
+        /// # Panics
+        ///
+        /// Panics if `config` can't hold the circuit's gates. Use [`Self::try_new`] for configs
+        /// that aren't known to be valid, e.g. ones read from circuit artifacts.
         pub fn new(config: CircuitConfig) -> Self {
+            Self::with_generic_config(config)
+        }
+
+        /// Like [`Self::new`], but returns an error instead of panicking on an unusable config.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if `config` fails [`check_config`].
+        pub fn try_new(config: CircuitConfig) -> anyhow::Result<Self> {
+            check_config(&config)?;
+            Ok(Self::new(config))
+        }
+
+        /// Creates a circuit that accepts both version 1 and version 2 nullifiers, for use during
+        /// a nullifier migration window. Deposits with a transfer count of at least `v2_cutover`
+        /// are claimed with version 2, all others with version 1, so no deposit can be claimed
+        /// with both. See [`crate::nullifier::NullifierVersion`].
+        ///
+        /// Circuits built with [`Self::new`] don't know the cutover and prove version 1
+        /// nullifiers for every deposit. Verifiers must reject their proofs from the moment
+        /// they accept proofs of this circuit, or a deposit past the cutover can be claimed
+        /// once with each version.
+        pub fn with_nullifier_migration(config: CircuitConfig, v2_cutover: u64) -> Self {
+            Self::from_targets(config, |builder| {
+                CircuitTargets::new_with_nullifier_migration(builder, v2_cutover)
+            })
+        }
+
+        /// Creates a circuit accepting storage proofs of the given shape, e.g. with larger nodes
+        /// for chains with wide trie branches. See [`StorageProofParams`].
+        pub fn with_storage_proof_params(
+            config: CircuitConfig,
+            params: StorageProofParams,
+        ) -> Self {
+            Self::from_targets(config, |builder| {
+                CircuitTargets::with_storage_proof_params(builder, params)
+            })
+        }
+
+        /// Like [`Self::with_storage_proof_params`], but returns an error instead of panicking
+        /// on an unusable config or proof shape.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if `config` fails [`check_config`] or
+        /// `params` fails [`StorageProofParams::validate`].
+        pub fn try_with_storage_proof_params(
+            config: CircuitConfig,
+            params: StorageProofParams,
+        ) -> anyhow::Result<Self> {
+            check_config(&config)?;
+            params.validate()?;
+            Ok(Self::with_storage_proof_params(config, params))
+        }
+
+        /// Creates a circuit binding proofs to a verifier supplied [`crate::types::ProofContext`],
+        /// so they can't be replayed on another chain. See [`CircuitTargets::with_context`].
+        pub fn with_context(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_context)
+        }
+
+        /// Creates a circuit that only accepts deposits made for a destination
+        /// [`crate::types::ChainId`], for deployments of the wormhole pallet on several chains.
+        /// Deposit addresses and nullifiers are derived with
+        /// [`UnspendableAccount::from_secret_for_chain`] and
+        /// [`Nullifier::from_preimage_for_chain`]. See [`CircuitTargets::with_destination_chain`].
+        pub fn with_destination_chain(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_destination_chain)
+        }
+
+        /// Creates a circuit that pays a [`RelayerFee`] out of the funding amount, so relayers
+        /// submitting proofs on behalf of users can be paid by the pallet. See
+        /// [`CircuitTargets::with_relayer_fee`].
+        pub fn with_relayer_fee(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_relayer_fee)
+        }
+
+        /// Creates a circuit that also accepts 20 byte EVM addresses as exit accounts, for
+        /// deployments bridging to EVM chains. See [`crate::exit_format`] and
+        /// [`CircuitTargets::with_exit_account_format`].
+        pub fn with_exit_account_format(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_exit_account_format)
+        }
+
+        /// Creates a circuit that also proves the nullifier is not in an accumulator of spent
+        /// nullifiers of depth `depth`, for chains that verify claims without a nullifier set and
+        /// only keep the accumulator root. See [`crate::accumulator`] and
+        /// [`CircuitTargets::with_spent_nullifier_accumulator`].
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if `depth` is zero or above [`MAX_ACCUMULATOR_DEPTH`].
+        pub fn with_spent_nullifier_accumulator(
+            config: CircuitConfig,
+            depth: usize,
+        ) -> anyhow::Result<Self> {
+            ensure!(
+                (1..=MAX_ACCUMULATOR_DEPTH).contains(&depth),
+                "accumulator depth must be between 1 and {}, got: {}",
+                MAX_ACCUMULATOR_DEPTH,
+                depth
+            );
+            Ok(Self::from_targets(config, |builder| {
+                CircuitTargets::with_spent_nullifier_accumulator(builder, depth)
+            }))
+        }
+
+        /// Creates a circuit that commits to the funding account with a private blinding, so the
+        /// origin of a claim can be proven to a designated party later without being public. See
+        /// [`crate::funding_commitment`] and [`CircuitTargets::with_funding_commitment`].
+        pub fn with_funding_commitment(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_funding_commitment)
+        }
+
+        /// Creates a circuit exposing the hash of a [`ClaimIntent`], so a claim can be registered
+        /// on chain before its proof is submitted and can't be front-run from the mempool. See
+        /// [`crate::intent`] and [`CircuitTargets::with_intent_hash`].
+        pub fn with_intent_hash(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_intent_hash)
+        }
+
+        /// Creates a circuit exposing only the digest of its public inputs, for aggregators that
+        /// don't need the individual values in-circuit. See
+        /// [`CircuitTargets::with_public_inputs_digest`].
+        pub fn with_public_inputs_digest(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::with_public_inputs_digest)
+        }
+    }
+
+    impl<Cfg: GenericConfig<D, F = F>> WormholeCircuit<Cfg> {
+        /// Creates a new circuit that will be built for the proving config `Cfg`.
+        pub fn with_generic_config(config: CircuitConfig) -> Self {
+            Self::from_targets(config, CircuitTargets::new)
+        }
+
+        fn from_targets(
+            config: CircuitConfig,
+            targets: impl FnOnce(&mut CircuitBuilder<F, D>) -> CircuitTargets,
+        ) -> Self {
             let mut builder = CircuitBuilder::<F, D>::new(config);
 
             // Setup targets
-            let targets = CircuitTargets::new(&mut builder);
+            let targets = targets(&mut builder);
+            if let Err(e) = targets.check_optional_features() {
+                panic!("{}", e);
+            }
 
             // Setup circuits.
             Nullifier::circuit(&targets.nullifier, &mut builder);
             UnspendableAccount::circuit(&targets.unspendable_account, &mut builder);
             StorageProof::circuit(&targets.storage_proof, &mut builder);
             SubstrateAccount::circuit(&targets.exit_account, &mut builder);
+            if let Some(relayer_fee) = &targets.relayer_fee {
+                RelayerFee::circuit(relayer_fee, &mut builder);
+            }
+            if let Some(exit_account_format) = &targets.exit_account_format {
+                ExitAccountFormat::circuit(exit_account_format, &mut builder);
+            }
+            if let Some(spent_nullifiers) = &targets.spent_nullifiers {
+                NonMembershipProof::circuit(spent_nullifiers, &mut builder);
+            }
+            if let Some(funding_commitment) = &targets.funding_commitment {
+                FundingAccountOpening::circuit(funding_commitment, &mut builder);
+            }
+            if let Some(intent) = &targets.intent {
+                ClaimIntent::circuit(intent, &mut builder);
+            }
 
             // Ensure that shared inputs to each fragment are the same.
             connect_shared_targets(&targets, &mut builder);
 
-            Self { builder, targets }
+            // Caught here rather than when building, so a fragment registering a public input
+            // it shouldn't fails every test that creates the circuit.
+            assert_eq!(
+                builder.num_public_inputs(),
+                targets.num_public_inputs(),
+                "wormhole circuit registered a different number of public inputs than its layout"
+            );
+
+            Self {
+                builder,
+                targets,
+                _config: PhantomData,
+            }
         }
 
         pub fn targets(&self) -> CircuitTargets {
             self.targets.clone()
         }
 
-        pub fn build_circuit(self) -> CircuitData<F, C, D> {
-            self.builder.build()
+        /// The number of public inputs of the circuit, see [`CircuitTargets::num_public_inputs`].
+        pub fn num_public_inputs(&self) -> usize {
+            self.builder.num_public_inputs()
+        }
+
+        /// Describes the circuit for audits, see [`CircuitTargets::describe`].
+        pub fn describe(&self) -> anyhow::Result<CircuitSummary> {
+            self.targets.describe()
+        }
+
+        /// The number of gates added so far. The built circuit has at least this many rows.
+        pub fn num_gates(&self) -> usize {
+            self.builder.num_gates()
+        }
+
+        /// # Panics
+        ///
+        /// Panics if the fragments registered their public inputs out of layout, see
+        /// [`CircuitTargets::check_public_input_layout`].
+        pub fn build_circuit(self) -> CircuitData<F, Cfg, D> {
+            let data = self.builder.build();
+            if let Err(e) = self
+                .targets
+                .check_public_input_layout(&data.prover_only.public_inputs)
+            {
+                panic!("wormhole circuit has an inconsistent public input layout: {}", e);
+            }
+            data
         }
 
-        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
-            self.builder.build_prover()
+        /// # Panics
+        ///
+        /// See [`Self::build_circuit`].
+        pub fn build_prover(self) -> ProverCircuitData<F, Cfg, D> {
+            self.build_circuit().prover_data()
         }
 
-        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
-            self.builder.build_verifier()
+        /// # Panics
+        ///
+        /// See [`Self::build_circuit`].
+        pub fn build_verifier(self) -> VerifierCircuitData<F, Cfg, D> {
+            self.build_circuit().verifier_data()
         }
     }
 
@@ -114,7 +816,7 @@ pub mod circuit_logic {
             .nullifier
             .secret
             .iter()
-            .zip(&targets.unspendable_account.secret)
+            .zip(targets.unspendable_account.secret.iter())
         {
             builder.connect(a, b);
         }
@@ -0,0 +1,276 @@
+//! Assembling [`CircuitInputs`] from a node.
+//!
+//! [`fetch_inputs`] connects to a node's websocket RPC and gathers everything needed to claim a
+//! deposit: the storage root of the finalized block, the transfer count of the deposit and a read
+//! proof of its transfer record. The read proof is turned into a storage proof with a
+//! [`StorageSnapshot`], so the inputs are only returned if the record is under the root, and a
+//! wrong secret, funding account or amount fails here rather than while proving.
+//!
+//! Transfer records are keyed by the hash of the transfer, so the funding account and amount
+//! can't be read from the chain and are given as a [`Deposit`]. The transfer count is found by
+//! looking up the records of the last [`MAX_TRANSFER_COUNT_SEARCH`] transfers.
+//!
+//! # Storage layout
+//!
+//! The node is expected to store transfers like the Quantus balances pallet:
+//! - `TransferCount`, the number of transfers so far as a SCALE encoded `u64`, under
+//!   [`TRANSFER_COUNT_KEY`].
+//! - `TransferProof`, with an entry for every transfer under [`TRANSFER_PROOF_PREFIX`] followed by
+//!   the [`LeafInputs::hash`] of the transfer, see [`transfer_proof_key`].
+use anyhow::{bail, Context};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::rpc_params;
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use serde::Deserialize;
+use zk_circuits_common::utils::digest_felts_to_bytes;
+
+use crate::inputs::{CircuitInputs, PrivateCircuitInputs, PublicCircuitInputs};
+use crate::nullifier::Nullifier;
+use crate::storage_proof::leaf::LeafInputs;
+use crate::storage_proof::snapshot::StorageSnapshot;
+use crate::storage_proof::StorageProofParams;
+use crate::types::{AccountId, RootHash, SecretBytes};
+use crate::unspendable_account::UnspendableAccount;
+
+/// `twox128("Balances") ++ twox128("TransferCount")`.
+pub const TRANSFER_COUNT_KEY: [u8; 32] = [
+    0xc2, 0x26, 0x12, 0x76, 0xcc, 0x9d, 0x1f, 0x85, 0x98, 0xea, 0x4b, 0x6a, 0x74, 0xb1, 0x5c, 0x2f,
+    0xfd, 0x1a, 0xd9, 0x5f, 0xea, 0x07, 0xfa, 0xf3, 0x86, 0xc8, 0x2b, 0xb3, 0xab, 0xcc, 0xa1, 0x6c,
+];
+/// `twox128("Balances") ++ twox128("TransferProof")`.
+pub const TRANSFER_PROOF_PREFIX: [u8; 32] = [
+    0xc2, 0x26, 0x12, 0x76, 0xcc, 0x9d, 0x1f, 0x85, 0x98, 0xea, 0x4b, 0x6a, 0x74, 0xb1, 0x5c, 0x2f,
+    0x52, 0x85, 0x7e, 0x7e, 0xa4, 0x9e, 0x78, 0x5c, 0x4e, 0x3e, 0x1f, 0x77, 0xa7, 0x10, 0xcf, 0xc2,
+];
+/// How many of the most recent transfers [`fetch_inputs`] looks through for the deposit.
+pub const MAX_TRANSFER_COUNT_SEARCH: u64 = 1024;
+
+/// A transfer into the unspendable account of a secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deposit {
+    pub funding_account: AccountId,
+    pub funding_amount: u128,
+}
+
+impl Deposit {
+    /// The leaf inputs of the deposit into the unspendable account of `secret`, had it been the
+    /// transfer with `transfer_count`.
+    pub fn leaf_inputs(
+        &self,
+        secret: &SecretBytes,
+        transfer_count: u64,
+    ) -> anyhow::Result<LeafInputs> {
+        let unspendable_account = UnspendableAccount::from_secret(secret).account_id;
+        LeafInputs::new(
+            transfer_count,
+            self.funding_account,
+            AccountId::from(unspendable_account),
+            self.funding_amount,
+        )
+    }
+}
+
+/// The storage key of the transfer record with `leaf_inputs`.
+pub fn transfer_proof_key(leaf_inputs: &LeafInputs) -> Vec<u8> {
+    let mut key = TRANSFER_PROOF_PREFIX.to_vec();
+    key.extend_from_slice(&*digest_felts_to_bytes(leaf_inputs.hash()));
+    key
+}
+
+/// Assembles the inputs to claim `deposit`, the transfer with `transfer_count`, to
+/// `exit_account`. `read_proof` holds the trie nodes of a read proof of its transfer record under
+/// `root_hash`, in any order.
+///
+/// # Errors
+///
+/// Returns an error if a node is too large for the circuit, or the read proof doesn't prove the
+/// transfer record under `root_hash`.
+pub fn assemble_inputs(
+    secret: SecretBytes,
+    deposit: &Deposit,
+    transfer_count: u64,
+    root_hash: RootHash,
+    read_proof: Vec<Vec<u8>>,
+    exit_account: AccountId,
+) -> anyhow::Result<CircuitInputs> {
+    let mut snapshot = StorageSnapshot::new(StorageProofParams::default());
+    for node in read_proof {
+        snapshot.insert(node).context("read proof node is too large for the circuit")?;
+    }
+    inputs_from_snapshot(secret, deposit, transfer_count, root_hash, &snapshot, exit_account)
+        .context("read proof does not prove the transfer record of the deposit")
+}
+
+/// Like [`assemble_inputs`], but proves the transfer record with the nodes of `snapshot`.
+pub(crate) fn inputs_from_snapshot(
+    secret: SecretBytes,
+    deposit: &Deposit,
+    transfer_count: u64,
+    root_hash: RootHash,
+    snapshot: &StorageSnapshot,
+    exit_account: AccountId,
+) -> anyhow::Result<CircuitInputs> {
+    let leaf_inputs = deposit.leaf_inputs(&secret, transfer_count)?;
+    let storage_proof = snapshot.prove(root_hash, leaf_inputs.hash())?;
+
+    let unspendable_account = UnspendableAccount::from_secret(&secret).account_id;
+    Ok(CircuitInputs {
+        private: PrivateCircuitInputs {
+            secret,
+            storage_proof,
+            transfer_count,
+            funding_account: deposit.funding_account,
+            unspendable_account: AccountId::from(unspendable_account),
+        },
+        public: PublicCircuitInputs {
+            funding_amount: deposit.funding_amount,
+            nullifier: Nullifier::from_preimage(secret.as_slice(), transfer_count).hash.into(),
+            root_hash,
+            exit_account,
+        },
+    })
+}
+
+/// Fetches the inputs to claim `deposit` into the unspendable account of `secret` to
+/// `exit_account` against the finalized block of the node at `ws_url`, see the
+/// [module documentation](self).
+///
+/// # Errors
+///
+/// Returns an error if the node can't be reached or its responses are malformed, the deposit is
+/// not among the last [`MAX_TRANSFER_COUNT_SEARCH`] transfers, or its read proof is invalid.
+pub async fn fetch_inputs(
+    ws_url: &str,
+    secret: SecretBytes,
+    deposit: Deposit,
+    exit_account: AccountId,
+) -> anyhow::Result<CircuitInputs> {
+    let client = connect(ws_url).await?;
+    let (block_hash, root_hash) = finalized_state(&client).await?;
+    let transfer_count = find_transfer_count(&client, &block_hash, &secret, &deposit).await?;
+
+    let key = encode_key(&transfer_proof_key(&deposit.leaf_inputs(&secret, transfer_count)?));
+    let nodes = read_proof_nodes(&client, &[key], &block_hash).await?;
+
+    assemble_inputs(secret, &deposit, transfer_count, root_hash, nodes, exit_account)
+}
+
+pub(crate) async fn connect(ws_url: &str) -> anyhow::Result<WsClient> {
+    WsClientBuilder::default()
+        .build(ws_url)
+        .await
+        .with_context(|| format!("failed to connect to {}", ws_url))
+}
+
+/// The hash and state root of the finalized block.
+pub(crate) async fn finalized_state(client: &WsClient) -> anyhow::Result<(String, RootHash)> {
+    let block_hash: String = client.request("chain_getFinalizedHead", rpc_params![]).await?;
+    let header: Header = client.request("chain_getHeader", rpc_params![&block_hash]).await?;
+    let root_hash = crate::hex_util::decode_digest(&header.state_root)
+        .context("invalid state root")?
+        .into();
+    Ok((block_hash, root_hash))
+}
+
+/// The number of transfers made up to `block_hash`.
+pub(crate) async fn transfer_count_at(client: &WsClient, block_hash: &str) -> anyhow::Result<u64> {
+    let count: Option<String> = client
+        .request("state_getStorage", rpc_params![encode_key(&TRANSFER_COUNT_KEY), block_hash])
+        .await?;
+    match count {
+        Some(count) => Ok(u64::from_le_bytes(
+            crate::hex_util::decode_array(&count).context("invalid transfer count")?,
+        )),
+        None => Ok(0),
+    }
+}
+
+/// The subset of `keys` with a value at `block_hash`.
+pub(crate) async fn stored_keys(
+    client: &WsClient,
+    keys: &[String],
+    block_hash: &str,
+) -> anyhow::Result<Vec<String>> {
+    let change_sets: Vec<StorageChangeSet> = client
+        .request("state_queryStorageAt", rpc_params![keys, block_hash])
+        .await?;
+    Ok(change_sets
+        .into_iter()
+        .flat_map(|change_set| change_set.changes)
+        .filter_map(|(key, value)| value.map(|_| key))
+        .collect())
+}
+
+/// The trie nodes of a read proof of `keys` at `block_hash`.
+pub(crate) async fn read_proof_nodes(
+    client: &WsClient,
+    keys: &[String],
+    block_hash: &str,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    let read_proof: ReadProof = client
+        .request("state_getReadProof", rpc_params![keys, block_hash])
+        .await?;
+    read_proof
+        .proof
+        .iter()
+        .map(|node| crate::hex_util::decode(node))
+        .collect::<Result<Vec<_>, _>>()
+        .context("invalid read proof node")
+}
+
+/// The count of the most recent transfer matching `deposit` at `block_hash`.
+async fn find_transfer_count(
+    client: &WsClient,
+    block_hash: &str,
+    secret: &SecretBytes,
+    deposit: &Deposit,
+) -> anyhow::Result<u64> {
+    let count = transfer_count_at(client, block_hash).await?;
+    let candidates = (count.saturating_sub(MAX_TRANSFER_COUNT_SEARCH)..count)
+        .rev()
+        .map(|transfer_count| {
+            let leaf_inputs = deposit.leaf_inputs(secret, transfer_count)?;
+            Ok((transfer_count, encode_key(&transfer_proof_key(&leaf_inputs))))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if candidates.is_empty() {
+        bail!("no transfers at block {}", block_hash);
+    }
+
+    let keys: Vec<String> = candidates.iter().map(|(_, key)| key.clone()).collect();
+    let stored = stored_keys(client, &keys, block_hash).await?;
+
+    candidates
+        .iter()
+        .find(|(_, key)| stored.contains(key))
+        .map(|&(transfer_count, _)| transfer_count)
+        .with_context(|| {
+            format!(
+                "no transfer of {} from {} to the unspendable account among the last {} \
+                 transfers",
+                deposit.funding_amount,
+                hex::encode(*deposit.funding_account),
+                MAX_TRANSFER_COUNT_SEARCH
+            )
+        })
+}
+
+pub(crate) fn encode_key(key: &[u8]) -> String {
+    format!("{}{}", crate::hex_util::HEX_PREFIX, hex::encode(key))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Header {
+    state_root: String,
+}
+
+#[derive(Deserialize)]
+struct ReadProof {
+    proof: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct StorageChangeSet {
+    changes: Vec<(String, Option<String>)>,
+}
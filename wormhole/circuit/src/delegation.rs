@@ -0,0 +1,359 @@
+//! Delegated proving.
+//!
+//! Most of the wormhole circuit is the storage proof, which is too slow to prove on some wallets.
+//! Delegated proving splits the circuit in two:
+//!
+//! - The client proves the [`ClientCommitment`] circuit, which only holds the parts derived from
+//!   the secret: the nullifier and the unspendable account. It is small and quick to prove.
+//! - A delegated prover proves the [`DelegatedClaim`] circuit. It verifies the client proof
+//!   recursively, and proves the storage leaf of the deposit to the client's unspendable account.
+//!
+//! The secret never leaves the client. The delegated prover learns which deposit is spent, its
+//! nullifier and the exit account, so it can link them. It can't redirect the funds, as the exit
+//! account is fixed by the client proof.
+//!
+//! Delegated proofs have the public inputs of the wormhole circuit in the same layout, see
+//! [`crate::inputs`], but are verified with the verifier data of the [`DelegatedClaim`] circuit.
+//!
+//! ## Public Inputs of the Client Proof
+//!
+//! - `nullifier`: the nullifier of the spent deposit.
+//! - `unspendable_account`: the address the deposit was made to.
+//! - `transfer_count`: the transfer count of the deposit, as 32-bit limbs.
+//! - `exit_account`: the account the delegated proof pays out to.
+use core::array;
+
+use anyhow::{anyhow, bail};
+use plonky2::{
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CommonCircuitData, VerifierCircuitData, VerifierCircuitTarget},
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::utils::felts_to_u64;
+
+use crate::codec::ByteCodec;
+use crate::inputs::{register_public_input, Public};
+use crate::nullifier::{Nullifier, NullifierTargets};
+use crate::storage_proof::leaf::LeafInputs;
+use crate::storage_proof::{ProcessedStorageProof, StorageProof, StorageProofTargets};
+use crate::substrate_account::{ExitAccountTargets, SubstrateAccount};
+use crate::types::{AccountId, NullifierHash, RootHash, SecretBytes};
+use crate::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+
+pub const NULLIFIER_START_INDEX: usize = 0;
+pub const NULLIFIER_END_INDEX: usize = 4;
+pub const UNSPENDABLE_ACCOUNT_START_INDEX: usize = 4;
+pub const UNSPENDABLE_ACCOUNT_END_INDEX: usize = 8;
+pub const TRANSFER_COUNT_START_INDEX: usize = 8;
+pub const TRANSFER_COUNT_END_INDEX: usize = 10;
+pub const EXIT_ACCOUNT_START_INDEX: usize = 10;
+pub const EXIT_ACCOUNT_END_INDEX: usize = 14;
+
+/// Inputs to the client circuit.
+#[derive(Debug, Clone)]
+pub struct ClientCommitment {
+    pub secret: SecretBytes,
+    pub transfer_count: u64,
+    pub exit_account: AccountId,
+}
+
+impl ClientCommitment {
+    pub fn nullifier(&self) -> NullifierHash {
+        Nullifier::from_preimage(self.secret.as_slice(), self.transfer_count)
+            .hash
+            .into()
+    }
+
+    /// The address the spent deposit was made to, which the client hands to the delegated prover
+    /// along with its proof.
+    pub fn unspendable_account(&self) -> AccountId {
+        UnspendableAccount::from_secret(&self.secret)
+            .account_id
+            .into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientCommitmentTargets {
+    pub nullifier: NullifierTargets,
+    pub unspendable_account: UnspendableAccountTargets,
+    pub exit_account: ExitAccountTargets,
+}
+
+impl ClientCommitmentTargets {
+    pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        let nullifier = NullifierTargets::new(builder);
+        let unspendable_account = UnspendableAccountTargets::new(builder);
+        register_public_input(builder, &Public::new(unspendable_account.account_id));
+        // The transfer count is revealed so the relayer can locate the deposit's storage proof.
+        register_public_input(builder, &nullifier.transfer_count.declassify());
+        let exit_account = ExitAccountTargets::new(builder);
+
+        Self {
+            nullifier,
+            unspendable_account,
+            exit_account,
+        }
+    }
+}
+
+impl CircuitFragment for ClientCommitment {
+    type Targets = ClientCommitmentTargets;
+
+    /// Builds a circuit that asserts that the nullifier and unspendable account are derived from
+    /// the same secret and the public transfer count.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        Nullifier::circuit(&targets.nullifier, builder);
+        UnspendableAccount::circuit(&targets.unspendable_account, builder);
+        for (&a, &b) in targets
+            .nullifier
+            .secret
+            .iter()
+            .zip(targets.unspendable_account.secret.iter())
+        {
+            builder.connect(a, b);
+        }
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("client_commitment")
+            .fragment(Nullifier::describe(&targets.nullifier))
+            .fragment(UnspendableAccount::describe(&targets.unspendable_account))
+            .equal("nullifier.secret", "unspendable_account.secret")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        let nullifier = Nullifier::from_preimage(self.secret.as_slice(), self.transfer_count);
+        let unspendable_account = UnspendableAccount::from_secret(&self.secret);
+        let exit_account = SubstrateAccount::from_bytes(self.exit_account.as_slice())?;
+
+        nullifier.fill_targets(pw, targets.nullifier)?;
+        unspendable_account.fill_targets(pw, targets.unspendable_account)?;
+        exit_account.fill_targets(pw, targets.exit_account)
+    }
+}
+
+/// Inputs to the delegated circuit.
+#[derive(Debug, Clone)]
+pub struct DelegatedClaim {
+    /// A proof of the client circuit.
+    pub client_proof: ProofWithPublicInputs<F, C, D>,
+    pub funding_account: AccountId,
+    pub funding_amount: u128,
+    pub root_hash: RootHash,
+    /// A storage proof of the deposit to the unspendable account in the client proof.
+    pub storage_proof: ProcessedStorageProof,
+}
+
+impl DelegatedClaim {
+    /// The unspendable account in the client proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client proof has too few public inputs.
+    pub fn unspendable_account(&self) -> anyhow::Result<AccountId> {
+        AccountId::try_from(self.client_public_inputs(
+            UNSPENDABLE_ACCOUNT_START_INDEX..UNSPENDABLE_ACCOUNT_END_INDEX,
+        )?)
+    }
+
+    /// The transfer count in the client proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client proof has too few public inputs, or the transfer count
+    /// limbs are not 32-bit.
+    pub fn transfer_count(&self) -> anyhow::Result<u64> {
+        let limbs =
+            self.client_public_inputs(TRANSFER_COUNT_START_INDEX..TRANSFER_COUNT_END_INDEX)?;
+        felts_to_u64(array::from_fn(|i| limbs[i]))
+            .map_err(|e| anyhow!("invalid transfer count in client proof: {:?}", e))
+    }
+
+    fn client_public_inputs(&self, range: core::ops::Range<usize>) -> anyhow::Result<&[F]> {
+        match self.client_proof.public_inputs.get(range) {
+            Some(public_inputs) => Ok(public_inputs),
+            None => bail!(
+                "client proof has {} public inputs, expected {}",
+                self.client_proof.public_inputs.len(),
+                EXIT_ACCOUNT_END_INDEX
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DelegatedClaimTargets {
+    pub client_proof: ProofWithPublicInputsTarget<D>,
+    /// The verifier data of the client circuit, fixed as constants.
+    pub client_verifier: VerifierCircuitTarget,
+    pub client_common: CommonCircuitData<F, D>,
+    pub storage_proof: StorageProofTargets,
+}
+
+impl DelegatedClaimTargets {
+    /// Registers the public inputs of the wormhole circuit, taking the nullifier and exit account
+    /// from the client proof.
+    pub fn new(
+        builder: &mut CircuitBuilder<F, D>,
+        client_verifier: &VerifierCircuitData<F, C, D>,
+    ) -> Self {
+        let client_proof = builder.add_virtual_proof_with_pis(&client_verifier.common);
+        let nullifier = &client_proof.public_inputs[NULLIFIER_START_INDEX..NULLIFIER_END_INDEX];
+        register_public_input(builder, &Public::new(nullifier.to_vec()));
+        let storage_proof = StorageProofTargets::new(builder);
+        let exit_account =
+            &client_proof.public_inputs[EXIT_ACCOUNT_START_INDEX..EXIT_ACCOUNT_END_INDEX];
+        register_public_input(builder, &Public::new(exit_account.to_vec()));
+
+        Self {
+            client_verifier: builder.constant_verifier_data(&client_verifier.verifier_only),
+            client_common: client_verifier.common.clone(),
+            client_proof,
+            storage_proof,
+        }
+    }
+}
+
+impl CircuitFragment for DelegatedClaim {
+    type Targets = DelegatedClaimTargets;
+
+    /// Builds a circuit that verifies the client proof, and asserts that the storage leaf pays
+    /// its unspendable account with its transfer count.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        builder.verify_proof::<C>(
+            &targets.client_proof,
+            &targets.client_verifier,
+            &targets.client_common,
+        );
+        StorageProof::circuit(&targets.storage_proof, builder);
+
+        let client_inputs = &targets.client_proof.public_inputs;
+        let leaf_inputs = &targets.storage_proof.leaf_inputs;
+        let unspendable_account =
+            &client_inputs[UNSPENDABLE_ACCOUNT_START_INDEX..UNSPENDABLE_ACCOUNT_END_INDEX];
+        for (&a, &b) in unspendable_account.iter().zip(&leaf_inputs.to_account.elements) {
+            builder.connect(a, b);
+        }
+        let transfer_count = &client_inputs[TRANSFER_COUNT_START_INDEX..TRANSFER_COUNT_END_INDEX];
+        for (&a, &b) in transfer_count.iter().zip(&leaf_inputs.transfer_count) {
+            builder.connect(a, b);
+        }
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("delegated_claim")
+            .assert("client_proof verifies with the client circuit's verifier data as constants")
+            .fragment(StorageProof::describe(&targets.storage_proof))
+            .equal(
+                "client_proof.unspendable_account",
+                "storage_proof.leaf_inputs.to_account",
+            )
+            .equal(
+                "client_proof.transfer_count",
+                "storage_proof.leaf_inputs.transfer_count",
+            )
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        let leaf_inputs = LeafInputs::new(
+            self.transfer_count()?,
+            self.funding_account,
+            self.unspendable_account()?,
+            self.funding_amount,
+        )?;
+        let storage_proof = StorageProof::new(&self.storage_proof, self.root_hash, leaf_inputs);
+
+        pw.set_proof_with_pis_target(&targets.client_proof, &self.client_proof)?;
+        storage_proof.fill_targets(pw, targets.storage_proof)
+    }
+}
+
+#[cfg(feature = "std")]
+pub mod circuit_logic {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData},
+    };
+    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+    use super::{ClientCommitment, ClientCommitmentTargets, DelegatedClaim, DelegatedClaimTargets};
+
+    /// The circuit proven by the client.
+    pub struct ClientCommitmentCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: ClientCommitmentTargets,
+    }
+
+    impl ClientCommitmentCircuit {
+        pub fn new(config: CircuitConfig) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = ClientCommitmentTargets::new(&mut builder);
+            ClientCommitment::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> ClientCommitmentTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+
+    /// The circuit proven by the delegated prover, for proofs of the client circuit with the
+    /// given verifier data.
+    pub struct DelegatedClaimCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: DelegatedClaimTargets,
+    }
+
+    impl DelegatedClaimCircuit {
+        pub fn new(config: CircuitConfig, client_verifier: &VerifierCircuitData<F, C, D>) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = DelegatedClaimTargets::new(&mut builder, client_verifier);
+            DelegatedClaim::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> DelegatedClaimTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+}
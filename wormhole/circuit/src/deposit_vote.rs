@@ -0,0 +1,265 @@
+//! Votes weighted by wormhole deposits.
+//!
+//! Lets deposit holders vote on a proposal without a separate voter registration tree. The
+//! circuit proves that the prover knows the secret of an unspendable account that received a
+//! deposit of at least `threshold` under a storage root, and emits a vote nullifier:
+//!
+//! ```text
+//! vote_nullifier = H(H(salt || secret || proposal_id))
+//! ```
+//!
+//! The vote nullifier is the same for every deposit to the account, so each secret votes at most
+//! once per proposal, however many deposits it received. It is unrelated to the wormhole
+//! nullifiers of the deposits, so votes can't be linked to claims. Leaves stay in storage after
+//! a claim, so claimed deposits are eligible too; governance fixes the eligible deposits by
+//! choosing the storage root.
+//!
+//! ## Public Inputs
+//!
+//! - `proposal_id`: the proposal voted on.
+//! - `root_hash`: the storage root the deposit is proven against.
+//! - `threshold`: the minimum deposit amount, as big-endian 32-bit limbs.
+//! - `vote`: `1` for yes, `0` for no.
+//! - `vote_nullifier`: unique per secret and proposal.
+use alloc::vec::Vec;
+use anyhow::bail;
+use plonky2::{
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::{assert_u32_limbs_at_least, range_check_u32};
+use zk_circuits_common::utils::{
+    felts_to_hashout, injective_bytes_to_felts, injective_string_to_felt, u128_to_felts, Digest,
+    FELTS_PER_U128,
+};
+
+use crate::inputs::{register_public_input, Public};
+use crate::storage_proof::leaf::LeafInputs;
+use crate::storage_proof::{ProcessedStorageProof, StorageProof, StorageProofTargets};
+use crate::types::{AccountId, RootHash, SecretBytes};
+use crate::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+
+/// Separates vote nullifiers from the wormhole nullifier and unspendable account hashes.
+pub const VOTE_NULLIFIER_SALT: &str = "~govote~";
+
+pub const PROPOSAL_ID_START_INDEX: usize = 0;
+pub const PROPOSAL_ID_END_INDEX: usize = 4;
+pub const ROOT_HASH_START_INDEX: usize = 4;
+pub const ROOT_HASH_END_INDEX: usize = 8;
+pub const THRESHOLD_START_INDEX: usize = 8;
+pub const THRESHOLD_END_INDEX: usize = 12;
+pub const VOTE_INDEX: usize = 12;
+pub const VOTE_NULLIFIER_START_INDEX: usize = 13;
+pub const VOTE_NULLIFIER_END_INDEX: usize = 17;
+
+/// The vote nullifier of `secret` on `proposal_id`.
+pub fn vote_nullifier(secret: &SecretBytes, proposal_id: Digest) -> Digest {
+    let mut preimage = Vec::new();
+    preimage.extend(injective_string_to_felt(VOTE_NULLIFIER_SALT));
+    preimage.extend(injective_bytes_to_felts(secret.as_slice()));
+    preimage.extend(proposal_id);
+
+    let inner_hash = PoseidonHash::hash_no_pad(&preimage).elements;
+    PoseidonHash::hash_no_pad(&inner_hash).elements
+}
+
+/// Inputs to the deposit vote circuit.
+#[derive(Debug, Clone)]
+pub struct DepositVote {
+    pub proposal_id: Digest,
+    pub root_hash: RootHash,
+    pub threshold: u128,
+    pub vote: bool,
+    /// The secret the unspendable account was derived from.
+    pub secret: SecretBytes,
+    pub transfer_count: u64,
+    pub funding_account: AccountId,
+    pub funding_amount: u128,
+    pub storage_proof: ProcessedStorageProof,
+}
+
+#[derive(Debug, Clone)]
+pub struct DepositVoteTargets {
+    pub proposal_id: HashOutTarget,
+    pub root_hash: HashOutTarget,
+    pub threshold: [Target; FELTS_PER_U128],
+    pub vote: BoolTarget,
+    pub vote_nullifier: HashOutTarget,
+    pub unspendable_account: UnspendableAccountTargets,
+    pub storage_proof: StorageProofTargets,
+}
+
+impl DepositVoteTargets {
+    pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        let proposal_id = builder.add_virtual_hash_public_input();
+        let root_hash = builder.add_virtual_hash_public_input();
+        let threshold = core::array::from_fn(|_| builder.add_virtual_public_input());
+        let vote = builder.add_virtual_bool_target_safe();
+        register_public_input(builder, &Public::new(vote));
+        let vote_nullifier = builder.add_virtual_hash_public_input();
+
+        Self {
+            proposal_id,
+            root_hash,
+            threshold,
+            vote,
+            vote_nullifier,
+            unspendable_account: UnspendableAccountTargets::new(builder),
+            storage_proof: StorageProofTargets::new_private(builder),
+        }
+    }
+}
+
+impl CircuitFragment for DepositVote {
+    type Targets = DepositVoteTargets;
+
+    /// Builds a circuit that asserts that the unspendable account of the secret has a leaf under
+    /// `root_hash` with an amount of at least `threshold`, and that `vote_nullifier` was derived
+    /// from the secret and `proposal_id`.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        range_check_u32(builder, &targets.threshold);
+        // The wormhole circuit range checks the secret as part of the nullifier, which isn't
+        // computed here.
+        range_check_u32(builder, &targets.unspendable_account.secret[..]);
+        UnspendableAccount::circuit(&targets.unspendable_account, builder);
+        StorageProof::circuit(&targets.storage_proof, builder);
+
+        // The leaf must pay the unspendable account and live under the public root.
+        builder.connect_hashes(
+            targets.unspendable_account.account_id,
+            targets.storage_proof.leaf_inputs.to_account,
+        );
+        builder.connect_hashes(targets.root_hash, targets.storage_proof.root_hash);
+
+        assert_u32_limbs_at_least(
+            builder,
+            &targets.storage_proof.leaf_inputs.funding_amount,
+            &targets.threshold,
+        );
+
+        let salt = injective_string_to_felt(VOTE_NULLIFIER_SALT);
+        let mut preimage = Vec::new();
+        preimage.push(builder.constant(salt[0]));
+        preimage.push(builder.constant(salt[1]));
+        preimage.extend(targets.unspendable_account.secret.iter());
+        preimage.extend(targets.proposal_id.elements);
+        let inner_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        let computed_nullifier =
+            builder.hash_n_to_hash_no_pad::<PoseidonHash>(inner_hash.elements.to_vec());
+        builder.connect_hashes(computed_nullifier, targets.vote_nullifier);
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("deposit_vote")
+            .range_check("vote", 1)
+            .range_check("threshold", 32)
+            .range_check("unspendable_account.secret", 32)
+            .fragment(UnspendableAccount::describe(&targets.unspendable_account))
+            .fragment(StorageProof::describe(&targets.storage_proof))
+            .equal(
+                "unspendable_account.account_id",
+                "storage_proof.leaf_inputs.to_account",
+            )
+            .equal("root_hash", "storage_proof.root_hash")
+            .assert("storage_proof.leaf_inputs.funding_amount >= threshold")
+            .poseidon(
+                "inner_hash",
+                &[
+                    "VOTE_NULLIFIER_SALT",
+                    "unspendable_account.secret",
+                    "proposal_id",
+                ],
+            )
+            .poseidon("computed_nullifier", &["inner_hash"])
+            .equal("computed_nullifier", "vote_nullifier")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        if self.funding_amount < self.threshold {
+            bail!(
+                "deposit amount {} is below threshold {}",
+                self.funding_amount,
+                self.threshold
+            );
+        }
+
+        pw.set_hash_target(targets.proposal_id, felts_to_hashout(&self.proposal_id))?;
+        pw.set_hash_target(
+            targets.root_hash,
+            felts_to_hashout(&self.root_hash.to_felts()),
+        )?;
+        pw.set_target_arr(&targets.threshold, &u128_to_felts(self.threshold))?;
+        pw.set_bool_target(targets.vote, self.vote)?;
+        pw.set_hash_target(
+            targets.vote_nullifier,
+            felts_to_hashout(&vote_nullifier(&self.secret, self.proposal_id)),
+        )?;
+
+        let unspendable_account = UnspendableAccount::from_secret(&self.secret);
+        let leaf_inputs = LeafInputs::new(
+            self.transfer_count,
+            self.funding_account,
+            AccountId::from(unspendable_account.account_id),
+            self.funding_amount,
+        )?;
+        let storage_proof = StorageProof::new(&self.storage_proof, self.root_hash, leaf_inputs);
+
+        unspendable_account.fill_targets(pw, targets.unspendable_account)?;
+        storage_proof.fill_targets(pw, targets.storage_proof)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub mod circuit_logic {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData},
+    };
+    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+    use super::{DepositVote, DepositVoteTargets};
+
+    /// The deposit vote circuit.
+    pub struct DepositVoteCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: DepositVoteTargets,
+    }
+
+    impl DepositVoteCircuit {
+        pub fn new(config: CircuitConfig) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = DepositVoteTargets::new(&mut builder);
+            DepositVote::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> DepositVoteTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+}
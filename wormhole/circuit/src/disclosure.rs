@@ -0,0 +1,353 @@
+//! Selective disclosure of the funding account.
+//!
+//! An auxiliary circuit a user can opt into to reveal, to a designated auditor only, which
+//! account funded the wormhole claim identified by a nullifier. It proves the same nullifier,
+//! unspendable account and storage leaf statements as the wormhole circuit, and additionally
+//! encrypts the leaf's funding account under a key shared with the auditor:
+//!
+//! ```text
+//! auditor_key_hash = H(auditor_key || AUDITOR_ID)
+//! ciphertext       = funding_account + H(auditor_key || nullifier)
+//! ```
+//!
+//! The nullifier is unique per claim, so every disclosure uses a fresh pad. Without
+//! `auditor_key`, the ciphertext reveals nothing about the funding account, so regular wormhole
+//! proofs keep their privacy.
+//!
+//! ## Trust Model
+//!
+//! The circuit is built for one designated auditor: `AUDITOR_ID` is the [`auditor_id`] of the
+//! auditor's published public key, a constant of the circuit, so its verifier digest identifies
+//! the auditor. The circuit has no public key arithmetic, so it can't check that `auditor_key`
+//! was agreed with the auditor, and a user could encrypt under a key only they know. The
+//! binding to the auditor is therefore enforced on chain:
+//!
+//! 1. The user and the auditor agree on `auditor_key` outside the circuit, through a key exchange
+//!    with an ephemeral key of the user against the auditor's published key.
+//! 2. The auditor registers `auditor_key_hash` on chain, which attests that it holds the key.
+//!    The hash depends on `AUDITOR_ID`, so a registration for one auditor doesn't match a
+//!    disclosure for another.
+//! 3. The pallet only accepts disclosures verified against the designated auditor's circuit
+//!    whose `auditor_key_hash` the auditor registered.
+//!
+//! The auditor can decrypt every disclosure the pallet accepts. A user who skips the key exchange
+//! can't produce an accepted disclosure, and anyone other than the user and the auditor learns
+//! nothing about the funding account.
+//!
+//! ## Public Inputs
+//!
+//! - `nullifier`: the nullifier of the disclosed claim.
+//! - `root_hash`: the storage root the leaf is proven against.
+//! - `funding_amount`: the amount of the disclosed deposit.
+//! - `auditor_key_hash`: identifies the key the disclosure is encrypted under, for the auditor
+//!   of the circuit.
+//! - `ciphertext`: the encrypted funding account.
+use anyhow::{bail, ensure};
+use plonky2::{
+    field::types::{Field, PrimeField64},
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::range_check_u32;
+use zk_circuits_common::utils::{felts_to_hashout, injective_bytes_to_felts, Digest};
+
+use crate::inputs::CircuitInputs;
+use crate::nullifier::{Nullifier, NullifierTargets};
+use crate::storage_proof::{StorageProof, StorageProofTargets};
+use crate::types::{AccountId, NullifierHash};
+use crate::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+
+pub const AUDITOR_KEY_NUM_TARGETS: usize = 8;
+
+pub const NULLIFIER_START_INDEX: usize = 0;
+pub const NULLIFIER_END_INDEX: usize = 4;
+pub const ROOT_HASH_START_INDEX: usize = 4;
+pub const ROOT_HASH_END_INDEX: usize = 8;
+pub const FUNDING_AMOUNT_START_INDEX: usize = 8;
+pub const FUNDING_AMOUNT_END_INDEX: usize = 12;
+pub const AUDITOR_KEY_HASH_START_INDEX: usize = 12;
+pub const AUDITOR_KEY_HASH_END_INDEX: usize = 16;
+pub const CIPHERTEXT_START_INDEX: usize = 16;
+pub const CIPHERTEXT_END_INDEX: usize = 20;
+
+/// Identifies the auditor with the published `auditor_public_key`, which disclosure circuits are
+/// built for.
+pub fn auditor_id(auditor_public_key: &[u8; 32]) -> Digest {
+    PoseidonHash::hash_no_pad(&injective_bytes_to_felts(auditor_public_key)).elements
+}
+
+/// Hashes an auditor key agreed with the auditor `auditor_id` to the value exposed as
+/// `auditor_key_hash`, which the auditor registers on chain.
+pub fn auditor_key_hash(auditor_key: &[u8; 32], auditor_id: Digest) -> Digest {
+    let mut preimage = injective_bytes_to_felts(auditor_key);
+    preimage.extend(auditor_id);
+    PoseidonHash::hash_no_pad(&preimage).elements
+}
+
+fn pad(auditor_key: &[u8; 32], nullifier: Digest) -> Digest {
+    let mut preimage = injective_bytes_to_felts(auditor_key);
+    preimage.extend(nullifier);
+    PoseidonHash::hash_no_pad(&preimage).elements
+}
+
+/// Encrypts `funding_account` for the auditor holding `auditor_key`.
+pub fn encrypt_funding_account(
+    auditor_key: &[u8; 32],
+    nullifier: NullifierHash,
+    funding_account: AccountId,
+) -> Digest {
+    let pad = pad(auditor_key, nullifier.to_felts());
+    let funding_account = funding_account.to_felts();
+    core::array::from_fn(|i| funding_account[i] + pad[i])
+}
+
+/// Recovers the funding account from a disclosure's `ciphertext` public inputs.
+pub fn decrypt_funding_account(
+    auditor_key: &[u8; 32],
+    nullifier: NullifierHash,
+    ciphertext: Digest,
+) -> AccountId {
+    let pad = pad(auditor_key, nullifier.to_felts());
+    let funding_account: Digest = core::array::from_fn(|i| {
+        let felt = ciphertext[i] - pad[i];
+        F::from_canonical_u64(felt.to_canonical_u64())
+    });
+    AccountId::from(funding_account)
+}
+
+#[derive(Debug, Clone)]
+pub struct DisclosureTargets {
+    pub nullifier: NullifierTargets,
+    pub unspendable_account: UnspendableAccountTargets,
+    pub storage_proof: StorageProofTargets,
+    pub auditor_key: [Target; AUDITOR_KEY_NUM_TARGETS],
+    pub auditor_key_hash: HashOutTarget,
+    pub ciphertext: HashOutTarget,
+    /// The [`auditor_id`] of the designated auditor, a constant of the circuit.
+    pub auditor_id: Digest,
+}
+
+impl DisclosureTargets {
+    /// Allocates the targets of a disclosure to the auditor `auditor_id`.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, auditor_id: Digest) -> Self {
+        Self {
+            nullifier: NullifierTargets::new(builder),
+            unspendable_account: UnspendableAccountTargets::new(builder),
+            storage_proof: StorageProofTargets::new(builder),
+            auditor_key: core::array::from_fn(|_| builder.add_virtual_target()),
+            auditor_key_hash: builder.add_virtual_hash_public_input(),
+            ciphertext: builder.add_virtual_hash_public_input(),
+            auditor_id,
+        }
+    }
+}
+
+/// Disclosure of the funding account of a wormhole claim.
+#[derive(Debug)]
+pub struct FundingDisclosure {
+    pub nullifier: Nullifier,
+    pub unspendable_account: UnspendableAccount,
+    pub storage_proof: StorageProof,
+    pub auditor_key: [F; AUDITOR_KEY_NUM_TARGETS],
+    pub auditor_key_hash: Digest,
+    pub ciphertext: Digest,
+    pub auditor_id: Digest,
+}
+
+impl FundingDisclosure {
+    /// Discloses the funding account of the claim described by `inputs` to the auditor
+    /// `auditor_id`, under the `auditor_key` agreed with it.
+    pub fn new(
+        inputs: &CircuitInputs,
+        auditor_key: &[u8; 32],
+        auditor_id: Digest,
+    ) -> anyhow::Result<Self> {
+        let ciphertext = encrypt_funding_account(
+            auditor_key,
+            inputs.public.nullifier,
+            inputs.private.funding_account,
+        );
+
+        Ok(Self {
+            nullifier: Nullifier::from(inputs),
+            unspendable_account: UnspendableAccount::from(inputs),
+            storage_proof: StorageProof::try_from(inputs)?,
+            auditor_key: injective_bytes_to_felts(auditor_key).try_into().unwrap(),
+            auditor_key_hash: auditor_key_hash(auditor_key, auditor_id),
+            ciphertext,
+            auditor_id,
+        })
+    }
+}
+
+impl CircuitFragment for FundingDisclosure {
+    type Targets = DisclosureTargets;
+
+    /// Builds a circuit that asserts the wormhole claim statements for the nullifier and that
+    /// `ciphertext` is the leaf's funding account encrypted under the key hashing to
+    /// `auditor_key_hash` for the designated auditor.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        Nullifier::circuit(&targets.nullifier, builder);
+        UnspendableAccount::circuit(&targets.unspendable_account, builder);
+        StorageProof::circuit(&targets.storage_proof, builder);
+
+        // Tie the fragments together exactly like the wormhole circuit does.
+        for (&a, &b) in targets
+            .nullifier
+            .secret
+            .iter()
+            .zip(targets.unspendable_account.secret.iter())
+        {
+            builder.connect(a, b);
+        }
+        for (&a, &b) in targets
+            .nullifier
+            .transfer_count
+            .iter()
+            .zip(&targets.storage_proof.leaf_inputs.transfer_count)
+        {
+            builder.connect(a, b);
+        }
+        builder.connect_hashes(
+            targets.unspendable_account.account_id,
+            targets.storage_proof.leaf_inputs.to_account,
+        );
+
+        // Bind the key and the designated auditor to the public key hash.
+        range_check_u32(builder, &targets.auditor_key);
+        let mut preimage = targets.auditor_key.to_vec();
+        preimage.extend(targets.auditor_id.map(|felt| builder.constant(felt)));
+        let key_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        builder.connect_hashes(key_hash, targets.auditor_key_hash);
+
+        // Encrypt the funding account with a pad derived from the key and nullifier.
+        let mut preimage = targets.auditor_key.to_vec();
+        preimage.extend(targets.nullifier.hash.elements);
+        let pad = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        let funding_account = targets.storage_proof.leaf_inputs.funding_account;
+        for ((&account, &pad), &ciphertext) in funding_account
+            .elements
+            .iter()
+            .zip(&pad.elements)
+            .zip(&targets.ciphertext.elements)
+        {
+            let encrypted = builder.add(account, pad);
+            builder.connect(encrypted, ciphertext);
+        }
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("funding_disclosure")
+            .fragment(Nullifier::describe(&targets.nullifier))
+            .fragment(UnspendableAccount::describe(&targets.unspendable_account))
+            .fragment(StorageProof::describe(&targets.storage_proof))
+            .equal("nullifier.secret", "unspendable_account.secret")
+            .equal(
+                "nullifier.transfer_count",
+                "storage_proof.leaf_inputs.transfer_count",
+            )
+            .equal(
+                "unspendable_account.account_id",
+                "storage_proof.leaf_inputs.to_account",
+            )
+            .range_check("auditor_key", 32)
+            .poseidon("key_hash", &["auditor_key", "AUDITOR_ID"])
+            .equal("key_hash", "auditor_key_hash")
+            .poseidon("pad", &["auditor_key", "nullifier.hash"])
+            .equal(
+                "ciphertext",
+                "storage_proof.leaf_inputs.funding_account + pad",
+            )
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            self.auditor_id == targets.auditor_id,
+            "the disclosure is for another auditor than the circuit"
+        );
+        self.nullifier.fill_targets(pw, targets.nullifier)?;
+        self.unspendable_account.fill_targets(pw, targets.unspendable_account)?;
+        self.storage_proof.fill_targets(pw, targets.storage_proof)?;
+
+        pw.set_target_arr(&targets.auditor_key, &self.auditor_key)?;
+        pw.set_hash_target(targets.auditor_key_hash, felts_to_hashout(&self.auditor_key_hash))?;
+        pw.set_hash_target(targets.ciphertext, felts_to_hashout(&self.ciphertext))?;
+
+        Ok(())
+    }
+}
+
+/// Parses the public inputs of a disclosure proof.
+pub fn disclosure_public_inputs(public_inputs: &[F]) -> anyhow::Result<(NullifierHash, Digest)> {
+    if public_inputs.len() != CIPHERTEXT_END_INDEX {
+        bail!(
+            "Expected {} public inputs for a disclosure proof, got: {}",
+            CIPHERTEXT_END_INDEX,
+            public_inputs.len()
+        );
+    }
+
+    let nullifier: Digest = public_inputs[NULLIFIER_START_INDEX..NULLIFIER_END_INDEX]
+        .try_into()
+        .unwrap();
+    let ciphertext: Digest = public_inputs[CIPHERTEXT_START_INDEX..CIPHERTEXT_END_INDEX]
+        .try_into()
+        .unwrap();
+    Ok((NullifierHash::from(nullifier), ciphertext))
+}
+
+#[cfg(feature = "std")]
+pub mod circuit_logic {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData},
+    };
+    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+    use zk_circuits_common::utils::Digest;
+
+    use super::{DisclosureTargets, FundingDisclosure};
+
+    /// The selective disclosure circuit.
+    pub struct DisclosureCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: DisclosureTargets,
+    }
+
+    impl DisclosureCircuit {
+        /// Builds the disclosure circuit of the auditor `auditor_id`, see
+        /// [`super::auditor_id`].
+        pub fn new(config: CircuitConfig, auditor_id: Digest) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = DisclosureTargets::new(&mut builder, auditor_id);
+            FundingDisclosure::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> DisclosureTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Machine-readable description of the field element encodings used by the wormhole circuit.
+//!
+//! Verifier tooling in other languages has to map public inputs back to integers and bytes the
+//! same way [`zk_circuits_common::utils`] does. [`WORMHOLE_ENCODING`] describes those
+//! conventions and the public input layout of the wormhole circuit. The circuit builder
+//! serializes it to `encoding.json` next to `common.bin`.
+//!
+//! The descriptor is versioned: any change to an encoding or to the public input layout must
+//! bump [`ENCODING_DESCRIPTOR_VERSION`].
+use serde::Serialize;
+use zk_circuits_common::utils::{
+    DIGEST_BYTES_PER_ELEMENT, DIGEST_NUM_FIELD_ELEMENTS, FELTS_PER_U128, FELTS_PER_U64,
+    INJECTIVE_BYTES_PER_ELEMENT,
+};
+
+use crate::inputs::{
+    EXIT_ACCOUNT_END_INDEX, EXIT_ACCOUNT_START_INDEX, FUNDING_AMOUNT_END_INDEX,
+    FUNDING_AMOUNT_START_INDEX, NULLIFIER_END_INDEX, NULLIFIER_START_INDEX, ROOT_HASH_END_INDEX,
+    ROOT_HASH_START_INDEX,
+};
+
+pub const ENCODING_DESCRIPTOR_VERSION: u32 = 1;
+
+/// The file name the circuit builder writes the descriptor to.
+pub const ENCODING_DESCRIPTOR_FILE_NAME: &str = "encoding.json";
+
+/// Describes the field, value encodings and public input layout of a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EncodingDescriptor {
+    pub version: u32,
+    pub field: FieldDescriptor,
+    pub encodings: &'static [ValueEncoding],
+    pub public_inputs: &'static [PublicInputField],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FieldDescriptor {
+    pub name: &'static str,
+    /// The field order in decimal, as it does not fit in a JSON number without loss.
+    pub order: &'static str,
+}
+
+/// How a value is split into field elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValueLayout {
+    /// An unsigned integer split into `limb_bits`-bit limbs, most significant limb first.
+    BigEndianLimbs { limb_bits: u32 },
+    /// A byte string split into `chunk_bytes`-byte chunks in order, each read as a little endian
+    /// integer. A short final chunk is zero padded.
+    LittleEndianChunks { chunk_bytes: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ValueEncoding {
+    pub name: &'static str,
+    /// The number of field elements a value occupies, or `None` for variable length values.
+    pub num_felts: Option<usize>,
+    pub layout: ValueLayout,
+    pub description: &'static str,
+}
+
+/// A named range of public inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PublicInputField {
+    pub name: &'static str,
+    pub start: usize,
+    pub end: usize,
+    /// The name of an entry in [`EncodingDescriptor::encodings`].
+    pub encoding: &'static str,
+}
+
+pub const U64_ENCODING: ValueEncoding = ValueEncoding {
+    name: "u64",
+    num_felts: Some(FELTS_PER_U64),
+    layout: ValueLayout::BigEndianLimbs { limb_bits: 32 },
+    description: "Every limb is a canonical field element below 2^32.",
+};
+
+pub const U128_ENCODING: ValueEncoding = ValueEncoding {
+    name: "u128",
+    num_felts: Some(FELTS_PER_U128),
+    layout: ValueLayout::BigEndianLimbs { limb_bits: 32 },
+    description: "Every limb is a canonical field element below 2^32.",
+};
+
+pub const DIGEST_ENCODING: ValueEncoding = ValueEncoding {
+    name: "digest",
+    num_felts: Some(DIGEST_NUM_FIELD_ELEMENTS),
+    layout: ValueLayout::LittleEndianChunks {
+        chunk_bytes: DIGEST_BYTES_PER_ELEMENT,
+    },
+    description: "A 32 byte hash or account id. Every chunk must be below the field order.",
+};
+
+pub const INJECTIVE_BYTES_ENCODING: ValueEncoding = ValueEncoding {
+    name: "injective_bytes",
+    num_felts: None,
+    layout: ValueLayout::LittleEndianChunks {
+        chunk_bytes: INJECTIVE_BYTES_PER_ELEMENT,
+    },
+    description: "Arbitrary bytes such as secrets and salts. Every chunk is below 2^32, so the \
+                  mapping is injective for a fixed length.",
+};
+
+/// The descriptor of the wormhole circuit.
+pub const WORMHOLE_ENCODING: EncodingDescriptor = EncodingDescriptor {
+    version: ENCODING_DESCRIPTOR_VERSION,
+    field: FieldDescriptor {
+        name: "goldilocks",
+        order: "18446744069414584321",
+    },
+    encodings: &[
+        U64_ENCODING,
+        U128_ENCODING,
+        DIGEST_ENCODING,
+        INJECTIVE_BYTES_ENCODING,
+    ],
+    public_inputs: &[
+        PublicInputField {
+            name: "nullifier",
+            start: NULLIFIER_START_INDEX,
+            end: NULLIFIER_END_INDEX,
+            encoding: "digest",
+        },
+        PublicInputField {
+            name: "root_hash",
+            start: ROOT_HASH_START_INDEX,
+            end: ROOT_HASH_END_INDEX,
+            encoding: "digest",
+        },
+        PublicInputField {
+            name: "funding_amount",
+            start: FUNDING_AMOUNT_START_INDEX,
+            end: FUNDING_AMOUNT_END_INDEX,
+            encoding: "u128",
+        },
+        PublicInputField {
+            name: "exit_account",
+            start: EXIT_ACCOUNT_START_INDEX,
+            end: EXIT_ACCOUNT_END_INDEX,
+            encoding: "digest",
+        },
+    ],
+};
+
+impl EncodingDescriptor {
+    /// Looks up an encoding by name.
+    pub fn encoding(&self, name: &str) -> Option<&ValueEncoding> {
+        self.encodings.iter().find(|encoding| encoding.name == name)
+    }
+}
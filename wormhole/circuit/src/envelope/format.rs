@@ -0,0 +1,270 @@
+//! Wire formats for [`ProofEnvelope`]s.
+//!
+//! Integrators embed proofs in different transports, e.g. SCALE extrinsics, bincode RPC messages,
+//! CBOR documents or JSON APIs. [`EnvelopeFormat`] encodes an envelope in each of them behind a
+//! common header, so a receiver can decode any of them with [`EnvelopeFormat::decode`] instead of
+//! every integrator wrapping [`ByteCodec::to_bytes`] differently.
+//!
+//! # Header
+//!
+//! Every encoding starts with [`FORMAT_MAGIC`] followed by the ASCII tag of the format, see
+//! [`EnvelopeFormat::tag`]. Both are ASCII, so JSON encodings stay valid UTF-8 text. The payload
+//! after the header is:
+//!
+//! - [`EnvelopeFormat::Raw`]: the [`ByteCodec`] encoding of the envelope.
+//! - [`EnvelopeFormat::Bincode`], [`EnvelopeFormat::Cbor`]: a struct of the envelope version, the
+//!   verifier digest and the proof, with both byte fields encoded as byte strings.
+//! - [`EnvelopeFormat::JsonHex`]: a JSON object with the same fields, and both byte fields as
+//!   `0x` prefixed hex strings.
+//!
+//! The formats other than [`EnvelopeFormat::Raw`] are behind the `envelope-bincode`,
+//! `envelope-cbor` and `envelope-json` features.
+use alloc::vec::Vec;
+use anyhow::bail;
+
+use super::ProofEnvelope;
+use crate::codec::ByteCodec;
+
+/// Magic bytes prefixed to every envelope encoded by [`EnvelopeFormat::encode`].
+pub const FORMAT_MAGIC: [u8; 4] = *b"QPWF";
+/// Length of the format header: magic and format tag.
+pub const FORMAT_HEADER_LEN: usize = FORMAT_MAGIC.len() + 1;
+
+/// A wire format for [`ProofEnvelope`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeFormat {
+    /// The [`ByteCodec`] encoding.
+    Raw,
+    #[cfg(feature = "envelope-bincode")]
+    Bincode,
+    #[cfg(feature = "envelope-cbor")]
+    Cbor,
+    #[cfg(feature = "envelope-json")]
+    JsonHex,
+}
+
+impl EnvelopeFormat {
+    /// Every format enabled in this build.
+    pub const ALL: &'static [Self] = &[
+        Self::Raw,
+        #[cfg(feature = "envelope-bincode")]
+        Self::Bincode,
+        #[cfg(feature = "envelope-cbor")]
+        Self::Cbor,
+        #[cfg(feature = "envelope-json")]
+        Self::JsonHex,
+    ];
+
+    /// The byte identifying the format in the header.
+    pub fn tag(self) -> u8 {
+        match self {
+            Self::Raw => b'r',
+            #[cfg(feature = "envelope-bincode")]
+            Self::Bincode => b'b',
+            #[cfg(feature = "envelope-cbor")]
+            Self::Cbor => b'c',
+            #[cfg(feature = "envelope-json")]
+            Self::JsonHex => b'j',
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the tag is unknown or its format is not enabled in this build.
+    pub fn from_tag(tag: u8) -> anyhow::Result<Self> {
+        match Self::ALL.iter().find(|format| format.tag() == tag) {
+            Some(&format) => Ok(format),
+            None => bail!("unknown or disabled proof envelope format: {:#04x}", tag),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            #[cfg(feature = "envelope-bincode")]
+            Self::Bincode => "bincode",
+            #[cfg(feature = "envelope-cbor")]
+            Self::Cbor => "cbor",
+            #[cfg(feature = "envelope-json")]
+            Self::JsonHex => "json-hex",
+        }
+    }
+
+    /// Encodes `envelope` in this format, prefixed with the format header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the serializer fails.
+    pub fn encode(self, envelope: &ProofEnvelope) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(FORMAT_HEADER_LEN + envelope.proof.len());
+        bytes.extend(FORMAT_MAGIC);
+        bytes.push(self.tag());
+
+        match self {
+            Self::Raw => bytes.extend(envelope.to_bytes()),
+            #[cfg(feature = "envelope-bincode")]
+            Self::Bincode => {
+                let binary = serde_impl::Binary::from(envelope);
+                bincode::serialize_into(&mut bytes, &binary).map_err(serde_impl::encode_error)?
+            }
+            #[cfg(feature = "envelope-cbor")]
+            Self::Cbor => {
+                let binary = serde_impl::Binary::from(envelope);
+                ciborium::into_writer(&binary, &mut bytes).map_err(serde_impl::encode_error)?
+            }
+            #[cfg(feature = "envelope-json")]
+            Self::JsonHex => {
+                let json = serde_impl::Json::from(envelope);
+                bytes.extend(serde_json::to_vec(&json).map_err(serde_impl::encode_error)?)
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decodes an envelope encoded by [`Self::encode`] in any enabled format, and returns it with
+    /// the format it was encoded in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header is missing or names a disabled format, or the payload is
+    /// not a valid envelope in that format.
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<(Self, ProofEnvelope)> {
+        if bytes.len() < FORMAT_HEADER_LEN || bytes[..FORMAT_MAGIC.len()] != FORMAT_MAGIC {
+            bail!("missing proof envelope format header");
+        }
+
+        let format = Self::from_tag(bytes[FORMAT_MAGIC.len()])?;
+        let payload = &bytes[FORMAT_HEADER_LEN..];
+        let envelope = match format {
+            Self::Raw => ProofEnvelope::from_bytes(payload)?,
+            #[cfg(feature = "envelope-bincode")]
+            Self::Bincode => bincode::deserialize::<serde_impl::Binary>(payload)
+                .map_err(serde_impl::decode_error)?
+                .try_into()?,
+            #[cfg(feature = "envelope-cbor")]
+            Self::Cbor => ciborium::from_reader::<serde_impl::Binary, _>(payload)
+                .map_err(serde_impl::decode_error)?
+                .try_into()?,
+            #[cfg(feature = "envelope-json")]
+            Self::JsonHex => serde_json::from_slice::<serde_impl::Json>(payload)
+                .map_err(serde_impl::decode_error)?
+                .try_into()?,
+        };
+
+        Ok((format, envelope))
+    }
+}
+
+#[cfg(any(
+    feature = "envelope-bincode",
+    feature = "envelope-cbor",
+    feature = "envelope-json"
+))]
+mod serde_impl {
+    #[cfg(any(feature = "envelope-bincode", feature = "envelope-cbor"))]
+    use alloc::vec::Vec;
+    use anyhow::{anyhow, bail};
+    use core::fmt::Display;
+    use serde::{Deserialize, Serialize};
+    use zk_circuits_common::utils::BytesDigest;
+
+    use super::super::{ProofEnvelope, ENVELOPE_VERSION};
+
+    pub(super) fn encode_error(e: impl Display) -> anyhow::Error {
+        anyhow!("failed to encode proof envelope: {}", e)
+    }
+
+    pub(super) fn decode_error(e: impl Display) -> anyhow::Error {
+        anyhow!("failed to decode proof envelope: {}", e)
+    }
+
+    fn check_version(version: u8) -> anyhow::Result<()> {
+        if version != ENVELOPE_VERSION {
+            bail!(
+                "unsupported proof envelope version: {}, expected: {}",
+                version,
+                ENVELOPE_VERSION
+            );
+        }
+        Ok(())
+    }
+
+    fn verifier_digest(bytes: &[u8]) -> anyhow::Result<BytesDigest> {
+        BytesDigest::try_from(bytes)
+            .map_err(|e| anyhow!("invalid verifier digest in proof envelope: {:?}", e))
+    }
+
+    /// The envelope in binary serde formats.
+    #[cfg(any(feature = "envelope-bincode", feature = "envelope-cbor"))]
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Binary {
+        version: u8,
+        #[serde(with = "serde_bytes")]
+        verifier_digest: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        proof: Vec<u8>,
+    }
+
+    #[cfg(any(feature = "envelope-bincode", feature = "envelope-cbor"))]
+    impl From<&ProofEnvelope> for Binary {
+        fn from(envelope: &ProofEnvelope) -> Self {
+            Self {
+                version: ENVELOPE_VERSION,
+                verifier_digest: envelope.verifier_digest.to_vec(),
+                proof: envelope.proof.clone(),
+            }
+        }
+    }
+
+    #[cfg(any(feature = "envelope-bincode", feature = "envelope-cbor"))]
+    impl TryFrom<Binary> for ProofEnvelope {
+        type Error = anyhow::Error;
+
+        fn try_from(binary: Binary) -> Result<Self, Self::Error> {
+            check_version(binary.version)?;
+            Ok(Self {
+                verifier_digest: verifier_digest(&binary.verifier_digest)?,
+                proof: binary.proof,
+            })
+        }
+    }
+
+    /// The envelope in JSON, with the byte fields as hex strings.
+    #[cfg(feature = "envelope-json")]
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Json {
+        version: u8,
+        verifier_digest: alloc::string::String,
+        proof: alloc::string::String,
+    }
+
+    #[cfg(feature = "envelope-json")]
+    impl From<&ProofEnvelope> for Json {
+        fn from(envelope: &ProofEnvelope) -> Self {
+            Self {
+                version: ENVELOPE_VERSION,
+                verifier_digest: alloc::format!("0x{}", hex::encode(*envelope.verifier_digest)),
+                proof: alloc::format!("0x{}", hex::encode(&envelope.proof)),
+            }
+        }
+    }
+
+    #[cfg(feature = "envelope-json")]
+    impl TryFrom<Json> for ProofEnvelope {
+        type Error = anyhow::Error;
+
+        fn try_from(json: Json) -> Result<Self, Self::Error> {
+            check_version(json.version)?;
+            let decode = |field: &str, value: &str| {
+                crate::hex_util::decode(value)
+                    .map_err(|e| anyhow!("invalid hex in proof envelope {}: {}", field, e))
+            };
+            let digest = decode("verifier_digest", &json.verifier_digest)?;
+            Ok(Self {
+                verifier_digest: verifier_digest(&digest)?,
+                proof: decode("proof", &json.proof)?,
+            })
+        }
+    }
+}
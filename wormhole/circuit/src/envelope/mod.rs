@@ -0,0 +1,142 @@
+//! Proof envelope.
+//!
+//! A [`ProofEnvelope`] wraps a serialized proof together with the digest of the verifier data it
+//! was generated for. Services that run several circuit versions side by side can route a proof to
+//! the right verifier, and reject version skew, by comparing digests before doing any
+//! cryptographic work.
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail};
+#[cfg(feature = "scale")]
+use parity_scale_codec::{Decode, Encode, EncodeLike, Error as ScaleError, Input, Output};
+use plonky2::{
+    field::types::{Field, PrimeField64},
+    hash::hash_types::HashOut,
+    plonk::{circuit_data::CommonCircuitData, proof::ProofWithPublicInputs},
+};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::BytesDigest;
+
+use crate::codec::ByteCodec;
+
+pub mod format;
+#[cfg(feature = "envelope-encryption")]
+pub mod sealed;
+
+/// Magic bytes prefixed to every serialized envelope.
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"QPWH";
+/// The current envelope format version.
+pub const ENVELOPE_VERSION: u8 = 1;
+/// Length of the envelope header: magic, version and verifier digest.
+pub const ENVELOPE_HEADER_LEN: usize = ENVELOPE_MAGIC.len() + 1 + 32;
+
+/// A serialized proof tagged with the digest of the verifier data it verifies against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofEnvelope {
+    /// The circuit digest of the verifier data this proof was generated for.
+    pub verifier_digest: BytesDigest,
+    /// The serialized [`ProofWithPublicInputs`].
+    pub proof: Vec<u8>,
+}
+
+impl ProofEnvelope {
+    pub fn new(verifier_digest: BytesDigest, proof: &ProofWithPublicInputs<F, C, D>) -> Self {
+        Self {
+            verifier_digest,
+            proof: proof.to_bytes(),
+        }
+    }
+
+    /// Deserializes the wrapped proof.
+    pub fn proof(
+        &self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        ProofWithPublicInputs::from_bytes(self.proof.clone(), common_data)
+            .map_err(|e| anyhow!("failed to deserialize enveloped proof: {}", e))
+    }
+}
+
+impl ByteCodec for ProofEnvelope {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENVELOPE_HEADER_LEN + self.proof.len());
+        bytes.extend(ENVELOPE_MAGIC);
+        bytes.push(ENVELOPE_VERSION);
+        bytes.extend(*self.verifier_digest);
+        bytes.extend(&self.proof);
+        bytes
+    }
+
+    fn from_bytes(slice: &[u8]) -> anyhow::Result<Self> {
+        if slice.len() < ENVELOPE_HEADER_LEN {
+            bail!(
+                "Expected at least {} bytes for ProofEnvelope, got: {}",
+                ENVELOPE_HEADER_LEN,
+                slice.len()
+            );
+        }
+
+        let (magic, rest) = slice.split_at(ENVELOPE_MAGIC.len());
+        if magic != ENVELOPE_MAGIC {
+            bail!("invalid proof envelope magic bytes");
+        }
+
+        let (version, rest) = rest.split_at(1);
+        if version[0] != ENVELOPE_VERSION {
+            bail!(
+                "unsupported proof envelope version: {}, expected: {}",
+                version[0],
+                ENVELOPE_VERSION
+            );
+        }
+
+        let (digest, proof) = rest.split_at(32);
+        let verifier_digest = BytesDigest::try_from(digest)
+            .map_err(|e| anyhow!("invalid verifier digest in proof envelope: {:?}", e))?;
+
+        Ok(Self {
+            verifier_digest,
+            proof: proof.to_vec(),
+        })
+    }
+}
+
+/// SCALE encodes as the envelope version, the verifier digest and the length prefixed proof.
+/// Unlike [`ByteCodec`], there are no magic bytes, as SCALE values are always decoded as a known
+/// type.
+#[cfg(feature = "scale")]
+impl Encode for ProofEnvelope {
+    fn size_hint(&self) -> usize {
+        1 + 32 + self.proof.size_hint()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        ENVELOPE_VERSION.encode_to(dest);
+        self.verifier_digest.encode_to(dest);
+        self.proof.encode_to(dest);
+    }
+}
+
+#[cfg(feature = "scale")]
+impl EncodeLike for ProofEnvelope {}
+
+#[cfg(feature = "scale")]
+impl Decode for ProofEnvelope {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, ScaleError> {
+        if u8::decode(input)? != ENVELOPE_VERSION {
+            return Err("unsupported proof envelope version".into());
+        }
+
+        Ok(Self {
+            verifier_digest: BytesDigest::decode(input)?,
+            proof: Vec::decode(input)?,
+        })
+    }
+}
+
+/// Converts a plonky2 circuit digest into its canonical byte representation.
+pub fn circuit_digest_to_bytes(digest: HashOut<F>) -> BytesDigest {
+    let felts = digest
+        .elements
+        .map(|felt| F::from_canonical_u64(felt.to_canonical_u64()));
+    BytesDigest::from(felts)
+}
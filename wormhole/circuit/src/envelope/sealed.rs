@@ -0,0 +1,328 @@
+//! Private metadata sealed to an aggregator.
+//!
+//! Submitters often send metadata along with a proof that only the aggregator should read, e.g.
+//! a refund address. A [`SealedSubmission`] carries the [`ProofEnvelope`] in the clear and the
+//! metadata encrypted to an X25519 key of the aggregator:
+//!
+//! - The submitter generates an ephemeral X25519 key and agrees on a shared secret with the
+//!   aggregator's [`AggregatorPublicKey`].
+//! - HKDF-SHA256 derives a ChaCha20-Poly1305 key from the shared secret, salted with both public
+//!   keys. Every key encrypts a single message, so the nonce is zero.
+//! - The header and the envelope are authenticated as associated data, so sealed metadata can't
+//!   be moved to another proof.
+//!
+//! Aggregators rotate keys by adding a new [`AggregatorSecretKey`] to their [`AggregatorKeyring`],
+//! publishing its public key, and retiring the old key once the submissions sealed to it have
+//! drained. Every submission names the [`KeyId`] of the key it was sealed to.
+//!
+//! # Wire Format
+//!
+//! [`SEALED_MAGIC`], [`SEALED_VERSION`], the key id, the ephemeral public key, the length of the
+//! envelope as a little endian `u32`, the [`ByteCodec`] encoding of the envelope, and the
+//! ciphertext with its 16 byte tag.
+//!
+//! This module is behind the `envelope-encryption` feature.
+use alloc::{collections::BTreeMap, vec::Vec};
+use anyhow::{anyhow, bail};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use rand_core::{CryptoRngCore, OsRng};
+use sha2::{Digest as _, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey, SharedSecret, StaticSecret};
+
+use super::ProofEnvelope;
+use crate::codec::ByteCodec;
+
+/// Magic bytes prefixed to every serialized [`SealedSubmission`].
+pub const SEALED_MAGIC: [u8; 4] = *b"QPSL";
+/// The current sealed submission format version.
+pub const SEALED_VERSION: u8 = 1;
+/// Length of the header: magic, version, key id, ephemeral public key and envelope length.
+pub const SEALED_HEADER_LEN: usize = SEALED_MAGIC.len() + 1 + KEY_ID_LEN + 32 + 4;
+
+const KEY_ID_LEN: usize = 8;
+const KDF_INFO: &[u8] = b"qp-wormhole sealed metadata v1";
+const NONCE: [u8; 12] = [0; 12];
+
+/// Identifies an aggregator key: the first 8 bytes of the SHA-256 of its public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyId(pub [u8; KEY_ID_LEN]);
+
+impl core::fmt::Display for KeyId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+/// The key submitters seal metadata to, as published by the aggregator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregatorPublicKey(PublicKey);
+
+impl AggregatorPublicKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(PublicKey::from(bytes))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn key_id(&self) -> KeyId {
+        let hash = Sha256::digest(self.0.as_bytes());
+        KeyId(hash[..KEY_ID_LEN].try_into().unwrap())
+    }
+}
+
+/// The secret of an [`AggregatorPublicKey`]. The bytes are left out of the
+/// [`core::fmt::Debug`] output.
+#[derive(Clone)]
+pub struct AggregatorSecretKey(StaticSecret);
+
+impl AggregatorSecretKey {
+    /// Generates a key from the operating system's randomness.
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn public_key(&self) -> AggregatorPublicKey {
+        AggregatorPublicKey(PublicKey::from(&self.0))
+    }
+}
+
+impl core::fmt::Debug for AggregatorSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AggregatorSecretKey({})", self.public_key().key_id())
+    }
+}
+
+/// The keys an aggregator opens submissions with. Submitters should seal to
+/// [`Self::current_public_key`], the other keys are kept to open submissions sealed before the
+/// last rotation.
+#[derive(Debug, Default, Clone)]
+pub struct AggregatorKeyring {
+    keys: BTreeMap<KeyId, AggregatorSecretKey>,
+    current: Option<KeyId>,
+}
+
+impl AggregatorKeyring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `key` and makes it the current key. Returns its id.
+    pub fn rotate(&mut self, key: AggregatorSecretKey) -> KeyId {
+        let key_id = key.public_key().key_id();
+        self.keys.insert(key_id, key);
+        self.current = Some(key_id);
+        key_id
+    }
+
+    /// Removes the key with `key_id`, so submissions sealed to it can no longer be opened.
+    /// Returns whether the keyring held it. Retiring the current key leaves the keyring without
+    /// a current key until the next [`Self::rotate`].
+    pub fn retire(&mut self, key_id: &KeyId) -> bool {
+        if self.current.as_ref() == Some(key_id) {
+            self.current = None;
+        }
+        self.keys.remove(key_id).is_some()
+    }
+
+    /// The key submitters should seal to, if the keyring has a current key.
+    pub fn current_public_key(&self) -> Option<AggregatorPublicKey> {
+        let current = self.current?;
+        self.keys.get(&current).map(AggregatorSecretKey::public_key)
+    }
+
+    /// The ids of the keys in the keyring, in ascending byte order.
+    pub fn key_ids(&self) -> impl Iterator<Item = KeyId> + '_ {
+        self.keys.keys().copied()
+    }
+
+    /// Decrypts the metadata of `sealed` with the key it was sealed to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring doesn't hold the key, e.g. because it was retired, or the
+    /// submission was tampered with.
+    pub fn open(&self, sealed: &SealedSubmission) -> anyhow::Result<Vec<u8>> {
+        let key = self.keys.get(&sealed.key_id).ok_or_else(|| {
+            anyhow!(
+                "no aggregator key {} in the keyring, it may have been retired",
+                sealed.key_id
+            )
+        })?;
+
+        let ephemeral_public_key = PublicKey::from(sealed.ephemeral_public_key);
+        let shared_secret = key.0.diffie_hellman(&ephemeral_public_key);
+        if !shared_secret.was_contributory() {
+            bail!("sealed submission has a low order ephemeral key");
+        }
+
+        let cipher = metadata_cipher(
+            &shared_secret,
+            &sealed.ephemeral_public_key,
+            &key.public_key(),
+        );
+        let aad = sealed.associated_data();
+        cipher
+            .decrypt(
+                Nonce::from_slice(&NONCE),
+                Payload {
+                    msg: &sealed.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("failed to open sealed metadata, the submission was modified"))
+    }
+}
+
+/// A [`ProofEnvelope`] with metadata only the aggregator can read, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedSubmission {
+    pub envelope: ProofEnvelope,
+    /// The id of the aggregator key the metadata is sealed to.
+    pub key_id: KeyId,
+    pub ephemeral_public_key: [u8; 32],
+    /// The encrypted metadata followed by the authentication tag.
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedSubmission {
+    /// Seals `metadata` to `recipient` with an ephemeral key from the operating system's
+    /// randomness.
+    pub fn seal(
+        envelope: ProofEnvelope,
+        metadata: &[u8],
+        recipient: &AggregatorPublicKey,
+    ) -> anyhow::Result<Self> {
+        Self::seal_with_rng(envelope, metadata, recipient, &mut OsRng)
+    }
+
+    /// Like [`Self::seal`], but draws the ephemeral key from `rng`.
+    pub fn seal_with_rng(
+        envelope: ProofEnvelope,
+        metadata: &[u8],
+        recipient: &AggregatorPublicKey,
+        rng: &mut impl CryptoRngCore,
+    ) -> anyhow::Result<Self> {
+        let ephemeral_secret = EphemeralSecret::random_from_rng(rng);
+        let ephemeral_public_key = PublicKey::from(&ephemeral_secret).to_bytes();
+        let shared_secret = ephemeral_secret.diffie_hellman(&recipient.0);
+        if !shared_secret.was_contributory() {
+            bail!("aggregator public key is a low order point");
+        }
+
+        let mut sealed = Self {
+            envelope,
+            key_id: recipient.key_id(),
+            ephemeral_public_key,
+            ciphertext: Vec::new(),
+        };
+        let cipher = metadata_cipher(&shared_secret, &ephemeral_public_key, recipient);
+        let aad = sealed.associated_data();
+        sealed.ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&NONCE),
+                Payload {
+                    msg: metadata,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("failed to seal {} bytes of metadata", metadata.len()))?;
+
+        Ok(sealed)
+    }
+
+    /// Everything but the ciphertext, in the order of the wire format.
+    fn associated_data(&self) -> Vec<u8> {
+        let envelope = self.envelope.to_bytes();
+        let mut bytes = Vec::with_capacity(SEALED_HEADER_LEN + envelope.len());
+        bytes.extend(SEALED_MAGIC);
+        bytes.push(SEALED_VERSION);
+        bytes.extend(self.key_id.0);
+        bytes.extend(self.ephemeral_public_key);
+        bytes.extend((envelope.len() as u32).to_le_bytes());
+        bytes.extend(envelope);
+        bytes
+    }
+}
+
+fn metadata_cipher(
+    shared_secret: &SharedSecret,
+    ephemeral_public_key: &[u8; 32],
+    recipient: &AggregatorPublicKey,
+) -> ChaCha20Poly1305 {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral_public_key);
+    salt[32..].copy_from_slice(recipient.0.as_bytes());
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret.as_bytes())
+        .expand(KDF_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(&key.into())
+}
+
+impl ByteCodec for SealedSubmission {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.associated_data();
+        bytes.extend(&self.ciphertext);
+        bytes
+    }
+
+    fn from_bytes(slice: &[u8]) -> anyhow::Result<Self> {
+        if slice.len() < SEALED_HEADER_LEN {
+            bail!(
+                "Expected at least {} bytes for SealedSubmission, got: {}",
+                SEALED_HEADER_LEN,
+                slice.len()
+            );
+        }
+
+        let (magic, rest) = slice.split_at(SEALED_MAGIC.len());
+        if magic != SEALED_MAGIC {
+            bail!("invalid sealed submission magic bytes");
+        }
+
+        let (version, rest) = rest.split_at(1);
+        if version[0] != SEALED_VERSION {
+            bail!(
+                "unsupported sealed submission version: {}, expected: {}",
+                version[0],
+                SEALED_VERSION
+            );
+        }
+
+        let (key_id, rest) = rest.split_at(KEY_ID_LEN);
+        let (ephemeral_public_key, rest) = rest.split_at(32);
+        let (envelope_len, rest) = rest.split_at(4);
+        let envelope_len = u32::from_le_bytes(envelope_len.try_into().unwrap()) as usize;
+        if rest.len() < envelope_len {
+            bail!(
+                "sealed submission holds {} bytes after the header, the envelope alone has {}",
+                rest.len(),
+                envelope_len
+            );
+        }
+        let (envelope, ciphertext) = rest.split_at(envelope_len);
+        let envelope = ProofEnvelope::from_bytes(envelope)
+            .map_err(|e| anyhow!("invalid envelope in sealed submission: {}", e))?;
+
+        Ok(Self {
+            envelope,
+            key_id: KeyId(key_id.try_into().unwrap()),
+            ephemeral_public_key: ephemeral_public_key.try_into().unwrap(),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
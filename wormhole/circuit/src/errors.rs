@@ -0,0 +1,142 @@
+//! Catalog of the structured errors returned while preparing wormhole proofs.
+//!
+//! Errors with a stable code, such as [`StorageProofError`], [`LeafError`], [`HexError`],
+//! [`DigestError`] and [`ShapeError`], can be mapped to user-facing guidance by support tooling
+//! and UIs. [`error_catalog`] lists every code with a description and its typical cause. The
+//! circuit builder's `error-catalog` binary writes it to JSON.
+//!
+//! Other failures, including those of the prover, verifier and aggregator, are reported as
+//! `anyhow` errors without a code and are not part of the catalog. Codes may be added, but
+//! existing codes are never reused for a different error.
+//!
+//! [`DigestError`]: zk_circuits_common::utils::DigestError
+//! [`ShapeError`]: zk_circuits_common::circuit::ShapeError
+use serde::Serialize;
+
+#[cfg(doc)]
+use crate::{
+    hex_util::HexError,
+    storage_proof::{leaf::LeafError, StorageProofError},
+};
+
+/// A kind of error, identified by the code its `code()` method returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorCatalogEntry {
+    pub code: &'static str,
+    /// What the error means.
+    pub description: &'static str,
+    /// What usually leads to it, phrased for whoever has to fix it.
+    pub typical_cause: &'static str,
+}
+
+const ERROR_CATALOG: &[ErrorCatalogEntry] = &[
+    ErrorCatalogEntry {
+        code: "digest.chunk_out_of_field_range",
+        description: "An 8 byte chunk of a digest is not a canonical Goldilocks field element.",
+        typical_cause: "The bytes are not a digest produced by the Poseidon hasher, e.g. a hash \
+                        from another hash function or corrupted data.",
+    },
+    ErrorCatalogEntry {
+        code: "digest.invalid_length",
+        description: "A digest does not have 32 bytes.",
+        typical_cause: "A truncated or wrongly decoded hex string, such as one that still has \
+                        its 0x prefix or lost a leading zero.",
+    },
+    ErrorCatalogEntry {
+        code: "hex.invalid_character",
+        description: "A hex string contains a character that is not a hex digit.",
+        typical_cause: "Whitespace, a line break or quotes were copied along with the value, or \
+                        it is encoded in another format such as base64.",
+    },
+    ErrorCatalogEntry {
+        code: "hex.invalid_length",
+        description: "A hex string decodes to a different number of bytes than expected.",
+        typical_cause: "A value of another kind was given, e.g. an address instead of a \
+                        secret, or the string was truncated.",
+    },
+    ErrorCatalogEntry {
+        code: "hex.odd_length",
+        description: "A hex string has an odd number of digits.",
+        typical_cause: "A digit was dropped while copying, or a leading zero was stripped by a \
+                        number formatter.",
+    },
+    ErrorCatalogEntry {
+        code: "hex.uppercase_character",
+        description: "A hex string contains an uppercase digit.",
+        typical_cause: "The value was encoded by a tool that writes uppercase hex; only the \
+                        lowercase encoding is accepted.",
+    },
+    ErrorCatalogEntry {
+        code: "leaf.mismatch",
+        description: "No transfer count near the given one makes the leaf node of the storage \
+                      proof hold the transfer.",
+        typical_cause: "The funding account, funding amount or unspendable account differs from \
+                        the transfer, or the proof was fetched for another storage key.",
+    },
+    ErrorCatalogEntry {
+        code: "leaf.stale_transfer_count",
+        description: "The leaf node of the storage proof holds the transfer with another \
+                      transfer count than the given one.",
+        typical_cause: "The transfer count was read before later transfers were made or from \
+                        an outdated cache; the error names the count to use instead.",
+    },
+    ErrorCatalogEntry {
+        code: "shape.length_mismatch",
+        description: "An input of a circuit fragment has a different number of values than its \
+                      targets hold.",
+        typical_cause: "The circuit was built for other parameters than the inputs, e.g. another \
+                        tree depth or batch size, or a secret was not 32 bytes.",
+    },
+    ErrorCatalogEntry {
+        code: "shape.too_long",
+        description: "An input of a circuit fragment has more values than its targets hold.",
+        typical_cause: "The inputs are for a larger circuit than the one being proven, e.g. a \
+                        deeper tree or a larger batch.",
+    },
+    ErrorCatalogEntry {
+        code: "shape.too_short",
+        description: "An input of a circuit fragment has fewer values than another input \
+                      requires.",
+        typical_cause: "A Merkle path was truncated, or its depth was set for another tree.",
+    },
+    ErrorCatalogEntry {
+        code: "storage_proof.index_out_of_bounds",
+        description: "A hash index leaves no room for a 32 byte hash in its node.",
+        typical_cause: "The index belongs to another node of the proof, or the node was \
+                        truncated.",
+    },
+    ErrorCatalogEntry {
+        code: "storage_proof.length_mismatch",
+        description: "The storage proof has a different number of nodes than hash indices.",
+        typical_cause: "The indices were computed for another proof, or a node was dropped \
+                        while processing the RPC response.",
+    },
+    ErrorCatalogEntry {
+        code: "storage_proof.node_too_large",
+        description: "A storage proof node is larger than the circuit supports.",
+        typical_cause: "The node was not trimmed to its encoded length, or the proof is from a \
+                        chain with a different trie layout.",
+    },
+    ErrorCatalogEntry {
+        code: "storage_proof.too_many_nodes",
+        description: "The storage proof has more nodes than the circuit supports.",
+        typical_cause: "The storage trie is deeper than the circuit was built for, or the \
+                        proof was fetched for the wrong key.",
+    },
+    ErrorCatalogEntry {
+        code: "storage_proof.unaligned_index",
+        description: "A hash index does not fall on a field element boundary of its node.",
+        typical_cause: "The index was computed in bytes rather than hex characters, or for the \
+                        unpadded node.",
+    },
+];
+
+/// Every structured error, sorted by code.
+pub fn error_catalog() -> &'static [ErrorCatalogEntry] {
+    ERROR_CATALOG
+}
+
+/// The catalog entry of `code`, if there is one.
+pub fn catalog_entry(code: &str) -> Option<&'static ErrorCatalogEntry> {
+    ERROR_CATALOG.iter().find(|entry| entry.code == code)
+}
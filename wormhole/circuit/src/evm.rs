@@ -0,0 +1,138 @@
+//! Conversions between public inputs and the 32 byte words of EVM contracts.
+//!
+//! Solidity verifiers and bridges read public inputs as `bytes32` and `uint256` words. With
+//! [`PublicCircuitInputs::to_evm_words`] every public input takes one word, in the order of the
+//! public inputs:
+//!
+//! - `nullifier`, `root_hash` and `exit_account` are `bytes32` holding the same 32 bytes the
+//!   chain uses for them.
+//! - `funding_amount` is a big-endian `uint256`, so the amount is in the low 16 bytes and its
+//!   four 32 bit limbs appear most significant first, like in the public inputs.
+//!
+//! [`PublicCircuitInputs::to_evm_bytes`] concatenates the words, which is their
+//! `abi.encode(bytes32, bytes32, uint256, bytes32)`. Verifiers that take the raw public inputs
+//! instead get one big-endian `uint256` per field element from [`felts_to_evm_words`].
+use alloc::{format, vec::Vec};
+use anyhow::{anyhow, bail, Context};
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use zk_circuits_common::circuit::F;
+
+use crate::inputs::PublicCircuitInputs;
+use crate::types::{AccountId, NullifierHash, RootHash};
+
+/// The number of bytes of an EVM word.
+pub const EVM_WORD_BYTES: usize = 32;
+
+/// The number of words of [`PublicCircuitInputs::to_evm_words`].
+pub const PUBLIC_INPUTS_EVM_WORDS: usize = 4;
+
+/// A 32 byte EVM word, e.g. a `bytes32` or a big-endian `uint256`.
+pub type EvmWord = [u8; EVM_WORD_BYTES];
+
+/// Encodes `value` as a big-endian `uint256`.
+pub fn u128_to_evm_word(value: u128) -> EvmWord {
+    let mut word = [0u8; EVM_WORD_BYTES];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Decodes a big-endian `uint256` into a `u128`.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't fit into 128 bits.
+pub fn evm_word_to_u128(word: &EvmWord) -> anyhow::Result<u128> {
+    if word[..16].iter().any(|&byte| byte != 0) {
+        bail!("uint256 does not fit into 128 bits");
+    }
+    Ok(u128::from_be_bytes(word[16..].try_into().unwrap()))
+}
+
+/// Encodes every field element as a big-endian `uint256` of its canonical value.
+pub fn felts_to_evm_words(felts: &[F]) -> Vec<EvmWord> {
+    felts
+        .iter()
+        .map(|felt| u128_to_evm_word(felt.to_canonical_u64().into()))
+        .collect()
+}
+
+/// Decodes big-endian `uint256` words into field elements.
+///
+/// # Errors
+///
+/// Returns an error if a word is not the canonical value of a field element.
+pub fn evm_words_to_felts(words: &[EvmWord]) -> anyhow::Result<Vec<F>> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let value = evm_word_to_u128(word).with_context(|| format!("word {}", i))?;
+            if value >= F::ORDER.into() {
+                bail!("word {} is not a canonical field element", i);
+            }
+            Ok(F::from_canonical_u64(value as u64))
+        })
+        .collect()
+}
+
+impl PublicCircuitInputs {
+    /// The public inputs as EVM words, one per input in the order of the public inputs.
+    pub fn to_evm_words(&self) -> [EvmWord; PUBLIC_INPUTS_EVM_WORDS] {
+        [
+            *self.nullifier,
+            *self.root_hash,
+            u128_to_evm_word(self.funding_amount),
+            *self.exit_account,
+        ]
+    }
+
+    /// Parses public inputs from the words of [`Self::to_evm_words`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a `bytes32` isn't a valid digest or the amount doesn't fit into 128
+    /// bits.
+    pub fn from_evm_words(words: &[EvmWord; PUBLIC_INPUTS_EVM_WORDS]) -> anyhow::Result<Self> {
+        let nullifier =
+            NullifierHash::try_from(words[0]).map_err(|e| anyhow!("invalid nullifier: {:?}", e))?;
+        let root_hash =
+            RootHash::try_from(words[1]).map_err(|e| anyhow!("invalid root hash: {:?}", e))?;
+        let funding_amount = evm_word_to_u128(&words[2]).context("invalid funding amount")?;
+        let exit_account =
+            AccountId::try_from(words[3]).map_err(|e| anyhow!("invalid exit account: {:?}", e))?;
+
+        Ok(Self {
+            funding_amount,
+            nullifier,
+            root_hash,
+            exit_account,
+        })
+    }
+
+    /// The words of [`Self::to_evm_words`], concatenated.
+    pub fn to_evm_bytes(&self) -> Vec<u8> {
+        self.to_evm_words().concat()
+    }
+
+    /// Parses public inputs from the bytes of [`Self::to_evm_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` doesn't hold exactly [`PUBLIC_INPUTS_EVM_WORDS`] words, or
+    /// [`Self::from_evm_words`] rejects them.
+    pub fn from_evm_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() != PUBLIC_INPUTS_EVM_WORDS * EVM_WORD_BYTES {
+            bail!(
+                "public inputs should be {} bytes, got: {}",
+                PUBLIC_INPUTS_EVM_WORDS * EVM_WORD_BYTES,
+                bytes.len()
+            );
+        }
+        let words = core::array::from_fn(|i| {
+            bytes[i * EVM_WORD_BYTES..(i + 1) * EVM_WORD_BYTES]
+                .try_into()
+                .unwrap()
+        });
+        Self::from_evm_words(&words)
+    }
+}
@@ -0,0 +1,178 @@
+//! Exit accounts on EVM chains.
+//!
+//! The exit account is a 32 byte account id. Deployments bridging to EVM chains pay out to 20
+//! byte addresses instead, which circuits built with
+//! [`crate::circuit::circuit_logic::WormholeCircuit::with_exit_account_format`] accept in a padded
+//! encoding, tagged by a public format flag:
+//!
+//! ```text
+//! exit_account = a[0..5] || 000 || a[5..10] || 000 || a[10..15] || 000 || a[15..20] || 000
+//! ```
+//!
+//! Each 8 byte chunk, one field element of the exit account, holds 5 address bytes, so the
+//! encoding is always a valid account id and distinct addresses never collide. If the flag is
+//! set, the circuit asserts that the exit account is in this encoding, so the pallet can recover
+//! the address with [`ExitAccountFormat::evm_address`].
+//!
+//! ## Public Inputs
+//!
+//! After the exit account:
+//! - `exit_account_format`: `0` for substrate accounts, `1` for EVM addresses.
+use anyhow::{bail, ensure};
+use plonky2::{
+    field::types::Field,
+    hash::hash_types::HashOutTarget,
+    iop::{
+        target::BoolTarget,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+
+use crate::inputs::{
+    register_public_input, Public, EXIT_ACCOUNT_FORMAT_END_INDEX, EXIT_ACCOUNT_FORMAT_INDEX,
+};
+use crate::types::AccountId;
+
+/// The length of an EVM address in bytes.
+pub const EVM_ADDRESS_LEN: usize = 20;
+/// The number of address bytes in each 8 byte chunk of an encoded EVM exit account.
+const EVM_BYTES_PER_CHUNK: usize = 5;
+const CHUNK_LEN: usize = 8;
+
+/// The format of the exit account of a proof.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExitAccountFormat {
+    /// A 32 byte substrate account id.
+    #[default]
+    Substrate,
+    /// A 20 byte EVM address, encoded with [`ExitAccountFormat::evm_exit_account`].
+    Evm,
+}
+
+impl ExitAccountFormat {
+    /// Encodes an EVM address as an exit account, see the [module documentation](self).
+    pub fn evm_exit_account(address: [u8; EVM_ADDRESS_LEN]) -> AccountId {
+        let mut bytes = [0u8; 32];
+        for (chunk, address_bytes) in bytes
+            .chunks_mut(CHUNK_LEN)
+            .zip(address.chunks(EVM_BYTES_PER_CHUNK))
+        {
+            chunk[..EVM_BYTES_PER_CHUNK].copy_from_slice(address_bytes);
+        }
+        AccountId::try_from(bytes).expect("chunks of 5 bytes are canonical field elements")
+    }
+
+    /// Decodes the EVM address of an exit account encoded with [`Self::evm_exit_account`], or
+    /// returns `None` if it isn't one.
+    pub fn evm_address(exit_account: &AccountId) -> Option<[u8; EVM_ADDRESS_LEN]> {
+        let mut address = [0u8; EVM_ADDRESS_LEN];
+        for (chunk, address_bytes) in exit_account
+            .chunks(CHUNK_LEN)
+            .zip(address.chunks_mut(EVM_BYTES_PER_CHUNK))
+        {
+            if chunk[EVM_BYTES_PER_CHUNK..].iter().any(|&byte| byte != 0) {
+                return None;
+            }
+            address_bytes.copy_from_slice(&chunk[..EVM_BYTES_PER_CHUNK]);
+        }
+        Some(address)
+    }
+
+    /// Checks that `exit_account` is valid in this format. Every account id is a valid
+    /// substrate account.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the format is EVM and the exit account is not an encoded address.
+    pub fn check(self, exit_account: &AccountId) -> anyhow::Result<()> {
+        if self == Self::Evm {
+            ensure!(
+                Self::evm_address(exit_account).is_some(),
+                "exit account {} is not an encoded EVM address",
+                hex::encode(**exit_account)
+            );
+        }
+        Ok(())
+    }
+
+    /// Parses the format from the public inputs of a proof of a circuit built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_exit_account_format`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no exit account format, or the flag is not a bit.
+    pub fn try_from_public_inputs(pis: &[F]) -> anyhow::Result<Self> {
+        if pis.len() != EXIT_ACCOUNT_FORMAT_END_INDEX {
+            bail!("proof does not have an exit account format");
+        }
+        match pis[EXIT_ACCOUNT_FORMAT_INDEX] {
+            flag if flag == F::ZERO => Ok(Self::Substrate),
+            flag if flag == F::ONE => Ok(Self::Evm),
+            flag => bail!("invalid exit account format flag: {}", flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExitAccountFormatTargets {
+    /// Set if the exit account is an encoded EVM address.
+    pub is_evm: BoolTarget,
+    /// The exit account, which is filled by the exit account fragment.
+    pub exit_account: HashOutTarget,
+}
+
+impl ExitAccountFormatTargets {
+    /// Registers the format flag as a public input.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, exit_account: HashOutTarget) -> Self {
+        let is_evm = builder.add_virtual_bool_target_safe();
+        register_public_input(builder, &Public::new(is_evm));
+        Self {
+            is_evm,
+            exit_account,
+        }
+    }
+}
+
+impl CircuitFragment for ExitAccountFormat {
+    type Targets = ExitAccountFormatTargets;
+
+    /// Asserts that every element of the exit account fits in 5 bytes if `is_evm` is set.
+    fn circuit(
+        &Self::Targets {
+            is_evm,
+            exit_account,
+        }: &Self::Targets,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        let address_bits = EVM_BYTES_PER_CHUNK * 8;
+        for element in exit_account.elements {
+            let (_, high) = builder.split_low_high(element, address_bits, CHUNK_LEN * 8);
+            let evm_high = builder.mul(is_evm.target, high);
+            builder.assert_zero(evm_high);
+        }
+    }
+
+    fn describe(_targets: &Self::Targets) -> FragmentSummary {
+        let address_bits = EVM_BYTES_PER_CHUNK * 8;
+        FragmentSummary::new("exit_account_format")
+            .range_check("is_evm", 1)
+            .assert(format!(
+                "exit_account[i] == low[i] + high[i] * 2^{} for i < 4",
+                address_bits
+            ))
+            .range_check("low", address_bits)
+            .range_check("high", CHUNK_LEN * 8 - address_bits)
+            .assert("high == 0 if is_evm")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        pw.set_bool_target(targets.is_evm, *self == Self::Evm)
+    }
+}
@@ -0,0 +1,153 @@
+//! Hiding commitments to the funding account, for dispute resolution.
+//!
+//! The funding account of a claim stays private in the wormhole circuit. Circuits built with
+//! [`crate::circuit::circuit_logic::WormholeCircuit::with_funding_commitment`] additionally
+//! expose a commitment to it, with a blinding chosen by the prover:
+//!
+//! ```text
+//! funding_commitment = H(funding_account || blinding)
+//! ```
+//!
+//! Without the blinding the commitment reveals nothing about the funding account, so the origin
+//! of a claim stays private by default. If a dispute arises, the prover hands the
+//! [`FundingAccountOpening`] to a designated party, who checks it against the commitment of the
+//! proof with [`FundingAccountOpening::verify`].
+//!
+//! ## Public Inputs
+//!
+//! After the exit account:
+//! - `funding_commitment`: the commitment to the funding account.
+use anyhow::{bail, ensure};
+use plonky2::{
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::range_check_u32;
+use zk_circuits_common::utils::{felts_to_hashout, injective_bytes_to_felts, Digest};
+
+use crate::inputs::{Private, FUNDING_COMMITMENT_END_INDEX, FUNDING_COMMITMENT_START_INDEX};
+use crate::types::{AccountId, SecretBytes};
+
+/// The number of targets the 32 byte blinding is split into.
+pub const BLINDING_NUM_TARGETS: usize = 8;
+
+/// A funding account and the blinding it is committed to with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundingAccountOpening {
+    pub funding_account: AccountId,
+    /// Random bytes chosen by the prover. Reusing a blinding for the same funding account makes
+    /// its commitments linkable.
+    pub blinding: SecretBytes,
+}
+
+impl FundingAccountOpening {
+    pub fn new(funding_account: AccountId, blinding: SecretBytes) -> Self {
+        Self {
+            funding_account,
+            blinding,
+        }
+    }
+
+    /// The commitment `H(funding_account || blinding)` exposed by the circuit.
+    pub fn commitment(&self) -> Digest {
+        let mut preimage = self.funding_account.to_felts().to_vec();
+        preimage.extend(injective_bytes_to_felts(self.blinding.as_slice()));
+        PoseidonHash::hash_no_pad(&preimage).elements
+    }
+
+    /// Checks that this opens `commitment`, e.g. the one returned by
+    /// [`Self::commitment_from_public_inputs`] for a disputed proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the funding account or blinding differ from the committed ones.
+    pub fn verify(&self, commitment: &Digest) -> anyhow::Result<()> {
+        ensure!(
+            self.commitment() == *commitment,
+            "opening does not match the funding commitment"
+        );
+        Ok(())
+    }
+
+    /// Parses the funding commitment from the public inputs of a proof of a circuit built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_funding_commitment`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no funding commitment.
+    pub fn commitment_from_public_inputs(pis: &[F]) -> anyhow::Result<Digest> {
+        if pis.len() != FUNDING_COMMITMENT_END_INDEX {
+            bail!("proof does not commit to its funding account");
+        }
+        Ok(core::array::from_fn(|i| {
+            pis[FUNDING_COMMITMENT_START_INDEX + i]
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FundingCommitmentTargets {
+    pub commitment: HashOutTarget,
+    pub blinding: Private<[Target; BLINDING_NUM_TARGETS]>,
+    /// The funding account of the storage proof leaf, which is filled by the storage proof.
+    pub funding_account: HashOutTarget,
+}
+
+impl FundingCommitmentTargets {
+    /// Registers the commitment to `funding_account` as public inputs.
+    pub fn new(builder: &mut CircuitBuilder<F, D>, funding_account: HashOutTarget) -> Self {
+        Self {
+            commitment: builder.add_virtual_hash_public_input(),
+            blinding: Private::new(core::array::from_fn(|_| builder.add_virtual_target())),
+            funding_account,
+        }
+    }
+}
+
+impl CircuitFragment for FundingAccountOpening {
+    type Targets = FundingCommitmentTargets;
+
+    /// Asserts that `commitment = H(funding_account || blinding)`.
+    fn circuit(
+        &Self::Targets {
+            commitment,
+            ref blinding,
+            funding_account,
+        }: &Self::Targets,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        // Each blinding element holds 4 bytes, so an opening has a single byte representation.
+        range_check_u32(builder, &blinding[..]);
+
+        let mut preimage = funding_account.elements.to_vec();
+        preimage.extend(blinding.iter());
+        let computed = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        builder.connect_hashes(computed, commitment);
+    }
+
+    fn describe(_targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("funding_commitment")
+            .range_check("blinding", 32)
+            .poseidon("computed_commitment", &["funding_account", "blinding"])
+            .equal("computed_commitment", "commitment")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        pw.set_hash_target(targets.commitment, felts_to_hashout(&self.commitment()))?;
+        pw.set_target_arr(
+            &targets.blinding[..],
+            &injective_bytes_to_felts(self.blinding.as_slice()),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! Strict decoding of hex encoded inputs.
+//!
+//! Secrets, roots, nodes and proofs given as hex are accepted in a single canonical form: lowercase
+//! digits, optionally prefixed with `0x`, without whitespace and with an even number of digits.
+//! Anything else is rejected with a [`HexError`] rather than normalized, so the same value always
+//! has one encoding and typos such as a stray space or a dropped digit surface at the boundary.
+use alloc::vec::Vec;
+
+use zk_circuits_common::utils::BytesDigest;
+
+/// The optional prefix of hex strings.
+pub const HEX_PREFIX: &str = "0x";
+
+/// Why a hex string was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexError {
+    /// The digits have an odd length.
+    OddLength { len: usize },
+    /// A character is not a hex digit. The index is relative to the digits, after the prefix.
+    InvalidCharacter { index: usize, character: char },
+    /// A hex digit is uppercase.
+    UppercaseCharacter { index: usize, character: char },
+    /// The decoded value doesn't have the expected number of bytes.
+    InvalidLength { expected: usize, got: usize },
+}
+
+impl HexError {
+    /// A stable identifier of the error kind, listed in [`crate::errors::error_catalog`].
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::OddLength { .. } => "hex.odd_length",
+            Self::InvalidCharacter { .. } => "hex.invalid_character",
+            Self::UppercaseCharacter { .. } => "hex.uppercase_character",
+            Self::InvalidLength { .. } => "hex.invalid_length",
+        }
+    }
+}
+
+impl core::fmt::Display for HexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::OddLength { len } => write!(f, "hex string has an odd length: {}", len),
+            Self::InvalidCharacter { index, character } => {
+                write!(f, "invalid hex character {:?} at index {}", character, index)
+            }
+            Self::UppercaseCharacter { index, character } => write!(
+                f,
+                "uppercase hex character {:?} at index {}, hex must be lowercase",
+                character, index
+            ),
+            Self::InvalidLength { expected, got } => {
+                write!(f, "expected {} hex encoded bytes, got: {}", expected, got)
+            }
+        }
+    }
+}
+
+impl core::error::Error for HexError {}
+
+/// Decodes a lowercase hex string with an optional `0x` prefix.
+pub fn decode(hex: &str) -> Result<Vec<u8>, HexError> {
+    let digits = hex.strip_prefix(HEX_PREFIX).unwrap_or(hex);
+    if digits.len() % 2 != 0 {
+        return Err(HexError::OddLength { len: digits.len() });
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    let mut high = 0u8;
+    for (index, character) in digits.char_indices() {
+        let nibble = match character {
+            '0'..='9' => character as u8 - b'0',
+            'a'..='f' => character as u8 - b'a' + 10,
+            'A'..='F' => return Err(HexError::UppercaseCharacter { index, character }),
+            _ => return Err(HexError::InvalidCharacter { index, character }),
+        };
+        if index % 2 == 0 {
+            high = nibble;
+        } else {
+            bytes.push((high << 4) | nibble);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Decodes a hex string of exactly `N` bytes, see [`decode`].
+pub fn decode_array<const N: usize>(hex: &str) -> Result<[u8; N], HexError> {
+    let bytes = decode(hex)?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| HexError::InvalidLength {
+        expected: N,
+        got: bytes.len(),
+    })
+}
+
+/// Decodes a hex encoded 32 byte digest, such as a root hash, whose chunks must be canonical
+/// field elements.
+///
+/// # Errors
+///
+/// Returns an error if the hex is not canonical, or the bytes are not a valid [`BytesDigest`].
+pub fn decode_digest(hex: &str) -> anyhow::Result<BytesDigest> {
+    let bytes = decode_array::<32>(hex)?;
+    BytesDigest::try_from(bytes).map_err(|e| anyhow::anyhow!("invalid digest: {:?}", e))
+}
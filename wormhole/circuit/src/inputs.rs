@@ -1,14 +1,28 @@
 #![allow(clippy::new_without_default)]
+use crate::hex_util;
+use crate::nullifier::Nullifier;
+use crate::storage_proof::leaf::{find_transfer_count, LeafError};
 use crate::storage_proof::ProcessedStorageProof;
-use alloc::vec::Vec;
+#[cfg(feature = "redact")]
+use crate::types::Redacted;
+use crate::types::{AccountId, NullifierHash, RootHash, SecretBytes};
+use crate::unspendable_account::UnspendableAccount;
+use alloc::{vec, vec::Vec};
 use anyhow::{bail, Context};
+use core::ops::Deref;
 use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::Field;
+use plonky2::hash::{hash_types::HashOutTarget, poseidon::PoseidonHash};
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::config::Hasher;
 use plonky2::plonk::proof::ProofWithPublicInputs;
-use zk_circuits_common::circuit::{C, D, F};
-use zk_circuits_common::utils::{felts_to_u128, BytesDigest};
+use zk_circuits_common::circuit::{TransferProofJson, C, D, F};
+use zk_circuits_common::utils::{felts_to_u128, u128_to_felts, Digest};
 
-/// The total size of the public inputs field element vector.
-pub const PUBLIC_INPUTS_FELTS_LEN: usize = 14;
+/// The number of public inputs of the wormhole circuit without optional features. The public
+/// inputs of optional features start at this index.
+pub const PUBLIC_INPUTS_FELTS_LEN: usize = 16;
 pub const NULLIFIER_START_INDEX: usize = 0;
 pub const NULLIFIER_END_INDEX: usize = 4;
 pub const ROOT_HASH_START_INDEX: usize = 4;
@@ -17,6 +31,68 @@ pub const FUNDING_AMOUNT_START_INDEX: usize = 8;
 pub const FUNDING_AMOUNT_END_INDEX: usize = 12;
 pub const EXIT_ACCOUNT_START_INDEX: usize = 12;
 pub const EXIT_ACCOUNT_END_INDEX: usize = 16;
+/// The hash of the [`crate::types::ProofContext`], only present in proofs of circuits built with
+/// [`crate::circuit::circuit_logic::WormholeCircuit::with_context`].
+pub const CONTEXT_START_INDEX: usize = 16;
+pub const CONTEXT_END_INDEX: usize = 20;
+/// The [`crate::types::ChainId`] the deposit was claimed for, only present in proofs of circuits
+/// built with [`crate::circuit::circuit_logic::WormholeCircuit::with_destination_chain`].
+pub const DESTINATION_CHAIN_ID_START_INDEX: usize = 16;
+pub const DESTINATION_CHAIN_ID_END_INDEX: usize = 18;
+/// The [`crate::relayer::RelayerFee`] paid out of the funding amount, only present in proofs of
+/// circuits built with [`crate::circuit::circuit_logic::WormholeCircuit::with_relayer_fee`].
+pub const RELAYER_ACCOUNT_START_INDEX: usize = 16;
+pub const RELAYER_ACCOUNT_END_INDEX: usize = 20;
+pub const RELAYER_FEE_START_INDEX: usize = 20;
+pub const RELAYER_FEE_END_INDEX: usize = 24;
+pub const EXIT_AMOUNT_START_INDEX: usize = 24;
+pub const EXIT_AMOUNT_END_INDEX: usize = 28;
+/// The [`crate::exit_format::ExitAccountFormat`] flag, only present in proofs of circuits built
+/// with [`crate::circuit::circuit_logic::WormholeCircuit::with_exit_account_format`].
+pub const EXIT_ACCOUNT_FORMAT_INDEX: usize = 16;
+pub const EXIT_ACCOUNT_FORMAT_END_INDEX: usize = 17;
+/// The root of the [`crate::accumulator::NullifierAccumulator`] the nullifier is not in, only
+/// present in proofs of circuits built with
+/// [`crate::circuit::circuit_logic::WormholeCircuit::with_spent_nullifier_accumulator`].
+pub const SPENT_NULLIFIERS_ROOT_START_INDEX: usize = 16;
+pub const SPENT_NULLIFIERS_ROOT_END_INDEX: usize = 20;
+/// The commitment to the funding account of the claim, only present in proofs of circuits built
+/// with [`crate::circuit::circuit_logic::WormholeCircuit::with_funding_commitment`].
+pub const FUNDING_COMMITMENT_START_INDEX: usize = 16;
+pub const FUNDING_COMMITMENT_END_INDEX: usize = 20;
+/// The hash of the [`crate::intent::ClaimIntent`] registered for the claim, only present in proofs
+/// of circuits built with [`crate::circuit::circuit_logic::WormholeCircuit::with_intent_hash`].
+pub const INTENT_HASH_START_INDEX: usize = 16;
+pub const INTENT_HASH_END_INDEX: usize = 20;
+/// Whether the nullifier is a [`crate::nullifier::NullifierVersion::V2`] nullifier, as derived
+/// from the transfer count of the deposit. Only present in proofs of circuits built with
+/// [`crate::circuit::circuit_logic::WormholeCircuit::with_nullifier_migration`].
+pub const NULLIFIER_IS_V2_INDEX: usize = 16;
+pub const NULLIFIER_IS_V2_END_INDEX: usize = 17;
+/// The [`PublicCircuitInputs::digest`], the only public input of proofs of circuits built with
+/// [`crate::circuit::circuit_logic::WormholeCircuit::with_public_inputs_digest`].
+pub const PUBLIC_INPUTS_DIGEST_START_INDEX: usize = 0;
+pub const PUBLIC_INPUTS_DIGEST_END_INDEX: usize = 4;
+
+// The fields are contiguous and fill the public inputs, and every optional feature starts right
+// after them, so a change to one index can't silently overlap or shift another.
+const _: () = {
+    assert!(NULLIFIER_START_INDEX == 0);
+    assert!(ROOT_HASH_START_INDEX == NULLIFIER_END_INDEX);
+    assert!(FUNDING_AMOUNT_START_INDEX == ROOT_HASH_END_INDEX);
+    assert!(EXIT_ACCOUNT_START_INDEX == FUNDING_AMOUNT_END_INDEX);
+    assert!(EXIT_ACCOUNT_END_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(CONTEXT_START_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(DESTINATION_CHAIN_ID_START_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(RELAYER_ACCOUNT_START_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(RELAYER_FEE_START_INDEX == RELAYER_ACCOUNT_END_INDEX);
+    assert!(EXIT_AMOUNT_START_INDEX == RELAYER_FEE_END_INDEX);
+    assert!(EXIT_ACCOUNT_FORMAT_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(SPENT_NULLIFIERS_ROOT_START_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(FUNDING_COMMITMENT_START_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(INTENT_HASH_START_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+    assert!(NULLIFIER_IS_V2_INDEX == PUBLIC_INPUTS_FELTS_LEN);
+};
 
 /// Inputs required to commit to the wormhole circuit.
 #[derive(Debug, Clone)]
@@ -25,36 +101,160 @@ pub struct CircuitInputs {
     pub private: PrivateCircuitInputs,
 }
 
+impl CircuitInputs {
+    /// Assembles the inputs to claim the deposit of `funding_amount` from `funding_account` into
+    /// the unspendable account of `secret` to `exit_account`, with the storage proof a node
+    /// exported for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `proof` doesn't follow the [`TransferProofJson`] schema, or its storage
+    /// proof doesn't fit the circuit.
+    pub fn from_transfer_proof_json(
+        proof: &TransferProofJson,
+        secret: SecretBytes,
+        funding_account: AccountId,
+        funding_amount: u128,
+        exit_account: AccountId,
+    ) -> anyhow::Result<Self> {
+        proof.validate().context("invalid transfer proof")?;
+        let root_hash = RootHash::from(hex_util::decode_digest(&proof.state_root)?);
+        let nodes = proof
+            .storage_proof
+            .iter()
+            .map(|node| hex_util::decode(node))
+            .collect::<Result<Vec<_>, _>>()?;
+        let storage_proof = ProcessedStorageProof::new(nodes, proof.indices.clone())?;
+
+        let transfer_count = proof.transfer_count;
+        let unspendable_account = UnspendableAccount::from_secret(&secret).account_id;
+        Ok(Self {
+            public: PublicCircuitInputs {
+                funding_amount,
+                nullifier: Nullifier::from_preimage(secret.as_slice(), transfer_count)
+                    .hash
+                    .into(),
+                root_hash,
+                exit_account,
+            },
+            private: PrivateCircuitInputs {
+                secret,
+                storage_proof,
+                transfer_count,
+                funding_account,
+                unspendable_account: AccountId::from(unspendable_account),
+            },
+        })
+    }
+
+    /// Replaces a stale transfer count with the one within `window` that the leaf node of the
+    /// storage proof holds, see [`find_transfer_count`]. The nullifier is derived again with the
+    /// version of the current one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LeafError::Mismatch`] if no transfer count within `window` matches the leaf.
+    pub fn with_corrected_transfer_count(mut self, window: u64) -> anyhow::Result<Self> {
+        let given = self.private.transfer_count;
+        let Some(found) = find_transfer_count(&self, window)? else {
+            return Err(LeafError::Mismatch {
+                transfer_count: given,
+                window,
+            }
+            .into());
+        };
+        if found != given {
+            let secret = self.private.secret.as_slice();
+            let version = Nullifier::new(self.public.nullifier, secret, given).version;
+            let nullifier = Nullifier::from_preimage_versioned(secret, found, version);
+            self.public.nullifier = nullifier.hash.into();
+            self.private.transfer_count = found;
+        }
+
+        Ok(self)
+    }
+}
+
 /// All of the public inputs required for the circuit.
+///
+/// With the `scale` feature, the inputs are SCALE encoded in field order, with the digests as
+/// their 32 raw bytes.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "scale",
+    derive(parity_scale_codec::Encode, parity_scale_codec::Decode)
+)]
 pub struct PublicCircuitInputs {
     /// Amount to be withdrawn.
     pub funding_amount: u128,
     /// The nullifier.
-    pub nullifier: BytesDigest,
+    pub nullifier: NullifierHash,
     /// The root hash of the storage trie.
-    pub root_hash: BytesDigest,
+    pub root_hash: RootHash,
     /// The address of the account to pay out to.
-    pub exit_account: BytesDigest,
+    pub exit_account: AccountId,
 }
 
 /// All of the private inputs required for the circuit.
-#[derive(Debug, Clone)]
+///
+/// With the `redact` feature, the [`core::fmt::Debug`] output only shows the number of storage
+/// proof nodes. Any other field would link the deposit to the withdrawal in logs.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "redact"), derive(Debug))]
 pub struct PrivateCircuitInputs {
     /// Raw bytes of the secret of the nullifier and the unspendable account
-    pub secret: [u8; 32],
+    pub secret: SecretBytes,
     /// A sequence of key-value nodes representing the storage proof.
     ///
     /// Each element is a tuple where the items are the left and right splits of a proof node split
     /// in half at the expected childs hash index.
     pub storage_proof: ProcessedStorageProof,
     pub transfer_count: u64,
-    pub funding_account: BytesDigest,
+    pub funding_account: AccountId,
     /// The unspendable account hash.
-    pub unspendable_account: BytesDigest,
+    pub unspendable_account: AccountId,
+}
+
+#[cfg(feature = "redact")]
+impl core::fmt::Debug for PrivateCircuitInputs {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PrivateCircuitInputs")
+            .field("secret", &self.secret)
+            .field(
+                "storage_proof",
+                &Redacted::Values(self.storage_proof.proof.len(), "nodes"),
+            )
+            .field("transfer_count", &Redacted::Value)
+            .field("funding_account", &Redacted::Value)
+            .field("unspendable_account", &Redacted::Value)
+            .finish()
+    }
 }
 
 impl PublicCircuitInputs {
+    /// The canonical public inputs of a padding leaf of an aggregated proof: an all-zero nullifier
+    /// and exit account, and zero for every other field.
+    ///
+    /// The aggregation circuit registers all-zero public inputs for the leaves a tree is padded
+    /// with, which [`Self::try_from_leaf`] decodes to these inputs, so consumers tell padding apart
+    /// from claims with [`Self::is_padding`].
+    pub fn padding() -> Self {
+        Self {
+            funding_amount: 0,
+            nullifier: NullifierHash::default(),
+            root_hash: RootHash::default(),
+            exit_account: AccountId::default(),
+        }
+    }
+
+    /// Returns `true` if these are the inputs of a padding leaf, see [`Self::padding`].
+    ///
+    /// Only the nullifier and exit account are checked. The nullifier of a claim is a Poseidon
+    /// hash, so it is all-zero together with the exit account for padding only.
+    pub fn is_padding(&self) -> bool {
+        self.nullifier == NullifierHash::default() && self.exit_account == AccountId::default()
+    }
+
     /// Parse a vector of `PublicCircuitInputs` from a *root aggregated* proof.
     /// `leaf_pi_len` should match the leaf circuit's public input length.
     /// `num_leaves` should match `TreeAggregationConfig.num_leaf_proofs`.
@@ -84,27 +284,54 @@ impl PublicCircuitInputs {
 
         leaf_public_inputs
             .chunks(leaf_pi_len)
-            .map(Self::try_from_slice)
+            .map(Self::try_from_leaf)
             .collect()
     }
 
+    /// Decodes the public inputs of one leaf of an aggregated proof. Padding leaves, whose public
+    /// inputs are all zero whatever the layout of the leaf circuit, decode to [`Self::padding`].
+    pub fn try_from_leaf(leaf: &[F]) -> anyhow::Result<Self> {
+        if leaf.iter().all(|felt| felt.is_zero()) {
+            return Ok(Self::padding());
+        }
+        Self::try_from_slice(leaf)
+    }
+
+    /// The public inputs as field elements, in the order the wormhole circuit registers them.
+    pub fn to_field_elements(&self) -> Vec<F> {
+        let mut felts = Vec::with_capacity(EXIT_ACCOUNT_END_INDEX);
+        felts.extend(self.nullifier.to_felts());
+        felts.extend(self.root_hash.to_felts());
+        felts.extend(u128_to_felts(self.funding_amount));
+        felts.extend(self.exit_account.to_felts());
+        felts
+    }
+
+    /// The hash `H(nullifier || root_hash || funding_amount || exit_account)` of the public
+    /// inputs, in the order of [`Self::to_field_elements`].
+    ///
+    /// Aggregators can expose this digest instead of the 16 felts of each leaf, and verifiers
+    /// holding the inputs recompute it. [`public_inputs_digest_circuit`] computes it in-circuit.
+    pub fn digest(&self) -> Digest {
+        PoseidonHash::hash_no_pad(&self.to_field_elements()).elements
+    }
+
     pub fn try_from_slice(pis: &[GoldilocksField]) -> anyhow::Result<Self> {
-        const LEAF_PI_LEN: usize = 16;
         // Public inputs are ordered as follows:
         // Nullifier.hash: 4 felts
         // StorageProof.root_hash: 4 felts
         // StorageProof.funding_amount: 4 felts
         // ExitAccount.address: 4 felts
-        if pis.len() != LEAF_PI_LEN {
+        if pis.len() != PUBLIC_INPUTS_FELTS_LEN {
             bail!(
                 "public inputs should contain: {} field elements, got: {}",
-                LEAF_PI_LEN,
+                PUBLIC_INPUTS_FELTS_LEN,
                 pis.len()
             )
         }
-        let nullifier = BytesDigest::try_from(&pis[NULLIFIER_START_INDEX..NULLIFIER_END_INDEX])
+        let nullifier = NullifierHash::try_from(&pis[NULLIFIER_START_INDEX..NULLIFIER_END_INDEX])
             .context("failed to deserialize nullifier hash")?;
-        let root_hash = BytesDigest::try_from(&pis[ROOT_HASH_START_INDEX..ROOT_HASH_END_INDEX])
+        let root_hash = RootHash::try_from(&pis[ROOT_HASH_START_INDEX..ROOT_HASH_END_INDEX])
             .context("failed to deserialize root hash")?;
         let funding_amount = felts_to_u128(
             <[F; 4]>::try_from(&pis[FUNDING_AMOUNT_START_INDEX..FUNDING_AMOUNT_END_INDEX])
@@ -112,7 +339,7 @@ impl PublicCircuitInputs {
         )
         .unwrap();
         let exit_account =
-            BytesDigest::try_from(&pis[EXIT_ACCOUNT_START_INDEX..EXIT_ACCOUNT_END_INDEX])
+            AccountId::try_from(&pis[EXIT_ACCOUNT_START_INDEX..EXIT_ACCOUNT_END_INDEX])
                 .context("failed to deserialize exit account")?;
 
         Ok(PublicCircuitInputs {
@@ -132,3 +359,116 @@ impl TryFrom<&ProofWithPublicInputs<F, C, D>> for PublicCircuitInputs {
             .context("failed to deserialize public inputs from proof")
     }
 }
+
+/// Computes [`PublicCircuitInputs::digest`] in-circuit from the targets of the public inputs,
+/// in the order of [`PublicCircuitInputs::to_field_elements`], e.g. the first
+/// [`EXIT_ACCOUNT_END_INDEX`] public inputs of a leaf proof.
+///
+/// # Panics
+///
+/// Panics if `public_inputs` doesn't hold exactly [`EXIT_ACCOUNT_END_INDEX`] targets.
+pub fn public_inputs_digest_circuit(
+    builder: &mut CircuitBuilder<F, D>,
+    public_inputs: &[Target],
+) -> HashOutTarget {
+    assert_eq!(
+        public_inputs.len(),
+        EXIT_ACCOUNT_END_INDEX,
+        "the public inputs digest hashes {} targets",
+        EXIT_ACCOUNT_END_INDEX
+    );
+    builder.hash_n_to_hash_no_pad::<PoseidonHash>(public_inputs.to_vec())
+}
+
+/// Targets of private inputs, or of values derived from them, which must never become public
+/// inputs.
+///
+/// Circuits use private targets freely in constraints, which is what the [`Deref`] is for, but
+/// only [`Public`] targets can be registered as public inputs with [`register_public_input`]. A
+/// private target that is meant to be public, such as the transfer count a delegating client
+/// reveals, has to be passed through [`Private::declassify`], so every such place is explicit.
+///
+/// This crate's `clippy.toml` disallows registering public inputs with the builder directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Private<T>(T);
+
+/// Targets that may be registered as public inputs, see [`Private`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Public<T>(T);
+
+impl<T> Private<T> {
+    pub fn new(targets: T) -> Self {
+        Self(targets)
+    }
+
+    /// Marks the targets as public, for private inputs the circuit intentionally reveals.
+    pub fn declassify(self) -> Public<T> {
+        Public(self.0)
+    }
+}
+
+impl<T> Public<T> {
+    pub fn new(targets: T) -> Self {
+        Self(targets)
+    }
+}
+
+impl<T> Deref for Private<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for Public<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Values made of targets, which can be registered as public inputs.
+pub trait Targets {
+    fn targets(&self) -> Vec<Target>;
+}
+
+impl Targets for Target {
+    fn targets(&self) -> Vec<Target> {
+        vec![*self]
+    }
+}
+
+impl Targets for BoolTarget {
+    fn targets(&self) -> Vec<Target> {
+        vec![self.target]
+    }
+}
+
+impl Targets for HashOutTarget {
+    fn targets(&self) -> Vec<Target> {
+        self.elements.to_vec()
+    }
+}
+
+impl<const N: usize> Targets for [Target; N] {
+    fn targets(&self) -> Vec<Target> {
+        self.to_vec()
+    }
+}
+
+impl Targets for Vec<Target> {
+    fn targets(&self) -> Vec<Target> {
+        self.clone()
+    }
+}
+
+/// Registers `input` as public inputs, in order. This is the only way the circuits of this crate
+/// register targets they already have as public inputs, see [`Private`].
+#[allow(clippy::disallowed_methods)]
+pub fn register_public_input<T: Targets>(builder: &mut CircuitBuilder<F, D>, input: &Public<T>) {
+    builder.register_public_inputs(&input.targets());
+}
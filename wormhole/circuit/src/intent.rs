@@ -0,0 +1,174 @@
+//! Claim intents, for registering a claim on chain before its proof is submitted.
+//!
+//! A claim proof sits in the mempool before it is included, and anyone watching it learns the
+//! nullifier it spends. Circuits built with
+//! [`crate::circuit::circuit_logic::WormholeCircuit::with_intent_hash`] additionally expose the
+//! hash of a [`ClaimIntent`]:
+//!
+//! ```text
+//! intent_hash = H(nullifier || exit_account || min_amount)
+//! ```
+//!
+//! The claimant first registers the intent hash on chain, which reveals neither the nullifier nor
+//! the exit account, and submits the proof once the registration is included. The pallet only
+//! accepts a proof whose intent hash was registered before it, so a front-runner copying the
+//! proof from the mempool is too late to register its own intent. The circuit asserts that the
+//! funding amount is at least `min_amount`, so the registered intent also bounds the claimed
+//! amount from below.
+//!
+//! ## Public Inputs
+//!
+//! After the exit account:
+//! - `intent_hash`: the hash of the claim intent.
+use anyhow::{bail, ensure};
+use plonky2::{
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::{assert_u32_limbs_at_least, range_check_u32};
+use zk_circuits_common::utils::{felts_to_hashout, u128_to_felts, Digest, FELTS_PER_U128};
+
+use crate::inputs::{Private, INTENT_HASH_END_INDEX, INTENT_HASH_START_INDEX};
+use crate::types::{AccountId, NullifierHash};
+
+/// The claim a claimant registers on chain before submitting its proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimIntent {
+    pub nullifier: NullifierHash,
+    pub exit_account: AccountId,
+    /// The smallest funding amount the claimant accepts.
+    pub min_amount: u128,
+}
+
+impl ClaimIntent {
+    pub fn new(nullifier: NullifierHash, exit_account: AccountId, min_amount: u128) -> Self {
+        Self {
+            nullifier,
+            exit_account,
+            min_amount,
+        }
+    }
+
+    /// The hash `H(nullifier || exit_account || min_amount)` registered on chain and exposed by
+    /// the circuit.
+    pub fn hash(&self) -> Digest {
+        let mut preimage = self.nullifier.to_felts().to_vec();
+        preimage.extend(self.exit_account.to_felts());
+        preimage.extend(u128_to_felts(self.min_amount));
+        PoseidonHash::hash_no_pad(&preimage).elements
+    }
+
+    /// Checks that a claim of `funding_amount` satisfies this intent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `funding_amount` is below the minimum amount.
+    pub fn check_funding_amount(&self, funding_amount: u128) -> anyhow::Result<()> {
+        ensure!(
+            funding_amount >= self.min_amount,
+            "funding amount {} is below the minimum amount {} of the claim intent",
+            funding_amount,
+            self.min_amount
+        );
+        Ok(())
+    }
+
+    /// Parses the intent hash from the public inputs of a proof of a circuit built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_intent_hash`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no intent hash.
+    pub fn hash_from_public_inputs(pis: &[F]) -> anyhow::Result<Digest> {
+        if pis.len() != INTENT_HASH_END_INDEX {
+            bail!("proof does not commit to a claim intent");
+        }
+        Ok(core::array::from_fn(|i| pis[INTENT_HASH_START_INDEX + i]))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClaimIntentTargets {
+    pub intent_hash: HashOutTarget,
+    pub min_amount: Private<[Target; FELTS_PER_U128]>,
+    /// The nullifier hash, which is filled by the nullifier.
+    pub nullifier: HashOutTarget,
+    /// The exit account, which is filled by the exit account.
+    pub exit_account: HashOutTarget,
+    /// The funding amount of the storage proof leaf, which is filled by the storage proof.
+    pub funding_amount: [Target; FELTS_PER_U128],
+}
+
+impl ClaimIntentTargets {
+    /// Registers the hash of the intent to claim `funding_amount` with `nullifier` to
+    /// `exit_account` as public inputs.
+    pub fn new(
+        builder: &mut CircuitBuilder<F, D>,
+        nullifier: HashOutTarget,
+        exit_account: HashOutTarget,
+        funding_amount: [Target; FELTS_PER_U128],
+    ) -> Self {
+        Self {
+            intent_hash: builder.add_virtual_hash_public_input(),
+            min_amount: Private::new(core::array::from_fn(|_| builder.add_virtual_target())),
+            nullifier,
+            exit_account,
+            funding_amount,
+        }
+    }
+}
+
+impl CircuitFragment for ClaimIntent {
+    type Targets = ClaimIntentTargets;
+
+    /// Asserts that `intent_hash = H(nullifier || exit_account || min_amount)` and that the
+    /// funding amount is at least `min_amount`.
+    fn circuit(
+        &Self::Targets {
+            intent_hash,
+            ref min_amount,
+            nullifier,
+            exit_account,
+            ref funding_amount,
+        }: &Self::Targets,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        // The funding amount is range checked by the storage proof.
+        range_check_u32(builder, &min_amount[..]);
+
+        let mut preimage = nullifier.elements.to_vec();
+        preimage.extend(exit_account.elements);
+        preimage.extend(min_amount.iter());
+        let computed = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        builder.connect_hashes(computed, intent_hash);
+
+        assert_u32_limbs_at_least(builder, funding_amount, &min_amount[..]);
+    }
+
+    fn describe(_targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("claim_intent")
+            .range_check("min_amount", 32)
+            .poseidon(
+                "computed_intent_hash",
+                &["nullifier", "exit_account", "min_amount"],
+            )
+            .equal("computed_intent_hash", "intent_hash")
+            .assert("funding_amount >= min_amount")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        pw.set_hash_target(targets.intent_hash, felts_to_hashout(&self.hash()))?;
+        pw.set_target_arr(&targets.min_amount[..], &u128_to_felts(self.min_amount))?;
+        Ok(())
+    }
+}
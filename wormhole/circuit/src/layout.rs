@@ -0,0 +1,296 @@
+//! The canonical layout of the targets of the wormhole circuit.
+//!
+//! Targets are allocated in the order the fragments of a circuit are constructed, and the public
+//! inputs are registered in that order too. Artifacts written by one version of this crate only
+//! line up with the prover and verifier of another if both construct the targets the same way.
+//! [`TargetsLayout`] lists the targets of every fragment of a [`CircuitTargets`] in a fixed
+//! order, and the circuit builder serializes it to `targets.json` next to `common.bin`.
+//! Verifiers compare the layout of a loaded artifact with the layout of the circuit they are
+//! compiled against using [`TargetsLayout::diff`].
+//!
+//! The layout is versioned: any change to its format must bump [`TARGETS_LAYOUT_VERSION`].
+//!
+//! [`CircuitTargets`]: crate::circuit::circuit_logic::CircuitTargets
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use plonky2::iop::target::Target;
+use serde::{Deserialize, Serialize};
+
+pub const TARGETS_LAYOUT_VERSION: u32 = 1;
+
+/// The file name the circuit builder writes the layout to.
+pub const TARGETS_LAYOUT_FILE_NAME: &str = "targets.json";
+
+/// A target, by the wire it is routed to or the index of the virtual target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetIndex {
+    Wire { row: usize, column: usize },
+    Virtual(usize),
+}
+
+impl From<Target> for TargetIndex {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::Wire(wire) => Self::Wire {
+                row: wire.row,
+                column: wire.column,
+            },
+            Target::VirtualTarget { index } => Self::Virtual(index),
+        }
+    }
+}
+
+/// The targets of one field of a fragment's targets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldLayout {
+    pub name: String,
+    pub targets: Vec<TargetIndex>,
+}
+
+/// The targets of one fragment, field by field in the order of its targets struct.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FragmentLayout {
+    pub name: String,
+    pub fields: Vec<FieldLayout>,
+}
+
+impl FragmentLayout {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds the field `name` holding `targets`.
+    pub fn field(mut self, name: &str, targets: impl IntoIterator<Item = Target>) -> Self {
+        self.fields.push(FieldLayout {
+            name: name.to_string(),
+            targets: targets.into_iter().map(TargetIndex::from).collect(),
+        });
+        self
+    }
+
+    /// Adds the field `name` if the fragment has it.
+    pub fn optional_field<I>(self, name: &str, targets: Option<I>) -> Self
+    where
+        I: IntoIterator<Item = Target>,
+    {
+        match targets {
+            Some(targets) => self.field(name, targets),
+            None => self,
+        }
+    }
+}
+
+/// The targets of every fragment of a circuit, in the order the fragments are constructed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetsLayout {
+    pub version: u32,
+    pub fragments: Vec<FragmentLayout>,
+}
+
+impl TargetsLayout {
+    pub fn new(fragments: Vec<FragmentLayout>) -> Self {
+        Self {
+            version: TARGETS_LAYOUT_VERSION,
+            fragments,
+        }
+    }
+
+    /// Summarizes how `other` differs from this layout, one line per fragment or field. Empty if
+    /// the layouts are the same.
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        let mut diff = Vec::new();
+        if self.version != other.version {
+            diff.push(format!(
+                "layout version: {} -> {}",
+                self.version, other.version
+            ));
+            return diff;
+        }
+
+        let names = |layout: &Self| -> Vec<String> {
+            layout
+                .fragments
+                .iter()
+                .map(|fragment| fragment.name.clone())
+                .collect()
+        };
+        for fragment in &self.fragments {
+            match other.fragment(&fragment.name) {
+                Some(other_fragment) => fragment.diff_into(other_fragment, &mut diff),
+                None => diff.push(format!("removed fragment: {}", fragment.name)),
+            }
+        }
+        for fragment in &other.fragments {
+            if self.fragment(&fragment.name).is_none() {
+                diff.push(format!("added fragment: {}", fragment.name));
+            }
+        }
+        if diff.is_empty() && names(self) != names(other) {
+            diff.push(format!(
+                "fragment order: {} -> {}",
+                names(self).join(", "),
+                names(other).join(", ")
+            ));
+        }
+
+        diff
+    }
+
+    /// The fragment named `name`, if the circuit has it.
+    pub fn fragment(&self, name: &str) -> Option<&FragmentLayout> {
+        self.fragments.iter().find(|fragment| fragment.name == name)
+    }
+}
+
+impl FragmentLayout {
+    fn diff_into(&self, other: &Self, diff: &mut Vec<String>) {
+        for field in &self.fields {
+            let Some(other_field) = other.fields.iter().find(|f| f.name == field.name) else {
+                diff.push(format!("removed field: {}.{}", self.name, field.name));
+                continue;
+            };
+            if field.targets.len() != other_field.targets.len() {
+                diff.push(format!(
+                    "{}.{}: {} -> {} targets",
+                    self.name,
+                    field.name,
+                    field.targets.len(),
+                    other_field.targets.len()
+                ));
+            } else if field.targets != other_field.targets {
+                diff.push(format!("{}.{}: targets moved", self.name, field.name));
+            }
+        }
+        for field in &other.fields {
+            if !self.fields.iter().any(|f| f.name == field.name) {
+                diff.push(format!("added field: {}.{}", self.name, field.name));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod circuit_targets {
+    use alloc::vec;
+    use plonky2::hash::hash_types::HashOutTarget;
+    use plonky2::iop::target::Target;
+
+    use super::{FragmentLayout, TargetsLayout};
+    use crate::circuit::circuit_logic::CircuitTargets;
+
+    fn hash(target: HashOutTarget) -> [Target; 4] {
+        target.elements
+    }
+
+    impl CircuitTargets {
+        /// The layout of these targets, see [`crate::layout`]. Optional fragments are only
+        /// listed if the circuit has them.
+        pub fn layout(&self) -> TargetsLayout {
+            let nullifier = &self.nullifier;
+            let unspendable_account = &self.unspendable_account;
+            let storage_proof = &self.storage_proof;
+            let leaf = &storage_proof.leaf_inputs;
+
+            let mut fragments = vec![
+                FragmentLayout::new("nullifier")
+                    .field("hash", hash(nullifier.hash))
+                    .field("secret", nullifier.secret.iter().copied())
+                    .field("transfer_count", *nullifier.transfer_count)
+                    .optional_field("is_v2", nullifier.is_v2.map(|is_v2| [is_v2.target]))
+                    .optional_field("destination_chain_id", nullifier.destination_chain_id),
+                FragmentLayout::new("unspendable_account")
+                    .field("account_id", hash(unspendable_account.account_id))
+                    .field("secret", *unspendable_account.secret)
+                    .optional_field(
+                        "destination_chain_id",
+                        unspendable_account.destination_chain_id,
+                    ),
+                FragmentLayout::new("storage_proof")
+                    .field("root_hash", hash(storage_proof.root_hash))
+                    .field("proof_len", [storage_proof.proof_len])
+                    .field(
+                        "proof_data",
+                        storage_proof.proof_data.iter().flatten().copied(),
+                    )
+                    .field("indices", storage_proof.indices.iter().copied())
+                    .field("leaf_inputs.transfer_count", leaf.transfer_count)
+                    .field("leaf_inputs.funding_account", hash(leaf.funding_account))
+                    .field("leaf_inputs.to_account", hash(leaf.to_account))
+                    .field("leaf_inputs.funding_amount", leaf.funding_amount),
+                FragmentLayout::new("exit_account")
+                    .field("address", hash(self.exit_account.address)),
+            ];
+            if let Some(context) = self.context {
+                fragments.push(FragmentLayout::new("context").field("hash", hash(context)));
+            }
+            if let Some(chain_id) = self.destination_chain_id {
+                fragments
+                    .push(FragmentLayout::new("destination_chain_id").field("chain_id", chain_id));
+            }
+            if let Some(relayer_fee) = &self.relayer_fee {
+                fragments.push(
+                    FragmentLayout::new("relayer_fee")
+                        .field("relayer_account", hash(relayer_fee.relayer_account))
+                        .field("fee", relayer_fee.fee)
+                        .field("exit_amount", relayer_fee.exit_amount)
+                        .field("funding_amount", relayer_fee.funding_amount),
+                );
+            }
+            if let Some(digest) = self.public_inputs_digest {
+                fragments.push(
+                    FragmentLayout::new("public_inputs_digest").field("digest", hash(digest)),
+                );
+            }
+            if let Some(exit_account_format) = &self.exit_account_format {
+                fragments.push(
+                    FragmentLayout::new("exit_account_format")
+                        .field("is_evm", [exit_account_format.is_evm.target])
+                        .field("exit_account", hash(exit_account_format.exit_account)),
+                );
+            }
+            if let Some(spent_nullifiers) = &self.spent_nullifiers {
+                let low_leaf = &spent_nullifiers.low_leaf;
+                fragments.push(
+                    FragmentLayout::new("spent_nullifiers")
+                        .field("root", hash(spent_nullifiers.root))
+                        .field("nullifier", hash(spent_nullifiers.nullifier))
+                        .field("low_leaf.value", hash(low_leaf.value))
+                        .field("low_leaf.next_value", hash(low_leaf.next_value))
+                        .field("low_leaf.next_index", [low_leaf.next_index])
+                        .field("low_leaf_index", [spent_nullifiers.low_leaf_index])
+                        .field(
+                            "siblings",
+                            spent_nullifiers.siblings.iter().flat_map(|s| s.elements),
+                        ),
+                );
+            }
+            if let Some(funding_commitment) = &self.funding_commitment {
+                fragments.push(
+                    FragmentLayout::new("funding_commitment")
+                        .field("commitment", hash(funding_commitment.commitment))
+                        .field("blinding", *funding_commitment.blinding)
+                        .field("funding_account", hash(funding_commitment.funding_account)),
+                );
+            }
+            if let Some(intent) = &self.intent {
+                fragments.push(
+                    FragmentLayout::new("claim_intent")
+                        .field("intent_hash", hash(intent.intent_hash))
+                        .field("min_amount", *intent.min_amount)
+                        .field("nullifier", hash(intent.nullifier))
+                        .field("exit_account", hash(intent.exit_account))
+                        .field("funding_amount", intent.funding_amount),
+                );
+            }
+
+            TargetsLayout::new(fragments)
+        }
+    }
+}
@@ -2,10 +2,33 @@
 
 extern crate alloc;
 
+pub mod accumulator;
 pub mod circuit;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod codec;
+pub mod delegation;
+pub mod deposit_vote;
+pub mod disclosure;
+pub mod encoding;
+pub mod envelope;
+pub mod errors;
+pub mod evm;
+pub mod exit_format;
+pub mod funding_commitment;
+pub mod hex_util;
 pub mod inputs;
+pub mod intent;
+pub mod layout;
 pub mod nullifier;
+#[cfg(feature = "client")]
+pub mod recover;
+pub mod relayer;
+pub mod reserve;
+pub mod statement;
 pub mod storage_proof;
 pub mod substrate_account;
+pub mod transfer;
+pub mod types;
 pub mod unspendable_account;
+pub mod vectors;
@@ -1,4 +1,4 @@
-use alloc::vec::Vec;
+use alloc::{format, vec, vec::Vec};
 use core::array;
 use core::mem::size_of;
 use zk_circuits_common::utils::digest_bytes_to_felts;
@@ -6,19 +6,26 @@ use zk_circuits_common::utils::digest_felts_to_bytes;
 
 use crate::codec::ByteCodec;
 use crate::codec::FieldElementCodec;
-use crate::inputs::CircuitInputs;
+use crate::inputs::{CircuitInputs, Private, NULLIFIER_IS_V2_END_INDEX, NULLIFIER_IS_V2_INDEX};
+#[cfg(feature = "redact")]
+use crate::types::Redacted;
+use crate::types::{ChainId, NullifierHash};
+use anyhow::{anyhow, bail, ensure};
 use plonky2::{
+    field::types::Field,
     hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
     iop::{
-        target::Target,
+        target::{BoolTarget, Target},
         witness::{PartialWitness, WitnessWrite},
     },
     plonk::{circuit_builder::CircuitBuilder, config::Hasher},
 };
-use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::circuit::{CircuitFragment, ShapeError, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::{range_check_u32, u32_limbs_less_than};
 use zk_circuits_common::utils::{
-    injective_bytes_to_felts, injective_felts_to_bytes, injective_string_to_felt, u64_to_felts,
-    BytesDigest, Digest,
+    felts_to_u64, injective_bytes_to_felts, injective_felts_to_bytes, injective_string_to_felt,
+    u64_to_felts, Digest, FELTS_PER_U64,
 };
 
 pub const NULLIFIER_SALT: &str = "~nullif~";
@@ -30,45 +37,182 @@ pub const PREIMAGE_NUM_TARGETS: usize =
     SECRET_NUM_TARGETS + NONCE_NUM_TARGETS + FUNDING_ACCOUNT_NUM_TARGETS;
 pub const NULLIFIER_SIZE_FELTS: usize = 4 + 4 + 1 + 4;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// The layout of the nullifier preimage.
+///
+/// Deposits are claimed with the nullifier derivation that was current when they were made, so
+/// changing the preimage requires a migration window in which the circuit accepts both versions.
+/// Circuits built with [`NullifierTargets::new_with_migration`] do, while
+/// [`NullifierTargets::new`] only accepts [`NullifierVersion::V1`].
+///
+/// The version of a deposit is not up to the prover. Transfer counts are globally increasing, so
+/// the migration circuit takes a cutover transfer count from which deposits use
+/// [`NullifierVersion::V2`], see [`Self::for_transfer_count`]. Otherwise the same deposit could be
+/// claimed once with each version, as the nullifiers differ.
+///
+/// The cutover only binds proofs of the migration circuit. The version 1 circuit still proves a
+/// version 1 nullifier for any deposit, including those past the cutover, so verifiers must stop
+/// accepting proofs of the version 1 circuit once they accept the migration circuit.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum NullifierVersion {
+    /// `H(H(salt || secret || transfer_count))`, used by all deposits made before versioning.
+    #[default]
+    V1,
+    /// `H(H(salt || version || secret || transfer_count))`, with the version as one field
+    /// element.
+    V2,
+}
+
+impl NullifierVersion {
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// The version of the nullifier of the deposit with `transfer_count`, in a migration to
+    /// [`Self::V2`] from the transfer count `v2_cutover` on.
+    pub fn for_transfer_count(transfer_count: u64, v2_cutover: u64) -> Self {
+        if transfer_count < v2_cutover {
+            Self::V1
+        } else {
+            Self::V2
+        }
+    }
+
+    /// Parses the version from the public inputs of a proof of a circuit built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_nullifier_migration`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no nullifier version, or its flag is not a bit.
+    pub fn try_from_public_inputs(pis: &[F]) -> anyhow::Result<Self> {
+        if pis.len() != NULLIFIER_IS_V2_END_INDEX {
+            bail!("proof does not expose its nullifier version");
+        }
+        let flag = pis[NULLIFIER_IS_V2_INDEX];
+        if flag == F::ZERO {
+            Ok(Self::V1)
+        } else if flag == F::ONE {
+            Ok(Self::V2)
+        } else {
+            bail!("invalid nullifier version flag: {}", flag)
+        }
+    }
+}
+
+fn nullifier_hash(
+    secret: &[F],
+    transfer_count: [F; TRANSFER_COUNT_NUM_TARGETS],
+    version: NullifierVersion,
+    chain_id: Option<ChainId>,
+) -> Digest {
+    let mut preimage = Vec::new();
+    preimage.extend(injective_string_to_felt(NULLIFIER_SALT));
+    if version != NullifierVersion::V1 {
+        preimage.push(F::from_canonical_u8(version.as_u8()));
+    }
+    if let Some(chain_id) = chain_id {
+        preimage.extend(chain_id.to_felts());
+    }
+    preimage.extend(secret);
+    preimage.extend(transfer_count);
+
+    let inner_hash = PoseidonHash::hash_no_pad(&preimage).elements;
+    PoseidonHash::hash_no_pad(&inner_hash).elements
+}
+
+/// Returns the version `hash` was derived with, assuming [`NullifierVersion::V1`] if it matches
+/// none of the newer ones.
+fn detect_version(
+    hash: Digest,
+    secret: &[F],
+    transfer_count: [F; TRANSFER_COUNT_NUM_TARGETS],
+) -> NullifierVersion {
+    if nullifier_hash(secret, transfer_count, NullifierVersion::V2, None) == hash {
+        NullifierVersion::V2
+    } else {
+        NullifierVersion::V1
+    }
+}
+
+/// A nullifier and its preimage.
+///
+/// With the `redact` feature, the secret and transfer count are left out of the
+/// [`core::fmt::Debug`] output.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(not(feature = "redact"), derive(Debug))]
 pub struct Nullifier {
     pub hash: Digest,
     pub secret: Vec<F>,
     transfer_count: [F; TRANSFER_COUNT_NUM_TARGETS],
+    pub version: NullifierVersion,
+}
+
+#[cfg(feature = "redact")]
+impl core::fmt::Debug for Nullifier {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Nullifier")
+            .field("hash", &self.hash)
+            .field("secret", &Redacted::Values(self.secret.len(), "felts"))
+            .field("transfer_count", &Redacted::Value)
+            .field("version", &self.version)
+            .finish()
+    }
 }
 
 impl Nullifier {
-    pub fn new(digest: BytesDigest, secret: &[u8], transfer_count: u64) -> Self {
-        let hash = digest_bytes_to_felts(digest);
+    /// Creates a nullifier from its public hash and preimage. The version is detected from the
+    /// hash.
+    pub fn new(nullifier: NullifierHash, secret: &[u8], transfer_count: u64) -> Self {
+        let hash = nullifier.to_felts();
         let secret = injective_bytes_to_felts(secret);
         let transfer_count = u64_to_felts(transfer_count);
+        let version = detect_version(hash, &secret, transfer_count);
 
         Self {
             hash,
             secret,
             transfer_count,
+            version,
         }
     }
 
     pub fn from_preimage(secret: &[u8], transfer_count: u64) -> Self {
-        let mut preimage = Vec::new();
+        Self::from_preimage_versioned(secret, transfer_count, NullifierVersion::V1)
+    }
 
-        let salt = injective_string_to_felt(NULLIFIER_SALT);
+    pub fn from_preimage_versioned(
+        secret: &[u8],
+        transfer_count: u64,
+        version: NullifierVersion,
+    ) -> Self {
         let secret = injective_bytes_to_felts(secret);
         let transfer_count = u64_to_felts(transfer_count);
+        let hash = nullifier_hash(&secret, transfer_count, version, None);
 
-        preimage.extend(salt);
-        preimage.extend(secret.clone());
-        preimage.extend(transfer_count);
+        Self {
+            hash,
+            secret,
+            transfer_count,
+            version,
+        }
+    }
 
-        let inner_hash = PoseidonHash::hash_no_pad(&preimage).elements;
-        let outer_hash = PoseidonHash::hash_no_pad(&inner_hash).elements;
-        let hash = Digest::from(outer_hash);
+    /// Creates a [`NullifierVersion::V1`] nullifier bound to `chain_id`, for circuits built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_destination_chain`]. The chain id
+    /// follows the salt in the preimage.
+    pub fn from_preimage_for_chain(secret: &[u8], transfer_count: u64, chain_id: ChainId) -> Self {
+        let secret = injective_bytes_to_felts(secret);
+        let transfer_count = u64_to_felts(transfer_count);
+        let version = NullifierVersion::V1;
+        let hash = nullifier_hash(&secret, transfer_count, version, Some(chain_id));
 
         Self {
             hash,
             secret,
             transfer_count,
+            version,
         }
     }
 }
@@ -124,11 +268,13 @@ impl ByteCodec for Nullifier {
             ));
         }
         let transfer_count: [F; TRANSFER_COUNT_NUM_TARGETS] = transfer_count.try_into().unwrap();
+        let version = detect_version(hash, &secret, transfer_count);
 
         Ok(Self {
             hash,
             secret,
             transfer_count,
+            version,
         })
     }
 }
@@ -171,11 +317,13 @@ impl FieldElementCodec for Nullifier {
         let transfer_count = elements[offset..offset + transfer_count_size]
             .try_into()
             .map_err(|_| anyhow::anyhow!("Failed to deserialize nullifier transfer_count"))?;
+        let version = detect_version(hash, &secret, transfer_count);
 
         Ok(Self {
             hash,
             secret,
             transfer_count,
+            version,
         })
     }
 }
@@ -184,7 +332,7 @@ impl From<&CircuitInputs> for Nullifier {
     fn from(inputs: &CircuitInputs) -> Self {
         Self::new(
             inputs.public.nullifier,
-            &inputs.private.secret,
+            inputs.private.secret.as_slice(),
             inputs.private.transfer_count,
         )
     }
@@ -193,30 +341,72 @@ impl From<&CircuitInputs> for Nullifier {
 #[derive(Debug, Clone)]
 pub struct NullifierTargets {
     pub hash: HashOutTarget,
-    pub secret: Vec<Target>,
-    pub transfer_count: [Target; TRANSFER_COUNT_NUM_TARGETS],
+    pub secret: Private<Vec<Target>>,
+    pub transfer_count: Private<[Target; TRANSFER_COUNT_NUM_TARGETS]>,
+    /// Set if the nullifier is a [`NullifierVersion::V2`] nullifier. Only allocated by
+    /// [`Self::new_with_migration`], and derived from the transfer count and `v2_cutover`.
+    pub is_v2: Option<BoolTarget>,
+    /// The transfer count from which deposits use [`NullifierVersion::V2`] nullifiers. Only used
+    /// if `is_v2` is allocated.
+    pub v2_cutover: u64,
+    /// The destination chain id the nullifier is bound to. Only set by circuits built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_destination_chain`], which share
+    /// these targets with the public inputs and fill them separately.
+    pub destination_chain_id: Option<[Target; FELTS_PER_U64]>,
 }
 
 impl NullifierTargets {
+    /// Targets for a circuit that only accepts [`NullifierVersion::V1`] nullifiers.
     pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
         Self {
             hash: builder.add_virtual_hash_public_input(),
-            secret: builder.add_virtual_targets(SECRET_NUM_TARGETS),
-            transfer_count: array::from_fn(|_| builder.add_virtual_target()),
+            secret: Private::new(builder.add_virtual_targets(SECRET_NUM_TARGETS)),
+            transfer_count: Private::new(array::from_fn(|_| builder.add_virtual_target())),
+            is_v2: None,
+            v2_cutover: 0,
+            destination_chain_id: None,
+        }
+    }
+
+    /// Like [`Self::new`], but keeps the nullifier hash private, for circuits that expose it
+    /// through a digest.
+    pub fn new_private(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self {
+            hash: builder.add_virtual_hash(),
+            secret: Private::new(builder.add_virtual_targets(SECRET_NUM_TARGETS)),
+            transfer_count: Private::new(array::from_fn(|_| builder.add_virtual_target())),
+            is_v2: None,
+            v2_cutover: 0,
+            destination_chain_id: None,
         }
     }
+
+    /// Targets for a circuit that accepts both [`NullifierVersion::V1`] and
+    /// [`NullifierVersion::V2`] nullifiers, for use during a migration window. Deposits with a
+    /// transfer count of at least `v2_cutover` must use version 2, all others version 1.
+    pub fn new_with_migration(builder: &mut CircuitBuilder<F, D>, v2_cutover: u64) -> Self {
+        let mut targets = Self::new(builder);
+        targets.is_v2 = Some(builder.add_virtual_bool_target_safe());
+        targets.v2_cutover = v2_cutover;
+        targets
+    }
 }
 
 impl CircuitFragment for Nullifier {
     type Targets = NullifierTargets;
 
     /// Builds a circuit that assert that nullifier was computed with `H(H(nullifier +
-    /// extrinsic_index + secret))`
+    /// extrinsic_index + secret))`, or with the [`NullifierVersion::V2`] preimage if the targets
+    /// allow it and the transfer count is at least the cutover. If the targets have a destination
+    /// chain id, it follows the salt.
     fn circuit(
         &Self::Targets {
             hash,
             ref secret,
             ref transfer_count,
+            is_v2,
+            v2_cutover,
+            destination_chain_id,
         }: &Self::Targets,
         builder: &mut CircuitBuilder<F, D>,
     ) {
@@ -224,21 +414,74 @@ impl CircuitFragment for Nullifier {
         let salt_felts = injective_string_to_felt(NULLIFIER_SALT);
         preimage.push(builder.constant(salt_felts[0]));
         preimage.push(builder.constant(salt_felts[1]));
-        preimage.extend(secret);
-        preimage.extend(transfer_count);
+        preimage.extend(destination_chain_id.iter().flatten());
+        preimage.extend(secret.iter());
+        preimage.extend(transfer_count.iter());
 
         // Range check all the preimage targets to be 32 bits.
-        for target in preimage.iter() {
-            builder.range_check(*target, 32);
-        }
+        range_check_u32(builder, &preimage);
 
         // Compute the `generated_account` by double-hashing the preimage (salt + secret).
         let inner_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage.clone());
         let computed_hash =
             builder.hash_n_to_hash_no_pad::<PoseidonHash>(inner_hash.elements.to_vec());
 
-        // Assert that hashes are equal.
-        builder.connect_hashes(computed_hash, hash);
+        let Some(is_v2) = is_v2 else {
+            // Assert that hashes are equal.
+            builder.connect_hashes(computed_hash, hash);
+            return;
+        };
+
+        // The version follows from the deposit, so the prover can't claim it with both. The
+        // transfer count is range checked with the preimage.
+        let cutover = u64_to_felts(v2_cutover).map(|limb| builder.constant(limb));
+        let is_v1 = u32_limbs_less_than(builder, &transfer_count[..], &cutover);
+        let derived_is_v2 = builder.not(is_v1);
+        builder.connect(derived_is_v2.target, is_v2.target);
+
+        // The version 2 preimage inserts the version after the salt.
+        let version = builder.constant(F::from_canonical_u8(NullifierVersion::V2.as_u8()));
+        preimage.insert(salt_felts.len(), version);
+        let inner_hash_v2 = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+        let computed_hash_v2 =
+            builder.hash_n_to_hash_no_pad::<PoseidonHash>(inner_hash_v2.elements.to_vec());
+
+        for ((&v1, &v2), &expected) in computed_hash
+            .elements
+            .iter()
+            .zip(&computed_hash_v2.elements)
+            .zip(&hash.elements)
+        {
+            let selected = builder.select(is_v2, v2, v1);
+            builder.connect(selected, expected);
+        }
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let mut preimage = vec!["NULLIFIER_SALT"];
+        if targets.destination_chain_id.is_some() {
+            preimage.push("destination_chain_id");
+        }
+        preimage.extend(["secret", "transfer_count"]);
+
+        let summary = FragmentSummary::new("nullifier")
+            .range_check(preimage.join(", "), 32)
+            .poseidon("inner_hash", &preimage)
+            .poseidon("computed_hash", &["inner_hash"]);
+        if targets.is_v2.is_none() {
+            return summary.equal("computed_hash", "hash");
+        }
+
+        preimage.insert(1, "NullifierVersion::V2");
+        summary
+            .range_check("is_v2", 1)
+            .assert(format!(
+                "is_v2 == (transfer_count >= {})",
+                targets.v2_cutover
+            ))
+            .poseidon("inner_hash_v2", &preimage)
+            .poseidon("computed_hash_v2", &["inner_hash_v2"])
+            .assert("hash == (is_v2 ? computed_hash_v2 : computed_hash)")
     }
 
     fn fill_targets(
@@ -246,9 +489,39 @@ impl CircuitFragment for Nullifier {
         pw: &mut PartialWitness<F>,
         targets: Self::Targets,
     ) -> anyhow::Result<()> {
+        ShapeError::check_len(
+            "nullifier",
+            "secret",
+            targets.secret.len(),
+            self.secret.len(),
+        )?;
+
         pw.set_hash_target(targets.hash, self.hash.into())?;
-        pw.set_target_arr(&targets.secret, &self.secret)?;
-        pw.set_target_arr(&targets.transfer_count, &self.transfer_count)?;
+        pw.set_target_arr(&targets.secret[..], &self.secret)?;
+        pw.set_target_arr(&targets.transfer_count[..], &self.transfer_count)?;
+
+        match targets.is_v2 {
+            Some(is_v2) => {
+                let transfer_count = felts_to_u64(self.transfer_count)
+                    .map_err(|e| anyhow!("failed to deserialize transfer count: {:?}", e))?;
+                let version =
+                    NullifierVersion::for_transfer_count(transfer_count, targets.v2_cutover);
+                ensure!(
+                    self.version == version,
+                    "the deposit with transfer count {} must be claimed with a version {} \
+                     nullifier, got version {}",
+                    transfer_count,
+                    version.as_u8(),
+                    self.version.as_u8()
+                );
+                pw.set_bool_target(is_v2, self.version == NullifierVersion::V2)?
+            }
+            None if self.version != NullifierVersion::V1 => bail!(
+                "circuit only accepts version 1 nullifiers, got version {}",
+                self.version.as_u8()
+            ),
+            None => {}
+        }
         Ok(())
     }
 }
@@ -0,0 +1,167 @@
+//! Recovering the deposits of a secret.
+//!
+//! A wallet restored from its secret can derive its unspendable account, but not the transfers
+//! made into it: transfer records are keyed by the hash of the whole transfer, see
+//! [`transfer_proof_key`], so they can't be looked up by recipient. [`recover`] and
+//! [`recover_from_snapshot`] instead take candidate [`Deposit`]s, e.g. the funding accounts and
+//! amounts in the wallet's history or reported by an indexer, and scan a range of transfer counts
+//! for their records.
+//!
+//! Every record found is proven under the state root before it is reported, so candidates from
+//! an untrusted source can't produce inputs for deposits that don't exist. Each
+//! [`ClaimableDeposit`] holds [`CircuitInputs`] that are ready to prove, claiming to the given exit
+//! account.
+use core::ops::{Bound, Range, RangeBounds};
+
+use anyhow::Context;
+
+use crate::client::{
+    connect, encode_key, finalized_state, inputs_from_snapshot, read_proof_nodes, stored_keys,
+    transfer_count_at, transfer_proof_key, Deposit,
+};
+use crate::inputs::CircuitInputs;
+use crate::storage_proof::snapshot::StorageSnapshot;
+use crate::storage_proof::StorageProofParams;
+use crate::types::{AccountId, RootHash, SecretBytes};
+
+/// How many storage keys [`recover`] queries the node for at once.
+pub const RECOVERY_QUERY_BATCH_SIZE: usize = 512;
+
+/// A deposit found by [`recover`] or [`recover_from_snapshot`].
+#[derive(Debug, Clone)]
+pub struct ClaimableDeposit {
+    pub deposit: Deposit,
+    pub transfer_count: u64,
+    /// The inputs claiming the deposit, proven under [`Self::root_hash`].
+    pub inputs: CircuitInputs,
+}
+
+impl ClaimableDeposit {
+    /// The state root the deposit was proven under.
+    pub fn root_hash(&self) -> RootHash {
+        self.inputs.public.root_hash
+    }
+}
+
+/// Finds the transfers matching any of `candidates` into the unspendable account of `secret`
+/// among `transfer_counts` in `snapshot`, proven under `root_hash`. Deposits are returned in
+/// transfer count order.
+///
+/// Every candidate is looked up at every transfer count, so the cost grows with the product of
+/// the two.
+///
+/// # Errors
+///
+/// Returns an error if the leaf inputs of a candidate can't be computed.
+pub fn recover_from_snapshot(
+    secret: SecretBytes,
+    candidates: &[Deposit],
+    transfer_counts: Range<u64>,
+    snapshot: &StorageSnapshot,
+    root_hash: RootHash,
+    exit_account: AccountId,
+) -> anyhow::Result<Vec<ClaimableDeposit>> {
+    let mut deposits = Vec::new();
+    for transfer_count in transfer_counts {
+        for deposit in candidates {
+            // Only a missing record is skipped, invalid candidates are reported.
+            deposit.leaf_inputs(&secret, transfer_count)?;
+            let Ok(inputs) = inputs_from_snapshot(
+                secret,
+                deposit,
+                transfer_count,
+                root_hash,
+                snapshot,
+                exit_account,
+            ) else {
+                continue;
+            };
+            deposits.push(ClaimableDeposit {
+                deposit: *deposit,
+                transfer_count,
+                inputs,
+            });
+        }
+    }
+    Ok(deposits)
+}
+
+/// Like [`recover_from_snapshot`], but against the finalized block of the node at `ws_url`. An
+/// unbounded end of `transfer_counts` is the number of transfers made so far, so `..` scans
+/// every transfer.
+///
+/// Records are queried [`RECOVERY_QUERY_BATCH_SIZE`] keys at a time, and a read proof is only
+/// fetched for the records that exist.
+///
+/// # Errors
+///
+/// Returns an error if the node can't be reached or its responses are malformed, or the read
+/// proof of a record the node reported is invalid.
+pub async fn recover(
+    ws_url: &str,
+    secret: SecretBytes,
+    candidates: &[Deposit],
+    transfer_counts: impl RangeBounds<u64>,
+    exit_account: AccountId,
+) -> anyhow::Result<Vec<ClaimableDeposit>> {
+    let start = match transfer_counts.start_bound().cloned() {
+        Bound::Included(start) => start,
+        Bound::Excluded(start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = transfer_counts.end_bound().cloned();
+
+    let client = connect(ws_url).await?;
+    let (block_hash, root_hash) = finalized_state(&client).await?;
+    let end = match end {
+        Bound::Included(end) => end.saturating_add(1),
+        Bound::Excluded(end) => end,
+        Bound::Unbounded => transfer_count_at(&client, &block_hash).await?,
+    };
+
+    let mut keys = Vec::new();
+    for transfer_count in start..end {
+        for deposit in candidates {
+            let leaf_inputs = deposit.leaf_inputs(&secret, transfer_count)?;
+            keys.push((transfer_count, *deposit, encode_key(&transfer_proof_key(&leaf_inputs))));
+        }
+    }
+
+    let mut found = Vec::new();
+    for batch in keys.chunks(RECOVERY_QUERY_BATCH_SIZE) {
+        let batch_keys: Vec<String> = batch.iter().map(|(_, _, key)| key.clone()).collect();
+        let stored = stored_keys(&client, &batch_keys, &block_hash).await?;
+        found.extend(batch.iter().filter(|(_, _, key)| stored.contains(key)));
+    }
+    tracing::debug!(num_found = found.len(), "found transfer records");
+
+    let mut snapshot = StorageSnapshot::new(StorageProofParams::default());
+    for batch in found.chunks(RECOVERY_QUERY_BATCH_SIZE) {
+        let batch_keys: Vec<String> = batch.iter().map(|(_, _, key)| key.clone()).collect();
+        for node in read_proof_nodes(&client, &batch_keys, &block_hash).await? {
+            snapshot.insert(node).context("read proof node is too large for the circuit")?;
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|&(transfer_count, deposit, _)| {
+            let inputs = inputs_from_snapshot(
+                secret,
+                &deposit,
+                transfer_count,
+                root_hash,
+                &snapshot,
+                exit_account,
+            )
+            .with_context(|| {
+                format!("read proof does not prove the transfer with count {}", transfer_count)
+            })?;
+            Ok(ClaimableDeposit {
+                deposit,
+                transfer_count,
+                inputs,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,165 @@
+//! Fees paid to relayers out of a claimed deposit.
+//!
+//! A relayer submits proofs on behalf of users who have no funds on the exit chain to pay for the
+//! transaction. Circuits built with
+//! [`crate::circuit::circuit_logic::WormholeCircuit::with_relayer_fee`] split the funding amount
+//! into a fee paid to the relayer and the remaining exit amount, both public inputs, so the pallet
+//! can pay the relayer without trusting it with the whole deposit.
+//!
+//! ## Public Inputs
+//!
+//! After the exit account:
+//! - `relayer_account`: the account the fee is paid to.
+//! - `relayer_fee`: the fee, as big-endian 32-bit limbs.
+//! - `exit_amount`: `funding_amount - relayer_fee`, paid to the exit account, as big-endian
+//!   32-bit limbs.
+use anyhow::{anyhow, bail, Context};
+use plonky2::{
+    hash::hash_types::HashOutTarget,
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::{add_u32_limbs, range_check_u32};
+use zk_circuits_common::utils::{felts_to_hashout, felts_to_u128, u128_to_felts, FELTS_PER_U128};
+
+use crate::inputs::{
+    EXIT_AMOUNT_END_INDEX, EXIT_AMOUNT_START_INDEX, RELAYER_ACCOUNT_END_INDEX,
+    RELAYER_ACCOUNT_START_INDEX, RELAYER_FEE_START_INDEX,
+};
+use crate::types::AccountId;
+
+/// The fee a relayer is paid for claiming a deposit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayerFee {
+    pub relayer_account: AccountId,
+    pub fee: u128,
+    /// The funding amount less the fee.
+    pub exit_amount: u128,
+}
+
+impl RelayerFee {
+    /// Pays `fee` out of `funding_amount` to `relayer_account`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `fee` exceeds `funding_amount`.
+    pub fn new(
+        relayer_account: AccountId,
+        fee: u128,
+        funding_amount: u128,
+    ) -> anyhow::Result<Self> {
+        let exit_amount = funding_amount.checked_sub(fee).ok_or_else(|| {
+            anyhow!(
+                "relayer fee {} exceeds the funding amount {}",
+                fee,
+                funding_amount
+            )
+        })?;
+        Ok(Self {
+            relayer_account,
+            fee,
+            exit_amount,
+        })
+    }
+
+    /// Parses the relayer fee from the public inputs of a proof of a circuit built with
+    /// [`crate::circuit::circuit_logic::WormholeCircuit::with_relayer_fee`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no relayer fee, or its public inputs are malformed.
+    pub fn try_from_public_inputs(pis: &[F]) -> anyhow::Result<Self> {
+        if pis.len() != EXIT_AMOUNT_END_INDEX {
+            bail!("proof does not pay a relayer fee");
+        }
+        let relayer_account =
+            AccountId::try_from(&pis[RELAYER_ACCOUNT_START_INDEX..RELAYER_ACCOUNT_END_INDEX])
+                .context("failed to deserialize relayer account")?;
+        let amount = |start: usize, name: &str| -> anyhow::Result<u128> {
+            let felts = core::array::from_fn(|i| pis[start + i]);
+            felts_to_u128(felts).map_err(|e| anyhow!("failed to deserialize {}: {:?}", name, e))
+        };
+        Ok(Self {
+            relayer_account,
+            fee: amount(RELAYER_FEE_START_INDEX, "relayer fee")?,
+            exit_amount: amount(EXIT_AMOUNT_START_INDEX, "exit amount")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RelayerFeeTargets {
+    pub relayer_account: HashOutTarget,
+    pub fee: [Target; FELTS_PER_U128],
+    pub exit_amount: [Target; FELTS_PER_U128],
+    /// The funding amount of the storage proof leaf, which is filled by the storage proof.
+    pub funding_amount: [Target; FELTS_PER_U128],
+}
+
+impl RelayerFeeTargets {
+    /// Registers the relayer account, fee and exit amount as public inputs, and splits
+    /// `funding_amount` into the fee and exit amount.
+    pub fn new(
+        builder: &mut CircuitBuilder<F, D>,
+        funding_amount: [Target; FELTS_PER_U128],
+    ) -> Self {
+        Self {
+            relayer_account: builder.add_virtual_hash_public_input(),
+            fee: core::array::from_fn(|_| builder.add_virtual_public_input()),
+            exit_amount: core::array::from_fn(|_| builder.add_virtual_public_input()),
+            funding_amount,
+        }
+    }
+}
+
+impl CircuitFragment for RelayerFee {
+    type Targets = RelayerFeeTargets;
+
+    /// Asserts that `exit_amount + fee = funding_amount`. As all three are range checked
+    /// integers, this also asserts that the fee does not exceed the funding amount.
+    fn circuit(
+        &Self::Targets {
+            relayer_account: _,
+            ref fee,
+            ref exit_amount,
+            ref funding_amount,
+        }: &Self::Targets,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        // The funding amount is range checked by the storage proof.
+        range_check_u32(builder, fee);
+        range_check_u32(builder, exit_amount);
+
+        let sum = add_u32_limbs(builder, &[exit_amount.to_vec(), fee.to_vec()], FELTS_PER_U128);
+        let zero = builder.zero();
+        builder.connect(sum[0], zero);
+        for (&limb, &funding_limb) in sum[1..].iter().zip(funding_amount) {
+            builder.connect(limb, funding_limb);
+        }
+    }
+
+    fn describe(_targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("relayer_fee")
+            .range_check("fee, exit_amount", 32)
+            .assert("exit_amount + fee == funding_amount, without a carry out of the top limb")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        pw.set_hash_target(
+            targets.relayer_account,
+            felts_to_hashout(&self.relayer_account.to_felts()),
+        )?;
+        pw.set_target_arr(&targets.fee, &u128_to_felts(self.fee))?;
+        pw.set_target_arr(&targets.exit_amount, &u128_to_felts(self.exit_amount))?;
+        Ok(())
+    }
+}
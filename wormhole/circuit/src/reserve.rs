@@ -0,0 +1,256 @@
+//! Proof of reserve.
+//!
+//! Proves that the funding amounts of `K` storage leaves under the same storage root add up to
+//! at least a public threshold, without revealing which leaves were used. Every leaf must have
+//! been sent to a distinct unspendable account whose secret the prover knows, so custodians can
+//! attest to their wormhole deposits without linking them to later withdrawals.
+//!
+//! ## Public Inputs
+//!
+//! - `root_hash`: the storage root all leaves are proven against.
+//! - `threshold`: the minimum total amount, as big-endian 32-bit limbs.
+use alloc::vec::Vec;
+use anyhow::bail;
+use plonky2::{
+    hash::hash_types::HashOutTarget,
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+use zk_circuits_common::circuit::{CircuitFragment, ShapeError, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::gadgets::{add_u32_limbs, assert_u32_limbs_at_least, range_check_u32};
+use zk_circuits_common::utils::{felts_to_hashout, u128_to_felts, FELTS_PER_U128};
+
+use crate::storage_proof::leaf::LeafInputs;
+use crate::storage_proof::{ProcessedStorageProof, StorageProof, StorageProofTargets};
+use crate::types::{AccountId, RootHash, SecretBytes};
+use crate::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+
+pub const ROOT_HASH_START_INDEX: usize = 0;
+pub const ROOT_HASH_END_INDEX: usize = 4;
+pub const THRESHOLD_START_INDEX: usize = 4;
+pub const THRESHOLD_END_INDEX: usize = 8;
+
+/// One deposit counted towards the reserve.
+#[derive(Debug, Clone)]
+pub struct ReserveAccount {
+    /// The secret the unspendable account was derived from.
+    pub secret: SecretBytes,
+    pub transfer_count: u64,
+    pub funding_account: AccountId,
+    pub funding_amount: u128,
+    pub storage_proof: ProcessedStorageProof,
+}
+
+/// Inputs to the proof of reserve circuit.
+#[derive(Debug, Clone)]
+pub struct ProofOfReserve {
+    pub root_hash: RootHash,
+    pub threshold: u128,
+    pub accounts: Vec<ReserveAccount>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReserveAccountTargets {
+    pub unspendable_account: UnspendableAccountTargets,
+    pub storage_proof: StorageProofTargets,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReserveTargets {
+    pub root_hash: HashOutTarget,
+    pub threshold: [Target; FELTS_PER_U128],
+    pub accounts: Vec<ReserveAccountTargets>,
+}
+
+impl ReserveTargets {
+    pub fn new(builder: &mut CircuitBuilder<F, D>, num_accounts: usize) -> Self {
+        let root_hash = builder.add_virtual_hash_public_input();
+        let threshold = core::array::from_fn(|_| builder.add_virtual_public_input());
+        let accounts = (0..num_accounts)
+            .map(|_| ReserveAccountTargets {
+                unspendable_account: UnspendableAccountTargets::new(builder),
+                storage_proof: StorageProofTargets::new_private(builder),
+            })
+            .collect();
+
+        Self {
+            root_hash,
+            threshold,
+            accounts,
+        }
+    }
+}
+
+impl CircuitFragment for ProofOfReserve {
+    type Targets = ReserveTargets;
+
+    /// Builds a circuit that asserts that every account is an unspendable account with a leaf
+    /// under `root_hash`, that no account is used twice, and that the leaf amounts sum to at
+    /// least `threshold`.
+    fn circuit(
+        &Self::Targets {
+            root_hash,
+            ref threshold,
+            ref accounts,
+        }: &Self::Targets,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        range_check_u32(builder, threshold);
+
+        for account in accounts {
+            // The wormhole circuit range checks the secret as part of the nullifier, which isn't
+            // computed here.
+            range_check_u32(builder, &account.unspendable_account.secret[..]);
+            UnspendableAccount::circuit(&account.unspendable_account, builder);
+            StorageProof::circuit(&account.storage_proof, builder);
+
+            // Each leaf must pay the unspendable account and live under the public root.
+            builder.connect_hashes(
+                account.unspendable_account.account_id,
+                account.storage_proof.leaf_inputs.to_account,
+            );
+            builder.connect_hashes(root_hash, account.storage_proof.root_hash);
+        }
+
+        // Accounts must be pairwise distinct so no deposit is counted twice.
+        let zero = builder.zero();
+        for (i, a) in accounts.iter().enumerate() {
+            for b in &accounts[i + 1..] {
+                let mut all_equal = builder._true();
+                for (&x, &y) in a
+                    .unspendable_account
+                    .account_id
+                    .elements
+                    .iter()
+                    .zip(&b.unspendable_account.account_id.elements)
+                {
+                    let equal = builder.is_equal(x, y);
+                    all_equal = builder.and(all_equal, equal);
+                }
+                builder.connect(all_equal.target, zero);
+            }
+        }
+
+        let amounts = accounts
+            .iter()
+            .map(|account| account.storage_proof.leaf_inputs.funding_amount.to_vec())
+            .collect::<Vec<_>>();
+        let total = add_u32_limbs(builder, &amounts, FELTS_PER_U128);
+
+        // The total has an extra limb for the carry.
+        let mut padded_threshold = Vec::with_capacity(FELTS_PER_U128 + 1);
+        padded_threshold.push(zero);
+        padded_threshold.extend(threshold);
+        assert_u32_limbs_at_least(builder, &total, &padded_threshold);
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let mut summary = FragmentSummary::new("proof_of_reserve").range_check("threshold", 32);
+        // Every account has the same constraints.
+        if let Some(account) = targets.accounts.first() {
+            summary = summary.fragment(
+                FragmentSummary::new(format!("accounts[i] for i < {}", targets.accounts.len()))
+                    .range_check("unspendable_account.secret", 32)
+                    .fragment(UnspendableAccount::describe(&account.unspendable_account))
+                    .fragment(StorageProof::describe(&account.storage_proof))
+                    .equal(
+                        "unspendable_account.account_id",
+                        "storage_proof.leaf_inputs.to_account",
+                    )
+                    .equal("root_hash", "storage_proof.root_hash"),
+            );
+        }
+        summary
+            .assert("accounts[i].unspendable_account.account_id are pairwise distinct")
+            .assert("sum(accounts[i].storage_proof.leaf_inputs.funding_amount) >= threshold")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        ShapeError::check_len(
+            "proof_of_reserve",
+            "accounts",
+            targets.accounts.len(),
+            self.accounts.len(),
+        )?;
+
+        let total = self
+            .accounts
+            .iter()
+            .fold(0u128, |total, account| total.saturating_add(account.funding_amount));
+        if total < self.threshold {
+            bail!("reserve total {} is below threshold {}", total, self.threshold);
+        }
+
+        let root_hash = felts_to_hashout(&self.root_hash.to_felts());
+        pw.set_hash_target(targets.root_hash, root_hash)?;
+        pw.set_target_arr(&targets.threshold, &u128_to_felts(self.threshold))?;
+
+        for (account, account_targets) in self.accounts.iter().zip(targets.accounts) {
+            let unspendable_account = UnspendableAccount::from_secret(&account.secret);
+            let leaf_inputs = LeafInputs::new(
+                account.transfer_count,
+                account.funding_account,
+                AccountId::from(unspendable_account.account_id),
+                account.funding_amount,
+            )?;
+            let storage_proof =
+                StorageProof::new(&account.storage_proof, self.root_hash, leaf_inputs);
+
+            unspendable_account.fill_targets(pw, account_targets.unspendable_account)?;
+            storage_proof.fill_targets(pw, account_targets.storage_proof)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub mod circuit_logic {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData},
+    };
+    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+    use super::{ProofOfReserve, ReserveTargets};
+
+    /// The proof of reserve circuit for a fixed number of accounts.
+    pub struct ReserveCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: ReserveTargets,
+    }
+
+    impl ReserveCircuit {
+        pub fn new(config: CircuitConfig, num_accounts: usize) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = ReserveTargets::new(&mut builder, num_accounts);
+            ProofOfReserve::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> ReserveTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+}
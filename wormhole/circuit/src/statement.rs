@@ -0,0 +1,165 @@
+//! The statement the wormhole circuit proves, as structured data.
+//!
+//! [`WORMHOLE_STATEMENT`] breaks the claim of a wormhole proof down into clauses and names the
+//! fragments of [`crate::circuit::circuit_logic::CircuitTargets::describe`] that implement each
+//! of them, so auditors and integrators can go from a sentence of the statement to the
+//! constraints enforcing it and back.
+//!
+//! [`Statement::check`] compares the statement against the summary of a built circuit. A
+//! fragment that no clause names, or a clause whose fragments the circuit doesn't have, fails it,
+//! so adding or removing a fragment has to update the statement as well.
+use alloc::{collections::BTreeSet, vec::Vec};
+use anyhow::ensure;
+use serde::Serialize;
+use zk_circuits_common::describe::CircuitSummary;
+
+/// One part of the claim of a proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Clause {
+    pub name: &'static str,
+    pub statement: &'static str,
+    /// The names of the fragments whose constraints enforce the clause.
+    pub fragments: &'static [&'static str],
+    /// The circuit option adding the clause, or `None` if every wormhole circuit has it.
+    pub feature: Option<&'static str>,
+}
+
+/// The claim of a proof, split into [`Clause`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Statement {
+    pub claim: &'static str,
+    pub clauses: &'static [Clause],
+}
+
+pub const WORMHOLE_STATEMENT: Statement = Statement {
+    claim: "The prover knows a secret such that the unspendable account derived from it received \
+            funding_amount in a transfer included in the storage trie with root root_hash, and \
+            nullifier is derived from the same secret and the count of that transfer.",
+    clauses: &[
+        Clause {
+            name: "nullifier",
+            statement: "nullifier == Poseidon(Poseidon(NULLIFIER_SALT || secret || \
+                        transfer_count))",
+            fragments: &["nullifier"],
+            feature: None,
+        },
+        Clause {
+            name: "unspendable_account",
+            statement: "unspendable_account == Poseidon(Poseidon(UNSPENDABLE_SALT || secret))",
+            fragments: &["unspendable_account"],
+            feature: None,
+        },
+        Clause {
+            name: "same_secret_and_transfer",
+            statement: "The nullifier and the unspendable account are derived from the same \
+                        secret, and the nullifier from the transfer count of the proven transfer \
+                        to the unspendable account.",
+            fragments: &["shared_targets"],
+            feature: None,
+        },
+        Clause {
+            name: "transfer_included",
+            statement: "A transfer of funding_amount from funding_account to the unspendable \
+                        account with transfer_count is a leaf of the storage trie with root \
+                        root_hash.",
+            fragments: &["storage_proof"],
+            feature: None,
+        },
+        Clause {
+            name: "exit_account",
+            statement: "The proof is bound to exit_account, which is a public input and not \
+                        otherwise constrained.",
+            fragments: &["exit_account"],
+            feature: None,
+        },
+        Clause {
+            name: "relayer_fee",
+            statement: "exit_amount + fee == funding_amount, so the relayer is paid out of the \
+                        claimed deposit.",
+            fragments: &["relayer_fee"],
+            feature: Some("relayer_fee"),
+        },
+        Clause {
+            name: "exit_account_format",
+            statement: "If is_evm, exit_account holds a 20 byte EVM address.",
+            fragments: &["exit_account_format"],
+            feature: Some("exit_account_format"),
+        },
+        Clause {
+            name: "nullifier_unspent",
+            statement: "nullifier is not in the accumulator of spent nullifiers with root \
+                        spent_nullifiers_root.",
+            fragments: &["spent_nullifiers"],
+            feature: Some("spent_nullifier_accumulator"),
+        },
+        Clause {
+            name: "funding_commitment",
+            statement: "funding_commitment == Poseidon(funding_account || blinding) for the \
+                        funding account of the proven transfer and a private blinding.",
+            fragments: &["funding_commitment"],
+            feature: Some("funding_commitment"),
+        },
+        Clause {
+            name: "claim_intent",
+            statement: "intent_hash == Poseidon(nullifier || exit_account || min_amount) for a \
+                        private min_amount, and funding_amount >= min_amount.",
+            fragments: &["claim_intent"],
+            feature: Some("intent_hash"),
+        },
+        Clause {
+            name: "public_inputs_digest",
+            statement: "public_inputs_digest == Poseidon(nullifier || root_hash || \
+                        funding_amount || exit_account), which are kept private.",
+            fragments: &["public_inputs_digest"],
+            feature: Some("public_inputs_digest"),
+        },
+    ],
+};
+
+impl Statement {
+    /// The clause named `name`.
+    pub fn clause(&self, name: &str) -> Option<&Clause> {
+        self.clauses.iter().find(|clause| clause.name == name)
+    }
+
+    /// The names of every fragment a clause names, without duplicates.
+    pub fn fragments(&self) -> BTreeSet<&'static str> {
+        self.clauses
+            .iter()
+            .flat_map(|clause| clause.fragments.iter().copied())
+            .collect()
+    }
+
+    /// Checks the statement against the summary of a built circuit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a fragment of `summary` is not named by any clause, or a clause that
+    /// every circuit has names a fragment `summary` doesn't have.
+    pub fn check(&self, summary: &CircuitSummary) -> anyhow::Result<()> {
+        let described: Vec<&str> = summary
+            .fragments
+            .iter()
+            .map(|fragment| fragment.name.as_str())
+            .collect();
+        let stated = self.fragments();
+        for name in &described {
+            ensure!(
+                stated.contains(name),
+                "fragment {} is not part of any clause of the statement",
+                name
+            );
+        }
+        for clause in self.clauses.iter().filter(|clause| clause.feature.is_none()) {
+            for fragment in clause.fragments {
+                ensure!(
+                    described.contains(fragment),
+                    "clause {} names fragment {}, which the circuit doesn't have",
+                    clause.name,
+                    fragment
+                );
+            }
+        }
+        Ok(())
+    }
+}
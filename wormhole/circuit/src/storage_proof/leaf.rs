@@ -1,16 +1,18 @@
 use alloc::vec::Vec;
 use core::array;
 use plonky2::{
-    hash::hash_types::HashOutTarget, iop::target::Target, plonk::circuit_builder::CircuitBuilder,
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::target::Target,
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
 };
 
 use crate::codec::ByteCodec;
-use crate::inputs::CircuitInputs;
+use crate::inputs::{register_public_input, CircuitInputs, Public};
+use crate::storage_proof::StorageProof;
 use crate::substrate_account::SubstrateAccount;
+use crate::types::AccountId;
 use zk_circuits_common::circuit::{D, F};
-use zk_circuits_common::utils::{
-    u128_to_felts, u64_to_felts, BytesDigest, FELTS_PER_U128, FELTS_PER_U64,
-};
+use zk_circuits_common::utils::{u128_to_felts, u64_to_felts, Digest, FELTS_PER_U128, FELTS_PER_U64};
 
 pub const NUM_LEAF_INPUT_FELTS: usize = 11;
 
@@ -19,15 +21,29 @@ pub struct LeafTargets {
     pub transfer_count: [Target; FELTS_PER_U64],
     pub funding_account: HashOutTarget,
     pub to_account: HashOutTarget,
+    /// Big-endian 32-bit limbs, range checked by [`super::StorageProof`]'s circuit.
     pub funding_amount: [Target; FELTS_PER_U128],
 }
 
 impl LeafTargets {
     pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_visibility(builder, true)
+    }
+
+    /// Like [`Self::new`], but keeps the funding amount private instead of registering it as a
+    /// public input.
+    pub fn new_private(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_visibility(builder, false)
+    }
+
+    pub(crate) fn with_visibility(builder: &mut CircuitBuilder<F, D>, public: bool) -> Self {
         let transfer_count = array::from_fn(|_| builder.add_virtual_target());
         let funding_account = builder.add_virtual_hash();
         let to_account = builder.add_virtual_hash();
-        let funding_amount = array::from_fn(|_| builder.add_virtual_public_input());
+        let funding_amount = array::from_fn(|_| builder.add_virtual_target());
+        if public {
+            register_funding_amount(builder, &funding_amount);
+        }
 
         Self {
             transfer_count,
@@ -37,6 +53,8 @@ impl LeafTargets {
         }
     }
 
+    /// The leaf input targets in the order they are hashed, matching
+    /// [`LeafInputs::to_field_elements`].
     pub fn collect_to_vec(&self) -> Vec<Target> {
         self.transfer_count
             .iter()
@@ -46,6 +64,12 @@ impl LeafTargets {
             .cloned()
             .collect()
     }
+
+    /// Computes the leaf inputs hash in the circuit, matching [`LeafInputs::hash`].
+    pub fn hash(&self, builder: &mut CircuitBuilder<F, D>) -> HashOutTarget {
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>(self.collect_to_vec())
+    }
+
     pub fn collect_32_bit_targets(&self) -> Vec<Target> {
         self.transfer_count
             .iter()
@@ -55,6 +79,20 @@ impl LeafTargets {
     }
 }
 
+/// Registers the leaf's funding amount as the `funding_amount` public input.
+///
+/// This is where the public amount is bound to the storage leaf. The public inputs are the leaf's
+/// funding amount targets themselves rather than copies constrained to be equal, so the prover
+/// can't fill them with different values. The same targets are range checked and hashed into the
+/// leaf hash, see [`LeafTargets::hash`], which the storage proof circuit requires to be in the
+/// leaf node under the root.
+pub fn register_funding_amount(
+    builder: &mut CircuitBuilder<F, D>,
+    funding_amount: &[Target; FELTS_PER_U128],
+) {
+    register_public_input(builder, &Public::new(*funding_amount));
+}
+
 #[derive(Debug)]
 pub struct LeafInputs {
     pub transfer_count: [F; FELTS_PER_U64],
@@ -66,8 +104,8 @@ pub struct LeafInputs {
 impl LeafInputs {
     pub fn new(
         transfer_count: u64,
-        funding_account: BytesDigest,
-        to_account: BytesDigest,
+        funding_account: AccountId,
+        to_account: AccountId,
         funding_amount: u128,
     ) -> anyhow::Result<Self> {
         let transfer_count = u64_to_felts(transfer_count);
@@ -81,6 +119,25 @@ impl LeafInputs {
             funding_amount,
         })
     }
+
+    /// The leaf inputs as field elements, in the order the storage proof circuit hashes them.
+    pub fn to_field_elements(&self) -> Vec<F> {
+        self.transfer_count
+            .iter()
+            .chain(self.funding_account.0.iter())
+            .chain(self.to_account.0.iter())
+            .chain(self.funding_amount.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// The hash of the leaf inputs, which the leaf node of a storage proof must contain.
+    ///
+    /// This is the same value [`LeafTargets::hash`] computes in the circuit, so the chain and
+    /// clients should use it instead of hashing the fields themselves.
+    pub fn hash(&self) -> Digest {
+        PoseidonHash::hash_no_pad(&self.to_field_elements()).elements
+    }
 }
 
 impl TryFrom<&CircuitInputs> for LeafInputs {
@@ -95,3 +152,100 @@ impl TryFrom<&CircuitInputs> for LeafInputs {
         )
     }
 }
+
+/// How many transfer counts on either side of the given one [`check_transfer_count`] tries.
+pub const TRANSFER_COUNT_SEARCH_WINDOW: u64 = 16;
+
+/// Why the leaf node of a storage proof doesn't hold the transfer of [`CircuitInputs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafError {
+    /// The leaf holds the transfer, but with another transfer count.
+    StaleTransferCount { given: u64, found: u64 },
+    /// No transfer count within `window` of the given one matches the leaf.
+    Mismatch { transfer_count: u64, window: u64 },
+}
+
+impl LeafError {
+    /// A stable identifier of the error kind, listed in [`crate::errors::error_catalog`].
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::StaleTransferCount { .. } => "leaf.stale_transfer_count",
+            Self::Mismatch { .. } => "leaf.mismatch",
+        }
+    }
+}
+
+impl core::fmt::Display for LeafError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::StaleTransferCount { given, found } => write!(
+                f,
+                "transfer count {} doesn't match the storage proof leaf, which holds transfer \
+                 count {}",
+                given, found
+            ),
+            Self::Mismatch {
+                transfer_count,
+                window,
+            } => write!(
+                f,
+                "no transfer count within {} of {} matches the storage proof leaf, check the \
+                 funding account, funding amount and secret",
+                window, transfer_count
+            ),
+        }
+    }
+}
+
+impl core::error::Error for LeafError {}
+
+/// Finds the transfer count within `window` of the one in `inputs` for which the leaf node of the
+/// storage proof holds the transfer, trying the given count first.
+///
+/// Returns `None` if the proof has no nodes or no count in the window matches.
+///
+/// # Errors
+///
+/// Returns an error if an account of `inputs` can't be encoded.
+pub fn find_transfer_count(inputs: &CircuitInputs, window: u64) -> anyhow::Result<Option<u64>> {
+    let mut storage_proof = StorageProof::try_from(inputs)?;
+    let given = inputs.private.transfer_count;
+    let candidates = (1..=window)
+        .flat_map(|distance| [given.checked_add(distance), given.checked_sub(distance)])
+        .flatten();
+    for transfer_count in core::iter::once(given).chain(candidates) {
+        storage_proof.leaf_inputs.transfer_count = u64_to_felts(transfer_count);
+        if storage_proof.leaf_matches() {
+            return Ok(Some(transfer_count));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks that the leaf node of the storage proof of `inputs` holds their transfer, before a
+/// proof is attempted.
+///
+/// An outdated transfer count otherwise only surfaces as an unsatisfied constraint while proving.
+/// Proofs without nodes are left to the shape checks of the storage proof.
+///
+/// # Errors
+///
+/// Returns a [`LeafError::StaleTransferCount`] with the matching count if one within `window` of
+/// the given one matches, and a [`LeafError::Mismatch`] if none does.
+pub fn check_transfer_count(inputs: &CircuitInputs, window: u64) -> anyhow::Result<()> {
+    if inputs.private.storage_proof.proof.is_empty() {
+        return Ok(());
+    }
+
+    let given = inputs.private.transfer_count;
+    match find_transfer_count(inputs, window)? {
+        Some(found) if found == given => Ok(()),
+        Some(found) => Err(LeafError::StaleTransferCount { given, found }.into()),
+        None => Err(LeafError::Mismatch {
+            transfer_count: given,
+            window,
+        }
+        .into()),
+    }
+}
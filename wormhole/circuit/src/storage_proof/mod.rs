@@ -1,8 +1,8 @@
-use alloc::{vec, vec::Vec};
-use anyhow::bail;
+use alloc::{format, vec, vec::Vec};
+use anyhow::{bail, Context};
 use plonky2::{
-    field::types::Field,
-    hash::hash_types::{HashOut, HashOutTarget},
+    field::types::{Field, PrimeField64},
+    hash::hash_types::HashOutTarget,
     iop::target::Target,
     plonk::circuit_builder::CircuitBuilder,
 };
@@ -10,19 +10,107 @@ use plonky2::{
 use crate::{
     inputs::CircuitInputs,
     storage_proof::leaf::{LeafInputs, LeafTargets},
+    types::RootHash,
 };
-use zk_circuits_common::utils::{digest_bytes_to_felts, injective_bytes_to_felts};
+use zk_circuits_common::utils::injective_bytes_to_felts;
 use zk_circuits_common::{
-    circuit::{CircuitFragment, D, F},
-    utils::INJECTIVE_BYTES_PER_ELEMENT,
+    circuit::{CircuitFragment, ShapeError, D, F},
+    describe::FragmentSummary,
+    utils::{Digest, DIGEST_NUM_FIELD_ELEMENTS, INJECTIVE_BYTES_PER_ELEMENT},
 };
 
 pub mod leaf;
+pub mod snapshot;
 
 pub const MAX_PROOF_LEN: usize = 20;
 pub const PROOF_NODE_MAX_SIZE_F: usize = 188; // Should match the felt preimage max set on poseidon-resonance crate.
-pub const PROOF_NODE_MAX_SIZE_B: usize = 256;
+pub const PROOF_NODE_MAX_SIZE_B: usize = PROOF_NODE_MAX_SIZE_F * INJECTIVE_BYTES_PER_ELEMENT;
+/// The number of hex characters a child hash occupies within a node.
+const HASH_SIZE_HEX: usize = 64;
+/// Indices must point at the start of a field element, which holds 4 bytes or 8 hex characters.
+const INDEX_ALIGNMENT_HEX: usize = INJECTIVE_BYTES_PER_ELEMENT * 2;
 pub const FELTS_PER_AMOUNT: usize = 2;
+/// The number of field elements a child hash occupies within a node.
+const HASH_SIZE_F: usize = HASH_SIZE_HEX / INDEX_ALIGNMENT_HEX;
+
+// The circuit reads a child hash as 8 little endian 32-bit limbs, which only lines up with the
+// injective encoding if these hold.
+const _: () = assert!(HASH_SIZE_HEX % INDEX_ALIGNMENT_HEX == 0);
+const _: () = assert!(HASH_SIZE_F == 2 * DIGEST_NUM_FIELD_ELEMENTS);
+const _: () = assert!(MAX_PROOF_LEN > 0);
+const _: () = assert!(PROOF_NODE_MAX_SIZE_F >= HASH_SIZE_F);
+
+/// The shape of the storage proofs a circuit accepts.
+///
+/// Proof nodes are zero padded to [`Self::node_max_size_f`] field elements before they are
+/// hashed, so the node size must match the felt preimage size of the trie hasher of the chain
+/// the proofs come from. The default matches [`MAX_PROOF_LEN`] and [`PROOF_NODE_MAX_SIZE_F`];
+/// chains with wider branch nodes need a circuit built with a larger node size, which costs
+/// roughly one Poseidon permutation and a few gates per 8 additional felts per node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageProofParams {
+    pub max_proof_len: usize,
+    pub node_max_size_f: usize,
+}
+
+impl Default for StorageProofParams {
+    fn default() -> Self {
+        Self {
+            max_proof_len: MAX_PROOF_LEN,
+            node_max_size_f: PROOF_NODE_MAX_SIZE_F,
+        }
+    }
+}
+
+impl StorageProofParams {
+    /// # Errors
+    ///
+    /// Returns an error if `max_proof_len` is zero or a node can't hold a child hash.
+    pub fn new(max_proof_len: usize, node_max_size_f: usize) -> anyhow::Result<Self> {
+        let params = Self {
+            max_proof_len,
+            node_max_size_f,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Checks that a circuit can be built for proofs of this shape. The fields are public, so
+    /// params that didn't go through [`Self::new`] are checked again before building.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `max_proof_len` is zero or a node can't hold a child hash.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.max_proof_len == 0 {
+            bail!("storage proofs must allow at least one node");
+        }
+        if self.node_max_size_f < HASH_SIZE_F {
+            bail!(
+                "proof nodes must hold at least {} field elements, got: {}",
+                HASH_SIZE_F,
+                self.node_max_size_f
+            );
+        }
+        if self
+            .node_max_size_f
+            .checked_mul(INJECTIVE_BYTES_PER_ELEMENT)
+            .is_none()
+        {
+            bail!(
+                "proof nodes of {} field elements overflow the byte size",
+                self.node_max_size_f
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The largest node size in bytes.
+    pub fn node_max_size_b(&self) -> usize {
+        self.node_max_size_f * INJECTIVE_BYTES_PER_ELEMENT
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct StorageProofTargets {
@@ -35,26 +123,128 @@ pub struct StorageProofTargets {
 
 impl StorageProofTargets {
     pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_params(builder, StorageProofParams::default())
+    }
+
+    /// Like [`Self::new`], but for proofs of the given shape.
+    pub fn with_params(builder: &mut CircuitBuilder<F, D>, params: StorageProofParams) -> Self {
+        Self::with_visibility(builder, params, true)
+    }
+
+    /// Like [`Self::new`], but keeps the root hash and funding amount private. Circuits proving
+    /// several storage leaves use this and expose the values they need themselves.
+    pub fn new_private(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self::with_visibility(builder, StorageProofParams::default(), false)
+    }
+
+    fn with_visibility(
+        builder: &mut CircuitBuilder<F, D>,
+        params: StorageProofParams,
+        public: bool,
+    ) -> Self {
         // Setup targets. Each 8-bytes are represented as their equivalent field element. We also
         // need to track total proof length to allow for variable length.
-        let proof_data: Vec<_> = (0..MAX_PROOF_LEN)
-            .map(|_| builder.add_virtual_targets(PROOF_NODE_MAX_SIZE_F))
+        let proof_data: Vec<_> = (0..params.max_proof_len)
+            .map(|_| builder.add_virtual_targets(params.node_max_size_f))
             .collect();
 
-        let indices: Vec<_> = (0..MAX_PROOF_LEN)
+        let indices: Vec<_> = (0..params.max_proof_len)
             .map(|_| builder.add_virtual_target())
             .collect();
 
+        let root_hash = if public {
+            builder.add_virtual_hash_public_input()
+        } else {
+            builder.add_virtual_hash()
+        };
+
         Self {
-            root_hash: builder.add_virtual_hash_public_input(),
+            root_hash,
             proof_len: builder.add_virtual_target(),
             proof_data,
             indices,
-            leaf_inputs: LeafTargets::new(builder),
+            leaf_inputs: LeafTargets::with_visibility(builder, public),
+        }
+    }
+
+    /// The shape of the proofs these targets hold.
+    pub fn params(&self) -> StorageProofParams {
+        StorageProofParams {
+            max_proof_len: self.proof_data.len(),
+            node_max_size_f: self.proof_data.first().map_or(0, Vec::len),
         }
     }
 }
 
+/// Why a storage proof was rejected by [`ProcessedStorageProof::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageProofError {
+    LengthMismatch {
+        proof_len: usize,
+        indices_len: usize,
+    },
+    TooManyNodes { len: usize, max: usize },
+    NodeTooLarge { node: usize, len: usize, max: usize },
+    UnalignedIndex { node: usize, index: usize },
+    IndexOutOfBounds {
+        node: usize,
+        index: usize,
+        node_len: usize,
+    },
+}
+
+impl StorageProofError {
+    /// A stable identifier of the error kind, listed in [`crate::errors::error_catalog`].
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::LengthMismatch { .. } => "storage_proof.length_mismatch",
+            Self::TooManyNodes { .. } => "storage_proof.too_many_nodes",
+            Self::NodeTooLarge { .. } => "storage_proof.node_too_large",
+            Self::UnalignedIndex { .. } => "storage_proof.unaligned_index",
+            Self::IndexOutOfBounds { .. } => "storage_proof.index_out_of_bounds",
+        }
+    }
+}
+
+impl core::fmt::Display for StorageProofError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::LengthMismatch {
+                proof_len,
+                indices_len,
+            } => write!(
+                f,
+                "indices length must be equal to proof length, actual lengths: {}, {}",
+                proof_len, indices_len
+            ),
+            Self::TooManyNodes { len, max } => {
+                write!(f, "proof has {} nodes, at most {} are supported", len, max)
+            }
+            Self::NodeTooLarge { node, len, max } => write!(
+                f,
+                "proof node {} is {} bytes, at most {} are supported",
+                node, len, max
+            ),
+            Self::UnalignedIndex { node, index } => write!(
+                f,
+                "index {} of proof node {} is not a multiple of {}",
+                index, node, INDEX_ALIGNMENT_HEX
+            ),
+            Self::IndexOutOfBounds {
+                node,
+                index,
+                node_len,
+            } => write!(
+                f,
+                "index {} of proof node {} leaves no room for a hash in {} hex characters",
+                index, node, node_len
+            ),
+        }
+    }
+}
+
+impl core::error::Error for StorageProofError {}
+
 /// A storgae proof along with an array of indices where the hash child ndoes are placed.
 #[derive(Debug, Clone)]
 pub struct ProcessedStorageProof {
@@ -63,13 +253,67 @@ pub struct ProcessedStorageProof {
 }
 
 impl ProcessedStorageProof {
+    /// Validates the shape of a storage proof.
+    ///
+    /// `indices[i]` is the position, in hex characters, of the child hash within `proof[i]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`StorageProofError`] if the lengths of `proof` and `indices` differ, the proof
+    /// has more than [`MAX_PROOF_LEN`] nodes, a node is larger than [`PROOF_NODE_MAX_SIZE_B`], or
+    /// an index is not aligned to a field element or leaves no room for a hash in its node.
     pub fn new(proof: Vec<Vec<u8>>, indices: Vec<usize>) -> anyhow::Result<Self> {
+        Self::with_params(proof, indices, StorageProofParams::default())
+    }
+
+    /// Like [`Self::new`], but validates the proof against the limits in `params` instead of the
+    /// defaults.
+    pub fn with_params(
+        proof: Vec<Vec<u8>>,
+        indices: Vec<usize>,
+        params: StorageProofParams,
+    ) -> anyhow::Result<Self> {
         if proof.len() != indices.len() {
-            bail!(
-                "indices length must be equal to proof length, actual lengths: {}, {}",
-                proof.len(),
-                indices.len()
-            );
+            return Err(StorageProofError::LengthMismatch {
+                proof_len: proof.len(),
+                indices_len: indices.len(),
+            }
+            .into());
+        }
+        if proof.len() > params.max_proof_len {
+            return Err(StorageProofError::TooManyNodes {
+                len: proof.len(),
+                max: params.max_proof_len,
+            }
+            .into());
+        }
+
+        let node_max_size_b = params.node_max_size_b();
+        for (node_index, (node, &index)) in proof.iter().zip(&indices).enumerate() {
+            if node.len() > node_max_size_b {
+                return Err(StorageProofError::NodeTooLarge {
+                    node: node_index,
+                    len: node.len(),
+                    max: node_max_size_b,
+                }
+                .into());
+            }
+            if index % INDEX_ALIGNMENT_HEX != 0 {
+                return Err(StorageProofError::UnalignedIndex {
+                    node: node_index,
+                    index,
+                }
+                .into());
+            }
+            let node_len = node.len() * 2;
+            if index.saturating_add(HASH_SIZE_HEX) > node_len {
+                return Err(StorageProofError::IndexOutOfBounds {
+                    node: node_index,
+                    index,
+                    node_len,
+                }
+                .into());
+            }
         }
 
         Ok(Self { proof, indices })
@@ -81,14 +325,14 @@ impl ProcessedStorageProof {
 pub struct StorageProof {
     pub proof: Vec<Vec<F>>,
     pub indices: Vec<F>,
-    pub root_hash: [u8; 32],
+    pub root_hash: RootHash,
     pub leaf_inputs: LeafInputs,
 }
 
 impl StorageProof {
     pub fn new(
         processed_proof: &ProcessedStorageProof,
-        root_hash: [u8; 32],
+        root_hash: RootHash,
         leaf_inputs: LeafInputs,
     ) -> Self {
         let proof: Vec<Vec<F>> = processed_proof
@@ -96,11 +340,11 @@ impl StorageProof {
             .iter()
             .map(|node| injective_bytes_to_felts(node))
             .collect();
-        // print the length of the proof at index 4
-        // println!(
-        //     "[+] StorageProof: proof length at index 4: {}",
-        //     proof.get(4).map_or(0, |node| node.len())
-        // );
+        tracing::trace!(
+            num_nodes = proof.len(),
+            max_node_len = proof.iter().map(Vec::len).max().unwrap_or_default(),
+            "processed storage proof"
+        );
 
         let indices = processed_proof
             .indices
@@ -119,6 +363,179 @@ impl StorageProof {
             leaf_inputs,
         }
     }
+
+    /// Like [`Self::new`], but does the same work for every proof of the shape `params`, see
+    /// [`Self::fill_targets_hardened`]. Each of the `params.max_proof_len` node slots is copied
+    /// into a buffer of the largest node size and converted in full, whether or not the proof has
+    /// a node there. The result equals that of [`Self::new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`StorageProofError`] if the proof doesn't fit `params`.
+    pub fn new_hardened(
+        processed_proof: &ProcessedStorageProof,
+        root_hash: RootHash,
+        leaf_inputs: LeafInputs,
+        params: StorageProofParams,
+    ) -> anyhow::Result<Self> {
+        let ProcessedStorageProof { proof, indices } = processed_proof;
+        if proof.len() > params.max_proof_len {
+            return Err(StorageProofError::TooManyNodes {
+                len: proof.len(),
+                max: params.max_proof_len,
+            }
+            .into());
+        }
+
+        let node_max_size_b = params.node_max_size_b();
+        let mut buffer = vec![0u8; node_max_size_b];
+        let mut nodes = Vec::with_capacity(params.max_proof_len);
+        let mut node_indices = Vec::with_capacity(params.max_proof_len);
+        for i in 0..params.max_proof_len {
+            let node = proof.get(i).map_or(&[][..], Vec::as_slice);
+            if node.len() > node_max_size_b {
+                return Err(StorageProofError::NodeTooLarge {
+                    node: i,
+                    len: node.len(),
+                    max: node_max_size_b,
+                }
+                .into());
+            }
+            for (j, byte) in buffer.iter_mut().enumerate() {
+                *byte = node.get(j).copied().unwrap_or(0);
+            }
+            nodes.push(injective_bytes_to_felts(&buffer));
+
+            let index = indices.get(i).copied().unwrap_or(0) / (INJECTIVE_BYTES_PER_ELEMENT * 2);
+            node_indices.push(F::from_canonical_usize(index));
+        }
+
+        // Trim the zero padding off again, which doesn't touch the elements.
+        nodes.truncate(proof.len());
+        node_indices.truncate(proof.len());
+        for (node, bytes) in nodes.iter_mut().zip(proof) {
+            node.truncate(bytes.len().div_ceil(INJECTIVE_BYTES_PER_ELEMENT));
+        }
+
+        Ok(StorageProof {
+            proof: nodes,
+            indices: node_indices,
+            root_hash,
+            leaf_inputs,
+        })
+    }
+
+    /// The hash the last node holds at its index, read the way the circuit reads child hashes:
+    /// 8 32-bit limbs combined pairwise into the elements of a digest.
+    ///
+    /// Returns `None` if the proof has no nodes.
+    pub fn leaf_node_hash(&self) -> Option<Digest> {
+        let node = self.proof.last()?;
+        let start = self.indices.last()?.to_canonical_u64() as usize;
+        let limb = |i: usize| node.get(start + i).copied().unwrap_or(F::ZERO);
+        let two_pow_32 = F::from_canonical_u64(1 << 32);
+        let hash: Digest = core::array::from_fn(|i| limb(2 * i) + limb(2 * i + 1) * two_pow_32);
+        Some(hash)
+    }
+
+    /// Whether the leaf node holds the hash of the leaf inputs.
+    ///
+    /// The leaf node may not hold the first nibble of the hash, so like the circuit this skips the
+    /// first element.
+    pub fn leaf_matches(&self) -> bool {
+        self.leaf_node_hash()
+            .is_some_and(|hash| hash[1..] == self.leaf_inputs.hash()[1..])
+    }
+
+    /// Like [`CircuitFragment::fill_targets`], but fills every node slot element by element, so
+    /// the work depends on the shape of the circuit rather than on the length of the proof and
+    /// the sizes of its nodes.
+    ///
+    /// This is best effort: the branches selecting between node elements and padding, memory
+    /// access patterns and the work done before the proof reaches the prover still depend on the
+    /// proof.
+    pub fn fill_targets_hardened(
+        &self,
+        pw: &mut plonky2::iop::witness::PartialWitness<F>,
+        targets: StorageProofTargets,
+    ) -> anyhow::Result<()> {
+        self.fill_targets_with(pw, targets, true)
+    }
+
+    fn fill_targets_with(
+        &self,
+        pw: &mut plonky2::iop::witness::PartialWitness<F>,
+        targets: StorageProofTargets,
+        hardened: bool,
+    ) -> anyhow::Result<()> {
+        use plonky2::iop::witness::WitnessWrite;
+        use zk_circuits_common::utils::felts_to_hashout;
+
+        let StorageProofParams {
+            max_proof_len,
+            node_max_size_f,
+        } = targets.params();
+
+        ShapeError::check_max_len("storage_proof", "proof", max_proof_len, self.proof.len())?;
+        ShapeError::check_len(
+            "storage_proof",
+            "indices",
+            self.proof.len(),
+            self.indices.len(),
+        )?;
+        for (i, node) in self.proof.iter().enumerate() {
+            ShapeError::check_max_len("storage_proof", "proof_data", node_max_size_f, node.len())
+                .with_context(|| format!("proof node {}", i))?;
+        }
+
+        pw.set_hash_target(targets.root_hash, felts_to_hashout(&self.root_hash.to_felts()))?;
+        pw.set_target(targets.proof_len, F::from_canonical_usize(self.proof.len()))?;
+
+        if hardened {
+            let mut padded_proof_node = vec![F::ZERO; node_max_size_f];
+            for i in 0..max_proof_len {
+                let node = self.proof.get(i).map_or(&[][..], Vec::as_slice);
+                for (j, felt) in padded_proof_node.iter_mut().enumerate() {
+                    *felt = node.get(j).copied().unwrap_or(F::ZERO);
+                }
+                pw.set_target_arr(&targets.proof_data[i], &padded_proof_node)?;
+            }
+        } else {
+            let empty_proof_node = vec![F::ZERO; node_max_size_f];
+            for i in 0..max_proof_len {
+                match self.proof.get(i) {
+                    Some(node) => {
+                        let mut padded_proof_node = node.clone();
+                        padded_proof_node.resize(node_max_size_f, F::ZERO);
+                        pw.set_target_arr(&targets.proof_data[i], &padded_proof_node)?;
+                    }
+                    None => pw.set_target_arr(&targets.proof_data[i], &empty_proof_node)?,
+                }
+            }
+        }
+
+        for i in 0..max_proof_len {
+            let &felt = self.indices.get(i).unwrap_or(&F::ZERO);
+            pw.set_target(targets.indices[i], felt)?;
+        }
+
+        // Set leaf input targets.
+        let funding_account = felts_to_hashout(&self.leaf_inputs.funding_account.0);
+        let to_account = felts_to_hashout(&self.leaf_inputs.to_account.0);
+
+        pw.set_target_arr(
+            &targets.leaf_inputs.transfer_count,
+            &self.leaf_inputs.transfer_count,
+        )?;
+        pw.set_hash_target(targets.leaf_inputs.funding_account, funding_account)?;
+        pw.set_hash_target(targets.leaf_inputs.to_account, to_account)?;
+        pw.set_target_arr(
+            &targets.leaf_inputs.funding_amount,
+            &self.leaf_inputs.funding_amount,
+        )?;
+
+        Ok(())
+    }
 }
 
 impl TryFrom<&CircuitInputs> for StorageProof {
@@ -127,7 +544,7 @@ impl TryFrom<&CircuitInputs> for StorageProof {
     fn try_from(inputs: &CircuitInputs) -> Result<Self, Self::Error> {
         Ok(Self::new(
             &inputs.private.storage_proof,
-            *inputs.public.root_hash,
+            inputs.public.root_hash,
             LeafInputs::try_from(inputs)?,
         ))
     }
@@ -148,26 +565,26 @@ impl CircuitFragment for StorageProof {
         builder: &mut CircuitBuilder<F, D>,
     ) {
         use plonky2::hash::poseidon::PoseidonHash;
-        use zk_circuits_common::gadgets::is_const_less_than;
+        use zk_circuits_common::gadgets::{is_const_less_than, range_check_u32, recompose_u32_pair};
 
         let leaf_targets_32_bit = leaf_inputs.collect_32_bit_targets();
-        // Range contrain the first 2 and last 4 elements of the leaf inputs (transfer_count and funding_amount) to be 32 bits.
-        for target in leaf_targets_32_bit.iter() {
-            builder.range_check(*target, 32);
-        }
+        // Range constrain the transfer count and funding amount limbs to 32 bits. Otherwise a
+        // limb of 2^32 or more would give the same amount a second encoding, and with it another
+        // leaf hash. Every amount of the circuits built on the storage proof is either these
+        // limbs or derived from them.
+        range_check_u32(builder, &leaf_targets_32_bit);
 
         // Calculate the leaf inputs hash.
-        let leaf_inputs_hash =
-            builder.hash_n_to_hash_no_pad::<PoseidonHash>(leaf_inputs.collect_to_vec());
-
-        // constant 2^32 for (lo + hi * 2^32) reconstruction
-        let two_pow_32 = builder.constant(F::from_canonical_u64(1u64 << 32));
+        let leaf_inputs_hash = leaf_inputs.hash(builder);
 
         // The first node should be the root node so we initialize `prev_hash` to the provided `root_hash`.
         let mut prev_hash = root_hash;
-        let n_log = (usize::BITS - (MAX_PROOF_LEN - 1).leading_zeros()) as usize;
-        for i in 0..MAX_PROOF_LEN {
+        let max_proof_len = proof_data.len();
+        // Enough bits to hold any proof length up to and including `max_proof_len`.
+        let n_log = (usize::BITS - max_proof_len.leading_zeros()) as usize;
+        for i in 0..max_proof_len {
             let node = &proof_data[i];
+            let node_max_size_f = node.len();
 
             // Check if this is a valid proof node or a dummy one.
             let is_proof_node = is_const_less_than(builder, i, proof_len, n_log);
@@ -194,29 +611,25 @@ impl CircuitFragment for StorageProof {
                 builder.zero(),
             ];
             let expected_hash_index = indices[i];
-            for (j, felt) in node.iter().enumerate().take(PROOF_NODE_MAX_SIZE_F - 8) {
-                // Range constrain each target in the node to be 32 bits.
-                builder.range_check(*felt, 32);
+            // Range constrain each target in the node to be 32 bits.
+            range_check_u32(builder, node);
+            // A hash may start at any felt that leaves room for its 8 limbs, including the last
+            // such felt of a maximal node.
+            for j in 0..=node_max_size_f - HASH_SIZE_F {
                 let felt_index = builder.constant(F::from_canonical_usize(j));
                 let is_start_of_hash = builder.is_equal(felt_index, expected_hash_index);
 
                 // If this is the start of the hash, set the next 4 felts of `found_hash`.
-                // Combine pairs (lo, hi) -> lo + hi * 2^32 (little-endian)
-                let mut combine_le_32x2 = |lo: Target, hi: Target| {
-                    let hi_shifted = builder.mul(hi, two_pow_32);
-                    builder.add(lo, hi_shifted)
-                };
-
                 // Reconstruct the 4 hash elements from the next 8 felts (32-bit limbs).
                 // Layout (little-endian pairs):
                 // h0 = node[j+0] (lo) , node[j+1] (hi)
                 // h1 = node[j+2] (lo) , node[j+3] (hi)
                 // h2 = node[j+4] (lo) , node[j+5] (hi)
                 // h3 = node[j+6] (lo) , node[j+7] (hi)
-                let h0 = combine_le_32x2(node[j], node[j + 1]);
-                let h1 = combine_le_32x2(node[j + 2], node[j + 3]);
-                let h2 = combine_le_32x2(node[j + 4], node[j + 5]);
-                let h3 = combine_le_32x2(node[j + 6], node[j + 7]);
+                let h0 = recompose_u32_pair(builder, node[j], node[j + 1]);
+                let h1 = recompose_u32_pair(builder, node[j + 2], node[j + 3]);
+                let h2 = recompose_u32_pair(builder, node[j + 4], node[j + 5]);
+                let h3 = recompose_u32_pair(builder, node[j + 6], node[j + 7]);
 
                 // If this is the start of the hash, set the 4 reconstructed felts into found_hash.
                 found_hash[0] = builder.select(is_start_of_hash, h0, found_hash[0]);
@@ -224,10 +637,6 @@ impl CircuitFragment for StorageProof {
                 found_hash[2] = builder.select(is_start_of_hash, h2, found_hash[2]);
                 found_hash[3] = builder.select(is_start_of_hash, h3, found_hash[3]);
             }
-            // Range check the last 8 felts of the node to be 32 bits.
-            for felt in node.iter().skip(PROOF_NODE_MAX_SIZE_F - 8) {
-                builder.range_check(*felt, 32);
-            }
 
             // Lastly, we do an additional check if this is the leaf node - that the hash of its
             // inputs is contained within the node. Note: we only compare the last 3 felts since
@@ -243,74 +652,48 @@ impl CircuitFragment for StorageProof {
         }
     }
 
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        let StorageProofParams {
+            max_proof_len,
+            node_max_size_f,
+        } = targets.params();
+        let n_log = (usize::BITS - max_proof_len.leading_zeros()) as usize;
+
+        FragmentSummary::new("storage_proof")
+            .range_check("leaf_inputs.transfer_count, leaf_inputs.funding_amount", 32)
+            .poseidon(
+                "leaf_inputs_hash",
+                &[
+                    "leaf_inputs.transfer_count",
+                    "leaf_inputs.funding_account",
+                    "leaf_inputs.to_account",
+                    "leaf_inputs.funding_amount",
+                ],
+            )
+            .range_check("proof_len", n_log)
+            .range_check(format!("proof_data[i] for i < {}", max_proof_len), 32)
+            .poseidon("node_hash[i]", &["proof_data[i]"])
+            .assert(
+                "node_hash[i] == child_hash[i - 1] for i < proof_len, with child_hash[-1] = \
+                 root_hash",
+            )
+            .assert(format!(
+                "child_hash[i] == the {} 32-bit limbs of proof_data[i] from indices[i] on, \
+                 recomposed as little-endian pairs, or 0 if indices[i] > {}",
+                HASH_SIZE_F,
+                node_max_size_f - HASH_SIZE_F
+            ))
+            .assert(format!(
+                "leaf_inputs_hash[1..4] == child_hash[proof_len - 1][1..4] if proof_len < {}",
+                max_proof_len
+            ))
+    }
+
     fn fill_targets(
         &self,
         pw: &mut plonky2::iop::witness::PartialWitness<F>,
         targets: Self::Targets,
     ) -> anyhow::Result<()> {
-        use plonky2::iop::witness::WitnessWrite;
-        use zk_circuits_common::utils::felts_to_hashout;
-
-        const EMPTY_PROOF_NODE: [F; PROOF_NODE_MAX_SIZE_F] = [F::ZERO; PROOF_NODE_MAX_SIZE_F];
-
-        pw.set_hash_target(targets.root_hash, bytes_32_to_hashout(self.root_hash))?;
-        // bail if proof is too long
-        if self.proof.len() > MAX_PROOF_LEN {
-            bail!(
-                "proof length exceeds maximum allowed length: {} > {}",
-                self.proof.len(),
-                MAX_PROOF_LEN
-            );
-        }
-        pw.set_target(targets.proof_len, F::from_canonical_usize(self.proof.len()))?;
-
-        for i in 0..MAX_PROOF_LEN {
-            match self.proof.get(i) {
-                Some(node) => {
-                    let mut padded_proof_node = node.clone();
-
-                    if padded_proof_node.len() > PROOF_NODE_MAX_SIZE_F {
-                        bail!(
-                            "proof node at index {} is too large: {}",
-                            i,
-                            padded_proof_node.len()
-                        );
-                    }
-                    padded_proof_node.resize(PROOF_NODE_MAX_SIZE_F, F::ZERO);
-                    pw.set_target_arr(&targets.proof_data[i], &padded_proof_node)?;
-                }
-                None => pw.set_target_arr(&targets.proof_data[i], &EMPTY_PROOF_NODE)?,
-            }
-        }
-
-        for i in 0..MAX_PROOF_LEN {
-            let &felt = self.indices.get(i).unwrap_or(&F::ZERO);
-            pw.set_target(targets.indices[i], felt)?;
-        }
-
-        // Set leaf input targets.
-        let funding_account = felts_to_hashout(&self.leaf_inputs.funding_account.0);
-        let to_account = felts_to_hashout(&self.leaf_inputs.to_account.0);
-
-        pw.set_target_arr(
-            &targets.leaf_inputs.transfer_count,
-            &self.leaf_inputs.transfer_count,
-        )?;
-        pw.set_hash_target(targets.leaf_inputs.funding_account, funding_account)?;
-        pw.set_hash_target(targets.leaf_inputs.to_account, to_account)?;
-        pw.set_target_arr(
-            &targets.leaf_inputs.funding_amount,
-            &self.leaf_inputs.funding_amount,
-        )?;
-
-        Ok(())
+        self.fill_targets_with(pw, targets, false)
     }
 }
-
-fn bytes_32_to_hashout(bytes: [u8; 32]) -> HashOut<F> {
-    use zk_circuits_common::utils::BytesDigest;
-
-    let digest = BytesDigest::try_from(bytes).unwrap();
-    let elements = digest_bytes_to_felts(digest);
-    HashOut { elements }
-}
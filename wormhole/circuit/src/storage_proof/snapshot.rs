@@ -0,0 +1,247 @@
+//! Storage proofs from state snapshots.
+//!
+//! Backfills and stress tests need storage proofs for many transfers, and fetching each one from
+//! a node over RPC puts a lot of load on it. A [`StorageSnapshot`] holds the trie nodes of a state
+//! dump instead and builds [`ProcessedStorageProof`]s offline.
+//!
+//! Nodes are looked up by the hash the storage proof circuit computes for them, and child hashes
+//! are found by scanning each node for the hash of another node at a field element aligned
+//! position. The snapshot therefore doesn't need to decode the trie's node format, and every
+//! proof it returns passes the same checks as the circuit.
+//!
+//! # Format
+//!
+//! [`StorageSnapshot::from_reader`] reads one node per line as hex, optionally prefixed with the
+//! hex of its hash and a space, like the `key value` lines of a node database dump. Given hashes
+//! are checked against the computed ones. Empty lines and lines starting with `#` are skipped.
+//! Hex must be canonical, see [`crate::hex_util`].
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec::Vec;
+use anyhow::bail;
+use plonky2::{field::types::Field, hash::poseidon::PoseidonHash, plonk::config::Hasher};
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::utils::{
+    digest_felts_to_bytes, injective_bytes_to_felts, Digest, INJECTIVE_BYTES_PER_ELEMENT,
+};
+
+use super::{ProcessedStorageProof, StorageProofParams, HASH_SIZE_F};
+use crate::types::RootHash;
+
+/// The number of bytes a child hash occupies within a node.
+const HASH_SIZE_B: usize = HASH_SIZE_F * INJECTIVE_BYTES_PER_ELEMENT;
+/// The leaf node may only hold part of the first element of the leaf inputs hash, so only the
+/// bytes of the remaining elements are matched, like in the circuit.
+const LEAF_MATCH_OFFSET_B: usize = HASH_SIZE_B / 4;
+
+/// The trie nodes of a state snapshot, indexed by their hashes.
+#[derive(Debug, Clone)]
+pub struct StorageSnapshot {
+    params: StorageProofParams,
+    nodes: Vec<Vec<u8>>,
+    by_hash: BTreeMap<[u8; 32], usize>,
+}
+
+impl StorageSnapshot {
+    /// Creates an empty snapshot for proofs of the given shape. The node size must match the
+    /// trie hasher of the chain the snapshot was taken from, see [`StorageProofParams`].
+    pub fn new(params: StorageProofParams) -> Self {
+        Self {
+            params,
+            nodes: Vec::new(),
+            by_hash: BTreeMap::new(),
+        }
+    }
+
+    /// The hash of a node, as it appears in its parent or as the storage root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is larger than the node size in `params`.
+    pub fn node_hash(node: &[u8], params: &StorageProofParams) -> anyhow::Result<[u8; 32]> {
+        if node.len() > params.node_max_size_b() {
+            bail!(
+                "node is {} bytes, at most {} are supported",
+                node.len(),
+                params.node_max_size_b()
+            );
+        }
+
+        let mut felts = injective_bytes_to_felts(node);
+        felts.resize(params.node_max_size_f, F::ZERO);
+        let hash: Digest = PoseidonHash::hash_no_pad(&felts).elements;
+        Ok(*digest_felts_to_bytes(hash))
+    }
+
+    /// Adds a node and returns its hash. Adding a node twice has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is larger than the node size of the snapshot.
+    pub fn insert(&mut self, node: Vec<u8>) -> anyhow::Result<[u8; 32]> {
+        let hash = Self::node_hash(&node, &self.params)?;
+        if !self.by_hash.contains_key(&hash) {
+            self.by_hash.insert(hash, self.nodes.len());
+            self.nodes.push(node);
+        }
+        Ok(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn params(&self) -> StorageProofParams {
+        self.params
+    }
+
+    /// Builds a proof that the trie under `root_hash` holds the leaf whose inputs hash to
+    /// `leaf_hash`, see [`super::leaf::LeafInputs::hash`].
+    ///
+    /// The proof is the shortest path of nodes from the root to a node containing the leaf hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the root is not in the snapshot, or no node containing the leaf hash
+    /// can be reached from it within the maximum proof length.
+    pub fn prove(
+        &self,
+        root_hash: RootHash,
+        leaf_hash: Digest,
+    ) -> anyhow::Result<ProcessedStorageProof> {
+        let Some(&root) = self.by_hash.get(&*root_hash) else {
+            bail!("root hash {} is not in the snapshot", hex::encode(*root_hash));
+        };
+        let leaf_bytes = digest_felts_to_bytes(leaf_hash);
+        let leaf_suffix = &leaf_bytes[LEAF_MATCH_OFFSET_B..];
+
+        // Breadth first search, remembering the parent and child hash offset of every node.
+        let mut parents: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+        let mut visited = BTreeSet::from([root]);
+        let mut queue = VecDeque::from([(root, 1)]);
+        while let Some((node, depth)) = queue.pop_front() {
+            if let Some(offset) = self.hash_offsets(node).find(|&offset| {
+                self.nodes[node][offset + LEAF_MATCH_OFFSET_B..offset + HASH_SIZE_B] == *leaf_suffix
+            }) {
+                return self.build_proof(node, offset, &parents);
+            }
+            if depth == self.params.max_proof_len {
+                continue;
+            }
+
+            for offset in self.hash_offsets(node) {
+                let window = &self.nodes[node][offset..offset + HASH_SIZE_B];
+                let Some(&child) = self.by_hash.get(window) else {
+                    continue;
+                };
+                if visited.insert(child) {
+                    parents.insert(child, (node, offset));
+                    queue.push_back((child, depth + 1));
+                }
+            }
+        }
+
+        bail!(
+            "no node containing leaf hash {} within {} nodes of the root",
+            hex::encode(*leaf_bytes),
+            self.params.max_proof_len
+        )
+    }
+
+    /// The byte offsets within `node` the circuit can read a child hash from.
+    fn hash_offsets(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        let node_len = self.nodes[node].len();
+        let max_felt_index = self.params.node_max_size_f - HASH_SIZE_F;
+        (0..=max_felt_index)
+            .map(|i| i * INJECTIVE_BYTES_PER_ELEMENT)
+            .take_while(move |&offset| offset + HASH_SIZE_B <= node_len)
+    }
+
+    fn build_proof(
+        &self,
+        leaf_node: usize,
+        leaf_offset: usize,
+        parents: &BTreeMap<usize, (usize, usize)>,
+    ) -> anyhow::Result<ProcessedStorageProof> {
+        let mut path = Vec::from([(leaf_node, leaf_offset)]);
+        let mut node = leaf_node;
+        while let Some(&(parent, offset)) = parents.get(&node) {
+            path.push((parent, offset));
+            node = parent;
+        }
+        path.reverse();
+
+        // Indices are given in hex characters.
+        let (proof, indices): (Vec<_>, Vec<_>) = path
+            .into_iter()
+            .map(|(node, offset)| (self.nodes[node].clone(), offset * 2))
+            .unzip();
+        ProcessedStorageProof::with_params(proof, indices, self.params)
+    }
+}
+
+#[cfg(feature = "std")]
+impl StorageSnapshot {
+    /// Reads a snapshot in the format described in the [module docs](self).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a line is not valid hex, a node is too large, or a given hash doesn't
+    /// match its node.
+    pub fn from_reader(
+        reader: impl std::io::BufRead,
+        params: StorageProofParams,
+    ) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let mut snapshot = Self::new(params);
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (expected_hash, node) = match line.split_once(' ') {
+                Some((hash, node)) => (Some(hash), node.trim()),
+                None => (None, line),
+            };
+            let node = crate::hex_util::decode(node)
+                .with_context(|| format!("line {} is not a hex encoded node", i + 1))?;
+            let hash = snapshot
+                .insert(node)
+                .with_context(|| format!("invalid node on line {}", i + 1))?;
+
+            if let Some(expected_hash) = expected_hash {
+                let expected_hash = crate::hex_util::decode(expected_hash)
+                    .with_context(|| format!("line {} has an invalid hash", i + 1))?;
+                if expected_hash != hash {
+                    bail!(
+                        "node on line {} hashes to {}, expected {}",
+                        i + 1,
+                        hex::encode(hash),
+                        hex::encode(expected_hash)
+                    );
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Reads a snapshot from a file, see [`Self::from_reader`].
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+        params: StorageProofParams,
+    ) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open snapshot {:?}", path))?;
+        Self::from_reader(std::io::BufReader::new(file), params)
+    }
+}
@@ -2,6 +2,7 @@ use alloc::vec::Vec;
 use core::ops::Deref;
 use plonky2::iop::witness::PartialWitness;
 use zk_circuits_common::circuit::CircuitFragment;
+use zk_circuits_common::describe::FragmentSummary;
 
 use crate::codec::{ByteCodec, FieldElementCodec};
 use plonky2::{
@@ -79,6 +80,13 @@ impl ExitAccountTargets {
             address: builder.add_virtual_hash_public_input(),
         }
     }
+
+    /// Like [`Self::new`], but keeps the exit account private.
+    pub fn new_private(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self {
+            address: builder.add_virtual_hash(),
+        }
+    }
 }
 
 impl CircuitFragment for SubstrateAccount {
@@ -87,6 +95,11 @@ impl CircuitFragment for SubstrateAccount {
     /// Builds a dummy circuit to include the exit account as a public input.
     fn circuit(Self::Targets { address: _ }: &Self::Targets, _builder: &mut CircuitBuilder<F, D>) {}
 
+    /// The exit account is unconstrained, it only binds the proof to the account.
+    fn describe(_targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("exit_account")
+    }
+
     fn fill_targets(
         &self,
         pw: &mut PartialWitness<F>,
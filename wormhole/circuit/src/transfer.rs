@@ -0,0 +1,218 @@
+//! Private transfers within the wormhole pool.
+//!
+//! Spends a deposit like the wormhole circuit, but instead of paying out to a public exit
+//! account, pays the same amount into a new unspendable account. The recipient hands the sender
+//! the inner hash of their account (see [`UnspendableAccount::inner_hash_from_secret`]), and the
+//! circuit proves that the new account is its hash. Nobody holds a signing key for such an
+//! account, so the funds can only leave it through another wormhole proof by the recipient, and
+//! value moves between owners without ever passing through a public account.
+//!
+//! ## Public Inputs
+//!
+//! The layout matches the wormhole circuit, with the new account in place of the exit account, so
+//! transfer proofs can be aggregated with the same stride.
+//!
+//! - `nullifier`: the nullifier of the spent deposit.
+//! - `root_hash`: the storage root the spent deposit is proven against.
+//! - `funding_amount`: the amount moved, as big-endian 32-bit limbs.
+//! - `new_account`: the unspendable account the amount is paid into.
+use plonky2::{
+    hash::{hash_types::HashOutTarget, poseidon::PoseidonHash},
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{circuit_builder::CircuitBuilder, config::Hasher},
+};
+use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
+use zk_circuits_common::utils::{felts_to_hashout, Digest};
+
+use crate::nullifier::{Nullifier, NullifierTargets};
+use crate::storage_proof::leaf::LeafInputs;
+use crate::storage_proof::{ProcessedStorageProof, StorageProof, StorageProofTargets};
+use crate::types::{AccountId, NullifierHash, RootHash, SecretBytes};
+use crate::unspendable_account::{UnspendableAccount, UnspendableAccountTargets};
+
+pub const NULLIFIER_START_INDEX: usize = 0;
+pub const NULLIFIER_END_INDEX: usize = 4;
+pub const ROOT_HASH_START_INDEX: usize = 4;
+pub const ROOT_HASH_END_INDEX: usize = 8;
+pub const FUNDING_AMOUNT_START_INDEX: usize = 8;
+pub const FUNDING_AMOUNT_END_INDEX: usize = 12;
+pub const NEW_ACCOUNT_START_INDEX: usize = 12;
+pub const NEW_ACCOUNT_END_INDEX: usize = 16;
+
+/// Inputs to the transfer circuit.
+#[derive(Debug, Clone)]
+pub struct PoolTransfer {
+    /// The secret of the spent deposit's unspendable account and nullifier.
+    pub secret: SecretBytes,
+    pub transfer_count: u64,
+    pub funding_account: AccountId,
+    pub funding_amount: u128,
+    pub root_hash: RootHash,
+    pub storage_proof: ProcessedStorageProof,
+    /// The inner hash of the recipient's unspendable account.
+    pub recipient: Digest,
+}
+
+impl PoolTransfer {
+    /// The nullifier of the spent deposit.
+    pub fn nullifier(&self) -> NullifierHash {
+        Nullifier::from_preimage(self.secret.as_slice(), self.transfer_count)
+            .hash
+            .into()
+    }
+
+    /// The unspendable account the amount is paid into.
+    pub fn new_account(&self) -> AccountId {
+        PoseidonHash::hash_no_pad(&self.recipient).elements.into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferTargets {
+    pub nullifier: NullifierTargets,
+    pub unspendable_account: UnspendableAccountTargets,
+    pub storage_proof: StorageProofTargets,
+    pub new_account: HashOutTarget,
+    pub recipient: HashOutTarget,
+}
+
+impl TransferTargets {
+    pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
+        Self {
+            nullifier: NullifierTargets::new(builder),
+            unspendable_account: UnspendableAccountTargets::new(builder),
+            storage_proof: StorageProofTargets::new(builder),
+            new_account: builder.add_virtual_hash_public_input(),
+            recipient: builder.add_virtual_hash(),
+        }
+    }
+}
+
+impl CircuitFragment for PoolTransfer {
+    type Targets = TransferTargets;
+
+    /// Builds a circuit that asserts that the nullifier and storage leaf belong to a deposit the
+    /// prover can spend, and that `new_account` is the hash of the private recipient inner hash.
+    fn circuit(targets: &Self::Targets, builder: &mut CircuitBuilder<F, D>) {
+        Nullifier::circuit(&targets.nullifier, builder);
+        UnspendableAccount::circuit(&targets.unspendable_account, builder);
+        StorageProof::circuit(&targets.storage_proof, builder);
+
+        // The nullifier and unspendable account must share the secret, and the leaf must pay the
+        // unspendable account with the nullified transfer.
+        for (&a, &b) in targets
+            .nullifier
+            .secret
+            .iter()
+            .zip(targets.unspendable_account.secret.iter())
+        {
+            builder.connect(a, b);
+        }
+        for (&a, &b) in targets
+            .nullifier
+            .transfer_count
+            .iter()
+            .zip(&targets.storage_proof.leaf_inputs.transfer_count)
+        {
+            builder.connect(a, b);
+        }
+        builder.connect_hashes(
+            targets.unspendable_account.account_id,
+            targets.storage_proof.leaf_inputs.to_account,
+        );
+
+        // The new account is the outer hash of an unspendable account.
+        let new_account =
+            builder.hash_n_to_hash_no_pad::<PoseidonHash>(targets.recipient.elements.to_vec());
+        builder.connect_hashes(new_account, targets.new_account);
+    }
+
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        FragmentSummary::new("pool_transfer")
+            .fragment(Nullifier::describe(&targets.nullifier))
+            .fragment(UnspendableAccount::describe(&targets.unspendable_account))
+            .fragment(StorageProof::describe(&targets.storage_proof))
+            .equal("nullifier.secret", "unspendable_account.secret")
+            .equal(
+                "nullifier.transfer_count",
+                "storage_proof.leaf_inputs.transfer_count",
+            )
+            .equal(
+                "unspendable_account.account_id",
+                "storage_proof.leaf_inputs.to_account",
+            )
+            .poseidon("computed_account", &["recipient"])
+            .equal("computed_account", "new_account")
+    }
+
+    fn fill_targets(
+        &self,
+        pw: &mut PartialWitness<F>,
+        targets: Self::Targets,
+    ) -> anyhow::Result<()> {
+        let nullifier = Nullifier::from_preimage(self.secret.as_slice(), self.transfer_count);
+        let unspendable_account = UnspendableAccount::from_secret(&self.secret);
+        let leaf_inputs = LeafInputs::new(
+            self.transfer_count,
+            self.funding_account,
+            AccountId::from(unspendable_account.account_id),
+            self.funding_amount,
+        )?;
+        let storage_proof = StorageProof::new(&self.storage_proof, self.root_hash, leaf_inputs);
+
+        nullifier.fill_targets(pw, targets.nullifier)?;
+        unspendable_account.fill_targets(pw, targets.unspendable_account)?;
+        storage_proof.fill_targets(pw, targets.storage_proof)?;
+        pw.set_hash_target(
+            targets.new_account,
+            felts_to_hashout(&self.new_account().to_felts()),
+        )?;
+        pw.set_hash_target(targets.recipient, felts_to_hashout(&self.recipient))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub mod circuit_logic {
+    use plonky2::plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData, ProverCircuitData, VerifierCircuitData},
+    };
+    use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+    use super::{PoolTransfer, TransferTargets};
+
+    /// The transfer circuit.
+    pub struct TransferCircuit {
+        builder: CircuitBuilder<F, D>,
+        targets: TransferTargets,
+    }
+
+    impl TransferCircuit {
+        pub fn new(config: CircuitConfig) -> Self {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let targets = TransferTargets::new(&mut builder);
+            PoolTransfer::circuit(&targets, &mut builder);
+
+            Self { builder, targets }
+        }
+
+        pub fn targets(&self) -> TransferTargets {
+            self.targets.clone()
+        }
+
+        pub fn build_circuit(self) -> CircuitData<F, C, D> {
+            self.builder.build()
+        }
+
+        pub fn build_prover(self) -> ProverCircuitData<F, C, D> {
+            self.builder.build_prover()
+        }
+
+        pub fn build_verifier(self) -> VerifierCircuitData<F, C, D> {
+            self.builder.build_verifier()
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Distinct types for the 32 byte values passed into the wormhole circuit.
+//!
+//! Nullifiers, root hashes and account ids are all [`BytesDigest`]s, which made it easy to pass
+//! one where another is expected. Each gets its own type here, and converting between them has to
+//! go through [`BytesDigest`] explicitly.
+use core::ops::Deref;
+
+use plonky2::{hash::poseidon::PoseidonHash, plonk::config::Hasher};
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::utils::{
+    digest_bytes_to_felts, injective_bytes_to_felts, u64_to_felts, BytesDigest, Digest,
+    DigestError, FELTS_PER_U64,
+};
+
+macro_rules! digest_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(
+            feature = "scale",
+            derive(
+                parity_scale_codec::Encode,
+                parity_scale_codec::Decode,
+                parity_scale_codec::MaxEncodedLen
+            )
+        )]
+        pub struct $name(BytesDigest);
+
+        impl $name {
+            pub const fn new(digest: BytesDigest) -> Self {
+                Self(digest)
+            }
+
+            pub fn to_felts(&self) -> Digest {
+                digest_bytes_to_felts(self.0)
+            }
+        }
+
+        impl From<BytesDigest> for $name {
+            fn from(value: BytesDigest) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for BytesDigest {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl From<Digest> for $name {
+            fn from(value: Digest) -> Self {
+                Self(BytesDigest::from(value))
+            }
+        }
+
+        impl TryFrom<[u8; 32]> for $name {
+            type Error = DigestError;
+
+            fn try_from(value: [u8; 32]) -> Result<Self, Self::Error> {
+                BytesDigest::try_from(value).map(Self)
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = DigestError;
+
+            fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+                BytesDigest::try_from(value).map(Self)
+            }
+        }
+
+        impl TryFrom<&[F]> for $name {
+            type Error = anyhow::Error;
+
+            fn try_from(value: &[F]) -> Result<Self, Self::Error> {
+                BytesDigest::try_from(value).map(Self)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = [u8; 32];
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    };
+}
+
+digest_newtype!(
+    /// The root hash of a storage trie.
+    RootHash
+);
+
+digest_newtype!(
+    /// The public hash of a [`crate::nullifier::Nullifier`].
+    NullifierHash
+);
+
+digest_newtype!(
+    /// The id of a funding, unspendable or exit account.
+    AccountId
+);
+
+/// The secret behind a nullifier and an unspendable account.
+///
+/// Unlike the digest types, any 32 bytes are a valid secret. With the `redact` feature, which is
+/// on by default, the bytes are left out of the [`core::fmt::Debug`] output so they don't end up
+/// in logs.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct SecretBytes([u8; 32]);
+
+impl SecretBytes {
+    pub const fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl core::fmt::Debug for SecretBytes {
+    #[cfg(feature = "redact")]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SecretBytes(..)")
+    }
+
+    #[cfg(not(feature = "redact"))]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "SecretBytes({})", hex::encode(self.0))
+    }
+}
+
+/// Stands in for secret material in the [`core::fmt::Debug`] output of types holding private
+/// inputs, with the `redact` feature. Only the number of values is shown.
+#[cfg(feature = "redact")]
+pub(crate) enum Redacted {
+    Value,
+    Values(usize, &'static str),
+}
+
+#[cfg(feature = "redact")]
+impl core::fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Value => f.write_str("<redacted>"),
+            Self::Values(len, unit) => write!(f, "<{} {} redacted>", len, unit),
+        }
+    }
+}
+
+impl From<[u8; 32]> for SecretBytes {
+    fn from(value: [u8; 32]) -> Self {
+        Self(value)
+    }
+}
+
+impl TryFrom<&[u8]> for SecretBytes {
+    type Error = DigestError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let bytes = value.try_into().map_err(|_| DigestError::InvalidLength {
+            expected: 32,
+            got: value.len(),
+        })?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A value supplied by the verifier that a proof is bound to, e.g. a chain's genesis hash
+/// combined with a spend window.
+///
+/// Circuits built with [`crate::circuit::circuit_logic::WormholeCircuit::with_context`] expose
+/// the hash returned by [`ProofContext::to_felts`] as public inputs, so a proof generated for one
+/// context fails to verify in another. Any 32 bytes are a valid context.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofContext([u8; 32]);
+
+impl ProofContext {
+    pub const fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// The Poseidon hash of the context, as it appears in the public inputs.
+    pub fn to_felts(&self) -> Digest {
+        PoseidonHash::hash_no_pad(&injective_bytes_to_felts(&self.0)).elements
+    }
+}
+
+impl From<[u8; 32]> for ProofContext {
+    fn from(value: [u8; 32]) -> Self {
+        Self(value)
+    }
+}
+
+impl TryFrom<&[u8]> for ProofContext {
+    type Error = DigestError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let bytes = value.try_into().map_err(|_| DigestError::InvalidLength {
+            expected: 32,
+            got: value.len(),
+        })?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Deref for ProofContext {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The id of the chain a deposit can be claimed on, in deployments of the wormhole pallet on
+/// several chains.
+///
+/// Circuits built with
+/// [`crate::circuit::circuit_logic::WormholeCircuit::with_destination_chain`] prepend the id to
+/// the preimages of both the unspendable account and the nullifier, and expose it as public
+/// inputs. The deposit address therefore commits to a single chain, whose verifier checks the id
+/// before accepting a proof, and nullifiers of different chains never collide.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChainId(u64);
+
+impl ChainId {
+    pub const fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// The id as big-endian 32-bit limbs, as it appears in the public inputs.
+    pub fn to_felts(&self) -> [F; FELTS_PER_U64] {
+        u64_to_felts(self.0)
+    }
+}
+
+impl From<u64> for ChainId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
@@ -1,4 +1,5 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
+use core::array;
 use core::mem::size_of;
 
 use plonky2::{
@@ -11,48 +12,72 @@ use plonky2::{
 };
 
 use crate::codec::FieldElementCodec;
-use crate::{codec::ByteCodec, inputs::CircuitInputs};
+#[cfg(feature = "redact")]
+use crate::types::Redacted;
+use crate::types::{AccountId, ChainId};
+use crate::{
+    codec::ByteCodec,
+    inputs::{CircuitInputs, Private},
+};
 use zk_circuits_common::circuit::{CircuitFragment, D, F};
+use zk_circuits_common::describe::FragmentSummary;
 use zk_circuits_common::utils::{
     digest_bytes_to_felts, digest_felts_to_bytes, injective_bytes_to_felts,
-    injective_felts_to_bytes, injective_string_to_felt, BytesDigest, Digest,
+    injective_felts_to_bytes, injective_string_to_felt, BytesDigest, Digest, FELTS_PER_U64,
 };
 
 pub const SECRET_NUM_TARGETS: usize = 8;
 pub const PREIMAGE_NUM_TARGETS: usize = 10;
 pub const UNSPENDABLE_SALT: &str = "wormhole";
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// An unspendable account and the secret it is derived from.
+///
+/// With the `redact` feature, the secret is left out of the [`core::fmt::Debug`] output.
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(not(feature = "redact"), derive(Debug))]
 pub struct UnspendableAccount {
     pub account_id: Digest,
     pub secret: [F; SECRET_NUM_TARGETS],
 }
 
+#[cfg(feature = "redact")]
+impl core::fmt::Debug for UnspendableAccount {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnspendableAccount")
+            .field("account_id", &self.account_id)
+            .field("secret", &Redacted::Values(self.secret.len(), "felts"))
+            .finish()
+    }
+}
+
 impl UnspendableAccount {
-    pub fn new(account_id: BytesDigest, secret: &[u8]) -> Self {
-        let account_id = digest_bytes_to_felts(account_id);
+    pub fn new(account_id: AccountId, secret: &[u8]) -> Self {
+        let account_id = account_id.to_felts();
         let secret: [F; SECRET_NUM_TARGETS] = injective_bytes_to_felts(secret).try_into().unwrap();
         Self { account_id, secret }
     }
 
     pub fn from_secret(secret: &[u8; 32]) -> Self {
-        // First, convert the preimage to its representation as field elements.
+        Self::from_inner_hash(secret, Self::inner_hash_from_secret(secret))
+    }
+
+    /// The unspendable account of `secret` that can only be claimed on `chain_id`, for circuits
+    /// built with [`crate::circuit::circuit_logic::WormholeCircuit::with_destination_chain`].
+    pub fn from_secret_for_chain(secret: &[u8; 32], chain_id: ChainId) -> Self {
         let mut preimage = Vec::new();
-        let secret_felts: [F; SECRET_NUM_TARGETS] =
-            injective_bytes_to_felts(secret).try_into().unwrap();
         preimage.extend(injective_string_to_felt(UNSPENDABLE_SALT));
-        preimage.extend(secret_felts);
+        preimage.extend(chain_id.to_felts());
+        preimage.extend(injective_bytes_to_felts(secret));
 
-        if preimage.len() != PREIMAGE_NUM_TARGETS {
-            panic!(
-                "Expected preimage to be 80 bytes (10 field elements), got {} field elements",
-                preimage.len()
-            );
-        }
+        let inner_hash = PoseidonHash::hash_no_pad(&preimage).elements;
+        Self::from_inner_hash(secret, inner_hash)
+    }
+
+    fn from_inner_hash(secret: &[u8; 32], inner_hash: Digest) -> Self {
+        let secret_felts: [F; SECRET_NUM_TARGETS] =
+            injective_bytes_to_felts(secret).try_into().unwrap();
 
         // Hash twice to get the account id.
-        let inner_hash = PoseidonHash::hash_no_pad(&preimage).elements;
-        // println!("inner_hash: {:?}", hex::encode(felts_to_bytes(&inner_hash)));
         let outer_hash = PoseidonHash::hash_no_pad(&inner_hash).elements;
         let account_id = Digest::from(outer_hash);
 
@@ -61,6 +86,26 @@ impl UnspendableAccount {
             secret: secret_felts,
         }
     }
+
+    /// The inner hash `H(salt || secret)`, whose hash is the account id.
+    ///
+    /// Knowing the inner hash doesn't reveal the secret, so the owner of an unspendable account
+    /// can hand it out to receive private transfers. See [`crate::transfer`].
+    pub fn inner_hash_from_secret(secret: &[u8; 32]) -> Digest {
+        // First, convert the preimage to its representation as field elements.
+        let mut preimage = Vec::new();
+        preimage.extend(injective_string_to_felt(UNSPENDABLE_SALT));
+        preimage.extend(injective_bytes_to_felts(secret));
+
+        if preimage.len() != PREIMAGE_NUM_TARGETS {
+            panic!(
+                "Expected preimage to be 80 bytes (10 field elements), got {} field elements",
+                preimage.len()
+            );
+        }
+
+        PoseidonHash::hash_no_pad(&preimage).elements
+    }
 }
 
 impl ByteCodec for UnspendableAccount {
@@ -153,24 +198,28 @@ impl FieldElementCodec for UnspendableAccount {
 
 impl From<&CircuitInputs> for UnspendableAccount {
     fn from(inputs: &CircuitInputs) -> Self {
-        Self::new(inputs.private.unspendable_account, &inputs.private.secret)
+        Self::new(
+            inputs.private.unspendable_account,
+            inputs.private.secret.as_slice(),
+        )
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct UnspendableAccountTargets {
     pub account_id: HashOutTarget,
-    pub secret: [Target; SECRET_NUM_TARGETS],
+    pub secret: Private<[Target; SECRET_NUM_TARGETS]>,
+    /// The destination chain id the account is bound to. See
+    /// [`crate::nullifier::NullifierTargets::destination_chain_id`].
+    pub destination_chain_id: Option<[Target; FELTS_PER_U64]>,
 }
 
 impl UnspendableAccountTargets {
     pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
         Self {
             account_id: builder.add_virtual_hash(),
-            secret: builder
-                .add_virtual_targets(SECRET_NUM_TARGETS)
-                .try_into()
-                .unwrap(),
+            secret: Private::new(array::from_fn(|_| builder.add_virtual_target())),
+            destination_chain_id: None,
         }
     }
 }
@@ -179,10 +228,12 @@ impl CircuitFragment for UnspendableAccount {
     type Targets = UnspendableAccountTargets;
 
     /// Builds a circuit that asserts that the `unspendable_account` was generated from `H(H(salt+secret))`.
+    /// If the targets have a destination chain id, it follows the salt.
     fn circuit(
         &Self::Targets {
             account_id,
             ref secret,
+            destination_chain_id,
         }: &Self::Targets,
         builder: &mut CircuitBuilder<F, D>,
     ) {
@@ -195,8 +246,10 @@ impl CircuitFragment for UnspendableAccount {
             builder.range_check(*target, 32);
         }
         // Don't need to perform a range check on the secret since we are already donig that on the
-        // nullifier circuit and we ensuring the secret is the same across both circuits.
-        preimage.extend(secret);
+        // nullifier circuit and we ensuring the secret is the same across both circuits. The same
+        // goes for the chain id, which both circuits share.
+        preimage.extend(destination_chain_id.iter().flatten());
+        preimage.extend(secret.iter());
 
         // Compute the `generated_account` by double-hashing the preimage (salt + secret).
         let inner_hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage.clone());
@@ -207,6 +260,21 @@ impl CircuitFragment for UnspendableAccount {
         builder.connect_hashes(generated_account, account_id);
     }
 
+    fn describe(targets: &Self::Targets) -> FragmentSummary {
+        // The secret and chain id are range checked by the nullifier they are shared with.
+        let mut preimage = vec!["UNSPENDABLE_SALT"];
+        if targets.destination_chain_id.is_some() {
+            preimage.push("destination_chain_id");
+        }
+        preimage.push("secret");
+
+        FragmentSummary::new("unspendable_account")
+            .range_check("UNSPENDABLE_SALT", 32)
+            .poseidon("inner_hash", &preimage)
+            .poseidon("generated_account", &["inner_hash"])
+            .equal("generated_account", "account_id")
+    }
+
     fn fill_targets(
         &self,
         pw: &mut PartialWitness<F>,
@@ -214,7 +282,7 @@ impl CircuitFragment for UnspendableAccount {
     ) -> anyhow::Result<()> {
         // Unspendable account circuit values.
         pw.set_hash_target(targets.account_id, self.account_id.into())?;
-        pw.set_target_arr(&targets.secret, &self.secret)?;
+        pw.set_target_arr(&targets.secret[..], &self.secret)?;
 
         Ok(())
     }
@@ -0,0 +1,213 @@
+//! Test vectors for the Poseidon preimages of the wormhole circuit.
+//!
+//! Wallets compute nullifiers, unspendable accounts and storage leaf hashes outside of the
+//! circuit, often in other languages, and must match the encoding used here exactly.
+//! [`test_vectors`] returns a fixed suite of inputs with their expected outputs. The circuit
+//! builder's `test-vectors` binary writes it to JSON.
+//!
+//! In the JSON encoding, byte strings are hex encoded without a `0x` prefix and integers are
+//! decimal strings, since `u64` and `u128` values do not fit in a JSON number. Digests are
+//! serialized from canonical field elements.
+//!
+//! The types and functions in this module are a stable API. Vectors may be appended to the
+//! suite, but existing vectors only change together with the circuit, in which case
+//! [`TEST_VECTORS_VERSION`] is bumped.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use anyhow::{bail, Context};
+use plonky2::field::types::{Field, PrimeField64};
+use serde::{Deserialize, Serialize};
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::utils::{digest_felts_to_bytes, BytesDigest, Digest};
+
+use crate::hex_util;
+use crate::nullifier::Nullifier;
+use crate::storage_proof::leaf::LeafInputs;
+use crate::unspendable_account::UnspendableAccount;
+
+pub const TEST_VECTORS_VERSION: u32 = 1;
+
+const SECRETS: [[u8; 32]; 4] = [
+    [0u8; 32],
+    [0xffu8; 32],
+    [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25, 26, 27, 28, 29, 30, 31,
+    ],
+    [
+        76, 133, 135, 189, 66, 46, 1, 217, 97, 172, 220, 117, 231, 214, 111, 103, 97, 183, 175,
+        124, 155, 24, 100, 164, 146, 243, 105, 201, 214, 114, 79, 5,
+    ],
+];
+
+const TRANSFER_COUNTS: [u64; 5] = [0, 1, 0xffff_ffff, 1 << 32, u64::MAX];
+
+const FUNDING_AMOUNTS: [u128; 4] = [0, 1, (1 << 64) + 5, u128::MAX];
+
+/// `H(H(salt || secret || transfer_count))`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NullifierVector {
+    pub secret: String,
+    pub transfer_count: String,
+    pub nullifier: String,
+}
+
+/// `H(H(salt || secret))`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnspendableAccountVector {
+    pub secret: String,
+    pub account_id: String,
+}
+
+/// `H(transfer_count || funding_account || to_account || funding_amount)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafHashVector {
+    pub transfer_count: String,
+    pub funding_account: String,
+    pub to_account: String,
+    pub funding_amount: String,
+    pub leaf_hash: String,
+}
+
+/// The full test vector suite.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u32,
+    pub nullifiers: Vec<NullifierVector>,
+    pub unspendable_accounts: Vec<UnspendableAccountVector>,
+    pub leaf_hashes: Vec<LeafHashVector>,
+}
+
+fn digest_hex(digest: Digest) -> String {
+    let canonical = digest.map(|felt| F::from_canonical_u64(felt.to_canonical_u64()));
+    hex::encode(*digest_felts_to_bytes(canonical))
+}
+
+fn decode_secret(secret: &str) -> anyhow::Result<[u8; 32]> {
+    hex_util::decode_array(secret).context("secret is not a 32 byte hex string")
+}
+
+fn decode_digest(digest: &str) -> anyhow::Result<BytesDigest> {
+    hex_util::decode_digest(digest).context("digest is not a valid hex digest")
+}
+
+/// Computes the nullifier vector for `secret` and `transfer_count`.
+pub fn nullifier_vector(secret: &[u8; 32], transfer_count: u64) -> NullifierVector {
+    let nullifier = Nullifier::from_preimage(secret, transfer_count);
+    NullifierVector {
+        secret: hex::encode(secret),
+        transfer_count: transfer_count.to_string(),
+        nullifier: digest_hex(nullifier.hash),
+    }
+}
+
+/// Computes the unspendable account vector for `secret`.
+pub fn unspendable_account_vector(secret: &[u8; 32]) -> UnspendableAccountVector {
+    let account = UnspendableAccount::from_secret(secret);
+    UnspendableAccountVector {
+        secret: hex::encode(secret),
+        account_id: digest_hex(account.account_id),
+    }
+}
+
+/// Computes the storage leaf hash vector for the given leaf fields.
+pub fn leaf_hash_vector(
+    transfer_count: u64,
+    funding_account: BytesDigest,
+    to_account: BytesDigest,
+    funding_amount: u128,
+) -> anyhow::Result<LeafHashVector> {
+    let leaf_inputs = LeafInputs::new(
+        transfer_count,
+        funding_account.into(),
+        to_account.into(),
+        funding_amount,
+    )?;
+    Ok(LeafHashVector {
+        transfer_count: transfer_count.to_string(),
+        funding_account: hex::encode(*funding_account),
+        to_account: hex::encode(*to_account),
+        funding_amount: funding_amount.to_string(),
+        leaf_hash: digest_hex(leaf_inputs.hash()),
+    })
+}
+
+/// Returns the test vector suite.
+pub fn test_vectors() -> TestVectors {
+    let nullifiers = SECRETS
+        .iter()
+        .flat_map(|secret| {
+            TRANSFER_COUNTS
+                .iter()
+                .map(move |&transfer_count| nullifier_vector(secret, transfer_count))
+        })
+        .collect();
+
+    let unspendable_accounts = SECRETS.iter().map(unspendable_account_vector).collect();
+
+    // Pay each funding account to the unspendable account of the next secret.
+    let accounts = SECRETS
+        .iter()
+        .map(|secret| digest_felts_to_bytes(UnspendableAccount::from_secret(secret).account_id))
+        .collect::<Vec<_>>();
+    let leaf_hashes = FUNDING_AMOUNTS
+        .iter()
+        .zip(TRANSFER_COUNTS)
+        .enumerate()
+        .map(|(i, (&funding_amount, transfer_count))| {
+            let funding_account = accounts[i % accounts.len()];
+            let to_account = accounts[(i + 1) % accounts.len()];
+            leaf_hash_vector(transfer_count, funding_account, to_account, funding_amount)
+                .expect("unspendable account ids are valid digests")
+        })
+        .collect();
+
+    TestVectors {
+        version: TEST_VECTORS_VERSION,
+        nullifiers,
+        unspendable_accounts,
+        leaf_hashes,
+    }
+}
+
+impl TestVectors {
+    /// Recomputes every vector and checks it against the expected output.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.version != TEST_VECTORS_VERSION {
+            bail!(
+                "unsupported test vectors version {}, expected {}",
+                self.version,
+                TEST_VECTORS_VERSION
+            );
+        }
+
+        for (i, vector) in self.nullifiers.iter().enumerate() {
+            let secret = decode_secret(&vector.secret)?;
+            let transfer_count = vector.transfer_count.parse()?;
+            if nullifier_vector(&secret, transfer_count) != *vector {
+                bail!("nullifier vector {} does not match", i);
+            }
+        }
+
+        for (i, vector) in self.unspendable_accounts.iter().enumerate() {
+            let secret = decode_secret(&vector.secret)?;
+            if unspendable_account_vector(&secret) != *vector {
+                bail!("unspendable account vector {} does not match", i);
+            }
+        }
+
+        for (i, vector) in self.leaf_hashes.iter().enumerate() {
+            let expected = leaf_hash_vector(
+                vector.transfer_count.parse()?,
+                decode_digest(&vector.funding_account)?,
+                decode_digest(&vector.to_account)?,
+                vector.funding_amount.parse()?,
+            )?;
+            if expected != *vector {
+                bail!("leaf hash vector {} does not match", i);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,16 +1,16 @@
-use plonky2::hash::poseidon::PoseidonHash;
 use plonky2::plonk::circuit_data::CircuitConfig;
-use plonky2::plonk::config::Hasher;
 use plonky2::plonk::proof::ProofWithPublicInputs;
 use wormhole_circuit::inputs::{CircuitInputs, PrivateCircuitInputs, PublicCircuitInputs};
 use wormhole_circuit::nullifier::Nullifier;
-use wormhole_circuit::storage_proof::ProcessedStorageProof;
+use wormhole_circuit::storage_proof::{leaf::LeafInputs, ProcessedStorageProof};
 use wormhole_circuit::substrate_account::SubstrateAccount;
+use wormhole_circuit::types::RootHash;
 use wormhole_circuit::unspendable_account::UnspendableAccount;
 use wormhole_prover::WormholeProver;
-use zk_circuits_common::utils::{digest_felts_to_bytes, u128_to_felts, u64_to_felts};
 
 fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
     // Create inputs. In practice, each input would be gathered from the real node.
     let funding_account = SubstrateAccount::new(&[
         226, 124, 203, 9, 80, 60, 124, 205, 165, 5, 178, 216, 195, 15, 149, 38, 116, 1, 238, 133,
@@ -21,13 +21,13 @@ fn main() -> anyhow::Result<()> {
     let funding_amount = 1_000_000_000_000u128;
     let transfer_count = 0u64;
 
-    let mut leaf_inputs_felts = Vec::new();
-    leaf_inputs_felts.extend(&u64_to_felts(transfer_count));
-    leaf_inputs_felts.extend_from_slice(&funding_account.0);
-    leaf_inputs_felts.extend_from_slice(&unspendable_account);
-    leaf_inputs_felts.extend_from_slice(&u128_to_felts(funding_amount));
-    let leaf_inputs_hash = PoseidonHash::hash_no_pad(&leaf_inputs_felts);
-    let root_hash = digest_felts_to_bytes(leaf_inputs_hash.elements);
+    let leaf_inputs = LeafInputs::new(
+        transfer_count,
+        (*funding_account).into(),
+        unspendable_account.into(),
+        funding_amount,
+    )?;
+    let root_hash = RootHash::from(leaf_inputs.hash());
 
     let exit_account_id = 8226349481601990196u64;
     let exit_account_bytes = exit_account_id.to_le_bytes();
@@ -39,7 +39,7 @@ fn main() -> anyhow::Result<()> {
 
     let inputs = CircuitInputs {
         private: PrivateCircuitInputs {
-            secret,
+            secret: secret.into(),
             transfer_count: 0,
             funding_account: (*funding_account).into(),
             storage_proof: ProcessedStorageProof::new(vec![], vec![]).unwrap(),
@@ -63,12 +63,12 @@ fn main() -> anyhow::Result<()> {
     > = prover_next.prove().expect("proof failed; qed");
 
     let public_inputs = PublicCircuitInputs::try_from(&proof)?;
-    // print the public inputs
-    println!("{:?}", public_inputs);
+    tracing::info!(?public_inputs, "generated proof");
 
     // write the proof as hex
     let proof_hex = hex::encode(proof.to_bytes());
     // store the proof hex to file
     std::fs::write("proof_from_bins.hex", proof_hex)?;
+    tracing::info!(path = "proof_from_bins.hex", "proof saved");
     Ok(())
 }
@@ -0,0 +1,38 @@
+//! Parses proof envelopes in every wire format and verifies the proofs they wrap.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use parity_scale_codec::{Decode, Encode};
+use qp_wormhole_fuzz::verifier;
+use wormhole_circuit::codec::ByteCodec;
+use wormhole_circuit::envelope::format::EnvelopeFormat;
+use wormhole_circuit::envelope::sealed::SealedSubmission;
+use wormhole_circuit::envelope::ProofEnvelope;
+
+fuzz_target!(|data: &[u8]| {
+    let mut envelopes = Vec::new();
+
+    if let Ok(envelope) = ProofEnvelope::from_bytes(data) {
+        assert_eq!(envelope.to_bytes(), data);
+        envelopes.push(envelope);
+    }
+    if let Ok((format, envelope)) = EnvelopeFormat::decode(data) {
+        let encoded = format.encode(&envelope).expect("decoded envelope encodes");
+        let (_, decoded) = EnvelopeFormat::decode(&encoded).expect("encoded envelope decodes");
+        assert_eq!(decoded, envelope);
+        envelopes.push(envelope);
+    }
+    if let Ok(envelope) = ProofEnvelope::decode(&mut &data[..]) {
+        let decoded = ProofEnvelope::decode(&mut &envelope.encode()[..]).ok();
+        assert_eq!(decoded.as_ref(), Some(&envelope));
+        envelopes.push(envelope);
+    }
+    if let Ok(sealed) = SealedSubmission::from_bytes(data) {
+        assert_eq!(sealed.to_bytes(), data);
+        envelopes.push(sealed.envelope);
+    }
+
+    for envelope in envelopes {
+        let _ = verifier().verify_envelope(&envelope);
+    }
+});
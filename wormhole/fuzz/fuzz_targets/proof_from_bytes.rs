@@ -0,0 +1,14 @@
+//! Deserializes and verifies proofs of the wormhole circuit, as the verifier and the aggregator
+//! service do with every submitted proof.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qp_wormhole_fuzz::verifier;
+use wormhole_verifier::prepared::{verify_prepared, PreparedProof};
+
+fuzz_target!(|data: &[u8]| {
+    let verifier = verifier();
+    if let Ok(prepared) = PreparedProof::prepare(data, &verifier.circuit_data.common) {
+        let _ = verify_prepared(&prepared, &verifier.circuit_data);
+    }
+});
@@ -0,0 +1,27 @@
+//! Validates raw storage proofs and converts the accepted ones to circuit inputs, checking that
+//! the hardened conversion agrees with the plain one.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qp_wormhole_fuzz::StorageProofInput;
+use test_helpers::storage_proof::{default_root_hash, TestInputs};
+use wormhole_circuit::storage_proof::leaf::LeafInputs;
+use wormhole_circuit::storage_proof::{ProcessedStorageProof, StorageProof, StorageProofParams};
+
+fuzz_target!(|data: &[u8]| {
+    let input = StorageProofInput::decode(data);
+    let Ok(processed) = ProcessedStorageProof::new(input.proof, input.indices) else {
+        return;
+    };
+
+    let plain = StorageProof::new(&processed, default_root_hash(), LeafInputs::test_inputs());
+    let hardened = StorageProof::new_hardened(
+        &processed,
+        default_root_hash(),
+        LeafInputs::test_inputs(),
+        StorageProofParams::default(),
+    )
+    .expect("a validated proof fits the default params");
+    assert_eq!(plain.proof, hardened.proof);
+    assert_eq!(plain.indices, hardened.indices);
+});
@@ -0,0 +1,61 @@
+//! Writes the seed corpus of every fuzz target to `corpus/<target>`, from a proof of the test
+//! inputs and the storage proof the tests use.
+use std::fs;
+use std::path::Path;
+
+use parity_scale_codec::Encode;
+use qp_wormhole_fuzz::{circuit_config, verifier, StorageProofInput, TARGETS};
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::codec::ByteCodec;
+use wormhole_circuit::envelope::format::EnvelopeFormat;
+use wormhole_circuit::envelope::sealed::{AggregatorSecretKey, SealedSubmission};
+use wormhole_circuit::envelope::ProofEnvelope;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::storage_proof::ProcessedStorageProof;
+use wormhole_prover::WormholeProver;
+
+fn write_seed(target: &str, name: &str, bytes: &[u8]) -> anyhow::Result<()> {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), bytes)?;
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let proof = WormholeProver::new(circuit_config())
+        .commit(&CircuitInputs::test_inputs())?
+        .prove()?
+        .to_bytes();
+    write_seed(TARGETS[0], "test-proof", &proof)?;
+
+    let envelope = ProofEnvelope {
+        verifier_digest: verifier().verifier_digest(),
+        proof,
+    };
+    write_seed(TARGETS[1], "raw", &envelope.to_bytes())?;
+    write_seed(TARGETS[1], "scale", &envelope.encode())?;
+    for format in EnvelopeFormat::ALL {
+        let name = format!("format-{}", format.name());
+        write_seed(TARGETS[1], &name, &format.encode(&envelope)?)?;
+    }
+    let key = AggregatorSecretKey::from_bytes([1; 32]);
+    let sealed = SealedSubmission::seal(envelope, b"refund address", &key.public_key())?;
+    write_seed(TARGETS[1], "sealed", &sealed.to_bytes())?;
+
+    let storage_proof = StorageProofInput::from(&ProcessedStorageProof::test_inputs());
+    write_seed(TARGETS[2], "test-inputs", &storage_proof.encode())?;
+    for len in 1..storage_proof.proof.len() {
+        let prefix = StorageProofInput {
+            proof: storage_proof.proof[..len].to_vec(),
+            indices: storage_proof.indices[..len].to_vec(),
+        };
+        write_seed(
+            TARGETS[2],
+            &format!("test-inputs-{}-nodes", len),
+            &prefix.encode(),
+        )?;
+    }
+
+    println!("Seed corpus written to corpus/");
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Shared setup of the fuzz targets.
+//!
+//! The targets parse input the aggregator and verifier services receive from the network, see
+//! the README. Every target must only ever return an error on malformed input, never panic.
+use std::sync::OnceLock;
+
+use wormhole_circuit::storage_proof::ProcessedStorageProof;
+use wormhole_verifier::prelude::CircuitConfig;
+use wormhole_verifier::WormholeVerifier;
+
+/// The names of the fuzz targets, which are also the names of their corpus directories.
+pub const TARGETS: [&str; 3] = ["proof_from_bytes", "proof_envelope", "storage_proof"];
+
+/// The config of the fuzzed wormhole circuit, the zero knowledge config the services use.
+pub fn circuit_config() -> CircuitConfig {
+    CircuitConfig::standard_recursion_zk_config()
+}
+
+/// The verifier of the wormhole circuit, built once per fuzzing process. It is built from the
+/// source rather than deserialized, so it never falls behind changes to the circuit.
+pub fn verifier() -> &'static WormholeVerifier {
+    static VERIFIER: OnceLock<WormholeVerifier> = OnceLock::new();
+    VERIFIER.get_or_init(|| WormholeVerifier::new(circuit_config(), None))
+}
+
+/// The raw storage proof the `storage_proof` target decodes from its input.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageProofInput {
+    pub proof: Vec<Vec<u8>>,
+    pub indices: Vec<usize>,
+}
+
+impl StorageProofInput {
+    /// Decodes nodes until `data` runs out. Every node is its index and its length, both as
+    /// little endian `u16`s, followed by the node bytes. A node cut short keeps the bytes that are
+    /// left, so every input decodes.
+    pub fn decode(mut data: &[u8]) -> Self {
+        let mut input = Self::default();
+        while data.len() >= 4 {
+            let index = u16::from_le_bytes([data[0], data[1]]) as usize;
+            let len = u16::from_le_bytes([data[2], data[3]]) as usize;
+            let (node, rest) = data[4..].split_at(len.min(data.len() - 4));
+            input.proof.push(node.to_vec());
+            input.indices.push(index);
+            data = rest;
+        }
+        input
+    }
+
+    /// The encoding [`Self::decode`] reads, used to write corpus seeds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index or node length doesn't fit in a `u16`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (node, &index) in self.proof.iter().zip(&self.indices) {
+            let index = u16::try_from(index).expect("index fits in u16");
+            let len = u16::try_from(node.len()).expect("node length fits in u16");
+            bytes.extend(index.to_le_bytes());
+            bytes.extend(len.to_le_bytes());
+            bytes.extend(node);
+        }
+        bytes
+    }
+}
+
+impl From<&ProcessedStorageProof> for StorageProofInput {
+    fn from(processed: &ProcessedStorageProof) -> Self {
+        Self {
+            proof: processed.proof.clone(),
+            indices: processed.indices.clone(),
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use std::time::Duration;
 
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
 use plonky2::plonk::circuit_data::CircuitConfig;
 use qp_wormhole_prover::WormholeProver;
 use test_helpers::storage_proof::TestInputs;
 use wormhole_circuit::inputs::CircuitInputs;
+use zk_circuits_common::config::ConfigPreset;
 
 const MEASUREMENT_TIME_S: u64 = 20;
 
@@ -20,11 +21,38 @@ fn create_proof_benchmark(c: &mut Criterion) {
     });
 }
 
+fn config_presets_benchmark(c: &mut Criterion) {
+    let inputs = CircuitInputs::test_inputs();
+    for preset in ConfigPreset::ALL {
+        let config = preset.config(false);
+
+        // Report the proof size next to the timings.
+        let proof = WormholeProver::new(config.clone())
+            .commit(&inputs)
+            .unwrap()
+            .prove()
+            .unwrap();
+        println!(
+            "{} preset: proof size {} bytes",
+            preset.name(),
+            proof.to_bytes().len()
+        );
+
+        c.bench_function(&format!("prover_prove_{}", preset.name()), |b| {
+            b.iter_batched(
+                || WormholeProver::new(config.clone()).commit(&inputs).unwrap(),
+                |prover| prover.prove().unwrap(),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default()
         .measurement_time(Duration::from_secs(MEASUREMENT_TIME_S))
         .sample_size(10);
-    targets = create_proof_benchmark
+    targets = create_proof_benchmark, config_presets_benchmark
 );
 criterion_main!(benches);
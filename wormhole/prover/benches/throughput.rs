@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use qp_wormhole_prover::WormholeProver;
+use test_helpers::storage_proof::TestInputs;
+use test_helpers::throughput::{circuit_config, thread_pool, ThroughputSummary, THREAD_COUNTS};
+use wormhole_circuit::inputs::CircuitInputs;
+
+const MEASUREMENT_TIME_S: u64 = 20;
+
+/// Proves the test inputs with and without zero knowledge on every thread count. Only proving is
+/// timed, building the circuit and filling the witness are not.
+fn prove_throughput_benchmark(c: &mut Criterion) {
+    let inputs = CircuitInputs::test_inputs();
+    let mut summary = ThroughputSummary::default();
+    let mut group = c.benchmark_group("prover_throughput");
+    for zk in [false, true] {
+        let config = circuit_config(zk);
+        for threads in THREAD_COUNTS {
+            let pool = thread_pool(threads);
+            let id = BenchmarkId::new(if zk { "zk" } else { "no_zk" }, threads);
+            group.bench_function(id, |b| {
+                b.iter_custom(|iters| {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iters {
+                        let prover = WormholeProver::new(config.clone()).commit(&inputs).unwrap();
+                        let start = Instant::now();
+                        pool.install(|| prover.prove().unwrap());
+                        elapsed += start.elapsed();
+                    }
+                    summary.record("prover_prove", zk, threads, 1, iters, elapsed);
+                    elapsed
+                });
+            });
+        }
+    }
+    group.finish();
+    summary.emit().unwrap();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .measurement_time(Duration::from_secs(MEASUREMENT_TIME_S))
+        .sample_size(10);
+    targets = prove_throughput_benchmark
+);
+criterion_main!(benches);
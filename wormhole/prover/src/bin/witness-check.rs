@@ -0,0 +1,33 @@
+//! Reports which constraint family a redacted witness failed, see
+//! [`qp_wormhole_prover::diagnostics`].
+//!
+//! Usage: `witness-check <redacted-witness.json>`
+use anyhow::{bail, Context, Result};
+use qp_wormhole_prover::diagnostics::RedactedWitness;
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(path), None) = (args.next(), args.next()) else {
+        bail!("usage: witness-check <redacted-witness.json>");
+    };
+
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path))?;
+    let witness = RedactedWitness::from_json(&json)?;
+    for named in &witness.public_inputs {
+        println!("{}: {:?}", named.name, named.values);
+    }
+    println!("storage proof nodes: {}", witness.storage_proof_len);
+
+    match witness.check()? {
+        Some(family) => {
+            let check = witness.checks.iter().find(|check| check.family == family);
+            match check.and_then(|check| check.code.as_deref()) {
+                Some(code) => println!("failed constraint family: {:?} ({})", family, code),
+                None => println!("failed constraint family: {:?}", family),
+            }
+        }
+        None => println!("all constraint families passed"),
+    }
+    Ok(())
+}
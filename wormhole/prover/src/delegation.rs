@@ -0,0 +1,107 @@
+//! Client and server halves of delegated proving.
+//!
+//! A low-power client proves only the secret derived part of a claim with a [`DelegationClient`],
+//! and sends the proof to a [`DelegatedProver`], which adds the storage proof and proves the rest.
+//! See [`wormhole_circuit::delegation`] for the circuits and what the delegated prover learns.
+//!
+//! Both sides must build their circuits with the same config, and the delegated prover must be
+//! created with the verifier data of the client circuit, see [`DelegationClient::verifier_data`].
+use anyhow::{anyhow, Context};
+use plonky2::{
+    iop::witness::PartialWitness,
+    plonk::{
+        circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData},
+        proof::ProofWithPublicInputs,
+    },
+};
+use wormhole_circuit::delegation::circuit_logic::{ClientCommitmentCircuit, DelegatedClaimCircuit};
+use wormhole_circuit::delegation::{
+    ClientCommitment, ClientCommitmentTargets, DelegatedClaim, DelegatedClaimTargets,
+};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+/// Proves the client half of a claim.
+pub struct DelegationClient {
+    pub circuit_data: CircuitData<F, C, D>,
+    targets: ClientCommitmentTargets,
+}
+
+impl DelegationClient {
+    pub fn new(config: CircuitConfig) -> Self {
+        let circuit = ClientCommitmentCircuit::new(config);
+        let targets = circuit.targets();
+        Self {
+            circuit_data: circuit.build_circuit(),
+            targets,
+        }
+    }
+
+    /// The verifier data of the client circuit, which the delegated prover is created with.
+    pub fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.circuit_data.verifier_data()
+    }
+
+    /// Proves `commitment`. The proof is sent to the delegated prover along with the unspendable
+    /// account, see [`ClientCommitment::unspendable_account`], so it can find the deposit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if proving fails.
+    pub fn prove(
+        &self,
+        commitment: &ClientCommitment,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let _span = tracing::info_span!("delegation_client_prove").entered();
+        let mut pw = PartialWitness::new();
+        commitment.fill_targets(&mut pw, self.targets.clone())?;
+        self.circuit_data
+            .prove(pw)
+            .map_err(|e| anyhow!("Failed to prove: {}", e))
+    }
+}
+
+/// Completes claims from client proofs, without learning the client's secret.
+pub struct DelegatedProver {
+    pub circuit_data: CircuitData<F, C, D>,
+    client_verifier: VerifierCircuitData<F, C, D>,
+    targets: DelegatedClaimTargets,
+}
+
+impl DelegatedProver {
+    /// Builds the delegated circuit for proofs of the client circuit with the verifier data
+    /// `client_verifier`.
+    pub fn new(config: CircuitConfig, client_verifier: VerifierCircuitData<F, C, D>) -> Self {
+        let circuit = DelegatedClaimCircuit::new(config, &client_verifier);
+        let targets = circuit.targets();
+        Self {
+            circuit_data: circuit.build_circuit(),
+            client_verifier,
+            targets,
+        }
+    }
+
+    /// The verifier data delegated proofs are verified with.
+    pub fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.circuit_data.verifier_data()
+    }
+
+    /// Proves `claim`. The proof has the public inputs of a wormhole proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client proof doesn't verify, or the storage proof doesn't prove a
+    /// deposit to the client's unspendable account.
+    pub fn prove(&self, claim: &DelegatedClaim) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let _span = tracing::info_span!("delegated_prove").entered();
+        // Fail early with a clear error instead of an unsatisfiable witness.
+        self.client_verifier
+            .verify(claim.client_proof.clone())
+            .context("client proof failed verification")?;
+
+        let mut pw = PartialWitness::new();
+        claim.fill_targets(&mut pw, self.targets.clone())?;
+        self.circuit_data
+            .prove(pw)
+            .map_err(|e| anyhow!("Failed to prove: {}", e))
+    }
+}
@@ -0,0 +1,248 @@
+//! Redacted witnesses for debugging failed proofs.
+//!
+//! When proving fails, plonky2 only reports the wire whose values conflict, and reproducing the
+//! failure requires the user's secret. Instead, the user exports a [`RedactedWitness`] with
+//! [`RedactedWitness::from_inputs`] and sends it to support. It contains:
+//!
+//! - the public inputs of the proof, by name,
+//! - the number of storage proof nodes,
+//! - whether the inputs satisfy each [`ConstraintFamily`] of the circuit, checked natively on the
+//!   user's machine, and the error code of the failure, if there is one.
+//!
+//! The secret, transfer count, funding and unspendable accounts, and storage proof nodes are left
+//! out, so the export reveals no more than the proof would have, except for the length of the
+//! storage proof. [`RedactedWitness::check`] validates an imported export and returns the first
+//! family that failed.
+//!
+//! The checks assume the default wormhole circuit. Inputs for circuits bound to a destination
+//! chain fail the [`ConstraintFamily::Nullifier`] and [`ConstraintFamily::UnspendableAccount`]
+//! checks, as those derive the hashes without the chain id.
+use anyhow::{anyhow, bail};
+use plonky2::{
+    field::types::{Field, Field64, PrimeField64},
+    hash::poseidon::PoseidonHash,
+    plonk::config::Hasher,
+};
+use serde::{Deserialize, Serialize};
+use wormhole_circuit::inputs::{
+    CircuitInputs, PublicCircuitInputs, EXIT_ACCOUNT_END_INDEX, EXIT_ACCOUNT_START_INDEX,
+    FUNDING_AMOUNT_END_INDEX, FUNDING_AMOUNT_START_INDEX, NULLIFIER_END_INDEX,
+    NULLIFIER_START_INDEX, ROOT_HASH_END_INDEX, ROOT_HASH_START_INDEX,
+};
+use wormhole_circuit::nullifier::{Nullifier, NullifierVersion};
+use wormhole_circuit::storage_proof::{
+    ProcessedStorageProof, StorageProof, StorageProofError, StorageProofParams,
+};
+use wormhole_circuit::unspendable_account::UnspendableAccount;
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::utils::Digest;
+
+/// A group of constraints of the wormhole circuit that is checked as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintFamily {
+    /// The public nullifier is derived from the secret and transfer count.
+    Nullifier,
+    /// The unspendable account is derived from the secret.
+    UnspendableAccount,
+    /// The storage proof fits the circuit's limits on node count and size.
+    StorageProofShape,
+    /// Each storage proof node hashes to the child hash of its parent, starting at the root.
+    StorageProofPath,
+    /// The leaf node holds the hash of the transfer to the unspendable account.
+    Leaf,
+}
+
+/// The outcome of checking one [`ConstraintFamily`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintCheck {
+    pub family: ConstraintFamily,
+    pub passed: bool,
+    /// The code of the structured error the check failed with, see
+    /// [`wormhole_circuit::errors::error_catalog`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl ConstraintCheck {
+    fn new(family: ConstraintFamily, passed: bool, code: Option<&str>) -> Self {
+        Self {
+            family,
+            passed,
+            code: code.map(str::to_string),
+        }
+    }
+}
+
+/// The values of a group of targets, as canonical field elements.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedValues {
+    pub name: String,
+    pub values: Vec<u64>,
+}
+
+/// A witness with every secret dependent value removed, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactedWitness {
+    pub public_inputs: Vec<NamedValues>,
+    pub storage_proof_len: usize,
+    /// The checks in the order the circuit applies the constraints.
+    pub checks: Vec<ConstraintCheck>,
+}
+
+impl RedactedWitness {
+    /// Checks `inputs` against a circuit accepting storage proofs of the shape `params` and
+    /// redacts them.
+    pub fn from_inputs(inputs: &CircuitInputs, params: StorageProofParams) -> Self {
+        let public_inputs = inputs.public.to_field_elements();
+        let named = |name: &str, start: usize, end: usize| NamedValues {
+            name: name.to_string(),
+            values: public_inputs[start..end]
+                .iter()
+                .map(F::to_canonical_u64)
+                .collect(),
+        };
+
+        Self {
+            public_inputs: vec![
+                named("nullifier", NULLIFIER_START_INDEX, NULLIFIER_END_INDEX),
+                named("root_hash", ROOT_HASH_START_INDEX, ROOT_HASH_END_INDEX),
+                named(
+                    "funding_amount",
+                    FUNDING_AMOUNT_START_INDEX,
+                    FUNDING_AMOUNT_END_INDEX,
+                ),
+                named(
+                    "exit_account",
+                    EXIT_ACCOUNT_START_INDEX,
+                    EXIT_ACCOUNT_END_INDEX,
+                ),
+            ],
+            storage_proof_len: inputs.private.storage_proof.proof.len(),
+            checks: run_checks(inputs, params),
+        }
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Validates an imported export and returns the first family that failed, or `None` if the
+    /// inputs satisfy every family, in which case the proof failed for another reason, such as
+    /// a prover for a different circuit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a public input is missing or not canonical, or the export doesn't
+    /// check every family.
+    pub fn check(&self) -> anyhow::Result<Option<ConstraintFamily>> {
+        let mut public_inputs = Vec::new();
+        for name in ["nullifier", "root_hash", "funding_amount", "exit_account"] {
+            let named = self
+                .public_inputs
+                .iter()
+                .find(|named| named.name == name)
+                .ok_or_else(|| anyhow!("redacted witness has no {} public input", name))?;
+            for &value in &named.values {
+                if value >= F::ORDER {
+                    bail!("{} is not a canonical field element: {}", name, value);
+                }
+                public_inputs.push(F::from_canonical_u64(value));
+            }
+        }
+        PublicCircuitInputs::try_from_slice(&public_inputs)?;
+
+        let mut first_failure = None;
+        for family in FAMILIES {
+            let check = self
+                .checks
+                .iter()
+                .find(|check| check.family == family)
+                .ok_or_else(|| anyhow!("redacted witness has no {:?} check", family))?;
+            if !check.passed && first_failure.is_none() {
+                first_failure = Some(family);
+            }
+        }
+        Ok(first_failure)
+    }
+}
+
+const FAMILIES: [ConstraintFamily; 5] = [
+    ConstraintFamily::Nullifier,
+    ConstraintFamily::UnspendableAccount,
+    ConstraintFamily::StorageProofShape,
+    ConstraintFamily::StorageProofPath,
+    ConstraintFamily::Leaf,
+];
+
+fn run_checks(inputs: &CircuitInputs, params: StorageProofParams) -> Vec<ConstraintCheck> {
+    let secret = inputs.private.secret.as_slice();
+    let transfer_count = inputs.private.transfer_count;
+    let nullifier = inputs.public.nullifier.to_felts();
+    let nullifier_passed = [NullifierVersion::V1, NullifierVersion::V2]
+        .into_iter()
+        .any(|version| {
+            Nullifier::from_preimage_versioned(secret, transfer_count, version).hash == nullifier
+        });
+
+    let unspendable_account = UnspendableAccount::from_secret(&inputs.private.secret);
+    let unspendable_passed =
+        unspendable_account.account_id == inputs.private.unspendable_account.to_felts();
+
+    let processed = &inputs.private.storage_proof;
+    let shape = ProcessedStorageProof::with_params(
+        processed.proof.clone(),
+        processed.indices.clone(),
+        params,
+    )
+    .map(|_| ())
+    .map_err(|e| e.downcast_ref::<StorageProofError>().map(|e| e.code()));
+
+    let (path_passed, leaf_passed) = match (shape, StorageProof::try_from(inputs)) {
+        (Ok(()), Ok(storage_proof)) => check_path(&storage_proof, params),
+        _ => (false, false),
+    };
+
+    vec![
+        ConstraintCheck::new(ConstraintFamily::Nullifier, nullifier_passed, None),
+        ConstraintCheck::new(
+            ConstraintFamily::UnspendableAccount,
+            unspendable_passed,
+            None,
+        ),
+        ConstraintCheck::new(
+            ConstraintFamily::StorageProofShape,
+            shape.is_ok(),
+            shape.err().flatten(),
+        ),
+        ConstraintCheck::new(ConstraintFamily::StorageProofPath, path_passed, None),
+        ConstraintCheck::new(ConstraintFamily::Leaf, leaf_passed, None),
+    ]
+}
+
+/// Walks the storage proof like the circuit does, returning whether the path from the root and
+/// the leaf hash check out.
+fn check_path(storage_proof: &StorageProof, params: StorageProofParams) -> (bool, bool) {
+    let two_pow_32 = F::from_canonical_u64(1 << 32);
+    let mut prev_hash = storage_proof.root_hash.to_felts();
+    for (node, index) in storage_proof.proof.iter().zip(&storage_proof.indices) {
+        let mut padded_node = node.clone();
+        padded_node.resize(params.node_max_size_f, F::ZERO);
+        if PoseidonHash::hash_no_pad(&padded_node).elements != prev_hash {
+            return (false, false);
+        }
+
+        let start = index.to_canonical_u64() as usize;
+        let limbs = &padded_node[start..start + 8];
+        prev_hash = core::array::from_fn(|i| limbs[2 * i] + limbs[2 * i + 1] * two_pow_32);
+    }
+
+    // The leaf node may not hold the first nibble of the hash, so the circuit skips the first
+    // element.
+    let leaf_hash: Digest = storage_proof.leaf_inputs.hash();
+    (true, leaf_hash[1..] == prev_hash[1..])
+}
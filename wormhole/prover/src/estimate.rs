@@ -0,0 +1,137 @@
+//! Estimates of the resources needed to prove the wormhole circuit.
+//!
+//! Proving takes seconds on a desktop but can take minutes on a phone. [`estimate_proving`] times
+//! a small calibration proof on the current machine and extrapolates it to the wormhole circuit,
+//! so wallets can tell users how long proving will take, or delegate it to a proving service.
+//!
+//! The extrapolation assumes proving time grows like `n log n` in the number of rows `n`. Memory
+//! is not measured but computed from the size of the polynomials and Merkle trees the prover
+//! holds. Both are approximations and can be off by a factor of two.
+use std::mem::size_of;
+use std::time::Instant;
+
+use plonky2::{
+    hash::{hash_types::HashOut, poseidon::PoseidonHash},
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CommonCircuitData},
+    },
+};
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use zk_circuits_common::circuit::{C, D, F};
+
+/// The number of chained Poseidon hashes in the calibration circuit, one row each.
+const CALIBRATION_HASHES: usize = 1 << 10;
+
+/// Constants and sigmas, wires, permutation products and quotient polynomials.
+const NUM_COMMITMENTS: usize = 4;
+
+/// The estimated cost of a proof on the current machine.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// The number of rows of the circuit, a power of two.
+    pub rows: usize,
+    /// The proving time in seconds, not including building or loading the circuit.
+    pub approx_seconds: f64,
+    /// The peak heap usage of proving in bytes.
+    pub approx_memory: usize,
+}
+
+/// Estimates the cost of proving the wormhole circuit built with `config` on this machine.
+///
+/// This counts the gates of the circuit without building it, which is slow, and proves a small
+/// calibration circuit. It takes well under a second on a desktop. With the prover already
+/// loaded, [`estimate_proving_for`] gives the exact number of rows from its common data.
+///
+/// # Errors
+///
+/// Returns an error if the wormhole circuit can't be built with `config`, or the calibration
+/// proof fails.
+pub fn estimate_proving(config: CircuitConfig) -> anyhow::Result<Estimate> {
+    let num_gates = WormholeCircuit::try_new(config.clone())?.num_gates();
+    // Building adds gates, e.g. to hash the public inputs, and pads to a power of two.
+    let rows = (num_gates + 1).next_power_of_two();
+
+    let calibration = Calibration::run(&config)?;
+    Ok(calibration.extrapolate(&calibration.common, rows))
+}
+
+/// Estimates the cost of proving a built circuit with the given common data on this machine,
+/// e.g. `prover.circuit_data.common` of a [`crate::WormholeProver`].
+///
+/// # Errors
+///
+/// Returns an error if the calibration proof fails.
+pub fn estimate_proving_for(common: &CommonCircuitData<F, D>) -> anyhow::Result<Estimate> {
+    let calibration = Calibration::run(&common.config)?;
+    Ok(calibration.extrapolate(common, common.degree()))
+}
+
+/// A proof of a small circuit timed on this machine.
+struct Calibration {
+    common: CommonCircuitData<F, D>,
+    seconds: f64,
+}
+
+impl Calibration {
+    fn run(config: &CircuitConfig) -> anyhow::Result<Self> {
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let input = builder.add_virtual_hash_public_input();
+        let mut state = input;
+        for _ in 0..CALIBRATION_HASHES {
+            state = builder.hash_n_to_hash_no_pad::<PoseidonHash>(state.elements.to_vec());
+        }
+        builder.register_public_inputs(&state.elements);
+        let circuit_data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(input, HashOut::ZERO)?;
+        let start = Instant::now();
+        circuit_data.prove(pw)?;
+
+        Ok(Self {
+            common: circuit_data.common,
+            seconds: start.elapsed().as_secs_f64(),
+        })
+    }
+
+    fn extrapolate(&self, common: &CommonCircuitData<F, D>, rows: usize) -> Estimate {
+        let cost = |rows: usize| rows as f64 * rows.ilog2() as f64;
+        Estimate {
+            rows,
+            approx_seconds: self.seconds * cost(rows) / cost(self.common.degree()),
+            approx_memory: prover_memory(common, rows),
+        }
+    }
+}
+
+/// The size of the polynomials and Merkle trees the prover holds once it has committed to all of
+/// them, which dominate its memory use.
+pub(crate) fn prover_memory(common: &CommonCircuitData<F, D>, rows: usize) -> usize {
+    let config = &common.config;
+    let num_polynomials = config.num_wires
+        + common.num_constants
+        + config.num_routed_wires
+        + config.num_challenges * (1 + common.num_partial_products)
+        + config.num_challenges * common.quotient_degree_factor;
+    let lde_rows = rows << config.fri_config.rate_bits;
+
+    // Every polynomial is held both as coefficients and as evaluations over the LDE domain.
+    let polynomials = num_polynomials * (rows + lde_rows) * size_of::<F>();
+    // A Merkle tree holds about two digests per leaf.
+    let merkle_trees = NUM_COMMITMENTS * 2 * lde_rows * size_of::<HashOut<F>>();
+    polynomials + merkle_trees
+}
+
+/// The memory in bytes the system can give to new allocations without swapping, read from
+/// `MemAvailable` in `/proc/meminfo`. `None` on systems without it.
+pub fn available_memory() -> Option<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    // The value is given in kB, which the kernel means as KiB.
+    let kib: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    kib.checked_mul(1024)
+}
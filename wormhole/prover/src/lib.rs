@@ -4,7 +4,9 @@
 //! and generating a zero-knowledge proof using those inputs.
 //!
 //! The typical usage flow involves:
-//! 1. Initializing the prover (e.g., via [`WormholeProver::default`] or [`WormholeProver::new`]).
+//! 1. Initializing the prover, either from the circuit builder's artifacts with
+//!    [`WormholeProver::from_artifacts_dir`] or by building the circuit with
+//!    [`WormholeProver::build_fresh`].
 //! 2. Creating user inputs with [`CircuitInputs`].
 //! 3. Committing user inputs using [`WormholeProver::commit`].
 //! 4. Generating a proof using [`WormholeProver::prove`].
@@ -24,7 +26,7 @@
 //! // Create inputs. In practice, each input would be gathered from the real node.
 //! let inputs = CircuitInputs {
 //!     private: PrivateCircuitInputs {
-//!         secret: [1u8; 32],
+//!         secret: [1u8; 32].into(),
 //!         transfer_count: 0,
 //!         funding_account: [2u8; 32].try_into().unwrap(),
 //!         storage_proof: ProcessedStorageProof::new(vec![], vec![]).unwrap(),
@@ -48,56 +50,71 @@
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
-use anyhow::{anyhow, bail};
+#[cfg(feature = "std")]
+pub mod delegation;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod estimate;
+pub mod prelude;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+#[cfg(feature = "std")]
+pub mod scheduler;
+#[cfg(feature = "std")]
+pub mod self_test;
+pub mod witness;
+
+use anyhow::{anyhow, bail, Context};
 use plonky2::{
     iop::witness::PartialWitness,
     plonk::{
         circuit_data::{
             CircuitConfig, CommonCircuitData, ProverCircuitData, ProverOnlyCircuitData,
         },
-        config::PoseidonGoldilocksConfig,
+        config::{GenericConfig, PoseidonGoldilocksConfig},
         proof::ProofWithPublicInputs,
     },
     util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer},
 };
 #[cfg(feature = "std")]
-use std::{fs, path::Path};
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
 
+use wormhole_circuit::accumulator::NonMembershipProof;
 use wormhole_circuit::circuit::circuit_logic::{CircuitTargets, WormholeCircuit};
-use wormhole_circuit::codec::ByteCodec;
-use wormhole_circuit::nullifier::Nullifier;
-use wormhole_circuit::{inputs::CircuitInputs, substrate_account::SubstrateAccount};
-use wormhole_circuit::{storage_proof::StorageProof, unspendable_account::UnspendableAccount};
-use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use wormhole_circuit::envelope::{circuit_digest_to_bytes, ProofEnvelope};
+use wormhole_circuit::exit_format::ExitAccountFormat;
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use wormhole_circuit::relayer::RelayerFee;
+use wormhole_circuit::storage_proof::StorageProofParams;
+use wormhole_circuit::types::{ChainId, ProofContext, SecretBytes};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::BytesDigest;
 
-#[derive(Debug)]
-pub struct WormholeProver {
-    pub circuit_data: ProverCircuitData<F, C, D>,
-    partial_witness: PartialWitness<F>,
-    targets: Option<CircuitTargets>,
-}
+use crate::witness::{FillMode, VariantInputs};
 
-#[cfg(feature = "std")]
-impl Default for WormholeProver {
-    fn default() -> Self {
-        Self::new_from_files(
-            Path::new("generated-bins/prover.bin"),
-            Path::new("generated-bins/common.bin"),
-        )
-        .unwrap_or_else(|_| {
-            let wormhole_circuit = WormholeCircuit::default();
-            let partial_witness = PartialWitness::new();
+/// The file the circuit builder writes the prover only circuit data to.
+pub const PROVER_DATA_FILE_NAME: &str = "prover.bin";
 
-            let targets = Some(wormhole_circuit.targets());
-            let circuit_data = wormhole_circuit.build_prover();
+/// The file the circuit builder writes the common circuit data to.
+pub const COMMON_DATA_FILE_NAME: &str = "common.bin";
 
-            Self {
-                circuit_data,
-                partial_witness,
-                targets,
-            }
-        })
-    }
+/// Proves the wormhole circuit with the proving config `Cfg`.
+///
+/// `Cfg` defaults to the Poseidon Goldilocks config the published circuit binaries are built
+/// with. Provers for other configs are created with [`WormholeProver::with_generic_config`].
+#[derive(Debug)]
+pub struct WormholeProver<Cfg: GenericConfig<D, F = F> = C> {
+    pub circuit_data: ProverCircuitData<F, Cfg, D>,
+    partial_witness: PartialWitness<F>,
+    targets: Option<CircuitTargets>,
+    fill_mode: FillMode,
 }
 
 impl WormholeProver {
@@ -119,13 +136,14 @@ impl WormholeProver {
             &generator_serializer,
             &common_data,
         )
-        .map_err(|e| anyhow!("Failed to deserialize prover only data: {}", e));
+        .map_err(|_| "Failed to deserialize prover only data")?;
 
-        let wormhole_circuit = WormholeCircuit::new(common_data.config.clone());
+        let wormhole_circuit = WormholeCircuit::try_new(common_data.config.clone())
+            .map_err(|_| "Common circuit data has an unusable circuit config")?;
         let targets = Some(wormhole_circuit.targets());
 
         let circuit_data = ProverCircuitData {
-            prover_only: prover_only_data.unwrap(),
+            prover_only: prover_only_data,
             common: common_data,
         };
 
@@ -133,6 +151,7 @@ impl WormholeProver {
             circuit_data,
             partial_witness: PartialWitness::new(),
             targets,
+            fill_mode: FillMode::default(),
         })
     }
 
@@ -171,7 +190,8 @@ impl WormholeProver {
             )
         })?;
 
-        let wormhole_circuit = WormholeCircuit::new(common_data.config.clone());
+        let wormhole_circuit = WormholeCircuit::try_new(common_data.config.clone())
+            .with_context(|| format!("Unusable circuit config in {:?}", common_data_path))?;
         let targets = Some(wormhole_circuit.targets());
 
         let circuit_data = ProverCircuitData {
@@ -183,12 +203,238 @@ impl WormholeProver {
             circuit_data,
             partial_witness: PartialWitness::new(),
             targets,
+            fill_mode: FillMode::default(),
         })
     }
 
-    /// Creates a new [`WormholeProver`].
+    /// Loads a [`WormholeProver`] from the [`PROVER_DATA_FILE_NAME`] and
+    /// [`COMMON_DATA_FILE_NAME`] files the circuit builder writes to `dir`.
+    ///
+    /// This never falls back to building the circuit, which takes minutes. Use
+    /// [`WormholeProver::build_fresh`] for that.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file is missing or can't be deserialized.
+    #[cfg(feature = "std")]
+    pub fn from_artifacts_dir(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let prover_data_path = dir.join(PROVER_DATA_FILE_NAME);
+        let common_data_path = dir.join(COMMON_DATA_FILE_NAME);
+
+        for path in [&prover_data_path, &common_data_path] {
+            if !path.is_file() {
+                bail!(
+                    "wormhole circuit artifact {:?} not found, generate it with \
+                     `cargo run --release -p qp-wormhole-circuit-builder`",
+                    path
+                );
+            }
+        }
+
+        Self::new_from_files(&prover_data_path, &common_data_path)
+    }
+
+    /// Builds the wormhole circuit from scratch, which takes minutes. Prefer
+    /// [`WormholeProver::from_artifacts_dir`] outside of tests and tooling.
+    pub fn build_fresh(config: CircuitConfig) -> Self {
+        Self::new(config)
+    }
+
+    /// Creates a new [`WormholeProver`], building the circuit from scratch.
     pub fn new(config: CircuitConfig) -> Self {
-        let wormhole_circuit = WormholeCircuit::new(config);
+        Self::with_generic_config(config)
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit accepting both nullifier versions, version 2
+    /// from the transfer count `v2_cutover` on. See [`WormholeCircuit::with_nullifier_migration`].
+    pub fn with_nullifier_migration(config: CircuitConfig, v2_cutover: u64) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_nullifier_migration(config, v2_cutover);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit accepting storage proofs of the given shape.
+    /// See [`WormholeCircuit::with_storage_proof_params`].
+    pub fn with_storage_proof_params(config: CircuitConfig, params: StorageProofParams) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_storage_proof_params(config, params);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit binding proofs to a [`ProofContext`]. Inputs
+    /// are committed with [`WormholeProver::commit_with_context`]. See
+    /// [`WormholeCircuit::with_context`].
+    pub fn with_context(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_context(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit binding deposits to a destination
+    /// [`ChainId`]. Inputs are committed with [`WormholeProver::commit_for_chain`]. See
+    /// [`WormholeCircuit::with_destination_chain`].
+    pub fn with_destination_chain(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_destination_chain(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit paying a [`RelayerFee`] out of the funding
+    /// amount. Inputs are committed with [`WormholeProver::commit_with_relayer_fee`]. See
+    /// [`WormholeCircuit::with_relayer_fee`].
+    pub fn with_relayer_fee(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_relayer_fee(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit accepting EVM addresses as exit accounts.
+    /// Inputs are committed with [`WormholeProver::commit_with_exit_account_format`]. See
+    /// [`WormholeCircuit::with_exit_account_format`].
+    pub fn with_exit_account_format(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_exit_account_format(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit proving that the nullifier is not in an
+    /// accumulator of spent nullifiers of depth `depth`. Inputs are committed with
+    /// [`WormholeProver::commit_with_spent_nullifier_proof`]. See
+    /// [`WormholeCircuit::with_spent_nullifier_accumulator`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `depth` is not a supported accumulator depth.
+    pub fn with_spent_nullifier_accumulator(
+        config: CircuitConfig,
+        depth: usize,
+    ) -> anyhow::Result<Self> {
+        let wormhole_circuit = WormholeCircuit::with_spent_nullifier_accumulator(config, depth)?;
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Ok(Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        })
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit committing to the funding account. Inputs are
+    /// committed with [`WormholeProver::commit_with_funding_commitment`]. See
+    /// [`WormholeCircuit::with_funding_commitment`].
+    pub fn with_funding_commitment(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_funding_commitment(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit exposing a claim intent hash. Inputs are
+    /// committed with [`WormholeProver::commit_with_intent`]. See
+    /// [`WormholeCircuit::with_intent_hash`].
+    pub fn with_intent_hash(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_intent_hash(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Creates a [`WormholeProver`] for the circuit exposing only the digest of its public
+    /// inputs. Inputs are committed with [`WormholeProver::commit`]. See
+    /// [`WormholeCircuit::with_public_inputs_digest`].
+    pub fn with_public_inputs_digest(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::with_public_inputs_digest(config);
+        let targets = Some(wormhole_circuit.targets());
+        let circuit_data = wormhole_circuit.build_prover();
+
+        Self {
+            circuit_data,
+            partial_witness: PartialWitness::new(),
+            targets,
+            fill_mode: FillMode::default(),
+        }
+    }
+
+    /// Returns the digest of the verifier data matching this prover's circuit.
+    pub fn verifier_digest(&self) -> BytesDigest {
+        circuit_digest_to_bytes(self.circuit_data.prover_only.circuit_digest)
+    }
+
+    /// Prove the circuit with commited values and wrap the proof in a [`ProofEnvelope`] tagged
+    /// with this circuit's verifier digest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has not commited to any inputs.
+    pub fn prove_envelope(self) -> anyhow::Result<ProofEnvelope> {
+        let verifier_digest = self.verifier_digest();
+        let proof = self.prove()?;
+        Ok(ProofEnvelope::new(verifier_digest, &proof))
+    }
+}
+
+impl<Cfg: GenericConfig<D, F = F>> WormholeProver<Cfg> {
+    /// Creates a new [`WormholeProver`] that builds and proves the circuit with the proving
+    /// config `Cfg`.
+    pub fn with_generic_config(config: CircuitConfig) -> Self {
+        let wormhole_circuit = WormholeCircuit::<Cfg>::with_generic_config(config);
         let partial_witness = PartialWitness::new();
 
         let targets = Some(wormhole_circuit.targets());
@@ -198,41 +444,405 @@ impl WormholeProver {
             circuit_data,
             partial_witness,
             targets,
+            fill_mode: FillMode::default(),
         }
     }
 
+    /// Sets how the witness is filled when inputs are committed, e.g. [`FillMode::Hardened`] to
+    /// keep the filling time from revealing the shape of the storage proof to co-tenants. See
+    /// the [`witness`] module for what this does and doesn't hide.
+    pub fn with_fill_mode(mut self, fill_mode: FillMode) -> Self {
+        self.fill_mode = fill_mode;
+        self
+    }
+
+    /// Returns the public inputs a proof of `inputs` will have, without proving.
+    ///
+    /// The result equals [`PublicCircuitInputs::try_from`] applied to the proof, and
+    /// [`PublicCircuitInputs::to_field_elements`] gives the proof's public inputs in order. Wallets
+    /// can use this to show the nullifier and exit account before starting to prove. This doesn't
+    /// check that `inputs` are valid, so proving may still fail.
+    pub fn expected_public_inputs(&self, inputs: &CircuitInputs) -> PublicCircuitInputs {
+        let public_inputs = inputs.public.to_field_elements();
+        debug_assert_eq!(public_inputs.len(), self.circuit_data.common.num_public_inputs);
+        PublicCircuitInputs::try_from_slice(&public_inputs)
+            .expect("public inputs built from typed values are canonical")
+    }
+
     /// Commits the provided [`CircuitInputs`] to the circuit by filling relevant targets.
     ///
     /// # Errors
     ///
     /// Returns an error if the prover has already commited to inputs previously.
     pub fn commit(mut self, circuit_inputs: &CircuitInputs) -> anyhow::Result<Self> {
+        let _span = tracing::debug_span!("wormhole_commit").entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::None,
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
+
+    /// Like [`WormholeProver::commit`], but binds the proof to `context`. The prover must have been
+    /// created with [`WormholeProver::with_context`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously or its circuit
+    /// does not bind proofs to a context.
+    pub fn commit_with_context(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        context: &ProofContext,
+    ) -> anyhow::Result<Self> {
+        let _span = tracing::debug_span!("wormhole_commit", with_context = true).entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::Context(context),
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
+
+    /// Like [`WormholeProver::commit`], but claims the deposit on `chain_id`. The prover must
+    /// have been created with [`WormholeProver::with_destination_chain`], and the inputs derived
+    /// for `chain_id`, see [`VariantInputs::DestinationChain`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously or its circuit
+    /// does not bind deposits to a destination chain.
+    pub fn commit_for_chain(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        chain_id: &ChainId,
+    ) -> anyhow::Result<Self> {
+        let _span = tracing::debug_span!("wormhole_commit", chain_id = chain_id.get()).entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::DestinationChain(chain_id),
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
+
+    /// Like [`WormholeProver::commit`], but pays `relayer_fee` out of the funding amount. The
+    /// prover must have been created with [`WormholeProver::with_relayer_fee`], and `relayer_fee`
+    /// with the funding amount of `circuit_inputs`, see [`RelayerFee::new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously, its circuit
+    /// does not pay a relayer fee or the fee doesn't match the funding amount.
+    pub fn commit_with_relayer_fee(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        relayer_fee: &RelayerFee,
+    ) -> anyhow::Result<Self> {
+        let _span = tracing::debug_span!("wormhole_commit", with_relayer_fee = true).entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::RelayerFee(relayer_fee),
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
+
+    /// Like [`WormholeProver::commit`], but tags the exit account with `format`. The prover must
+    /// have been created with [`WormholeProver::with_exit_account_format`], and EVM exit accounts
+    /// encoded with [`ExitAccountFormat::evm_exit_account`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously, its circuit
+    /// does not accept EVM exit accounts or the exit account is not valid in `format`.
+    pub fn commit_with_exit_account_format(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        format: ExitAccountFormat,
+    ) -> anyhow::Result<Self> {
+        let _span =
+            tracing::debug_span!("wormhole_commit", exit_account_format = ?format).entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::ExitAccountFormat(format),
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
+
+    /// Like [`WormholeProver::commit`], but proves with `proof` that the nullifier is not spent.
+    /// The prover must have been created with
+    /// [`WormholeProver::with_spent_nullifier_accumulator`], and `proof` with
+    /// [`wormhole_circuit::accumulator::NullifierAccumulator::prove_non_membership`] against the
+    /// accumulator root the verifier expects.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously, its circuit
+    /// does not check for double spends or `proof` doesn't show the nullifier is unspent.
+    pub fn commit_with_spent_nullifier_proof(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        proof: &NonMembershipProof,
+    ) -> anyhow::Result<Self> {
+        let _span =
+            tracing::debug_span!("wormhole_commit", with_spent_nullifier_proof = true).entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::SpentNullifierProof(proof),
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
+
+    /// Like [`WormholeProver::commit`], but commits to the funding account with `blinding`. The
+    /// prover must have been created with [`WormholeProver::with_funding_commitment`].
+    ///
+    /// `blinding` must be random and kept with the funding account, which together form the
+    /// [`wormhole_circuit::funding_commitment::FundingAccountOpening`] revealed in a dispute.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously, or its circuit
+    /// does not commit to the funding account.
+    pub fn commit_with_funding_commitment(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        blinding: &SecretBytes,
+    ) -> anyhow::Result<Self> {
+        let _span =
+            tracing::debug_span!("wormhole_commit", with_funding_commitment = true).entered();
         let Some(targets) = self.targets.take() else {
             bail!("prover has already commited to inputs");
         };
 
-        let nullifier = Nullifier::from(circuit_inputs);
-        let storage_proof = StorageProof::try_from(circuit_inputs)?;
-        let unspendable_account = UnspendableAccount::from(circuit_inputs);
-        let exit_account =
-            SubstrateAccount::from_bytes(circuit_inputs.public.exit_account.as_slice())?;
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::FundingCommitment(blinding),
+            self.fill_mode,
+        )?;
+        Ok(self)
+    }
 
-        nullifier.fill_targets(&mut self.partial_witness, targets.nullifier)?;
-        unspendable_account.fill_targets(&mut self.partial_witness, targets.unspendable_account)?;
-        storage_proof.fill_targets(&mut self.partial_witness, targets.storage_proof)?;
-        exit_account.fill_targets(&mut self.partial_witness, targets.exit_account)?;
+    /// Like [`WormholeProver::commit`], but exposes the hash of the
+    /// [`wormhole_circuit::intent::ClaimIntent`] to claim the inputs with a funding amount of at
+    /// least `min_amount`. The prover must have been created with
+    /// [`WormholeProver::with_intent_hash`].
+    ///
+    /// The intent hash has to be registered on chain before the proof is submitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs previously, its circuit
+    /// does not expose a claim intent, or the funding amount is below `min_amount`.
+    pub fn commit_with_intent(
+        mut self,
+        circuit_inputs: &CircuitInputs,
+        min_amount: u128,
+    ) -> anyhow::Result<Self> {
+        let _span = tracing::debug_span!("wormhole_commit", with_intent = true).entered();
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        witness::fill_witness(
+            &mut self.partial_witness,
+            targets,
+            circuit_inputs,
+            VariantInputs::Intent { min_amount },
+            self.fill_mode,
+        )?;
         Ok(self)
     }
 
+    /// Prove the circuit with a witness filled and serialized by a client, see the [`witness`]
+    /// module for the trust model this implies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs or the witness does not
+    /// match this circuit.
+    pub fn prove_with_witness(
+        mut self,
+        witness_bytes: &[u8],
+    ) -> anyhow::Result<ProofWithPublicInputs<F, Cfg, D>> {
+        let Some(targets) = self.targets.take() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        self.partial_witness = {
+            let _span = tracing::debug_span!("wormhole_commit", from_witness = true).entered();
+            witness::witness_from_bytes(witness_bytes, &targets)?
+        };
+        self.prove()
+    }
+
+    /// Commits `circuit_inputs` and proves them like [`WormholeProver::commit`] followed by
+    /// [`WormholeProver::prove`], reporting the time and memory used by each phase. See the
+    /// [`profiling`] module for how memory is measured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if committing or proving fails.
+    #[cfg(feature = "profiling")]
+    pub fn prove_with_report(
+        self,
+        circuit_inputs: &CircuitInputs,
+    ) -> anyhow::Result<(ProofWithPublicInputs<F, Cfg, D>, profiling::ProverReport)> {
+        let mut report = profiling::ProverReport::default();
+        let prover = report.measure("commit", || self.commit(circuit_inputs))?;
+        let proof = report.measure("prove", || prover.prove())?;
+        Ok((proof, report))
+    }
+
+    /// The number of proofs [`WormholeProver::prove_many`] runs at once: one per available
+    /// thread, but no more than fit into the memory currently available, estimated like the
+    /// [`estimate`] module does. At least one proof always runs.
+    #[cfg(feature = "std")]
+    pub fn max_parallel_proofs(&self) -> usize {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let common = &self.circuit_data.common;
+        let proof_memory = estimate::prover_memory(common, common.degree()).max(1);
+        let by_memory =
+            estimate::available_memory().map_or(usize::MAX, |available| available / proof_memory);
+        threads.min(by_memory).max(1)
+    }
+
+    /// Proves independent `inputs` in parallel, e.g. the claims of many users batched by a
+    /// relayer, and returns their results in the order of `inputs`.
+    ///
+    /// The threads share the circuit data, and every proof fills its own witness like
+    /// [`WormholeProver::commit`] does, so the prover must be for the plain wormhole circuit.
+    /// At most [`WormholeProver::max_parallel_proofs`] proofs run at once. An input that fails
+    /// to prove, or panics while proving, doesn't affect the others.
+    ///
+    /// Every result is an error if the prover has already commited to inputs.
+    #[cfg(feature = "std")]
+    pub fn prove_many(
+        &self,
+        inputs: Vec<CircuitInputs>,
+    ) -> Vec<anyhow::Result<ProofWithPublicInputs<F, Cfg, D>>> {
+        let Some(targets) = &self.targets else {
+            return inputs
+                .iter()
+                .map(|_| Err(anyhow!("prover has already commited to inputs")))
+                .collect();
+        };
+        let num_workers = self.max_parallel_proofs().min(inputs.len());
+        let _span = tracing::info_span!("wormhole_prove_many", proofs = inputs.len(), num_workers)
+            .entered();
+
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<_> = inputs.iter().map(|_| None).collect();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..num_workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut proven = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(circuit_inputs) = inputs.get(i) else {
+                                return proven;
+                            };
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                                self.prove_one(targets, circuit_inputs)
+                            }))
+                            .unwrap_or_else(|_| Err(anyhow!("proving panicked")));
+                            proven.push((i, result));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                let proven = worker.join().expect("proving panics are caught");
+                for (i, result) in proven {
+                    results[i] = Some(result);
+                }
+            }
+        });
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every input is proven by a worker"))
+            .collect()
+    }
+
+    /// Proves `circuit_inputs` with a fresh witness, leaving the prover untouched.
+    #[cfg(feature = "std")]
+    fn prove_one(
+        &self,
+        targets: &CircuitTargets,
+        circuit_inputs: &CircuitInputs,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, Cfg, D>> {
+        let mut partial_witness = PartialWitness::new();
+        witness::fill_witness(
+            &mut partial_witness,
+            targets.clone(),
+            circuit_inputs,
+            VariantInputs::None,
+            self.fill_mode,
+        )?;
+        self.circuit_data
+            .prove(partial_witness)
+            .map_err(|e| anyhow!("Failed to prove: {}", e))
+    }
+
     /// Prove the circuit with commited values. It's necessary to call [`WormholeProver::commit`]
     /// before running this function.
     ///
     /// # Errors
     ///
     /// Returns an error if the prover has not commited to any inputs.
-    pub fn prove(self) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
-        self.circuit_data
+    pub fn prove(self) -> anyhow::Result<ProofWithPublicInputs<F, Cfg, D>> {
+        let _span = tracing::info_span!("wormhole_prove").entered();
+        let proof = self
+            .circuit_data
             .prove(self.partial_witness)
-            .map_err(|e| anyhow!("Failed to prove: {}", e))
+            .map_err(|e| anyhow!("Failed to prove: {}", e))?;
+        tracing::debug!(
+            num_public_inputs = proof.public_inputs.len(),
+            "proof generated"
+        );
+        Ok(proof)
     }
 }
@@ -0,0 +1,8 @@
+//! Everything needed to generate wormhole proofs, including the plonky2 types of
+//! [`zk_circuits_common::prelude`].
+pub use wormhole_circuit::inputs::{CircuitInputs, PrivateCircuitInputs, PublicCircuitInputs};
+pub use zk_circuits_common::prelude::*;
+
+#[cfg(feature = "std")]
+pub use crate::estimate::{estimate_proving, Estimate};
+pub use crate::WormholeProver;
@@ -0,0 +1,153 @@
+//! Memory profiling of the prover.
+//!
+//! Enabled with the `profiling` feature. [`CountingAllocator`] wraps the system allocator and
+//! counts allocations and live heap bytes in process wide counters. A binary that wants memory
+//! numbers installs it as its global allocator:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: qp_wormhole_prover::profiling::CountingAllocator =
+//!     qp_wormhole_prover::profiling::CountingAllocator;
+//! ```
+//!
+//! [`WormholeProver::prove_with_report`](crate::WormholeProver::prove_with_report) then returns a
+//! [`ProverReport`] with the time, allocations and peak heap usage of each proving phase. Without
+//! the allocator installed, the memory numbers are all zero.
+//!
+//! The counters are shared by all threads, so the numbers of a phase include allocations made
+//! concurrently by other work in the process.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator counting the allocations made through the system allocator.
+pub struct CountingAllocator;
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+fn record_dealloc(size: usize) {
+    LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// The resources used by one phase of proving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseReport {
+    pub name: &'static str,
+    pub duration: Duration,
+    /// The number of allocations, counting a reallocation as one.
+    pub allocations: u64,
+    /// The total number of bytes allocated, including memory freed during the phase.
+    pub allocated_bytes: u64,
+    /// The highest number of live heap bytes during the phase.
+    pub peak_live_bytes: usize,
+}
+
+/// The resources used by each phase of a proof, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProverReport {
+    pub phases: Vec<PhaseReport>,
+}
+
+impl ProverReport {
+    /// The highest number of live heap bytes across all phases.
+    pub fn peak_live_bytes(&self) -> usize {
+        self.phases
+            .iter()
+            .map(|phase| phase.peak_live_bytes)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The total time spent in all phases.
+    pub fn duration(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+
+    /// Runs `f` as the phase `name` and appends its report.
+    pub fn measure<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let (result, phase) = measure(name, f);
+        self.phases.push(phase);
+        result
+    }
+}
+
+/// Runs `f` and reports the resources it used.
+pub fn measure<T>(name: &'static str, f: impl FnOnce() -> T) -> (T, PhaseReport) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    // Start tracking the peak of this phase from the current heap size.
+    PEAK_LIVE_BYTES.store(LIVE_BYTES.load(Ordering::Relaxed), Ordering::Relaxed);
+    let start = Instant::now();
+
+    let result = f();
+
+    let phase = PhaseReport {
+        name,
+        duration: start.elapsed(),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+        peak_live_bytes: PEAK_LIVE_BYTES.load(Ordering::Relaxed),
+    };
+    (result, phase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn measure_counts_allocations() {
+        const LEN: usize = 1 << 20;
+
+        let (len, phase) = measure("alloc", || vec![1u8; LEN].len());
+        assert_eq!(len, LEN);
+        assert!(phase.allocations >= 1);
+        assert!(phase.allocated_bytes >= LEN as u64);
+        assert!(phase.peak_live_bytes >= LEN);
+    }
+}
@@ -0,0 +1,356 @@
+//! Scheduling proof jobs for a proving service.
+//!
+//! A [`ProverScheduler`] owns one loaded [`WormholeProver`] and proves the jobs submitted to it on
+//! a pool of worker threads, which share the circuit data. Queued jobs are started by
+//! [`ProofJob::priority`], highest first, then by earliest [`ProofJob::deadline`], then in the
+//! order they were submitted. A job whose deadline passes before it is started is expired instead
+//! of proven, a job that has started is always finished.
+//!
+//! Proving holds its polynomials and Merkle trees for the whole proof, so running too many proofs
+//! at once exhausts the machine. Besides [`SchedulerConfig::max_concurrent`], a job is only
+//! started if the memory of the running proofs, estimated like [`crate::estimate`] does, stays
+//! within [`SchedulerConfig::memory_budget`].
+//!
+//! Jobs are proven with [`WormholeProver::commit`], so the prover must be for the plain wormhole
+//! circuit, e.g. loaded with [`WormholeProver::from_artifacts_dir`].
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail};
+use plonky2::{
+    iop::witness::PartialWitness,
+    plonk::{circuit_data::ProverCircuitData, proof::ProofWithPublicInputs},
+};
+use wormhole_circuit::circuit::circuit_logic::CircuitTargets;
+use wormhole_circuit::inputs::CircuitInputs;
+use zk_circuits_common::circuit::{C, D, F};
+
+use crate::estimate::prover_memory;
+use crate::witness::{self, FillMode};
+use crate::WormholeProver;
+
+/// The limits of a [`ProverScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedulerConfig {
+    /// The maximum number of proofs proven at once, one worker thread each.
+    pub max_concurrent: usize,
+    /// The memory in bytes the running proofs may use together.
+    pub memory_budget: usize,
+}
+
+/// A proof to be proven by a [`ProverScheduler`].
+#[derive(Debug, Clone)]
+pub struct ProofJob {
+    pub inputs: CircuitInputs,
+    /// Jobs with a higher priority are started first.
+    pub priority: u32,
+    /// When the job expires if it hasn't been started. Jobs without a deadline never expire.
+    pub deadline: Option<Instant>,
+}
+
+/// Identifies a job submitted to a [`ProverScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+/// The state of a job, see [`ProverScheduler::status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting to be started, after `position` other queued jobs.
+    Queued { position: usize },
+    Running,
+    /// Proven. The proof is returned by [`ProverScheduler::wait`].
+    Completed,
+    /// Proving failed with the given error.
+    Failed(String),
+    /// The deadline passed before the job was started.
+    Expired,
+}
+
+/// Queues proof jobs and proves them on worker threads, see the [module documentation](self).
+///
+/// Dropping the scheduler discards the queued jobs and waits for the running ones to finish.
+pub struct ProverScheduler {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ProverScheduler {
+    /// Starts `config.max_concurrent` workers proving with `prover`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `prover` has already committed to inputs, no proofs may run at once,
+    /// or a single proof doesn't fit the memory budget.
+    pub fn new(prover: WormholeProver, config: SchedulerConfig) -> anyhow::Result<Self> {
+        let Some(targets) = prover.targets else {
+            bail!("prover has already commited to inputs");
+        };
+        if config.max_concurrent == 0 {
+            bail!("the scheduler must run at least one proof at once");
+        }
+        let common = &prover.circuit_data.common;
+        let proof_memory = prover_memory(common, common.degree());
+        if proof_memory > config.memory_budget {
+            bail!(
+                "memory budget of {} bytes doesn't fit a single proof, which takes about {} bytes",
+                config.memory_budget,
+                proof_memory
+            );
+        }
+
+        let shared = Arc::new(Shared {
+            circuit_data: prover.circuit_data,
+            targets,
+            fill_mode: prover.fill_mode,
+            config,
+            proof_memory,
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        });
+        let workers = (0..config.max_concurrent)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.run_worker())
+            })
+            .collect();
+
+        Ok(Self { shared, workers })
+    }
+
+    /// The estimated memory in bytes of one running proof, which is charged against the memory
+    /// budget.
+    pub fn proof_memory(&self) -> usize {
+        self.shared.proof_memory
+    }
+
+    /// Queues `job` and returns its id.
+    pub fn submit(&self, job: ProofJob) -> JobId {
+        let mut state = self.shared.lock();
+        let id = JobId(state.next_id);
+        state.next_id += 1;
+        tracing::debug!(job = id.0, priority = job.priority, "proof job queued");
+        state.jobs.insert(id, JobState::Queued(job));
+        drop(state);
+
+        self.shared.changed.notify_all();
+        id
+    }
+
+    /// The state of job `id`, or `None` if there is no such job or its result has already been
+    /// taken by [`ProverScheduler::wait`].
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        let mut state = self.shared.lock();
+        state.expire_overdue(Instant::now());
+
+        let status = match state.jobs.get(&id)? {
+            JobState::Queued(_) => {
+                let position = state.queue().iter().position(|&queued| queued == id);
+                JobStatus::Queued {
+                    position: position.expect("queued job is in the queue"),
+                }
+            }
+            JobState::Running => JobStatus::Running,
+            JobState::Completed(_) => JobStatus::Completed,
+            JobState::Failed(error) => JobStatus::Failed(error.clone()),
+            JobState::Expired => JobStatus::Expired,
+        };
+        Some(status)
+    }
+
+    /// Blocks until job `id` is finished and takes its result, so its status is gone afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such job, its result has already been taken, or it failed
+    /// or expired.
+    pub fn wait(&self, id: JobId) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let mut state = self.shared.lock();
+        loop {
+            state.expire_overdue(Instant::now());
+            let deadline = match state.jobs.get(&id) {
+                None => bail!("unknown proof job {}", id.0),
+                Some(JobState::Queued(job)) => job.deadline,
+                Some(JobState::Running) => None,
+                Some(_) => break,
+            };
+            state = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    self.shared.wait_timeout(state, timeout)
+                }
+                None => self.shared.wait(state),
+            };
+        }
+
+        match state.jobs.remove(&id).expect("finished job is present") {
+            JobState::Completed(proof) => Ok(proof),
+            JobState::Failed(error) => Err(anyhow!("proof job {} failed: {}", id.0, error)),
+            JobState::Expired => bail!("proof job {} expired before it was started", id.0),
+            JobState::Queued(_) | JobState::Running => unreachable!("job is finished"),
+        }
+    }
+}
+
+impl Drop for ProverScheduler {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+enum JobState {
+    Queued(ProofJob),
+    Running,
+    Completed(ProofWithPublicInputs<F, C, D>),
+    Failed(String),
+    Expired,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: HashMap<JobId, JobState>,
+    next_id: u64,
+    running: usize,
+    shutdown: bool,
+}
+
+impl State {
+    fn expire_overdue(&mut self, now: Instant) {
+        for (id, job) in self.jobs.iter_mut() {
+            let JobState::Queued(ProofJob {
+                deadline: Some(deadline),
+                ..
+            }) = job
+            else {
+                continue;
+            };
+            if *deadline <= now {
+                tracing::debug!(job = id.0, "proof job expired");
+                *job = JobState::Expired;
+            }
+        }
+    }
+
+    /// The queued jobs in the order they are started.
+    fn queue(&self) -> Vec<JobId> {
+        let mut queue: Vec<_> = self
+            .jobs
+            .iter()
+            .filter_map(|(&id, job)| match job {
+                // Jobs with a deadline sort before those without one.
+                JobState::Queued(job) => {
+                    Some((Reverse(job.priority), job.deadline.is_none(), job.deadline, id))
+                }
+                _ => None,
+            })
+            .collect();
+        queue.sort();
+        queue.into_iter().map(|(_, _, _, id)| id).collect()
+    }
+}
+
+struct Shared {
+    circuit_data: ProverCircuitData<F, C, D>,
+    targets: CircuitTargets,
+    fill_mode: FillMode,
+    config: SchedulerConfig,
+    proof_memory: usize,
+    state: Mutex<State>,
+    /// Notified whenever a job is queued or finished, or the scheduler shuts down.
+    changed: Condvar,
+}
+
+impl Shared {
+    // No code panics while holding the lock, proving panics are caught outside of it.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait_timeout<'a>(
+        &self,
+        state: MutexGuard<'a, State>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, State> {
+        self.changed
+            .wait_timeout(state, timeout)
+            .unwrap_or_else(PoisonError::into_inner)
+            .0
+    }
+
+    /// Whether another proof can be started without exceeding the limits.
+    fn admits(&self, state: &State) -> bool {
+        state.running < self.config.max_concurrent
+            && (state.running + 1) * self.proof_memory <= self.config.memory_budget
+    }
+
+    fn run_worker(&self) {
+        loop {
+            let (id, job) = {
+                let mut state = self.lock();
+                loop {
+                    if state.shutdown {
+                        return;
+                    }
+                    state.expire_overdue(Instant::now());
+                    if self.admits(&state) {
+                        if let Some(&id) = state.queue().first() {
+                            let Some(JobState::Queued(job)) =
+                                state.jobs.insert(id, JobState::Running)
+                            else {
+                                unreachable!("job is queued");
+                            };
+                            state.running += 1;
+                            break (id, job);
+                        }
+                    }
+                    state = self.wait(state);
+                }
+            };
+
+            tracing::debug!(job = id.0, "proof job started");
+            let result = panic::catch_unwind(AssertUnwindSafe(|| self.prove(&job.inputs)))
+                .unwrap_or_else(|_| Err(anyhow!("proving panicked")));
+
+            let mut state = self.lock();
+            state.running -= 1;
+            let job_state = match result {
+                Ok(proof) => JobState::Completed(proof),
+                Err(e) => {
+                    tracing::warn!(job = id.0, error = %e, "proof job failed");
+                    JobState::Failed(format!("{:#}", e))
+                }
+            };
+            state.jobs.insert(id, job_state);
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    fn prove(&self, inputs: &CircuitInputs) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let _span = tracing::info_span!("scheduled_prove").entered();
+        let mut pw = PartialWitness::new();
+        witness::fill_witness(
+            &mut pw,
+            self.targets.clone(),
+            inputs,
+            witness::VariantInputs::None,
+            self.fill_mode,
+        )?;
+        self.circuit_data
+            .prove(pw)
+            .map_err(|e| anyhow!("Failed to prove: {}", e))
+    }
+}
@@ -0,0 +1,221 @@
+//! A readiness check for provers.
+//!
+//! Corrupted artifacts or a plonky2 build that serializes differently usually still load, and only
+//! fail once the first user proof is generated. [`WormholeProver::self_test`] proves built-in
+//! inputs with the loaded circuit data and verifies the proof against the same data, so services
+//! can run it before accepting jobs.
+//!
+//! The inputs are derived from a fixed secret and a single node storage proof holding the leaf,
+//! so they don't depend on chain state and are valid for any storage proof shape.
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, ensure};
+use plonky2::{
+    field::types::Field,
+    hash::poseidon::PoseidonHash,
+    iop::witness::PartialWitness,
+    plonk::{
+        circuit_data::{VerifierCircuitData, VerifierOnlyCircuitData},
+        config::{GenericConfig, Hasher},
+    },
+};
+use wormhole_circuit::accumulator::NullifierAccumulator;
+use wormhole_circuit::exit_format::ExitAccountFormat;
+use wormhole_circuit::inputs::{CircuitInputs, PrivateCircuitInputs, PublicCircuitInputs};
+use wormhole_circuit::nullifier::Nullifier;
+use wormhole_circuit::relayer::RelayerFee;
+use wormhole_circuit::storage_proof::leaf::LeafInputs;
+use wormhole_circuit::storage_proof::{ProcessedStorageProof, StorageProofParams};
+use wormhole_circuit::types::{
+    AccountId, ChainId, NullifierHash, ProofContext, RootHash, SecretBytes,
+};
+use wormhole_circuit::unspendable_account::UnspendableAccount;
+use zk_circuits_common::circuit::{D, F};
+use zk_circuits_common::utils::{digest_felts_to_bytes, injective_bytes_to_felts};
+
+use crate::witness::{self, VariantInputs};
+use crate::WormholeProver;
+
+const SELF_TEST_SECRET: [u8; 32] = [0x5e; 32];
+const SELF_TEST_TRANSFER_COUNT: u64 = 1;
+const SELF_TEST_FUNDING_ACCOUNT: [u8; 32] = [1u8; 32];
+const SELF_TEST_FUNDING_AMOUNT: u128 = 1_000;
+const SELF_TEST_EXIT_ACCOUNT: [u8; 32] = [2u8; 32];
+/// The chain the deposit is claimed on, for circuits bound to a destination chain.
+const SELF_TEST_CHAIN_ID: u64 = 0;
+
+/// The time each step of a [`WormholeProver::self_test`] took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub fill: Duration,
+    pub prove: Duration,
+    pub verify: Duration,
+}
+
+impl SelfTestReport {
+    pub fn total(&self) -> Duration {
+        self.fill + self.prove + self.verify
+    }
+}
+
+/// The inputs proven by [`WormholeProver::self_test`], for a circuit accepting storage proofs of
+/// the shape `params`, and bound to `chain_id` if it is set.
+///
+/// # Errors
+///
+/// Returns an error if `params` can't hold a single node with a hash.
+pub fn self_test_inputs(
+    params: StorageProofParams,
+    chain_id: Option<ChainId>,
+) -> anyhow::Result<CircuitInputs> {
+    let (nullifier, unspendable_account) = match chain_id {
+        Some(chain_id) => (
+            Nullifier::from_preimage_for_chain(
+                &SELF_TEST_SECRET,
+                SELF_TEST_TRANSFER_COUNT,
+                chain_id,
+            ),
+            UnspendableAccount::from_secret_for_chain(&SELF_TEST_SECRET, chain_id),
+        ),
+        None => (
+            Nullifier::from_preimage(&SELF_TEST_SECRET, SELF_TEST_TRANSFER_COUNT),
+            UnspendableAccount::from_secret(&SELF_TEST_SECRET),
+        ),
+    };
+    let unspendable_account = AccountId::from(unspendable_account.account_id);
+    let funding_account = AccountId::try_from(SELF_TEST_FUNDING_ACCOUNT)?;
+
+    // A single node holding the leaf hash, so it is both the root and the leaf.
+    let leaf_inputs = LeafInputs::new(
+        SELF_TEST_TRANSFER_COUNT,
+        funding_account,
+        unspendable_account,
+        SELF_TEST_FUNDING_AMOUNT,
+    )?;
+    let node = digest_felts_to_bytes(leaf_inputs.hash()).to_vec();
+    let mut node_felts = injective_bytes_to_felts(&node);
+    node_felts.resize(params.node_max_size_f, F::ZERO);
+    let root_hash = RootHash::from(PoseidonHash::hash_no_pad(&node_felts).elements);
+    let storage_proof = ProcessedStorageProof::with_params(vec![node], vec![0], params)?;
+
+    Ok(CircuitInputs {
+        private: PrivateCircuitInputs {
+            secret: SELF_TEST_SECRET.into(),
+            storage_proof,
+            transfer_count: SELF_TEST_TRANSFER_COUNT,
+            funding_account,
+            unspendable_account,
+        },
+        public: PublicCircuitInputs {
+            funding_amount: SELF_TEST_FUNDING_AMOUNT,
+            nullifier: NullifierHash::from(nullifier.hash),
+            root_hash,
+            exit_account: AccountId::try_from(SELF_TEST_EXIT_ACCOUNT)?,
+        },
+    })
+}
+
+impl<Cfg: GenericConfig<D, F = F>> WormholeProver<Cfg> {
+    /// Proves the [`self_test_inputs`] with this prover's circuit data and verifies the proof,
+    /// without committing the prover to any inputs. See the [module documentation](self).
+    ///
+    /// Circuits with optional public inputs are filled with a zero context, no relayer fee, a
+    /// substrate exit account, an empty accumulator of spent nullifiers and a zero blinding of
+    /// the funding commitment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prover has already commited to inputs, or proving or verifying
+    /// fails, which means the circuit data is corrupted or was built for a different circuit.
+    pub fn self_test(&self) -> anyhow::Result<SelfTestReport> {
+        let _span = tracing::info_span!("wormhole_self_test").entered();
+        let Some(targets) = self.targets.clone() else {
+            bail!("prover has already commited to inputs");
+        };
+
+        let start = Instant::now();
+        let chain_id = targets
+            .destination_chain_id
+            .map(|_| ChainId::new(SELF_TEST_CHAIN_ID));
+        let inputs = self_test_inputs(targets.storage_proof.params(), chain_id)?;
+        let digest_only = targets.public_inputs_digest.is_some();
+        let context = ProofContext::from([0u8; 32]);
+        let relayer_fee = targets
+            .relayer_fee
+            .as_ref()
+            .map(|_| {
+                let funding_amount = inputs.public.funding_amount;
+                RelayerFee::new(inputs.public.exit_account, 0, funding_amount)
+            })
+            .transpose()?;
+        let spent_nullifier_proof = targets
+            .spent_nullifiers
+            .as_ref()
+            .map(|spent_nullifiers| {
+                let accumulator = NullifierAccumulator::new(spent_nullifiers.depth())?;
+                accumulator.prove_non_membership(&inputs.public.nullifier)
+            })
+            .transpose()?;
+        let blinding = SecretBytes::default();
+        let variant = if let Some(chain_id) = &chain_id {
+            VariantInputs::DestinationChain(chain_id)
+        } else if targets.context.is_some() {
+            VariantInputs::Context(&context)
+        } else if let Some(relayer_fee) = &relayer_fee {
+            VariantInputs::RelayerFee(relayer_fee)
+        } else if targets.exit_account_format.is_some() {
+            VariantInputs::ExitAccountFormat(ExitAccountFormat::Substrate)
+        } else if let Some(proof) = &spent_nullifier_proof {
+            VariantInputs::SpentNullifierProof(proof)
+        } else if targets.funding_commitment.is_some() {
+            VariantInputs::FundingCommitment(&blinding)
+        } else if targets.intent.is_some() {
+            VariantInputs::Intent { min_amount: 0 }
+        } else {
+            VariantInputs::None
+        };
+        let mut pw = PartialWitness::new();
+        witness::fill_witness(&mut pw, targets, &inputs, variant, self.fill_mode)?;
+        let fill = start.elapsed();
+
+        let start = Instant::now();
+        let proof = self
+            .circuit_data
+            .prove(pw)
+            .map_err(|e| anyhow!("self test failed to prove: {}", e))?;
+        let prove = start.elapsed();
+
+        // Verify against the commitments of the loaded prover data, whose digest is the one
+        // published verifiers check.
+        let start = Instant::now();
+        let prover_only = &self.circuit_data.prover_only;
+        let constants_sigmas_cap = &prover_only.constants_sigmas_commitment.merkle_tree.cap;
+        let verifier_data = VerifierCircuitData::<F, Cfg, D> {
+            verifier_only: VerifierOnlyCircuitData {
+                constants_sigmas_cap: constants_sigmas_cap.clone(),
+                circuit_digest: prover_only.circuit_digest,
+            },
+            common: self.circuit_data.common.clone(),
+        };
+        verifier_data
+            .verify(proof.clone())
+            .map_err(|e| anyhow!("self test proof failed to verify: {}", e))?;
+        let verify = start.elapsed();
+
+        let public_inputs_match = if digest_only {
+            proof.public_inputs == inputs.public.digest()
+        } else {
+            let expected = inputs.public.to_field_elements();
+            proof.public_inputs.starts_with(&expected)
+        };
+        ensure!(public_inputs_match, "self test proof has unexpected public inputs");
+
+        let report = SelfTestReport {
+            fill,
+            prove,
+            verify,
+        };
+        tracing::info!(total_ms = report.total().as_millis() as u64, "self test passed");
+        Ok(report)
+    }
+}
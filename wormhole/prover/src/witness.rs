@@ -0,0 +1,335 @@
+//! Delegated proving.
+//!
+//! Building [`ProverCircuitData`] and running the prover is the expensive part of generating a
+//! wormhole proof. A lightweight client can instead fill the witness from its [`CircuitInputs`]
+//! locally with [`witness_from_inputs`] and send the serialized values to a proving server that
+//! owns the prover data. The client still lays out the circuit and adds all of its constraints to
+//! get the targets, but skips building the prover data from them. The server proves with
+//! [`WormholeProver::prove_with_witness`](crate::WormholeProver::prove_with_witness).
+//!
+//! # Trust model
+//!
+//! The serialized witness contains every private input of the circuit, including the secret
+//! the nullifier and unspendable account are derived from. Whoever holds it can generate proofs
+//! spending the same funds, and plonky2 offers no way to outsource the FFT work without handing
+//! over the witness. This mode must therefore only be used with a proving server inside the
+//! client's trust boundary (e.g. the user's own hardware), over an authenticated and encrypted
+//! channel. Clients that cannot trust the server must prove locally.
+//!
+//! # Hardened filling
+//!
+//! Filling the witness normally does work proportional to the length of the storage proof and
+//! the sizes of its nodes, so a co-tenant timing the prover learns the shape of the proof.
+//! With [`FillMode::Hardened`], every node slot of the circuit is converted and filled in full,
+//! see [`StorageProof::new_hardened`], making the filling time depend on the circuit only. Amounts
+//! are always split into the same number of limbs, so their magnitude doesn't show in either
+//! mode.
+//!
+//! The hardening is best effort. The remaining leakage includes branches and memory accesses that
+//! select between proof data and padding, the allocator, the parsing and validation of the
+//! inputs before they reach the prover, and the proving itself, which this crate doesn't
+//! control. Hardened filling is slower, but still negligible next to proving.
+//!
+//! # Format
+//!
+//! A little endian `u32` holding the number of values, followed by one little endian `u64` per
+//! target in the order given by [`CircuitTargets::input_targets`].
+//!
+//! [`ProverCircuitData`]: plonky2::plonk::circuit_data::ProverCircuitData
+use anyhow::{anyhow, bail};
+use plonky2::{
+    field::types::{Field, Field64, PrimeField64},
+    iop::witness::{PartialWitness, Witness, WitnessWrite},
+    plonk::circuit_data::CircuitConfig,
+};
+use wormhole_circuit::accumulator::NonMembershipProof;
+use wormhole_circuit::circuit::circuit_logic::{CircuitTargets, WormholeCircuit};
+use wormhole_circuit::codec::ByteCodec;
+use wormhole_circuit::exit_format::ExitAccountFormat;
+use wormhole_circuit::funding_commitment::FundingAccountOpening;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::intent::ClaimIntent;
+use wormhole_circuit::nullifier::Nullifier;
+use wormhole_circuit::relayer::RelayerFee;
+use wormhole_circuit::storage_proof::leaf::{
+    check_transfer_count, LeafInputs, TRANSFER_COUNT_SEARCH_WINDOW,
+};
+use wormhole_circuit::storage_proof::StorageProof;
+use wormhole_circuit::substrate_account::SubstrateAccount;
+use wormhole_circuit::types::{ChainId, ProofContext, SecretBytes};
+use wormhole_circuit::unspendable_account::UnspendableAccount;
+use zk_circuits_common::circuit::{CircuitFragment, F};
+use zk_circuits_common::utils::felts_to_hashout;
+
+const LEN_PREFIX_SIZE: usize = 4;
+const VALUE_SIZE: usize = 8;
+
+/// How the witness is filled, see the [module documentation](self#hardened-filling).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillMode {
+    /// Only the parts of the storage proof that are present are processed.
+    #[default]
+    Fast,
+    /// The storage proof is processed as if it had the largest shape the circuit accepts.
+    Hardened,
+}
+
+/// The values of the optional public inputs of a circuit variant, see [`fill_witness`]. Each
+/// variant but [`Self::None`] belongs to the [`CircuitTargets`] constructor of the same name.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum VariantInputs<'a> {
+    /// The circuit has no optional public inputs.
+    #[default]
+    None,
+    /// Binds the proof to the context.
+    Context(&'a ProofContext),
+    /// Claims the deposit on the chain. The nullifier and unspendable account of the inputs must
+    /// have been derived for it, see [`Nullifier::from_preimage_for_chain`] and
+    /// [`UnspendableAccount::from_secret_for_chain`], or proving fails.
+    DestinationChain(&'a ChainId),
+    /// Pays the fee out of the funding amount. It must have been created for the funding amount
+    /// of the inputs.
+    RelayerFee(&'a RelayerFee),
+    /// The format the exit account of the inputs is in.
+    ExitAccountFormat(ExitAccountFormat),
+    /// Proves that the nullifier of the inputs is missing from an accumulator of spent
+    /// nullifiers.
+    SpentNullifierProof(&'a NonMembershipProof),
+    /// Commits to the funding account with the blinding. The funding account is later proven to
+    /// a designated party with the [`FundingAccountOpening`] of the funding account and blinding.
+    FundingCommitment(&'a SecretBytes),
+    /// Exposes the hash of the [`ClaimIntent`] to claim the inputs with a funding amount of at
+    /// least `min_amount`.
+    Intent { min_amount: u128 },
+}
+
+/// Fills `targets` from the provided [`CircuitInputs`], and the optional public inputs of the
+/// circuit variant from `variant`.
+///
+/// # Errors
+///
+/// Returns an error if `variant` doesn't match the optional public inputs of the circuit, or
+/// its values don't fit `circuit_inputs`: the exit account is not valid in the
+/// [`ExitAccountFormat`], see [`ExitAccountFormat::check`], the relayer fee and exit amount don't
+/// add up to the funding amount, the [`NonMembershipProof`] doesn't show the nullifier is
+/// unspent, see [`NonMembershipProof::verify`], or the funding amount is below the intent's
+/// minimum.
+pub fn fill_witness(
+    pw: &mut PartialWitness<F>,
+    mut targets: CircuitTargets,
+    circuit_inputs: &CircuitInputs,
+    variant: VariantInputs,
+    mode: FillMode,
+) -> anyhow::Result<()> {
+    // Take the targets of the provided variant, any optional targets left over are missing
+    // their inputs.
+    match variant {
+        VariantInputs::None => {}
+        VariantInputs::Context(context) => {
+            let Some(context_target) = targets.context.take() else {
+                bail!("the circuit does not bind proofs to a context");
+            };
+            pw.set_hash_target(context_target, felts_to_hashout(&context.to_felts()))?;
+        }
+        VariantInputs::DestinationChain(chain_id) => {
+            let Some(chain_id_targets) = targets.destination_chain_id.take() else {
+                bail!("the circuit does not bind deposits to a destination chain");
+            };
+            pw.set_target_arr(&chain_id_targets, &chain_id.to_felts())?;
+        }
+        VariantInputs::RelayerFee(relayer_fee) => {
+            let Some(relayer_fee_targets) = targets.relayer_fee.take() else {
+                bail!("the circuit does not pay a relayer fee");
+            };
+            let funding_amount = circuit_inputs.public.funding_amount;
+            if relayer_fee.fee.checked_add(relayer_fee.exit_amount) != Some(funding_amount) {
+                bail!(
+                    "relayer fee {} and exit amount {} don't add up to the funding amount {}",
+                    relayer_fee.fee,
+                    relayer_fee.exit_amount,
+                    funding_amount
+                );
+            }
+            relayer_fee.fill_targets(pw, relayer_fee_targets)?;
+        }
+        VariantInputs::ExitAccountFormat(format) => {
+            let Some(format_targets) = targets.exit_account_format.take() else {
+                bail!("the circuit does not accept EVM exit accounts");
+            };
+            format.check(&circuit_inputs.public.exit_account)?;
+            format.fill_targets(pw, format_targets)?;
+        }
+        VariantInputs::SpentNullifierProof(proof) => {
+            let Some(spent_nullifiers_targets) = targets.spent_nullifiers.take() else {
+                bail!("the circuit does not check for double spends");
+            };
+            proof.verify(&circuit_inputs.public.nullifier)?;
+            proof.fill_targets(pw, spent_nullifiers_targets)?;
+        }
+        VariantInputs::FundingCommitment(blinding) => {
+            let Some(funding_commitment_targets) = targets.funding_commitment.take() else {
+                bail!("the circuit does not commit to the funding account");
+            };
+            let opening =
+                FundingAccountOpening::new(circuit_inputs.private.funding_account, *blinding);
+            opening.fill_targets(pw, funding_commitment_targets)?;
+        }
+        VariantInputs::Intent { min_amount } => {
+            let Some(intent_targets) = targets.intent.take() else {
+                bail!("the circuit does not expose a claim intent");
+            };
+            let public = &circuit_inputs.public;
+            let intent = ClaimIntent::new(public.nullifier, public.exit_account, min_amount);
+            intent.check_funding_amount(public.funding_amount)?;
+            intent.fill_targets(pw, intent_targets)?;
+        }
+    }
+
+    if targets.context.is_some() {
+        bail!("the circuit binds proofs to a context, which must be provided");
+    }
+    if targets.destination_chain_id.is_some() {
+        bail!("the circuit binds deposits to a destination chain, which must be provided");
+    }
+    if targets.relayer_fee.is_some() {
+        bail!("the circuit pays a relayer fee, which must be provided");
+    }
+    if targets.exit_account_format.is_some() {
+        bail!("the circuit accepts EVM exit accounts, so the exit account format must be provided");
+    }
+    if targets.spent_nullifiers.is_some() {
+        bail!("the circuit checks for double spends, so a spent nullifier proof must be provided");
+    }
+    if targets.funding_commitment.is_some() {
+        bail!("the circuit commits to the funding account, so a blinding must be provided");
+    }
+    if targets.intent.is_some() {
+        bail!("the circuit exposes a claim intent, so a minimum amount must be provided");
+    }
+
+    fill_input_targets(pw, targets, circuit_inputs, mode)
+}
+
+fn fill_input_targets(
+    pw: &mut PartialWitness<F>,
+    targets: CircuitTargets,
+    circuit_inputs: &CircuitInputs,
+    mode: FillMode,
+) -> anyhow::Result<()> {
+    // A stale transfer count would otherwise only show up as an unsatisfied constraint.
+    check_transfer_count(circuit_inputs, TRANSFER_COUNT_SEARCH_WINDOW)?;
+    let nullifier = Nullifier::from(circuit_inputs);
+    let unspendable_account = UnspendableAccount::from(circuit_inputs);
+    let exit_account = SubstrateAccount::from_bytes(circuit_inputs.public.exit_account.as_slice())?;
+
+    nullifier.fill_targets(pw, targets.nullifier)?;
+    unspendable_account.fill_targets(pw, targets.unspendable_account)?;
+    match mode {
+        FillMode::Fast => {
+            let storage_proof = StorageProof::try_from(circuit_inputs)?;
+            storage_proof.fill_targets(pw, targets.storage_proof)?;
+        }
+        FillMode::Hardened => {
+            let storage_proof = StorageProof::new_hardened(
+                &circuit_inputs.private.storage_proof,
+                circuit_inputs.public.root_hash,
+                LeafInputs::try_from(circuit_inputs)?,
+                targets.storage_proof.params(),
+            )?;
+            storage_proof.fill_targets_hardened(pw, targets.storage_proof)?;
+        }
+    }
+    exit_account.fill_targets(pw, targets.exit_account)?;
+    Ok(())
+}
+
+/// Fills a witness from the provided [`CircuitInputs`] and serializes it, without building the
+/// prover circuit data. The circuit is still laid out with [`WormholeCircuit::try_new`], which
+/// adds every constraint, to allocate its targets.
+///
+/// `config` must be the config of the circuit the proving server was built with.
+pub fn witness_from_inputs(
+    config: CircuitConfig,
+    circuit_inputs: &CircuitInputs,
+) -> anyhow::Result<Vec<u8>> {
+    let targets = WormholeCircuit::try_new(config)?.targets();
+    let mut pw = PartialWitness::new();
+    fill_witness(
+        &mut pw,
+        targets.clone(),
+        circuit_inputs,
+        VariantInputs::None,
+        FillMode::Fast,
+    )?;
+    witness_to_bytes(&pw, &targets)
+}
+
+/// Serializes the values of a filled witness.
+///
+/// # Errors
+///
+/// Returns an error if any of the input targets has not been filled.
+pub fn witness_to_bytes(
+    pw: &PartialWitness<F>,
+    targets: &CircuitTargets,
+) -> anyhow::Result<Vec<u8>> {
+    let input_targets = targets.input_targets();
+    let mut bytes = Vec::with_capacity(LEN_PREFIX_SIZE + input_targets.len() * VALUE_SIZE);
+    bytes.extend((input_targets.len() as u32).to_le_bytes());
+
+    for (i, &target) in input_targets.iter().enumerate() {
+        let value = pw
+            .try_get_target(target)
+            .ok_or_else(|| anyhow!("witness value {} has not been set", i))?;
+        bytes.extend(value.to_canonical_u64().to_le_bytes());
+    }
+
+    Ok(bytes)
+}
+
+/// Deserializes witness values produced by [`witness_to_bytes`] into a fresh witness for
+/// `targets`.
+///
+/// # Errors
+///
+/// Returns an error if the number of values does not match the circuit or any value is not a
+/// canonical field element.
+pub fn witness_from_bytes(
+    bytes: &[u8],
+    targets: &CircuitTargets,
+) -> anyhow::Result<PartialWitness<F>> {
+    let input_targets = targets.input_targets();
+    let expected_len = LEN_PREFIX_SIZE + input_targets.len() * VALUE_SIZE;
+    if bytes.len() != expected_len {
+        bail!(
+            "Expected {} bytes for witness, got: {}",
+            expected_len,
+            bytes.len()
+        );
+    }
+
+    let (len_prefix, values) = bytes.split_at(LEN_PREFIX_SIZE);
+    let num_values = u32::from_le_bytes(len_prefix.try_into()?) as usize;
+    if num_values != input_targets.len() {
+        bail!(
+            "witness has {} values, circuit expects {}",
+            num_values,
+            input_targets.len()
+        );
+    }
+
+    let mut pw = PartialWitness::new();
+    for (i, (&target, chunk)) in input_targets
+        .iter()
+        .zip(values.chunks_exact(VALUE_SIZE))
+        .enumerate()
+    {
+        let value = u64::from_le_bytes(chunk.try_into()?);
+        if value >= F::ORDER {
+            bail!("witness value {} is not a canonical field element", i);
+        }
+        pw.set_target(target, F::from_canonical_u64(value))?;
+    }
+
+    Ok(pw)
+}
@@ -1,8 +1,27 @@
 #![cfg(test)]
 
-use wormhole_aggregator::aggregator::WormholeProofAggregator;
-use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use plonky2::{
+    field::types::Field,
+    hash::poseidon::PoseidonHash,
+    iop::witness::PartialWitness,
+    plonk::{
+        circuit_builder::CircuitBuilder, circuit_data::CircuitData, config::Hasher,
+        proof::ProofWithPublicInputs,
+    },
+};
+use voting::aggregate::{AggregatedVoteEnvelope, MixedVotesError};
+use voting::{
+    VoteCircuitData, VotePrivateInputs, VotePublicInputs, VoteTargets, VOTE_PUBLIC_INPUTS_LEN,
+};
+use wormhole_aggregator::aggregator::{DuplicatePolicy, WormholeProofAggregator};
+use wormhole_aggregator::circuits::shape::{
+    leaf_ordering_commitment, tree_shape_commitment, TreeShapeCommitments,
+};
+use wormhole_aggregator::circuits::tree::{aggregate_to_tree, TreeAggregationConfig};
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs, EXIT_ACCOUNT_END_INDEX};
 use wormhole_prover::WormholeProver;
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::{Digest, PrivateKey};
 
 use crate::aggregator::circuit_config;
 use test_helpers::storage_proof::TestInputs;
@@ -28,7 +47,8 @@ fn push_proof_to_full_buffer() {
     let inputs = CircuitInputs::test_inputs();
     let proof = prover.commit(&inputs).unwrap().prove().unwrap();
 
-    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config());
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_duplicate_policy(DuplicatePolicy::Allow);
 
     // Fill up the proof buffer.
     for _ in 0..aggregator.config.num_leaf_proofs {
@@ -42,6 +62,36 @@ fn push_proof_to_full_buffer() {
     assert_eq!(proofs_buffer.len(), aggregator.config.num_leaf_proofs);
 }
 
+#[test]
+fn duplicate_proofs_follow_policy() {
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    // A different proof of the same deposit is a duplicate too.
+    let mut same_nullifier = proof.clone();
+    same_nullifier.public_inputs[8] += F::ONE;
+
+    // Duplicates are rejected by default.
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config());
+    assert_eq!(aggregator.duplicate_policy, DuplicatePolicy::Reject);
+    aggregator.push_proof(proof.clone()).unwrap();
+    assert!(aggregator.push_proof(proof.clone()).is_err());
+    assert!(aggregator.push_proof(same_nullifier.clone()).is_err());
+    assert_eq!(aggregator.duplicates_seen(), 2);
+    assert_eq!(aggregator.proofs_buffer.as_ref().unwrap().len(), 1);
+
+    aggregator.duplicate_policy = DuplicatePolicy::Skip;
+    aggregator.push_proof(same_nullifier).unwrap();
+    assert_eq!(aggregator.duplicates_seen(), 3);
+    assert_eq!(aggregator.proofs_buffer.as_ref().unwrap().len(), 1);
+
+    aggregator.duplicate_policy = DuplicatePolicy::Allow;
+    aggregator.push_proof(proof).unwrap();
+    assert_eq!(aggregator.duplicates_seen(), 4);
+    assert_eq!(aggregator.proofs_buffer.unwrap().len(), 2);
+}
+
 #[ignore]
 #[test]
 fn aggregate_single_proof() {
@@ -66,7 +116,8 @@ fn aggregate_proofs_into_tree() {
     let public_inputs = PublicCircuitInputs::try_from(&proof).unwrap();
     println!("public inputs of original proof = {:?}", public_inputs);
 
-    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config());
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_duplicate_policy(DuplicatePolicy::Allow);
 
     // Fill up the proof buffer.
     for _ in 0..aggregator.config.num_leaf_proofs {
@@ -97,7 +148,8 @@ fn aggregate_half_full_proof_array_into_tree() {
     let inputs = CircuitInputs::test_inputs();
     let proof = prover.commit(&inputs).unwrap().prove().unwrap();
 
-    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config());
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_duplicate_policy(DuplicatePolicy::Allow);
 
     // Fill up the proof buffer.
     for _ in 0..aggregator.config.num_leaf_proofs {
@@ -110,3 +162,335 @@ fn aggregate_half_full_proof_array_into_tree() {
         .verify(aggregated_proof.proof)
         .unwrap();
 }
+
+#[test]
+fn aggregate_skipping_invalid_replaces_bad_proofs() {
+    // Create a proof.
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    // Tamper with the funding amount of a second copy.
+    let mut invalid_proof = proof.clone();
+    invalid_proof.public_inputs[8] += F::ONE;
+
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_duplicate_policy(DuplicatePolicy::Allow);
+    aggregator.push_proof(proof.clone()).unwrap();
+    aggregator.push_proof(invalid_proof).unwrap();
+    aggregator.push_proof(proof).unwrap();
+
+    let (aggregated_proof, report) = aggregator.aggregate_skipping_invalid().unwrap();
+    assert!(!report.is_clean());
+    assert_eq!(report.replaced.len(), 1);
+    assert_eq!(report.replaced[0].index, 1);
+
+    let leaves = aggregator
+        .extract_leaf_public_inputs(&aggregated_proof.proof)
+        .unwrap();
+    assert!(leaves[1].is_padding());
+    assert!(!leaves[0].is_padding() && !leaves[2].is_padding());
+
+    aggregated_proof
+        .circuit_data
+        .verify(aggregated_proof.proof)
+        .unwrap();
+}
+
+#[test]
+fn aggregate_sizes_tree_to_batch() {
+    // Create a proof.
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let public_inputs = PublicCircuitInputs::try_from(&proof).unwrap();
+
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_duplicate_policy(DuplicatePolicy::Allow);
+    for _ in 0..3 {
+        aggregator.push_proof(proof.clone()).unwrap();
+    }
+    assert_eq!(aggregator.tree_config_for(3).num_leaf_proofs, 3);
+
+    let aggregated_proof = aggregator.aggregate().unwrap();
+    let all_leaf_public_inputs = aggregator
+        .extract_leaf_public_inputs(&aggregated_proof.proof)
+        .unwrap();
+    assert_eq!(all_leaf_public_inputs, vec![public_inputs; 3]);
+
+    aggregated_proof
+        .circuit_data
+        .verify(aggregated_proof.proof)
+        .unwrap();
+}
+
+#[test]
+fn verify_aggregated_proof_against_expectations() {
+    // Create a proof.
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let public_inputs = PublicCircuitInputs::try_from(&proof).unwrap();
+
+    // Three proofs in a tree of four leaves, so the last leaf is padding.
+    let config = TreeAggregationConfig::new(2, 2);
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(config)
+        .with_duplicate_policy(DuplicatePolicy::Allow);
+    for _ in 0..3 {
+        aggregator.push_proof(proof.clone()).unwrap();
+    }
+    let aggregated_proof = aggregator.aggregate().unwrap();
+
+    let expected = vec![public_inputs.clone(); 3];
+    let mismatches = aggregator
+        .verify_aggregated_with_expectations(&aggregated_proof, &expected, config)
+        .unwrap();
+    assert!(mismatches.is_empty());
+
+    let wrong_amount = PublicCircuitInputs {
+        funding_amount: public_inputs.funding_amount + 1,
+        ..public_inputs.clone()
+    };
+    let expected = vec![
+        public_inputs.clone(),
+        wrong_amount,
+        public_inputs.clone(),
+        public_inputs.clone(),
+        public_inputs,
+    ];
+    let mismatches = aggregator
+        .verify_aggregated_with_expectations(&aggregated_proof, &expected, config)
+        .unwrap();
+    // The padding leaf and the expectation beyond the tree are reported too.
+    assert_eq!(mismatches, vec![1, 3, 4]);
+}
+
+#[test]
+fn padding_leaves_decode_to_the_padding_marker() {
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let public_inputs = PublicCircuitInputs::try_from(&proof).unwrap();
+
+    // Three proofs in a tree of four leaves, so the last leaf is padding.
+    let config = TreeAggregationConfig::new(2, 2);
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(config)
+        .with_duplicate_policy(DuplicatePolicy::Allow);
+    for _ in 0..3 {
+        aggregator.push_proof(proof.clone()).unwrap();
+    }
+    let aggregated_proof = aggregator.aggregate().unwrap();
+
+    let leaves = aggregator
+        .extract_leaf_public_inputs(&aggregated_proof.proof)
+        .unwrap();
+    assert_eq!(leaves.len(), 4);
+    assert_eq!(leaves[..3], vec![public_inputs.clone(); 3]);
+    assert!(leaves[..3].iter().all(|leaf| !leaf.is_padding()));
+    assert_eq!(leaves[3], PublicCircuitInputs::padding());
+    assert!(leaves[3].is_padding());
+
+    // The aggregation circuit zeroes the padding leaf, so the shared decoder recognises it too.
+    let padding_leaf = &aggregated_proof.proof.public_inputs[3 * EXIT_ACCOUNT_END_INDEX..];
+    assert!(padding_leaf.iter().all(|felt| felt.is_zero()));
+    let decoded = PublicCircuitInputs::try_from_aggregated(
+        &aggregated_proof.proof,
+        EXIT_ACCOUNT_END_INDEX,
+        config.num_leaf_proofs,
+    )
+    .unwrap();
+    assert_eq!(decoded, leaves);
+
+    // Expecting padding matches the padding leaf, and nothing else.
+    let mut expected = vec![public_inputs; 3];
+    expected.push(PublicCircuitInputs::padding());
+    let mismatches = aggregator
+        .verify_aggregated_with_expectations(&aggregated_proof, &expected, config)
+        .unwrap();
+    assert!(mismatches.is_empty());
+
+    expected.swap(0, 3);
+    let mismatches = aggregator
+        .verify_aggregated_with_expectations(&aggregated_proof, &expected, config)
+        .unwrap();
+    assert_eq!(mismatches, vec![0, 3]);
+}
+
+#[test]
+fn aggregated_proof_commits_to_tree_shape() {
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let public_inputs = PublicCircuitInputs::try_from(&proof).unwrap();
+
+    let config = TreeAggregationConfig::new(2, 1);
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(config)
+        .with_tree_shape_commitment();
+    aggregator.push_proof(proof.clone()).unwrap();
+    let aggregated_proof = aggregator.aggregate().unwrap();
+
+    let (leaves, commitments) =
+        TreeShapeCommitments::split_public_inputs(&aggregated_proof.proof.public_inputs).unwrap();
+    let leaf_pi_len = aggregator.leaf_circuit_data.common.num_public_inputs;
+    assert_eq!(leaves.len(), config.num_leaf_proofs * leaf_pi_len);
+    assert_eq!(commitments.tree_shape, tree_shape_commitment(&config));
+    assert_ne!(
+        commitments.tree_shape,
+        tree_shape_commitment(&TreeAggregationConfig::new(2, 2))
+    );
+    assert_eq!(
+        commitments.leaf_ordering,
+        leaf_ordering_commitment(leaves, leaf_pi_len).unwrap()
+    );
+
+    // The leaves are still readable, and the proof still verifies.
+    let extracted = aggregator
+        .extract_leaf_public_inputs(&aggregated_proof.proof)
+        .unwrap();
+    assert_eq!(extracted[0], public_inputs);
+    let mismatches = aggregator
+        .verify_aggregated_with_expectations(&aggregated_proof, &[public_inputs], config)
+        .unwrap();
+    assert!(mismatches.is_empty());
+}
+
+/// Proves a vote for each of 8 voters in a depth 3 voter tree, alternating yes and no.
+fn vote_proofs() -> (
+    CircuitData<F, C, D>,
+    Vec<ProofWithPublicInputs<F, C, D>>,
+    Vec<VotePublicInputs>,
+) {
+    const DEPTH: usize = 3;
+
+    let private_keys: Vec<PrivateKey> = (1..=1u64 << DEPTH)
+        .map(|i| [F::from_canonical_u64(i); 4])
+        .collect();
+    let leaves: Vec<Digest> = private_keys
+        .iter()
+        .map(|key| PoseidonHash::hash_no_pad(key).elements)
+        .collect();
+
+    let mut levels = vec![leaves];
+    while levels.last().unwrap().len() > 1 {
+        let parents = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| PoseidonHash::hash_no_pad(&[pair[0], pair[1]].concat()).elements)
+            .collect();
+        levels.push(parents);
+    }
+    let merkle_root = levels[DEPTH][0];
+    let proposal_id = [F::from_canonical_u64(42); 4];
+
+    let mut builder = CircuitBuilder::<F, D>::new(circuit_config());
+    let targets = VoteTargets::new(&mut builder);
+    VoteCircuitData::circuit(&targets, &mut builder);
+    let circuit_data = builder.build::<C>();
+
+    let mut proofs = Vec::new();
+    let mut public_inputs = Vec::new();
+    for (i, private_key) in private_keys.into_iter().enumerate() {
+        let leaf = levels[0][i];
+        let merkle_siblings = (0..DEPTH).map(|l| levels[l][(i >> l) ^ 1]).collect();
+        let path_indices = (0..DEPTH).map(|l| (i >> l) & 1 == 1).collect();
+        let nullifier = PoseidonHash::hash_no_pad(&[leaf, proposal_id].concat()).elements;
+
+        let vote_public_inputs = VotePublicInputs {
+            proposal_id,
+            merkle_root,
+            previous_merkle_roots: Vec::new(),
+            vote: i % 2 == 0,
+            nullifier,
+            voter_tag: None,
+            sequence: 0,
+        };
+        let vote_private_inputs = VotePrivateInputs {
+            private_key,
+            merkle_siblings,
+            path_indices,
+            actual_merkle_depth: DEPTH,
+            proposal_metadata: None,
+        };
+        let vote = VoteCircuitData::new(vote_public_inputs.clone(), vote_private_inputs);
+
+        let mut pw = PartialWitness::new();
+        vote.fill_targets(&mut pw, targets.clone()).unwrap();
+        proofs.push(circuit_data.prove(pw).unwrap());
+        public_inputs.push(vote_public_inputs);
+    }
+
+    (circuit_data, proofs, public_inputs)
+}
+
+#[test]
+fn aggregate_vote_proofs_into_tree() {
+    let (vote_circuit_data, proofs, public_inputs) = vote_proofs();
+    assert_eq!(VOTE_PUBLIC_INPUTS_LEN, EXIT_ACCOUNT_END_INDEX);
+    assert_eq!(vote_circuit_data.common.num_public_inputs, VOTE_PUBLIC_INPUTS_LEN);
+
+    let aggregated_proof = aggregate_to_tree(
+        proofs,
+        &vote_circuit_data.common,
+        &vote_circuit_data.verifier_only,
+        TreeAggregationConfig::new(2, 3),
+    )
+    .unwrap();
+
+    let votes = VotePublicInputs::try_from_aggregated(
+        &aggregated_proof.proof.public_inputs,
+        VOTE_PUBLIC_INPUTS_LEN,
+    )
+    .unwrap();
+    let expected: Vec<_> = public_inputs
+        .iter()
+        .map(VotePublicInputs::to_field_elements)
+        .collect();
+    let actual: Vec<_> = votes
+        .iter()
+        .map(VotePublicInputs::to_field_elements)
+        .collect();
+    assert_eq!(actual, expected);
+    assert_eq!(votes.iter().filter(|vote| vote.vote).count(), 4);
+
+    aggregated_proof
+        .circuit_data
+        .verify(aggregated_proof.proof)
+        .unwrap();
+}
+
+#[test]
+fn aggregated_vote_envelope_binds_every_leaf() {
+    let (vote_circuit_data, proofs, public_inputs) = vote_proofs();
+    let aggregated_proof = aggregate_to_tree(
+        proofs,
+        &vote_circuit_data.common,
+        &vote_circuit_data.verifier_only,
+        TreeAggregationConfig::new(2, 3),
+    )
+    .unwrap();
+    let verifier = aggregated_proof.circuit_data.verifier_data();
+
+    let envelope =
+        AggregatedVoteEnvelope::new(&aggregated_proof.proof, VOTE_PUBLIC_INPUTS_LEN, 8).unwrap();
+    assert_eq!(envelope.proposal_id, public_inputs[0].proposal_id);
+    assert_eq!(envelope.merkle_root, public_inputs[0].merkle_root);
+    assert_eq!((envelope.yes, envelope.no), (4, 4));
+
+    let envelope = AggregatedVoteEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+    let votes = envelope.verify(&verifier, VOTE_PUBLIC_INPUTS_LEN).unwrap();
+    assert_eq!(votes.len(), 8);
+
+    // Claiming another voter set flags every leaf.
+    let mut other_root = envelope;
+    other_root.merkle_root = [F::ZERO; 4];
+    let err = other_root
+        .verify(&verifier, VOTE_PUBLIC_INPUTS_LEN)
+        .unwrap_err();
+    let mixed = err.downcast_ref::<MixedVotesError>().unwrap();
+    let indices: Vec<usize> = mixed.leaves.iter().map(|leaf| leaf.index).collect();
+    assert_eq!(indices, (0..8).collect::<Vec<_>>());
+}
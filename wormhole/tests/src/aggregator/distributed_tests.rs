@@ -0,0 +1,84 @@
+use std::net::TcpListener;
+use std::thread;
+
+use test_helpers::storage_proof::TestInputs;
+use wormhole_aggregator::aggregator::WormholeProofAggregator;
+use wormhole_aggregator::circuits::tree::TreeAggregationConfig;
+use wormhole_aggregator::distributed::{serve, ChunkJob, TcpWorker, Worker};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+
+use crate::aggregator::circuit_config;
+
+/// Starts a worker serving one parent on a local port.
+fn tcp_worker() -> Box<dyn Worker> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        serve(stream.try_clone().unwrap(), stream).unwrap();
+    });
+    Box::new(TcpWorker::connect(addr).unwrap())
+}
+
+/// Aggregates the chunk with its slots swapped, which gives a valid proof of the wrong
+/// public inputs.
+struct SwappingWorker;
+
+impl Worker for SwappingWorker {
+    fn aggregate(&mut self, job: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut job = ChunkJob::from_bytes(job)?;
+        job.proofs.reverse();
+        job.padding.reverse();
+        Ok(job.run()?.to_bytes())
+    }
+}
+
+#[test]
+fn distributed_aggregation_matches_local() {
+    let prover = WormholeProver::new(circuit_config());
+    let proof = prover
+        .commit(&CircuitInputs::test_inputs())
+        .unwrap()
+        .prove()
+        .unwrap();
+    let config = TreeAggregationConfig::new(2, 2);
+
+    let mut local =
+        WormholeProofAggregator::from_circuit_config(circuit_config()).with_config(config);
+    local.push_proof(proof.clone()).unwrap();
+    let local_root = local.aggregate().unwrap();
+
+    let mut distributed = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(config)
+        .with_workers(vec![tcp_worker(), tcp_worker()]);
+    distributed.push_proof(proof).unwrap();
+    let distributed_root = distributed.aggregate().unwrap();
+
+    distributed_root
+        .circuit_data
+        .verify(distributed_root.proof.clone())
+        .unwrap();
+    assert_eq!(
+        distributed_root.proof.public_inputs,
+        local_root.proof.public_inputs
+    );
+}
+
+#[test]
+fn worker_proof_of_other_leaves_is_rejected() {
+    let prover = WormholeProver::new(circuit_config());
+    let proof = prover
+        .commit(&CircuitInputs::test_inputs())
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(TreeAggregationConfig::new(2, 1))
+        .with_workers(vec![Box::new(SwappingWorker)]);
+    aggregator.push_proof(proof).unwrap();
+
+    let error = aggregator.aggregate().unwrap_err();
+    assert!(error.to_string().contains("aggregates other proofs"));
+}
@@ -0,0 +1,85 @@
+#![cfg(test)]
+
+use std::time::Duration;
+
+use wormhole_aggregator::intake::{IntakeLimits, IntakeRejection, ProofIntake};
+use wormhole_aggregator::service::IncomingProof;
+
+fn incoming(len: usize, source_id: Option<&str>) -> IncomingProof {
+    IncomingProof {
+        origin: "test".to_string(),
+        bytes: vec![0; len],
+        source_id: source_id.map(str::to_string),
+    }
+}
+
+#[test]
+fn oversized_proofs_are_rejected() {
+    let limits = IntakeLimits {
+        max_proof_bytes: 16,
+        ..IntakeLimits::default()
+    };
+    let (intake, _proofs) = ProofIntake::new(limits);
+
+    intake.submit(incoming(16, None)).unwrap();
+    assert_eq!(
+        intake.submit(incoming(17, None)),
+        Err(IntakeRejection::TooLarge { size: 17, max: 16 })
+    );
+}
+
+#[test]
+fn full_queue_rejects_until_drained() {
+    let limits = IntakeLimits {
+        max_queue_depth: 2,
+        ..IntakeLimits::default()
+    };
+    let (intake, proofs) = ProofIntake::new(limits);
+
+    intake.submit(incoming(1, None)).unwrap();
+    intake.submit(incoming(1, Some("a"))).unwrap();
+    assert_eq!(
+        intake.submit(incoming(1, None)),
+        Err(IntakeRejection::QueueFull { depth: 2 })
+    );
+
+    proofs.recv_timeout(Duration::from_secs(1)).unwrap();
+    intake.submit(incoming(1, None)).unwrap();
+}
+
+#[test]
+fn quotas_are_kept_per_source() {
+    let limits = IntakeLimits {
+        max_proofs_per_source: Some(1),
+        ..IntakeLimits::default()
+    };
+    let (intake, proofs) = ProofIntake::new(limits);
+
+    intake.submit(incoming(1, Some("a"))).unwrap();
+    assert_eq!(
+        intake.submit(incoming(1, Some("a"))),
+        Err(IntakeRejection::QuotaExceeded {
+            source_id: "a".to_string(),
+            quota: 1,
+        })
+    );
+    // Other sources and submissions without a source have their own quota.
+    intake.submit(incoming(1, Some("b"))).unwrap();
+    intake.submit(incoming(1, None)).unwrap();
+    intake.submit(incoming(1, None)).unwrap();
+
+    // The quota is released once the service picks the proof up.
+    let first = proofs.recv_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!(first.source_id.as_deref(), Some("a"));
+    intake.submit(incoming(1, Some("a"))).unwrap();
+}
+
+#[test]
+fn submissions_after_shutdown_are_rejected() {
+    let (intake, proofs) = ProofIntake::new(IntakeLimits::default());
+    drop(proofs);
+    assert_eq!(
+        intake.submit(incoming(1, None)),
+        Err(IntakeRejection::Closed)
+    );
+}
@@ -0,0 +1,77 @@
+#![cfg(test)]
+
+use wormhole_aggregator::aggregator::{DuplicatePolicy, WormholeProofAggregator};
+use wormhole_aggregator::metrics::gather;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+
+use crate::aggregator::circuit_config;
+
+/// The value of the sample `series`, e.g. `name{label="value"}`, in a scrape.
+fn sample(scrape: &str, series: &str) -> Option<f64> {
+    scrape.lines().find_map(|line| {
+        let (name, value) = line.rsplit_once(' ')?;
+        (name == series).then(|| value.parse().unwrap())
+    })
+}
+
+#[test]
+fn scrape_is_well_formed() {
+    let scrape = gather();
+    for line in scrape.lines() {
+        if let Some(comment) = line.strip_prefix("# ") {
+            assert!(comment.starts_with("HELP ") || comment.starts_with("TYPE "));
+            continue;
+        }
+        let (series, value) = line.rsplit_once(' ').unwrap();
+        assert!(series.starts_with("wormhole_aggregator_"), "{}", line);
+        value.parse::<f64>().unwrap();
+    }
+    for stage in ["verify", "push", "aggregate", "intake"] {
+        let series = format!("wormhole_aggregator_failures_total{{stage=\"{}\"}}", stage);
+        assert!(sample(&scrape, &series).is_some());
+    }
+}
+
+#[test]
+fn aggregation_is_recorded() {
+    // Other tests in this process record too, so only check that the values moved.
+    let before = gather();
+    let pushed_before = sample(&before, "wormhole_aggregator_proofs_pushed_total").unwrap();
+    let push_failures = "wormhole_aggregator_failures_total{stage=\"push\"}";
+    let push_failures_before = sample(&before, push_failures).unwrap();
+
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_duplicate_policy(DuplicatePolicy::Allow);
+    for _ in 0..aggregator.config.num_leaf_proofs {
+        aggregator.push_proof(proof.clone()).unwrap();
+    }
+    assert!(aggregator.push_proof(proof).is_err());
+    aggregator.aggregate().unwrap();
+
+    let after = gather();
+    let pushed = sample(&after, "wormhole_aggregator_proofs_pushed_total").unwrap();
+    assert!(pushed >= pushed_before + aggregator.config.num_leaf_proofs as f64);
+    assert!(sample(&after, push_failures).unwrap() > push_failures_before);
+
+    // The leaf level was timed, with cumulative buckets ending in the total count.
+    let count = sample(
+        &after,
+        "wormhole_aggregator_level_duration_seconds_count{level=\"0\"}",
+    )
+    .unwrap();
+    assert!(count >= 1.0);
+    let buckets: Vec<f64> = after
+        .lines()
+        .filter(|line| {
+            line.starts_with("wormhole_aggregator_level_duration_seconds_bucket{level=\"0\",")
+        })
+        .map(|line| line.rsplit_once(' ').unwrap().1.parse().unwrap())
+        .collect();
+    assert!(buckets.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(buckets.last(), Some(&count));
+}
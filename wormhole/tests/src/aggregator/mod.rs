@@ -2,6 +2,16 @@
 
 use plonky2::plonk::circuit_data::CircuitConfig;
 pub mod aggregator_tests;
+#[cfg(feature = "distributed")]
+pub mod distributed_tests;
+pub mod intake_tests;
+#[cfg(feature = "metrics")]
+pub mod metrics_tests;
+pub mod service_tests;
+#[cfg(feature = "size-snapshots")]
+pub mod size_snapshot_tests;
+pub mod soak_tests;
+pub mod verification_tables_tests;
 
 fn circuit_config() -> CircuitConfig {
     CircuitConfig::standard_recursion_config()
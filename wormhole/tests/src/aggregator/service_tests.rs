@@ -0,0 +1,155 @@
+#![cfg(test)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::bail;
+use wormhole_aggregator::aggregator::WormholeProofAggregator;
+use wormhole_aggregator::circuits::tree::{AggregatedProof, TreeAggregationConfig};
+use wormhole_aggregator::intake::{IntakeLimits, ProofIntake};
+use wormhole_aggregator::service::{
+    read_proof_file, AggregationService, BatchPolicy, IncomingProof, ProofSource, SubmitHook,
+    PROCESSED_DIR, REJECTED_DIR,
+};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+use zk_circuits_common::circuit::{C, D, F};
+
+use crate::aggregator::circuit_config;
+use test_helpers::storage_proof::TestInputs;
+
+/// Records the submitted proof paths and fails the first submission.
+struct FlakyHook {
+    submitted: Arc<Mutex<Vec<PathBuf>>>,
+    failed: bool,
+}
+
+impl SubmitHook for FlakyHook {
+    fn submit(
+        &mut self,
+        proof_path: &Path,
+        _proof: &AggregatedProof<F, C, D>,
+    ) -> anyhow::Result<()> {
+        if !self.failed {
+            self.failed = true;
+            bail!("submission endpoint unavailable");
+        }
+        self.submitted
+            .lock()
+            .unwrap()
+            .push(proof_path.to_path_buf());
+        Ok(())
+    }
+}
+
+#[test]
+fn proof_files_are_hex_decoded_by_extension() {
+    let temp_dir = Path::new("temp_test_service_files");
+    fs::create_dir_all(temp_dir).unwrap();
+
+    let raw_path = temp_dir.join("proof.bin");
+    let hex_path = temp_dir.join("proof.hex");
+    fs::write(&raw_path, [1u8, 2, 3]).unwrap();
+    fs::write(&hex_path, "010203\n").unwrap();
+
+    assert_eq!(read_proof_file(&raw_path).unwrap(), vec![1, 2, 3]);
+    assert_eq!(read_proof_file(&hex_path).unwrap(), vec![1, 2, 3]);
+
+    fs::remove_dir_all(temp_dir).unwrap();
+}
+
+#[test]
+fn directory_source_yields_new_files() {
+    let temp_dir = Path::new("temp_test_service_watch");
+    fs::create_dir_all(temp_dir).unwrap();
+    fs::write(temp_dir.join("a.hex"), "aa").unwrap();
+    fs::write(temp_dir.join("b.bin"), [0xbb]).unwrap();
+    // Files still being written and files that aren't valid hex are not delivered.
+    fs::write(temp_dir.join("c.tmp"), [0xcc]).unwrap();
+    fs::write(temp_dir.join("d.hex"), "not hex").unwrap();
+
+    let source = ProofSource::Directory {
+        path: temp_dir.to_path_buf(),
+        poll_interval: Duration::from_millis(10),
+    };
+    let proofs = source.spawn();
+
+    let first = proofs.recv_timeout(Duration::from_secs(5)).unwrap();
+    let second = proofs.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(first.bytes, vec![0xaa]);
+    assert_eq!(second.bytes, vec![0xbb]);
+
+    // Delivered files must not be delivered again.
+    assert!(proofs.recv_timeout(Duration::from_millis(100)).is_err());
+
+    assert!(temp_dir.join(PROCESSED_DIR).join("a.hex").is_file());
+    assert!(temp_dir.join(PROCESSED_DIR).join("b.bin").is_file());
+    assert!(temp_dir.join(REJECTED_DIR).join("d.hex").is_file());
+    assert!(temp_dir.join("c.tmp").is_file());
+
+    fs::remove_dir_all(temp_dir).unwrap();
+}
+
+#[test]
+fn batch_size_larger_than_tree_is_rejected() {
+    let aggregator = WormholeProofAggregator::from_circuit_config(circuit_config());
+    let policy = BatchPolicy {
+        max_proofs: aggregator.config.num_leaf_proofs + 1,
+        max_wait: Duration::from_secs(1),
+    };
+
+    let result = AggregationService::new(aggregator, policy, "temp_test_service_output", None);
+    assert!(result.is_err());
+}
+
+#[test]
+fn failed_submission_is_retried_and_service_keeps_running() {
+    let output_dir = Path::new("temp_test_service_flaky_hook");
+    let prover = WormholeProver::new(circuit_config());
+    let proof = prover
+        .commit(&CircuitInputs::test_inputs())
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(TreeAggregationConfig::new(2, 1));
+    let policy = BatchPolicy {
+        max_proofs: 1,
+        max_wait: Duration::from_secs(60),
+    };
+    let submitted = Arc::new(Mutex::new(Vec::new()));
+    let hook = FlakyHook {
+        submitted: submitted.clone(),
+        failed: false,
+    };
+    let mut service =
+        AggregationService::new(aggregator, policy, output_dir, Some(Box::new(hook))).unwrap();
+
+    // Each proof is a batch of its own. The first batch fails to submit, and is submitted again
+    // before the second one.
+    let (intake, proofs) = ProofIntake::new(IntakeLimits::default());
+    for origin in ["first", "second"] {
+        let incoming = IncomingProof {
+            origin: origin.to_string(),
+            bytes: proof.to_bytes(),
+            source_id: None,
+        };
+        intake.submit(incoming).unwrap();
+    }
+    drop(intake);
+    service.run(proofs).unwrap();
+
+    assert_eq!(
+        *submitted.lock().unwrap(),
+        [
+            output_dir.join("aggregated_000000.bin"),
+            output_dir.join("aggregated_000001.bin")
+        ]
+    );
+    assert_eq!(service.num_unsubmitted(), 0);
+
+    fs::remove_dir_all(output_dir).unwrap();
+}
@@ -0,0 +1,98 @@
+//! Snapshot of the sizes of serialized circuit artifacts and proofs.
+//!
+//! Run with `cargo test -p tests --features size-snapshots size_snapshot`. Sizes missing from the
+//! snapshot are recorded, and sizes that drift from it by more than its threshold fail the test,
+//! so that accidental circuit growth shows up in review. After an intended change, rerun with
+//! `UPDATE_SIZE_SNAPSHOTS=1` and commit the updated snapshot.
+use std::collections::BTreeMap;
+use std::{env, fs};
+
+use plonky2::util::serialization::DefaultGateSerializer;
+use serde::{Deserialize, Serialize};
+use wormhole_aggregator::aggregator::WormholeProofAggregator;
+use wormhole_aggregator::circuits::tree::TreeAggregationConfig;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+
+use crate::aggregator::circuit_config;
+use test_helpers::storage_proof::TestInputs;
+
+const SNAPSHOT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/artifact_sizes.json");
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SizeSnapshot {
+    /// The allowed drift from a recorded size, in percent.
+    threshold_percent: u64,
+    /// Sizes in bytes, by artifact name.
+    sizes: BTreeMap<String, u64>,
+}
+
+fn measure_sizes() -> BTreeMap<String, u64> {
+    let mut sizes = BTreeMap::new();
+
+    let circuit_data = WormholeCircuit::new(circuit_config()).build_circuit();
+    let common_bytes = circuit_data
+        .common
+        .to_bytes(&DefaultGateSerializer)
+        .unwrap();
+    let verifier_bytes = circuit_data.verifier_only.to_bytes().unwrap();
+    sizes.insert("common.bin".to_string(), common_bytes.len() as u64);
+    sizes.insert("verifier.bin".to_string(), verifier_bytes.len() as u64);
+
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    sizes.insert("leaf_proof".to_string(), proof.to_bytes().len() as u64);
+
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(TreeAggregationConfig::new(2, 3));
+    aggregator.push_proof(proof).unwrap();
+    let aggregated = aggregator.aggregate().unwrap();
+    sizes.insert(
+        "aggregate_depth_3".to_string(),
+        aggregated.proof.to_bytes().len() as u64,
+    );
+
+    sizes
+}
+
+#[test]
+fn artifact_sizes_match_snapshot() {
+    let mut snapshot: SizeSnapshot =
+        serde_json::from_str(&fs::read_to_string(SNAPSHOT_PATH).unwrap()).unwrap();
+    let update = env::var_os("UPDATE_SIZE_SNAPSHOTS").is_some();
+
+    let mut drifted = Vec::new();
+    let mut changed = false;
+    for (name, size) in measure_sizes() {
+        match snapshot.sizes.get(&name) {
+            Some(&recorded) if recorded == size => {}
+            Some(&recorded) if !update => {
+                // Compare in integers: the drift exceeds the threshold if
+                // |size - recorded| / recorded > threshold / 100.
+                if size.abs_diff(recorded) * 100 > recorded * snapshot.threshold_percent {
+                    drifted.push(format!("{}: {} bytes, recorded {}", name, size, recorded));
+                }
+            }
+            _ => {
+                println!("recording size of {}: {} bytes", name, size);
+                snapshot.sizes.insert(name, size);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        let json = serde_json::to_string_pretty(&snapshot).unwrap();
+        fs::write(SNAPSHOT_PATH, json + "\n").unwrap();
+    }
+    assert!(
+        drifted.is_empty(),
+        "sizes drifted by more than {}% from {}, rerun with UPDATE_SIZE_SNAPSHOTS=1 if this is \
+         intended:\n{}",
+        snapshot.threshold_percent,
+        SNAPSHOT_PATH,
+        drifted.join("\n")
+    );
+}
@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use std::time::Duration;
+
+use wormhole_aggregator::soak::{parse_resident_memory, SoakFailure, SoakStats};
+
+const MIB: u64 = 1024 * 1024;
+
+#[test]
+fn resident_memory_is_parsed_from_proc_status() {
+    let status = "Name:\tsoak\nVmPeak:\t  204800 kB\nVmRSS:\t  102400 kB\nThreads:\t8\n";
+    assert_eq!(parse_resident_memory(status), Some(100 * MIB));
+    assert_eq!(parse_resident_memory("Name:\tsoak\n"), None);
+}
+
+#[test]
+fn latency_percentiles_use_nearest_rank() {
+    let mut stats = SoakStats::default();
+    assert_eq!(stats.latency_percentile(50.0), None);
+
+    for millis in (1..=100).rev() {
+        stats.record_batch(Duration::from_millis(millis), None);
+    }
+    let millis = |percentile| stats.latency_percentile(percentile).map(|l| l.as_millis());
+    assert_eq!(millis(50.0), Some(50));
+    assert_eq!(millis(99.0), Some(99));
+    assert_eq!(millis(100.0), Some(100));
+    assert_eq!(millis(0.0), Some(1));
+}
+
+#[test]
+fn memory_growth_is_measured_after_warmup() {
+    let mut stats = SoakStats::default();
+    // The first batches grow the heap, which isn't counted as growth.
+    for memory in [100, 400, 500, 510, 520] {
+        stats.record_batch(Duration::from_secs(1), Some(memory * MIB));
+    }
+
+    assert_eq!(stats.baseline_memory(3), Some(500 * MIB));
+    assert_eq!(stats.memory_growth(3), Some(20 * MIB as i64));
+    let summary = stats.summary(3);
+    assert_eq!(summary.peak_memory, Some(520 * MIB));
+    // A test that hasn't finished its warmup has no growth yet.
+    assert_eq!(stats.memory_growth(10), Some(0));
+}
+
+#[test]
+fn failures_are_counted_by_kind() {
+    let mut stats = SoakStats::default();
+    stats.record_batch(Duration::from_secs(1), None);
+    stats.record_failure(SoakFailure::Proof, None);
+    stats.record_failure(SoakFailure::Aggregation, None);
+    stats.record_failure(SoakFailure::Verification, None);
+
+    // Only the aggregation failure is a batch, and it has no latency.
+    assert_eq!(stats.batches(), 2);
+    assert_eq!(stats.failures(), 3);
+    let summary = stats.summary(0);
+    assert_eq!(summary.max, Some(Duration::from_secs(1)));
+    assert_eq!(summary.proof_failures, 1);
+    assert_eq!(summary.aggregation_failures, 1);
+    assert_eq!(summary.verification_failures, 1);
+}
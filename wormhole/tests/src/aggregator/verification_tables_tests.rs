@@ -0,0 +1,79 @@
+#![cfg(test)]
+
+use wormhole_aggregator::aggregator::WormholeProofAggregator;
+use wormhole_aggregator::circuits::tree::TreeAggregationConfig;
+use wormhole_aggregator::verification_tables::RootVerificationTables;
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use wormhole_prover::WormholeProver;
+
+use crate::aggregator::circuit_config;
+use test_helpers::storage_proof::TestInputs;
+
+#[test]
+fn verification_tables_verify_root_proofs_after_round_trip() {
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let public_inputs = PublicCircuitInputs::try_from(&proof).unwrap();
+
+    // One proof in a tree of two leaves, so the second leaf is padding.
+    let config = TreeAggregationConfig::new(2, 1);
+    let mut aggregator = WormholeProofAggregator::from_circuit_config(circuit_config())
+        .with_config(config)
+        .with_tree_shape_commitment();
+    aggregator.push_proof(proof).unwrap();
+    let aggregated_proof = aggregator.aggregate().unwrap();
+
+    let tables = aggregator
+        .verification_tables(&aggregated_proof, config)
+        .unwrap();
+    let tables = RootVerificationTables::from_bytes(&tables.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        tables.circuit_digest(),
+        aggregated_proof
+            .circuit_data
+            .verifier_only
+            .circuit_digest
+            .elements
+    );
+
+    let proof_bytes = aggregated_proof.proof.to_bytes();
+    let leaves = tables.verify(&proof_bytes).unwrap();
+    assert_eq!(leaves, vec![Some(public_inputs), None]);
+
+    let mut tampered = proof_bytes;
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(tables.verify(&tampered).is_err());
+}
+
+#[test]
+fn verification_tables_reject_the_wrong_tree() {
+    let prover = WormholeProver::new(circuit_config());
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    let config = TreeAggregationConfig::new(2, 1);
+    let mut aggregator =
+        WormholeProofAggregator::from_circuit_config(circuit_config()).with_config(config);
+    aggregator.push_proof(proof).unwrap();
+    let aggregated_proof = aggregator.aggregate().unwrap();
+
+    // The root circuit doesn't hold the leaves of a deeper tree.
+    assert!(aggregator
+        .verification_tables(&aggregated_proof, TreeAggregationConfig::new(2, 2))
+        .is_err());
+
+    let bytes = aggregator
+        .verification_tables(&aggregated_proof, config)
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+    assert!(RootVerificationTables::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut unknown_version = bytes.clone();
+    unknown_version[0] = 0;
+    assert!(RootVerificationTables::from_bytes(&unknown_version).is_err());
+    let mut trailing = bytes;
+    trailing.push(0);
+    assert!(RootVerificationTables::from_bytes(&trailing).is_err());
+}
@@ -0,0 +1,165 @@
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::iop::witness::WitnessWrite;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use wormhole_circuit::accumulator::{
+    AccumulatorTargets, AccumulatorUpdate, NonMembershipProof, NonMembershipTargets,
+    NullifierAccumulator, NEW_ROOT_END_INDEX, NEW_ROOT_START_INDEX, NULLIFIERS_START_INDEX,
+    OLD_ROOT_END_INDEX, OLD_ROOT_START_INDEX, START_INDEX_INDEX,
+};
+use wormhole_circuit::types::NullifierHash;
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::felts_to_hashout;
+
+#[cfg(test)]
+const DEPTH: usize = 4;
+
+#[cfg(test)]
+fn nullifier(elements: [u64; 4]) -> NullifierHash {
+    elements.map(F::from_canonical_u64).into()
+}
+
+#[cfg(test)]
+fn run_test(update: &AccumulatorUpdate) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = AccumulatorTargets::new(&mut builder, DEPTH, update.insertions.len());
+    AccumulatorUpdate::circuit(&targets, &mut builder);
+
+    update.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[cfg(test)]
+fn run_non_membership_test(
+    proof: &NonMembershipProof,
+    nullifier: &NullifierHash,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let nullifier_target = builder.add_virtual_hash();
+    let targets = NonMembershipTargets::new(&mut builder, nullifier_target, DEPTH);
+    NonMembershipProof::circuit(&targets, &mut builder);
+
+    pw.set_hash_target(nullifier_target, felts_to_hashout(&nullifier.to_felts()))?;
+    proof.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn prove_batch_insertion() {
+    let mut accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    let first = accumulator
+        .insert_batch(&[nullifier([5, 0, 0, 0]), nullifier([1, 2, 3, 4])])
+        .unwrap();
+    run_test(&first).unwrap();
+
+    // Insert between existing leaves, after the largest, and differing only in a low element.
+    let nullifiers = [
+        nullifier([3, 0, 0, 0]),
+        nullifier([F::NEG_ONE.to_canonical_u64(), 0, 0, 0]),
+        nullifier([1, 2, 3, 5]),
+    ];
+    let second = accumulator.insert_batch(&nullifiers).unwrap();
+    assert_eq!(second.old_root, first.new_root);
+    assert_eq!(second.start_index, 3);
+    assert_eq!(accumulator.next_index(), 6);
+
+    let proof = run_test(&second).unwrap();
+    let pi = &proof.public_inputs;
+    assert_eq!(pi[OLD_ROOT_START_INDEX..OLD_ROOT_END_INDEX], first.new_root);
+    assert_eq!(pi[NEW_ROOT_START_INDEX..NEW_ROOT_END_INDEX], accumulator.root());
+    assert_eq!(pi[START_INDEX_INDEX].to_canonical_u64(), 3);
+    for (i, nullifier) in nullifiers.iter().enumerate() {
+        let start = NULLIFIERS_START_INDEX + 4 * i;
+        assert_eq!(pi[start..start + 4], nullifier.to_felts());
+    }
+}
+
+#[test]
+fn spent_nullifiers_are_rejected() {
+    let mut accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    let spent = nullifier([7, 7, 7, 7]);
+    accumulator.insert_batch(&[spent]).unwrap();
+    let root = accumulator.root();
+
+    assert!(accumulator.insert_batch(&[spent]).is_err());
+    let fresh = nullifier([8, 0, 0, 0]);
+    assert!(accumulator.insert_batch(&[fresh, fresh]).is_err());
+    assert!(accumulator.insert_batch(&[nullifier([0, 0, 0, 0])]).is_err());
+    assert_eq!(accumulator.root(), root);
+    assert!(accumulator.contains(&spent));
+    assert!(!accumulator.contains(&fresh));
+}
+
+#[test]
+fn full_accumulator_is_rejected() {
+    let mut accumulator = NullifierAccumulator::new(1).unwrap();
+    assert!(accumulator.insert_batch(&[nullifier([1, 0, 0, 0]), nullifier([2, 0, 0, 0])]).is_err());
+    accumulator.insert_batch(&[nullifier([1, 0, 0, 0])]).unwrap();
+    assert!(accumulator.insert_batch(&[nullifier([2, 0, 0, 0])]).is_err());
+}
+
+#[test]
+fn reinserting_spent_nullifier_fails_to_prove() {
+    let mut accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    let spent = nullifier([7, 7, 7, 7]);
+    let mut update = accumulator.insert_batch(&[spent]).unwrap();
+    accumulator.insert_batch(&[nullifier([9, 0, 0, 0])]).unwrap();
+
+    // Replay the first insertion against the current root. The sentinel now points at the spent
+    // nullifier, so the low leaf in the witness is no longer in the tree.
+    update.old_root = accumulator.root();
+    update.start_index = accumulator.next_index();
+    assert!(run_test(&update).is_err());
+}
+
+#[test]
+fn invalid_depth_is_rejected() {
+    assert!(NullifierAccumulator::new(0).is_err());
+    assert!(NullifierAccumulator::new(33).is_err());
+}
+
+#[test]
+fn prove_non_membership() {
+    let mut accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    accumulator
+        .insert_batch(&[nullifier([5, 0, 0, 0]), nullifier([1, 2, 3, 4])])
+        .unwrap();
+
+    // Between two spent nullifiers, and after the largest one.
+    for unspent in [nullifier([3, 0, 0, 0]), nullifier([6, 0, 0, 0])] {
+        let proof = accumulator.prove_non_membership(&unspent).unwrap();
+        proof.verify(&unspent).unwrap();
+        assert_eq!(proof.root, accumulator.root());
+
+        let proof = run_non_membership_test(&proof, &unspent).unwrap();
+        assert_eq!(proof.public_inputs, accumulator.root());
+    }
+}
+
+#[test]
+fn spent_nullifier_has_no_non_membership_proof() {
+    let mut accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    let spent = nullifier([5, 0, 0, 0]);
+    accumulator.insert_batch(&[spent]).unwrap();
+    assert!(accumulator.prove_non_membership(&spent).is_err());
+    assert!(accumulator
+        .prove_non_membership(&nullifier([0, 0, 0, 0]))
+        .is_err());
+
+    // The low leaf of a nullifier just below the spent one doesn't enclose the spent one.
+    let proof = accumulator
+        .prove_non_membership(&nullifier([4, 0, 0, 0]))
+        .unwrap();
+    assert!(proof.verify(&spent).is_err());
+    assert!(run_non_membership_test(&proof, &spent).is_err());
+
+    // A proof against the root before the nullifier was spent is only valid for that root.
+    let stale = NullifierAccumulator::new(DEPTH)
+        .unwrap()
+        .prove_non_membership(&spent)
+        .unwrap();
+    assert_ne!(stale.root, accumulator.root());
+    let mut replayed = stale;
+    replayed.root = accumulator.root();
+    assert!(replayed.verify(&spent).is_err());
+    assert!(run_non_membership_test(&replayed, &spent).is_err());
+}
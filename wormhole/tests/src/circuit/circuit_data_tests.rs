@@ -1,54 +1,38 @@
 use anyhow::Context;
 use anyhow::Result;
-use plonky2::field::types::Field;
-use plonky2::hash::poseidon::PoseidonHash;
 use plonky2::plonk::circuit_data::CircuitConfig;
-use plonky2::plonk::config::{Hasher, PoseidonGoldilocksConfig};
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
 use plonky2::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
 use rand::RngCore;
-use serde::de::DeserializeOwned;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{env, fs};
 use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
 use wormhole_circuit::circuit::{circuit_data_from_bytes, circuit_data_to_bytes};
+use wormhole_circuit::client::{fetch_inputs, Deposit};
 use wormhole_circuit::inputs::{CircuitInputs, PrivateCircuitInputs, PublicCircuitInputs};
 use wormhole_circuit::nullifier::Nullifier;
-use wormhole_circuit::storage_proof::ProcessedStorageProof;
+use wormhole_circuit::storage_proof::{leaf::LeafInputs, ProcessedStorageProof};
 use wormhole_circuit::substrate_account::SubstrateAccount;
+use wormhole_circuit::types::{AccountId, RootHash};
 use wormhole_circuit::unspendable_account::UnspendableAccount;
 use wormhole_prover::WormholeProver;
 use wormhole_verifier::WormholeVerifier;
-use zk_circuits_common::circuit::{TransferProofJson, D, F};
-use zk_circuits_common::utils::u64_to_felts;
-use zk_circuits_common::utils::{digest_felts_to_bytes, u128_to_felts};
-
-/// Extract the last valid JSON object of type T from an arbitrary stdout blob.
-/// Robust against extra logs before/after the JSON.
-fn extract_last_json<T: DeserializeOwned>(s: &str) -> Result<T> {
-    let bytes = s.as_bytes();
-    let mut last_ok: Option<T> = None;
-
-    for i in 0..bytes.len() {
-        if bytes[i] == b'{' {
-            let slice = &s[i..];
-            let mut de = serde_json::Deserializer::from_str(slice);
-            if let Ok(val) = T::deserialize(&mut de) {
-                // We consumed a full JSON object starting at i. Keep the latest one.
-                last_ok = Some(val);
-            }
-        }
-    }
+use zk_circuits_common::circuit::D;
 
-    last_ok.ok_or_else(|| anyhow::anyhow!("no valid JSON object found in stdout"))
+/// The websocket url of the local node the fuzzing test deposits to.
+fn node_url() -> String {
+    env::var("WORMHOLE_NODE_URL").unwrap_or_else(|_| "ws://127.0.0.1:9944".to_string())
 }
 
-fn run_remote_example(secret_hex: &str, amount: u128) -> Result<TransferProofJson> {
+/// Transfers `amount` from Alice into the unspendable account of `secret_hex` with the remote
+/// example.
+fn submit_deposit(secret_hex: &str, amount: u128) -> Result<()> {
     let example_dir = PathBuf::from(env::var("QUANTUS_API_CLIENT_EXAMPLE_DIR").context(
         "QUANTUS_API_CLIENT_EXAMPLE_DIR not set; run `setup_qac.sh` and `source .env.qac`",
     )?);
 
-    let output = Command::new("cargo")
+    let status = Command::new("cargo")
         .args([
             "run",
             "--release",
@@ -59,14 +43,10 @@ fn run_remote_example(secret_hex: &str, amount: u128) -> Result<TransferProofJso
             &amount.to_string(),
         ])
         .current_dir(&example_dir)
-        .output()
+        .status()
         .context("failed to run remote example")?;
-
-    // parse JSON from stdout (use your extract_last_json if you have logs)
-    let stdout = String::from_utf8(output.stdout).context("stdout not UTF-8")?;
-    let parsed: TransferProofJson =
-        serde_json::from_str(&stdout).or_else(|_| extract_last_json(&stdout))?;
-    Ok(parsed)
+    anyhow::ensure!(status.success(), "remote example failed: {}", status);
+    Ok(())
 }
 
 #[test]
@@ -145,19 +125,18 @@ fn test_prover_and_verifier_from_file_e2e() -> Result<()> {
     let funding_amount = 1000u128;
     let transfer_count = 0u64;
 
-    let mut leaf_inputs_felts = Vec::new();
-    leaf_inputs_felts.extend(&u64_to_felts(transfer_count));
-    leaf_inputs_felts.extend_from_slice(&funding_account.0);
-    leaf_inputs_felts.extend_from_slice(&unspendable_account);
-    leaf_inputs_felts.extend_from_slice(&u128_to_felts(funding_amount));
-
-    let leaf_inputs_hash = PoseidonHash::hash_no_pad(&leaf_inputs_felts);
-    let root_hash: [u8; 32] = *digest_felts_to_bytes(leaf_inputs_hash.elements);
+    let leaf_inputs = LeafInputs::new(
+        transfer_count,
+        (*funding_account).into(),
+        unspendable_account.into(),
+        funding_amount,
+    )?;
+    let root_hash = RootHash::from(leaf_inputs.hash());
 
     let exit_account = SubstrateAccount::new(&[2u8; 32])?;
     let inputs = CircuitInputs {
         private: PrivateCircuitInputs {
-            secret,
+            secret: secret.into(),
             funding_account: (*funding_account).into(),
             storage_proof: ProcessedStorageProof::new(vec![], vec![]).unwrap(),
             unspendable_account: (unspendable_account).into(),
@@ -166,7 +145,7 @@ fn test_prover_and_verifier_from_file_e2e() -> Result<()> {
         public: PublicCircuitInputs {
             funding_amount,
             nullifier: Nullifier::from_preimage(&secret, 0).hash.into(),
-            root_hash: root_hash.try_into().unwrap(),
+            root_hash,
             exit_account: (*exit_account).into(),
         },
     };
@@ -227,6 +206,14 @@ fn test_prover_and_verifier_fuzzing() -> Result<()> {
     let verifier = WormholeVerifier::new_from_files(&verifier_path, &common_path)?;
 
     const FUZZ_ITERATIONS: usize = 100;
+    // The Alice test account of the dev node, which funds the deposits.
+    const ALICE: [u8; 32] = [
+        223, 23, 232, 59, 97, 108, 223, 113, 2, 89, 54, 39, 126, 65, 248, 106, 156, 219, 7, 123,
+        213, 197, 228, 118, 177, 81, 61, 77, 23, 89, 200, 80,
+    ];
+    let alice = AccountId::try_from(ALICE).unwrap();
+    let exit_account = AccountId::try_from([2u8; 32]).unwrap();
+    let runtime = tokio::runtime::Runtime::new()?;
     let mut panic_count = 0;
 
     for i in 0..FUZZ_ITERATIONS {
@@ -246,60 +233,14 @@ fn test_prover_and_verifier_fuzzing() -> Result<()> {
 
         // wrap iteration in its own scope for error capture
         let iter_result: Result<()> = (|| {
-            // Run CLI and parse proof JSON
-            let proof_json = run_remote_example(&secret_hex, funding_amount)?;
-
-            // Convert JSON to chain values
-            let state_root_bytes: [u8; 32] = hex::decode(&proof_json.state_root)
-                .map_err(|e| anyhow::anyhow!("bad state_root hex: {}", e))?
-                .as_slice()
-                .try_into()
-                .map_err(|_| anyhow::anyhow!("state_root must be 32 bytes"))?;
-
-            let storage_proof_bytes: Vec<Vec<u8>> = proof_json
-                .storage_proof
-                .iter()
-                .map(|s| {
-                    hex::decode(s).map_err(|e| anyhow::anyhow!("bad storage_proof hex: {}", e))
-                })
-                .collect::<Result<Vec<_>>>()?;
-
-            let processed_proof =
-                ProcessedStorageProof::new(storage_proof_bytes, proof_json.indices.clone())
-                    .context("failed to build ProcessedStorageProof")?;
-
-            let funding_account = SubstrateAccount::new(&[
-                223, 23, 232, 59, 97, 108, 223, 113, 2, 89, 54, 39, 126, 65, 248, 106, 156, 219, 7,
-                123, 213, 197, 228, 118, 177, 81, 61, 77, 23, 89, 200, 80,
-            ])?; // Alice test account from dev node.
-            let unspendable_account = UnspendableAccount::from_secret(&secret).account_id;
-
-            let transfer_count_from_chain = proof_json.transfer_count;
-
-            let mut leaf_inputs_felts = Vec::new();
-            leaf_inputs_felts.push(F::from_noncanonical_u64(transfer_count_from_chain));
-            leaf_inputs_felts.extend_from_slice(&funding_account.0);
-            leaf_inputs_felts.extend_from_slice(&unspendable_account);
-            leaf_inputs_felts.extend_from_slice(&u128_to_felts(funding_amount));
-
-            let exit_account = SubstrateAccount::new(&[2u8; 32])?;
-            let inputs = CircuitInputs {
-                private: PrivateCircuitInputs {
-                    secret,
-                    funding_account: (*funding_account).into(),
-                    storage_proof: processed_proof,
-                    unspendable_account: (unspendable_account).into(),
-                    transfer_count: transfer_count_from_chain,
-                },
-                public: PublicCircuitInputs {
-                    funding_amount,
-                    nullifier: Nullifier::from_preimage(&secret, transfer_count_from_chain)
-                        .hash
-                        .into(),
-                    root_hash: state_root_bytes.try_into().unwrap(),
-                    exit_account: (*exit_account).into(),
-                },
+            submit_deposit(&secret_hex, funding_amount)?;
+
+            let deposit = Deposit {
+                funding_account: alice,
+                funding_amount,
             };
+            let inputs =
+                runtime.block_on(fetch_inputs(&node_url(), secret.into(), deposit, exit_account))?;
 
             let prover_next = prover.commit(&inputs)?;
             let proof = prover_next.prove()?;
@@ -0,0 +1,95 @@
+use test_helpers::storage_proof::{default_root_hash, TestInputs, DEFAULT_STORAGE_PROOF};
+use test_helpers::{DEFAULT_FUNDING_ACCOUNT, DEFAULT_FUNDING_AMOUNT, DEFAULT_TRANSFER_COUNT};
+use wormhole_circuit::client::{assemble_inputs, transfer_proof_key, Deposit, TRANSFER_PROOF_PREFIX};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::storage_proof::leaf::LeafInputs;
+use wormhole_circuit::types::AccountId;
+use zk_circuits_common::utils::digest_felts_to_bytes;
+
+#[cfg(test)]
+fn default_deposit() -> Deposit {
+    Deposit {
+        funding_account: AccountId::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap(),
+        funding_amount: DEFAULT_FUNDING_AMOUNT,
+    }
+}
+
+/// The nodes of the default storage proof in the unordered form of a read proof, with an
+/// unrelated node.
+#[cfg(test)]
+fn read_proof() -> Vec<Vec<u8>> {
+    let mut nodes: Vec<_> = DEFAULT_STORAGE_PROOF
+        .iter()
+        .rev()
+        .map(|node| hex::decode(node).unwrap())
+        .collect();
+    nodes.push(vec![7u8; 64]);
+    nodes
+}
+
+#[test]
+fn deposit_leaf_inputs_match_transfer() {
+    let inputs = CircuitInputs::test_inputs();
+    let leaf_inputs = default_deposit()
+        .leaf_inputs(&inputs.private.secret, DEFAULT_TRANSFER_COUNT)
+        .unwrap();
+    assert_eq!(leaf_inputs.hash(), LeafInputs::test_inputs().hash());
+
+    let key = transfer_proof_key(&leaf_inputs);
+    assert_eq!(key[..32], TRANSFER_PROOF_PREFIX);
+    assert_eq!(key[32..], *digest_felts_to_bytes(leaf_inputs.hash()));
+}
+
+#[test]
+fn assembled_inputs_match_test_inputs() {
+    let expected = CircuitInputs::test_inputs();
+    let inputs = assemble_inputs(
+        expected.private.secret,
+        &default_deposit(),
+        DEFAULT_TRANSFER_COUNT,
+        default_root_hash(),
+        read_proof(),
+        expected.public.exit_account,
+    )
+    .unwrap();
+
+    assert_eq!(inputs.public, expected.public);
+    assert_eq!(inputs.private.storage_proof.proof, expected.private.storage_proof.proof);
+    assert_eq!(inputs.private.storage_proof.indices, expected.private.storage_proof.indices);
+    assert_eq!(inputs.private.transfer_count, expected.private.transfer_count);
+    assert_eq!(inputs.private.funding_account, expected.private.funding_account);
+    assert_eq!(inputs.private.unspendable_account, expected.private.unspendable_account);
+}
+
+#[test]
+fn mismatched_deposit_is_rejected() {
+    let expected = CircuitInputs::test_inputs();
+    let assemble = |deposit: &Deposit, transfer_count| {
+        assemble_inputs(
+            expected.private.secret,
+            deposit,
+            transfer_count,
+            default_root_hash(),
+            read_proof(),
+            expected.public.exit_account,
+        )
+    };
+
+    let mut wrong_amount = default_deposit();
+    wrong_amount.funding_amount += 1;
+    assert!(assemble(&wrong_amount, DEFAULT_TRANSFER_COUNT).is_err());
+    assert!(assemble(&default_deposit(), DEFAULT_TRANSFER_COUNT + 1).is_err());
+
+    // A read proof missing a node doesn't reach the transfer record.
+    let mut nodes = read_proof();
+    nodes.remove(0);
+    let result = assemble_inputs(
+        expected.private.secret,
+        &default_deposit(),
+        DEFAULT_TRANSFER_COUNT,
+        default_root_hash(),
+        nodes,
+        expected.public.exit_account,
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,57 @@
+use plonky2::plonk::circuit_data::CircuitConfig;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::storage_proof::StorageProofParams;
+use zk_circuits_common::circuit::check_config;
+use zk_circuits_common::config::ConfigPreset;
+
+#[test]
+fn presets_round_trip_through_names() {
+    for preset in ConfigPreset::ALL {
+        assert_eq!(preset.name().parse::<ConfigPreset>().unwrap(), preset);
+    }
+    assert!("turbo".parse::<ConfigPreset>().is_err());
+}
+
+#[test]
+fn presets_keep_security_budget() {
+    for preset in ConfigPreset::ALL {
+        let fri = preset.config(false).fri_config;
+        let bits = fri.rate_bits * fri.num_query_rounds + fri.proof_of_work_bits as usize;
+        assert!(bits >= 100, "{} preset only has {} bits", preset.name(), bits);
+    }
+}
+
+#[test]
+fn fast_preset_never_enables_zero_knowledge() {
+    assert!(!ConfigPreset::Fast.config(true).zero_knowledge);
+    assert!(ConfigPreset::SmallProof.config(true).zero_knowledge);
+}
+
+#[test]
+fn presets_pass_config_check() {
+    for preset in ConfigPreset::ALL {
+        check_config(&preset.config(false)).unwrap();
+    }
+}
+
+#[test]
+fn try_new_rejects_configs_without_room_for_poseidon() {
+    let mut config = CircuitConfig::standard_recursion_config();
+    config.num_wires = 64;
+    config.num_routed_wires = 64;
+    assert!(check_config(&config).is_err());
+    assert!(WormholeCircuit::try_new(config).is_err());
+}
+
+#[test]
+fn try_with_storage_proof_params_rejects_unvalidated_params() {
+    let params = StorageProofParams {
+        max_proof_len: 0,
+        node_max_size_f: 188,
+    };
+    let result = WormholeCircuit::try_with_storage_proof_params(
+        CircuitConfig::standard_recursion_config(),
+        params,
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,77 @@
+use plonky2::field::types::Field;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use test_helpers::storage_proof::TestInputs;
+use test_helpers::{DEFAULT_FUNDING_AMOUNT, DEFAULT_TRANSFER_COUNT};
+use wormhole_circuit::deposit_vote::{
+    vote_nullifier, DepositVote, DepositVoteTargets, VOTE_INDEX, VOTE_NULLIFIER_END_INDEX,
+    VOTE_NULLIFIER_START_INDEX,
+};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::nullifier::Nullifier;
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::Digest;
+
+#[cfg(test)]
+const PROPOSAL_ID: Digest = [F::ONE, F::TWO, F::ZERO, F::ONE];
+
+#[cfg(test)]
+fn deposit_vote() -> DepositVote {
+    let inputs = CircuitInputs::test_inputs();
+    DepositVote {
+        proposal_id: PROPOSAL_ID,
+        root_hash: inputs.public.root_hash,
+        threshold: DEFAULT_FUNDING_AMOUNT,
+        vote: true,
+        secret: inputs.private.secret,
+        transfer_count: DEFAULT_TRANSFER_COUNT,
+        funding_account: inputs.private.funding_account,
+        funding_amount: DEFAULT_FUNDING_AMOUNT,
+        storage_proof: inputs.private.storage_proof,
+    }
+}
+
+#[cfg(test)]
+fn run_test(vote: &DepositVote) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = DepositVoteTargets::new(&mut builder);
+    DepositVote::circuit(&targets, &mut builder);
+
+    vote.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn prove_deposit_vote() {
+    let vote = deposit_vote();
+    let proof = run_test(&vote).unwrap();
+
+    assert_eq!(proof.public_inputs[VOTE_INDEX], F::ONE);
+    assert_eq!(
+        proof.public_inputs[VOTE_NULLIFIER_START_INDEX..VOTE_NULLIFIER_END_INDEX],
+        vote_nullifier(&vote.secret, PROPOSAL_ID)
+    );
+}
+
+#[test]
+fn threshold_above_deposit_is_rejected() {
+    let vote = DepositVote {
+        threshold: DEFAULT_FUNDING_AMOUNT + 1,
+        ..deposit_vote()
+    };
+    assert!(run_test(&vote).is_err());
+}
+
+#[test]
+fn vote_nullifier_depends_on_proposal() {
+    let secret = deposit_vote().secret;
+    let other_proposal = [F::ZERO, F::ZERO, F::ZERO, F::ONE];
+    assert_ne!(vote_nullifier(&secret, PROPOSAL_ID), vote_nullifier(&secret, other_proposal));
+}
+
+#[test]
+fn vote_nullifier_differs_from_wormhole_nullifier() {
+    let inputs = CircuitInputs::test_inputs();
+    let wormhole_nullifier =
+        Nullifier::from_preimage(inputs.private.secret.as_slice(), DEFAULT_TRANSFER_COUNT);
+    assert_ne!(vote_nullifier(&inputs.private.secret, PROPOSAL_ID), wormhole_nullifier.hash);
+}
@@ -0,0 +1,152 @@
+use plonky2::plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig};
+use wormhole_circuit::circuit::circuit_logic::{CircuitTargets, WormholeCircuit};
+use wormhole_circuit::encoding::WORMHOLE_ENCODING;
+use wormhole_circuit::inputs::PUBLIC_INPUTS_DIGEST_END_INDEX;
+use wormhole_circuit::storage_proof::MAX_PROOF_LEN;
+use zk_circuits_common::circuit::{D, F};
+use zk_circuits_common::describe::{CircuitSummary, Constraint, FragmentSummary};
+
+fn describe(targets: fn(&mut CircuitBuilder<F, D>) -> CircuitTargets) -> CircuitSummary {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    targets(&mut builder).describe().unwrap()
+}
+
+fn fragment<'a>(summary: &'a CircuitSummary, name: &str) -> &'a FragmentSummary {
+    summary
+        .fragments
+        .iter()
+        .find(|fragment| fragment.name == name)
+        .unwrap_or_else(|| panic!("no fragment {}", name))
+}
+
+fn fragment_names(summary: &CircuitSummary) -> Vec<&str> {
+    summary
+        .fragments
+        .iter()
+        .map(|fragment| fragment.name.as_str())
+        .collect()
+}
+
+#[test]
+fn public_inputs_follow_the_encoding() {
+    let summary = describe(CircuitTargets::new);
+    let layout: Vec<_> = WORMHOLE_ENCODING
+        .public_inputs
+        .iter()
+        .map(|field| (field.name, field.start, field.end))
+        .collect();
+    let described: Vec<_> = summary
+        .public_inputs
+        .iter()
+        .map(|input| (input.name.as_str(), input.start, input.end))
+        .collect();
+    assert_eq!(described, layout);
+}
+
+#[test]
+fn lists_every_fragment_of_the_circuit() {
+    let summary = describe(CircuitTargets::new);
+    assert_eq!(
+        fragment_names(&summary),
+        [
+            "nullifier",
+            "unspendable_account",
+            "storage_proof",
+            "exit_account",
+            "shared_targets"
+        ]
+    );
+
+    let summary = describe(CircuitTargets::with_relayer_fee);
+    assert!(fragment_names(&summary).contains(&"relayer_fee"));
+    let summary = describe(CircuitTargets::with_exit_account_format);
+    assert!(fragment_names(&summary).contains(&"exit_account_format"));
+    let summary = describe(|builder| CircuitTargets::with_spent_nullifier_accumulator(builder, 4));
+    assert!(fragment_names(&summary).contains(&"spent_nullifiers"));
+    let summary = describe(CircuitTargets::with_funding_commitment);
+    assert!(fragment_names(&summary).contains(&"funding_commitment"));
+    let summary = describe(CircuitTargets::with_intent_hash);
+    assert!(fragment_names(&summary).contains(&"claim_intent"));
+}
+
+#[test]
+fn nullifier_lists_its_hashes_and_range_checks() {
+    let summary = describe(CircuitTargets::new);
+    let nullifier = fragment(&summary, "nullifier");
+    assert_eq!(
+        nullifier.constraints,
+        [
+            Constraint::RangeCheck {
+                value: "NULLIFIER_SALT, secret, transfer_count".to_string(),
+                bits: 32,
+            },
+            Constraint::Hash {
+                hash: "Poseidon".to_string(),
+                inputs: ["NULLIFIER_SALT", "secret", "transfer_count"]
+                    .map(String::from)
+                    .to_vec(),
+                output: "inner_hash".to_string(),
+            },
+            Constraint::Hash {
+                hash: "Poseidon".to_string(),
+                inputs: vec!["inner_hash".to_string()],
+                output: "computed_hash".to_string(),
+            },
+            Constraint::Equal {
+                left: "computed_hash".to_string(),
+                right: "hash".to_string(),
+            },
+        ]
+    );
+    assert_eq!(nullifier.num_hashes(), 2);
+}
+
+#[test]
+fn optional_features_change_the_summary() {
+    let summary = describe(|builder| CircuitTargets::new_with_nullifier_migration(builder, 4));
+    assert_eq!(fragment(&summary, "nullifier").num_hashes(), 4);
+
+    let summary = describe(CircuitTargets::with_destination_chain);
+    let Constraint::Hash { inputs, .. } = &fragment(&summary, "unspendable_account").constraints[1]
+    else {
+        panic!("unspendable account doesn't start with a hash");
+    };
+    assert_eq!(inputs[1], "destination_chain_id");
+
+    let summary = describe(CircuitTargets::with_public_inputs_digest);
+    assert_eq!(summary.public_inputs.len(), 1);
+    assert_eq!(summary.public_inputs[0].end, PUBLIC_INPUTS_DIGEST_END_INDEX);
+    assert_eq!(fragment(&summary, "public_inputs_digest").num_hashes(), 1);
+}
+
+#[test]
+fn storage_proof_states_when_the_leaf_is_checked() {
+    let summary = describe(CircuitTargets::new);
+    let text = fragment(&summary, "storage_proof").to_string();
+    assert!(
+        text.contains(&format!("if proof_len < {}", MAX_PROOF_LEN)),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn summary_prints_as_text_and_json() {
+    let summary = WormholeCircuit::new(CircuitConfig::standard_recursion_config())
+        .describe()
+        .unwrap();
+    let text = summary.to_string();
+    assert!(text.starts_with("wormhole circuit\npublic inputs:\n"));
+    assert!(text.contains(
+        "    hash:  inner_hash == Poseidon(NULLIFIER_SALT || secret || transfer_count)\n"
+    ));
+    assert!(
+        text.contains("    range: leaf_inputs.transfer_count, leaf_inputs.funding_amount < 2^32\n")
+    );
+
+    let json = serde_json::to_value(&summary).unwrap();
+    let nullifier = &json["fragments"][0];
+    assert_eq!(nullifier["name"], "nullifier");
+    assert_eq!(nullifier["constraints"][1]["kind"], "hash");
+    assert_eq!(nullifier["constraints"][1]["output"], "inner_hash");
+}
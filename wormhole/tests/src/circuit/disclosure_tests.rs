@@ -0,0 +1,79 @@
+use plonky2::field::types::Field;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::disclosure::{
+    auditor_id, auditor_key_hash, decrypt_funding_account, disclosure_public_inputs,
+    DisclosureTargets, FundingDisclosure, AUDITOR_KEY_HASH_END_INDEX, AUDITOR_KEY_HASH_START_INDEX,
+};
+use wormhole_circuit::inputs::CircuitInputs;
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+#[cfg(test)]
+const AUDITOR_KEY: [u8; 32] = [7u8; 32];
+
+#[cfg(test)]
+const AUDITOR_PUBLIC_KEY: [u8; 32] = [9u8; 32];
+
+#[cfg(test)]
+fn run_test(disclosure: &FundingDisclosure) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = DisclosureTargets::new(&mut builder, auditor_id(&AUDITOR_PUBLIC_KEY));
+    FundingDisclosure::circuit(&targets, &mut builder);
+
+    disclosure.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn auditor_recovers_funding_account() {
+    let inputs = CircuitInputs::test_inputs();
+    let disclosure =
+        FundingDisclosure::new(&inputs, &AUDITOR_KEY, auditor_id(&AUDITOR_PUBLIC_KEY)).unwrap();
+    let proof = run_test(&disclosure).unwrap();
+
+    assert_eq!(
+        proof.public_inputs[AUDITOR_KEY_HASH_START_INDEX..AUDITOR_KEY_HASH_END_INDEX],
+        auditor_key_hash(&AUDITOR_KEY, auditor_id(&AUDITOR_PUBLIC_KEY))
+    );
+
+    let (nullifier, ciphertext) = disclosure_public_inputs(&proof.public_inputs).unwrap();
+    assert_eq!(nullifier, inputs.public.nullifier);
+    let funding_account = decrypt_funding_account(&AUDITOR_KEY, nullifier, ciphertext);
+    assert_eq!(funding_account, inputs.private.funding_account);
+}
+
+#[test]
+fn other_key_does_not_recover_funding_account() {
+    let inputs = CircuitInputs::test_inputs();
+    let disclosure =
+        FundingDisclosure::new(&inputs, &AUDITOR_KEY, auditor_id(&AUDITOR_PUBLIC_KEY)).unwrap();
+    let proof = run_test(&disclosure).unwrap();
+
+    let (nullifier, ciphertext) = disclosure_public_inputs(&proof.public_inputs).unwrap();
+    let funding_account = decrypt_funding_account(&[8u8; 32], nullifier, ciphertext);
+    assert_ne!(funding_account, inputs.private.funding_account);
+}
+
+#[test]
+fn ciphertext_of_other_account_is_rejected() {
+    let inputs = CircuitInputs::test_inputs();
+    let mut disclosure =
+        FundingDisclosure::new(&inputs, &AUDITOR_KEY, auditor_id(&AUDITOR_PUBLIC_KEY)).unwrap();
+    disclosure.ciphertext[0] += F::from_canonical_u64(1);
+
+    assert!(run_test(&disclosure).is_err());
+}
+
+#[test]
+fn disclosure_for_other_auditor_is_rejected() {
+    let inputs = CircuitInputs::test_inputs();
+    let other_auditor = auditor_id(&[10u8; 32]);
+    let disclosure = FundingDisclosure::new(&inputs, &AUDITOR_KEY, other_auditor).unwrap();
+    assert!(run_test(&disclosure).is_err());
+
+    // The key hash registered for the other auditor doesn't match a disclosure for this one.
+    let mut disclosure =
+        FundingDisclosure::new(&inputs, &AUDITOR_KEY, auditor_id(&AUDITOR_PUBLIC_KEY)).unwrap();
+    disclosure.auditor_key_hash = auditor_key_hash(&AUDITOR_KEY, other_auditor);
+    assert!(run_test(&disclosure).is_err());
+}
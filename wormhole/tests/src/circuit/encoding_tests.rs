@@ -0,0 +1,174 @@
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::iop::target::Target;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use wormhole_circuit::circuit::circuit_logic::{CircuitTargets, WormholeCircuit};
+use wormhole_circuit::encoding::{ValueLayout, WORMHOLE_ENCODING};
+use wormhole_circuit::inputs::{
+    CONTEXT_END_INDEX, DESTINATION_CHAIN_ID_END_INDEX, EXIT_ACCOUNT_FORMAT_END_INDEX,
+    EXIT_AMOUNT_END_INDEX, FUNDING_COMMITMENT_END_INDEX, INTENT_HASH_END_INDEX,
+    NULLIFIER_IS_V2_END_INDEX, PUBLIC_INPUTS_DIGEST_END_INDEX, PUBLIC_INPUTS_FELTS_LEN,
+    SPENT_NULLIFIERS_ROOT_END_INDEX,
+};
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::utils::{
+    digest_bytes_to_felts, injective_bytes_to_felts, u128_to_felts, u64_to_felts, BytesDigest,
+};
+
+#[test]
+fn field_order_matches_goldilocks() {
+    assert_eq!(WORMHOLE_ENCODING.field.order, F::ORDER.to_string());
+}
+
+#[test]
+fn public_inputs_are_contiguous_and_match_encodings() {
+    let mut next = 0;
+    for field in WORMHOLE_ENCODING.public_inputs {
+        assert_eq!(field.start, next, "gap before {}", field.name);
+        let encoding = WORMHOLE_ENCODING
+            .encoding(field.encoding)
+            .unwrap_or_else(|| panic!("unknown encoding {}", field.encoding));
+        assert_eq!(Some(field.end - field.start), encoding.num_felts);
+        next = field.end;
+    }
+}
+
+#[test]
+fn limb_layouts_match_conversions() {
+    let u128_encoding = WORMHOLE_ENCODING.encoding("u128").unwrap();
+    assert_eq!(u128_encoding.layout, ValueLayout::BigEndianLimbs { limb_bits: 32 });
+    let felts = u128_to_felts(0x00000001_00000002_00000003_00000004);
+    assert_eq!(felts, [1, 2, 3, 4].map(F::from_canonical_u64));
+
+    let felts = u64_to_felts(0x00000001_00000002);
+    assert_eq!(felts, [1, 2].map(F::from_canonical_u64));
+}
+
+#[test]
+fn chunk_layouts_match_conversions() {
+    let mut bytes = [0u8; 32];
+    bytes[0] = 1;
+    bytes[9] = 2;
+    let felts = digest_bytes_to_felts(BytesDigest::try_from(bytes).unwrap());
+    assert_eq!(felts[0].to_canonical_u64(), 1);
+    assert_eq!(felts[1].to_canonical_u64(), 2 << 8);
+
+    let felts = injective_bytes_to_felts(&[1, 0, 0, 0, 0, 2]);
+    assert_eq!(felts, [F::ONE, F::from_canonical_u64(2 << 8)]);
+}
+
+#[test]
+fn descriptor_serializes_to_json() {
+    let json = serde_json::to_value(WORMHOLE_ENCODING).unwrap();
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["public_inputs"][2]["name"], "funding_amount");
+    assert_eq!(json["public_inputs"][2]["encoding"], "u128");
+    assert_eq!(json["encodings"][2]["layout"]["kind"], "little_endian_chunks");
+    assert_eq!(json["encodings"][2]["layout"]["chunk_bytes"], 8);
+}
+
+#[test]
+fn fragments_register_public_inputs_in_layout() {
+    let (mut builder, _) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = CircuitTargets::new(&mut builder);
+    let public_inputs: Vec<Target> = [
+        &targets.nullifier.hash.elements[..],
+        &targets.storage_proof.root_hash.elements,
+        &targets.storage_proof.leaf_inputs.funding_amount,
+        &targets.exit_account.address.elements,
+    ]
+    .concat();
+    targets.check_public_input_layout(&public_inputs).unwrap();
+
+    // Registering the root hash before the nullifier shifts both.
+    let mut swapped = public_inputs.clone();
+    swapped[..8].rotate_left(4);
+    let err = targets.check_public_input_layout(&swapped).unwrap_err();
+    assert!(err.to_string().contains("nullifier"));
+
+    let err = targets
+        .check_public_input_layout(&public_inputs[..12])
+        .unwrap_err();
+    assert!(err.to_string().contains("layout has 16"));
+}
+
+#[test]
+fn optional_features_can_not_be_combined() {
+    let (mut builder, _) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let mut targets = CircuitTargets::with_context(&mut builder);
+    targets.check_optional_features().unwrap();
+
+    // Both would start right after the exit account.
+    let chain_id = CircuitTargets::with_destination_chain(&mut builder).destination_chain_id;
+    targets.destination_chain_id = chain_id;
+    let err = targets.check_optional_features().unwrap_err();
+    assert!(err.to_string().contains("context, destination_chain_id"));
+    assert!(targets.describe().is_err());
+}
+
+#[test]
+fn built_circuits_have_consistent_public_input_layout() {
+    let config = CircuitConfig::standard_recursion_config();
+    for circuit in [
+        WormholeCircuit::new(config.clone()),
+        WormholeCircuit::with_nullifier_migration(config.clone(), 4),
+        WormholeCircuit::with_context(config.clone()),
+        WormholeCircuit::with_destination_chain(config.clone()),
+        WormholeCircuit::with_public_inputs_digest(config.clone()),
+        WormholeCircuit::with_exit_account_format(config.clone()),
+        WormholeCircuit::with_spent_nullifier_accumulator(config.clone(), 4).unwrap(),
+        WormholeCircuit::with_funding_commitment(config.clone()),
+        WormholeCircuit::with_intent_hash(config),
+    ] {
+        let targets = circuit.targets();
+        let data = circuit.build_circuit();
+        targets
+            .check_public_input_layout(&data.prover_only.public_inputs)
+            .unwrap();
+    }
+}
+
+#[test]
+fn num_public_inputs_match_layout_constants() {
+    let config = CircuitConfig::standard_recursion_config();
+    for (circuit, expected) in [
+        (WormholeCircuit::new(config.clone()), PUBLIC_INPUTS_FELTS_LEN),
+        (
+            WormholeCircuit::with_nullifier_migration(config.clone(), 4),
+            NULLIFIER_IS_V2_END_INDEX,
+        ),
+        (WormholeCircuit::with_context(config.clone()), CONTEXT_END_INDEX),
+        (
+            WormholeCircuit::with_destination_chain(config.clone()),
+            DESTINATION_CHAIN_ID_END_INDEX,
+        ),
+        (WormholeCircuit::with_relayer_fee(config.clone()), EXIT_AMOUNT_END_INDEX),
+        (
+            WormholeCircuit::with_exit_account_format(config.clone()),
+            EXIT_ACCOUNT_FORMAT_END_INDEX,
+        ),
+        (
+            WormholeCircuit::with_spent_nullifier_accumulator(config.clone(), 4).unwrap(),
+            SPENT_NULLIFIERS_ROOT_END_INDEX,
+        ),
+        (
+            WormholeCircuit::with_funding_commitment(config.clone()),
+            FUNDING_COMMITMENT_END_INDEX,
+        ),
+        (
+            WormholeCircuit::with_intent_hash(config.clone()),
+            INTENT_HASH_END_INDEX,
+        ),
+        (
+            WormholeCircuit::with_public_inputs_digest(config),
+            PUBLIC_INPUTS_DIGEST_END_INDEX,
+        ),
+    ] {
+        assert_eq!(circuit.num_public_inputs(), expected);
+        assert_eq!(circuit.targets().num_public_inputs(), expected);
+        let data = circuit.build_circuit();
+        assert_eq!(data.common.num_public_inputs, expected);
+    }
+
+    let last = WORMHOLE_ENCODING.public_inputs.last().unwrap();
+    assert_eq!(last.end, PUBLIC_INPUTS_FELTS_LEN);
+}
@@ -0,0 +1,133 @@
+use parity_scale_codec::{Decode, Encode};
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::codec::ByteCodec;
+use wormhole_circuit::envelope::format::{EnvelopeFormat, FORMAT_HEADER_LEN, FORMAT_MAGIC};
+use wormhole_circuit::envelope::{
+    ProofEnvelope, ENVELOPE_HEADER_LEN, ENVELOPE_MAGIC, ENVELOPE_VERSION,
+};
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use zk_circuits_common::utils::BytesDigest;
+
+#[cfg(test)]
+fn test_envelope() -> ProofEnvelope {
+    ProofEnvelope {
+        verifier_digest: BytesDigest::try_from([7u8; 32]).unwrap(),
+        proof: vec![1, 2, 3, 4],
+    }
+}
+
+#[test]
+fn envelope_codec() {
+    let envelope = test_envelope();
+    let bytes = envelope.to_bytes();
+    assert_eq!(bytes.len(), ENVELOPE_HEADER_LEN + 4);
+    assert_eq!(bytes[..4], ENVELOPE_MAGIC);
+
+    let decoded = ProofEnvelope::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded, envelope);
+}
+
+#[test]
+fn envelope_with_invalid_magic_fails() {
+    let mut bytes = test_envelope().to_bytes();
+    bytes[0] ^= 0xFF;
+    let result = ProofEnvelope::from_bytes(&bytes);
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "invalid proof envelope magic bytes"
+    );
+}
+
+#[test]
+fn envelope_with_unknown_version_fails() {
+    let mut bytes = test_envelope().to_bytes();
+    bytes[4] = 0xFF;
+    let result = ProofEnvelope::from_bytes(&bytes);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("unsupported proof envelope version"));
+}
+
+#[test]
+fn truncated_envelope_fails() {
+    let bytes = test_envelope().to_bytes();
+    let result = ProofEnvelope::from_bytes(&bytes[..ENVELOPE_HEADER_LEN - 1]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn envelope_scale_codec() {
+    let envelope = test_envelope();
+    let bytes = envelope.encode();
+    assert_eq!(bytes[0], ENVELOPE_VERSION);
+    assert_eq!(ProofEnvelope::decode(&mut &bytes[..]).unwrap(), envelope);
+
+    let mut unsupported = bytes;
+    unsupported[0] = ENVELOPE_VERSION + 1;
+    assert!(ProofEnvelope::decode(&mut &unsupported[..]).is_err());
+}
+
+#[test]
+fn public_inputs_scale_codec() {
+    let public_inputs = CircuitInputs::test_inputs().public;
+    let bytes = public_inputs.encode();
+    // A u128 amount followed by three digests.
+    assert_eq!(bytes.len(), 16 + 3 * 32);
+
+    let decoded = PublicCircuitInputs::decode(&mut &bytes[..]).unwrap();
+    assert_eq!(decoded, public_inputs);
+}
+
+#[test]
+fn digest_scale_decoding_rejects_non_canonical_chunks() {
+    let digest = BytesDigest::try_from([7u8; 32]).unwrap();
+    assert_eq!(BytesDigest::decode(&mut &digest.encode()[..]).unwrap(), digest);
+
+    let non_canonical = [0xffu8; 32];
+    assert!(BytesDigest::decode(&mut &non_canonical[..]).is_err());
+}
+
+#[test]
+fn envelope_formats_round_trip() {
+    let envelope = test_envelope();
+    assert_eq!(EnvelopeFormat::ALL.len(), 4);
+    for &format in EnvelopeFormat::ALL {
+        let bytes = format.encode(&envelope).unwrap();
+        assert_eq!(bytes[..4], FORMAT_MAGIC);
+        assert_eq!(bytes[4], format.tag());
+        assert_eq!(EnvelopeFormat::from_tag(format.tag()).unwrap(), format);
+
+        let (decoded_format, decoded) = EnvelopeFormat::decode(&bytes).unwrap();
+        assert_eq!(decoded_format, format, "{}", format.name());
+        assert_eq!(decoded, envelope, "{}", format.name());
+    }
+}
+
+#[test]
+fn json_envelope_is_text() {
+    let bytes = EnvelopeFormat::JsonHex.encode(&test_envelope()).unwrap();
+    let text = std::str::from_utf8(&bytes).unwrap();
+    assert!(text.starts_with("QPWFj{"));
+
+    let json: serde_json::Value = serde_json::from_str(&text[FORMAT_HEADER_LEN..]).unwrap();
+    assert_eq!(json["version"], ENVELOPE_VERSION);
+    assert_eq!(json["proof"], "0x01020304");
+}
+
+#[test]
+fn envelope_format_header_is_checked() {
+    let bytes = EnvelopeFormat::Cbor.encode(&test_envelope()).unwrap();
+
+    let err = EnvelopeFormat::decode(&bytes[FORMAT_HEADER_LEN..]).unwrap_err();
+    assert!(err.to_string().contains("missing proof envelope format header"));
+
+    let mut unknown = bytes.clone();
+    unknown[4] = b'x';
+    assert!(EnvelopeFormat::decode(&unknown).is_err());
+
+    // A payload in another format than the header names is rejected.
+    let mut mislabeled = bytes;
+    mislabeled[4] = EnvelopeFormat::Bincode.tag();
+    assert!(EnvelopeFormat::decode(&mislabeled).is_err());
+}
@@ -0,0 +1,111 @@
+use wormhole_circuit::errors::{catalog_entry, error_catalog};
+use wormhole_circuit::hex_util::HexError;
+use wormhole_circuit::storage_proof::leaf::LeafError;
+use wormhole_circuit::storage_proof::StorageProofError;
+use zk_circuits_common::circuit::ShapeError;
+use zk_circuits_common::utils::DigestError;
+
+#[test]
+fn catalog_codes_are_sorted_and_unique() {
+    let codes: Vec<&str> = error_catalog().iter().map(|entry| entry.code).collect();
+    let mut sorted = codes.clone();
+    sorted.sort_unstable();
+    sorted.dedup();
+    assert_eq!(codes, sorted);
+
+    for entry in error_catalog() {
+        assert!(!entry.description.is_empty(), "{} has no description", entry.code);
+        assert!(!entry.typical_cause.is_empty(), "{} has no cause", entry.code);
+    }
+}
+
+#[test]
+fn every_structured_error_is_cataloged() {
+    let codes = [
+        DigestError::ChunkOutOfFieldRange {
+            chunk_index: 0,
+            value: u64::MAX,
+        }
+        .code(),
+        DigestError::InvalidLength {
+            expected: 32,
+            got: 31,
+        }
+        .code(),
+        HexError::OddLength { len: 3 }.code(),
+        HexError::InvalidCharacter {
+            index: 0,
+            character: 'g',
+        }
+        .code(),
+        HexError::UppercaseCharacter {
+            index: 0,
+            character: 'A',
+        }
+        .code(),
+        HexError::InvalidLength {
+            expected: 32,
+            got: 31,
+        }
+        .code(),
+        LeafError::StaleTransferCount { given: 3, found: 4 }.code(),
+        LeafError::Mismatch {
+            transfer_count: 4,
+            window: 16,
+        }
+        .code(),
+        ShapeError::LengthMismatch {
+            fragment: "nullifier",
+            field: "secret",
+            expected: 8,
+            got: 10,
+        }
+        .code(),
+        ShapeError::TooLong {
+            fragment: "storage_proof",
+            field: "proof",
+            max: 20,
+            got: 21,
+        }
+        .code(),
+        ShapeError::TooShort {
+            fragment: "vote",
+            field: "merkle_siblings",
+            min: 2,
+            got: 1,
+        }
+        .code(),
+        StorageProofError::LengthMismatch {
+            proof_len: 2,
+            indices_len: 1,
+        }
+        .code(),
+        StorageProofError::TooManyNodes { len: 21, max: 20 }.code(),
+        StorageProofError::NodeTooLarge {
+            node: 0,
+            len: 1000,
+            max: 752,
+        }
+        .code(),
+        StorageProofError::UnalignedIndex { node: 0, index: 1 }.code(),
+        StorageProofError::IndexOutOfBounds {
+            node: 0,
+            index: 90,
+            node_len: 96,
+        }
+        .code(),
+    ];
+
+    for code in codes {
+        assert!(catalog_entry(code).is_some(), "{} is not cataloged", code);
+    }
+    assert_eq!(codes.len(), error_catalog().len());
+}
+
+#[test]
+fn catalog_serializes_to_json() {
+    let json = serde_json::to_value(error_catalog()).unwrap();
+    let first = &json[0];
+    assert_eq!(first["code"], "digest.chunk_out_of_field_range");
+    assert!(first["typical_cause"].is_string());
+}
@@ -0,0 +1,66 @@
+use plonky2::field::types::{Field, Field64};
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::evm::{
+    evm_word_to_u128, evm_words_to_felts, felts_to_evm_words, u128_to_evm_word, EvmWord,
+};
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::utils::u128_to_felts;
+
+#[test]
+fn public_inputs_round_trip_through_evm_words() {
+    let public = CircuitInputs::test_inputs().public;
+    let words = public.to_evm_words();
+    assert_eq!(PublicCircuitInputs::from_evm_words(&words).unwrap(), public);
+
+    let bytes = public.to_evm_bytes();
+    assert_eq!(bytes.len(), 128);
+    assert_eq!(&bytes[64..96], &words[2]);
+    assert_eq!(PublicCircuitInputs::from_evm_bytes(&bytes).unwrap(), public);
+    assert!(PublicCircuitInputs::from_evm_bytes(&bytes[1..]).is_err());
+}
+
+#[test]
+fn digests_keep_their_bytes() {
+    let public = CircuitInputs::test_inputs().public;
+    let words = public.to_evm_words();
+    assert_eq!(words[0], *public.nullifier);
+    assert_eq!(words[1], *public.root_hash);
+    assert_eq!(words[3], *public.exit_account);
+}
+
+#[test]
+fn amount_is_a_big_endian_uint256() {
+    let amount = 0x00000001_00000002_00000003_00000004u128;
+    let word = u128_to_evm_word(amount);
+    let mut expected = [0u8; 32];
+    expected[16..].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4]);
+    assert_eq!(word, expected);
+    assert_eq!(evm_word_to_u128(&word).unwrap(), amount);
+
+    // The limbs of the public inputs appear in the same order.
+    let limbs = u128_to_felts(amount);
+    for (i, limb) in limbs.iter().enumerate() {
+        let bytes = &word[16 + 4 * i..20 + 4 * i];
+        assert_eq!(
+            F::from_canonical_u32(u32::from_be_bytes(bytes.try_into().unwrap())),
+            *limb
+        );
+    }
+
+    let mut too_large = word;
+    too_large[15] = 1;
+    assert!(evm_word_to_u128(&too_large).is_err());
+}
+
+#[test]
+fn felts_round_trip_through_evm_words() {
+    let felts = [F::ZERO, F::ONE, F::NEG_ONE, F::from_canonical_u64(1 << 40)];
+    let words = felts_to_evm_words(&felts);
+    assert_eq!(words[2][24..], (F::ORDER - 1).to_be_bytes());
+    assert_eq!(evm_words_to_felts(&words).unwrap(), felts);
+
+    let mut order: EvmWord = [0; 32];
+    order[24..].copy_from_slice(&F::ORDER.to_be_bytes());
+    assert!(evm_words_to_felts(&[order]).is_err());
+}
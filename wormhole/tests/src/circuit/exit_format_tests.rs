@@ -0,0 +1,81 @@
+use plonky2::{field::types::Field, plonk::proof::ProofWithPublicInputs};
+use wormhole_circuit::{
+    exit_format::{ExitAccountFormat, ExitAccountFormatTargets, EVM_ADDRESS_LEN},
+    inputs::EXIT_ACCOUNT_FORMAT_END_INDEX,
+    substrate_account::{ExitAccountTargets, SubstrateAccount},
+    types::AccountId,
+};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+
+const EVM_ADDRESS: [u8; EVM_ADDRESS_LEN] = [
+    0xd8, 0xda, 0x6b, 0xf2, 0x69, 0x64, 0xaf, 0x9d, 0x7e, 0xed, 0x9e, 0x03, 0xe5, 0x34, 0x15, 0xd3,
+    0x7a, 0xa9, 0x60, 0x45,
+];
+
+#[cfg(test)]
+fn run_test(
+    exit_account: AccountId,
+    format: ExitAccountFormat,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let exit_account_targets = ExitAccountTargets::new(&mut builder);
+    let targets = ExitAccountFormatTargets::new(&mut builder, exit_account_targets.address);
+    SubstrateAccount::circuit(&exit_account_targets, &mut builder);
+    ExitAccountFormat::circuit(&targets, &mut builder);
+
+    SubstrateAccount(exit_account.to_felts()).fill_targets(&mut pw, exit_account_targets)?;
+    format.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn evm_address_round_trip() {
+    let exit_account = ExitAccountFormat::evm_exit_account(EVM_ADDRESS);
+    assert_eq!(&exit_account[..5], &EVM_ADDRESS[..5]);
+    assert_eq!(&exit_account[5..8], &[0, 0, 0]);
+    assert_eq!(ExitAccountFormat::evm_address(&exit_account), Some(EVM_ADDRESS));
+    ExitAccountFormat::Evm.check(&exit_account).unwrap();
+
+    let substrate_account = AccountId::try_from([42u8; 32]).unwrap();
+    assert_eq!(ExitAccountFormat::evm_address(&substrate_account), None);
+    assert!(ExitAccountFormat::Evm.check(&substrate_account).is_err());
+    ExitAccountFormat::Substrate.check(&substrate_account).unwrap();
+}
+
+#[test]
+fn evm_exit_account_proves() {
+    let exit_account = ExitAccountFormat::evm_exit_account(EVM_ADDRESS);
+    let proof = run_test(exit_account, ExitAccountFormat::Evm).unwrap();
+    // The exit account followed by the format flag.
+    assert_eq!(proof.public_inputs.len(), 5);
+    assert_eq!(proof.public_inputs[4], F::ONE);
+
+    // Any account, including an encoded EVM address, can be used as a substrate account.
+    run_test(exit_account, ExitAccountFormat::Substrate).unwrap();
+    let substrate_account = AccountId::try_from([42u8; 32]).unwrap();
+    run_test(substrate_account, ExitAccountFormat::Substrate).unwrap();
+}
+
+#[test]
+fn substrate_account_is_not_an_evm_address() {
+    let substrate_account = AccountId::try_from([42u8; 32]).unwrap();
+    let result = std::panic::catch_unwind(|| run_test(substrate_account, ExitAccountFormat::Evm));
+    assert!(!matches!(result, Ok(Ok(_))));
+}
+
+#[test]
+fn format_from_public_inputs() {
+    let mut public_inputs = vec![F::ZERO; EXIT_ACCOUNT_FORMAT_END_INDEX];
+    assert_eq!(
+        ExitAccountFormat::try_from_public_inputs(&public_inputs).unwrap(),
+        ExitAccountFormat::Substrate
+    );
+    public_inputs[EXIT_ACCOUNT_FORMAT_END_INDEX - 1] = F::ONE;
+    assert_eq!(
+        ExitAccountFormat::try_from_public_inputs(&public_inputs).unwrap(),
+        ExitAccountFormat::Evm
+    );
+    public_inputs[EXIT_ACCOUNT_FORMAT_END_INDEX - 1] = F::TWO;
+    assert!(ExitAccountFormat::try_from_public_inputs(&public_inputs).is_err());
+    assert!(ExitAccountFormat::try_from_public_inputs(&public_inputs[1..]).is_err());
+}
@@ -0,0 +1,67 @@
+use plonky2::{
+    field::types::Field, iop::witness::WitnessWrite, plonk::proof::ProofWithPublicInputs,
+};
+use wormhole_circuit::{
+    funding_commitment::{FundingAccountOpening, FundingCommitmentTargets},
+    inputs::FUNDING_COMMITMENT_END_INDEX,
+    types::{AccountId, SecretBytes},
+};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::felts_to_hashout;
+
+#[cfg(test)]
+fn run_test(
+    opening: &FundingAccountOpening,
+    funding_account: AccountId,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let funding_account_target = builder.add_virtual_hash();
+    let targets = FundingCommitmentTargets::new(&mut builder, funding_account_target);
+    FundingAccountOpening::circuit(&targets, &mut builder);
+
+    pw.set_hash_target(
+        funding_account_target,
+        felts_to_hashout(&funding_account.to_felts()),
+    )?;
+    opening.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn commitment_proves() {
+    let funding_account = AccountId::try_from([3u8; 32]).unwrap();
+    let opening = FundingAccountOpening::new(funding_account, SecretBytes::new([9u8; 32]));
+    let proof = run_test(&opening, funding_account).unwrap();
+    assert_eq!(proof.public_inputs, opening.commitment().to_vec());
+    opening
+        .verify(&proof.public_inputs.clone().try_into().unwrap())
+        .unwrap();
+}
+
+#[test]
+fn commitment_of_another_funding_account_fails() {
+    let funding_account = AccountId::try_from([3u8; 32]).unwrap();
+    let other_account = AccountId::try_from([4u8; 32]).unwrap();
+    let opening = FundingAccountOpening::new(other_account, SecretBytes::new([9u8; 32]));
+    assert!(run_test(&opening, funding_account).is_err());
+}
+
+#[test]
+fn blinding_hides_the_funding_account() {
+    let funding_account = AccountId::try_from([3u8; 32]).unwrap();
+    let a = FundingAccountOpening::new(funding_account, SecretBytes::new([1u8; 32]));
+    let b = FundingAccountOpening::new(funding_account, SecretBytes::new([2u8; 32]));
+    assert_ne!(a.commitment(), b.commitment());
+    assert!(a.verify(&b.commitment()).is_err());
+}
+
+#[test]
+fn commitment_is_parsed_from_public_inputs() {
+    let mut pis = vec![F::ZERO; FUNDING_COMMITMENT_END_INDEX];
+    pis[FUNDING_COMMITMENT_END_INDEX - 1] = F::ONE;
+    let commitment = FundingAccountOpening::commitment_from_public_inputs(&pis).unwrap();
+    assert_eq!(commitment[3], F::ONE);
+
+    pis.pop();
+    assert!(FundingAccountOpening::commitment_from_public_inputs(&pis).is_err());
+}
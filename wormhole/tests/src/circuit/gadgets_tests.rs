@@ -0,0 +1,264 @@
+use plonky2::{field::types::Field, iop::witness::WitnessWrite};
+use zk_circuits_common::circuit::F;
+use zk_circuits_common::gadgets::{
+    add_u32_limbs, assert_less_than_or_equal, assert_u32_limbs_at_least, is_less_than,
+    range_check_u32, recompose_u32_pair, u32_limbs_carry_bits, u32_limbs_less_than,
+};
+use zk_circuits_common::utils::{u128_to_felts, FELTS_PER_U128};
+
+#[cfg(test)]
+fn prove_at_least(left: u128, right: u128) -> anyhow::Result<()> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let left_targets = builder.add_virtual_targets(FELTS_PER_U128);
+    let right_targets = builder.add_virtual_targets(FELTS_PER_U128);
+    assert_u32_limbs_at_least(&mut builder, &left_targets, &right_targets);
+
+    pw.set_target_arr(&left_targets, &u128_to_felts(left))?;
+    pw.set_target_arr(&right_targets, &u128_to_felts(right))?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw).map(|_| ())
+}
+
+#[test]
+fn limbs_at_least() {
+    prove_at_least(0, 0).unwrap();
+    prove_at_least(1, 0).unwrap();
+    prove_at_least(u128::MAX, u128::MAX).unwrap();
+    prove_at_least(1 << 64, (1 << 64) - 1).unwrap();
+}
+
+#[test]
+fn limbs_less_than_are_rejected() {
+    assert!(prove_at_least(0, 1).is_err());
+    assert!(prove_at_least((1 << 64) - 1, 1 << 64).is_err());
+    assert!(prove_at_least(u128::MAX - 1, u128::MAX).is_err());
+}
+
+#[test]
+fn limbs_less_than() {
+    let cases = [(0, 1), (1, 1), (1 << 64, (1 << 64) - 1), (u128::MAX - 1, u128::MAX)];
+    for (left, right) in cases {
+        let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+        let left_targets = builder.add_virtual_targets(FELTS_PER_U128);
+        let right_targets = builder.add_virtual_targets(FELTS_PER_U128);
+        let less_than = u32_limbs_less_than(&mut builder, &left_targets, &right_targets);
+        builder.register_public_input(less_than.target);
+
+        pw.set_target_arr(&left_targets, &u128_to_felts(left)).unwrap();
+        pw.set_target_arr(&right_targets, &u128_to_felts(right)).unwrap();
+        let proof = crate::circuit_helpers::build_and_prove_test(builder, pw).unwrap();
+        assert_eq!(proof.public_inputs, [F::from_bool(left < right)]);
+    }
+}
+
+#[test]
+fn add_limbs_carries_into_extra_limb() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let a = builder.add_virtual_targets(FELTS_PER_U128);
+    let b = builder.add_virtual_targets(FELTS_PER_U128);
+    let sum = add_u32_limbs(&mut builder, &[a.clone(), b.clone()], FELTS_PER_U128);
+    builder.register_public_inputs(&sum);
+
+    pw.set_target_arr(&a, &u128_to_felts(u128::MAX)).unwrap();
+    pw.set_target_arr(&b, &u128_to_felts(2)).unwrap();
+    let proof = crate::circuit_helpers::build_and_prove_test(builder, pw).unwrap();
+
+    // u128::MAX + 2 = 2^128 + 1.
+    let expected = [F::ONE, F::ZERO, F::ZERO, F::ZERO, F::ONE];
+    assert_eq!(proof.public_inputs, expected);
+}
+
+#[test]
+fn add_limbs_carries_the_largest_totals() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let numbers: Vec<_> = (0..3)
+        .map(|_| builder.add_virtual_targets(FELTS_PER_U128))
+        .collect();
+    let sum = add_u32_limbs(&mut builder, &numbers, FELTS_PER_U128);
+    builder.register_public_inputs(&sum);
+
+    for number in &numbers {
+        pw.set_target_arr(number, &u128_to_felts(u128::MAX)).unwrap();
+    }
+    let proof = crate::circuit_helpers::build_and_prove_test(builder, pw).unwrap();
+
+    // 3 * u128::MAX = 2^129 + 2^128 - 3, so every limb carries 2.
+    let max = F::from_canonical_u32(u32::MAX);
+    let expected = [F::TWO, max, max, max, max - F::TWO];
+    assert_eq!(proof.public_inputs, expected);
+}
+
+/// Proves that `total = low + carry * 2^32` under the range checks of the carry split of
+/// [`add_u32_limbs`], with `carry` bounded to `carry_bits`. The split itself is filled by a
+/// generator, which never picks a dishonest one, so a malicious split is set by hand here.
+#[cfg(test)]
+fn prove_carry_split(total: u64, low: u64, carry: u64, carry_bits: usize) -> anyhow::Result<()> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let [total_target, low_target, carry_target] =
+        core::array::from_fn(|_| builder.add_virtual_target());
+    builder.range_check(low_target, 32);
+    builder.range_check(carry_target, carry_bits);
+    let two_pow_32 = builder.constant(F::from_canonical_u64(1 << 32));
+    let recomposed = builder.mul_add(carry_target, two_pow_32, low_target);
+    builder.connect(recomposed, total_target);
+
+    pw.set_target(total_target, F::from_canonical_u64(total))?;
+    pw.set_target(low_target, F::from_canonical_u64(low))?;
+    pw.set_target(carry_target, F::from_canonical_u64(carry))?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw).map(|_| ())
+}
+
+#[test]
+fn add_limbs_rejects_wrapped_carry() {
+    // `(total + 1) + (2^32 - 1) * 2^32 = total + p`, so this split recomposes to the total but
+    // carries `2^32 - 1` into the next limb.
+    let total = 5;
+    let (low, carry) = (total + 1, u32::MAX as u64);
+    let recomposed =
+        F::from_canonical_u64(low) + F::from_canonical_u64(carry) * F::from_canonical_u64(1 << 32);
+    assert_eq!(recomposed, F::from_canonical_u64(total));
+
+    // A 32 bit carry accepts the wrapped split, the bound of adding two numbers doesn't.
+    prove_carry_split(total, low, carry, 32).unwrap();
+    let carry_bits = u32_limbs_carry_bits(2);
+    assert!(prove_carry_split(total, low, carry, carry_bits).is_err());
+    prove_carry_split(total, total, 0, carry_bits).unwrap();
+}
+
+#[test]
+fn carry_bits_cover_the_largest_carry() {
+    assert_eq!(u32_limbs_carry_bits(0), 1);
+    assert_eq!(u32_limbs_carry_bits(1), 1);
+    assert_eq!(u32_limbs_carry_bits(2), 2);
+    assert_eq!(u32_limbs_carry_bits(3), 2);
+    assert_eq!(u32_limbs_carry_bits(4), 3);
+    assert_eq!(u32_limbs_carry_bits((1 << 31) - 1), 31);
+}
+
+#[cfg(test)]
+fn prove_range_check(values: &[u64]) -> anyhow::Result<()> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = builder.add_virtual_targets(values.len());
+    range_check_u32(&mut builder, &targets);
+
+    let values: Vec<F> = values.iter().map(|&v| F::from_canonical_u64(v)).collect();
+    pw.set_target_arr(&targets, &values)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw).map(|_| ())
+}
+
+#[test]
+fn range_check_u32_accepts_32_bit_values() {
+    // More values than fit into one row of the gate.
+    let values: Vec<u64> = (0..9).map(|i| u32::MAX as u64 - i).collect();
+    prove_range_check(&values).unwrap();
+    prove_range_check(&[0]).unwrap();
+}
+
+#[test]
+fn range_check_u32_rejects_wider_values() {
+    assert!(prove_range_check(&[1 << 32]).is_err());
+    assert!(prove_range_check(&[1, 2, 3, u64::MAX >> 1]).is_err());
+}
+
+#[test]
+fn recompose_u32_pair_is_little_endian() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let [lo, hi] = [builder.add_virtual_target(), builder.add_virtual_target()];
+    let value = recompose_u32_pair(&mut builder, lo, hi);
+    builder.register_public_input(value);
+
+    pw.set_target(lo, F::from_canonical_u32(0xdead_beef)).unwrap();
+    pw.set_target(hi, F::from_canonical_u32(0x1234_5678)).unwrap();
+    let proof = crate::circuit_helpers::build_and_prove_test(builder, pw).unwrap();
+    assert_eq!(
+        proof.public_inputs,
+        [F::from_canonical_u64(0x1234_5678_dead_beef)]
+    );
+}
+
+#[cfg(feature = "u32-gate")]
+#[test]
+fn range_check_u32_packs_checks_into_rows() {
+    use zk_circuits_common::u32_gate::U32RangeCheckGate;
+
+    let (mut builder, _) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let num_ops = U32RangeCheckGate::new_from_config(&builder.config).num_ops;
+    assert!(num_ops > 1);
+
+    let targets = builder.add_virtual_targets(4 * num_ops);
+    let before = builder.num_gates();
+    // Separate calls share partially filled gates.
+    for target in targets {
+        range_check_u32(&mut builder, &[target]);
+    }
+    assert_eq!(builder.num_gates() - before, 4);
+}
+
+#[cfg(test)]
+fn prove_is_less_than(a: u64, b: u64, num_bits: usize) -> anyhow::Result<bool> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let [a_target, b_target] = [builder.add_virtual_target(), builder.add_virtual_target()];
+    let less_than = is_less_than(&mut builder, a_target, b_target, num_bits);
+    builder.register_public_input(less_than.target);
+
+    pw.set_target(a_target, F::from_canonical_u64(a))?;
+    pw.set_target(b_target, F::from_canonical_u64(b))?;
+    let proof = crate::circuit_helpers::build_and_prove_test(builder, pw)?;
+    Ok(proof.public_inputs[0] == F::ONE)
+}
+
+#[test]
+fn is_less_than_edge_cases() {
+    let max = u32::MAX as u64;
+    let cases = [
+        (0, 0),
+        (0, 1),
+        (1, 0),
+        (7, 7),
+        (max - 1, max),
+        (max, max - 1),
+        (max, max),
+        (0, max),
+        (max, 0),
+    ];
+    for (a, b) in cases {
+        assert_eq!(prove_is_less_than(a, b, 32).unwrap(), a < b, "{} < {}", a, b);
+    }
+}
+
+#[test]
+fn is_less_than_at_widest_supported_width() {
+    let max = (1u64 << 62) - 1;
+    assert!(prove_is_less_than(max - 1, max, 62).unwrap());
+    assert!(!prove_is_less_than(max, max, 62).unwrap());
+    assert!(!prove_is_less_than(max, 0, 62).unwrap());
+}
+
+#[test]
+#[should_panic(expected = "can't compare values of 63 bits")]
+fn is_less_than_rejects_wide_values() {
+    let (mut builder, _) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let [a, b] = [builder.add_virtual_target(), builder.add_virtual_target()];
+    is_less_than(&mut builder, a, b, 63);
+}
+
+#[cfg(test)]
+fn prove_less_than_or_equal(a: u64, b: u64) -> anyhow::Result<()> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let [a_target, b_target] = [builder.add_virtual_target(), builder.add_virtual_target()];
+    assert_less_than_or_equal(&mut builder, a_target, b_target, 32);
+
+    pw.set_target(a_target, F::from_canonical_u64(a))?;
+    pw.set_target(b_target, F::from_canonical_u64(b))?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw).map(|_| ())
+}
+
+#[test]
+fn less_than_or_equal_edge_cases() {
+    let max = u32::MAX as u64;
+    for (a, b) in [(0, 0), (0, 1), (5, 5), (max - 1, max), (max, max), (0, max)] {
+        prove_less_than_or_equal(a, b).unwrap();
+    }
+    for (a, b) in [(1, 0), (max, max - 1), (max, 0)] {
+        assert!(prove_less_than_or_equal(a, b).is_err(), "{} <= {}", a, b);
+    }
+}
@@ -0,0 +1,59 @@
+use wormhole_circuit::hex_util::{decode, decode_array, decode_digest, HexError};
+
+#[test]
+fn decodes_lowercase_hex_with_optional_prefix() {
+    assert_eq!(decode("00ff10").unwrap(), [0x00, 0xff, 0x10]);
+    assert_eq!(decode("0x00ff10").unwrap(), [0x00, 0xff, 0x10]);
+    assert_eq!(decode("").unwrap(), Vec::<u8>::new());
+    assert_eq!(decode("0x").unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn rejects_non_canonical_hex() {
+    assert_eq!(decode("abc"), Err(HexError::OddLength { len: 3 }));
+    assert_eq!(
+        decode("0xaB"),
+        Err(HexError::UppercaseCharacter {
+            index: 1,
+            character: 'B'
+        })
+    );
+    assert_eq!(
+        decode(" 0a"),
+        Err(HexError::InvalidCharacter {
+            index: 0,
+            character: ' '
+        })
+    );
+    assert_eq!(
+        decode("0a0\n"),
+        Err(HexError::InvalidCharacter {
+            index: 3,
+            character: '\n'
+        })
+    );
+    // Only a lowercase prefix is stripped.
+    assert!(decode("0X0a").is_err());
+}
+
+#[test]
+fn checks_decoded_length() {
+    assert_eq!(decode_array::<2>("0x0102").unwrap(), [1, 2]);
+    assert_eq!(
+        decode_array::<2>("010203"),
+        Err(HexError::InvalidLength {
+            expected: 2,
+            got: 3
+        })
+    );
+}
+
+#[test]
+fn decodes_digests_of_canonical_field_elements() {
+    let digest = decode_digest(&"01".repeat(32)).unwrap();
+    assert_eq!(*digest, [1u8; 32]);
+
+    // The first chunk is not a canonical field element.
+    assert!(decode_digest(&"ff".repeat(32)).is_err());
+    assert!(decode_digest(&"01".repeat(31)).is_err());
+}
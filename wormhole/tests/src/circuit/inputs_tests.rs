@@ -0,0 +1,204 @@
+use plonky2::field::types::Field;
+use plonky2::plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig};
+use test_helpers::storage_proof::{
+    TestInputs, DEFAULT_ROOT_HASH, DEFAULT_STORAGE_PROOF, DEFAULT_STORAGE_PROOF_INDICIES,
+};
+use test_helpers::{
+    DEFAULT_EXIT_ACCOUNT, DEFAULT_FUNDING_ACCOUNT, DEFAULT_FUNDING_AMOUNT, DEFAULT_SECRET,
+    DEFAULT_TRANSFER_COUNT,
+};
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::hex_util;
+use wormhole_circuit::inputs::{
+    register_public_input, CircuitInputs, Private, Public, PublicCircuitInputs,
+    PUBLIC_INPUTS_FELTS_LEN,
+};
+use wormhole_circuit::nullifier::Nullifier;
+use wormhole_circuit::types::AccountId;
+use wormhole_circuit::unspendable_account::UnspendableAccount;
+use zk_circuits_common::circuit::{TransferProofJson, C, D, F, TRANSFER_PROOF_JSON_VERSION};
+
+fn transfer_proof_json() -> TransferProofJson {
+    TransferProofJson {
+        version: TRANSFER_PROOF_JSON_VERSION,
+        transfer_count: DEFAULT_TRANSFER_COUNT,
+        state_root: DEFAULT_ROOT_HASH.to_string(),
+        storage_proof: DEFAULT_STORAGE_PROOF.map(str::to_string).to_vec(),
+        indices: DEFAULT_STORAGE_PROOF_INDICIES.to_vec(),
+    }
+}
+
+fn inputs_from_json(proof: &TransferProofJson) -> anyhow::Result<CircuitInputs> {
+    CircuitInputs::from_transfer_proof_json(
+        proof,
+        hex_util::decode_array(DEFAULT_SECRET).unwrap().into(),
+        AccountId::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap(),
+        DEFAULT_FUNDING_AMOUNT,
+        AccountId::try_from(DEFAULT_EXIT_ACCOUNT).unwrap(),
+    )
+}
+
+#[test]
+fn registers_public_and_declassified_targets_in_order() {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let public = [builder.add_virtual_target(), builder.add_virtual_target()];
+    let private = builder.add_virtual_target();
+    register_public_input(&mut builder, &Public::new(public));
+    register_public_input(&mut builder, &Private::new(private).declassify());
+
+    let data = builder.build_prover::<C>();
+    assert_eq!(
+        data.prover_only.public_inputs,
+        vec![public[0], public[1], private]
+    );
+}
+
+#[test]
+fn private_targets_are_not_public_inputs() {
+    let circuit = WormholeCircuit::default();
+    let targets = circuit.targets();
+    let data = circuit.build_prover();
+
+    let public_inputs = &data.prover_only.public_inputs;
+    let private = targets
+        .nullifier
+        .secret
+        .iter()
+        .chain(targets.nullifier.transfer_count.iter())
+        .chain(targets.unspendable_account.secret.iter());
+    for target in private {
+        assert!(!public_inputs.contains(target));
+    }
+}
+
+#[test]
+fn transfer_proof_json_converts_to_the_test_inputs() {
+    let inputs = inputs_from_json(&transfer_proof_json()).unwrap();
+    let expected = CircuitInputs::test_inputs();
+    assert_eq!(inputs.public, expected.public);
+
+    let (private, expected) = (inputs.private, expected.private);
+    assert_eq!(private.transfer_count, expected.transfer_count);
+    assert_eq!(private.funding_account, expected.funding_account);
+    assert_eq!(private.unspendable_account, expected.unspendable_account);
+    assert_eq!(private.storage_proof.proof, expected.storage_proof.proof);
+    assert_eq!(
+        private.storage_proof.indices,
+        expected.storage_proof.indices
+    );
+}
+
+#[test]
+fn transfer_proof_json_round_trips_and_rejects_unknown_fields() {
+    let proof = transfer_proof_json();
+    let json = serde_json::to_value(&proof).unwrap();
+    assert_eq!(json["version"], 1);
+    let parsed: TransferProofJson = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(parsed, proof);
+
+    let mut extended = json;
+    extended["block_hash"] = serde_json::Value::from("00");
+    assert!(serde_json::from_value::<TransferProofJson>(extended).is_err());
+}
+
+#[test]
+fn transfer_proof_json_is_validated() {
+    let cases: [(&str, fn(&mut TransferProofJson)); 6] = [
+        ("version", |proof| proof.version = 2),
+        ("prefixed root", |proof| {
+            proof.state_root.insert_str(0, "0x")
+        }),
+        ("short root", |proof| proof.state_root.truncate(62)),
+        ("uppercase node", |proof| {
+            proof.storage_proof[1].make_ascii_uppercase()
+        }),
+        ("missing index", |proof| {
+            proof.indices.pop();
+        }),
+        ("index out of range", |proof| {
+            proof.indices[6] = proof.storage_proof[6].len() - 32;
+        }),
+    ];
+
+    assert!(transfer_proof_json().validate().is_ok());
+    for (case, corrupt) in cases {
+        let mut proof = transfer_proof_json();
+        corrupt(&mut proof);
+        assert!(proof.validate().is_err(), "{} was accepted", case);
+        assert!(inputs_from_json(&proof).is_err(), "{} was converted", case);
+    }
+}
+
+#[test]
+fn padding_marker_needs_zero_nullifier_and_exit_account() {
+    let padding = PublicCircuitInputs::padding();
+    assert!(padding.is_padding());
+    assert!(!CircuitInputs::test_inputs().public.is_padding());
+
+    // Other fields don't matter, but both the nullifier and exit account have to be zero.
+    let with_amount = PublicCircuitInputs {
+        funding_amount: DEFAULT_FUNDING_AMOUNT,
+        ..padding.clone()
+    };
+    assert!(with_amount.is_padding());
+    let with_exit_account = PublicCircuitInputs {
+        exit_account: AccountId::try_from(DEFAULT_EXIT_ACCOUNT).unwrap(),
+        ..padding.clone()
+    };
+    assert!(!with_exit_account.is_padding());
+    let with_nullifier = PublicCircuitInputs {
+        nullifier: CircuitInputs::test_inputs().public.nullifier,
+        ..padding
+    };
+    assert!(!with_nullifier.is_padding());
+
+    // The marker round-trips through the field elements of a leaf.
+    let felts = PublicCircuitInputs::padding().to_field_elements();
+    assert!(PublicCircuitInputs::try_from_slice(&felts)
+        .unwrap()
+        .is_padding());
+
+    // Padding leaves of circuits with more public inputs decode to the marker too.
+    let felts = vec![F::ZERO; PUBLIC_INPUTS_FELTS_LEN + 4];
+    assert_eq!(
+        PublicCircuitInputs::try_from_leaf(&felts).unwrap(),
+        PublicCircuitInputs::padding()
+    );
+    let claim = CircuitInputs::test_inputs().public;
+    assert_eq!(
+        PublicCircuitInputs::try_from_leaf(&claim.to_field_elements()).unwrap(),
+        claim
+    );
+}
+
+#[test]
+fn debug_output_redacts_secrets() {
+    let inputs = CircuitInputs::test_inputs();
+    let private = format!("{:?}", inputs.private);
+    assert!(!private.contains(DEFAULT_SECRET), "{}", private);
+    assert!(
+        !private.contains(&format!("{:?}", inputs.private.funding_account)),
+        "{}",
+        private
+    );
+    assert!(private.contains("storage_proof: <"), "{}", private);
+
+    let secret = inputs.private.secret;
+    let nullifier = Nullifier::from_preimage(secret.as_slice(), DEFAULT_TRANSFER_COUNT);
+    let debug = format!("{:?}", nullifier);
+    assert!(
+        !debug.contains(&format!("{:?}", nullifier.secret)),
+        "{}",
+        debug
+    );
+    assert!(debug.contains("secret: <8 felts redacted>"), "{}", debug);
+
+    let unspendable_account = UnspendableAccount::from_secret(&secret);
+    let debug = format!("{:?}", unspendable_account);
+    assert!(
+        !debug.contains(&format!("{:?}", unspendable_account.secret)),
+        "{}",
+        debug
+    );
+    assert!(debug.contains("secret: <8 felts redacted>"), "{}", debug);
+}
@@ -0,0 +1,113 @@
+use plonky2::{
+    field::types::Field, iop::witness::WitnessWrite, plonk::proof::ProofWithPublicInputs,
+};
+use wormhole_circuit::{
+    inputs::INTENT_HASH_END_INDEX,
+    intent::{ClaimIntent, ClaimIntentTargets},
+    types::{AccountId, NullifierHash},
+};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::{felts_to_hashout, u128_to_felts};
+
+#[cfg(test)]
+fn run_test(
+    intent: &ClaimIntent,
+    nullifier: NullifierHash,
+    exit_account: AccountId,
+    funding_amount: u128,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let nullifier_target = builder.add_virtual_hash();
+    let exit_account_target = builder.add_virtual_hash();
+    let funding_amount_targets = core::array::from_fn(|_| builder.add_virtual_target());
+    let targets = ClaimIntentTargets::new(
+        &mut builder,
+        nullifier_target,
+        exit_account_target,
+        funding_amount_targets,
+    );
+    ClaimIntent::circuit(&targets, &mut builder);
+
+    pw.set_hash_target(nullifier_target, felts_to_hashout(&nullifier.to_felts()))?;
+    pw.set_hash_target(
+        exit_account_target,
+        felts_to_hashout(&exit_account.to_felts()),
+    )?;
+    pw.set_target_arr(&funding_amount_targets, &u128_to_felts(funding_amount))?;
+    intent.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[cfg(test)]
+fn test_intent(min_amount: u128) -> ClaimIntent {
+    let nullifier = NullifierHash::try_from([1u8; 32]).unwrap();
+    let exit_account = AccountId::try_from([2u8; 32]).unwrap();
+    ClaimIntent::new(nullifier, exit_account, min_amount)
+}
+
+#[test]
+fn intent_proves() {
+    let intent = test_intent(1_000);
+    for funding_amount in [1_000, 1_001, u128::MAX] {
+        let proof = run_test(
+            &intent,
+            intent.nullifier,
+            intent.exit_account,
+            funding_amount,
+        )
+        .unwrap();
+        assert_eq!(proof.public_inputs, intent.hash().to_vec());
+    }
+}
+
+#[test]
+fn funding_amount_below_minimum_fails() {
+    let intent = test_intent(1_000);
+    assert!(intent.check_funding_amount(999).is_err());
+    assert!(run_test(&intent, intent.nullifier, intent.exit_account, 999).is_err());
+
+    // Only the most significant limb differs.
+    let intent = test_intent(1 << 100);
+    let amount = (1 << 96) - 1;
+    assert!(run_test(&intent, intent.nullifier, intent.exit_account, amount).is_err());
+}
+
+#[test]
+fn intent_of_another_claim_fails() {
+    let intent = test_intent(1_000);
+    let other_nullifier = NullifierHash::try_from([3u8; 32]).unwrap();
+    assert!(run_test(&intent, other_nullifier, intent.exit_account, 1_000).is_err());
+    let other_account = AccountId::try_from([4u8; 32]).unwrap();
+    assert!(run_test(&intent, intent.nullifier, other_account, 1_000).is_err());
+}
+
+#[test]
+fn intent_hash_binds_every_field() {
+    let intent = test_intent(1_000);
+    let other_amount = ClaimIntent {
+        min_amount: 1_001,
+        ..intent
+    };
+    let other_account = ClaimIntent {
+        exit_account: AccountId::try_from([4u8; 32]).unwrap(),
+        ..intent
+    };
+    let other_nullifier = ClaimIntent {
+        nullifier: NullifierHash::try_from([3u8; 32]).unwrap(),
+        ..intent
+    };
+    assert_ne!(intent.hash(), other_amount.hash());
+    assert_ne!(intent.hash(), other_account.hash());
+    assert_ne!(intent.hash(), other_nullifier.hash());
+}
+
+#[test]
+fn intent_hash_is_parsed_from_public_inputs() {
+    let mut pis = vec![F::ZERO; INTENT_HASH_END_INDEX];
+    pis[INTENT_HASH_END_INDEX - 1] = F::ONE;
+    let intent_hash = ClaimIntent::hash_from_public_inputs(&pis).unwrap();
+    assert_eq!(intent_hash[3], F::ONE);
+
+    pis.pop();
+    assert!(ClaimIntent::hash_from_public_inputs(&pis).is_err());
+}
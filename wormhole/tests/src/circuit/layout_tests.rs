@@ -0,0 +1,87 @@
+use plonky2::plonk::circuit_data::CircuitConfig;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::layout::{TargetIndex, TargetsLayout, TARGETS_LAYOUT_VERSION};
+
+#[cfg(test)]
+fn layout() -> TargetsLayout {
+    WormholeCircuit::new(CircuitConfig::standard_recursion_config())
+        .targets()
+        .layout()
+}
+
+#[test]
+fn layout_is_the_same_for_every_build() {
+    let layout = layout();
+    assert_eq!(layout, self::layout());
+    assert_eq!(layout.version, TARGETS_LAYOUT_VERSION);
+
+    let names: Vec<_> = layout.fragments.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "nullifier",
+            "unspendable_account",
+            "storage_proof",
+            "exit_account"
+        ]
+    );
+
+    let json = serde_json::to_string(&layout).unwrap();
+    let parsed: TargetsLayout = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, layout);
+    assert!(layout.diff(&parsed).is_empty());
+}
+
+#[test]
+fn optional_fragments_are_listed() {
+    let with_context = WormholeCircuit::with_context(CircuitConfig::standard_recursion_config())
+        .targets()
+        .layout();
+    assert_eq!(with_context.fragments.last().unwrap().name, "context");
+    assert!(
+        layout()
+            .diff(&with_context)
+            .contains(&"added fragment: context".to_string()),
+        "{:?}",
+        layout().diff(&with_context)
+    );
+}
+
+#[test]
+fn diff_reports_moved_and_resized_fields() {
+    let layout = layout();
+
+    let mut moved = layout.clone();
+    moved.fragments[0].fields[0].targets.reverse();
+    assert_eq!(layout.diff(&moved), vec!["nullifier.hash: targets moved"]);
+
+    let mut resized = layout.clone();
+    resized.fragments[2].fields[0]
+        .targets
+        .push(TargetIndex::Virtual(usize::MAX));
+    assert_eq!(
+        layout.diff(&resized),
+        vec!["storage_proof.root_hash: 4 -> 5 targets"]
+    );
+
+    let mut reordered = layout.clone();
+    reordered.fragments.swap(0, 1);
+    assert_eq!(
+        layout.diff(&reordered),
+        vec![
+            "fragment order: nullifier, unspendable_account, storage_proof, exit_account -> \
+             unspendable_account, nullifier, storage_proof, exit_account"
+        ]
+    );
+
+    let mut newer = layout.clone();
+    newer.version += 1;
+    assert_eq!(
+        layout.diff(&newer),
+        vec![format!(
+            "layout version: {} -> {}",
+            TARGETS_LAYOUT_VERSION,
+            TARGETS_LAYOUT_VERSION + 1
+        )]
+    );
+}
@@ -0,0 +1,124 @@
+use plonky2::{
+    field::types::Field, hash::poseidon::PoseidonHash, iop::witness::WitnessWrite,
+    plonk::config::Hasher,
+};
+use wormhole_circuit::accumulator::{IndexedLeaf, NullifierAccumulator};
+use wormhole_circuit::types::NullifierHash;
+use zk_circuits_common::circuit::{D, F};
+use zk_circuits_common::merkle::{hash_pair, merkle_proof_verify, merkle_root};
+use zk_circuits_common::utils::{felts_to_hashout, Digest};
+
+/// The leaves and levels of a tree of 4 leaves.
+fn tree() -> (Vec<Digest>, Digest, Digest, Digest) {
+    let leaves: Vec<Digest> = (0..4u64)
+        .map(|i| PoseidonHash::hash_no_pad(&[F::from_canonical_u64(i)]).elements)
+        .collect();
+    let left = hash_pair::<PoseidonHash>(&leaves[0], &leaves[1]);
+    let right = hash_pair::<PoseidonHash>(&leaves[2], &leaves[3]);
+    let root = hash_pair::<PoseidonHash>(&left, &right);
+    (leaves, left, right, root)
+}
+
+/// Proves leaf `index` of the tree of [`tree`] against `root`, in a circuit sized for trees of
+/// depth `max_depth`.
+fn run_test(
+    index: usize,
+    root: Digest,
+    max_depth: usize,
+    active_depth: Option<usize>,
+) -> anyhow::Result<()> {
+    let (leaves, left, right, _) = tree();
+    let mut siblings = vec![leaves[index ^ 1], if index < 2 { right } else { left }];
+    let mut path_indices = vec![index & 1 == 1, index & 2 == 2];
+    siblings.resize(max_depth, Digest::default());
+    path_indices.resize(max_depth, false);
+
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let root_target = builder.add_virtual_hash();
+    let leaf_target = builder.add_virtual_hash();
+    let sibling_targets = builder.add_virtual_hashes(max_depth);
+    let index_targets: Vec<_> = (0..max_depth)
+        .map(|_| builder.add_virtual_bool_target_safe())
+        .collect();
+    let depth_target = active_depth.map(|_| builder.add_virtual_target());
+    merkle_proof_verify::<PoseidonHash, F, D>(
+        &mut builder,
+        root_target,
+        leaf_target,
+        &index_targets,
+        &sibling_targets,
+        depth_target,
+    );
+
+    pw.set_hash_target(root_target, felts_to_hashout(&root))?;
+    pw.set_hash_target(leaf_target, felts_to_hashout(&leaves[index]))?;
+    for (&target, sibling) in sibling_targets.iter().zip(&siblings) {
+        pw.set_hash_target(target, felts_to_hashout(sibling))?;
+    }
+    for (&target, &is_right) in index_targets.iter().zip(&path_indices) {
+        pw.set_bool_target(target, is_right)?;
+    }
+    if let (Some(target), Some(depth)) = (depth_target, active_depth) {
+        pw.set_target(target, F::from_canonical_usize(depth))?;
+    }
+    crate::circuit_helpers::build_and_prove_test(builder, pw).map(|_| ())
+}
+
+#[test]
+fn native_root_matches_tree() {
+    let (leaves, left, right, root) = tree();
+    assert_eq!(
+        merkle_root::<PoseidonHash>(leaves[2], &[false, true], &[leaves[3], left]),
+        root
+    );
+    assert_eq!(
+        merkle_root::<PoseidonHash>(leaves[1], &[true, false], &[leaves[0], right]),
+        root
+    );
+}
+
+#[test]
+fn every_leaf_verifies_in_circuit() {
+    let (_, _, _, root) = tree();
+    for index in 0..4 {
+        run_test(index, root, 2, None).unwrap();
+    }
+}
+
+#[test]
+fn wrong_root_fails() {
+    let (_, left, _, _) = tree();
+    assert!(run_test(0, left, 2, None).is_err());
+}
+
+#[test]
+fn active_depth_skips_the_upper_levels() {
+    let (_, left, _, root) = tree();
+    // A circuit for trees of depth 4 verifies a proof of depth 2.
+    run_test(3, root, 4, Some(2)).unwrap();
+    run_test(0, root, 4, Some(2)).unwrap();
+    // With a depth of 1 the path ends at the left subtree.
+    run_test(1, left, 4, Some(1)).unwrap();
+    // The padded levels are hashed when they are active.
+    assert!(run_test(3, root, 4, Some(3)).is_err());
+    assert!(run_test(3, root, 4, None).is_err());
+}
+
+#[test]
+fn accumulator_proofs_use_the_shared_merkle_proofs() {
+    let mut accumulator = NullifierAccumulator::new(4).unwrap();
+    let nullifier = NullifierHash::from([F::from_canonical_u64(7); 4]);
+    let proof = accumulator.prove_non_membership(&nullifier).unwrap();
+
+    let path_indices: Vec<bool> = (0..proof.siblings.len())
+        .map(|height| (proof.low_leaf_index >> height) & 1 == 1)
+        .collect();
+    assert_eq!(
+        merkle_root::<PoseidonHash>(proof.low_leaf.hash(), &path_indices, &proof.siblings),
+        accumulator.root()
+    );
+    assert_eq!(proof.low_leaf, IndexedLeaf::default());
+
+    accumulator.insert_batch(&[nullifier]).unwrap();
+    assert!(accumulator.contains(&nullifier));
+}
@@ -1,12 +1,64 @@
 #[cfg(test)]
+pub mod accumulator_tests;
+#[cfg(test)]
 pub mod circuit_data_tests;
 #[cfg(test)]
+pub mod client_tests;
+#[cfg(test)]
+pub mod config_tests;
+#[cfg(test)]
+pub mod deposit_vote_tests;
+#[cfg(test)]
+pub mod describe_tests;
+#[cfg(test)]
+pub mod disclosure_tests;
+#[cfg(test)]
+pub mod encoding_tests;
+#[cfg(test)]
+pub mod envelope_tests;
+#[cfg(test)]
+pub mod errors_tests;
+#[cfg(test)]
+pub mod evm_tests;
+#[cfg(test)]
+pub mod exit_format_tests;
+#[cfg(test)]
+pub mod funding_commitment_tests;
+#[cfg(test)]
+pub mod gadgets_tests;
+#[cfg(test)]
+pub mod hex_util_tests;
+#[cfg(test)]
+pub mod inputs_tests;
+#[cfg(test)]
+pub mod intent_tests;
+#[cfg(test)]
+pub mod layout_tests;
+#[cfg(test)]
+pub mod merkle_tests;
+#[cfg(test)]
 pub mod nullifier_tests;
 #[cfg(test)]
+pub mod recover_tests;
+#[cfg(test)]
+pub mod reserve_tests;
+#[cfg(test)]
+pub mod sealed_tests;
+#[cfg(test)]
+pub mod shape_tests;
+#[cfg(test)]
+pub mod snapshot_tests;
+#[cfg(test)]
+pub mod statement_tests;
+#[cfg(test)]
 pub mod storage_proof_tests;
 #[cfg(test)]
 pub mod substrate_account_tests;
 #[cfg(test)]
+pub mod transfer_tests;
+#[cfg(test)]
 pub mod unspendable_account_tests;
 #[cfg(test)]
 pub mod utils_tests;
+#[cfg(test)]
+pub mod vectors_tests;
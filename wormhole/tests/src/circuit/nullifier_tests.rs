@@ -1,11 +1,12 @@
-use plonky2::{field::types::Field, plonk::proof::ProofWithPublicInputs};
+use plonky2::{field::types::Field, iop::witness::WitnessWrite, plonk::proof::ProofWithPublicInputs};
 use test_helpers::{DEFAULT_SECRET, DEFAULT_TRANSFER_COUNT};
 use wormhole_circuit::{
     codec::FieldElementCodec,
-    nullifier::{Nullifier, NullifierTargets},
+    nullifier::{Nullifier, NullifierTargets, NullifierVersion},
+    types::ChainId,
 };
 use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
-use zk_circuits_common::utils::injective_bytes_to_felts;
+use zk_circuits_common::utils::{felts_to_hashout, injective_bytes_to_felts};
 
 #[cfg(test)]
 fn run_test(nullifier: &Nullifier) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
@@ -17,6 +18,56 @@ fn run_test(nullifier: &Nullifier) -> anyhow::Result<ProofWithPublicInputs<F, C,
     crate::circuit_helpers::build_and_prove_test(builder, pw)
 }
 
+#[cfg(test)]
+fn run_migration_test(
+    nullifier: &Nullifier,
+    v2_cutover: u64,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = NullifierTargets::new_with_migration(&mut builder, v2_cutover);
+    Nullifier::circuit(&targets, &mut builder);
+
+    nullifier.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+/// Like [`run_migration_test`], but fills the targets by hand, so a version that doesn't match the
+/// transfer count reaches the circuit instead of failing before proving.
+#[cfg(test)]
+fn run_migration_test_with_flag(
+    nullifier: &Nullifier,
+    v2_cutover: u64,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = NullifierTargets::new_with_migration(&mut builder, v2_cutover);
+    Nullifier::circuit(&targets, &mut builder);
+
+    // The transfer count follows the hash and the secret.
+    let transfer_count = &nullifier.to_field_elements()[12..];
+    pw.set_hash_target(targets.hash, felts_to_hashout(&nullifier.hash))?;
+    pw.set_target_arr(&targets.secret[..], &nullifier.secret)?;
+    pw.set_target_arr(&targets.transfer_count[..], transfer_count)?;
+    let is_v2 = nullifier.version == NullifierVersion::V2;
+    pw.set_bool_target(targets.is_v2.unwrap(), is_v2)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[cfg(test)]
+fn run_chain_test(
+    nullifier: &Nullifier,
+    chain_id: ChainId,
+) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let mut targets = NullifierTargets::new(&mut builder);
+    let chain_id_targets = [builder.add_virtual_public_input(), builder.add_virtual_public_input()];
+    targets.destination_chain_id = Some(chain_id_targets);
+    Nullifier::circuit(&targets, &mut builder);
+
+    pw.set_target_arr(&chain_id_targets, &chain_id.to_felts())?;
+    nullifier.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
 pub trait TestInputs {
     fn test_inputs() -> Self;
 }
@@ -91,3 +142,117 @@ fn codec_empty_elements() {
         "Expected 14 field elements for Nullifier, got: 0"
     );
 }
+
+#[test]
+fn versions_derive_different_nullifiers() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let v1 = Nullifier::from_preimage(&secret, DEFAULT_TRANSFER_COUNT);
+    let v2 =
+        Nullifier::from_preimage_versioned(&secret, DEFAULT_TRANSFER_COUNT, NullifierVersion::V2);
+    assert_eq!(v1.version, NullifierVersion::V1);
+    assert_eq!(v2.version, NullifierVersion::V2);
+    assert_ne!(v1.hash, v2.hash);
+}
+
+#[test]
+fn version_is_detected_from_hash() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let v2 =
+        Nullifier::from_preimage_versioned(&secret, DEFAULT_TRANSFER_COUNT, NullifierVersion::V2);
+
+    let detected = Nullifier::new(v2.hash.into(), &secret, DEFAULT_TRANSFER_COUNT);
+    assert_eq!(detected, v2);
+
+    let decoded = Nullifier::from_field_elements(&v2.to_field_elements()).unwrap();
+    assert_eq!(decoded.version, NullifierVersion::V2);
+}
+
+#[test]
+fn migration_circuit_accepts_both_versions() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let v2_cutover = DEFAULT_TRANSFER_COUNT + 1;
+    for transfer_count in [0, DEFAULT_TRANSFER_COUNT, v2_cutover, u64::MAX] {
+        let version = NullifierVersion::for_transfer_count(transfer_count, v2_cutover);
+        let nullifier = Nullifier::from_preimage_versioned(&secret, transfer_count, version);
+        run_migration_test(&nullifier, v2_cutover).unwrap();
+    }
+}
+
+#[test]
+fn migration_circuit_rejects_wrong_version_flag() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let mut nullifier =
+        Nullifier::from_preimage_versioned(&secret, DEFAULT_TRANSFER_COUNT, NullifierVersion::V2);
+    nullifier.version = NullifierVersion::V1;
+
+    let res = run_migration_test(&nullifier, DEFAULT_TRANSFER_COUNT);
+    assert!(res.is_err());
+}
+
+#[test]
+fn migration_circuit_rejects_other_version_of_claimed_deposit() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let v1 = Nullifier::from_preimage(&secret, DEFAULT_TRANSFER_COUNT);
+    let v2 =
+        Nullifier::from_preimage_versioned(&secret, DEFAULT_TRANSFER_COUNT, NullifierVersion::V2);
+
+    // Before the cutover the deposit is claimed with version 1, so its version 2 nullifier is
+    // rejected, whether the prover sets the flag or not.
+    let v2_cutover = DEFAULT_TRANSFER_COUNT + 1;
+    run_migration_test(&v1, v2_cutover).unwrap();
+    assert!(run_migration_test(&v2, v2_cutover).is_err());
+    assert!(run_migration_test_with_flag(&v2, v2_cutover).is_err());
+
+    // From the cutover on it's the other way around.
+    let v2_cutover = DEFAULT_TRANSFER_COUNT;
+    run_migration_test(&v2, v2_cutover).unwrap();
+    assert!(run_migration_test(&v1, v2_cutover).is_err());
+    assert!(run_migration_test_with_flag(&v1, v2_cutover).is_err());
+}
+
+#[test]
+fn version_follows_the_cutover() {
+    let cases = [
+        (0, 0, NullifierVersion::V2),
+        (4, 5, NullifierVersion::V1),
+        (5, 5, NullifierVersion::V2),
+        (u64::MAX, u64::MAX, NullifierVersion::V2),
+    ];
+    for (transfer_count, v2_cutover, version) in cases {
+        let derived = NullifierVersion::for_transfer_count(transfer_count, v2_cutover);
+        assert_eq!(derived, version);
+    }
+}
+
+#[test]
+fn v1_circuit_rejects_v2_nullifier() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let nullifier =
+        Nullifier::from_preimage_versioned(&secret, DEFAULT_TRANSFER_COUNT, NullifierVersion::V2);
+
+    let res = run_test(&nullifier);
+    assert!(res.is_err());
+}
+
+#[test]
+fn chains_derive_different_nullifiers() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let unbound = Nullifier::from_preimage(&secret, DEFAULT_TRANSFER_COUNT);
+    let chain_a = Nullifier::from_preimage_for_chain(&secret, DEFAULT_TRANSFER_COUNT, 1.into());
+    let chain_b = Nullifier::from_preimage_for_chain(&secret, DEFAULT_TRANSFER_COUNT, 2.into());
+    assert_ne!(chain_a.hash, chain_b.hash);
+    assert_ne!(chain_a.hash, unbound.hash);
+}
+
+#[test]
+fn chain_circuit_rejects_nullifier_of_other_chain() {
+    let secret = hex::decode(DEFAULT_SECRET).unwrap();
+    let chain_id = ChainId::new(1);
+    let nullifier = Nullifier::from_preimage_for_chain(&secret, DEFAULT_TRANSFER_COUNT, chain_id);
+
+    let proof = run_chain_test(&nullifier, chain_id).unwrap();
+    assert_eq!(proof.public_inputs[4..6], chain_id.to_felts());
+
+    assert!(run_chain_test(&nullifier, ChainId::new(2)).is_err());
+    assert!(run_chain_test(&Nullifier::test_inputs(), chain_id).is_err());
+}
@@ -0,0 +1,80 @@
+use test_helpers::storage_proof::{default_root_hash, TestInputs, DEFAULT_STORAGE_PROOF};
+use test_helpers::{DEFAULT_FUNDING_ACCOUNT, DEFAULT_FUNDING_AMOUNT, DEFAULT_TRANSFER_COUNT};
+use wormhole_circuit::client::Deposit;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::recover::recover_from_snapshot;
+use wormhole_circuit::storage_proof::snapshot::StorageSnapshot;
+use wormhole_circuit::storage_proof::StorageProofParams;
+use wormhole_circuit::types::AccountId;
+
+#[cfg(test)]
+fn default_deposit() -> Deposit {
+    Deposit {
+        funding_account: AccountId::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap(),
+        funding_amount: DEFAULT_FUNDING_AMOUNT,
+    }
+}
+
+#[cfg(test)]
+fn snapshot() -> StorageSnapshot {
+    let mut snapshot = StorageSnapshot::new(StorageProofParams::default());
+    for node in DEFAULT_STORAGE_PROOF {
+        snapshot.insert(hex::decode(node).unwrap()).unwrap();
+    }
+    snapshot
+}
+
+#[test]
+fn recovers_deposit_among_candidates_and_transfer_counts() {
+    let expected = CircuitInputs::test_inputs();
+    let mut wrong_amount = default_deposit();
+    wrong_amount.funding_amount += 1;
+
+    let deposits = recover_from_snapshot(
+        expected.private.secret,
+        &[wrong_amount, default_deposit()],
+        0..DEFAULT_TRANSFER_COUNT + 4,
+        &snapshot(),
+        default_root_hash(),
+        expected.public.exit_account,
+    )
+    .unwrap();
+
+    assert_eq!(deposits.len(), 1);
+    let deposit = &deposits[0];
+    assert_eq!(deposit.deposit, default_deposit());
+    assert_eq!(deposit.transfer_count, DEFAULT_TRANSFER_COUNT);
+    assert_eq!(deposit.root_hash(), default_root_hash());
+    assert_eq!(deposit.inputs.public, expected.public);
+    assert_eq!(
+        deposit.inputs.private.storage_proof.proof,
+        expected.private.storage_proof.proof
+    );
+}
+
+#[test]
+fn nothing_is_recovered_outside_the_scanned_counts() {
+    let expected = CircuitInputs::test_inputs();
+    let deposits = recover_from_snapshot(
+        expected.private.secret,
+        &[default_deposit()],
+        DEFAULT_TRANSFER_COUNT + 1..DEFAULT_TRANSFER_COUNT + 8,
+        &snapshot(),
+        default_root_hash(),
+        expected.public.exit_account,
+    )
+    .unwrap();
+    assert!(deposits.is_empty());
+
+    // Another secret has another unspendable account, so the deposit isn't its.
+    let deposits = recover_from_snapshot(
+        [9u8; 32].into(),
+        &[default_deposit()],
+        0..DEFAULT_TRANSFER_COUNT + 1,
+        &snapshot(),
+        default_root_hash(),
+        expected.public.exit_account,
+    )
+    .unwrap();
+    assert!(deposits.is_empty());
+}
@@ -0,0 +1,67 @@
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use test_helpers::storage_proof::TestInputs;
+use test_helpers::{DEFAULT_FUNDING_AMOUNT, DEFAULT_TRANSFER_COUNT};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::reserve::{
+    ProofOfReserve, ReserveAccount, ReserveTargets, THRESHOLD_END_INDEX, THRESHOLD_START_INDEX,
+};
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::felts_to_u128;
+
+#[cfg(test)]
+fn reserve_account() -> ReserveAccount {
+    let inputs = CircuitInputs::test_inputs();
+    ReserveAccount {
+        secret: inputs.private.secret,
+        transfer_count: DEFAULT_TRANSFER_COUNT,
+        funding_account: inputs.private.funding_account,
+        funding_amount: DEFAULT_FUNDING_AMOUNT,
+        storage_proof: inputs.private.storage_proof,
+    }
+}
+
+#[cfg(test)]
+fn run_test(reserve: &ProofOfReserve) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = ReserveTargets::new(&mut builder, reserve.accounts.len());
+    ProofOfReserve::circuit(&targets, &mut builder);
+
+    reserve.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn prove_reserve_at_threshold() {
+    let reserve = ProofOfReserve {
+        root_hash: CircuitInputs::test_inputs().public.root_hash,
+        threshold: DEFAULT_FUNDING_AMOUNT,
+        accounts: vec![reserve_account()],
+    };
+    let proof = run_test(&reserve).unwrap();
+
+    let threshold = proof.public_inputs[THRESHOLD_START_INDEX..THRESHOLD_END_INDEX]
+        .try_into()
+        .unwrap();
+    assert_eq!(felts_to_u128(threshold).unwrap(), DEFAULT_FUNDING_AMOUNT);
+}
+
+#[test]
+fn threshold_above_reserve_is_rejected() {
+    let reserve = ProofOfReserve {
+        root_hash: CircuitInputs::test_inputs().public.root_hash,
+        threshold: DEFAULT_FUNDING_AMOUNT + 1,
+        accounts: vec![reserve_account()],
+    };
+    assert!(run_test(&reserve).is_err());
+}
+
+#[test]
+fn duplicate_account_is_rejected() {
+    // The total would exceed the threshold if the same deposit could be counted twice.
+    let reserve = ProofOfReserve {
+        root_hash: CircuitInputs::test_inputs().public.root_hash,
+        threshold: DEFAULT_FUNDING_AMOUNT * 2,
+        accounts: vec![reserve_account(), reserve_account()],
+    };
+    assert!(run_test(&reserve).is_err());
+}
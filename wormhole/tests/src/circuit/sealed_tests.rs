@@ -0,0 +1,121 @@
+use wormhole_circuit::codec::ByteCodec;
+use wormhole_circuit::envelope::sealed::{
+    AggregatorKeyring, AggregatorSecretKey, SealedSubmission, SEALED_HEADER_LEN, SEALED_MAGIC,
+};
+use wormhole_circuit::envelope::ProofEnvelope;
+use zk_circuits_common::utils::BytesDigest;
+
+#[cfg(test)]
+const REFUND_ADDRESS: &[u8] = b"refund to 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+#[cfg(test)]
+fn test_envelope() -> ProofEnvelope {
+    ProofEnvelope {
+        verifier_digest: BytesDigest::try_from([7u8; 32]).unwrap(),
+        proof: vec![1, 2, 3, 4],
+    }
+}
+
+#[cfg(test)]
+fn keyring_with_key() -> (AggregatorKeyring, AggregatorSecretKey) {
+    let key = AggregatorSecretKey::generate();
+    let mut keyring = AggregatorKeyring::new();
+    keyring.rotate(key.clone());
+    (keyring, key)
+}
+
+#[test]
+fn sealed_metadata_round_trip() {
+    let (keyring, key) = keyring_with_key();
+    let recipient = keyring.current_public_key().unwrap();
+    assert_eq!(recipient, key.public_key());
+
+    let sealed = SealedSubmission::seal(test_envelope(), REFUND_ADDRESS, &recipient).unwrap();
+    assert_eq!(sealed.envelope, test_envelope());
+    assert_eq!(sealed.key_id, recipient.key_id());
+    assert_ne!(sealed.ciphertext[..REFUND_ADDRESS.len()], *REFUND_ADDRESS);
+    assert_eq!(keyring.open(&sealed).unwrap(), REFUND_ADDRESS);
+}
+
+#[test]
+fn sealed_submission_codec() {
+    let (keyring, key) = keyring_with_key();
+    let sealed =
+        SealedSubmission::seal(test_envelope(), REFUND_ADDRESS, &key.public_key()).unwrap();
+
+    let bytes = sealed.to_bytes();
+    assert_eq!(bytes[..4], SEALED_MAGIC);
+    assert_eq!(
+        bytes.len(),
+        SEALED_HEADER_LEN + test_envelope().to_bytes().len() + sealed.ciphertext.len()
+    );
+
+    let decoded = SealedSubmission::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded, sealed);
+    assert_eq!(keyring.open(&decoded).unwrap(), REFUND_ADDRESS);
+    assert!(SealedSubmission::from_bytes(&bytes[..SEALED_HEADER_LEN - 1]).is_err());
+}
+
+#[test]
+fn rotated_key_opens_until_retired() {
+    let (mut keyring, old_key) = keyring_with_key();
+    let sealed_to_old =
+        SealedSubmission::seal(test_envelope(), REFUND_ADDRESS, &old_key.public_key()).unwrap();
+
+    let new_key = AggregatorSecretKey::generate();
+    let new_key_id = keyring.rotate(new_key.clone());
+    assert_eq!(keyring.current_public_key(), Some(new_key.public_key()));
+    assert_eq!(keyring.key_ids().count(), 2);
+
+    let sealed_to_new =
+        SealedSubmission::seal(test_envelope(), REFUND_ADDRESS, &new_key.public_key()).unwrap();
+    assert_eq!(sealed_to_new.key_id, new_key_id);
+    assert_eq!(keyring.open(&sealed_to_old).unwrap(), REFUND_ADDRESS);
+    assert_eq!(keyring.open(&sealed_to_new).unwrap(), REFUND_ADDRESS);
+
+    assert!(keyring.retire(&old_key.public_key().key_id()));
+    assert!(!keyring.retire(&old_key.public_key().key_id()));
+    let err = keyring.open(&sealed_to_old).unwrap_err();
+    assert!(err.to_string().contains("may have been retired"), "{}", err);
+    assert_eq!(keyring.open(&sealed_to_new).unwrap(), REFUND_ADDRESS);
+}
+
+#[test]
+fn tampered_submission_fails_to_open() {
+    let (keyring, key) = keyring_with_key();
+    let sealed =
+        SealedSubmission::seal(test_envelope(), REFUND_ADDRESS, &key.public_key()).unwrap();
+
+    let mut other_proof = sealed.clone();
+    other_proof.envelope.proof.push(5);
+    assert!(keyring.open(&other_proof).is_err());
+
+    let mut flipped = sealed.clone();
+    flipped.ciphertext[0] ^= 1;
+    assert!(keyring.open(&flipped).is_err());
+
+    let mut truncated = sealed;
+    truncated.ciphertext.pop();
+    assert!(keyring.open(&truncated).is_err());
+}
+
+#[test]
+fn metadata_sealed_to_another_key_fails_to_open() {
+    let (keyring, _) = keyring_with_key();
+    let other_key = AggregatorSecretKey::generate();
+    let mut sealed =
+        SealedSubmission::seal(test_envelope(), REFUND_ADDRESS, &other_key.public_key()).unwrap();
+    assert!(keyring.open(&sealed).is_err());
+
+    // Claiming the keyring's key id doesn't help either.
+    sealed.key_id = keyring.current_public_key().unwrap().key_id();
+    assert!(keyring.open(&sealed).is_err());
+}
+
+#[test]
+fn secret_key_debug_omits_bytes() {
+    let key = AggregatorSecretKey::from_bytes([0xAB; 32]);
+    let debug = format!("{:?}", key);
+    assert!(!debug.contains("abab"), "{}", debug);
+    assert!(debug.contains(&key.public_key().key_id().to_string()));
+}
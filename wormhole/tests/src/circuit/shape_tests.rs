@@ -0,0 +1,220 @@
+use plonky2::field::types::Field;
+use test_helpers::storage_proof::TestInputs;
+use test_helpers::DEFAULT_TRANSFER_COUNT;
+use wormhole_circuit::accumulator::{
+    AccumulatorTargets, NonMembershipTargets, NullifierAccumulator,
+};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::nullifier::{Nullifier, NullifierTargets};
+use wormhole_circuit::reserve::{ProofOfReserve, ReserveAccount, ReserveTargets};
+use wormhole_circuit::storage_proof::{StorageProof, StorageProofTargets, MAX_PROOF_LEN};
+use wormhole_circuit::types::NullifierHash;
+use zk_circuits_common::circuit::{CircuitFragment, ShapeError, F};
+
+#[cfg(test)]
+const DEPTH: usize = 4;
+
+#[cfg(test)]
+fn shape_error(result: anyhow::Result<()>) -> ShapeError {
+    let err = result.unwrap_err();
+    *err.downcast_ref::<ShapeError>()
+        .unwrap_or_else(|| panic!("not a shape error: {:#}", err))
+}
+
+#[test]
+fn nullifier_secret_of_wrong_length_is_rejected() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = NullifierTargets::new(&mut builder);
+    let expected = targets.secret.len();
+    let nullifier = Nullifier::from_preimage(&[7u8; 40], DEFAULT_TRANSFER_COUNT);
+
+    let err = shape_error(nullifier.fill_targets(&mut pw, targets));
+    assert_eq!(
+        err,
+        ShapeError::LengthMismatch {
+            fragment: "nullifier",
+            field: "secret",
+            expected,
+            got: nullifier.secret.len(),
+        }
+    );
+    assert_eq!(err.code(), "shape.length_mismatch");
+}
+
+#[test]
+fn storage_proof_with_too_many_nodes_is_rejected() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = StorageProofTargets::new(&mut builder);
+    let mut storage_proof = StorageProof::test_inputs();
+    let node = storage_proof.proof[0].clone();
+    storage_proof.proof.resize(MAX_PROOF_LEN + 1, node);
+    storage_proof.indices.resize(MAX_PROOF_LEN + 1, F::ZERO);
+
+    assert_eq!(
+        shape_error(storage_proof.fill_targets(&mut pw, targets)),
+        ShapeError::TooLong {
+            fragment: "storage_proof",
+            field: "proof",
+            max: MAX_PROOF_LEN,
+            got: MAX_PROOF_LEN + 1,
+        }
+    );
+}
+
+#[test]
+fn storage_proof_with_too_large_node_is_rejected() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = StorageProofTargets::new(&mut builder);
+    let max = targets.params().node_max_size_f;
+    let mut storage_proof = StorageProof::test_inputs();
+    storage_proof.proof[1].resize(max + 1, F::ZERO);
+
+    let err = storage_proof.fill_targets(&mut pw, targets).unwrap_err();
+    assert!(
+        format!("{:#}", err).starts_with("proof node 1: "),
+        "{:#}",
+        err
+    );
+    assert_eq!(
+        shape_error(Err(err)),
+        ShapeError::TooLong {
+            fragment: "storage_proof",
+            field: "proof_data",
+            max,
+            got: max + 1,
+        }
+    );
+}
+
+#[test]
+fn storage_proof_with_missing_index_is_rejected() {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = StorageProofTargets::new(&mut builder);
+    let mut storage_proof = StorageProof::test_inputs();
+    storage_proof.indices.pop();
+
+    assert_eq!(
+        shape_error(storage_proof.fill_targets(&mut pw, targets)),
+        ShapeError::LengthMismatch {
+            fragment: "storage_proof",
+            field: "indices",
+            expected: storage_proof.proof.len(),
+            got: storage_proof.proof.len() - 1,
+        }
+    );
+}
+
+#[test]
+fn accumulator_update_of_wrong_shape_is_rejected() {
+    let mut accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    let nullifiers: [NullifierHash; 2] =
+        [[5, 0, 0, 0], [1, 2, 3, 4]].map(|elements| elements.map(F::from_canonical_u64).into());
+    let update = accumulator.insert_batch(&nullifiers).unwrap();
+
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = AccumulatorTargets::new(&mut builder, DEPTH, 3);
+    assert_eq!(
+        shape_error(update.fill_targets(&mut pw, targets)),
+        ShapeError::LengthMismatch {
+            fragment: "accumulator_update",
+            field: "insertions",
+            expected: 3,
+            got: 2,
+        }
+    );
+
+    let mut short_path = update.clone();
+    short_path.insertions[1].new_leaf_siblings.pop();
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = AccumulatorTargets::new(&mut builder, DEPTH, 2);
+    assert_eq!(
+        shape_error(short_path.fill_targets(&mut pw, targets)),
+        ShapeError::LengthMismatch {
+            fragment: "accumulator_update",
+            field: "new_leaf_siblings",
+            expected: DEPTH,
+            got: DEPTH - 1,
+        }
+    );
+
+    let mut long_path = update;
+    long_path.insertions[0].low_leaf_siblings.push([F::ZERO; 4]);
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = AccumulatorTargets::new(&mut builder, DEPTH, 2);
+    assert_eq!(
+        shape_error(long_path.fill_targets(&mut pw, targets)),
+        ShapeError::LengthMismatch {
+            fragment: "accumulator_update",
+            field: "low_leaf_siblings",
+            expected: DEPTH,
+            got: DEPTH + 1,
+        }
+    );
+}
+
+#[test]
+fn non_membership_proof_of_wrong_depth_is_rejected() {
+    let accumulator = NullifierAccumulator::new(DEPTH).unwrap();
+    let nullifier: NullifierHash = [9, 9, 9, 9].map(F::from_canonical_u64).into();
+    let mut proof = accumulator.prove_non_membership(&nullifier).unwrap();
+    proof.siblings.pop();
+
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let nullifier_target = builder.add_virtual_hash();
+    let targets = NonMembershipTargets::new(&mut builder, nullifier_target, DEPTH);
+    assert_eq!(
+        shape_error(proof.fill_targets(&mut pw, targets)),
+        ShapeError::LengthMismatch {
+            fragment: "spent_nullifiers",
+            field: "siblings",
+            expected: DEPTH,
+            got: DEPTH - 1,
+        }
+    );
+}
+
+#[test]
+fn reserve_with_wrong_number_of_accounts_is_rejected() {
+    let inputs = CircuitInputs::test_inputs();
+    let reserve = ProofOfReserve {
+        root_hash: inputs.public.root_hash,
+        threshold: 0,
+        accounts: vec![ReserveAccount {
+            secret: inputs.private.secret,
+            transfer_count: DEFAULT_TRANSFER_COUNT,
+            funding_account: inputs.private.funding_account,
+            funding_amount: inputs.public.funding_amount,
+            storage_proof: inputs.private.storage_proof,
+        }],
+    };
+
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = ReserveTargets::new(&mut builder, 2);
+    assert_eq!(
+        shape_error(reserve.fill_targets(&mut pw, targets)),
+        ShapeError::LengthMismatch {
+            fragment: "proof_of_reserve",
+            field: "accounts",
+            expected: 2,
+            got: 1,
+        }
+    );
+}
+
+#[test]
+fn shape_errors_name_fragment_and_field() {
+    let err = ShapeError::TooShort {
+        fragment: "vote",
+        field: "merkle_siblings",
+        min: 3,
+        got: 2,
+    };
+    assert_eq!((err.fragment(), err.field()), ("vote", "merkle_siblings"));
+    assert_eq!(
+        err.to_string(),
+        "vote.merkle_siblings has 2 values, at least 3 are needed"
+    );
+    assert!(ShapeError::check_len("nullifier", "secret", 4, 4).is_ok());
+    assert!(ShapeError::check_max_len("storage_proof", "proof", 4, 5).is_err());
+    assert!(ShapeError::check_min_len("vote", "merkle_siblings", 2, 2).is_ok());
+}
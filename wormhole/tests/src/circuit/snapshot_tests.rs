@@ -0,0 +1,69 @@
+use test_helpers::storage_proof::{
+    default_root_hash, TestInputs, DEFAULT_STORAGE_PROOF, DEFAULT_STORAGE_PROOF_INDICIES,
+};
+use wormhole_circuit::storage_proof::{
+    leaf::LeafInputs, snapshot::StorageSnapshot, StorageProofParams,
+};
+
+#[cfg(test)]
+fn default_snapshot() -> StorageSnapshot {
+    let mut lines = vec!["# nodes of the default storage proof, leaf first".to_string()];
+    lines.extend(DEFAULT_STORAGE_PROOF.iter().rev().map(|node| node.to_string()));
+    // Unrelated nodes don't get in the way.
+    lines.push(hex::encode([7u8; 64]));
+    lines.push(String::new());
+
+    StorageSnapshot::from_reader(lines.join("\n").as_bytes(), StorageProofParams::default())
+        .unwrap()
+}
+
+#[test]
+fn snapshot_proof_matches_node_proof() {
+    let snapshot = default_snapshot();
+    assert_eq!(snapshot.len(), DEFAULT_STORAGE_PROOF.len() + 1);
+
+    let proof = snapshot
+        .prove(default_root_hash(), LeafInputs::test_inputs().hash())
+        .unwrap();
+    let expected: Vec<_> = DEFAULT_STORAGE_PROOF
+        .iter()
+        .map(|node| hex::decode(node).unwrap())
+        .collect();
+    assert_eq!(proof.proof, expected);
+    assert_eq!(proof.indices, DEFAULT_STORAGE_PROOF_INDICIES);
+}
+
+#[test]
+fn snapshot_checks_given_hashes() {
+    let root = DEFAULT_STORAGE_PROOF[0];
+    let line = format!("{} {}", hex::encode(*default_root_hash()), root);
+    StorageSnapshot::from_reader(line.as_bytes(), StorageProofParams::default()).unwrap();
+
+    let line = format!("{} {}", hex::encode([1u8; 32]), root);
+    let err =
+        StorageSnapshot::from_reader(line.as_bytes(), StorageProofParams::default()).unwrap_err();
+    assert!(err.to_string().contains("line 1"));
+}
+
+#[test]
+fn missing_leaf_or_root_is_rejected() {
+    let snapshot = default_snapshot();
+    let mut leaf_hash = LeafInputs::test_inputs().hash();
+    leaf_hash.reverse();
+    assert!(snapshot.prove(default_root_hash(), leaf_hash).is_err());
+
+    let root_hash = [1u8; 32].try_into().unwrap();
+    assert!(snapshot
+        .prove(root_hash, LeafInputs::test_inputs().hash())
+        .is_err());
+
+    // The leaf is 7 nodes deep.
+    let params = StorageProofParams::new(6, StorageProofParams::default().node_max_size_f).unwrap();
+    let mut shallow = StorageSnapshot::new(params);
+    for node in DEFAULT_STORAGE_PROOF {
+        shallow.insert(hex::decode(node).unwrap()).unwrap();
+    }
+    assert!(shallow
+        .prove(default_root_hash(), LeafInputs::test_inputs().hash())
+        .is_err());
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+
+use plonky2::plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig};
+use wormhole_circuit::circuit::circuit_logic::CircuitTargets;
+use wormhole_circuit::statement::{Statement, WORMHOLE_STATEMENT};
+use zk_circuits_common::circuit::{D, F};
+use zk_circuits_common::describe::{CircuitSummary, FragmentSummary};
+
+fn describe(targets: fn(&mut CircuitBuilder<F, D>) -> CircuitTargets) -> CircuitSummary {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    targets(&mut builder).describe().unwrap()
+}
+
+/// The summaries of the circuit with every option, so together they have every fragment.
+fn summaries() -> Vec<CircuitSummary> {
+    vec![
+        describe(CircuitTargets::new),
+        describe(|builder| CircuitTargets::new_with_nullifier_migration(builder, 4)),
+        describe(CircuitTargets::with_context),
+        describe(CircuitTargets::with_destination_chain),
+        describe(CircuitTargets::with_relayer_fee),
+        describe(CircuitTargets::with_exit_account_format),
+        describe(|builder| CircuitTargets::with_spent_nullifier_accumulator(builder, 4)),
+        describe(CircuitTargets::with_funding_commitment),
+        describe(CircuitTargets::with_intent_hash),
+        describe(CircuitTargets::with_public_inputs_digest),
+    ]
+}
+
+#[test]
+fn statement_matches_every_circuit_option() {
+    for summary in summaries() {
+        WORMHOLE_STATEMENT.check(&summary).unwrap();
+    }
+}
+
+#[test]
+fn every_stated_fragment_exists() {
+    let described: BTreeSet<String> = summaries()
+        .iter()
+        .flat_map(|summary| summary.fragments.iter().map(|f| f.name.clone()))
+        .collect();
+    let stated: BTreeSet<String> = WORMHOLE_STATEMENT
+        .fragments()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(stated, described);
+}
+
+#[test]
+fn unstated_fragments_fail_the_check() {
+    let summary = describe(CircuitTargets::new).fragment(FragmentSummary::new("new_fragment"));
+    let err = WORMHOLE_STATEMENT.check(&summary).unwrap_err();
+    assert!(err.to_string().contains("new_fragment"), "{}", err);
+
+    let mut summary = describe(CircuitTargets::new);
+    summary.fragments.retain(|f| f.name != "shared_targets");
+    let err = WORMHOLE_STATEMENT.check(&summary).unwrap_err();
+    assert!(err.to_string().contains("same_secret_and_transfer"), "{}", err);
+}
+
+#[test]
+fn optional_clauses_name_their_feature() {
+    let clause = WORMHOLE_STATEMENT.clause("relayer_fee").unwrap();
+    assert_eq!(clause.feature, Some("relayer_fee"));
+    assert_eq!(WORMHOLE_STATEMENT.clause("nullifier").unwrap().feature, None);
+
+    let empty = Statement {
+        claim: "",
+        clauses: &[],
+    };
+    assert!(empty.check(&describe(CircuitTargets::new)).is_err());
+
+    let json = serde_json::to_value(WORMHOLE_STATEMENT).unwrap();
+    assert_eq!(json["clauses"][0]["name"], "nullifier");
+    assert_eq!(json["clauses"][0]["fragments"][0], "nullifier");
+}
@@ -1,12 +1,30 @@
-use plonky2::{field::types::Field, plonk::proof::ProofWithPublicInputs};
+use plonky2::{
+    field::types::Field,
+    hash::poseidon::PoseidonHash,
+    plonk::{config::Hasher, proof::ProofWithPublicInputs},
+};
 use std::panic;
 use wormhole_circuit::{
-    storage_proof::{leaf::LeafInputs, ProcessedStorageProof, StorageProof, StorageProofTargets},
+    inputs::CircuitInputs,
+    nullifier::Nullifier,
+    storage_proof::{
+        leaf::{
+            check_transfer_count, find_transfer_count, LeafError, LeafInputs,
+            TRANSFER_COUNT_SEARCH_WINDOW,
+        },
+        snapshot::StorageSnapshot,
+        ProcessedStorageProof, StorageProof, StorageProofError, StorageProofParams,
+        StorageProofTargets, MAX_PROOF_LEN, PROOF_NODE_MAX_SIZE_B, PROOF_NODE_MAX_SIZE_F,
+    },
     substrate_account::SubstrateAccount,
+    types::RootHash,
 };
 use zk_circuits_common::{
     circuit::{CircuitFragment, C, D, F},
-    utils::u64_to_felts,
+    utils::{
+        digest_felts_to_bytes, felts_to_u128, injective_bytes_to_felts, u128_to_felts,
+        u64_to_felts,
+    },
 };
 
 use test_helpers::storage_proof::{default_root_hash, TestInputs};
@@ -31,7 +49,7 @@ fn build_and_verify_proof() {
 #[should_panic(expected = "set twice with different values")]
 fn invalid_root_hash_fails() {
     let mut proof = StorageProof::test_inputs();
-    proof.root_hash = [0u8; 32];
+    proof.root_hash = RootHash::default();
     run_test(&proof).unwrap();
 }
 
@@ -145,3 +163,274 @@ fn fuzz_tampered_proof() {
         "Only {panic_count} out of {FUZZ_ITERATIONS} iterations panicked",
     );
 }
+
+fn processed_proof_error(proof: Vec<Vec<u8>>, indices: Vec<usize>) -> StorageProofError {
+    let err = ProcessedStorageProof::new(proof, indices).unwrap_err();
+    *err.downcast_ref::<StorageProofError>().unwrap()
+}
+
+#[test]
+fn processed_proof_rejects_malformed_proofs() {
+    let valid = ProcessedStorageProof::test_inputs();
+    ProcessedStorageProof::new(valid.proof.clone(), valid.indices.clone()).unwrap();
+
+    let mut indices = valid.indices.clone();
+    indices.pop();
+    assert_eq!(
+        processed_proof_error(valid.proof.clone(), indices),
+        StorageProofError::LengthMismatch {
+            proof_len: 7,
+            indices_len: 6
+        }
+    );
+
+    let proof = vec![vec![0u8; 64]; MAX_PROOF_LEN + 1];
+    assert_eq!(
+        processed_proof_error(proof, vec![0; MAX_PROOF_LEN + 1]),
+        StorageProofError::TooManyNodes {
+            len: MAX_PROOF_LEN + 1,
+            max: MAX_PROOF_LEN
+        }
+    );
+
+    let mut proof = valid.proof.clone();
+    proof[2] = vec![0u8; PROOF_NODE_MAX_SIZE_B + 1];
+    assert_eq!(
+        processed_proof_error(proof, valid.indices.clone()),
+        StorageProofError::NodeTooLarge {
+            node: 2,
+            len: PROOF_NODE_MAX_SIZE_B + 1,
+            max: PROOF_NODE_MAX_SIZE_B
+        }
+    );
+
+    let mut indices = valid.indices.clone();
+    indices[1] += 4;
+    assert_eq!(
+        processed_proof_error(valid.proof.clone(), indices),
+        StorageProofError::UnalignedIndex { node: 1, index: 52 }
+    );
+
+    // The last node is 48 bytes, so a hash can't start past hex index 32.
+    let mut indices = valid.indices.clone();
+    indices[6] = 40;
+    assert_eq!(
+        processed_proof_error(valid.proof.clone(), indices),
+        StorageProofError::IndexOutOfBounds {
+            node: 6,
+            index: 40,
+            node_len: 96
+        }
+    );
+}
+
+#[test]
+fn large_nodes_with_params() {
+    let params = StorageProofParams::new(2, 256).unwrap();
+    let leaf_inputs = LeafInputs::test_inputs();
+
+    // A single node, larger than the default limit, holding the leaf hash.
+    let mut node = vec![0xab; PROOF_NODE_MAX_SIZE_B + 100];
+    node[400..432].copy_from_slice(&*digest_felts_to_bytes(leaf_inputs.hash()));
+    let indices = vec![800];
+
+    let mut node_felts = injective_bytes_to_felts(&node);
+    node_felts.resize(params.node_max_size_f, F::ZERO);
+    let root_hash = RootHash::from(PoseidonHash::hash_no_pad(&node_felts).elements);
+
+    assert!(ProcessedStorageProof::new(vec![node.clone()], indices.clone()).is_err());
+    let processed_proof = ProcessedStorageProof::with_params(vec![node], indices, params).unwrap();
+    let storage_proof = StorageProof::new(&processed_proof, root_hash, leaf_inputs);
+
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = StorageProofTargets::with_params(&mut builder, params);
+    assert_eq!(targets.params(), params);
+    StorageProof::circuit(&targets, &mut builder);
+
+    storage_proof.fill_targets(&mut pw, targets).unwrap();
+    crate::circuit_helpers::build_and_prove_test(builder, pw).unwrap();
+}
+
+/// Hashes a node the way the circuit does, after padding it to the maximum node size.
+fn node_hash(node: &[u8]) -> [F; 4] {
+    let mut node_felts = injective_bytes_to_felts(node);
+    node_felts.resize(PROOF_NODE_MAX_SIZE_F, F::ZERO);
+    PoseidonHash::hash_no_pad(&node_felts).elements
+}
+
+#[test]
+fn hash_in_last_felts_of_max_size_node() {
+    let leaf_inputs = LeafInputs::test_inputs();
+    let hash_start = PROOF_NODE_MAX_SIZE_B - 32;
+
+    // A leaf node of the maximum size, ending with the leaf hash.
+    let mut leaf_node = vec![0xab; PROOF_NODE_MAX_SIZE_B];
+    leaf_node[hash_start..].copy_from_slice(&*digest_felts_to_bytes(leaf_inputs.hash()));
+
+    // A root node of the maximum size, ending with the hash of the leaf node.
+    let mut root_node = vec![0xcd; PROOF_NODE_MAX_SIZE_B];
+    root_node[hash_start..].copy_from_slice(&*digest_felts_to_bytes(node_hash(&leaf_node)));
+    let root_hash = RootHash::from(node_hash(&root_node));
+
+    let indices = vec![hash_start * 2; 2];
+    let processed_proof = ProcessedStorageProof::new(vec![root_node, leaf_node], indices).unwrap();
+    let storage_proof = StorageProof::new(&processed_proof, root_hash, leaf_inputs);
+    run_test(&storage_proof).unwrap();
+}
+
+#[test]
+fn storage_proof_params_reject_degenerate_shapes() {
+    assert!(StorageProofParams::new(0, PROOF_NODE_MAX_SIZE_F).is_err());
+    assert!(StorageProofParams::new(MAX_PROOF_LEN, 7).is_err());
+    assert_eq!(
+        StorageProofParams::new(MAX_PROOF_LEN, PROOF_NODE_MAX_SIZE_F).unwrap(),
+        StorageProofParams::default()
+    );
+}
+
+#[test]
+fn hardened_conversion_matches_new() {
+    let processed_proof = ProcessedStorageProof::test_inputs();
+    let params = StorageProofParams::default();
+    let fast = StorageProof::new(
+        &processed_proof,
+        default_root_hash(),
+        LeafInputs::test_inputs(),
+    );
+    let hardened = StorageProof::new_hardened(
+        &processed_proof,
+        default_root_hash(),
+        LeafInputs::test_inputs(),
+        params,
+    )
+    .unwrap();
+    assert_eq!(hardened.proof, fast.proof);
+    assert_eq!(hardened.indices, fast.indices);
+
+    // Hardened filling proves the same witness.
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = StorageProofTargets::new(&mut builder);
+    StorageProof::circuit(&targets, &mut builder);
+    hardened.fill_targets_hardened(&mut pw, targets).unwrap();
+    crate::circuit_helpers::build_and_prove_test(builder, pw).unwrap();
+
+    let too_long = ProcessedStorageProof {
+        proof: vec![vec![0u8; 64]; MAX_PROOF_LEN + 1],
+        indices: vec![0; MAX_PROOF_LEN + 1],
+    };
+    let result = StorageProof::new_hardened(
+        &too_long,
+        default_root_hash(),
+        LeafInputs::test_inputs(),
+        params,
+    );
+    assert!(result.is_err());
+}
+
+/// A storage proof of a single node holding the hash of `leaf_inputs`, so the leaf inputs don't
+/// have to come from a real transfer.
+#[cfg(test)]
+fn single_node_proof(leaf_inputs: LeafInputs) -> StorageProof {
+    let leaf_hash = leaf_inputs.hash();
+    let mut snapshot = StorageSnapshot::new(StorageProofParams::default());
+    let root_hash = snapshot.insert(digest_felts_to_bytes(leaf_hash).to_vec()).unwrap();
+    let root_hash = RootHash::try_from(root_hash).unwrap();
+    let proof = snapshot.prove(root_hash, leaf_hash).unwrap();
+    StorageProof::new(&proof, root_hash, leaf_inputs)
+}
+
+#[test]
+fn non_canonical_funding_amount_is_rejected() {
+    let amount = (1u128 << 32) + 5;
+    let mut canonical = LeafInputs::test_inputs();
+    canonical.funding_amount = u128_to_felts(amount);
+    run_test(&single_node_proof(canonical)).unwrap();
+
+    // The same amount with the second lowest limb carried into the lowest one. It hashes to a
+    // different leaf, which the prover can include in a valid storage proof, so only the range
+    // check on the limbs rejects it.
+    let mut non_canonical = LeafInputs::test_inputs();
+    non_canonical.funding_amount = [
+        F::ZERO,
+        F::ZERO,
+        F::ZERO,
+        F::from_canonical_u64(amount as u64),
+    ];
+    assert!(felts_to_u128(non_canonical.funding_amount).is_err());
+    assert!(run_test(&single_node_proof(non_canonical)).is_err());
+}
+
+#[cfg(test)]
+fn stale_inputs(offset: u64) -> CircuitInputs {
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.private.transfer_count += offset;
+    inputs
+}
+
+#[test]
+fn finds_the_transfer_count_the_leaf_holds() {
+    let inputs = CircuitInputs::test_inputs();
+    let expected = inputs.private.transfer_count;
+    assert_eq!(
+        find_transfer_count(&inputs, TRANSFER_COUNT_SEARCH_WINDOW).unwrap(),
+        Some(expected)
+    );
+    assert_eq!(
+        find_transfer_count(&stale_inputs(3), TRANSFER_COUNT_SEARCH_WINDOW).unwrap(),
+        Some(expected)
+    );
+    // Outside of the window.
+    assert_eq!(find_transfer_count(&stale_inputs(3), 2).unwrap(), None);
+
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.funding_amount += 1;
+    assert_eq!(
+        find_transfer_count(&inputs, TRANSFER_COUNT_SEARCH_WINDOW).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn stale_transfer_count_error_suggests_the_matching_count() {
+    check_transfer_count(&CircuitInputs::test_inputs(), TRANSFER_COUNT_SEARCH_WINDOW).unwrap();
+
+    let inputs = stale_inputs(1);
+    let err = check_transfer_count(&inputs, TRANSFER_COUNT_SEARCH_WINDOW).unwrap_err();
+    let found = CircuitInputs::test_inputs().private.transfer_count;
+    assert_eq!(
+        err.downcast_ref::<LeafError>(),
+        Some(&LeafError::StaleTransferCount {
+            given: found + 1,
+            found
+        })
+    );
+    assert!(err.to_string().contains(&found.to_string()), "{}", err);
+
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.funding_amount += 1;
+    let err = check_transfer_count(&inputs, TRANSFER_COUNT_SEARCH_WINDOW).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<LeafError>().map(LeafError::code),
+        Some("leaf.mismatch")
+    );
+}
+
+#[test]
+fn corrects_a_stale_transfer_count_and_its_nullifier() {
+    let expected = CircuitInputs::test_inputs();
+    let mut stale = stale_inputs(2);
+    let secret = stale.private.secret;
+    let nullifier = Nullifier::from_preimage(secret.as_slice(), stale.private.transfer_count);
+    stale.public.nullifier = nullifier.hash.into();
+
+    let corrected = stale
+        .with_corrected_transfer_count(TRANSFER_COUNT_SEARCH_WINDOW)
+        .unwrap();
+    assert_eq!(corrected.public, expected.public);
+    assert_eq!(
+        corrected.private.transfer_count,
+        expected.private.transfer_count
+    );
+
+    assert!(stale_inputs(2).with_corrected_transfer_count(1).is_err());
+}
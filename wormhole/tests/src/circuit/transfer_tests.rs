@@ -0,0 +1,78 @@
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use test_helpers::storage_proof::TestInputs;
+use test_helpers::{DEFAULT_FUNDING_AMOUNT, DEFAULT_TRANSFER_COUNT};
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::transfer::{
+    PoolTransfer, TransferTargets, FUNDING_AMOUNT_END_INDEX, FUNDING_AMOUNT_START_INDEX,
+    NEW_ACCOUNT_END_INDEX, NEW_ACCOUNT_START_INDEX, NULLIFIER_END_INDEX, NULLIFIER_START_INDEX,
+};
+use wormhole_circuit::types::{AccountId, NullifierHash};
+use wormhole_circuit::unspendable_account::UnspendableAccount;
+use zk_circuits_common::circuit::{CircuitFragment, C, D, F};
+use zk_circuits_common::utils::felts_to_u128;
+
+#[cfg(test)]
+const RECIPIENT_SECRET: [u8; 32] = [7u8; 32];
+
+#[cfg(test)]
+fn pool_transfer() -> PoolTransfer {
+    let inputs = CircuitInputs::test_inputs();
+    PoolTransfer {
+        secret: inputs.private.secret,
+        transfer_count: DEFAULT_TRANSFER_COUNT,
+        funding_account: inputs.private.funding_account,
+        funding_amount: DEFAULT_FUNDING_AMOUNT,
+        root_hash: inputs.public.root_hash,
+        storage_proof: inputs.private.storage_proof,
+        recipient: UnspendableAccount::inner_hash_from_secret(&RECIPIENT_SECRET),
+    }
+}
+
+#[cfg(test)]
+fn run_test(transfer: &PoolTransfer) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+    let (mut builder, mut pw) = crate::circuit_helpers::setup_test_builder_and_witness(false);
+    let targets = TransferTargets::new(&mut builder);
+    PoolTransfer::circuit(&targets, &mut builder);
+
+    transfer.fill_targets(&mut pw, targets)?;
+    crate::circuit_helpers::build_and_prove_test(builder, pw)
+}
+
+#[test]
+fn transfer_pays_into_recipient_account() {
+    let transfer = pool_transfer();
+    let proof = run_test(&transfer).unwrap();
+    let pis = &proof.public_inputs;
+
+    let nullifier = NullifierHash::try_from(&pis[NULLIFIER_START_INDEX..NULLIFIER_END_INDEX]);
+    assert_eq!(nullifier.unwrap(), CircuitInputs::test_inputs().public.nullifier);
+
+    let amount = pis[FUNDING_AMOUNT_START_INDEX..FUNDING_AMOUNT_END_INDEX]
+        .try_into()
+        .unwrap();
+    assert_eq!(felts_to_u128(amount).unwrap(), DEFAULT_FUNDING_AMOUNT);
+
+    // The recipient can spend from the new account with their own secret.
+    let new_account = AccountId::try_from(&pis[NEW_ACCOUNT_START_INDEX..NEW_ACCOUNT_END_INDEX]);
+    let recipient_account = UnspendableAccount::from_secret(&RECIPIENT_SECRET).account_id;
+    assert_eq!(new_account.unwrap(), AccountId::from(recipient_account));
+    assert_eq!(transfer.new_account(), AccountId::from(recipient_account));
+}
+
+#[test]
+fn transfer_of_a_different_amount_is_rejected() {
+    let transfer = PoolTransfer {
+        funding_amount: DEFAULT_FUNDING_AMOUNT + 1,
+        ..pool_transfer()
+    };
+    assert!(run_test(&transfer).is_err());
+}
+
+#[test]
+fn transfer_with_wrong_secret_is_rejected() {
+    let transfer = PoolTransfer {
+        secret: [9u8; 32].into(),
+        ..pool_transfer()
+    };
+    assert!(run_test(&transfer).is_err());
+}
@@ -0,0 +1,67 @@
+use plonky2::{
+    iop::witness::{PartialWitness, WitnessWrite},
+    plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
+};
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::storage_proof::leaf::{LeafInputs, LeafTargets};
+use wormhole_circuit::types::AccountId;
+use wormhole_circuit::vectors::{test_vectors, TestVectors};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::felts_to_hashout;
+
+#[test]
+fn test_vectors_check() {
+    test_vectors().check().unwrap();
+}
+
+#[test]
+fn test_vectors_are_stable() {
+    assert_eq!(test_vectors(), test_vectors());
+}
+
+#[test]
+fn test_vectors_round_trip_through_json() {
+    let vectors = test_vectors();
+    let json = serde_json::to_string(&vectors).unwrap();
+    let decoded: TestVectors = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, vectors);
+    decoded.check().unwrap();
+}
+
+#[test]
+fn tampered_test_vectors_fail_check() {
+    let mut vectors = test_vectors();
+    vectors.nullifiers[0].transfer_count = "7".to_string();
+    assert!(vectors.check().is_err());
+
+    let mut vectors = test_vectors();
+    vectors.leaf_hashes[1].funding_amount = "2".to_string();
+    assert!(vectors.check().is_err());
+}
+
+fn circuit_leaf_hash(leaf_inputs: &LeafInputs) -> Vec<F> {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let targets = LeafTargets::new_private(&mut builder);
+    let hash = targets.hash(&mut builder);
+    builder.register_public_inputs(&hash.elements);
+    let data = builder.build::<C>();
+
+    let mut pw = PartialWitness::new();
+    pw.set_target_arr(&targets.transfer_count, &leaf_inputs.transfer_count).unwrap();
+    pw.set_hash_target(targets.funding_account, felts_to_hashout(&leaf_inputs.funding_account.0))
+        .unwrap();
+    pw.set_hash_target(targets.to_account, felts_to_hashout(&leaf_inputs.to_account.0)).unwrap();
+    pw.set_target_arr(&targets.funding_amount, &leaf_inputs.funding_amount).unwrap();
+
+    data.prove(pw).unwrap().public_inputs
+}
+
+#[test]
+fn leaf_hash_matches_circuit() {
+    let leaf_inputs = LeafInputs::test_inputs();
+    assert_eq!(circuit_leaf_hash(&leaf_inputs), leaf_inputs.hash());
+
+    let account = AccountId::try_from([0xaa; 32]).unwrap();
+    let leaf_inputs = LeafInputs::new(u64::MAX, account, account, u128::MAX).unwrap();
+    assert_eq!(circuit_leaf_hash(&leaf_inputs), leaf_inputs.hash());
+}
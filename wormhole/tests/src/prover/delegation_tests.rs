@@ -0,0 +1,73 @@
+use plonky2::field::types::Field;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::delegation::{ClientCommitment, DelegatedClaim, EXIT_ACCOUNT_START_INDEX};
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use wormhole_prover::delegation::{DelegatedProver, DelegationClient};
+use zk_circuits_common::circuit::F;
+
+#[cfg(test)]
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+#[cfg(test)]
+fn commitment(inputs: &CircuitInputs) -> ClientCommitment {
+    ClientCommitment {
+        secret: inputs.private.secret,
+        transfer_count: inputs.private.transfer_count,
+        exit_account: inputs.public.exit_account,
+    }
+}
+
+#[cfg(test)]
+fn claim(inputs: &CircuitInputs, client: &DelegationClient) -> DelegatedClaim {
+    DelegatedClaim {
+        client_proof: client.prove(&commitment(inputs)).unwrap(),
+        funding_account: inputs.private.funding_account,
+        funding_amount: inputs.public.funding_amount,
+        root_hash: inputs.public.root_hash,
+        storage_proof: inputs.private.storage_proof.clone(),
+    }
+}
+
+#[test]
+fn delegated_proof_has_wormhole_public_inputs() {
+    let inputs = CircuitInputs::test_inputs();
+    let client = DelegationClient::new(CIRCUIT_CONFIG);
+    let prover = DelegatedProver::new(CIRCUIT_CONFIG, client.verifier_data());
+
+    let claim = claim(&inputs, &client);
+    assert_eq!(
+        claim.unspendable_account().unwrap(),
+        commitment(&inputs).unspendable_account()
+    );
+    let proof = prover.prove(&claim).unwrap();
+
+    prover.verifier_data().verify(proof.clone()).unwrap();
+    assert_eq!(PublicCircuitInputs::try_from(&proof).unwrap(), inputs.public);
+}
+
+#[test]
+fn claim_for_other_secret_fails() {
+    let inputs = CircuitInputs::test_inputs();
+    let client = DelegationClient::new(CIRCUIT_CONFIG);
+    let prover = DelegatedProver::new(CIRCUIT_CONFIG, client.verifier_data());
+
+    // The storage proof doesn't pay the unspendable account of another secret.
+    let mut other = inputs.clone();
+    other.private.secret = [9u8; 32].into();
+    let claim = claim(&other, &client);
+    assert!(prover.prove(&claim).is_err());
+}
+
+#[test]
+fn tampered_client_proof_is_rejected() {
+    let inputs = CircuitInputs::test_inputs();
+    let client = DelegationClient::new(CIRCUIT_CONFIG);
+    let prover = DelegatedProver::new(CIRCUIT_CONFIG, client.verifier_data());
+
+    // Redirecting the funds to another exit account invalidates the client proof.
+    let mut claim = claim(&inputs, &client);
+    claim.client_proof.public_inputs[EXIT_ACCOUNT_START_INDEX] += F::ONE;
+    let err = prover.prove(&claim).unwrap_err();
+    assert!(err.to_string().contains("client proof"));
+}
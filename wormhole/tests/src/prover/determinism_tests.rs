@@ -0,0 +1,44 @@
+use plonky2::plonk::circuit_data::CircuitConfig;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::witness::FillMode;
+use wormhole_prover::WormholeProver;
+
+use crate::circuit_helpers::assert_proofs_deterministic;
+
+#[cfg(test)]
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+#[test]
+fn circuit_digest_is_deterministic() {
+    let first = WormholeCircuit::new(CIRCUIT_CONFIG).build_verifier();
+    let second = WormholeCircuit::new(CIRCUIT_CONFIG).build_verifier();
+    assert_eq!(
+        first.verifier_only.circuit_digest,
+        second.verifier_only.circuit_digest
+    );
+}
+
+#[test]
+fn no_zk_proofs_are_deterministic() {
+    let inputs = CircuitInputs::test_inputs();
+    assert_proofs_deterministic(|| WormholeProver::new(CIRCUIT_CONFIG).commit(&inputs)?.prove());
+}
+
+#[test]
+fn hardened_filling_proves_the_same_proof() {
+    let inputs = CircuitInputs::test_inputs();
+    let fast = WormholeProver::new(CIRCUIT_CONFIG)
+        .commit(&inputs)
+        .unwrap()
+        .prove()
+        .unwrap();
+    let hardened = WormholeProver::new(CIRCUIT_CONFIG)
+        .with_fill_mode(FillMode::Hardened)
+        .commit(&inputs)
+        .unwrap()
+        .prove()
+        .unwrap();
+    assert_eq!(hardened.to_bytes(), fast.to_bytes());
+}
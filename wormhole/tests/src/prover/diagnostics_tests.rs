@@ -0,0 +1,60 @@
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_circuit::storage_proof::StorageProofParams;
+use wormhole_prover::diagnostics::{ConstraintFamily, RedactedWitness};
+
+#[cfg(test)]
+fn diagnose(inputs: &CircuitInputs) -> Option<ConstraintFamily> {
+    let witness = RedactedWitness::from_inputs(inputs, StorageProofParams::default());
+    let imported = RedactedWitness::from_json(&witness.to_json().unwrap()).unwrap();
+    assert_eq!(imported, witness);
+    imported.check().unwrap()
+}
+
+#[test]
+fn valid_inputs_pass_every_family() {
+    assert_eq!(diagnose(&CircuitInputs::test_inputs()), None);
+}
+
+#[test]
+fn wrong_secret_fails_nullifier() {
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.private.secret = [7u8; 32].into();
+    assert_eq!(diagnose(&inputs), Some(ConstraintFamily::Nullifier));
+}
+
+#[test]
+fn wrong_root_hash_fails_storage_proof_path() {
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.root_hash = [0u8; 32].try_into().unwrap();
+    assert_eq!(diagnose(&inputs), Some(ConstraintFamily::StorageProofPath));
+}
+
+#[test]
+fn wrong_funding_amount_fails_leaf() {
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.funding_amount += 1;
+    assert_eq!(diagnose(&inputs), Some(ConstraintFamily::Leaf));
+}
+
+#[test]
+fn too_many_nodes_fails_shape_with_code() {
+    let inputs = CircuitInputs::test_inputs();
+    let node_max_size_f = StorageProofParams::default().node_max_size_f;
+    let params = StorageProofParams::new(1, node_max_size_f).unwrap();
+    let witness = RedactedWitness::from_inputs(&inputs, params);
+
+    assert_eq!(witness.check().unwrap(), Some(ConstraintFamily::StorageProofShape));
+    let shape = &witness.checks[2];
+    assert_eq!(shape.code.as_deref(), Some("storage_proof.too_many_nodes"));
+}
+
+#[test]
+fn export_leaves_out_private_inputs() {
+    let inputs = CircuitInputs::test_inputs();
+    let witness = RedactedWitness::from_inputs(&inputs, StorageProofParams::default());
+    let json = witness.to_json().unwrap();
+    for name in ["secret", "transfer_count", "funding_account", "unspendable_account"] {
+        assert!(!json.contains(name));
+    }
+}
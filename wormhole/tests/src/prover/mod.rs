@@ -1,2 +1,12 @@
 #[cfg(test)]
+pub mod delegation_tests;
+#[cfg(test)]
+pub mod diagnostics_tests;
+#[cfg(test)]
+pub mod determinism_tests;
+#[cfg(test)]
 pub mod prover_tests;
+#[cfg(test)]
+pub mod scheduler_tests;
+#[cfg(test)]
+pub mod self_test_tests;
@@ -1,11 +1,20 @@
 use std::fs;
 
 use hex;
+use plonky2::field::types::Field;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use test_helpers::storage_proof::TestInputs;
-use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use wormhole_circuit::inputs::{
+    CircuitInputs, PublicCircuitInputs, FUNDING_AMOUNT_START_INDEX, PUBLIC_INPUTS_FELTS_LEN,
+};
+use wormhole_circuit::nullifier::{Nullifier, NullifierVersion};
+use wormhole_circuit::storage_proof::leaf::LeafError;
+use wormhole_circuit::types::{AccountId, NullifierHash, RootHash};
+use wormhole_prover::estimate::{estimate_proving, estimate_proving_for};
+use wormhole_prover::witness::witness_from_inputs;
 use wormhole_prover::WormholeProver;
-use zk_circuits_common::utils::BytesDigest;
+use wormhole_verifier::WormholeVerifier;
+use zk_circuits_common::circuit::F;
 
 #[cfg(test)]
 const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
@@ -17,6 +26,151 @@ fn commit_and_prove() {
     prover.commit(&inputs).unwrap().prove().unwrap();
 }
 
+#[test]
+fn prove_many_keeps_results_in_input_order() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.max_parallel_proofs() >= 1);
+
+    let valid = CircuitInputs::test_inputs();
+    let mut invalid = CircuitInputs::test_inputs();
+    invalid.public.funding_amount += 1;
+    let results = prover.prove_many(vec![valid.clone(), invalid, valid.clone()]);
+
+    assert_eq!(results.len(), 3);
+    assert!(results[1].is_err());
+    for result in [&results[0], &results[2]] {
+        let proof = result.as_ref().unwrap();
+        assert_eq!(PublicCircuitInputs::try_from(proof).unwrap(), valid.public);
+    }
+    assert!(prover.prove_many(vec![]).is_empty());
+
+    let committed = prover.commit(&valid).unwrap();
+    let results = committed.prove_many(vec![valid]);
+    assert!(results[0].is_err());
+}
+
+#[test]
+fn public_funding_amount_must_match_leaf() {
+    // The leaf in the storage proof holds the original amount, so claiming more fails.
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.funding_amount += 1;
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let result = prover.commit(&inputs).and_then(|prover| prover.prove());
+    assert!(result.is_err());
+}
+
+#[test]
+fn stale_transfer_count_is_rejected_before_proving() {
+    let mut inputs = CircuitInputs::test_inputs();
+    let transfer_count = inputs.private.transfer_count;
+    inputs.private.transfer_count -= 1;
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let err = prover.commit(&inputs).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<LeafError>(),
+        Some(&LeafError::StaleTransferCount {
+            given: transfer_count - 1,
+            found: transfer_count
+        })
+    );
+}
+
+#[test]
+fn tampered_public_funding_amount_fails_verification() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let mut proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    proof.public_inputs[FUNDING_AMOUNT_START_INDEX + 1] += F::ONE;
+
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    assert!(verifier.verify(proof).is_err());
+}
+
+#[test]
+fn estimate_matches_built_circuit() {
+    let estimate = estimate_proving(CIRCUIT_CONFIG).unwrap();
+    assert!(estimate.rows.is_power_of_two());
+    assert!(estimate.approx_seconds > 0.0);
+    assert!(estimate.approx_memory > 0);
+
+    // The gate count is a lower bound on the rows of the built circuit.
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let exact = estimate_proving_for(&prover.circuit_data.common).unwrap();
+    assert_eq!(exact.rows, prover.circuit_data.common.degree());
+    assert!(estimate.rows <= exact.rows && exact.rows <= 2 * estimate.rows);
+}
+
+#[test]
+fn from_artifacts_dir_fails_without_artifacts() {
+    let dir = std::env::temp_dir().join("wormhole-prover-missing-artifacts");
+    let err = WormholeProver::from_artifacts_dir(&dir).unwrap_err();
+    assert!(err.to_string().contains("not found"));
+}
+
+#[test]
+fn prove_v2_nullifier_with_migration_circuit() {
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.nullifier = Nullifier::from_preimage_versioned(
+        inputs.private.secret.as_slice(),
+        inputs.private.transfer_count,
+        NullifierVersion::V2,
+    )
+    .hash
+    .into();
+
+    let v2_cutover = inputs.private.transfer_count;
+    let prover = WormholeProver::with_nullifier_migration(CIRCUIT_CONFIG, v2_cutover);
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let pis = &proof.public_inputs;
+    let public_inputs = PublicCircuitInputs::try_from_slice(&pis[..PUBLIC_INPUTS_FELTS_LEN]);
+    assert_eq!(public_inputs.unwrap().nullifier, inputs.public.nullifier);
+    let version = NullifierVersion::try_from_public_inputs(pis).unwrap();
+    assert_eq!(version, NullifierVersion::V2);
+
+    // A deposit before the cutover is claimed with its version 1 nullifier only.
+    let prover = WormholeProver::with_nullifier_migration(CIRCUIT_CONFIG, v2_cutover + 1);
+    assert!(prover.commit(&inputs).is_err());
+
+    // The default circuit only accepts version 1 nullifiers.
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.commit(&inputs).is_err());
+}
+
+#[test]
+fn prove_with_delegated_witness() {
+    let inputs = CircuitInputs::test_inputs();
+    let witness = witness_from_inputs(CIRCUIT_CONFIG, &inputs).unwrap();
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let proof = prover.prove_with_witness(&witness).unwrap();
+
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    verifier.verify(proof.clone()).unwrap();
+    assert_eq!(PublicCircuitInputs::try_from(&proof).unwrap(), inputs.public);
+}
+
+#[test]
+fn truncated_witness_is_rejected() {
+    let inputs = CircuitInputs::test_inputs();
+    let mut witness = witness_from_inputs(CIRCUIT_CONFIG, &inputs).unwrap();
+    witness.truncate(witness.len() - 8);
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.prove_with_witness(&witness).is_err());
+}
+
+#[test]
+fn non_canonical_witness_value_is_rejected() {
+    let inputs = CircuitInputs::test_inputs();
+    let mut witness = witness_from_inputs(CIRCUIT_CONFIG, &inputs).unwrap();
+    witness[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.prove_with_witness(&witness).is_err());
+}
+
 #[test]
 fn proof_can_be_deserialized() {
     let prover = WormholeProver::new(CIRCUIT_CONFIG);
@@ -28,57 +182,40 @@ fn proof_can_be_deserialized() {
     // Build the expected values
     let expected = PublicCircuitInputs {
         funding_amount: 1_000_000_000_000u128,
-        nullifier: BytesDigest::try_from([
+        nullifier: NullifierHash::try_from([
             169, 76, 150, 35, 66, 248, 76, 193, 57, 204, 106, 33, 169, 160, 248, 113, 235, 144,
             212, 48, 9, 232, 146, 7, 105, 125, 170, 24, 33, 54, 135, 28,
         ])
         .unwrap(),
-        root_hash: BytesDigest::try_from([
+        root_hash: RootHash::try_from([
             95, 250, 42, 181, 176, 219, 152, 131, 178, 43, 30, 88, 16, 147, 46, 169, 217, 234, 177,
             132, 7, 48, 253, 57, 172, 231, 28, 38, 187, 141, 8, 45,
         ])
         .unwrap(),
-        exit_account: BytesDigest::try_from([4u8; 32]).unwrap(),
+        exit_account: AccountId::try_from([4u8; 32]).unwrap(),
     };
     assert_eq!(public_inputs, expected);
     println!("{:?}", public_inputs);
 }
 
 #[test]
-fn get_public_inputs() {
+fn expected_public_inputs_match_proof() {
     let prover = WormholeProver::new(CIRCUIT_CONFIG);
     let inputs = CircuitInputs::test_inputs();
-    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
-    let public_inputs = proof.public_inputs;
-    println!("{:?}", public_inputs);
-}
-
-#[test]
-#[ignore = "debug"]
-fn export_test_proof() {
-    const FILE_PATH: &str = "../../dummy_proof.bin";
-
-    let circuit_config = CircuitConfig::standard_recursion_config();
+    let expected = prover.expected_public_inputs(&inputs);
 
-    let prover = WormholeProver::new(circuit_config);
-    let inputs = CircuitInputs::test_inputs();
     let proof = prover.commit(&inputs).unwrap().prove().unwrap();
-    let proof_bytes = proof.to_bytes();
-    let _ = fs::write(FILE_PATH, proof_bytes);
+    assert_eq!(expected, PublicCircuitInputs::try_from(&proof).unwrap());
+    assert_eq!(expected.to_field_elements(), proof.public_inputs);
 }
 
 #[test]
-#[ignore = "debug"]
-fn export_test_proof_zk() {
-    const FILE_PATH: &str = "../../dummy_proof_zk.bin";
-
-    let circuit_config = CircuitConfig::standard_recursion_zk_config();
-
-    let prover = WormholeProver::new(circuit_config);
+fn get_public_inputs() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
     let inputs = CircuitInputs::test_inputs();
     let proof = prover.commit(&inputs).unwrap().prove().unwrap();
-    let proof_bytes = proof.to_bytes();
-    let _ = fs::write(FILE_PATH, proof_bytes);
+    let public_inputs = proof.public_inputs;
+    println!("{:?}", public_inputs);
 }
 
 #[test]
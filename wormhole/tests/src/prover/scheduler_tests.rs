@@ -0,0 +1,101 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use plonky2::plonk::circuit_data::CircuitConfig;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use wormhole_prover::scheduler::{JobId, JobStatus, ProofJob, ProverScheduler, SchedulerConfig};
+use wormhole_prover::WormholeProver;
+
+#[cfg(test)]
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+#[cfg(test)]
+const UNLIMITED: SchedulerConfig = SchedulerConfig {
+    max_concurrent: 1,
+    memory_budget: usize::MAX,
+};
+
+#[cfg(test)]
+fn job(priority: u32, deadline: Option<Instant>) -> ProofJob {
+    ProofJob {
+        inputs: CircuitInputs::test_inputs(),
+        priority,
+        deadline,
+    }
+}
+
+#[cfg(test)]
+fn wait_until_running(scheduler: &ProverScheduler, id: JobId) {
+    while scheduler.status(id) != Some(JobStatus::Running) {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn scheduled_jobs_are_proven() {
+    let scheduler = ProverScheduler::new(WormholeProver::new(CIRCUIT_CONFIG), UNLIMITED).unwrap();
+    let inputs = CircuitInputs::test_inputs();
+    let id = scheduler.submit(job(0, None));
+
+    let proof = scheduler.wait(id).unwrap();
+    assert_eq!(PublicCircuitInputs::try_from(&proof).unwrap(), inputs.public);
+    // The result can only be taken once.
+    assert_eq!(scheduler.status(id), None);
+    assert!(scheduler.wait(id).is_err());
+}
+
+#[test]
+fn queued_jobs_start_by_priority_then_deadline() {
+    let scheduler = ProverScheduler::new(WormholeProver::new(CIRCUIT_CONFIG), UNLIMITED).unwrap();
+    let running = scheduler.submit(job(0, None));
+    wait_until_running(&scheduler, running);
+
+    let later = Instant::now() + Duration::from_secs(3600);
+    let low = scheduler.submit(job(0, None));
+    let high_no_deadline = scheduler.submit(job(5, None));
+    let high_with_deadline = scheduler.submit(job(5, Some(later)));
+    let expired = scheduler.submit(job(9, Some(Instant::now())));
+
+    assert_eq!(scheduler.status(high_with_deadline), Some(JobStatus::Queued { position: 0 }));
+    assert_eq!(scheduler.status(high_no_deadline), Some(JobStatus::Queued { position: 1 }));
+    assert_eq!(scheduler.status(low), Some(JobStatus::Queued { position: 2 }));
+    assert_eq!(scheduler.status(expired), Some(JobStatus::Expired));
+
+    let err = scheduler.wait(expired).unwrap_err();
+    assert!(err.to_string().contains("expired"), "{}", err);
+    for id in [running, high_with_deadline, high_no_deadline, low] {
+        scheduler.wait(id).unwrap();
+    }
+}
+
+#[test]
+fn failed_jobs_report_the_error() {
+    let scheduler = ProverScheduler::new(WormholeProver::new(CIRCUIT_CONFIG), UNLIMITED).unwrap();
+    let mut invalid = job(0, None);
+    invalid.inputs.public.funding_amount += 1;
+    let id = scheduler.submit(invalid);
+
+    while !matches!(scheduler.status(id), Some(JobStatus::Failed(_))) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(scheduler.wait(id).is_err());
+}
+
+#[test]
+fn memory_budget_must_fit_a_proof() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let config = SchedulerConfig {
+        max_concurrent: 2,
+        memory_budget: 1 << 20,
+    };
+    let err = ProverScheduler::new(prover, config).err().unwrap();
+    assert!(err.to_string().contains("memory budget"), "{}", err);
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let config = SchedulerConfig {
+        max_concurrent: 0,
+        ..UNLIMITED
+    };
+    assert!(ProverScheduler::new(prover, config).is_err());
+}
@@ -0,0 +1,54 @@
+use plonky2::field::types::Field;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use wormhole_circuit::storage_proof::StorageProofParams;
+use wormhole_circuit::types::ChainId;
+use wormhole_prover::self_test::self_test_inputs;
+use wormhole_prover::WormholeProver;
+use zk_circuits_common::circuit::F;
+
+#[cfg(test)]
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+#[test]
+fn self_test_passes_without_committing() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let report = prover.self_test().unwrap();
+    assert_eq!(report.total(), report.fill + report.prove + report.verify);
+
+    // The prover can still be used afterwards.
+    let inputs = self_test_inputs(StorageProofParams::default(), None).unwrap();
+    prover.commit(&inputs).unwrap().prove().unwrap();
+}
+
+#[test]
+fn self_test_covers_circuit_variants() {
+    for prover in [
+        WormholeProver::with_context(CIRCUIT_CONFIG),
+        WormholeProver::with_destination_chain(CIRCUIT_CONFIG),
+        WormholeProver::with_relayer_fee(CIRCUIT_CONFIG),
+        WormholeProver::with_public_inputs_digest(CIRCUIT_CONFIG),
+        WormholeProver::with_exit_account_format(CIRCUIT_CONFIG),
+        WormholeProver::with_spent_nullifier_accumulator(CIRCUIT_CONFIG, 4).unwrap(),
+        WormholeProver::with_funding_commitment(CIRCUIT_CONFIG),
+        WormholeProver::with_intent_hash(CIRCUIT_CONFIG),
+    ] {
+        prover.self_test().unwrap();
+    }
+}
+
+#[test]
+fn self_test_detects_corrupted_prover_data() {
+    let mut prover = WormholeProver::new(CIRCUIT_CONFIG);
+    prover.circuit_data.prover_only.circuit_digest.elements[0] += F::ONE;
+    let err = prover.self_test().unwrap_err();
+    assert!(err.to_string().contains("self test"));
+}
+
+#[test]
+fn self_test_inputs_depend_on_chain() {
+    let params = StorageProofParams::default();
+    let inputs = self_test_inputs(params, None).unwrap();
+    let for_chain = self_test_inputs(params, Some(ChainId::new(1))).unwrap();
+    assert_ne!(inputs.public.nullifier, for_chain.public.nullifier);
+    assert_eq!(inputs.public, self_test_inputs(params, None).unwrap().public);
+}
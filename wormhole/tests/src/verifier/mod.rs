@@ -1,2 +1,8 @@
 #[cfg(test)]
+pub mod pallet_simulation_tests;
+#[cfg(test)]
+pub mod recursion_tests;
+#[cfg(test)]
+pub mod registry_tests;
+#[cfg(test)]
 pub mod verifier_tests;
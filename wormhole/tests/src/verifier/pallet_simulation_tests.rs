@@ -0,0 +1,152 @@
+//! Simulates the pallet side of a wormhole claim, encoding the intended on-chain semantics.
+//!
+//! The pallet keeps the storage roots of the last [`ROOT_WINDOW`] blocks and the set of spent
+//! nullifiers. A claim is a serialized leaf proof: it is verified and decoded with
+//! [`WormholeVerifier::verify_and_decode`], its root must be in the window and its nullifier
+//! unspent, and only then is the nullifier marked spent and the exit account credited.
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use plonky2::plonk::circuit_data::CircuitConfig;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs};
+use wormhole_circuit::types::RootHash;
+use wormhole_prover::WormholeProver;
+use wormhole_verifier::WormholeVerifier;
+
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+/// The number of recent storage roots claims may be made against.
+const ROOT_WINDOW: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+enum ClaimError {
+    InvalidProof,
+    UnknownRoot,
+    NullifierSpent,
+}
+
+struct SimulatedPallet {
+    verifier: WormholeVerifier,
+    roots: VecDeque<RootHash>,
+    nullifiers: HashSet<[u8; 32]>,
+    balances: HashMap<[u8; 32], u128>,
+}
+
+impl SimulatedPallet {
+    fn new(verifier: WormholeVerifier) -> Self {
+        Self {
+            verifier,
+            roots: VecDeque::with_capacity(ROOT_WINDOW),
+            nullifiers: HashSet::new(),
+            balances: HashMap::new(),
+        }
+    }
+
+    /// Records the storage root of a new block, dropping the oldest one out of the window.
+    fn import_block(&mut self, root: RootHash) {
+        if self.roots.len() == ROOT_WINDOW {
+            self.roots.pop_front();
+        }
+        self.roots.push_back(root);
+    }
+
+    fn claim(&mut self, proof_bytes: &[u8]) -> Result<PublicCircuitInputs, ClaimError> {
+        let inputs = self
+            .verifier
+            .verify_and_decode(proof_bytes)
+            .map_err(|_| ClaimError::InvalidProof)?;
+        if !self.roots.contains(&inputs.root_hash) {
+            return Err(ClaimError::UnknownRoot);
+        }
+        if self.nullifiers.contains(&*inputs.nullifier) {
+            return Err(ClaimError::NullifierSpent);
+        }
+
+        self.nullifiers.insert(*inputs.nullifier);
+        *self.balances.entry(*inputs.exit_account).or_default() += inputs.funding_amount;
+        Ok(inputs)
+    }
+
+    fn balance(&self, account: &[u8; 32]) -> u128 {
+        self.balances.get(account).copied().unwrap_or_default()
+    }
+}
+
+/// A pallet whose window holds the root of the test inputs, and a claim against it.
+fn pallet_with_claim() -> (SimulatedPallet, Vec<u8>, CircuitInputs) {
+    let inputs = CircuitInputs::test_inputs();
+    let proof = WormholeProver::new(CIRCUIT_CONFIG)
+        .commit(&inputs)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let mut pallet = SimulatedPallet::new(WormholeVerifier::new(CIRCUIT_CONFIG, None));
+    pallet.import_block(inputs.public.root_hash);
+    (pallet, proof.to_bytes(), inputs)
+}
+
+fn other_root(block: u8) -> RootHash {
+    RootHash::try_from([block; 32]).unwrap()
+}
+
+#[test]
+fn claim_credits_exit_account_and_spends_nullifier() {
+    let (mut pallet, proof_bytes, inputs) = pallet_with_claim();
+
+    let claimed = pallet.claim(&proof_bytes).unwrap();
+    assert_eq!(claimed, inputs.public);
+    assert_eq!(
+        pallet.balance(&inputs.public.exit_account),
+        inputs.public.funding_amount
+    );
+    assert!(pallet.nullifiers.contains(&*inputs.public.nullifier));
+}
+
+#[test]
+fn replayed_claim_is_rejected() {
+    let (mut pallet, proof_bytes, inputs) = pallet_with_claim();
+    pallet.claim(&proof_bytes).unwrap();
+
+    assert_eq!(pallet.claim(&proof_bytes), Err(ClaimError::NullifierSpent));
+    // The replay didn't pay out again.
+    assert_eq!(
+        pallet.balance(&inputs.public.exit_account),
+        inputs.public.funding_amount
+    );
+}
+
+#[test]
+fn claim_against_stale_root_is_rejected() {
+    let (mut pallet, proof_bytes, inputs) = pallet_with_claim();
+
+    // The root is still claimable until it leaves the window.
+    for block in 1..ROOT_WINDOW as u8 {
+        pallet.import_block(other_root(block));
+    }
+    assert!(pallet.roots.contains(&inputs.public.root_hash));
+
+    pallet.import_block(other_root(ROOT_WINDOW as u8));
+    assert_eq!(pallet.claim(&proof_bytes), Err(ClaimError::UnknownRoot));
+    assert!(pallet.nullifiers.is_empty());
+    assert_eq!(pallet.balance(&inputs.public.exit_account), 0);
+}
+
+#[test]
+fn malformed_proofs_are_rejected() {
+    let (mut pallet, proof_bytes, _) = pallet_with_claim();
+
+    let truncated = &proof_bytes[..proof_bytes.len() - 1];
+    assert_eq!(pallet.claim(truncated), Err(ClaimError::InvalidProof));
+    assert_eq!(pallet.claim(&[]), Err(ClaimError::InvalidProof));
+
+    // Tamper with the serialized public inputs, which come last.
+    let mut tampered = proof_bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert_eq!(pallet.claim(&tampered), Err(ClaimError::InvalidProof));
+
+    // Rejected claims don't change the state, so the valid one still goes through.
+    assert!(pallet.nullifiers.is_empty());
+    pallet.claim(&proof_bytes).unwrap();
+}
@@ -0,0 +1,54 @@
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::inputs::{CircuitInputs, PublicCircuitInputs, PUBLIC_INPUTS_FELTS_LEN};
+use wormhole_prover::WormholeProver;
+use wormhole_verifier::recursion::add_wormhole_proof_verification;
+use wormhole_verifier::WormholeVerifier;
+use zk_circuits_common::circuit::{C, D, F};
+
+#[cfg(test)]
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+#[test]
+fn outer_circuit_verifies_wormhole_proof_and_reads_public_inputs() {
+    let inputs = CircuitInputs::test_inputs();
+    let proof = WormholeProver::new(CIRCUIT_CONFIG)
+        .commit(&inputs)
+        .unwrap()
+        .prove()
+        .unwrap();
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+
+    // An outer circuit exposing the nullifier, root hash, funding amount and exit account of the
+    // verified proof.
+    let mut builder = CircuitBuilder::<F, D>::new(CIRCUIT_CONFIG);
+    let targets = add_wormhole_proof_verification(&mut builder, &verifier.circuit_data);
+    builder.register_public_inputs(&targets.nullifier.elements);
+    builder.register_public_inputs(&targets.root_hash.elements);
+    builder.register_public_inputs(&targets.funding_amount);
+    builder.register_public_inputs(&targets.exit_account.elements);
+    let outer = builder.build::<C>();
+
+    let mut pw = PartialWitness::new();
+    targets.set_proof(&mut pw, &proof).unwrap();
+    let outer_proof = outer.prove(pw).unwrap();
+    outer.verify(outer_proof.clone()).unwrap();
+
+    let public_inputs = PublicCircuitInputs::try_from_slice(&outer_proof.public_inputs).unwrap();
+    assert_eq!(public_inputs, inputs.public);
+    assert_eq!(
+        outer_proof.public_inputs,
+        proof.public_inputs[..PUBLIC_INPUTS_FELTS_LEN]
+    );
+}
+
+#[test]
+#[should_panic(expected = "public inputs")]
+fn rejects_circuits_exposing_only_the_digest() {
+    let verifier_data = WormholeCircuit::with_public_inputs_digest(CIRCUIT_CONFIG).build_verifier();
+    let mut builder = CircuitBuilder::<F, D>::new(CIRCUIT_CONFIG);
+    add_wormhole_proof_verification(&mut builder, &verifier_data);
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::util::serialization::DefaultGateSerializer;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::envelope::ProofEnvelope;
+use wormhole_circuit::inputs::CircuitInputs;
+use wormhole_prover::WormholeProver;
+use wormhole_verifier::registry::VerifierRegistry;
+use wormhole_verifier::WormholeVerifier;
+
+const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
+
+fn prove_envelope() -> ProofEnvelope {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    prover.commit(&inputs).unwrap().prove_envelope().unwrap()
+}
+
+/// A verifier of another circuit version, standing in for a previous release.
+fn other_verifier() -> WormholeVerifier {
+    WormholeVerifier::new(CircuitConfig::standard_recursion_zk_config(), None)
+}
+
+fn write_entry(dir: &Path, verifier: &WormholeVerifier) {
+    fs::create_dir_all(dir).unwrap();
+    let common_bytes = verifier
+        .circuit_data
+        .common
+        .to_bytes(&DefaultGateSerializer)
+        .unwrap();
+    fs::write(dir.join("common.bin"), common_bytes).unwrap();
+    let verifier_bytes = verifier.circuit_data.verifier_only.to_bytes().unwrap();
+    fs::write(dir.join("verifier.bin"), verifier_bytes).unwrap();
+}
+
+#[test]
+fn registry_dispatches_envelopes_by_digest() {
+    let envelope = prove_envelope();
+
+    let mut registry = VerifierRegistry::new();
+    assert!(registry.insert(other_verifier()).is_none());
+    assert!(registry.insert(WormholeVerifier::new(CIRCUIT_CONFIG, None)).is_none());
+    assert_eq!(registry.len(), 2);
+    assert!(registry.digests().any(|digest| digest == envelope.verifier_digest));
+
+    registry.verify(&envelope).unwrap();
+}
+
+#[test]
+fn registry_rejects_unknown_circuit_version() {
+    let envelope = prove_envelope();
+
+    let mut registry = VerifierRegistry::new();
+    registry.insert(other_verifier());
+    let result = registry.verify(&envelope);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("no verifier registered for"));
+
+    // Dropping a release stops its proofs from verifying.
+    let current = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    let digest = current.verifier_digest();
+    registry.insert(current);
+    registry.verify(&envelope).unwrap();
+    assert!(registry.remove(&digest).is_some());
+    assert!(registry.verify(&envelope).is_err());
+}
+
+#[test]
+fn registry_loads_directory_of_releases() {
+    let dir = std::env::temp_dir().join("wormhole-verifier-registry");
+    let _ = fs::remove_dir_all(&dir);
+    write_entry(&dir.join("current"), &WormholeVerifier::new(CIRCUIT_CONFIG, None));
+    write_entry(&dir.join("previous"), &other_verifier());
+    fs::write(dir.join("README"), "not a release").unwrap();
+
+    let registry = VerifierRegistry::load_dir(&dir).unwrap();
+    assert_eq!(registry.len(), 2);
+    registry.verify(&prove_envelope()).unwrap();
+
+    // The same release twice is rejected.
+    write_entry(&dir.join("copy"), &other_verifier());
+    let result = VerifierRegistry::load_dir(&dir);
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("which was already loaded"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
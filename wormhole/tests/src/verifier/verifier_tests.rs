@@ -1,11 +1,26 @@
+use plonky2::field::types::Field;
 use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::KeccakGoldilocksConfig;
 use plonky2::plonk::proof::ProofWithPublicInputs;
 use test_helpers::storage_proof::TestInputs;
-use wormhole_circuit::codec::FieldElementCodec;
-use wormhole_circuit::inputs::{CircuitInputs, EXIT_ACCOUNT_END_INDEX, EXIT_ACCOUNT_START_INDEX};
+use wormhole_circuit::accumulator::NullifierAccumulator;
+use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+use wormhole_circuit::codec::{ByteCodec, FieldElementCodec};
+use wormhole_circuit::envelope::ProofEnvelope;
+use wormhole_circuit::exit_format::ExitAccountFormat;
+use wormhole_circuit::funding_commitment::FundingAccountOpening;
+use wormhole_circuit::inputs::{
+    CircuitInputs, EXIT_ACCOUNT_END_INDEX, EXIT_ACCOUNT_FORMAT_INDEX, EXIT_ACCOUNT_START_INDEX,
+    FUNDING_COMMITMENT_START_INDEX, INTENT_HASH_START_INDEX, RELAYER_FEE_START_INDEX,
+};
+use wormhole_circuit::intent::ClaimIntent;
+use wormhole_circuit::relayer::RelayerFee;
 use wormhole_circuit::substrate_account::SubstrateAccount;
+use wormhole_circuit::types::{AccountId, ChainId, ProofContext, SecretBytes};
 use wormhole_prover::WormholeProver;
+use wormhole_verifier::shared::SharedVerifier;
 use wormhole_verifier::WormholeVerifier;
+use zk_circuits_common::circuit::F;
 
 #[cfg(test)]
 const CIRCUIT_CONFIG: CircuitConfig = CircuitConfig::standard_recursion_config();
@@ -21,6 +36,432 @@ fn verify_simple_proof() {
     verifier.verify(proof).unwrap();
 }
 
+#[test]
+fn verify_prepared_proof_repeatedly() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    let proof_bytes = proof.to_bytes();
+
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    let prepared = verifier.prepare(&proof_bytes).unwrap();
+    assert_eq!(prepared.proof(), &proof);
+    verifier.verify_prepared(&prepared).unwrap();
+    verifier.verify_prepared(&prepared).unwrap();
+
+    // Tampered public inputs still deserialize, but no longer verify.
+    let mut tampered = proof;
+    let modified_exit_account = SubstrateAccount::new(&[8u8; 32]).unwrap();
+    tampered.public_inputs[EXIT_ACCOUNT_START_INDEX..EXIT_ACCOUNT_END_INDEX]
+        .copy_from_slice(&modified_exit_account.to_field_elements());
+    let tampered = verifier.prepare(&tampered.to_bytes()).unwrap();
+    assert_ne!(tampered.hash(), prepared.hash());
+    assert!(verifier.verify_prepared(&tampered).is_err());
+
+    assert!(verifier.prepare(&proof_bytes[..16]).is_err());
+}
+
+#[test]
+fn verify_opaque_proof() {
+    use plonky2::field::types::PrimeField64;
+    use wormhole_verifier::prelude::Proof;
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = Proof::from(prover.commit(&inputs).unwrap().prove().unwrap());
+
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    let bytes = proof.to_bytes();
+    let decoded = Proof::from_bytes(bytes, &verifier.circuit_data.common).unwrap();
+    assert_eq!(decoded, proof);
+
+    let expected: Vec<u64> = inputs
+        .public
+        .to_field_elements()
+        .iter()
+        .map(PrimeField64::to_canonical_u64)
+        .collect();
+    assert_eq!(proof.public_inputs(), expected);
+
+    verifier.verify(decoded.into()).unwrap();
+}
+
+#[test]
+fn verify_proof_in_context() {
+    let mainnet = ProofContext::new([1u8; 32]);
+    let testnet = ProofContext::new([2u8; 32]);
+
+    let prover = WormholeProver::with_context(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover
+        .commit_with_context(&inputs, &mainnet)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let verifier = WormholeVerifier::with_context(CIRCUIT_CONFIG);
+    verifier.verify_in_context(proof.clone(), &mainnet).unwrap();
+
+    let err = verifier.verify_in_context(proof.clone(), &testnet).unwrap_err();
+    assert!(err.to_string().contains("different context"));
+
+    // Rewriting the context public inputs invalidates the proof.
+    let mut replayed = proof;
+    replayed.public_inputs[16..20].copy_from_slice(&testnet.to_felts());
+    assert!(verifier.verify_in_context(replayed, &testnet).is_err());
+}
+
+#[test]
+fn context_must_match_circuit() {
+    let inputs = CircuitInputs::test_inputs();
+    let context = ProofContext::new([1u8; 32]);
+
+    let prover = WormholeProver::with_context(CIRCUIT_CONFIG);
+    assert!(prover.commit(&inputs).is_err());
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.commit_with_context(&inputs, &context).is_err());
+}
+
+#[test]
+fn destination_chain_must_match_circuit() {
+    let inputs = CircuitInputs::test_inputs();
+    let chain_id = ChainId::new(1);
+
+    let prover = WormholeProver::with_destination_chain(CIRCUIT_CONFIG);
+    assert!(prover.commit(&inputs).is_err());
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.commit_for_chain(&inputs, &chain_id).is_err());
+}
+
+#[test]
+fn deposit_without_destination_chain_cannot_be_claimed_on_chain() {
+    // The test deposit pays an unspendable account derived without a chain id, so no chain of a
+    // multi-chain deployment accepts it.
+    let prover = WormholeProver::with_destination_chain(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let commitment = prover.commit_for_chain(&inputs, &ChainId::new(1)).unwrap();
+    assert!(commitment.prove().is_err());
+}
+
+#[test]
+fn proof_without_destination_chain_is_rejected_for_chain() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    let verifier = WormholeVerifier::with_destination_chain(CIRCUIT_CONFIG);
+    let err = verifier
+        .verify_for_chain(proof.clone(), &ChainId::new(1))
+        .unwrap_err();
+    assert!(err.to_string().contains("not bound to a destination chain"));
+
+    // Appending another chain's id doesn't make the proof verify.
+    let mut replayed = proof;
+    replayed.public_inputs.extend(ChainId::new(1).to_felts());
+    let err = verifier
+        .verify_for_chain(replayed.clone(), &ChainId::new(2))
+        .unwrap_err();
+    assert!(err.to_string().contains("different destination chain"));
+    assert!(verifier
+        .verify_for_chain(replayed, &ChainId::new(1))
+        .is_err());
+}
+
+#[test]
+fn relayer_fee_is_paid_out_of_funding_amount() {
+    let inputs = CircuitInputs::test_inputs();
+    let funding_amount = inputs.public.funding_amount;
+    let relayer_account = AccountId::try_from([7u8; 32]).unwrap();
+    let relayer_fee = RelayerFee::new(relayer_account, 1, funding_amount).unwrap();
+    assert_eq!(relayer_fee.exit_amount, funding_amount - 1);
+
+    let prover = WormholeProver::with_relayer_fee(CIRCUIT_CONFIG);
+    let proof = prover
+        .commit_with_relayer_fee(&inputs, &relayer_fee)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let verifier = WormholeVerifier::with_relayer_fee(CIRCUIT_CONFIG);
+    let paid = verifier.verify_with_relayer_fee(proof.clone()).unwrap();
+    assert_eq!(paid, relayer_fee);
+
+    // A relayer can't raise its fee after the fact.
+    let mut tampered = proof;
+    tampered.public_inputs[RELAYER_FEE_START_INDEX + 3] += F::ONE;
+    assert!(verifier.verify_with_relayer_fee(tampered).is_err());
+}
+
+#[test]
+fn evm_exit_account_is_tagged() {
+    let evm_address = [0x42; 20];
+    let mut inputs = CircuitInputs::test_inputs();
+    inputs.public.exit_account = ExitAccountFormat::evm_exit_account(evm_address);
+
+    let prover = WormholeProver::with_exit_account_format(CIRCUIT_CONFIG);
+    let proof = prover
+        .commit_with_exit_account_format(&inputs, ExitAccountFormat::Evm)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let verifier = WormholeVerifier::with_exit_account_format(CIRCUIT_CONFIG);
+    let format = verifier
+        .verify_with_exit_account_format(proof.clone())
+        .unwrap();
+    assert_eq!(format, ExitAccountFormat::Evm);
+    assert_eq!(
+        ExitAccountFormat::evm_address(&inputs.public.exit_account),
+        Some(evm_address)
+    );
+
+    // The format is bound to the proof.
+    let mut tampered = proof;
+    tampered.public_inputs[EXIT_ACCOUNT_FORMAT_INDEX] = F::ZERO;
+    assert!(verifier.verify_with_exit_account_format(tampered).is_err());
+
+    // Substrate accounts can't be claimed as EVM addresses.
+    let inputs = CircuitInputs::test_inputs();
+    let prover = WormholeProver::with_exit_account_format(CIRCUIT_CONFIG);
+    assert!(prover
+        .commit_with_exit_account_format(&inputs, ExitAccountFormat::Evm)
+        .is_err());
+}
+
+#[test]
+fn funding_commitment_opens_to_the_funding_account() {
+    let inputs = CircuitInputs::test_inputs();
+    let blinding = SecretBytes::new([7u8; 32]);
+
+    let prover = WormholeProver::with_funding_commitment(CIRCUIT_CONFIG);
+    let proof = prover
+        .commit_with_funding_commitment(&inputs, &blinding)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let verifier = WormholeVerifier::with_funding_commitment(CIRCUIT_CONFIG);
+    let commitment = verifier
+        .verify_with_funding_commitment(proof.clone())
+        .unwrap();
+
+    // Only the opening with the right funding account and blinding matches.
+    let funding_account = inputs.private.funding_account;
+    FundingAccountOpening::new(funding_account, blinding)
+        .verify(&commitment)
+        .unwrap();
+    let other_blinding = SecretBytes::new([8u8; 32]);
+    assert!(FundingAccountOpening::new(funding_account, other_blinding)
+        .verify(&commitment)
+        .is_err());
+    let other_account = inputs.public.exit_account;
+    assert!(FundingAccountOpening::new(other_account, blinding)
+        .verify(&commitment)
+        .is_err());
+
+    // The commitment is bound to the proof.
+    let mut tampered = proof;
+    tampered.public_inputs[FUNDING_COMMITMENT_START_INDEX] += F::ONE;
+    assert!(verifier.verify_with_funding_commitment(tampered).is_err());
+
+    // Proofs of circuits without the commitment don't have one.
+    let proof = WormholeProver::new(CIRCUIT_CONFIG)
+        .commit(&inputs)
+        .unwrap()
+        .prove()
+        .unwrap();
+    assert!(verifier.verify_with_funding_commitment(proof).is_err());
+
+    // The circuit needs a blinding.
+    let prover = WormholeProver::with_funding_commitment(CIRCUIT_CONFIG);
+    assert!(prover.commit(&inputs).is_err());
+}
+
+#[test]
+fn intent_hash_matches_the_registered_intent() {
+    let inputs = CircuitInputs::test_inputs();
+    let min_amount = inputs.public.funding_amount;
+
+    let prover = WormholeProver::with_intent_hash(CIRCUIT_CONFIG);
+    let proof = prover
+        .commit_with_intent(&inputs, min_amount)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let verifier = WormholeVerifier::with_intent_hash(CIRCUIT_CONFIG);
+    let intent_hash = verifier.verify_with_intent_hash(proof.clone()).unwrap();
+    let intent = ClaimIntent::new(
+        inputs.public.nullifier,
+        inputs.public.exit_account,
+        min_amount,
+    );
+    assert_eq!(intent_hash, intent.hash());
+
+    // The intent hash is bound to the proof.
+    let mut tampered = proof;
+    tampered.public_inputs[INTENT_HASH_START_INDEX] += F::ONE;
+    assert!(verifier.verify_with_intent_hash(tampered).is_err());
+
+    // Proofs of circuits without the intent hash don't have one.
+    let proof = WormholeProver::new(CIRCUIT_CONFIG)
+        .commit(&inputs)
+        .unwrap()
+        .prove()
+        .unwrap();
+    assert!(verifier.verify_with_intent_hash(proof).is_err());
+
+    // The circuit needs a minimum amount, which the funding amount must reach.
+    let prover = WormholeProver::with_intent_hash(CIRCUIT_CONFIG);
+    assert!(prover.commit(&inputs).is_err());
+    let prover = WormholeProver::with_intent_hash(CIRCUIT_CONFIG);
+    assert!(prover.commit_with_intent(&inputs, min_amount + 1).is_err());
+}
+
+#[test]
+fn spent_nullifiers_are_checked_in_the_proof() {
+    let inputs = CircuitInputs::test_inputs();
+    let nullifier = inputs.public.nullifier;
+    let mut accumulator = NullifierAccumulator::new(4).unwrap();
+    let non_membership = accumulator.prove_non_membership(&nullifier).unwrap();
+
+    let prover = WormholeProver::with_spent_nullifier_accumulator(CIRCUIT_CONFIG, 4).unwrap();
+    let proof = prover
+        .commit_with_spent_nullifier_proof(&inputs, &non_membership)
+        .unwrap()
+        .prove()
+        .unwrap();
+
+    let verifier = WormholeVerifier::with_spent_nullifier_accumulator(CIRCUIT_CONFIG, 4).unwrap();
+    verifier
+        .verify_against_spent_nullifiers(proof.clone(), &accumulator.root())
+        .unwrap();
+
+    // Once the nullifier is spent, the proof is stale and no new one can be made.
+    accumulator.insert_batch(&[nullifier]).unwrap();
+    let err = verifier
+        .verify_against_spent_nullifiers(proof, &accumulator.root())
+        .unwrap_err();
+    assert!(err.to_string().contains("different accumulator"));
+    assert!(accumulator.prove_non_membership(&nullifier).is_err());
+
+    // Nor can the stale proof be committed against the new root.
+    let mut replayed = non_membership;
+    replayed.root = accumulator.root();
+    let prover = WormholeProver::with_spent_nullifier_accumulator(CIRCUIT_CONFIG, 4).unwrap();
+    assert!(prover
+        .commit_with_spent_nullifier_proof(&inputs, &replayed)
+        .is_err());
+}
+
+#[test]
+fn public_inputs_digest_commits_to_public_inputs() {
+    let inputs = CircuitInputs::test_inputs();
+    let prover = WormholeProver::with_public_inputs_digest(CIRCUIT_CONFIG);
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    assert_eq!(proof.public_inputs, inputs.public.digest());
+
+    let verifier = WormholeVerifier::with_public_inputs_digest(CIRCUIT_CONFIG);
+    verifier
+        .verify_public_inputs_digest(proof.clone(), &inputs.public)
+        .unwrap();
+
+    // The digest binds every public input, so a different exit account is rejected.
+    let mut other = inputs.public.clone();
+    other.exit_account = AccountId::try_from([8u8; 32]).unwrap();
+    assert_ne!(other.digest(), inputs.public.digest());
+    let err = verifier
+        .verify_public_inputs_digest(proof.clone(), &other)
+        .unwrap_err();
+    assert!(err.to_string().contains("different public inputs"));
+
+    let mut tampered = proof;
+    tampered.public_inputs[0] += F::ONE;
+    assert!(verifier.verify(tampered).is_err());
+}
+
+#[test]
+fn relayer_fee_must_not_exceed_funding_amount() {
+    let inputs = CircuitInputs::test_inputs();
+    let funding_amount = inputs.public.funding_amount;
+    let relayer_account = AccountId::try_from([7u8; 32]).unwrap();
+    assert!(RelayerFee::new(relayer_account, funding_amount + 1, funding_amount).is_err());
+    RelayerFee::new(relayer_account, funding_amount, funding_amount).unwrap();
+
+    // Fees that don't add up to the funding amount are rejected before proving.
+    let relayer_fee = RelayerFee {
+        relayer_account,
+        fee: funding_amount,
+        exit_amount: 1,
+    };
+    let prover = WormholeProver::with_relayer_fee(CIRCUIT_CONFIG);
+    assert!(prover
+        .commit_with_relayer_fee(&inputs, &relayer_fee)
+        .is_err());
+}
+
+#[test]
+fn relayer_fee_must_match_circuit() {
+    let inputs = CircuitInputs::test_inputs();
+    let relayer_account = AccountId::try_from([7u8; 32]).unwrap();
+    let relayer_fee = RelayerFee::new(relayer_account, 0, inputs.public.funding_amount).unwrap();
+
+    let prover = WormholeProver::with_relayer_fee(CIRCUIT_CONFIG);
+    assert!(prover.commit(&inputs).is_err());
+
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    assert!(prover.commit_with_relayer_fee(&inputs, &relayer_fee).is_err());
+}
+
+#[test]
+fn verify_proof_with_keccak_config() {
+    let prover = WormholeProver::<KeccakGoldilocksConfig>::with_generic_config(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    let verifier =
+        WormholeVerifier::<KeccakGoldilocksConfig>::with_generic_config(CIRCUIT_CONFIG, None);
+    verifier.verify(proof).unwrap();
+}
+
+#[test]
+fn verifier_digest_matches_prover_digest() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    assert_eq!(prover.verifier_digest(), verifier.verifier_digest());
+}
+
+#[test]
+fn verify_enveloped_proof() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let envelope = prover.commit(&inputs).unwrap().prove_envelope().unwrap();
+
+    // Round trip through the wire format before verifying.
+    let envelope = ProofEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    verifier.verify_envelope(&envelope).unwrap();
+}
+
+#[test]
+fn envelope_for_other_circuit_version_is_rejected() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let envelope = prover.commit(&inputs).unwrap().prove_envelope().unwrap();
+
+    let verifier = WormholeVerifier::new(CircuitConfig::standard_recursion_zk_config(), None);
+    assert_ne!(envelope.verifier_digest, verifier.verifier_digest());
+
+    let result = verifier.verify_envelope(&envelope);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("proof was generated for verifier"));
+}
+
 #[test]
 fn cannot_verify_with_modified_exit_account() {
     let prover = WormholeProver::new(CIRCUIT_CONFIG);
@@ -65,6 +506,88 @@ fn cannot_verify_with_any_public_input_modification() {
     }
 }
 
+#[test]
+fn shared_verifier_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedVerifier>();
+}
+
+#[test]
+fn shared_verifier_caches_verified_proofs() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    let verifier = SharedVerifier::new(WormholeVerifier::new(CIRCUIT_CONFIG, None));
+    assert!(!verifier.is_cached(&proof));
+
+    verifier.verify(proof.clone()).unwrap();
+    assert!(verifier.is_cached(&proof));
+    verifier.verify(proof).unwrap();
+    assert_eq!(verifier.cache_len(), 1);
+}
+
+#[test]
+fn shared_verifier_does_not_cache_invalid_proofs() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let mut proof = prover.commit(&inputs).unwrap().prove().unwrap();
+    proof.public_inputs[EXIT_ACCOUNT_START_INDEX].0 ^= 1;
+
+    let verifier = SharedVerifier::new(WormholeVerifier::new(CIRCUIT_CONFIG, None));
+    assert!(verifier.verify(proof.clone()).is_err());
+    assert!(!verifier.is_cached(&proof));
+    assert!(verifier.verify(proof).is_err());
+    assert_eq!(verifier.cache_len(), 0);
+}
+
+#[test]
+fn shared_verifier_evicts_least_recently_used_proof() {
+    // Proofs with different exit accounts are distinct.
+    let proofs: Vec<_> = (0..3u8)
+        .map(|i| {
+            let mut inputs = CircuitInputs::test_inputs();
+            inputs.public.exit_account = AccountId::try_from([i; 32]).unwrap();
+            let prover = WormholeProver::new(CIRCUIT_CONFIG);
+            prover.commit(&inputs).unwrap().prove().unwrap()
+        })
+        .collect();
+
+    let verifier = SharedVerifier::with_capacity(WormholeVerifier::new(CIRCUIT_CONFIG, None), 2);
+    verifier.verify(proofs[0].clone()).unwrap();
+    verifier.verify(proofs[1].clone()).unwrap();
+    // Touch the first proof so the second one is the least recently used.
+    verifier.verify(proofs[0].clone()).unwrap();
+    verifier.verify(proofs[2].clone()).unwrap();
+
+    assert_eq!(verifier.cache_len(), 2);
+    assert!(verifier.is_cached(&proofs[0]));
+    assert!(!verifier.is_cached(&proofs[1]));
+    assert!(verifier.is_cached(&proofs[2]));
+}
+
+#[test]
+fn shared_verifier_verifies_from_multiple_threads() {
+    let prover = WormholeProver::new(CIRCUIT_CONFIG);
+    let inputs = CircuitInputs::test_inputs();
+    let proof = prover.commit(&inputs).unwrap().prove().unwrap();
+
+    let verifier = SharedVerifier::new(WormholeVerifier::new(CIRCUIT_CONFIG, None));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let verifier = verifier.clone();
+            let proof = proof.clone();
+            std::thread::spawn(move || verifier.verify(proof))
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap().unwrap();
+    }
+    assert!(verifier.is_cached(&proof));
+    assert_eq!(verifier.cache_len(), 1);
+}
+
 #[ignore]
 #[test]
 fn cannot_verify_with_modified_proof() {
@@ -89,3 +612,29 @@ fn cannot_verify_with_modified_proof() {
         }
     }
 }
+
+#[test]
+fn targets_layout_of_artifact_is_checked() {
+    let verifier = WormholeVerifier::new(CIRCUIT_CONFIG, None);
+    let layout = WormholeCircuit::new(CIRCUIT_CONFIG).targets().layout();
+    verifier.check_targets_layout(&layout).unwrap();
+
+    let path = std::env::temp_dir().join("qp-verifier-targets-layout.json");
+    std::fs::write(&path, serde_json::to_string(&layout).unwrap()).unwrap();
+    verifier.check_targets_layout_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut misaligned = layout;
+    misaligned.fragments[0].fields[0].targets.reverse();
+    let err = verifier.check_targets_layout(&misaligned).unwrap_err();
+    assert!(
+        err.to_string().contains("nullifier.hash: targets moved"),
+        "{}",
+        err
+    );
+
+    let with_context = WormholeCircuit::with_context(CIRCUIT_CONFIG)
+        .targets()
+        .layout();
+    assert!(verifier.check_targets_layout(&with_context).is_err());
+}
@@ -1,11 +1,14 @@
-use crate::storage_proof::{TestInputs, DEFAULT_ROOT_HASH};
+pub mod throughput;
+
+use crate::storage_proof::TestInputs;
 use wormhole_circuit::{
+    hex_util,
     inputs::{CircuitInputs, PrivateCircuitInputs, PublicCircuitInputs},
     nullifier::Nullifier,
     storage_proof::ProcessedStorageProof,
+    types::AccountId,
     unspendable_account::UnspendableAccount,
 };
-use zk_circuits_common::utils::BytesDigest;
 
 pub const DEFAULT_SECRET: &str = "4c8587bd422e01d961acdc75e7d66f6761b7af7c9b1864a492f369c9d6724f05";
 pub const DEFAULT_TRANSFER_COUNT: u64 = 4;
@@ -24,20 +27,16 @@ pub const DEFAULT_EXIT_ACCOUNT: [u8; 32] = [4u8; 32];
 
 impl TestInputs for CircuitInputs {
     fn test_inputs() -> Self {
-        let secret = hex::decode(DEFAULT_SECRET.trim()).unwrap();
-        let root_hash = hex::decode(DEFAULT_ROOT_HASH.trim())
-            .unwrap()
-            .as_slice()
-            .try_into()
-            .unwrap();
+        let secret: [u8; 32] =
+            hex_util::decode_array(DEFAULT_SECRET).expect("default secret is 32 byte hex");
+        let root_hash = storage_proof::default_root_hash();
 
-        let funding_account = BytesDigest::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap();
+        let funding_account = AccountId::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap();
         let nullifier = Nullifier::from_preimage(&secret, DEFAULT_TRANSFER_COUNT)
             .hash
             .into();
-        let secret: [u8; 32] = secret.try_into().expect("Expected 32 bytes for secret");
         let unspendable_account = UnspendableAccount::from_secret(&secret).account_id.into();
-        let exit_account = BytesDigest::try_from(DEFAULT_EXIT_ACCOUNT).unwrap();
+        let exit_account = AccountId::try_from(DEFAULT_EXIT_ACCOUNT).unwrap();
 
         let storage_proof = ProcessedStorageProof::test_inputs();
         Self {
@@ -48,7 +47,7 @@ impl TestInputs for CircuitInputs {
                 exit_account,
             },
             private: PrivateCircuitInputs {
-                secret,
+                secret: secret.into(),
                 storage_proof,
                 transfer_count: DEFAULT_TRANSFER_COUNT,
                 funding_account,
@@ -62,8 +61,9 @@ pub mod storage_proof {
     use crate::{
         DEFAULT_FUNDING_ACCOUNT, DEFAULT_FUNDING_AMOUNT, DEFAULT_TO_ACCOUNT, DEFAULT_TRANSFER_COUNT,
     };
+    use wormhole_circuit::hex_util;
     use wormhole_circuit::storage_proof::{leaf::LeafInputs, ProcessedStorageProof, StorageProof};
-    use zk_circuits_common::utils::BytesDigest;
+    use wormhole_circuit::types::{AccountId, RootHash};
 
     pub const DEFAULT_ROOT_HASH: &str =
         "5ffa2ab5b0db9883b22b1e5810932ea9d9eab1840730fd39ace71c26bb8d082d";
@@ -86,7 +86,7 @@ pub mod storage_proof {
     impl TestInputs for ProcessedStorageProof {
         fn test_inputs() -> Self {
             let proof = DEFAULT_STORAGE_PROOF
-                .map(|node| hex::decode(node).unwrap())
+                .map(|node| hex_util::decode(node).expect("default storage proof node is hex"))
                 .to_vec();
             let indices = DEFAULT_STORAGE_PROOF_INDICIES.to_vec();
             Self::new(proof, indices).unwrap()
@@ -95,8 +95,8 @@ pub mod storage_proof {
 
     impl TestInputs for LeafInputs {
         fn test_inputs() -> Self {
-            let funding_account = BytesDigest::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap();
-            let to_account = BytesDigest::try_from(DEFAULT_TO_ACCOUNT).unwrap();
+            let funding_account = AccountId::try_from(DEFAULT_FUNDING_ACCOUNT).unwrap();
+            let to_account = AccountId::try_from(DEFAULT_TO_ACCOUNT).unwrap();
             LeafInputs::new(
                 DEFAULT_TRANSFER_COUNT,
                 funding_account,
@@ -117,8 +117,12 @@ pub mod storage_proof {
         }
     }
 
-    pub fn default_root_hash() -> [u8; 32] {
-        hex::decode(DEFAULT_ROOT_HASH).unwrap().try_into().unwrap()
+    pub fn default_root_hash() -> RootHash {
+        hex_util::decode(DEFAULT_ROOT_HASH)
+            .expect("default root hash is hex")
+            .as_slice()
+            .try_into()
+            .unwrap()
     }
 }
 
@@ -126,6 +130,7 @@ pub mod nullifier {
     use crate::DEFAULT_TRANSFER_COUNT;
 
     use super::DEFAULT_SECRET;
+    use wormhole_circuit::hex_util;
     use wormhole_circuit::nullifier::Nullifier;
 
     pub trait TestInputs {
@@ -134,7 +139,7 @@ pub mod nullifier {
 
     impl TestInputs for Nullifier {
         fn test_inputs() -> Self {
-            let secret = hex::decode(DEFAULT_SECRET).unwrap();
+            let secret = hex_util::decode(DEFAULT_SECRET).expect("default secret is hex");
             Self::from_preimage(secret.as_slice(), DEFAULT_TRANSFER_COUNT)
         }
     }
@@ -0,0 +1,135 @@
+//! Shared setup of the throughput benchmarks of the prover and aggregator crates.
+//!
+//! The benchmarks run every proving configuration on rayon pools of [`THREAD_COUNTS`] threads
+//! and record the mean time of each run in a [`ThroughputSummary`], which is printed as JSON once
+//! the benchmarks are done, and written to the file at `$THROUGHPUT_SUMMARY` if it is set.
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use plonky2::plonk::circuit_data::CircuitConfig;
+
+/// The rayon thread counts every configuration is benchmarked with.
+pub const THREAD_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+/// The environment variable holding the path the summary is written to.
+pub const THROUGHPUT_SUMMARY_ENV: &str = "THROUGHPUT_SUMMARY";
+
+/// The circuit config of a benchmark run, with or without zero knowledge.
+pub fn circuit_config(zk: bool) -> CircuitConfig {
+    if zk {
+        CircuitConfig::standard_recursion_zk_config()
+    } else {
+        CircuitConfig::standard_recursion_config()
+    }
+}
+
+/// Builds a rayon pool with `threads` threads, which the benchmarked work is installed on.
+pub fn thread_pool(threads: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to build rayon thread pool")
+}
+
+/// The measurements of one benchmarked configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThroughputEntry {
+    pub benchmark: String,
+    pub zk: bool,
+    pub threads: usize,
+    /// The number of proofs one iteration produces, e.g. the leaves of an aggregation tree.
+    pub proofs_per_iter: usize,
+    pub iters: u64,
+    pub total: Duration,
+}
+
+impl ThroughputEntry {
+    pub fn mean(&self) -> Duration {
+        self.total / self.iters.max(1) as u32
+    }
+
+    /// The number of proofs per second, counting [`Self::proofs_per_iter`] proofs per iteration.
+    pub fn proofs_per_second(&self) -> f64 {
+        if self.total.is_zero() {
+            return 0.0;
+        }
+        (self.iters as f64 * self.proofs_per_iter as f64) / self.total.as_secs_f64()
+    }
+}
+
+/// Collects the [`ThroughputEntry`] of every benchmarked configuration.
+#[derive(Debug, Default)]
+pub struct ThroughputSummary {
+    entries: Vec<ThroughputEntry>,
+}
+
+impl ThroughputSummary {
+    /// Adds `iters` iterations taking `elapsed` to the entry of the configuration, creating it if
+    /// it doesn't exist yet. Criterion measures a benchmark in several rounds, so the same
+    /// configuration is recorded more than once.
+    pub fn record(
+        &mut self,
+        benchmark: &str,
+        zk: bool,
+        threads: usize,
+        proofs_per_iter: usize,
+        iters: u64,
+        elapsed: Duration,
+    ) {
+        let existing = self.entries.iter_mut().find(|entry| {
+            entry.benchmark == benchmark && entry.zk == zk && entry.threads == threads
+        });
+        match existing {
+            Some(entry) => {
+                entry.iters += iters;
+                entry.total += elapsed;
+            }
+            None => self.entries.push(ThroughputEntry {
+                benchmark: benchmark.to_string(),
+                zk,
+                threads,
+                proofs_per_iter,
+                iters,
+                total: elapsed,
+            }),
+        }
+    }
+
+    pub fn entries(&self) -> &[ThroughputEntry] {
+        &self.entries
+    }
+
+    /// Encodes the summary as a JSON array with one object per configuration.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[\n");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let separator = if i + 1 == self.entries.len() { "" } else { "," };
+            writeln!(
+                json,
+                "  {{\"benchmark\": \"{}\", \"zk\": {}, \"threads\": {}, \"iters\": {}, \
+                 \"mean_ms\": {:.3}, \"proofs_per_second\": {:.3}}}{}",
+                entry.benchmark,
+                entry.zk,
+                entry.threads,
+                entry.iters,
+                entry.mean().as_secs_f64() * 1000.0,
+                entry.proofs_per_second(),
+                separator
+            )
+            .expect("writing to a string can't fail");
+        }
+        json.push(']');
+        json
+    }
+
+    /// Prints the summary and writes it to `$THROUGHPUT_SUMMARY`, if it is set.
+    pub fn emit(&self) -> std::io::Result<()> {
+        let json = self.to_json();
+        println!("{}", json);
+        if let Ok(path) = std::env::var(THROUGHPUT_SUMMARY_ENV) {
+            std::fs::write(&path, json)?;
+            println!("throughput summary written to {}", path);
+        }
+        Ok(())
+    }
+}
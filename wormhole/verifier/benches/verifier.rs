@@ -1,28 +1,36 @@
-use std::fs;
 use std::time::Duration;
 
-use criterion::{criterion_group, criterion_main, Criterion};
-use plonky2::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierCircuitData};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use plonky2::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
 use plonky2::plonk::proof::ProofWithPublicInputs;
-use plonky2::util::serialization::DefaultGateSerializer;
+use qp_wormhole_prover::WormholeProver;
 use qp_wormhole_verifier::WormholeVerifier;
+use test_helpers::storage_proof::TestInputs;
+use wormhole_circuit::envelope::format::EnvelopeFormat;
+use wormhole_circuit::envelope::{circuit_digest_to_bytes, ProofEnvelope};
+use wormhole_circuit::inputs::CircuitInputs;
+use zk_circuits_common::circuit::{C, D, F};
 
 const MEASUREMENT_TIME_S: u64 = 20;
-const DATA_PATH: &str = "../bench-data";
+
+/// The verifier data of the zero knowledge wormhole circuit and a proof of the test inputs. Both
+/// are built from the source, so they always match the current circuit.
+fn test_proof() -> (VerifierCircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>) {
+    let config = CircuitConfig::standard_recursion_zk_config();
+    let prover = WormholeProver::new(config.clone());
+    let proof = prover
+        .commit(&CircuitInputs::test_inputs())
+        .unwrap()
+        .prove()
+        .unwrap();
+    let verifier = WormholeVerifier::new(config, None);
+    (verifier.circuit_data, proof)
+}
 
 fn verify_proof_benchmark(c: &mut Criterion) {
     let config = CircuitConfig::standard_recursion_zk_config();
     c.bench_function("verifier_verify_proof", |b| {
-        let common_data = fs::read(format!("{DATA_PATH}/common.bin")).unwrap();
-        let common_circuit_data =
-            CommonCircuitData::from_bytes(common_data, &DefaultGateSerializer).unwrap();
-        let proof_data = fs::read(format!("{DATA_PATH}/proof.bin")).unwrap();
-        let proof = ProofWithPublicInputs::from_bytes(proof_data, &common_circuit_data).unwrap();
-
-        let verifier_circuit_data_bytes = fs::read(format!("{DATA_PATH}/verifier.bin")).unwrap();
-        let verifier_circuit_data =
-            VerifierCircuitData::from_bytes(verifier_circuit_data_bytes, &DefaultGateSerializer)
-                .unwrap();
+        let (verifier_circuit_data, proof) = test_proof();
 
         b.iter(|| {
             let verifier =
@@ -32,11 +40,42 @@ fn verify_proof_benchmark(c: &mut Criterion) {
     });
 }
 
+fn envelope_formats_benchmark(c: &mut Criterion) {
+    let (verifier_circuit_data, proof) = test_proof();
+    let envelope = ProofEnvelope {
+        verifier_digest: circuit_digest_to_bytes(
+            verifier_circuit_data.verifier_only.circuit_digest,
+        ),
+        proof: proof.to_bytes(),
+    };
+
+    let mut group = c.benchmark_group("envelope_formats");
+    group.throughput(Throughput::Bytes(envelope.proof.len() as u64));
+    for &format in EnvelopeFormat::ALL {
+        let bytes = format.encode(&envelope).unwrap();
+        // Report the encoded size next to the timings.
+        println!(
+            "{} envelope: {} bytes for a {} byte proof",
+            format.name(),
+            bytes.len(),
+            envelope.proof.len()
+        );
+
+        group.bench_function(format!("encode_{}", format.name()), |b| {
+            b.iter(|| format.encode(&envelope).unwrap())
+        });
+        group.bench_function(format!("decode_{}", format.name()), |b| {
+            b.iter(|| EnvelopeFormat::decode(&bytes).unwrap())
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default()
         .measurement_time(Duration::from_secs(MEASUREMENT_TIME_S))
         .sample_size(10);
-    targets = verify_proof_benchmark
+    targets = verify_proof_benchmark, envelope_formats_benchmark
 );
 criterion_main!(benches);
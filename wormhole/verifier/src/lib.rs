@@ -27,7 +27,7 @@
 //! // Create inputs. In practice, each input would be gathered from the real node.
 //! let inputs = CircuitInputs {
 //!     private: PrivateCircuitInputs {
-//!         secret: [1u8; 32],
+//!         secret: [1u8; 32].into(),
 //!         transfer_count: 0,
 //!         funding_account: [2u8; 32].try_into().unwrap(),
 //!         storage_proof: ProcessedStorageProof::new(vec![], vec![]).unwrap(),
@@ -62,36 +62,140 @@
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 #[cfg(feature = "std")]
 use std::path::Path;
+use wormhole_circuit::envelope::{circuit_digest_to_bytes, ProofEnvelope};
+use wormhole_circuit::exit_format::ExitAccountFormat;
+use wormhole_circuit::funding_commitment::FundingAccountOpening;
+use wormhole_circuit::inputs::{
+    PublicCircuitInputs, CONTEXT_END_INDEX, CONTEXT_START_INDEX, DESTINATION_CHAIN_ID_END_INDEX,
+    DESTINATION_CHAIN_ID_START_INDEX, PUBLIC_INPUTS_DIGEST_END_INDEX,
+    SPENT_NULLIFIERS_ROOT_END_INDEX, SPENT_NULLIFIERS_ROOT_START_INDEX,
+};
+use wormhole_circuit::intent::ClaimIntent;
+use wormhole_circuit::relayer::RelayerFee;
+use wormhole_circuit::types::{ChainId, ProofContext};
 use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::{BytesDigest, Digest};
+
+use crate::prepared::{verify_prepared, PreparedProof};
 
 #[cfg(feature = "std")]
 use plonky2::plonk::circuit_data::CircuitConfig;
 #[cfg(feature = "std")]
 use wormhole_circuit::circuit::circuit_logic::WormholeCircuit;
+#[cfg(feature = "std")]
+use wormhole_circuit::layout::{TargetsLayout, TARGETS_LAYOUT_FILE_NAME};
 
 use plonky2::plonk::circuit_data::{
     CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
 };
+use plonky2::plonk::config::GenericConfig;
 pub use plonky2::plonk::proof::ProofWithPublicInputs;
 use plonky2::util::serialization::DefaultGateSerializer;
 
-pub struct WormholeVerifier {
-    pub circuit_data: VerifierCircuitData<F, C, D>,
+pub mod prelude;
+pub mod prepared;
+pub mod recursion;
+#[cfg(feature = "std")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod shared;
+
+/// Verifies wormhole proofs generated with the proving config `Cfg`.
+///
+/// `Cfg` defaults to the Poseidon Goldilocks config the published circuit binaries are built
+/// with. Verifiers for other configs are created with [`WormholeVerifier::with_generic_config`].
+pub struct WormholeVerifier<Cfg: GenericConfig<D, F = F> = C> {
+    pub circuit_data: VerifierCircuitData<F, Cfg, D>,
 }
 
 impl WormholeVerifier {
     #[cfg(feature = "std")]
     pub fn new(config: CircuitConfig, circuit_data: Option<VerifierCircuitData<F, C, D>>) -> Self {
-        let wormhole_circuit = WormholeCircuit::new(config);
-        let circuit_data = match circuit_data {
-            Some(circuit_data) => circuit_data,
-            None => wormhole_circuit.build_verifier(),
-        };
+        Self::with_generic_config(config, circuit_data)
+    }
 
-        Self { circuit_data }
+    /// Creates a [`WormholeVerifier`] for the circuit binding proofs to a [`ProofContext`]. See
+    /// [`WormholeCircuit::with_context`].
+    #[cfg(feature = "std")]
+    pub fn with_context(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_context(config).build_verifier(),
+        }
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit binding deposits to a destination
+    /// [`ChainId`]. See [`WormholeCircuit::with_destination_chain`].
+    #[cfg(feature = "std")]
+    pub fn with_destination_chain(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_destination_chain(config).build_verifier(),
+        }
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit paying a [`RelayerFee`] out of the funding
+    /// amount. See [`WormholeCircuit::with_relayer_fee`].
+    #[cfg(feature = "std")]
+    pub fn with_relayer_fee(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_relayer_fee(config).build_verifier(),
+        }
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit accepting EVM addresses as exit accounts.
+    /// See [`WormholeCircuit::with_exit_account_format`].
+    #[cfg(feature = "std")]
+    pub fn with_exit_account_format(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_exit_account_format(config).build_verifier(),
+        }
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit proving that the nullifier is not in an
+    /// accumulator of spent nullifiers of depth `depth`. See
+    /// [`WormholeCircuit::with_spent_nullifier_accumulator`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `depth` is not a supported accumulator depth.
+    #[cfg(feature = "std")]
+    pub fn with_spent_nullifier_accumulator(
+        config: CircuitConfig,
+        depth: usize,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            circuit_data: WormholeCircuit::with_spent_nullifier_accumulator(config, depth)?
+                .build_verifier(),
+        })
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit committing to the funding account. See
+    /// [`WormholeCircuit::with_funding_commitment`].
+    #[cfg(feature = "std")]
+    pub fn with_funding_commitment(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_funding_commitment(config).build_verifier(),
+        }
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit exposing a claim intent hash. See
+    /// [`WormholeCircuit::with_intent_hash`].
+    #[cfg(feature = "std")]
+    pub fn with_intent_hash(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_intent_hash(config).build_verifier(),
+        }
+    }
+
+    /// Creates a [`WormholeVerifier`] for the circuit exposing only the digest of its public
+    /// inputs. See [`WormholeCircuit::with_public_inputs_digest`].
+    #[cfg(feature = "std")]
+    pub fn with_public_inputs_digest(config: CircuitConfig) -> Self {
+        Self {
+            circuit_data: WormholeCircuit::with_public_inputs_digest(config).build_verifier(),
+        }
     }
 
     /// Creates a new [`WormholeVerifier`] from verifier and common data bytes.
@@ -147,14 +251,316 @@ impl WormholeVerifier {
         Ok(Self { circuit_data })
     }
 
+    /// Returns the digest of the verifier data, identifying the exact circuit version this
+    /// verifier accepts proofs for.
+    pub fn verifier_digest(&self) -> BytesDigest {
+        circuit_digest_to_bytes(self.circuit_data.verifier_only.circuit_digest)
+    }
+
+    /// Verify a [`ProofEnvelope`].
+    ///
+    /// The envelope's verifier digest is compared against [`Self::verifier_digest`] before the
+    /// proof is deserialized, so proofs generated for another circuit version are rejected cheaply.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the digests differ or the proof is not valid.
+    pub fn verify_envelope(&self, envelope: &ProofEnvelope) -> anyhow::Result<()> {
+        let expected = self.verifier_digest();
+        if envelope.verifier_digest != expected {
+            tracing::debug!(
+                verifier_digest = %hex::encode(*envelope.verifier_digest),
+                "rejected proof envelope for another verifier"
+            );
+            bail!(
+                "proof was generated for verifier {}, expected {}",
+                hex::encode(*envelope.verifier_digest),
+                hex::encode(*expected)
+            );
+        }
+
+        let proof = envelope.proof(&self.circuit_data.common)?;
+        self.verify(proof)
+    }
+
+    /// Checks that `layout`, the targets layout the circuit builder writes next to the verifier
+    /// data, matches the layout of the wormhole circuit this crate is compiled against, built
+    /// with the config of the verifier data. See [`wormhole_circuit::layout`].
+    ///
+    /// Artifacts of circuits with optional features, e.g. [`WormholeCircuit::with_context`], are
+    /// compared against the layout of their circuit with [`TargetsLayout::diff`] instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the differences if the layouts don't match, or if the circuit
+    /// can't be built with the config of the verifier data.
+    #[cfg(feature = "std")]
+    pub fn check_targets_layout(&self, layout: &TargetsLayout) -> anyhow::Result<()> {
+        let config = self.circuit_data.common.config.clone();
+        let expected = WormholeCircuit::try_new(config)?.targets().layout();
+        let diff = layout.diff(&expected);
+        if !diff.is_empty() {
+            bail!(
+                "the targets of the artifact don't line up with this version of the circuit: {}",
+                diff.join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::check_targets_layout`], but reads the layout from the
+    /// [`TARGETS_LAYOUT_FILE_NAME`] written by the circuit builder.
+    #[cfg(feature = "std")]
+    pub fn check_targets_layout_file(&self, layout_path: &Path) -> anyhow::Result<()> {
+        let json = std::fs::read_to_string(layout_path)?;
+        let layout: TargetsLayout = serde_json::from_str(&json).map_err(|e| {
+            anyhow!(
+                "Failed to deserialize targets layout from {:?}: {}",
+                layout_path,
+                e
+            )
+        })?;
+        self.check_targets_layout(&layout)
+    }
+}
+
+impl<Cfg: GenericConfig<D, F = F>> WormholeVerifier<Cfg> {
+    /// Creates a new [`WormholeVerifier`] for the proving config `Cfg`, building the circuit if
+    /// no verifier data is provided.
+    #[cfg(feature = "std")]
+    pub fn with_generic_config(
+        config: CircuitConfig,
+        circuit_data: Option<VerifierCircuitData<F, Cfg, D>>,
+    ) -> Self {
+        let circuit_data = match circuit_data {
+            Some(circuit_data) => circuit_data,
+            None => WormholeCircuit::<Cfg>::with_generic_config(config).build_verifier(),
+        };
+
+        Self { circuit_data }
+    }
+
     /// Verify a [`ProofWithPublicInputs`].
     ///
     /// # Errors
     ///
     /// Returns an error if the proof is not valid.
-    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
-        self.circuit_data
-            .verify(proof)
-            .map_err(|e| anyhow!("proof verification failed: {}", e))
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, Cfg, D>) -> anyhow::Result<()> {
+        let _span = tracing::info_span!("wormhole_verify").entered();
+        self.circuit_data.verify(proof).map_err(|e| {
+            tracing::debug!(error = %e, "proof verification failed");
+            anyhow!("proof verification failed: {}", e)
+        })
+    }
+
+    /// Deserializes `proof_bytes`, verifies the proof and decodes its [`PublicCircuitInputs`],
+    /// which is what a pallet needs to apply a claim in one call.
+    ///
+    /// Only proofs of the default circuit layout are decoded. Proofs of circuits with optional
+    /// public inputs must be verified with the matching `verify_*` method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a proof of this verifier's circuit, the proof has
+    /// optional public inputs, or it is not valid.
+    pub fn verify_and_decode(&self, proof_bytes: &[u8]) -> anyhow::Result<PublicCircuitInputs> {
+        let common = &self.circuit_data.common;
+        let proof = ProofWithPublicInputs::<F, Cfg, D>::from_bytes(proof_bytes.to_vec(), common)
+            .map_err(|e| anyhow!("failed to deserialize proof: {}", e))?;
+        let public_inputs = PublicCircuitInputs::try_from_slice(&proof.public_inputs)?;
+        self.verify(proof)?;
+        Ok(public_inputs)
+    }
+
+    /// Deserializes and hashes `proof_bytes` for [`Self::verify_prepared`]. See
+    /// [`PreparedProof`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a proof of this verifier's circuit.
+    pub fn prepare(&self, proof_bytes: &[u8]) -> anyhow::Result<PreparedProof<Cfg>> {
+        PreparedProof::prepare(proof_bytes, &self.circuit_data.common)
+    }
+
+    /// Verify a proof returned by [`Self::prepare`]. Unlike [`Self::verify`], the proof is not
+    /// consumed, so it can be verified again, e.g. once in the transaction pool and once on block
+    /// import, without deserializing it twice.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is not valid.
+    pub fn verify_prepared(&self, prepared: &PreparedProof<Cfg>) -> anyhow::Result<()> {
+        let _span = tracing::info_span!("wormhole_verify").entered();
+        verify_prepared(prepared, &self.circuit_data).inspect_err(|e| {
+            tracing::debug!(error = %e, "proof verification failed");
+        })
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] and check that it was generated for `context`.
+    ///
+    /// [`Self::verify`] accepts proofs of any context, so verifiers of circuits built with
+    /// [`ProofContext`] support must use this instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is not bound to `context` or is not valid.
+    pub fn verify_in_context(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+        context: &ProofContext,
+    ) -> anyhow::Result<()> {
+        let Some(proof_context) = proof
+            .public_inputs
+            .get(CONTEXT_START_INDEX..CONTEXT_END_INDEX)
+        else {
+            bail!("proof is not bound to a context");
+        };
+        if proof_context != context.to_felts() {
+            bail!("proof was generated for a different context");
+        }
+
+        self.verify(proof)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] and check that it claims a deposit made for
+    /// `chain_id`.
+    ///
+    /// Each chain of a multi-chain deployment must verify with its own id, otherwise a proof
+    /// claiming a deposit on one chain could be replayed on another.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is not bound to `chain_id` or is not valid.
+    pub fn verify_for_chain(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+        chain_id: &ChainId,
+    ) -> anyhow::Result<()> {
+        if proof.public_inputs.len() != DESTINATION_CHAIN_ID_END_INDEX {
+            bail!("proof is not bound to a destination chain");
+        }
+        let proof_chain_id =
+            &proof.public_inputs[DESTINATION_CHAIN_ID_START_INDEX..DESTINATION_CHAIN_ID_END_INDEX];
+        if proof_chain_id != chain_id.to_felts() {
+            bail!("proof was generated for a different destination chain");
+        }
+
+        self.verify(proof)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] of a circuit checking for double spends, and check
+    /// that its nullifier is missing from the accumulator of spent nullifiers with root
+    /// `spent_nullifiers_root`.
+    ///
+    /// Chains must pass their current accumulator root. A proof against an older root only shows
+    /// the nullifier was unspent at the time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is not checked against `spent_nullifiers_root` or is not
+    /// valid.
+    pub fn verify_against_spent_nullifiers(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+        spent_nullifiers_root: &Digest,
+    ) -> anyhow::Result<()> {
+        if proof.public_inputs.len() != SPENT_NULLIFIERS_ROOT_END_INDEX {
+            bail!("proof is not checked against an accumulator of spent nullifiers");
+        }
+        let root = &proof.public_inputs
+            [SPENT_NULLIFIERS_ROOT_START_INDEX..SPENT_NULLIFIERS_ROOT_END_INDEX];
+        if root != spent_nullifiers_root {
+            bail!("proof was generated against a different accumulator of spent nullifiers");
+        }
+
+        self.verify(proof)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] of a circuit paying a relayer fee, and return the
+    /// [`RelayerFee`] to pay out.
+    ///
+    /// The fee is paid to the relayer account and the exit amount to the exit account. Their sum
+    /// is the funding amount, so paying both never pays out more than was deposited.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof does not pay a relayer fee or is not valid.
+    pub fn verify_with_relayer_fee(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+    ) -> anyhow::Result<RelayerFee> {
+        let relayer_fee = RelayerFee::try_from_public_inputs(&proof.public_inputs)?;
+        self.verify(proof)?;
+        Ok(relayer_fee)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] of a circuit accepting EVM exit accounts, and return
+    /// the format of its exit account. EVM addresses are recovered with
+    /// [`ExitAccountFormat::evm_address`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no exit account format or is not valid.
+    pub fn verify_with_exit_account_format(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+    ) -> anyhow::Result<ExitAccountFormat> {
+        let format = ExitAccountFormat::try_from_public_inputs(&proof.public_inputs)?;
+        self.verify(proof)?;
+        Ok(format)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] of a circuit committing to the funding account, and
+    /// return the commitment. Keep it with the claim, so an opening revealed in a dispute can be
+    /// checked with [`FundingAccountOpening::verify`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no funding commitment or is not valid.
+    pub fn verify_with_funding_commitment(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+    ) -> anyhow::Result<Digest> {
+        let commitment =
+            FundingAccountOpening::commitment_from_public_inputs(&proof.public_inputs)?;
+        self.verify(proof)?;
+        Ok(commitment)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] of a circuit exposing a claim intent hash, and return
+    /// the intent hash. The claim must only be accepted if this hash was registered on chain
+    /// before the proof was submitted, see [`ClaimIntent`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof has no intent hash or is not valid.
+    pub fn verify_with_intent_hash(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+    ) -> anyhow::Result<Digest> {
+        let intent_hash = ClaimIntent::hash_from_public_inputs(&proof.public_inputs)?;
+        self.verify(proof)?;
+        Ok(intent_hash)
+    }
+
+    /// Verify a [`ProofWithPublicInputs`] of a circuit exposing only the digest of its public
+    /// inputs, and check that it commits to `public_inputs`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof does not commit to `public_inputs` or is not valid.
+    pub fn verify_public_inputs_digest(
+        &self,
+        proof: ProofWithPublicInputs<F, Cfg, D>,
+        public_inputs: &PublicCircuitInputs,
+    ) -> anyhow::Result<()> {
+        if proof.public_inputs.len() != PUBLIC_INPUTS_DIGEST_END_INDEX {
+            bail!("proof does not expose a public inputs digest");
+        }
+        if proof.public_inputs != public_inputs.digest() {
+            bail!("proof commits to different public inputs");
+        }
+
+        self.verify(proof)
     }
 }
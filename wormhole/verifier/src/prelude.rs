@@ -0,0 +1,8 @@
+//! Everything needed to verify wormhole proofs, including the plonky2 types of
+//! [`zk_circuits_common::prelude`].
+pub use wormhole_circuit::inputs::PublicCircuitInputs;
+pub use zk_circuits_common::prelude::*;
+
+pub use crate::prepared::PreparedProof;
+pub use crate::recursion::{add_wormhole_proof_verification, WormholeProofTargets};
+pub use crate::WormholeVerifier;
@@ -0,0 +1,75 @@
+//! Proofs prepared for repeated verification.
+//!
+//! Runtimes verifying through host functions often see the same proof several times, e.g. once
+//! when it enters the transaction pool and again on block import. [`PreparedProof::prepare`] does
+//! the work that doesn't depend on the verifier data once: it deserializes the proof and hashes
+//! its bytes into a key for caching verification results. [`verify_prepared`] then only runs the
+//! verification itself.
+use anyhow::anyhow;
+use plonky2::{
+    hash::poseidon::PoseidonHash,
+    plonk::{
+        circuit_data::{CommonCircuitData, VerifierCircuitData},
+        config::{GenericConfig, Hasher},
+        proof::ProofWithPublicInputs,
+    },
+};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::{injective_bytes_to_felts, Digest};
+
+/// A deserialized proof together with the hash of its serialization.
+#[derive(Debug, Clone)]
+pub struct PreparedProof<Cfg: GenericConfig<D, F = F> = C> {
+    proof: ProofWithPublicInputs<F, Cfg, D>,
+    hash: Digest,
+}
+
+impl<Cfg: GenericConfig<D, F = F>> PreparedProof<Cfg> {
+    /// Deserializes `proof_bytes` with the common data of the circuit it was generated for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a proof of a circuit with this common data.
+    pub fn prepare(proof_bytes: &[u8], common: &CommonCircuitData<F, D>) -> anyhow::Result<Self> {
+        let proof = ProofWithPublicInputs::from_bytes(proof_bytes.to_vec(), common)
+            .map_err(|e| anyhow!("failed to deserialize proof: {}", e))?;
+
+        Ok(Self {
+            proof,
+            hash: proof_bytes_hash(proof_bytes),
+        })
+    }
+
+    /// The Poseidon hash of the serialized proof. Two prepared proofs have the same hash only if
+    /// they were prepared from the same bytes, so it can key a cache of verification results.
+    pub fn hash(&self) -> Digest {
+        self.hash
+    }
+
+    pub fn proof(&self) -> &ProofWithPublicInputs<F, Cfg, D> {
+        &self.proof
+    }
+
+    pub fn into_proof(self) -> ProofWithPublicInputs<F, Cfg, D> {
+        self.proof
+    }
+}
+
+/// Verifies a [`PreparedProof`] against `verifier_data`.
+///
+/// # Errors
+///
+/// Returns an error if the proof is not valid.
+pub fn verify_prepared<Cfg: GenericConfig<D, F = F>>(
+    prepared: &PreparedProof<Cfg>,
+    verifier_data: &VerifierCircuitData<F, Cfg, D>,
+) -> anyhow::Result<()> {
+    verifier_data
+        .verify(prepared.proof.clone())
+        .map_err(|e| anyhow!("proof verification failed: {}", e))
+}
+
+/// Hashes a serialized proof. Used as the cache key of verified proofs.
+pub(crate) fn proof_bytes_hash(proof_bytes: &[u8]) -> Digest {
+    PoseidonHash::hash_no_pad(&injective_bytes_to_felts(proof_bytes)).elements
+}
@@ -0,0 +1,96 @@
+//! Verifying wormhole proofs inside other plonky2 circuits.
+//!
+//! Circuits consuming wormhole exits, e.g. a rollup circuit crediting them, verify the leaf proofs
+//! recursively instead of trusting a verifier outside the circuit.
+//! [`add_wormhole_proof_verification`] adds the targets of one wormhole proof to such a circuit
+//! and verifies it against the wormhole verifier data. The verifier data are fixed as constants,
+//! since with the verifier data as a witness the prover could substitute any circuit with the
+//! same common data. The public inputs of the proof are exposed in the layout of
+//! [`wormhole_circuit::inputs`] for the outer circuit to constrain.
+//!
+//! The verifier data are the [`WormholeVerifier::circuit_data`](crate::WormholeVerifier), e.g.
+//! loaded from the published `verifier.bin` and `common.bin` with
+//! [`WormholeVerifier::new_from_bytes`](crate::WormholeVerifier::new_from_bytes).
+use core::array;
+
+use plonky2::{
+    hash::hash_types::HashOutTarget,
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::VerifierCircuitData,
+        proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget},
+    },
+};
+use wormhole_circuit::inputs::{
+    EXIT_ACCOUNT_START_INDEX, FUNDING_AMOUNT_START_INDEX, NULLIFIER_START_INDEX,
+    PUBLIC_INPUTS_FELTS_LEN, ROOT_HASH_START_INDEX,
+};
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::FELTS_PER_U128;
+
+/// The targets of a wormhole proof verified in another circuit.
+#[derive(Debug, Clone)]
+pub struct WormholeProofTargets {
+    /// The proof with all of its public inputs, including those of optional features such as
+    /// the relayer fee.
+    pub proof: ProofWithPublicInputsTarget<D>,
+    pub nullifier: HashOutTarget,
+    pub root_hash: HashOutTarget,
+    pub funding_amount: [Target; FELTS_PER_U128],
+    pub exit_account: HashOutTarget,
+}
+
+impl WormholeProofTargets {
+    /// Fills the targets with `proof`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `proof` doesn't have the shape of the verified circuit.
+    pub fn set_proof(
+        &self,
+        pw: &mut PartialWitness<F>,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> anyhow::Result<()> {
+        pw.set_proof_with_pis_target(&self.proof, proof)
+    }
+}
+
+/// Adds a wormhole proof to `builder` and verifies it against `verifier_data`, the verifier data
+/// of the wormhole circuit or one of its variants.
+///
+/// # Panics
+///
+/// Panics if the circuit of `verifier_data` has fewer public inputs than the wormhole layout, e.g.
+/// because it only exposes the public inputs digest.
+pub fn add_wormhole_proof_verification(
+    builder: &mut CircuitBuilder<F, D>,
+    verifier_data: &VerifierCircuitData<F, C, D>,
+) -> WormholeProofTargets {
+    let common = &verifier_data.common;
+    assert!(
+        common.num_public_inputs >= PUBLIC_INPUTS_FELTS_LEN,
+        "verified circuit has {} public inputs, the wormhole layout has {}",
+        common.num_public_inputs,
+        PUBLIC_INPUTS_FELTS_LEN
+    );
+
+    let proof = builder.add_virtual_proof_with_pis(common);
+    let verifier = builder.constant_verifier_data(&verifier_data.verifier_only);
+    builder.verify_proof::<C>(&proof, &verifier, common);
+
+    let public_inputs = &proof.public_inputs;
+    let hash = |start: usize| HashOutTarget {
+        elements: array::from_fn(|i| public_inputs[start + i]),
+    };
+    WormholeProofTargets {
+        nullifier: hash(NULLIFIER_START_INDEX),
+        root_hash: hash(ROOT_HASH_START_INDEX),
+        funding_amount: array::from_fn(|i| public_inputs[FUNDING_AMOUNT_START_INDEX + i]),
+        exit_account: hash(EXIT_ACCOUNT_START_INDEX),
+        proof,
+    }
+}
@@ -0,0 +1,132 @@
+//! Verifiers for several circuit versions at once.
+//!
+//! During a rollout, clients on the current and previous releases submit proofs side by side.
+//! A [`VerifierRegistry`] holds the verifier data of every accepted release, keyed by its circuit
+//! digest, and routes each [`ProofEnvelope`] to the verifier it was generated for.
+//!
+//! ## Directory Layout
+//!
+//! [`VerifierRegistry::load_dir`] loads every subdirectory of a registry directory holding the
+//! `verifier.bin` and `common.bin` of one circuit version:
+//!
+//! ```text
+//! registry/
+//!   3f2a...9c/
+//!     common.bin
+//!     verifier.bin
+//!   81d0...4e/
+//!     common.bin
+//!     verifier.bin
+//! ```
+//!
+//! The circuit builder writes entries named after their circuit digest, but the names are not
+//! interpreted, so the artifact directories of past releases can be copied in as they are.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use wormhole_circuit::envelope::ProofEnvelope;
+use zk_circuits_common::utils::BytesDigest;
+
+use crate::WormholeVerifier;
+
+/// Verifiers of several circuit versions, keyed by the digest of their verifier data.
+#[derive(Default)]
+pub struct VerifierRegistry {
+    verifiers: BTreeMap<[u8; 32], WormholeVerifier>,
+}
+
+impl VerifierRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every circuit version in `dir`, see the [module documentation](self) for the
+    /// layout. Entries that are not directories are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a subdirectory doesn't hold valid verifier data, or two of them hold
+    /// the same circuit version.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let mut registry = Self::new();
+
+        let mut entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read registry directory {:?}", dir))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Sort, so that errors about duplicates name the same directory on every platform.
+        entries.sort();
+
+        for path in entries.into_iter().filter(|path| path.is_dir()) {
+            let verifier = WormholeVerifier::new_from_files(
+                &path.join("verifier.bin"),
+                &path.join("common.bin"),
+            )
+            .with_context(|| format!("Failed to load verifier from {:?}", path))?;
+
+            let digest = verifier.verifier_digest();
+            if registry.insert(verifier).is_some() {
+                bail!(
+                    "{:?} holds verifier {}, which was already loaded",
+                    path,
+                    hex::encode(*digest)
+                );
+            }
+            tracing::debug!(
+                verifier_digest = %hex::encode(*digest),
+                path = ?path,
+                "loaded verifier into registry"
+            );
+        }
+
+        Ok(registry)
+    }
+
+    /// Registers `verifier`, returning the verifier it replaces, if one with the same digest was
+    /// already registered.
+    pub fn insert(&mut self, verifier: WormholeVerifier) -> Option<WormholeVerifier> {
+        self.verifiers.insert(*verifier.verifier_digest(), verifier)
+    }
+
+    /// Unregisters the verifier with `digest`, e.g. once a release is no longer accepted.
+    pub fn remove(&mut self, digest: &BytesDigest) -> Option<WormholeVerifier> {
+        self.verifiers.remove(&**digest)
+    }
+
+    /// The verifier with `digest`, if one is registered.
+    pub fn get(&self, digest: &BytesDigest) -> Option<&WormholeVerifier> {
+        self.verifiers.get(&**digest)
+    }
+
+    /// The digests of the registered verifiers, in ascending byte order.
+    pub fn digests(&self) -> impl Iterator<Item = BytesDigest> + '_ {
+        self.verifiers.values().map(WormholeVerifier::verifier_digest)
+    }
+
+    /// The number of registered verifiers.
+    pub fn len(&self) -> usize {
+        self.verifiers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verifiers.is_empty()
+    }
+
+    /// Verify a [`ProofEnvelope`] with the verifier of the circuit version it was generated for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no verifier with the envelope's digest is registered, or the proof is
+    /// not valid.
+    pub fn verify(&self, envelope: &ProofEnvelope) -> anyhow::Result<()> {
+        let verifier = self.get(&envelope.verifier_digest).ok_or_else(|| {
+            anyhow!(
+                "no verifier registered for {}",
+                hex::encode(*envelope.verifier_digest)
+            )
+        })?;
+        verifier.verify_envelope(envelope)
+    }
+}
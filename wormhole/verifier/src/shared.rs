@@ -0,0 +1,149 @@
+//! A [`WormholeVerifier`] that can be shared between threads.
+//!
+//! Proofs arriving over gossip are often seen several times. [`SharedVerifier`] remembers the
+//! hashes of the most recently verified proofs, so verifying a duplicate only costs hashing its
+//! bytes. Proofs are keyed by a Poseidon hash of their serialization, which is collision
+//! resistant, so a different proof can't be passed off as a cached one. Only proofs that
+//! verified successfully are cached.
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use zk_circuits_common::circuit::{C, D, F};
+use zk_circuits_common::utils::Digest;
+
+use crate::prepared::proof_bytes_hash;
+use crate::WormholeVerifier;
+
+/// The number of verified proofs remembered by [`SharedVerifier::new`].
+pub const DEFAULT_CACHE_CAPACITY: usize = 4096;
+
+/// A cheaply cloneable, `Send + Sync` handle to a [`WormholeVerifier`] with a cache of recently
+/// verified proofs.
+#[derive(Clone)]
+pub struct SharedVerifier {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    verifier: WormholeVerifier,
+    cache: Mutex<VerifiedCache>,
+}
+
+impl SharedVerifier {
+    /// Wraps `verifier`, remembering up to [`DEFAULT_CACHE_CAPACITY`] verified proofs.
+    pub fn new(verifier: WormholeVerifier) -> Self {
+        Self::with_capacity(verifier, DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// Wraps `verifier`, remembering up to `capacity` verified proofs. A capacity of zero
+    /// disables caching.
+    pub fn with_capacity(verifier: WormholeVerifier, capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                verifier,
+                cache: Mutex::new(VerifiedCache::new(capacity)),
+            }),
+        }
+    }
+
+    /// The wrapped verifier.
+    pub fn verifier(&self) -> &WormholeVerifier {
+        &self.inner.verifier
+    }
+
+    /// Verifies `proof`, skipping the verification if the same proof was recently verified.
+    ///
+    /// The cache lock is not held while verifying, so proofs are verified concurrently. Two
+    /// threads verifying the same uncached proof at the same time both verify it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is not valid.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> anyhow::Result<()> {
+        let key = proof_hash(&proof);
+        if self.lock_cache().touch(&key) {
+            return Ok(());
+        }
+
+        self.inner.verifier.verify(proof)?;
+        self.lock_cache().insert(key);
+        Ok(())
+    }
+
+    /// Returns `true` if `proof` is in the cache of verified proofs.
+    pub fn is_cached(&self, proof: &ProofWithPublicInputs<F, C, D>) -> bool {
+        self.lock_cache().contains(&proof_hash(proof))
+    }
+
+    /// The number of cached proofs.
+    pub fn cache_len(&self) -> usize {
+        self.lock_cache().len()
+    }
+
+    fn lock_cache(&self) -> MutexGuard<'_, VerifiedCache> {
+        // The cache is consistent after every operation, so a panic in another thread while
+        // holding the lock doesn't leave it in a bad state.
+        self.inner.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn proof_hash(proof: &ProofWithPublicInputs<F, C, D>) -> Digest {
+    proof_bytes_hash(&proof.to_bytes())
+}
+
+/// A least recently used set of proof hashes.
+struct VerifiedCache {
+    capacity: usize,
+    tick: u64,
+    last_used: HashMap<Digest, u64>,
+    by_tick: BTreeMap<u64, Digest>,
+}
+
+impl VerifiedCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            last_used: HashMap::new(),
+            by_tick: BTreeMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.last_used.len()
+    }
+
+    fn contains(&self, key: &Digest) -> bool {
+        self.last_used.contains_key(key)
+    }
+
+    /// Marks `key` as most recently used, returning `false` if it is not cached.
+    fn touch(&mut self, key: &Digest) -> bool {
+        let Some(last_used) = self.last_used.get_mut(key) else {
+            return false;
+        };
+
+        self.tick += 1;
+        self.by_tick.remove(last_used);
+        *last_used = self.tick;
+        self.by_tick.insert(self.tick, *key);
+        true
+    }
+
+    fn insert(&mut self, key: Digest) {
+        if self.capacity == 0 || self.touch(&key) {
+            return;
+        }
+
+        if self.last_used.len() >= self.capacity {
+            if let Some((_, oldest)) = self.by_tick.pop_first() {
+                self.last_used.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        self.last_used.insert(key, self.tick);
+        self.by_tick.insert(self.tick, key);
+    }
+}